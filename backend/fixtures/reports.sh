@@ -0,0 +1,66 @@
+#!/usr/bin/env bash
+# Shared fixture for the /reports endpoints and the client's stats screens.
+# Creates a fresh user with a small, fixed ledger (Sep–Dec 2025) and prints its token.
+# The expected report values are listed in backend/README.md, section 6.6.
+#
+# usage: BASE=http://localhost:8080 ./fixtures/reports.sh [email]
+set -euo pipefail
+
+BASE=${BASE:-http://localhost:8080}
+EMAIL=${1:-reports-fixture-$(date +%s)@example.com}
+PASSWORD=FixturePass123!
+
+post() {
+  curl -sS -f -X POST "$BASE$1" \
+    -H "Content-Type: application/json" \
+    -H "Authorization: Bearer $TOKEN" \
+    -d "$2"
+}
+
+curl -sS -f -o /dev/null -X POST "$BASE/auth/register" \
+  -H "Content-Type: application/json" \
+  -d "{\"email\":\"$EMAIL\",\"password\":\"$PASSWORD\"}"
+TOKEN=$(curl -sS -f -X POST "$BASE/auth/login" \
+  -H "Content-Type: application/json" \
+  -d "{\"email\":\"$EMAIL\",\"password\":\"$PASSWORD\"}" | jq -r '.token')
+
+CHQ=$(post /accounts '{"name":"Chequing","account_type":"checking","currency":"CAD","opening_balance":1000.00}' | jq '.id')
+VISA=$(post /accounts '{"name":"Visa","account_type":"credit","currency":"CAD","opening_balance":0}' | jq '.id')
+SAVE=$(post /accounts '{"name":"Savings","account_type":"savings","currency":"CAD","opening_balance":5000.00}' | jq '.id')
+
+FOOD=$(post /categories '{"name":"Food","parent_id":null}' | jq '.id')
+RENT=$(post /categories '{"name":"Rent","parent_id":null}' | jq '.id')
+SALARY=$(post /categories '{"name":"Salary","parent_id":null}' | jq '.id')
+FUN=$(post /categories '{"name":"Fun","parent_id":null}' | jq '.id')
+
+# tx <date> <payee> <entries json>
+tx() {
+  post /transactions "{\"payee\":\"$2\",\"memo\":null,\"occurred_at\":\"$1\",\"entries\":$3}" | jq -r '.id'
+}
+entry() { # account category amount
+  echo "{\"account_id\":$1,\"category_id\":$2,\"amount\":$3,\"note\":null}"
+}
+
+tx 2025-09-01 Employer   "[$(entry $CHQ $SALARY 3000.00)]" >/dev/null
+tx 2025-09-01 Landlord   "[$(entry $CHQ $RENT -1400.00)]" >/dev/null
+tx 2025-09-14 Grocer     "[$(entry $VISA $FOOD -85.40)]" >/dev/null
+tx 2025-09-30 Cinema     "[$(entry $VISA $FUN -32.00)]" >/dev/null
+tx 2025-10-01 Employer   "[$(entry $CHQ $SALARY 3000.00)]" >/dev/null
+tx 2025-10-01 Landlord   "[$(entry $CHQ $RENT -1400.00)]" >/dev/null
+tx 2025-10-05 Grocer     "[$(entry $VISA $FOOD -120.15),$(entry $VISA $FUN -19.99)]" >/dev/null
+tx 2025-10-15 Transfer   "[$(entry $CHQ null -500.00),$(entry $SAVE null 500.00)]" >/dev/null
+tx 2025-10-20 "Visa payment" "[$(entry $CHQ null -257.39),$(entry $VISA null 257.39)]" >/dev/null
+tx 2025-10-31 Grocer     "[$(entry $VISA $FOOD -64.25)]" >/dev/null
+tx 2025-11-01 Employer   "[$(entry $CHQ $SALARY 3100.00)]" >/dev/null
+tx 2025-11-01 Landlord   "[$(entry $CHQ $RENT -1400.00)]" >/dev/null
+tx 2025-11-16 Refund     "[$(entry $VISA $FOOD 12.50)]" >/dev/null
+tx 2025-11-28 Concert    "[$(entry $VISA $FUN -150.00)]" >/dev/null
+tx 2025-12-01 Interest   "[$(entry $SAVE null 8.75)]" >/dev/null
+# deleted: must not show up in any report
+GONE=$(tx 2025-10-10 Mistake "[$(entry $CHQ $FOOD -999.99)]")
+curl -sS -f -o /dev/null -X DELETE "$BASE/transactions/$GONE" -H "Authorization: Bearer $TOKEN"
+
+echo "email:    $EMAIL"
+echo "password: $PASSWORD"
+echo "accounts: Chequing=$CHQ Visa=$VISA Savings=$SAVE"
+echo "TOKEN=$TOKEN"
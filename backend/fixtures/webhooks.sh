@@ -0,0 +1,111 @@
+#!/usr/bin/env bash
+# End-to-end check of webhook delivery against a local HTTP receiver.
+# Creates a fresh user subscribed to every event, starts a receiver that checks each signature
+# and answers 503 the first time it sees a delivery (so every delivery goes through one retry),
+# then triggers every kind of event and waits for the delivery log to show them delivered.
+# The server must be able to reach the receiver at $RECEIVER (default http://127.0.0.1:$PORT),
+# and be started with WEBHOOKS_ALLOW_LOCAL=1, plain http and loopback receivers are refused otherwise.
+# Takes about a minute: the first retry comes 30 s after the failed attempt.
+#
+# usage: BASE=http://localhost:8080 PORT=9099 ./fixtures/webhooks.sh
+set -euo pipefail
+
+BASE=${BASE:-http://localhost:8080}
+PORT=${PORT:-9099}
+RECEIVER=${RECEIVER:-http://127.0.0.1:$PORT}
+EMAIL=webhooks-fixture-$(date +%s)@example.com
+PASSWORD=FixturePass123!
+WORK=$(mktemp -d)
+
+post() {
+  curl -sS -f -X "${3:-POST}" "$BASE$1" \
+    -H "Content-Type: application/json" \
+    -H "Authorization: Bearer $TOKEN" \
+    -d "$2"
+}
+
+curl -sS -f -o /dev/null -X POST "$BASE/auth/register" \
+  -H "Content-Type: application/json" \
+  -d "{\"email\":\"$EMAIL\",\"password\":\"$PASSWORD\"}"
+TOKEN=$(curl -sS -f -X POST "$BASE/auth/login" \
+  -H "Content-Type: application/json" \
+  -d "{\"email\":\"$EMAIL\",\"password\":\"$PASSWORD\"}" | jq -r '.token')
+
+HOOK=$(post /webhooks "{\"url\":\"$RECEIVER/hook\",\"events\":[\"transaction.created\",\"transaction.updated\",\"transaction.deleted\",\"account.updated\",\"reconcile.completed\"]}")
+HOOK_ID=$(echo "$HOOK" | jq '.id')
+echo "$HOOK" | jq -r '.secret' > "$WORK/secret"
+
+# the receiver: one JSON line per request in $WORK/received
+python3 - "$PORT" "$WORK" <<'PY' &
+import hashlib, hmac, json, sys, time
+from http.server import BaseHTTPRequestHandler, HTTPServer
+
+port, work = int(sys.argv[1]), sys.argv[2]
+secret = open(f"{work}/secret").read().strip().encode()
+seen = set()
+
+class Receiver(BaseHTTPRequestHandler):
+    def do_POST(self):
+        body = self.rfile.read(int(self.headers["Content-Length"]))
+        ts = self.headers["X-Webhook-Timestamp"]
+        expected = "sha256=" + hmac.new(secret, f"{ts}.".encode() + body, hashlib.sha256).hexdigest()
+        delivery = self.headers["X-Webhook-Delivery"]
+        status = 200 if delivery in seen else 503
+        seen.add(delivery)
+        with open(f"{work}/received", "a") as f:
+            f.write(json.dumps({
+                "delivery": delivery,
+                "event": self.headers["X-Webhook-Event"],
+                "signed": hmac.compare_digest(expected, self.headers["X-Webhook-Signature"]),
+                "fresh": abs(time.time() - int(ts)) < 300,
+                "body_event": json.loads(body)["event"],
+                "answered": status,
+            }) + "\n")
+        self.send_response(status)
+        self.end_headers()
+
+    def log_message(self, *args):
+        pass
+
+HTTPServer(("0.0.0.0", port), Receiver).serve_forever()
+PY
+RECEIVER_PID=$!
+trap 'kill $RECEIVER_PID 2>/dev/null; rm -rf "$WORK"' EXIT
+sleep 0.5
+
+# every kind of event: two account creations and a credit terms change are account.updated
+CHQ=$(post /accounts '{"name":"Chequing","account_type":"checking","currency":"CAD","opening_balance":100.00}' | jq '.id')
+VISA=$(post /accounts '{"name":"Visa","account_type":"credit","currency":"CAD","opening_balance":0}' | jq '.id')
+post "/accounts/$VISA/credit" '{"credit_limit":1000,"statement_day":20,"due_day":15,"apr":19.99}' PATCH >/dev/null
+TX=$(post /transactions "{\"payee\":\"Grocer\",\"memo\":null,\"occurred_at\":\"2025-10-05\",\"entries\":[{\"account_id\":$CHQ,\"category_id\":null,\"amount\":-20.00,\"note\":null}]}")
+ENTRY=$(echo "$TX" | jq '.entries[0].id')
+TX=$(echo "$TX" | jq -r '.id')
+post "/entries/$ENTRY/status" '{"status":"cleared"}' PATCH >/dev/null
+curl -sS -f -o /dev/null -X DELETE "$BASE/transactions/$TX" -H "Authorization: Bearer $TOKEN"
+REC=$(post /reconciliations "{\"account_id\":$CHQ,\"statement_date\":\"2025-10-31\",\"statement_balance\":100.00}" | jq '.id')
+post "/reconciliations/$REC/finish" '' >/dev/null
+
+echo "waiting for the deliveries..."
+for _ in $(seq 1 60); do
+  DELIVERED=$(curl -sS -f "$BASE/webhooks/$HOOK_ID/deliveries?status=delivered" \
+    -H "Authorization: Bearer $TOKEN" | jq 'length')
+  [ "$DELIVERED" -ge 7 ] && break
+  sleep 2
+done
+
+LOG=$(curl -sS -f "$BASE/webhooks/$HOOK_ID/deliveries" -H "Authorization: Bearer $TOKEN")
+echo "$LOG" | jq -r '.[] | "\(.id) \(.event) \(.status) after \(.attempts) attempts: \([.log[].status_code] | map(tostring) | join(", "))"'
+
+fail=0
+check() { if eval "$2"; then echo "ok   $1"; else echo "FAIL $1"; fail=1; fi; }
+check "seven deliveries, every event" \
+  '[ "$(echo "$LOG" | jq -r "[.[].event] | sort | join(\",\")")" = "account.updated,account.updated,account.updated,reconcile.completed,transaction.created,transaction.deleted,transaction.updated" ]'
+check "all delivered on the second attempt" \
+  '[ "$(echo "$LOG" | jq "[.[] | select(.status == \"delivered\" and .attempts == 2)] | length")" = 7 ]'
+check "every request signed with the secret and fresh" \
+  '[ "$(jq -s "[.[] | select(.signed and .fresh)] | length" "$WORK/received")" = "$(wc -l < "$WORK/received")" ]'
+check "event header matches the body" \
+  '[ "$(jq -s "[.[] | select(.event != .body_event)] | length" "$WORK/received")" = 0 ]'
+check "dead-letter list empty" \
+  '[ "$(curl -sS -f "$BASE/webhooks/dead-letters" -H "Authorization: Bearer $TOKEN" | jq length)" = 0 ]'
+exit $fail
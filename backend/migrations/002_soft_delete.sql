@@ -0,0 +1,10 @@
+BEGIN;
+
+-- Soft delete: rows stay in place so a delete can be undone (restored)
+ALTER TABLE transactions ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE entries ADD COLUMN deleted_at TIMESTAMPTZ;
+
+CREATE INDEX transactions_user_live_idx ON transactions(user_id) WHERE deleted_at IS NULL;
+CREATE INDEX entries_user_live_idx ON entries(user_id, tx_id) WHERE deleted_at IS NULL;
+
+COMMIT;
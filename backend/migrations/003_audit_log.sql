@@ -0,0 +1,73 @@
+BEGIN;
+
+-- Append-only history of every change to financial data, filled by triggers
+CREATE TABLE audit_log (
+  id BIGSERIAL PRIMARY KEY,
+  user_id UUID NOT NULL,          -- owner of the changed row (no FK: history outlives rows)
+
+  table_name TEXT NOT NULL,       -- accounts, categories, transactions, entries
+  row_id TEXT NOT NULL,           -- id of the changed row (bigint or uuid as text)
+  tx_id UUID,                     -- owning transaction, for transactions and entries
+  action TEXT NOT NULL,           -- insert, update, delete, soft_delete, restore
+
+  before JSONB,                   -- NULL on insert
+  after JSONB,                    -- NULL on delete
+  changed_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX audit_log_user_time_idx ON audit_log(user_id, changed_at DESC);
+CREATE INDEX audit_log_user_tx_idx ON audit_log(user_id, tx_id);
+
+CREATE FUNCTION audit_row_change() RETURNS trigger AS $$
+DECLARE
+  old_row JSONB := CASE WHEN TG_OP <> 'INSERT' THEN to_jsonb(OLD) END;
+  new_row JSONB := CASE WHEN TG_OP <> 'DELETE' THEN to_jsonb(NEW) END;
+  cur_row JSONB := COALESCE(new_row, old_row);
+  act TEXT := lower(TG_OP);
+BEGIN
+  -- soft delete / restore are updates of deleted_at
+  IF TG_OP = 'UPDATE' THEN
+    IF old_row->>'deleted_at' IS NULL AND new_row->>'deleted_at' IS NOT NULL THEN
+      act := 'soft_delete';
+    ELSIF old_row->>'deleted_at' IS NOT NULL AND new_row->>'deleted_at' IS NULL THEN
+      act := 'restore';
+    END IF;
+  END IF;
+
+  INSERT INTO audit_log (user_id, table_name, row_id, tx_id, action, before, after)
+  VALUES (
+    (cur_row->>'user_id')::uuid,
+    TG_TABLE_NAME,
+    cur_row->>'id',
+    CASE TG_TABLE_NAME
+      WHEN 'transactions' THEN (cur_row->>'id')::uuid
+      WHEN 'entries' THEN (cur_row->>'tx_id')::uuid
+    END,
+    act,
+    old_row,
+    new_row
+  );
+  RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER accounts_audit AFTER INSERT OR UPDATE OR DELETE ON accounts
+  FOR EACH ROW EXECUTE FUNCTION audit_row_change();
+CREATE TRIGGER categories_audit AFTER INSERT OR UPDATE OR DELETE ON categories
+  FOR EACH ROW EXECUTE FUNCTION audit_row_change();
+CREATE TRIGGER transactions_audit AFTER INSERT OR UPDATE OR DELETE ON transactions
+  FOR EACH ROW EXECUTE FUNCTION audit_row_change();
+CREATE TRIGGER entries_audit AFTER INSERT OR UPDATE OR DELETE ON entries
+  FOR EACH ROW EXECUTE FUNCTION audit_row_change();
+
+-- Append-only: history rows can never be changed or removed
+CREATE FUNCTION audit_log_append_only() RETURNS trigger AS $$
+BEGIN
+  RAISE EXCEPTION 'audit_log is append-only';
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_log_no_change BEFORE UPDATE OR DELETE ON audit_log
+  FOR EACH ROW EXECUTE FUNCTION audit_log_append_only();
+
+COMMIT;
@@ -0,0 +1,79 @@
+BEGIN;
+
+-- Statement reconciliation sessions, one per account and statement
+CREATE TABLE reconciliations (
+  id BIGSERIAL PRIMARY KEY,
+  user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  account_id BIGINT NOT NULL,
+
+  statement_date DATE NOT NULL,              -- statement end date, entries up to it are in scope
+  statement_balance NUMERIC(14,2) NOT NULL,  -- closing balance printed on the statement
+  cleared_balance NUMERIC(14,2),             -- ledger balance when finished (NULL while open)
+  status TEXT NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'finished')),
+
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  finished_at TIMESTAMPTZ,
+
+  UNIQUE (user_id, id),
+  FOREIGN KEY (user_id, account_id) REFERENCES accounts(user_id, id) ON DELETE CASCADE
+);
+
+-- At most one open session per account
+CREATE UNIQUE INDEX reconciliations_one_open_idx
+  ON reconciliations(user_id, account_id) WHERE status = 'open';
+
+-- Entry status: uncleared -> cleared (ticked off) -> reconciled (locked by a finished session)
+ALTER TABLE entries
+  ADD COLUMN status TEXT NOT NULL DEFAULT 'uncleared'
+    CHECK (status IN ('uncleared', 'cleared', 'reconciled')),
+  ADD COLUMN reconciliation_id BIGINT,
+  ADD FOREIGN KEY (user_id, reconciliation_id) REFERENCES reconciliations(user_id, id),
+  ADD CONSTRAINT entries_reconciled_has_session
+    CHECK ((status = 'reconciled') = (reconciliation_id IS NOT NULL));
+
+CREATE INDEX entries_account_status_idx ON entries(user_id, account_id, status);
+
+-- Reconciled entries are locked: amount, account, transaction and status can't change,
+-- and they can't be deleted (soft or hard). Category and note stay editable.
+CREATE FUNCTION entries_reconciled_lock() RETURNS trigger AS $$
+BEGIN
+  IF OLD.status = 'reconciled' AND (
+    TG_OP = 'DELETE'
+    OR NEW.amount IS DISTINCT FROM OLD.amount
+    OR NEW.account_id IS DISTINCT FROM OLD.account_id
+    OR NEW.tx_id IS DISTINCT FROM OLD.tx_id
+    OR NEW.status IS DISTINCT FROM OLD.status
+    OR NEW.deleted_at IS DISTINCT FROM OLD.deleted_at
+  ) THEN
+    RAISE EXCEPTION 'entry % is reconciled and locked', OLD.id
+      USING ERRCODE = 'check_violation', CONSTRAINT = 'entries_reconciled_lock';
+  END IF;
+  IF TG_OP = 'DELETE' THEN
+    RETURN OLD;
+  END IF;
+  RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER entries_reconciled_lock BEFORE UPDATE OR DELETE ON entries
+  FOR EACH ROW EXECUTE FUNCTION entries_reconciled_lock();
+
+-- Same for the owning transaction: no soft delete or date change once a split is reconciled
+CREATE FUNCTION transactions_reconciled_lock() RETURNS trigger AS $$
+BEGIN
+  IF (NEW.deleted_at IS DISTINCT FROM OLD.deleted_at OR NEW.occurred_at IS DISTINCT FROM OLD.occurred_at)
+    AND EXISTS (SELECT 1 FROM entries WHERE tx_id = OLD.id AND status = 'reconciled') THEN
+    RAISE EXCEPTION 'transaction % has reconciled entries and is locked', OLD.id
+      USING ERRCODE = 'check_violation', CONSTRAINT = 'entries_reconciled_lock';
+  END IF;
+  RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER transactions_reconciled_lock BEFORE UPDATE ON transactions
+  FOR EACH ROW EXECUTE FUNCTION transactions_reconciled_lock();
+
+CREATE TRIGGER reconciliations_audit AFTER INSERT OR UPDATE OR DELETE ON reconciliations
+  FOR EACH ROW EXECUTE FUNCTION audit_row_change();
+
+COMMIT;
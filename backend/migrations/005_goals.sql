@@ -0,0 +1,34 @@
+BEGIN;
+
+-- Savings goals: a target amount, an optional deadline, and what counts towards it
+CREATE TABLE goals (
+  id BIGSERIAL PRIMARY KEY,
+  user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  name TEXT NOT NULL,
+
+  target_amount NUMERIC(14,2) NOT NULL CHECK (target_amount > 0),
+  target_date DATE,  -- deadline, NULL for an open ended goal
+  tag TEXT,          -- entries whose note or memo carries #tag are contributions
+
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+  UNIQUE (user_id, id),
+  UNIQUE (user_id, name),
+  CHECK (tag IS NULL OR tag ~ '^[a-z0-9_-]+$')
+);
+
+-- Accounts whose balance is the progress of a goal
+CREATE TABLE goal_accounts (
+  user_id UUID NOT NULL,
+  goal_id BIGINT NOT NULL,
+  account_id BIGINT NOT NULL,
+
+  PRIMARY KEY (goal_id, account_id),
+  FOREIGN KEY (user_id, goal_id) REFERENCES goals(user_id, id) ON DELETE CASCADE,
+  FOREIGN KEY (user_id, account_id) REFERENCES accounts(user_id, id) ON DELETE CASCADE
+);
+
+CREATE TRIGGER goals_audit AFTER INSERT OR UPDATE OR DELETE ON goals
+  FOR EACH ROW EXECUTE FUNCTION audit_row_change();
+
+COMMIT;
@@ -0,0 +1,14 @@
+BEGIN;
+
+-- Credit card terms, only credit accounts may carry them
+ALTER TABLE accounts
+  ADD COLUMN credit_limit NUMERIC(14,2) CHECK (credit_limit > 0),
+  ADD COLUMN statement_day SMALLINT CHECK (statement_day BETWEEN 1 AND 31), -- statement closes on this day, the last day in shorter months
+  ADD COLUMN due_day SMALLINT CHECK (due_day BETWEEN 1 AND 31),             -- payment due on this day after the statement closed
+  ADD COLUMN apr NUMERIC(5,2) CHECK (apr >= 0 AND apr < 100),               -- yearly interest rate in percent
+  ADD CONSTRAINT accounts_credit_terms_only_credit CHECK (
+    account_type = 'credit'
+    OR (credit_limit IS NULL AND statement_day IS NULL AND due_day IS NULL AND apr IS NULL)
+  );
+
+COMMIT;
@@ -0,0 +1,22 @@
+BEGIN;
+
+-- Loan and mortgage terms, only loan accounts may carry them and they come as a whole
+ALTER TABLE accounts
+  ADD COLUMN loan_principal NUMERIC(14,2) CHECK (loan_principal > 0),                        -- amount borrowed
+  ADD COLUMN loan_rate NUMERIC(5,2) CHECK (loan_rate >= 0 AND loan_rate < 100),              -- yearly interest rate in percent
+  ADD COLUMN loan_term_months INTEGER CHECK (loan_term_months BETWEEN 1 AND 600),
+  ADD COLUMN loan_frequency TEXT CHECK (loan_frequency IN ('monthly', 'biweekly', 'weekly')),
+  ADD COLUMN loan_first_payment DATE,
+  ADD CONSTRAINT accounts_loan_terms_only_loan CHECK (
+    account_type = 'loan'
+    OR (loan_principal IS NULL AND loan_rate IS NULL AND loan_term_months IS NULL
+        AND loan_frequency IS NULL AND loan_first_payment IS NULL)
+  ),
+  ADD CONSTRAINT accounts_loan_terms_complete CHECK (
+    (loan_principal IS NULL AND loan_rate IS NULL AND loan_term_months IS NULL
+        AND loan_frequency IS NULL AND loan_first_payment IS NULL)
+    OR (loan_principal IS NOT NULL AND loan_rate IS NOT NULL AND loan_term_months IS NOT NULL
+        AND loan_frequency IS NOT NULL AND loan_first_payment IS NOT NULL)
+  );
+
+COMMIT;
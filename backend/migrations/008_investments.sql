@@ -0,0 +1,70 @@
+BEGIN;
+
+-- How sells of an investment account pick their cost, only investment accounts have one
+ALTER TABLE accounts
+  ADD COLUMN cost_basis TEXT CHECK (cost_basis IN ('fifo', 'average')),
+  ADD CONSTRAINT accounts_cost_basis_only_investment CHECK (
+    (account_type = 'investment') = (cost_basis IS NOT NULL)
+  );
+
+-- Stocks, funds, ETFs... held in investment accounts
+CREATE TABLE securities (
+  id BIGSERIAL PRIMARY KEY,
+  user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+
+  symbol TEXT NOT NULL, -- ticker, upper case
+  name TEXT,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+  UNIQUE (user_id, id),
+  UNIQUE (user_id, symbol),
+  CHECK (symbol ~ '^[A-Z0-9.:-]+$')
+);
+
+-- Imported closing prices, one per security and day
+CREATE TABLE security_prices (
+  user_id UUID NOT NULL,
+  security_id BIGINT NOT NULL,
+  price_date DATE NOT NULL,
+  price NUMERIC(18,6) NOT NULL CHECK (price >= 0),
+
+  PRIMARY KEY (security_id, price_date),
+  FOREIGN KEY (user_id, security_id) REFERENCES securities(user_id, id) ON DELETE CASCADE
+);
+
+-- Buys, sells, dividends and splits; the cash side is the transaction tx_id, which has no
+-- entries for a split, deleting or restoring that transaction deletes or restores the trade
+CREATE TABLE trades (
+  id BIGSERIAL PRIMARY KEY,
+  user_id UUID NOT NULL,
+  account_id BIGINT NOT NULL,
+  security_id BIGINT NOT NULL,
+  tx_id UUID NOT NULL,
+
+  kind TEXT NOT NULL CHECK (kind IN ('buy', 'sell', 'dividend', 'split')),
+  quantity NUMERIC(18,6) NOT NULL DEFAULT 0, -- units bought or sold, new units per old unit for a split
+  price NUMERIC(18,6) NOT NULL DEFAULT 0,    -- per unit
+  fees NUMERIC(14,2) NOT NULL DEFAULT 0 CHECK (fees >= 0),
+  amount NUMERIC(14,2) NOT NULL DEFAULT 0,   -- cash in (+) or out (-) of the account
+
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+  UNIQUE (tx_id),
+  FOREIGN KEY (user_id, account_id) REFERENCES accounts(user_id, id),
+  FOREIGN KEY (user_id, security_id) REFERENCES securities(user_id, id),
+  FOREIGN KEY (user_id, tx_id) REFERENCES transactions(user_id, id) ON DELETE CASCADE,
+  CONSTRAINT trades_kind_fields CHECK (
+    (kind IN ('buy', 'sell') AND quantity > 0)
+    OR (kind = 'dividend' AND amount > 0)
+    OR (kind = 'split' AND quantity > 0)
+  )
+);
+
+CREATE INDEX trades_account_security_idx ON trades(account_id, security_id);
+
+CREATE TRIGGER securities_audit AFTER INSERT OR UPDATE OR DELETE ON securities
+  FOR EACH ROW EXECUTE FUNCTION audit_row_change();
+CREATE TRIGGER trades_audit AFTER INSERT OR UPDATE OR DELETE ON trades
+  FOR EACH ROW EXECUTE FUNCTION audit_row_change();
+
+COMMIT;
@@ -0,0 +1,10 @@
+BEGIN;
+
+-- Tax line a category reports under; sub-categories without one use their parent's
+ALTER TABLE categories
+  ADD COLUMN tax_line TEXT CHECK (tax_line IN (
+    'medical', 'charitable', 'childcare', 'education',
+    'business_income', 'business_expense', 'rental_income', 'rental_expense', 'investment_income'
+  ));
+
+COMMIT;
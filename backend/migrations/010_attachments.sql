@@ -0,0 +1,28 @@
+BEGIN;
+
+-- Receipts and documents attached to transactions; the bytes live in the attachment storage
+-- under storage_key, the row only describes them
+CREATE TABLE attachments (
+  id BIGSERIAL PRIMARY KEY,
+  user_id UUID NOT NULL,
+  tx_id UUID NOT NULL,
+
+  filename TEXT NOT NULL,      -- as uploaded, without any directory
+  content_type TEXT NOT NULL,  -- detected from the bytes, not trusted from the upload
+  size BIGINT NOT NULL CHECK (size > 0),
+  sha256 TEXT NOT NULL CHECK (sha256 ~ '^[0-9a-f]{64}$'),
+  storage_key TEXT NOT NULL UNIQUE,
+
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+  UNIQUE (tx_id, sha256),      -- the same file twice on one transaction is a mistake
+  FOREIGN KEY (user_id, tx_id) REFERENCES transactions(user_id, id) ON DELETE CASCADE,
+  CHECK (content_type IN ('image/jpeg', 'image/png', 'image/gif', 'image/webp', 'application/pdf'))
+);
+
+CREATE INDEX attachments_user_tx_idx ON attachments(user_id, tx_id);
+
+CREATE TRIGGER attachments_audit AFTER INSERT OR UPDATE OR DELETE ON attachments
+  FOR EACH ROW EXECUTE FUNCTION audit_row_change();
+
+COMMIT;
@@ -0,0 +1,101 @@
+BEGIN;
+
+-- Shared ledgers: every account, category, transaction... belongs to a workspace, users reach
+-- it through their membership. Everyone has a personal workspace whose id is their user id.
+CREATE TABLE workspaces (
+  id UUID PRIMARY KEY,
+  name TEXT NOT NULL CHECK (btrim(name) <> ''),
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- owner: everything, including members and deleting the workspace; editor: the ledger;
+-- viewer: read only
+CREATE TABLE workspace_members (
+  workspace_id UUID NOT NULL REFERENCES workspaces(id) ON DELETE CASCADE,
+  user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  role TEXT NOT NULL CHECK (role IN ('owner', 'editor', 'viewer')),
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+  PRIMARY KEY (workspace_id, user_id)
+);
+
+CREATE INDEX workspace_members_user_idx ON workspace_members(user_id);
+
+INSERT INTO workspaces (id, name) SELECT id, 'Personal' FROM users;
+INSERT INTO workspace_members (workspace_id, user_id, role) SELECT id, id, 'owner' FROM users;
+
+-- data rows move from their user to the user's personal workspace, same uuid
+ALTER TABLE accounts DROP CONSTRAINT accounts_user_id_fkey;
+ALTER TABLE categories DROP CONSTRAINT categories_user_id_fkey;
+ALTER TABLE transactions DROP CONSTRAINT transactions_user_id_fkey;
+ALTER TABLE entries DROP CONSTRAINT entries_user_id_fkey;
+ALTER TABLE reconciliations DROP CONSTRAINT reconciliations_user_id_fkey;
+ALTER TABLE goals DROP CONSTRAINT goals_user_id_fkey;
+ALTER TABLE securities DROP CONSTRAINT securities_user_id_fkey;
+
+DO $$
+DECLARE
+  t TEXT;
+  c RECORD;
+BEGIN
+  FOREACH t IN ARRAY ARRAY[
+    'accounts', 'categories', 'transactions', 'entries', 'reconciliations', 'goals',
+    'goal_accounts', 'securities', 'security_prices', 'trades', 'attachments', 'audit_log'
+  ] LOOP
+    EXECUTE format('ALTER TABLE %I RENAME COLUMN user_id TO workspace_id', t);
+  END LOOP;
+  -- keep constraint names in step with the column, the server matches on some of them
+  FOR c IN
+    SELECT conrelid::regclass AS tbl, conname FROM pg_constraint
+    WHERE connamespace = 'public'::regnamespace AND conname LIKE '%user_id%'
+      AND conrelid <> 'workspace_members'::regclass
+  LOOP
+    EXECUTE format('ALTER TABLE %s RENAME CONSTRAINT %I TO %I',
+      c.tbl, c.conname, replace(c.conname, 'user_id', 'workspace_id'));
+  END LOOP;
+END
+$$;
+
+ALTER TABLE accounts ADD FOREIGN KEY (workspace_id) REFERENCES workspaces(id) ON DELETE CASCADE;
+ALTER TABLE categories ADD FOREIGN KEY (workspace_id) REFERENCES workspaces(id) ON DELETE CASCADE;
+ALTER TABLE transactions ADD FOREIGN KEY (workspace_id) REFERENCES workspaces(id) ON DELETE CASCADE;
+ALTER TABLE entries ADD FOREIGN KEY (workspace_id) REFERENCES workspaces(id) ON DELETE CASCADE;
+ALTER TABLE reconciliations ADD FOREIGN KEY (workspace_id) REFERENCES workspaces(id) ON DELETE CASCADE;
+ALTER TABLE goals ADD FOREIGN KEY (workspace_id) REFERENCES workspaces(id) ON DELETE CASCADE;
+ALTER TABLE securities ADD FOREIGN KEY (workspace_id) REFERENCES workspaces(id) ON DELETE CASCADE;
+
+-- history is per workspace now
+CREATE OR REPLACE FUNCTION audit_row_change() RETURNS trigger AS $$
+DECLARE
+  old_row JSONB := CASE WHEN TG_OP <> 'INSERT' THEN to_jsonb(OLD) END;
+  new_row JSONB := CASE WHEN TG_OP <> 'DELETE' THEN to_jsonb(NEW) END;
+  cur_row JSONB := COALESCE(new_row, old_row);
+  act TEXT := lower(TG_OP);
+BEGIN
+  -- soft delete / restore are updates of deleted_at
+  IF TG_OP = 'UPDATE' THEN
+    IF old_row->>'deleted_at' IS NULL AND new_row->>'deleted_at' IS NOT NULL THEN
+      act := 'soft_delete';
+    ELSIF old_row->>'deleted_at' IS NOT NULL AND new_row->>'deleted_at' IS NULL THEN
+      act := 'restore';
+    END IF;
+  END IF;
+
+  INSERT INTO audit_log (workspace_id, table_name, row_id, tx_id, action, before, after)
+  VALUES (
+    (cur_row->>'workspace_id')::uuid,
+    TG_TABLE_NAME,
+    cur_row->>'id',
+    CASE TG_TABLE_NAME
+      WHEN 'transactions' THEN (cur_row->>'id')::uuid
+      WHEN 'entries' THEN (cur_row->>'tx_id')::uuid
+    END,
+    act,
+    old_row,
+    new_row
+  );
+  RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+COMMIT;
@@ -0,0 +1,12 @@
+BEGIN;
+
+-- People expenses are split with. A contact is an account of type 'contact' whose balance is
+-- what the person owes (positive) or is owed (negative): their share of an expense is an entry
+-- on it, settling up is a transfer between it and a real account.
+ALTER TABLE accounts
+  ADD COLUMN contact_email TEXT CHECK (contact_email LIKE '_%@_%'),
+  ADD CONSTRAINT accounts_contact_email_only_contact CHECK (
+    account_type = 'contact' OR contact_email IS NULL
+  );
+
+COMMIT;
@@ -0,0 +1,27 @@
+BEGIN;
+
+-- Personal access tokens: long-lived credentials for scripts and dashboards, each limited to
+-- some scopes. Only the SHA-256 of the token is kept, the token itself is shown once.
+-- read:ledger: every read; write:transactions: transactions, entries and attachments;
+-- admin: everything, tokens included
+CREATE TABLE api_tokens (
+  id BIGSERIAL PRIMARY KEY,
+  user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+
+  name TEXT NOT NULL CHECK (btrim(name) <> ''),
+  token_hash TEXT NOT NULL UNIQUE CHECK (token_hash ~ '^[0-9a-f]{64}$'),
+  prefix TEXT NOT NULL,        -- start of the token, to tell tokens apart in lists
+  scopes TEXT[] NOT NULL CHECK (
+    cardinality(scopes) > 0
+    AND scopes <@ ARRAY['read:ledger', 'write:transactions', 'admin']
+  ),
+
+  expires_at TIMESTAMPTZ,      -- never when null
+  last_used_at TIMESTAMPTZ,
+  revoked_at TIMESTAMPTZ,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX api_tokens_user_idx ON api_tokens(user_id);
+
+COMMIT;
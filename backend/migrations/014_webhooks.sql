@@ -0,0 +1,56 @@
+BEGIN;
+
+-- Webhooks: a user subscribes a URL to some events of a workspace. Events are written to an
+-- outbox in the same database transaction as the change, a worker posts them signed with the
+-- subscription's secret (kept as is, it is needed to sign) and retries with backoff until the
+-- delivery is dead.
+CREATE TABLE webhooks (
+  id BIGSERIAL PRIMARY KEY,
+  workspace_id UUID NOT NULL REFERENCES workspaces(id) ON DELETE CASCADE,
+  user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+
+  url TEXT NOT NULL CHECK (url ~ '^https?://'),
+  secret TEXT NOT NULL,
+  events TEXT[] NOT NULL CHECK (
+    cardinality(events) > 0
+    AND events <@ ARRAY['transaction.created', 'transaction.deleted', 'account.updated', 'reconcile.completed']
+  ),
+
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX webhooks_workspace_idx ON webhooks(workspace_id);
+
+-- the outbox, one row per event and subscription
+-- pending: waiting for its next attempt; delivered: got a 2xx; dead: out of attempts
+CREATE TABLE webhook_deliveries (
+  id BIGSERIAL PRIMARY KEY,
+  webhook_id BIGINT NOT NULL REFERENCES webhooks(id) ON DELETE CASCADE,
+
+  event TEXT NOT NULL,
+  payload JSONB NOT NULL,      -- the body that is posted
+
+  status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'delivered', 'dead')),
+  attempts INT NOT NULL DEFAULT 0,
+  next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  delivered_at TIMESTAMPTZ,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX webhook_deliveries_due_idx ON webhook_deliveries(next_attempt_at) WHERE status = 'pending';
+CREATE INDEX webhook_deliveries_webhook_idx ON webhook_deliveries(webhook_id, id);
+
+-- the delivery log, one row per attempt
+CREATE TABLE webhook_attempts (
+  id BIGSERIAL PRIMARY KEY,
+  delivery_id BIGINT NOT NULL REFERENCES webhook_deliveries(id) ON DELETE CASCADE,
+
+  status_code INT,             -- null when no response came back
+  error TEXT,                  -- null on success
+  duration_ms INT NOT NULL,
+  attempted_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX webhook_attempts_delivery_idx ON webhook_attempts(delivery_id);
+
+COMMIT;
@@ -0,0 +1,30 @@
+BEGIN;
+
+-- Deleting a workspace cascades to its entries, reconciled ones included. The delete sets
+-- app.deleting_workspace for its own transaction (set_config(..., true)) and the lock lets
+-- hard deletes of that workspace's entries through; everything else stays locked.
+CREATE OR REPLACE FUNCTION entries_reconciled_lock() RETURNS trigger AS $$
+BEGIN
+  IF TG_OP = 'DELETE'
+    AND current_setting('app.deleting_workspace', true) = OLD.workspace_id::text THEN
+    RETURN OLD;
+  END IF;
+  IF OLD.status = 'reconciled' AND (
+    TG_OP = 'DELETE'
+    OR NEW.amount IS DISTINCT FROM OLD.amount
+    OR NEW.account_id IS DISTINCT FROM OLD.account_id
+    OR NEW.tx_id IS DISTINCT FROM OLD.tx_id
+    OR NEW.status IS DISTINCT FROM OLD.status
+    OR NEW.deleted_at IS DISTINCT FROM OLD.deleted_at
+  ) THEN
+    RAISE EXCEPTION 'entry % is reconciled and locked', OLD.id
+      USING ERRCODE = 'check_violation', CONSTRAINT = 'entries_reconciled_lock';
+  END IF;
+  IF TG_OP = 'DELETE' THEN
+    RETURN OLD;
+  END IF;
+  RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+COMMIT;
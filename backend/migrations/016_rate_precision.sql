@@ -0,0 +1,9 @@
+BEGIN;
+
+-- Rates with two decimals rounded common ones like a 4.875% mortgage to 4.88%, which changes
+-- every payment of the schedule. Four decimals keep them as entered.
+ALTER TABLE accounts
+  ALTER COLUMN apr TYPE NUMERIC(7,4),
+  ALTER COLUMN loan_rate TYPE NUMERIC(7,4);
+
+COMMIT;
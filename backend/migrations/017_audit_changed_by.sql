@@ -0,0 +1,42 @@
+BEGIN;
+
+-- with several members per workspace the history has to say who made each change. The server
+-- sets app.user_id for every request transaction that writes; NULL for rows changed outside it.
+ALTER TABLE audit_log ADD COLUMN changed_by UUID;
+
+CREATE OR REPLACE FUNCTION audit_row_change() RETURNS trigger AS $$
+DECLARE
+  old_row JSONB := CASE WHEN TG_OP <> 'INSERT' THEN to_jsonb(OLD) END;
+  new_row JSONB := CASE WHEN TG_OP <> 'DELETE' THEN to_jsonb(NEW) END;
+  cur_row JSONB := COALESCE(new_row, old_row);
+  act TEXT := lower(TG_OP);
+BEGIN
+  -- soft delete / restore are updates of deleted_at
+  IF TG_OP = 'UPDATE' THEN
+    IF old_row->>'deleted_at' IS NULL AND new_row->>'deleted_at' IS NOT NULL THEN
+      act := 'soft_delete';
+    ELSIF old_row->>'deleted_at' IS NOT NULL AND new_row->>'deleted_at' IS NULL THEN
+      act := 'restore';
+    END IF;
+  END IF;
+
+  INSERT INTO audit_log (workspace_id, table_name, row_id, tx_id, action, before, after, changed_by)
+  VALUES (
+    (cur_row->>'workspace_id')::uuid,
+    TG_TABLE_NAME,
+    cur_row->>'id',
+    CASE TG_TABLE_NAME
+      WHEN 'transactions' THEN (cur_row->>'id')::uuid
+      WHEN 'entries' THEN (cur_row->>'tx_id')::uuid
+    END,
+    act,
+    old_row,
+    new_row,
+    -- unset is NULL, but a setting once set in the session reads back as '' afterwards
+    NULLIF(current_setting('app.user_id', true), '')::uuid
+  );
+  RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+COMMIT;
@@ -0,0 +1,14 @@
+BEGIN;
+
+-- deleting, restoring or ticking off a single entry changes its transaction without creating or
+-- deleting it, subscribers hear of it as transaction.updated
+ALTER TABLE webhooks DROP CONSTRAINT webhooks_events_check;
+ALTER TABLE webhooks ADD CONSTRAINT webhooks_events_check CHECK (
+  cardinality(events) > 0
+  AND events <@ ARRAY[
+    'transaction.created', 'transaction.updated', 'transaction.deleted', 'account.updated',
+    'reconcile.completed'
+  ]
+);
+
+COMMIT;
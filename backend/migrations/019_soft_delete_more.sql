@@ -0,0 +1,23 @@
+BEGIN;
+
+-- Soft delete for accounts, categories, goals and attachments too: an undone delete brings back
+-- the same row under its id, with what hangs off it (entries of deleted transactions,
+-- reconciliations, goal links, the stored file). Entries keep the category of a deleted
+-- category, the server reads it as uncategorized until the category is restored.
+ALTER TABLE accounts ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE categories ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE goals ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE attachments ADD COLUMN deleted_at TIMESTAMPTZ;
+
+-- names only have to be unique among live rows, so does a file on a transaction; the indexes
+-- keep the constraint names the server matches on
+ALTER TABLE accounts DROP CONSTRAINT accounts_workspace_id_name_key;
+CREATE UNIQUE INDEX accounts_workspace_id_name_key ON accounts(workspace_id, name) WHERE deleted_at IS NULL;
+ALTER TABLE categories DROP CONSTRAINT categories_workspace_id_name_key;
+CREATE UNIQUE INDEX categories_workspace_id_name_key ON categories(workspace_id, name) WHERE deleted_at IS NULL;
+ALTER TABLE goals DROP CONSTRAINT goals_workspace_id_name_key;
+CREATE UNIQUE INDEX goals_workspace_id_name_key ON goals(workspace_id, name) WHERE deleted_at IS NULL;
+ALTER TABLE attachments DROP CONSTRAINT attachments_tx_id_sha256_key;
+CREATE UNIQUE INDEX attachments_tx_id_sha256_key ON attachments(tx_id, sha256) WHERE deleted_at IS NULL;
+
+COMMIT;
@@ -20,7 +20,8 @@ use argon2::{
     password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
     Argon2,
 };
-use rand_core::OsRng;
+use rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
 pub async fn register(
     // this argument tells axum to parse the request body
     // as JSON into a `CreateUser` type
@@ -32,16 +33,30 @@ pub async fn register(
     let password_hash = hash_password(&payload.password)
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("hash error: {e}")))?;
 
+    let db_error = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}"));
+    let mut tx = state.pool.begin().await.map_err(db_error)?;
     let _res = sqlx::query(
         r#"INSERT INTO users (id, email, password_hash)
         VALUES ($1, $2, $3)"#)
         .bind(user_id)
         .bind(payload.email)
         .bind(password_hash) // Assume a function to hash passwords
-        .execute(&state.pool)
+        .execute(&mut *tx)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?;
-    
+        .map_err(db_error)?;
+    // everyone owns a personal workspace, same id as the user
+    sqlx::query("INSERT INTO workspaces (id, name) VALUES ($1, 'Personal')")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    sqlx::query("INSERT INTO workspace_members (workspace_id, user_id, role) VALUES ($1, $1, 'owner')")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+
     
     // this will be converted into a JSON response
     // with a status code of `201 Created`
@@ -133,7 +148,11 @@ pub fn decode_jwt(token: &str) -> Result<Claims, (StatusCode, String)> {
 
     Ok(data.claims)
 }
+/// the workspace a request works in, the personal one when absent
+pub const WORKSPACE_HEADER: &str = "x-workspace-id";
+
 pub async fn auth_middleware(
+    State(state): State<AppState>,
     mut req: Request,
     next: Next,
 ) -> Result<Response, (StatusCode, String)> {
@@ -147,14 +166,94 @@ pub async fn auth_middleware(
         .strip_prefix("Bearer ")
         .ok_or((StatusCode::UNAUTHORIZED, "Invalid Authorization scheme".into()))?;
 
-    let claims = decode_jwt(token)?;
-    let user_id = Uuid::parse_str(&claims.sub)
-        .map_err(|_| (StatusCode::UNAUTHORIZED, "Invalid sub in token".into()))?;
+    let (user_id, scopes) = if token.starts_with(TOKEN_PREFIX) {
+        token_user(&state.pool, token).await?
+    } else {
+        let claims = decode_jwt(token)?;
+        let user_id = Uuid::parse_str(&claims.sub)
+            .map_err(|_| (StatusCode::UNAUTHORIZED, "Invalid sub in token".into()))?;
+        // a login session can do everything its user can
+        (user_id, vec![Scope::Admin])
+    };
+
+    let workspace_id = match req.headers().get(WORKSPACE_HEADER) {
+        Some(v) => v
+            .to_str()
+            .ok()
+            .and_then(|s| Uuid::parse_str(s.trim()).ok())
+            .ok_or((StatusCode::BAD_REQUEST, "Invalid X-Workspace-Id header".into()))?,
+        None => user_id,
+    };
+    let role = member_role(&state.pool, workspace_id, user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?
+        .ok_or((StatusCode::FORBIDDEN, "Not a member of this workspace".into()))?;
+    // viewers only read the ledger, /workspaces stays open so they can still leave, /tokens
+    // are their own; webhooks push the ledger out, so they are for editors and owners
+    let path = req.uri().path();
+    if !role.can_edit()
+        && !req.method().is_safe()
+        && !path.starts_with("/workspaces")
+        && !path.starts_with("/tokens")
+    {
+        return Err((StatusCode::FORBIDDEN, "Viewers cannot change this workspace".into()));
+    }
 
-    req.extensions_mut().insert(AuthUser { user_id });
+    req.extensions_mut().insert(AuthUser { user_id, workspace_id, role, scopes });
 
     Ok(next.run(req).await)
 }
+/// layer of the routes that need `scope`, runs after `auth_middleware`
+pub async fn require_scope(
+    State(scope): State<Scope>,
+    req: Request,
+    next: Next,
+) -> Result<Response, (StatusCode, String)> {
+    let allowed = req
+        .extensions()
+        .get::<AuthUser>()
+        .is_some_and(|user| user.allows(scope));
+    if !allowed {
+        return Err((
+            StatusCode::FORBIDDEN,
+            format!("Token lacks the {} scope", scope.as_str()),
+        ));
+    }
+    Ok(next.run(req).await)
+}
+/// personal access tokens start with it, any other bearer token is taken for a login JWT
+pub const TOKEN_PREFIX: &str = "pat_";
+
+/// a new personal access token: the prefix and 32 random bytes in hex
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    format!("{TOKEN_PREFIX}{}", hex::encode(bytes))
+}
+/// what is stored of a token; tokens are random, a plain SHA-256 is enough to keep them secret
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+/// owner and scopes of a live personal access token, noting it was used
+async fn token_user(pool: &sqlx::PgPool, token: &str) -> Result<(Uuid, Vec<Scope>), (StatusCode, String)> {
+    let row: Option<(Uuid, Vec<String>)> = sqlx::query_as(
+        r#"
+        UPDATE api_tokens SET last_used_at = now()
+        WHERE token_hash = $1
+          AND revoked_at IS NULL
+          AND (expires_at IS NULL OR expires_at > now())
+        RETURNING user_id, scopes
+        "#,
+    )
+    .bind(hash_token(token))
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?;
+
+    let (user_id, scopes) =
+        row.ok_or((StatusCode::UNAUTHORIZED, "Invalid, expired or revoked token".into()))?;
+    Ok((user_id, scopes.iter().filter_map(|s| Scope::parse(s)).collect()))
+}
 #[derive(Serialize, Deserialize)]
 // Define a structure for holding claims data used in JWT tokens
 pub struct Claims {
@@ -189,6 +288,81 @@ pub struct LoginResp {
 #[derive(Clone, Debug)]
 pub struct AuthUser {
     pub user_id: Uuid,
+    pub workspace_id: Uuid, // every ledger query is scoped to it
+    pub role: Role,
+    pub scopes: Vec<Scope>, // of the token, admin for a login session
+}
+impl AuthUser {
+    pub fn allows(&self, scope: Scope) -> bool {
+        self.scopes.iter().any(|s| *s == scope || *s == Scope::Admin)
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scope {
+    ReadLedger,        // every read
+    WriteTransactions, // transactions, entries and attachments
+    Admin,             // everything, tokens included
+}
+impl Scope {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "read:ledger" => Some(Self::ReadLedger),
+            "write:transactions" => Some(Self::WriteTransactions),
+            "admin" => Some(Self::Admin),
+            _ => None,
+        }
+    }
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::ReadLedger => "read:ledger",
+            Self::WriteTransactions => "write:transactions",
+            Self::Admin => "admin",
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    Owner,  // the ledger, members, deleting the workspace
+    Editor, // the ledger
+    Viewer, // reads only
+}
+impl Role {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "owner" => Some(Self::Owner),
+            "editor" => Some(Self::Editor),
+            "viewer" => Some(Self::Viewer),
+            _ => None,
+        }
+    }
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Owner => "owner",
+            Self::Editor => "editor",
+            Self::Viewer => "viewer",
+        }
+    }
+    pub fn can_edit(self) -> bool {
+        self != Self::Viewer
+    }
+}
+/// role of a user in a workspace, none if not a member
+pub async fn member_role<'e, E>(
+    executor: E,
+    workspace_id: Uuid,
+    user_id: Uuid,
+) -> Result<Option<Role>, sqlx::Error>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    let role: Option<String> = sqlx::query_scalar(
+        "SELECT role FROM workspace_members WHERE workspace_id = $1 AND user_id = $2",
+    )
+    .bind(workspace_id)
+    .bind(user_id)
+    .fetch_optional(executor)
+    .await?;
+    Ok(role.as_deref().and_then(Role::parse))
 }
 #[derive(Debug, FromRow)]
 struct UserRow {
@@ -1,10 +1,14 @@
 mod auth;
 mod services;
 mod routes;
+mod storage;
+mod webhooks;
 use sqlx::{postgres::PgPoolOptions};
 use dotenvy;
+use std::sync::Arc;
 
 use crate::routes::AppState;
+use crate::storage::LocalStorage;
 // use crate::{auth, services};
 #[tokio::main]
 async fn main()-> anyhow::Result<()> {
@@ -17,10 +21,16 @@ async fn main()-> anyhow::Result<()> {
         .max_connections(10)
         .connect(&std::env::var("DATABASE_URL")?)
         .await?;
-    let state = AppState { pool };
+    let state = AppState {
+        pool,
+        storage: Arc::new(LocalStorage::from_env()),
+    };
+
+    // posts webhook deliveries from the outbox
+    tokio::spawn(webhooks::run_worker(state.pool.clone()));
 
     // build our application with a route
-    let app = routes::app().with_state(state);
+    let app = routes::app(state.clone()).with_state(state);
 
     // run our app with hyper, listening globally on port 8080
     let listener = tokio::net::TcpListener::bind("0.0.0.0:8080").await.unwrap();
@@ -1,34 +1,111 @@
 use axum::{
-    routing::{get, post,delete},
+    routing::{get, post, delete, patch},
+    extract::DefaultBodyLimit,
 
     Router,
-    middleware::from_fn,
+    middleware::from_fn_with_state,
 };
 use crate::{auth, services};
+use crate::auth::Scope;
+use crate::storage::Storage;
 use sqlx::{PgPool};
+use std::sync::Arc;
 
-pub fn app() -> axum::Router<AppState>{
-    let protected = Router::<AppState>::new()
+pub fn app(state: AppState) -> axum::Router<AppState>{
+    // routes are grouped by the scope a personal access token needs for them, login sessions
+    // have them all
+    let read = Router::<AppState>::new()
         .route("/", get(services::root))
-        // accounts
-        .route("/accounts", post(services::create_account_handler))
         .route("/accounts",get(services::list_accounts_handler))
-        .route("/accounts/{id}", delete(services::delete_account_handler))
-        // categories
-        .route("/categories", post(services::create_category_handler))
         .route("/categories", get(services::list_categories_handler))
-        .route("/categories/{id}", delete(services::delete_category_handler))
+        .route("/transactions", get(services::list_transactions_handler))
+        .route("/transactions/{id}/attachments", get(services::list_attachments_handler))
+        .route("/attachments/{id}", get(services::download_attachment_handler))
+        .route("/ledger",get(services::get_ledger_snapshot_handler))
+        .route("/reconciliations", get(services::list_reconciliations_handler))
+        .route("/reconciliations/{id}", get(services::get_reconciliation_handler))
+        .route("/audit", get(services::list_audit_handler))
+        .route("/reports/summary", get(services::report_summary_handler))
+        .route("/reports/top-categories", get(services::report_top_categories_handler))
+        .route("/reports/top-accounts", get(services::report_top_accounts_handler))
+        .route("/reports/trends", get(services::report_trends_handler))
+        .route("/reports/balances", get(services::report_balances_handler))
+        .route("/reports/contacts", get(services::report_contacts_handler))
+        .route("/goals", get(services::list_goals_handler))
+        .route("/securities", get(services::list_securities_handler))
+        .route("/workspaces", get(services::list_workspaces_handler))
+        .route("/workspaces/{id}/members", get(services::list_members_handler))
+        .route_layer(from_fn_with_state(Scope::ReadLedger, auth::require_scope));
+
+    let write_transactions = Router::<AppState>::new()
         // transactions
         .route("/transactions", post(services::create_transaction_handler))
-        .route("/transactions", get(services::list_transactions_handler))
         .route("/transactions/{id}", delete(services::delete_transaction_handler))
-        // ledger
-        .route("/ledger",get(services::get_ledger_snapshot_handler))
+        .route("/transactions/{id}/restore", post(services::restore_transaction_handler))
+        .route("/accounts/{id}/loan/payments", post(services::create_loan_payment_handler))
+        .route("/accounts/{id}/trades", post(services::create_trade_handler))
+        .route("/accounts/{id}/settlements", post(services::create_settlement_handler))
         // entries
         .route("/entries/{id}", delete(services::delete_entry_handler))
-        
+        .route("/entries/{id}/restore", post(services::restore_entry_handler))
+        .route("/entries/{id}/status", patch(services::set_entry_status_handler))
+        // attachments
+        .route(
+            "/transactions/{id}/attachments",
+            post(services::upload_attachment_handler)
+                .layer(DefaultBodyLimit::max(services::MAX_ATTACHMENT_BYTES)),
+        )
+        .route("/attachments/{id}", delete(services::delete_attachment_handler))
+        .route("/attachments/{id}/restore", post(services::restore_attachment_handler))
+        .route_layer(from_fn_with_state(Scope::WriteTransactions, auth::require_scope));
+
+    let admin = Router::<AppState>::new()
+        // accounts
+        .route("/accounts", post(services::create_account_handler))
+        .route("/accounts/{id}", delete(services::delete_account_handler))
+        .route("/accounts/{id}/restore", post(services::restore_account_handler))
+        .route("/accounts/{id}/credit", patch(services::set_credit_terms_handler))
+        .route("/accounts/{id}/loan", patch(services::set_loan_terms_handler))
+        .route("/accounts/{id}/cost-basis", patch(services::set_cost_basis_handler))
+        // categories
+        .route("/categories", post(services::create_category_handler))
+        .route("/categories/{id}", delete(services::delete_category_handler))
+        .route("/categories/{id}/restore", post(services::restore_category_handler))
+        .route("/categories/{id}/tax-line", patch(services::set_tax_line_handler))
+        // reconciliations
+        .route("/reconciliations", post(services::create_reconciliation_handler))
+        .route("/reconciliations/{id}", delete(services::cancel_reconciliation_handler))
+        .route("/reconciliations/{id}/finish", post(services::finish_reconciliation_handler))
+        // goals
+        .route("/goals", post(services::create_goal_handler))
+        .route("/goals/{id}", delete(services::delete_goal_handler))
+        .route("/goals/{id}/restore", post(services::restore_goal_handler))
+        // investments
+        .route("/securities", post(services::create_security_handler))
+        .route("/securities/prices", post(services::import_prices_handler))
+        // workspaces
+        .route("/workspaces", post(services::create_workspace_handler))
+        .route("/workspaces/{id}", delete(services::delete_workspace_handler))
+        .route("/workspaces/{id}/members", post(services::add_member_handler))
+        .route("/workspaces/{id}/members/{user_id}", patch(services::set_member_role_handler))
+        .route("/workspaces/{id}/members/{user_id}", delete(services::remove_member_handler))
+        // personal access tokens
+        .route("/tokens", get(services::list_tokens_handler))
+        .route("/tokens", post(services::create_token_handler))
+        .route("/tokens/{id}", delete(services::revoke_token_handler))
+        // webhooks
+        .route("/webhooks", get(services::list_webhooks_handler))
+        .route("/webhooks", post(services::create_webhook_handler))
+        .route("/webhooks/{id}", delete(services::delete_webhook_handler))
+        .route("/webhooks/{id}/deliveries", get(services::list_webhook_deliveries_handler))
+        .route("/webhooks/dead-letters", get(services::list_dead_deliveries_handler))
+        .route("/webhooks/deliveries/{id}/retry", post(services::retry_delivery_handler))
+        .route_layer(from_fn_with_state(Scope::Admin, auth::require_scope));
 
-        .layer(from_fn(auth::auth_middleware));
+    let protected = read
+        .merge(write_transactions)
+        .merge(admin)
+        .layer(from_fn_with_state(state, auth::auth_middleware));
     Router::new()
         //auth
         .route("/auth/register", post(auth::register))
@@ -38,4 +115,5 @@ pub fn app() -> axum::Router<AppState>{
 #[derive(Clone)]
 pub struct AppState {
     pub pool: PgPool,
+    pub storage: Arc<dyn Storage>,
 }
\ No newline at end of file
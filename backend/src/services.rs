@@ -1,10 +1,13 @@
-use crate::auth::AuthUser;
+use crate::auth::{self, AuthUser};
+use crate::webhooks;
 use crate::AppState;
 use rust_decimal::Decimal;
 use axum::{
     Extension,
     extract::{ Json,State,Query, Path},
-    http::StatusCode,
+    body::Bytes,
+    http::{StatusCode, HeaderName, header},
+    response::IntoResponse,
 };
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -13,14 +16,34 @@ use sqlx::PgPool;
 
 use sqlx::{Postgres, QueryBuilder, Transaction};
 use sqlx::types::chrono::NaiveDate;
+/// opens a database transaction on behalf of `user_id`, the audit trigger records them as
+/// `changed_by` on every row the transaction writes
+pub async fn begin_as(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<Transaction<'static, Postgres>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("SELECT set_config('app.user_id', $1::text, true)")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    Ok(tx)
+}
 pub async fn root(Extension(user): Extension<AuthUser>) -> String {
-    format!("Hello, user_id={}", user.user_id)
+    let scopes: Vec<&str> = user.scopes.iter().map(|s| s.as_str()).collect();
+    format!(
+        "Hello, user_id={} workspace_id={} role={} scopes={}",
+        user.user_id,
+        user.workspace_id,
+        user.role.as_str(),
+        scopes.join(",")
+    )
 }
 pub async fn get_ledger_snapshot_handler(
     State(state): State<AppState>,
     Extension(user): Extension<AuthUser>,
 ) -> Result<Json<CloudLedger>, (StatusCode, String)> {
-    let snapshot = build_ledger_snapshot(&state.pool, user.user_id)
+    let snapshot = build_ledger_snapshot(&state.pool, &user)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?;
 
@@ -28,8 +51,9 @@ pub async fn get_ledger_snapshot_handler(
 }
 pub async fn build_ledger_snapshot(
     pool: &PgPool,
-    user_id: Uuid,
+    member: &AuthUser,
 ) -> Result<CloudLedger, sqlx::Error> {
+    let workspace_id = member.workspace_id;
     let user = sqlx::query_as!(
         UserDto,
         r#"
@@ -40,10 +64,11 @@ pub async fn build_ledger_snapshot(
         FROM users
         WHERE id = $1
         "#,
-        user_id,
+        member.user_id,
     )
     .fetch_one(pool)
     .await?;
+    let workspace = get_workspace_db(pool, member.user_id, workspace_id).await?;
 
 
     let acc_query = AccountQuery {
@@ -57,15 +82,15 @@ pub async fn build_ledger_snapshot(
         include_balance: Some(false), 
     };
 
-    let accounts = list_accounts_db(pool, user_id, &acc_query).await?;
+    let accounts = list_accounts_db(pool, workspace_id, &acc_query).await?;
 
 
-    let categories = list_categories_db(pool, user_id).await?;
+    let categories = list_categories_db(pool, workspace_id).await?;
 
 
     let tx_limit = 1000;
     let tx_offset = 0;
-    let transactions = list_transactions_db(pool, user_id, tx_limit, tx_offset).await?;
+    let transactions = list_transactions_db(pool, workspace_id, tx_limit, tx_offset).await?;
 
 
     let mut all_entries: Vec<EntriesDto> = Vec::new();
@@ -75,12 +100,22 @@ pub async fn build_ledger_snapshot(
         }
     }
 
+    let securities = list_securities_db(pool, workspace_id).await?;
+    let trades = list_trades_db(pool, workspace_id, None).await?;
+    let prices = list_prices_db(pool, workspace_id).await?;
+    let attachments = list_attachments_db(pool, workspace_id, None).await?;
+
     Ok(CloudLedger {
         user,
+        workspace,
         accounts,
         categories,
         transactions,
         entries: all_entries,
+        securities,
+        trades,
+        prices,
+        attachments,
     })
 }
 pub async fn create_account_handler(
@@ -89,45 +124,77 @@ pub async fn create_account_handler(
     Json(req): Json<CreateAccountReq>,
 ) -> Result<Json<AccountDto>, (StatusCode, String)> {
     let currency = req.currency.as_deref().unwrap_or("CAD");
-    let opening_balance = req.opening_balance.unwrap_or(Decimal::ZERO);
+    // a new loan owes its principal unless told otherwise
+    let opening_balance = match (req.opening_balance, req.loan.loan_principal) {
+        (Some(ob), _) => ob,
+        (None, Some(principal)) if req.account_type == "loan" => -principal,
+        (None, _) => Decimal::ZERO,
+    };
 
-    let acc_row = create_account(
-        &state.pool,
-        user.user_id,
-        &req.name,
-        &req.account_type,
-        currency,
-        opening_balance,
-    )
+    let dto = create_account(&state.pool, user.workspace_id, user.user_id, &req, currency, opening_balance)
     .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?; 
+    .map_err(account_terms_error)?;
 
-    Ok(Json(acc_row.into()))
+    Ok(Json(dto))
 }
 pub async fn create_account(
     pool: &PgPool,
+    workspace_id: Uuid,
     user_id: Uuid,
-    name: &str,
-    account_type: &str,
+    req: &CreateAccountReq,
     currency: &str,
     opening_balance: Decimal,
-) -> Result<AccountRow, sqlx::Error> {
+) -> Result<AccountDto, sqlx::Error> {
+    let (credit, loan) = (&req.credit, &req.loan);
+    // investment accounts always have a cost basis method
+    let cost_basis = match req.account_type.as_str() {
+        "investment" => Some(req.cost_basis.as_deref().unwrap_or("fifo")),
+        _ => req.cost_basis.as_deref(),
+    };
+    let contact_email = req
+        .contact_email
+        .as_deref()
+        .map(str::trim)
+        .filter(|e| !e.is_empty());
+    let mut tx = begin_as(pool, user_id).await?;
     let acc = sqlx::query_as!(
         AccountRow,
         r#"
-        INSERT INTO accounts (user_id, name, account_type, currency, opening_balance)
-        VALUES ($1, $2, $3, $4, $5)
-        RETURNING id, user_id, name, account_type, currency, opening_balance, created_at
+        INSERT INTO accounts (
+            workspace_id, name, account_type, currency, opening_balance,
+            credit_limit, statement_day, due_day, apr,
+            loan_principal, loan_rate, loan_term_months, loan_frequency, loan_first_payment,
+            cost_basis, contact_email
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
+        RETURNING id, workspace_id, name, account_type, currency, opening_balance, created_at,
+            credit_limit, statement_day, due_day, apr,
+            loan_principal, loan_rate, loan_term_months, loan_frequency, loan_first_payment,
+            cost_basis, contact_email
         "#,
-        user_id,
-        name,
-        account_type,
+        workspace_id,
+        req.name,
+        req.account_type,
         currency,
-        opening_balance
+        opening_balance,
+        credit.credit_limit,
+        credit.statement_day,
+        credit.due_day,
+        credit.apr,
+        loan.loan_principal,
+        loan.loan_rate,
+        loan.loan_term_months,
+        loan.loan_frequency,
+        loan.loan_first_payment,
+        cost_basis,
+        contact_email
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *tx)
     .await?;
-    Ok(acc)
+    let dto: AccountDto = acc.into();
+    webhooks::enqueue(&mut tx, workspace_id, "account.updated", &dto).await?;
+    tx.commit().await?;
+    Ok(dto)
 }
 
 
@@ -136,7 +203,7 @@ pub async fn list_accounts_handler(
     Extension(user): Extension<AuthUser>,
     Query(q): Query<AccountQuery>,
 ) -> Result<Json<Vec<AccountDto>>, (StatusCode, String)> {
-    let rows = list_accounts_db(&state.pool, user.user_id, &q)
+    let rows = list_accounts_db(&state.pool, user.workspace_id, &q)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?;
 
@@ -144,7 +211,7 @@ pub async fn list_accounts_handler(
 }
 pub async fn list_accounts_db(
     pool: &PgPool,
-    user_id: Uuid,
+    workspace_id: Uuid,
     q: &AccountQuery,
 ) -> Result<Vec<AccountDto>, sqlx::Error> {
     let limit = q.limit.unwrap_or(50).clamp(1, 100);
@@ -167,26 +234,35 @@ pub async fn list_accounts_db(
             r#"
             SELECT
                 a.id, a.name, a.account_type, a.currency, a.opening_balance, a.created_at,
+                a.credit_limit, a.statement_day, a.due_day, a.apr,
+                a.loan_principal, a.loan_rate, a.loan_term_months, a.loan_frequency, a.loan_first_payment,
+                a.cost_basis, a.contact_email,
                 (a.opening_balance + COALESCE(SUM(e.amount), 0)) AS balance
             FROM accounts a
-            LEFT JOIN entries e ON e.account_id = a.id
-            LEFT JOIN transactions t ON t.id = e.tx_id
-            WHERE a.user_id =
+            LEFT JOIN (
+                entries e
+                JOIN transactions t ON t.id = e.tx_id AND t.deleted_at IS NULL
+            ) ON e.account_id = a.id AND e.deleted_at IS NULL
+            WHERE a.workspace_id =
             "#,
         );
-        qb.push_bind(user_id);
-        qb.push(" GROUP BY a.id ");
+        qb.push_bind(workspace_id);
+        qb.push(" AND a.deleted_at IS NULL GROUP BY a.id ");
     } else {
         qb.push(
             r#"
             SELECT
                 a.id, a.name, a.account_type, a.currency, a.opening_balance, a.created_at,
+                a.credit_limit, a.statement_day, a.due_day, a.apr,
+                a.loan_principal, a.loan_rate, a.loan_term_months, a.loan_frequency, a.loan_first_payment,
+                a.cost_basis, a.contact_email,
                 NULL::numeric AS balance
             FROM accounts a
-            WHERE a.user_id =
+            WHERE a.workspace_id =
             "#,
         );
-        qb.push_bind(user_id);
+        qb.push_bind(workspace_id);
+        qb.push(" AND a.deleted_at IS NULL");
     }
     // filters
     if let Some(t) = q.account_type.as_deref() {
@@ -218,54 +294,533 @@ pub async fn list_accounts_db(
 
     Ok(rows)
 }
-pub async fn delete_account_handler(
+/// credit card terms of an account, replaced as a whole; 400 for other account types
+pub async fn set_credit_terms_handler(
     State(state): State<AppState>,
     Extension(user): Extension<AuthUser>,
     Path(account_id): Path<i64>,
-) -> Result<StatusCode, (StatusCode, String)> {
-    delete_account_db(&state.pool, user.user_id, account_id)
+    Json(req): Json<CreditTermsReq>,
+) -> Result<Json<AccountDto>, (StatusCode, String)> {
+    let dto = set_credit_terms_db(&state.pool, user.workspace_id, user.user_id, account_id, &req)
+        .await
+        .map_err(account_terms_error)?;
+
+    Ok(Json(dto))
+}
+pub async fn set_credit_terms_db(
+    pool: &PgPool,
+    workspace_id: Uuid,
+    user_id: Uuid,
+    account_id: i64,
+    req: &CreditTermsReq,
+) -> Result<AccountDto, sqlx::Error> {
+    let mut tx: Transaction<'_, Postgres> = begin_as(pool, user_id).await?;
+    let acc = sqlx::query_as!(
+        AccountRow,
+        r#"
+        UPDATE accounts
+        SET credit_limit = $3, statement_day = $4, due_day = $5, apr = $6
+        WHERE workspace_id = $1 AND id = $2 AND deleted_at IS NULL
+        RETURNING id, workspace_id, name, account_type, currency, opening_balance, created_at,
+            credit_limit, statement_day, due_day, apr,
+            loan_principal, loan_rate, loan_term_months, loan_frequency, loan_first_payment,
+            cost_basis, contact_email
+        "#,
+        workspace_id,
+        account_id,
+        req.credit_limit,
+        req.statement_day,
+        req.due_day,
+        req.apr
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    let dto: AccountDto = acc.into();
+    webhooks::enqueue(&mut tx, workspace_id, "account.updated", &dto).await?;
+    tx.commit().await?;
+    Ok(dto)
+}
+/// loan terms of an account, all five fields or none; 400 for other account types
+pub async fn set_loan_terms_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(account_id): Path<i64>,
+    Json(req): Json<LoanTermsReq>,
+) -> Result<Json<AccountDto>, (StatusCode, String)> {
+    let dto = set_loan_terms_db(&state.pool, user.workspace_id, user.user_id, account_id, &req)
+        .await
+        .map_err(account_terms_error)?;
+
+    Ok(Json(dto))
+}
+pub async fn set_loan_terms_db(
+    pool: &PgPool,
+    workspace_id: Uuid,
+    user_id: Uuid,
+    account_id: i64,
+    req: &LoanTermsReq,
+) -> Result<AccountDto, sqlx::Error> {
+    let mut tx: Transaction<'_, Postgres> = begin_as(pool, user_id).await?;
+    let acc = sqlx::query_as!(
+        AccountRow,
+        r#"
+        UPDATE accounts
+        SET loan_principal = $3, loan_rate = $4, loan_term_months = $5,
+            loan_frequency = $6, loan_first_payment = $7
+        WHERE workspace_id = $1 AND id = $2 AND deleted_at IS NULL
+        RETURNING id, workspace_id, name, account_type, currency, opening_balance, created_at,
+            credit_limit, statement_day, due_day, apr,
+            loan_principal, loan_rate, loan_term_months, loan_frequency, loan_first_payment,
+            cost_basis, contact_email
+        "#,
+        workspace_id,
+        account_id,
+        req.loan_principal,
+        req.loan_rate,
+        req.loan_term_months,
+        req.loan_frequency,
+        req.loan_first_payment
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    let dto: AccountDto = acc.into();
+    webhooks::enqueue(&mut tx, workspace_id, "account.updated", &dto).await?;
+    tx.commit().await?;
+    Ok(dto)
+}
+/// payments a year for a loan_frequency
+fn loan_periods_per_year(frequency: &str) -> Decimal {
+    match frequency {
+        "weekly" => Decimal::from(52),
+        "biweekly" => Decimal::from(26),
+        _ => Decimal::from(12),
+    }
+}
+/// records a loan payment split into principal and interest
+///
+/// the interest is one period of the loan rate on what is owed at the payment date, paid from
+/// `from_account_id` under `interest_category_id`; the rest of `amount` moves to the loan account
+pub async fn create_loan_payment_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(account_id): Path<i64>,
+    Json(req): Json<LoanPaymentReq>,
+) -> Result<Json<TransactionsDto>, (StatusCode, String)> {
+    if req.amount <= Decimal::ZERO {
+        return Err((StatusCode::BAD_REQUEST, "amount must be positive".to_string()));
+    }
+    if req.from_account_id == account_id {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "a loan can't be paid from itself".to_string(),
+        ));
+    }
+    let db_error = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}"));
+    let loan = loan_balance_db(&state.pool, user.workspace_id, account_id, req.occurred_at)
+        .await
+        .map_err(db_error)?
+        .ok_or((StatusCode::NOT_FOUND, "account not found".to_string()))?;
+    let from_exists = sqlx::query_scalar!(
+        "SELECT EXISTS (SELECT 1 FROM accounts WHERE workspace_id = $1 AND id = $2 AND deleted_at IS NULL)",
+        user.workspace_id,
+        req.from_account_id
+    )
+    .fetch_one(&state.pool)
+    .await
+    .map_err(db_error)?;
+    if from_exists != Some(true) {
+        return Err((StatusCode::NOT_FOUND, "from account not found".to_string()));
+    }
+    let (Some(rate), Some(frequency)) = (loan.loan_rate, loan.loan_frequency.as_deref()) else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "account has no loan terms".to_string(),
+        ));
+    };
+    let owed = -loan.balance;
+    if owed <= Decimal::ZERO {
+        return Err((StatusCode::BAD_REQUEST, "loan is paid off".to_string()));
+    }
+    let interest = (owed * rate / Decimal::from(100) / loan_periods_per_year(frequency))
+        .round_dp(2)
+        .min(req.amount);
+    let principal = req.amount - interest;
+    if principal > owed {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("payment exceeds the {owed} owed plus {interest} interest"),
+        ));
+    }
+
+    let mut entries = Vec::new();
+    if principal > Decimal::ZERO {
+        for (account_id, amount) in [(req.from_account_id, -principal), (account_id, principal)] {
+            entries.push(CreateEntryReq {
+                account_id,
+                category_id: None,
+                amount,
+                note: Some("principal".to_string()),
+            });
+        }
+    }
+    if interest > Decimal::ZERO {
+        entries.push(CreateEntryReq {
+            account_id: req.from_account_id,
+            category_id: req.interest_category_id,
+            amount: -interest,
+            note: Some("interest".to_string()),
+        });
+    }
+    let tx = CreateTransactionsReq {
+        payee: Some(req.payee.unwrap_or(loan.name)),
+        memo: Some("loan payment".to_string()),
+        occurred_at: req.occurred_at,
+        entries,
+    };
+    let dto = create_transaction_with_entries_db(&state.pool, user.workspace_id, user.user_id, tx)
         .await
         .map_err(|e| match &e {
-            sqlx::Error::RowNotFound => {
-                (StatusCode::NOT_FOUND, "account not found".to_string())
-            }
             sqlx::Error::Database(db_err)
-                if db_err.constraint() == Some("entries_user_account_id_fkey") =>
+                if db_err.constraint() == Some("entries_category_id_fkey") =>
             {
-                // entries reference this account
-                (StatusCode::CONFLICT, "account has entries, cannot delete".to_string())
+                (StatusCode::NOT_FOUND, "interest category not found".to_string())
             }
-            _ => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("db error: {e}"),
-            ),
+            // the accounts were checked above, so a missing row is the category
+            sqlx::Error::RowNotFound => {
+                (StatusCode::NOT_FOUND, "interest category not found".to_string())
+            }
+            _ => db_error(e),
         })?;
+    Ok(Json(dto))
+}
+/// loan terms and balance of a loan account as of `date`, none when the account doesn't exist
+pub async fn loan_balance_db(
+    pool: &PgPool,
+    workspace_id: Uuid,
+    account_id: i64,
+    date: NaiveDate,
+) -> Result<Option<LoanBalanceRow>, sqlx::Error> {
+    let row = sqlx::query_as!(
+        LoanBalanceRow,
+        r#"
+        SELECT
+            a.name, a.loan_rate, a.loan_frequency,
+            (a.opening_balance + COALESCE(SUM(e.amount), 0)) AS "balance!"
+        FROM accounts a
+        LEFT JOIN (
+            entries e
+            JOIN transactions t ON t.id = e.tx_id AND t.deleted_at IS NULL AND t.occurred_at <= $3
+        ) ON e.account_id = a.id AND e.deleted_at IS NULL
+        WHERE a.workspace_id = $1 AND a.id = $2 AND a.deleted_at IS NULL
+        GROUP BY a.id
+        "#,
+        workspace_id,
+        account_id,
+        date
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row)
+}
+/// records settling up with a contact, a transfer between the contact and a real account
+///
+/// `amount` > 0 is money the contact paid in, < 0 money paid to them; without it the contact's
+/// balance at `occurred_at` is settled in full
+pub async fn create_settlement_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(contact_id): Path<i64>,
+    Json(req): Json<SettlementReq>,
+) -> Result<Json<TransactionsDto>, (StatusCode, String)> {
+    if req.account_id == contact_id {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "a contact can't settle with itself".to_string(),
+        ));
+    }
+    let db_error = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}"));
+    let contact = contact_balance_db(&state.pool, user.workspace_id, contact_id, req.occurred_at)
+        .await
+        .map_err(db_error)?
+        .ok_or((StatusCode::NOT_FOUND, "contact not found".to_string()))?;
+    if contact.account_type != "contact" {
+        return Err((StatusCode::BAD_REQUEST, "account is not a contact".to_string()));
+    }
+    let account_type = sqlx::query_scalar!(
+        "SELECT account_type FROM accounts WHERE workspace_id = $1 AND id = $2 AND deleted_at IS NULL",
+        user.workspace_id,
+        req.account_id
+    )
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(db_error)?;
+    match account_type.as_deref() {
+        None => return Err((StatusCode::NOT_FOUND, "account not found".to_string())),
+        Some("contact") => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "settle through a bank, cash or card account, not another contact".to_string(),
+            ));
+        }
+        Some(_) => {}
+    }
+    let amount = req.amount.unwrap_or(contact.balance);
+    if amount.is_zero() {
+        return Err((StatusCode::BAD_REQUEST, "nothing to settle".to_string()));
+    }
+
+    let entries = [(req.account_id, amount), (contact_id, -amount)]
+        .into_iter()
+        .map(|(account_id, amount)| CreateEntryReq {
+            account_id,
+            category_id: None,
+            amount,
+            note: Some("settlement".to_string()),
+        })
+        .collect();
+    let tx = CreateTransactionsReq {
+        payee: Some(req.payee.unwrap_or(contact.name)),
+        memo: Some("settlement".to_string()),
+        occurred_at: req.occurred_at,
+        entries,
+    };
+    let dto = create_transaction_with_entries_db(&state.pool, user.workspace_id, user.user_id, tx)
+        .await
+        .map_err(db_error)?;
+    Ok(Json(dto))
+}
+/// name, type and balance of an account as of `date`, none when the account doesn't exist
+pub async fn contact_balance_db(
+    pool: &PgPool,
+    workspace_id: Uuid,
+    account_id: i64,
+    date: NaiveDate,
+) -> Result<Option<ContactBalanceRow>, sqlx::Error> {
+    let row = sqlx::query_as!(
+        ContactBalanceRow,
+        r#"
+        SELECT
+            a.name, a.account_type,
+            (a.opening_balance + COALESCE(SUM(e.amount), 0)) AS "balance!"
+        FROM accounts a
+        LEFT JOIN (
+            entries e
+            JOIN transactions t ON t.id = e.tx_id AND t.deleted_at IS NULL AND t.occurred_at <= $3
+        ) ON e.account_id = a.id AND e.deleted_at IS NULL
+        WHERE a.workspace_id = $1 AND a.id = $2 AND a.deleted_at IS NULL
+        GROUP BY a.id
+        "#,
+        workspace_id,
+        account_id,
+        date
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row)
+}
+/// out of range or misplaced credit and loan terms or cost basis are the caller's fault
+fn account_terms_error(e: sqlx::Error) -> (StatusCode, String) {
+    match &e {
+        sqlx::Error::RowNotFound => (StatusCode::NOT_FOUND, "account not found".to_string()),
+        sqlx::Error::Database(db_err)
+            if db_err.constraint() == Some("accounts_credit_terms_only_credit") =>
+        {
+            (
+                StatusCode::BAD_REQUEST,
+                "only credit accounts have credit terms".to_string(),
+            )
+        }
+        sqlx::Error::Database(db_err)
+            if db_err.constraint().is_some_and(|c| {
+                ["credit_limit", "statement_day", "due_day", "apr"]
+                    .iter()
+                    .any(|col| c == format!("accounts_{col}_check"))
+            }) =>
+        {
+            (
+                StatusCode::BAD_REQUEST,
+                "credit_limit must be positive, statement_day and due_day 1-31, apr 0-100".to_string(),
+            )
+        }
+        sqlx::Error::Database(db_err)
+            if db_err.constraint() == Some("accounts_loan_terms_only_loan") =>
+        {
+            (
+                StatusCode::BAD_REQUEST,
+                "only loan accounts have loan terms".to_string(),
+            )
+        }
+        sqlx::Error::Database(db_err)
+            if db_err.constraint() == Some("accounts_loan_terms_complete") =>
+        {
+            (
+                StatusCode::BAD_REQUEST,
+                "loan terms need loan_principal, loan_rate, loan_term_months, loan_frequency and loan_first_payment together".to_string(),
+            )
+        }
+        sqlx::Error::Database(db_err)
+            if db_err.constraint() == Some("accounts_cost_basis_only_investment") =>
+        {
+            (
+                StatusCode::BAD_REQUEST,
+                "investment accounts, and only they, have a cost_basis".to_string(),
+            )
+        }
+        sqlx::Error::Database(db_err)
+            if db_err.constraint() == Some("accounts_contact_email_only_contact") =>
+        {
+            (
+                StatusCode::BAD_REQUEST,
+                "only contacts have a contact_email".to_string(),
+            )
+        }
+        sqlx::Error::Database(db_err) if db_err.constraint() == Some("accounts_contact_email_check") => (
+            StatusCode::BAD_REQUEST,
+            "contact_email is not an email address".to_string(),
+        ),
+        sqlx::Error::Database(db_err) if db_err.constraint() == Some("accounts_cost_basis_check") => (
+            StatusCode::BAD_REQUEST,
+            "cost_basis must be fifo or average".to_string(),
+        ),
+        sqlx::Error::Database(db_err)
+            if db_err.constraint().is_some_and(|c| {
+                ["loan_principal", "loan_rate", "loan_term_months", "loan_frequency"]
+                    .iter()
+                    .any(|col| c == format!("accounts_{col}_check"))
+            }) =>
+        {
+            (
+                StatusCode::BAD_REQUEST,
+                "loan_principal must be positive, loan_rate 0-100, loan_term_months 1-600, loan_frequency monthly, biweekly or weekly".to_string(),
+            )
+        }
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("db error: {e}"),
+        ),
+    }
+}
+fn delete_error(what: &str, e: DeleteError) -> (StatusCode, String) {
+    match e {
+        DeleteError::NotFound => (StatusCode::NOT_FOUND, format!("{what} not found")),
+        DeleteError::InUse(why) => (StatusCode::CONFLICT, format!("{why}, cannot delete")),
+        DeleteError::Db(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")),
+    }
+}
+pub async fn delete_account_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(account_id): Path<i64>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    delete_account_db(&state.pool, user.workspace_id, user.user_id, account_id)
+        .await
+        .map_err(|e| delete_error("account", e))?;
 
     Ok(StatusCode::NO_CONTENT)
 }
 
 pub async fn delete_account_db(
     pool: &PgPool,
+    workspace_id: Uuid,
     user_id: Uuid,
     account_id: i64,
-) -> Result<(), sqlx::Error> {
-    let result = sqlx::query!(
+) -> Result<(), DeleteError> {
+    let mut tx = begin_as(pool, user_id).await?;
+    // soft delete, entries of deleted transactions, reconciliations and goal links stay so a
+    // restore brings the account back as it was
+    let deleted_at = sqlx::query_scalar!(
         r#"
-        DELETE FROM accounts
-        WHERE id = $1 AND user_id = $2
+        UPDATE accounts
+        SET deleted_at = now()
+        WHERE id = $1 AND workspace_id = $2 AND deleted_at IS NULL
+        RETURNING deleted_at AS "deleted_at!"
         "#,
         account_id,
-        user_id,
+        workspace_id,
     )
-    .execute(pool)
+    .fetch_one(&mut *tx)
     .await?;
 
-    if result.rows_affected() == 0 {
-        return Err(sqlx::Error::RowNotFound);
+    // only live entries and trades keep an account, the row is locked above so none sneak in
+    let used = sqlx::query!(
+        r#"
+        SELECT
+            EXISTS (
+                SELECT 1 FROM entries e JOIN transactions t ON t.id = e.tx_id
+                WHERE e.workspace_id = $1 AND e.account_id = $2
+                  AND e.deleted_at IS NULL AND t.deleted_at IS NULL
+            ) AS "entries!",
+            EXISTS (
+                SELECT 1 FROM trades tr JOIN transactions t ON t.id = tr.tx_id
+                WHERE tr.workspace_id = $1 AND tr.account_id = $2 AND t.deleted_at IS NULL
+            ) AS "trades!"
+        "#,
+        workspace_id,
+        account_id,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    if used.entries {
+        return Err(DeleteError::InUse("account has entries"));
+    }
+    if used.trades {
+        return Err(DeleteError::InUse("account has trades"));
     }
 
+    let data = serde_json::json!({ "id": account_id, "deleted_at": deleted_at });
+    webhooks::enqueue(&mut tx, workspace_id, "account.updated", &data).await?;
+    tx.commit().await?;
     Ok(())
 }
+pub async fn restore_account_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(account_id): Path<i64>,
+) -> Result<Json<AccountDto>, (StatusCode, String)> {
+    let dto = restore_account_db(&state.pool, user.workspace_id, user.user_id, account_id)
+        .await
+        .map_err(|e| match &e {
+            sqlx::Error::RowNotFound => {
+                (StatusCode::NOT_FOUND, "deleted account not found".to_string())
+            }
+            sqlx::Error::Database(db_err)
+                if db_err.constraint() == Some("accounts_workspace_id_name_key") =>
+            {
+                (StatusCode::CONFLICT, "an account with this name exists".to_string())
+            }
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")),
+        })?;
+
+    Ok(Json(dto))
+}
+pub async fn restore_account_db(
+    pool: &PgPool,
+    workspace_id: Uuid,
+    user_id: Uuid,
+    account_id: i64,
+) -> Result<AccountDto, sqlx::Error> {
+    let mut tx = begin_as(pool, user_id).await?;
+    let acc = sqlx::query_as!(
+        AccountRow,
+        r#"
+        UPDATE accounts
+        SET deleted_at = NULL
+        WHERE workspace_id = $1 AND id = $2 AND deleted_at IS NOT NULL
+        RETURNING id, workspace_id, name, account_type, currency, opening_balance, created_at,
+            credit_limit, statement_day, due_day, apr,
+            loan_principal, loan_rate, loan_term_months, loan_frequency, loan_first_payment,
+            cost_basis, contact_email
+        "#,
+        workspace_id,
+        account_id,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    let dto: AccountDto = acc.into();
+    webhooks::enqueue(&mut tx, workspace_id, "account.updated", &dto).await?;
+    tx.commit().await?;
+    Ok(dto)
+}
 
 
 pub async fn create_category_handler(
@@ -277,12 +832,14 @@ pub async fn create_category_handler(
 
     let acc_row = create_category(
         &state.pool,
+        user.workspace_id,
         user.user_id,
         &req.name,
         req.parent_id,
+        req.tax_line.as_deref(),
     )
     .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?; 
+    .map_err(category_error)?;
 
     Ok(Json(acc_row.into()))
 }
@@ -290,30 +847,35 @@ pub async fn create_category_handler(
 
 pub async fn create_category(
     pool: &PgPool,
+    workspace_id: Uuid,
     user_id: Uuid,
     name: &str,
     parent_id: Option<i64>,
+    tax_line: Option<&str>,
 ) -> Result<CategoriesRow, sqlx::Error> {
+    let mut tx = begin_as(pool, user_id).await?;
     let acc = sqlx::query_as!(
         CategoriesRow,
         r#"
-        INSERT INTO categories (user_id, name, parent_id)
-        VALUES ($1, $2, $3)
-        RETURNING id, user_id, parent_id, name
+        INSERT INTO categories (workspace_id, name, parent_id, tax_line)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, workspace_id, parent_id, name, tax_line
         "#,
-        user_id,
+        workspace_id,
         name,
         parent_id,
+        tax_line,
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *tx)
     .await?;
+    tx.commit().await?;
     Ok(acc)
 }
 pub async fn list_categories_handler(
     State(state): State<AppState>,
     Extension(user): Extension<AuthUser>,
 ) -> Result<Json<Vec<CategoriesDto>>, (StatusCode, String)> {
-    let rows = list_categories_db(&state.pool, user.user_id)
+    let rows = list_categories_db(&state.pool, user.workspace_id)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?;
 
@@ -321,94 +883,197 @@ pub async fn list_categories_handler(
 }
 pub async fn list_categories_db(
     pool: &PgPool,
-    user_id: Uuid,
+    workspace_id: Uuid,
 ) -> Result<Vec<CategoriesDto>, sqlx::Error> {
     let rows = sqlx::query_as!(
         CategoriesDto,
         r#"
         SELECT
-            id, parent_id, name
+            id, parent_id, name, tax_line
         FROM categories 
-        WHERE user_id = $1
+        WHERE workspace_id = $1 AND deleted_at IS NULL
         ORDER BY parent_id NULLS FIRST, name
         "#,
-        user_id,
+        workspace_id,
     )
     .fetch_all(pool)
     .await?;
 
     Ok(rows)
 }
+/// sets or clears (null) the tax line of a category
+pub async fn set_tax_line_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(category_id): Path<i64>,
+    Json(req): Json<TaxLineReq>,
+) -> Result<Json<CategoriesDto>, (StatusCode, String)> {
+    let mut tx = begin_as(&state.pool, user.user_id).await.map_err(category_error)?;
+    let row = sqlx::query_as!(
+        CategoriesRow,
+        r#"
+        UPDATE categories
+        SET tax_line = $3
+        WHERE workspace_id = $1 AND id = $2 AND deleted_at IS NULL
+        RETURNING id, workspace_id, parent_id, name, tax_line
+        "#,
+        user.workspace_id,
+        category_id,
+        req.tax_line
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(category_error)?;
+    tx.commit().await.map_err(category_error)?;
+
+    Ok(Json(row.into()))
+}
+fn category_error(e: sqlx::Error) -> (StatusCode, String) {
+    match &e {
+        sqlx::Error::RowNotFound => (StatusCode::NOT_FOUND, "category not found".to_string()),
+        sqlx::Error::Database(db_err)
+            if db_err.constraint() == Some("categories_tax_line_check") =>
+        {
+            (
+                StatusCode::BAD_REQUEST,
+                "tax_line must be one of medical, charitable, childcare, education, business_income, business_expense, rental_income, rental_expense, investment_income".to_string(),
+            )
+        }
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("db error: {e}"),
+        ),
+    }
+}
 pub async fn delete_category_handler(
     State(state): State<AppState>,
     Extension(user): Extension<AuthUser>,
     Path(category_id): Path<i64>,
 ) -> Result<StatusCode, (StatusCode, String)> {
-    delete_category_db(&state.pool, user.user_id, category_id)
+    delete_category_db(&state.pool, user.workspace_id, user.user_id, category_id)
         .await
-        .map_err(|e| match e {
-            sqlx::Error::RowNotFound => {
-                (StatusCode::NOT_FOUND, "category not found".to_string())
-            }
-            _ => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("db error: {e}"),
-            ),
-        })?;
+        .map_err(|e| delete_error("category", e))?;
     Ok(StatusCode::NO_CONTENT)
 }
 pub async fn delete_category_db(
     pool: &PgPool,
+    workspace_id: Uuid,
     user_id: Uuid,
     category_id: i64,
-) -> Result<(), sqlx::Error> {
-    let res = sqlx::query!(
+) -> Result<(), DeleteError> {
+    let mut tx = begin_as(pool, user_id).await?;
+    // soft delete, entries keep the category and read as uncategorized until it is restored
+    sqlx::query!(
         r#"
-        DELETE FROM categories
-        WHERE id = $1 AND user_id = $2
+        UPDATE categories
+        SET deleted_at = now()
+        WHERE id = $1 AND workspace_id = $2 AND deleted_at IS NULL
+        RETURNING id
         "#,
         category_id,
-        user_id,
+        workspace_id,
     )
-    .execute(pool)
+    .fetch_one(&mut *tx)
     .await?;
 
-    if res.rows_affected() == 0 {
-        return Err(sqlx::Error::RowNotFound);
+    let has_children = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM categories
+            WHERE workspace_id = $1 AND parent_id = $2 AND deleted_at IS NULL
+        ) AS "exists!"
+        "#,
+        workspace_id,
+        category_id,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    if has_children {
+        return Err(DeleteError::InUse("category has sub-categories"));
     }
 
+    tx.commit().await?;
     Ok(())
 }
-pub async fn list_transactions_handler(
+pub async fn restore_category_handler(
     State(state): State<AppState>,
     Extension(user): Extension<AuthUser>,
-    Query(_q): Query<ListTxQuery>,
-) -> Result<Json<Vec<TransactionsDto>>, (StatusCode, String)> {
-    let limit = _q.limit.unwrap_or(50).clamp(1, 100);
-    let offset = _q.offset.unwrap_or(0).max(0); 
-    let rows = list_transactions_db(&state.pool, user.user_id,limit, offset)
+    Path(category_id): Path<i64>,
+) -> Result<Json<CategoriesDto>, (StatusCode, String)> {
+    let row = restore_category_db(&state.pool, user.workspace_id, user.user_id, category_id)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?;
+        .map_err(|e| match &e {
+            sqlx::Error::RowNotFound => (
+                StatusCode::NOT_FOUND,
+                "deleted category not found, or its parent is deleted".to_string(),
+            ),
+            sqlx::Error::Database(db_err)
+                if db_err.constraint() == Some("categories_workspace_id_name_key") =>
+            {
+                (StatusCode::CONFLICT, "a category with this name exists".to_string())
+            }
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")),
+        })?;
 
-    Ok(Json(rows))
+    Ok(Json(row.into()))
 }
-pub async fn list_transactions_db(
+pub async fn restore_category_db(
     pool: &PgPool,
+    workspace_id: Uuid,
     user_id: Uuid,
-    limit: i64,
-    offset: i64,
-) -> Result<Vec<TransactionsDto>, sqlx::Error> {
-    let tx_rows = sqlx::query_as!(
-        TransactionsRow,
+    category_id: i64,
+) -> Result<CategoriesRow, sqlx::Error> {
+    let mut tx = begin_as(pool, user_id).await?;
+    // a sub-category comes back only under a live parent
+    let row = sqlx::query_as!(
+        CategoriesRow,
+        r#"
+        UPDATE categories c
+        SET deleted_at = NULL
+        WHERE c.workspace_id = $1 AND c.id = $2 AND c.deleted_at IS NOT NULL
+          AND NOT EXISTS (
+            SELECT 1 FROM categories p WHERE p.id = c.parent_id AND p.deleted_at IS NOT NULL
+          )
+        RETURNING c.id, c.workspace_id, c.parent_id, c.name, c.tax_line
+        "#,
+        workspace_id,
+        category_id,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(row)
+}
+pub async fn list_transactions_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Query(_q): Query<ListTxQuery>,
+) -> Result<Json<Vec<TransactionsDto>>, (StatusCode, String)> {
+    let limit = _q.limit.unwrap_or(50).clamp(1, 100);
+    let offset = _q.offset.unwrap_or(0).max(0); 
+    let rows = list_transactions_db(&state.pool, user.workspace_id,limit, offset)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?;
+
+    Ok(Json(rows))
+}
+pub async fn list_transactions_db(
+    pool: &PgPool,
+    workspace_id: Uuid,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<TransactionsDto>, sqlx::Error> {
+    let tx_rows = sqlx::query_as!(
+        TransactionsRow,
         r#"
         SELECT
-            id, user_id, occurred_at, payee, memo, created_at
+            id, workspace_id, occurred_at, payee, memo, created_at
         FROM transactions 
-        WHERE user_id = $1
+        WHERE workspace_id = $1 AND deleted_at IS NULL
         ORDER BY occurred_at DESC, created_at DESC
         LIMIT $2 OFFSET $3
         "#,
-        user_id,
+        workspace_id,
         limit,
         offset,
     )
@@ -422,13 +1087,16 @@ pub async fn list_transactions_db(
         EntriesRow,
         r#"
         SELECT
-            id, user_id, tx_id, account_id, category_id, amount, note
-        FROM entries
-        WHERE user_id = $1 
-        AND tx_id = ANY($2)
-        ORDER BY tx_id,id
+            e.id, e.workspace_id, e.tx_id, e.account_id, c.id AS "category_id?", e.amount, e.note,
+            e.status
+        FROM entries e
+        LEFT JOIN categories c ON c.id = e.category_id AND c.deleted_at IS NULL
+        WHERE e.workspace_id = $1 
+        AND e.tx_id = ANY($2)
+        AND e.deleted_at IS NULL
+        ORDER BY e.tx_id, e.id
         "#,
-        user_id,
+        workspace_id,
         &tx_ids[..] as &[Uuid],
     )
     .fetch_all(pool)
@@ -458,11 +1126,15 @@ pub async fn create_transaction_handler(
 ) -> Result<Json<TransactionsDto>, (StatusCode, String)> {
     let tran_dto =create_transaction_with_entries_db(
         &state.pool,
+        user.workspace_id,
         user.user_id,
         req
     )
     .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?;
+    .map_err(|e| match e {
+        sqlx::Error::RowNotFound => (StatusCode::NOT_FOUND, "account or category not found".to_string()),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")),
+    })?;
     Ok(Json(tran_dto))
 
 
@@ -470,18 +1142,29 @@ pub async fn create_transaction_handler(
 
 pub async fn create_transaction_with_entries_db( 
     pool: &PgPool, 
-    user_id: Uuid, 
+    workspace_id: Uuid, 
+    user_id: Uuid,
     req: CreateTransactionsReq, 
 ) -> Result<TransactionsDto, sqlx::Error>  {
-    let mut tx: Transaction<'_, Postgres> = pool.begin().await?; // if error, return all
+    let mut tx: Transaction<'_, Postgres> = begin_as(pool, user_id).await?; // if error, return all
+    let dto = insert_transaction_with_entries(&mut tx, workspace_id, &req).await?;
+    tx.commit().await?;
+    Ok(dto)
+}
+/// inserts a transaction and its entries inside the caller's database transaction
+pub async fn insert_transaction_with_entries(
+    tx: &mut sqlx::PgConnection,
+    workspace_id: Uuid,
+    req: &CreateTransactionsReq,
+) -> Result<TransactionsDto, sqlx::Error> {
     let tx_row = sqlx::query_as!(
         TransactionsRow,
         r#"
-        INSERT INTO transactions (user_id, occurred_at, payee,memo, created_at)
+        INSERT INTO transactions (workspace_id, occurred_at, payee,memo, created_at)
         VALUES ($1, $2, $3, $4, $5)
-        RETURNING id, user_id, occurred_at, payee, memo, created_at
+        RETURNING id, workspace_id, occurred_at, payee, memo, created_at
         "#,
-        user_id,
+        workspace_id,
         req.occurred_at,
         req.payee,
         req.memo,
@@ -494,9 +1177,15 @@ pub async fn create_transaction_with_entries_db(
         let row = sqlx::query_as!(
             EntriesRow,
             r#"
-            INSERT INTO entries (tx_id, account_id, category_id, amount, note, user_id)
-            VALUES ($1, $2, $3, $4, $5, $6)
-            RETURNING id, user_id, tx_id, account_id, category_id, amount, note
+            INSERT INTO entries (tx_id, account_id, category_id, amount, note, workspace_id)
+            SELECT $1, $2, $3, $4, $5, $6
+            -- a deleted account or category is as unknown as a missing one
+            WHERE EXISTS (
+                SELECT 1 FROM accounts WHERE workspace_id = $6 AND id = $2 AND deleted_at IS NULL
+            ) AND ($3::bigint IS NULL OR EXISTS (
+                SELECT 1 FROM categories WHERE workspace_id = $6 AND id = $3 AND deleted_at IS NULL
+            ))
+            RETURNING id, workspace_id, tx_id, account_id, category_id, amount, note, status
             "#,
             
             tx_row.id,
@@ -504,99 +1193,2553 @@ pub async fn create_transaction_with_entries_db(
             entry.category_id,
             &entry.amount,
             entry.note,
-            user_id,
+            workspace_id,
         )
         .fetch_one(&mut *tx)
         .await?;
         entry_dtos.push(row.into());
     }
 
-    tx.commit().await?;
-    Ok(TransactionsDto {
+    let dto = TransactionsDto {
         id: tx_row.id,
         occurred_at: tx_row.occurred_at,
         payee: tx_row.payee,
         memo: tx_row.memo,
         created_at: tx_row.created_at,
         entries: entry_dtos,
+    };
+    webhooks::enqueue(&mut *tx, workspace_id, "transaction.created", &dto).await?;
+    Ok(dto)
+}
+/// a transaction with its live entries, deleted or not, inside the caller's database transaction
+pub async fn get_transaction_db(
+    tx: &mut sqlx::PgConnection,
+    workspace_id: Uuid,
+    tx_id: Uuid,
+) -> Result<TransactionsDto, sqlx::Error> {
+    let t = sqlx::query_as!(
+        TransactionsRow,
+        r#"
+        SELECT id, workspace_id, occurred_at, payee, memo, created_at
+        FROM transactions
+        WHERE workspace_id = $1 AND id = $2
+        "#,
+        workspace_id,
+        tx_id,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    let entries = sqlx::query_as!(
+        EntriesRow,
+        r#"
+        SELECT
+            e.id, e.workspace_id, e.tx_id, e.account_id, c.id AS "category_id?", e.amount, e.note,
+            e.status
+        FROM entries e
+        LEFT JOIN categories c ON c.id = e.category_id AND c.deleted_at IS NULL
+        WHERE e.workspace_id = $1 AND e.tx_id = $2 AND e.deleted_at IS NULL
+        ORDER BY e.id
+        "#,
+        workspace_id,
+        tx_id,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    Ok(TransactionsDto {
+        id: t.id,
+        occurred_at: t.occurred_at,
+        payee: t.payee,
+        memo: t.memo,
+        created_at: t.created_at,
+        entries: entries.into_iter().map(Into::into).collect(),
     })
 }
-pub async fn delete_transaction_handler(
+pub async fn delete_transaction_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(tx_id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    delete_transaction_db(&state.pool, user.workspace_id, user.user_id, tx_id)
+        .await
+        .map_err(|e| match &e {
+            sqlx::Error::RowNotFound => (StatusCode::NOT_FOUND, "transaction not found".to_string()),
+            sqlx::Error::Database(db_err)
+                if db_err.constraint() == Some("entries_reconciled_lock") =>
+            {
+                (StatusCode::CONFLICT, "transaction has reconciled entries, cannot delete".to_string())
+            }
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")),
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+pub async fn delete_transaction_db(
+    pool: &PgPool,
+    workspace_id: Uuid,
+    user_id: Uuid,
+    tx_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    let mut tx: Transaction<'_, Postgres> = begin_as(pool, user_id).await?;
+    // soft delete, entries stay attached so a restore brings them back
+    let deleted_at = sqlx::query_scalar!(
+        r#"
+        UPDATE transactions
+        SET deleted_at = now()
+        WHERE workspace_id = $1 AND id = $2 AND deleted_at IS NULL
+        RETURNING deleted_at AS "deleted_at!"
+        "#,
+        workspace_id,
+        tx_id,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let data = serde_json::json!({ "id": tx_id, "deleted_at": deleted_at });
+    webhooks::enqueue(&mut tx, workspace_id, "transaction.deleted", &data).await?;
+    tx.commit().await?;
+    Ok(())
+}
+pub async fn restore_transaction_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(tx_id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    restore_transaction_db(&state.pool, user.workspace_id, user.user_id, tx_id)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => {
+                (
+                    StatusCode::NOT_FOUND,
+                    "deleted transaction not found, or one of its accounts is deleted".to_string(),
+                )
+            }
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")),
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+pub async fn restore_transaction_db(
+    pool: &PgPool,
+    workspace_id: Uuid,
+    user_id: Uuid,
+    tx_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    let mut tx = begin_as(pool, user_id).await?;
+    let result = sqlx::query!(
+        r#"
+        UPDATE transactions t
+        SET deleted_at = NULL
+        WHERE t.workspace_id = $1 AND t.id = $2 AND t.deleted_at IS NOT NULL
+          AND NOT EXISTS (
+            SELECT 1 FROM entries e JOIN accounts a ON a.id = e.account_id
+            WHERE e.tx_id = t.id AND e.deleted_at IS NULL AND a.deleted_at IS NOT NULL
+          )
+        "#,
+        workspace_id,
+        tx_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound);
+    }
+
+    // it is back with its id, subscribers get it as if it were new
+    let dto = get_transaction_db(&mut tx, workspace_id, tx_id).await?;
+    webhooks::enqueue(&mut tx, workspace_id, "transaction.created", &dto).await?;
+    tx.commit().await?;
+    Ok(())
+}
+pub async fn delete_entry_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(entry_id): Path<i64>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    delete_entry_db(&state.pool, user.workspace_id, user.user_id, entry_id)
+        .await
+        .map_err(|e| match &e {
+            sqlx::Error::RowNotFound => {
+                (StatusCode::NOT_FOUND, "entry not found".to_string())
+            }
+            sqlx::Error::Database(db_err)
+                if db_err.constraint() == Some("entries_reconciled_lock") =>
+            {
+                (StatusCode::CONFLICT, "entry is reconciled, cannot delete".to_string())
+            }
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("db error: {e}"),
+            ),
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+pub async fn delete_entry_db(
+    pool: &PgPool,
+    workspace_id: Uuid,
+    user_id: Uuid,
+    entry_id: i64,
+) -> Result<(), sqlx::Error> {
+    let mut tx = begin_as(pool, user_id).await?;
+    let tx_id = sqlx::query_scalar!(
+        r#"
+        UPDATE entries
+        SET deleted_at = now()
+        WHERE id = $1 AND workspace_id = $2 AND deleted_at IS NULL
+        RETURNING tx_id
+        "#,
+        entry_id,
+        workspace_id,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let dto = get_transaction_db(&mut tx, workspace_id, tx_id).await?;
+    webhooks::enqueue(&mut tx, workspace_id, "transaction.updated", &dto).await?;
+    tx.commit().await?;
+    Ok(())
+}
+pub async fn restore_entry_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(entry_id): Path<i64>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    restore_entry_db(&state.pool, user.workspace_id, user.user_id, entry_id)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => {
+                (
+                    StatusCode::NOT_FOUND,
+                    "deleted entry not found, or its account is deleted".to_string(),
+                )
+            }
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("db error: {e}"),
+            ),
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+pub async fn restore_entry_db(
+    pool: &PgPool,
+    workspace_id: Uuid,
+    user_id: Uuid,
+    entry_id: i64,
+) -> Result<(), sqlx::Error> {
+    let mut tx = begin_as(pool, user_id).await?;
+    let tx_id = sqlx::query_scalar!(
+        r#"
+        UPDATE entries e
+        SET deleted_at = NULL
+        WHERE e.id = $1 AND e.workspace_id = $2 AND e.deleted_at IS NOT NULL
+          AND NOT EXISTS (
+            SELECT 1 FROM accounts a WHERE a.id = e.account_id AND a.deleted_at IS NOT NULL
+          )
+        RETURNING e.tx_id
+        "#,
+        entry_id,
+        workspace_id,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let dto = get_transaction_db(&mut tx, workspace_id, tx_id).await?;
+    webhooks::enqueue(&mut tx, workspace_id, "transaction.updated", &dto).await?;
+    tx.commit().await?;
+    Ok(())
+}
+pub async fn set_entry_status_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(entry_id): Path<i64>,
+    Json(req): Json<EntryStatusReq>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    // reconciled is only set by finishing a reconciliation
+    if !matches!(req.status.as_str(), "uncleared" | "cleared") {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "status must be uncleared or cleared".to_string(),
+        ));
+    }
+    set_entry_status_db(&state.pool, user.workspace_id, user.user_id, entry_id, &req.status)
+        .await
+        .map_err(|e| match &e {
+            sqlx::Error::RowNotFound => {
+                (StatusCode::NOT_FOUND, "entry not found".to_string())
+            }
+            sqlx::Error::Database(db_err)
+                if db_err.constraint() == Some("entries_reconciled_lock") =>
+            {
+                (StatusCode::CONFLICT, "entry is reconciled and locked".to_string())
+            }
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("db error: {e}"),
+            ),
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+pub async fn set_entry_status_db(
+    pool: &PgPool,
+    workspace_id: Uuid,
+    user_id: Uuid,
+    entry_id: i64,
+    status: &str,
+) -> Result<(), sqlx::Error> {
+    let mut tx = begin_as(pool, user_id).await?;
+    let tx_id = sqlx::query_scalar!(
+        r#"
+        UPDATE entries
+        SET status = $3
+        WHERE id = $1 AND workspace_id = $2 AND deleted_at IS NULL
+        RETURNING tx_id
+        "#,
+        entry_id,
+        workspace_id,
+        status,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let dto = get_transaction_db(&mut tx, workspace_id, tx_id).await?;
+    webhooks::enqueue(&mut tx, workspace_id, "transaction.updated", &dto).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn create_reconciliation_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<CreateReconciliationReq>,
+) -> Result<Json<ReconciliationDto>, (StatusCode, String)> {
+    let rec = create_reconciliation_db(&state.pool, user.workspace_id, user.user_id, &req)
+        .await
+        .map_err(|e| match &e {
+            sqlx::Error::Database(db_err)
+                if db_err.constraint() == Some("reconciliations_one_open_idx") =>
+            {
+                (
+                    StatusCode::CONFLICT,
+                    "account already has an open reconciliation".to_string(),
+                )
+            }
+            sqlx::Error::Database(db_err)
+                if db_err.constraint() == Some("reconciliations_workspace_id_account_id_fkey") =>
+            {
+                (StatusCode::NOT_FOUND, "account not found".to_string())
+            }
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("db error: {e}"),
+            ),
+        })?;
+
+    Ok(Json(rec))
+}
+pub async fn create_reconciliation_db(
+    pool: &PgPool,
+    workspace_id: Uuid,
+    user_id: Uuid,
+    req: &CreateReconciliationReq,
+) -> Result<ReconciliationDto, sqlx::Error> {
+    let mut tx = begin_as(pool, user_id).await?;
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO reconciliations (workspace_id, account_id, statement_date, statement_balance)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id
+        "#,
+        workspace_id,
+        req.account_id,
+        req.statement_date,
+        req.statement_balance,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let rec = get_reconciliation_db(&mut *tx, workspace_id, id).await?;
+    tx.commit().await?;
+    Ok(rec)
+}
+pub async fn get_reconciliation_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i64>,
+) -> Result<Json<ReconciliationDto>, (StatusCode, String)> {
+    let rec = get_reconciliation_db(&state.pool, user.workspace_id, id)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => {
+                (StatusCode::NOT_FOUND, "reconciliation not found".to_string())
+            }
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")),
+        })?;
+
+    Ok(Json(rec))
+}
+pub async fn get_reconciliation_db(
+    db: impl sqlx::PgExecutor<'_>,
+    workspace_id: Uuid,
+    id: i64,
+) -> Result<ReconciliationDto, sqlx::Error> {
+    sqlx::query_as!(
+        ReconciliationDto,
+        r#"
+        SELECT
+            r.id, r.account_id, r.statement_date, r.statement_balance, r.cleared_balance,
+            r.status, r.created_at, r.finished_at,
+            (SELECT COUNT(*) FROM entries e WHERE e.reconciliation_id = r.id) AS "entry_count!"
+        FROM reconciliations r
+        WHERE r.workspace_id = $1 AND r.id = $2
+        "#,
+        workspace_id,
+        id,
+    )
+    .fetch_one(db)
+    .await
+}
+pub async fn list_reconciliations_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Query(q): Query<ReconciliationQuery>,
+) -> Result<Json<Vec<ReconciliationDto>>, (StatusCode, String)> {
+    let rows = list_reconciliations_db(&state.pool, user.workspace_id, &q)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?;
+
+    Ok(Json(rows))
+}
+pub async fn list_reconciliations_db(
+    pool: &PgPool,
+    workspace_id: Uuid,
+    q: &ReconciliationQuery,
+) -> Result<Vec<ReconciliationDto>, sqlx::Error> {
+    let mut qb = QueryBuilder::new(
+        r#"
+        SELECT
+            r.id, r.account_id, r.statement_date, r.statement_balance, r.cleared_balance,
+            r.status, r.created_at, r.finished_at,
+            (SELECT COUNT(*) FROM entries e WHERE e.reconciliation_id = r.id) AS entry_count
+        FROM reconciliations r
+        WHERE r.workspace_id =
+        "#,
+    );
+    qb.push_bind(workspace_id);
+    if let Some(acc) = q.account_id {
+        qb.push(" AND r.account_id = ");
+        qb.push_bind(acc);
+    }
+    if let Some(st) = q.status.as_deref() {
+        qb.push(" AND r.status = ");
+        qb.push_bind(st);
+    }
+    qb.push(" ORDER BY r.statement_date DESC, r.id DESC");
+
+    let rows: Vec<ReconciliationDto> = qb
+        .build_query_as::<ReconciliationDto>()
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows)
+}
+pub async fn finish_reconciliation_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i64>,
+) -> Result<Json<ReconciliationDto>, (StatusCode, String)> {
+    let rec = finish_reconciliation_db(&state.pool, user.workspace_id, user.user_id, id)
+        .await
+        .map_err(|e| match e {
+            FinishReconError::NotFound => (
+                StatusCode::NOT_FOUND,
+                "open reconciliation not found".to_string(),
+            ),
+            FinishReconError::Unbalanced {
+                statement_balance,
+                cleared_balance,
+            } => (
+                StatusCode::CONFLICT,
+                format!(
+                    "statement balance {statement_balance} but cleared balance {cleared_balance}, difference {}",
+                    statement_balance - cleared_balance
+                ),
+            ),
+            FinishReconError::Db(e) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}"))
+            }
+        })?;
+
+    Ok(Json(rec))
+}
+pub async fn finish_reconciliation_db(
+    pool: &PgPool,
+    workspace_id: Uuid,
+    user_id: Uuid,
+    id: i64,
+) -> Result<ReconciliationDto, FinishReconError> {
+    let mut tx: Transaction<'_, Postgres> = begin_as(pool, user_id).await?;
+    let rec = sqlx::query!(
+        r#"
+        SELECT r.account_id, r.statement_date, r.statement_balance, a.opening_balance
+        FROM reconciliations r
+        JOIN accounts a ON a.workspace_id = r.workspace_id AND a.id = r.account_id
+        WHERE r.workspace_id = $1 AND r.id = $2 AND r.status = 'open'
+        FOR UPDATE OF r
+        "#,
+        workspace_id,
+        id,
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(FinishReconError::NotFound)?;
+
+    // opening balance + everything reconciled before + what is ticked off up to the statement date;
+    // the rows are locked, so an edit or delete waits and the set summed is the set reconciled
+    let rows = sqlx::query!(
+        r#"
+        SELECT e.id, e.amount, e.status
+        FROM entries e
+        JOIN transactions t ON t.id = e.tx_id
+        WHERE e.workspace_id = $1 AND e.account_id = $2
+          AND e.deleted_at IS NULL AND t.deleted_at IS NULL
+          AND (e.status = 'reconciled' OR (e.status = 'cleared' AND t.occurred_at <= $3))
+        FOR UPDATE OF e, t
+        "#,
+        workspace_id,
+        rec.account_id,
+        rec.statement_date,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    let cleared_balance = rec.opening_balance + rows.iter().map(|r| r.amount).sum::<Decimal>();
+    if cleared_balance != rec.statement_balance {
+        return Err(FinishReconError::Unbalanced {
+            statement_balance: rec.statement_balance,
+            cleared_balance,
+        });
+    }
+
+    // tie the ticked entries to this session
+    let ticked: Vec<i64> = rows
+        .iter()
+        .filter(|r| r.status == "cleared")
+        .map(|r| r.id)
+        .collect();
+    sqlx::query!(
+        r#"
+        UPDATE entries
+        SET status = 'reconciled', reconciliation_id = $2
+        WHERE id = ANY($1)
+        "#,
+        &ticked,
+        id,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE reconciliations
+        SET status = 'finished', cleared_balance = $3, finished_at = now()
+        WHERE workspace_id = $1 AND id = $2
+        "#,
+        workspace_id,
+        id,
+        cleared_balance,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let dto = get_reconciliation_db(&mut *tx, workspace_id, id).await?;
+    webhooks::enqueue(&mut tx, workspace_id, "reconcile.completed", &dto).await?;
+    tx.commit().await?;
+    Ok(dto)
+}
+pub async fn cancel_reconciliation_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    cancel_reconciliation_db(&state.pool, user.workspace_id, user.user_id, id)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => (
+                StatusCode::NOT_FOUND,
+                "open reconciliation not found".to_string(),
+            ),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")),
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+pub async fn cancel_reconciliation_db(
+    pool: &PgPool,
+    workspace_id: Uuid,
+    user_id: Uuid,
+    id: i64,
+) -> Result<(), sqlx::Error> {
+    let mut tx = begin_as(pool, user_id).await?;
+    // finished sessions are history and stay, ticked entries stay cleared
+    let res = sqlx::query!(
+        r#"
+        DELETE FROM reconciliations
+        WHERE workspace_id = $1 AND id = $2 AND status = 'open'
+        "#,
+        workspace_id,
+        id,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound);
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn list_audit_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Query(q): Query<AuditQuery>,
+) -> Result<Json<Vec<AuditDto>>, (StatusCode, String)> {
+    let rows = list_audit_db(&state.pool, user.workspace_id, &q)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?;
+
+    Ok(Json(rows))
+}
+pub async fn list_audit_db(
+    pool: &PgPool,
+    workspace_id: Uuid,
+    q: &AuditQuery,
+) -> Result<Vec<AuditDto>, sqlx::Error> {
+    let limit = q.limit.unwrap_or(50).clamp(1, 500);
+    let offset = q.offset.unwrap_or(0).max(0);
+
+    let mut qb = QueryBuilder::new(
+        r#"
+        SELECT
+            l.id, l.table_name, l.row_id, l.tx_id, l.action, l.before, l.after, l.changed_at,
+            l.changed_by, u.email AS changed_by_email
+        FROM audit_log l
+        LEFT JOIN users u ON u.id = l.changed_by
+        WHERE l.workspace_id =
+        "#,
+    );
+    qb.push_bind(workspace_id);
+    // filters
+    if let Some(t) = q.table.as_deref() {
+        qb.push(" AND l.table_name = ");
+        qb.push_bind(t);
+    }
+    if let Some(r) = q.row_id.as_deref() {
+        qb.push(" AND l.row_id = ");
+        qb.push_bind(r);
+    }
+    if let Some(tx) = q.tx_id {
+        qb.push(" AND l.tx_id = ");
+        qb.push_bind(tx);
+    }
+    if let Some(a) = q.action.as_deref() {
+        qb.push(" AND l.action = ");
+        qb.push_bind(a);
+    }
+    if let Some(from) = q.from {
+        qb.push(" AND l.changed_at >= ");
+        qb.push_bind(from);
+    }
+    if let Some(to) = q.to {
+        // inclusive end date
+        qb.push(" AND l.changed_at < ");
+        qb.push_bind(to);
+        qb.push(" + INTERVAL '1 day'");
+    }
+
+    qb.push(" ORDER BY l.changed_at DESC, l.id DESC");
+    qb.push(" LIMIT ");
+    qb.push_bind(limit);
+    qb.push(" OFFSET ");
+    qb.push_bind(offset);
+
+    let rows: Vec<AuditDto> = qb.build_query_as::<AuditDto>().fetch_all(pool).await?;
+
+    Ok(rows)
+}
+
+// reports: same numbers as the client's stat::ledger, computed in SQL.
+// An entry counts on its transaction date, amount >= 0 is income and < 0 is outcome,
+// deleted transactions and entries are left out.
+pub async fn report_summary_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Query(q): Query<ReportQuery>,
+) -> Result<Json<ReportTotalsDto>, (StatusCode, String)> {
+    let row = report_summary_db(&state.pool, user.workspace_id, &q)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?;
+
+    Ok(Json(row))
+}
+pub async fn report_summary_db(
+    pool: &PgPool,
+    workspace_id: Uuid,
+    q: &ReportQuery,
+) -> Result<ReportTotalsDto, sqlx::Error> {
+    let (from, to) = q.range();
+    let mut qb = QueryBuilder::new("SELECT ");
+    qb.push_bind(from);
+    qb.push("::date AS from, ");
+    qb.push_bind(to);
+    qb.push(
+        r#"::date AS to,
+            COALESCE(SUM(e.amount) FILTER (WHERE e.amount >= 0), 0) AS income,
+            COALESCE(SUM(e.amount) FILTER (WHERE e.amount < 0), 0) AS outcome,
+            COALESCE(SUM(e.amount), 0) AS net
+        FROM entries e
+        JOIN transactions t ON t.id = e.tx_id AND t.deleted_at IS NULL
+        WHERE e.deleted_at IS NULL AND e.workspace_id =
+        "#,
+    );
+    qb.push_bind(workspace_id);
+    qb.push(" AND t.occurred_at BETWEEN ");
+    qb.push_bind(from);
+    qb.push(" AND ");
+    qb.push_bind(to);
+    if let Some(acc) = q.account_id {
+        qb.push(" AND e.account_id = ");
+        qb.push_bind(acc);
+    }
+    if let Some(cat) = q.category_id {
+        qb.push(" AND e.category_id = ");
+        qb.push_bind(cat);
+    }
+
+    let row = qb
+        .build_query_as::<ReportTotalsDto>()
+        .fetch_one(pool)
+        .await?;
+
+    Ok(row)
+}
+pub async fn report_top_categories_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Query(q): Query<ReportQuery>,
+) -> Result<Json<Vec<ReportRankDto>>, (StatusCode, String)> {
+    let order = q.rank_order().ok_or((
+        StatusCode::BAD_REQUEST,
+        "rank must be income, outcome or net".to_string(),
+    ))?;
+    let rows = report_top_db(&state.pool, user.workspace_id, &q, ReportGroup::Category, order)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?;
+
+    Ok(Json(rows))
+}
+pub async fn report_top_accounts_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Query(q): Query<ReportQuery>,
+) -> Result<Json<Vec<ReportRankDto>>, (StatusCode, String)> {
+    let order = q.rank_order().ok_or((
+        StatusCode::BAD_REQUEST,
+        "rank must be income, outcome or net".to_string(),
+    ))?;
+    let rows = report_top_db(&state.pool, user.workspace_id, &q, ReportGroup::Account, order)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?;
+
+    Ok(Json(rows))
+}
+pub async fn report_top_db(
+    pool: &PgPool,
+    workspace_id: Uuid,
+    q: &ReportQuery,
+    group: ReportGroup,
+    order: &str,
+) -> Result<Vec<ReportRankDto>, sqlx::Error> {
+    let (from, to) = q.range();
+    let limit = q.limit.unwrap_or(10).clamp(1, 100);
+
+    // every category (account) used in range is listed, the other filter only narrows the sums,
+    // so a category without spending on the filtered account shows up with 0 like on the client
+    let (join, filter) = match group {
+        ReportGroup::Category => (
+            "JOIN categories g ON g.id = e.category_id AND g.deleted_at IS NULL",
+            q.account_id.map(|acc| (" AND e.account_id = ", acc)),
+        ),
+        ReportGroup::Account => (
+            // contacts are ranked on /reports/contacts, not here
+            "JOIN accounts g ON g.id = e.account_id AND g.account_type <> 'contact' AND g.deleted_at IS NULL",
+            q.category_id.map(|cat| (" AND e.category_id = ", cat)),
+        ),
+    };
+    let mut qb = QueryBuilder::new("SELECT g.id, g.name");
+    for (alias, cond) in [
+        ("income", "e.amount >= 0"),
+        ("outcome", "e.amount < 0"),
+        ("net", "TRUE"),
+    ] {
+        qb.push(format!(", COALESCE(SUM(e.amount) FILTER (WHERE {cond}").as_str());
+        if let Some((sql, id)) = filter {
+            qb.push(sql);
+            qb.push_bind(id);
+        }
+        qb.push(format!("), 0) AS {alias}").as_str());
+    }
+    qb.push(
+        r#"
+        FROM entries e
+        JOIN transactions t ON t.id = e.tx_id AND t.deleted_at IS NULL
+        "#,
+    );
+    qb.push(join);
+    qb.push(" WHERE e.deleted_at IS NULL AND e.workspace_id = ");
+    qb.push_bind(workspace_id);
+    qb.push(" AND t.occurred_at BETWEEN ");
+    qb.push_bind(from);
+    qb.push(" AND ");
+    qb.push_bind(to);
+    qb.push(format!(" GROUP BY g.id, g.name ORDER BY {order}, g.name, g.id").as_str());
+    qb.push(" LIMIT ");
+    qb.push_bind(limit);
+
+    let rows: Vec<ReportRankDto> = qb
+        .build_query_as::<ReportRankDto>()
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows)
+}
+pub async fn report_trends_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Query(q): Query<ReportQuery>,
+) -> Result<Json<Vec<ReportTotalsDto>>, (StatusCode, String)> {
+    let (unit, step) = match q.period.as_deref().unwrap_or("month") {
+        "week" => ("week", "1 week"),
+        "month" => ("month", "1 month"),
+        "quarter" => ("quarter", "3 months"),
+        "year" => ("year", "1 year"),
+        _ => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "period must be week, month, quarter or year".to_string(),
+            ));
+        }
+    };
+    let rows = report_trends_db(&state.pool, user.workspace_id, &q, unit, step)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?;
+
+    Ok(Json(rows))
+}
+pub async fn report_trends_db(
+    pool: &PgPool,
+    workspace_id: Uuid,
+    q: &ReportQuery,
+    unit: &str,
+    step: &str,
+) -> Result<Vec<ReportTotalsDto>, sqlx::Error> {
+    let (from, to) = q.range();
+    // one row per bucket, empty buckets included, the first and last are clipped to the range
+    let mut qb = QueryBuilder::new(
+        r#"
+        WITH buckets AS (
+            SELECT
+                GREATEST(b::date,
+        "#,
+    );
+    qb.push_bind(from);
+    qb.push("::date) AS bucket_from, LEAST((b + ");
+    qb.push_bind(step);
+    qb.push("::interval - INTERVAL '1 day')::date, ");
+    qb.push_bind(to);
+    qb.push("::date) AS bucket_to FROM generate_series(date_trunc(");
+    qb.push_bind(unit);
+    qb.push(", ");
+    qb.push_bind(from);
+    qb.push("::date::timestamp), ");
+    qb.push_bind(to);
+    qb.push("::date::timestamp, ");
+    qb.push_bind(step);
+    qb.push(
+        r#"::interval) AS b
+        )
+        SELECT
+            bk.bucket_from AS from, bk.bucket_to AS to,
+            COALESCE(SUM(e.amount) FILTER (WHERE e.amount >= 0), 0) AS income,
+            COALESCE(SUM(e.amount) FILTER (WHERE e.amount < 0), 0) AS outcome,
+            COALESCE(SUM(e.amount), 0) AS net
+        FROM buckets bk
+        LEFT JOIN (
+            entries e
+            JOIN transactions t ON t.id = e.tx_id AND t.deleted_at IS NULL
+        ) ON t.occurred_at BETWEEN bk.bucket_from AND bk.bucket_to
+            AND e.deleted_at IS NULL AND e.workspace_id =
+        "#,
+    );
+    qb.push_bind(workspace_id);
+    if let Some(acc) = q.account_id {
+        qb.push(" AND e.account_id = ");
+        qb.push_bind(acc);
+    }
+    if let Some(cat) = q.category_id {
+        qb.push(" AND e.category_id = ");
+        qb.push_bind(cat);
+    }
+    qb.push(" GROUP BY bk.bucket_from, bk.bucket_to ORDER BY bk.bucket_from");
+
+    let rows: Vec<ReportTotalsDto> = qb
+        .build_query_as::<ReportTotalsDto>()
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows)
+}
+pub async fn report_balances_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Query(q): Query<BalanceQuery>,
+) -> Result<Json<Vec<ReportBalanceDto>>, (StatusCode, String)> {
+    let date = q.date.unwrap_or_else(|| Utc::now().date_naive());
+    let rows = report_balances_db(&state.pool, user.workspace_id, date)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?;
+
+    Ok(Json(rows))
+}
+pub async fn report_balances_db(
+    pool: &PgPool,
+    workspace_id: Uuid,
+    date: NaiveDate,
+) -> Result<Vec<ReportBalanceDto>, sqlx::Error> {
+    // opening balance plus every entry dated on or before `date`; contacts are on /reports/contacts
+    let rows = sqlx::query_as::<_, ReportBalanceDto>(
+        r#"
+        SELECT
+            a.id AS account_id, a.name, a.account_type, a.currency,
+            (a.opening_balance + COALESCE(SUM(e.amount), 0)) AS balance
+        FROM accounts a
+        LEFT JOIN (
+            entries e
+            JOIN transactions t ON t.id = e.tx_id AND t.deleted_at IS NULL AND t.occurred_at <= $2
+        ) ON e.account_id = a.id AND e.deleted_at IS NULL
+        WHERE a.workspace_id = $1 AND a.account_type <> 'contact' AND a.deleted_at IS NULL
+        GROUP BY a.id
+        ORDER BY a.id
+        "#,
+    )
+    .bind(workspace_id)
+    .bind(date)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+/// what each contact owes (receivable) or is owed (payable) at `date`
+pub async fn report_contacts_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Query(q): Query<BalanceQuery>,
+) -> Result<Json<Vec<ReportContactDto>>, (StatusCode, String)> {
+    let date = q.date.unwrap_or_else(|| Utc::now().date_naive());
+    let rows = report_contacts_db(&state.pool, user.workspace_id, date)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?;
+
+    Ok(Json(rows))
+}
+pub async fn report_contacts_db(
+    pool: &PgPool,
+    workspace_id: Uuid,
+    date: NaiveDate,
+) -> Result<Vec<ReportContactDto>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, ReportContactDto>(
+        r#"
+        SELECT
+            account_id, name, contact_email, currency, balance,
+            GREATEST(balance, 0) AS receivable,
+            GREATEST(-balance, 0) AS payable
+        FROM (
+            SELECT
+                a.id AS account_id, a.name, a.contact_email, a.currency,
+                (a.opening_balance + COALESCE(SUM(e.amount), 0)) AS balance
+            FROM accounts a
+            LEFT JOIN (
+                entries e
+                JOIN transactions t ON t.id = e.tx_id AND t.deleted_at IS NULL AND t.occurred_at <= $2
+            ) ON e.account_id = a.id AND e.deleted_at IS NULL
+            WHERE a.workspace_id = $1 AND a.account_type = 'contact' AND a.deleted_at IS NULL
+            GROUP BY a.id
+        ) c
+        ORDER BY name, account_id
+        "#,
+    )
+    .bind(workspace_id)
+    .bind(date)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+// goals: a target amount by an optional deadline, progress is computed by the client
+// from the linked accounts' balances or the entries tagged #tag
+pub async fn create_goal_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<CreateGoalReq>,
+) -> Result<Json<GoalDto>, (StatusCode, String)> {
+    let bad = |msg: &str| (StatusCode::BAD_REQUEST, msg.to_string());
+    let name = req.name.trim();
+    if name.is_empty() {
+        return Err(bad("name must not be empty"));
+    }
+    if req.target_amount <= Decimal::ZERO {
+        return Err(bad("target_amount must be positive"));
+    }
+    // "#Trip" and "trip" are the same tag
+    let tag = req
+        .tag
+        .as_deref()
+        .map(|t| t.trim().trim_start_matches('#').to_lowercase())
+        .filter(|t| !t.is_empty());
+    let tag_ok = |t: &str| t.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if tag.as_deref().is_some_and(|t| !tag_ok(t)) {
+        return Err(bad("tag may only contain letters, digits, _ and -"));
+    }
+    if tag.is_none() && req.account_ids.is_empty() {
+        return Err(bad("a goal needs linked accounts or a tag"));
+    }
+
+    let goal = create_goal_db(&state.pool, user.workspace_id, user.user_id, name, &req, tag.as_deref())
+        .await
+        .map_err(|e| match &e {
+            sqlx::Error::Database(db_err)
+                if db_err.constraint() == Some("goals_workspace_id_name_key") =>
+            {
+                (StatusCode::CONFLICT, "a goal with this name exists".to_string())
+            }
+            sqlx::Error::Database(db_err)
+                if db_err.constraint() == Some("goal_accounts_workspace_id_account_id_fkey") =>
+            {
+                (StatusCode::NOT_FOUND, "account not found".to_string())
+            }
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("db error: {e}"),
+            ),
+        })?;
+
+    Ok(Json(goal))
+}
+pub async fn create_goal_db(
+    pool: &PgPool,
+    workspace_id: Uuid,
+    user_id: Uuid,
+    name: &str,
+    req: &CreateGoalReq,
+    tag: Option<&str>,
+) -> Result<GoalDto, sqlx::Error> {
+    let mut tx: Transaction<'_, Postgres> = begin_as(pool, user_id).await?;
+    let id: i64 = sqlx::query_scalar(
+        r#"
+        INSERT INTO goals (workspace_id, name, target_amount, target_date, tag)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id
+        "#,
+    )
+    .bind(workspace_id)
+    .bind(name)
+    .bind(req.target_amount)
+    .bind(req.target_date)
+    .bind(tag)
+    .fetch_one(&mut *tx)
+    .await?;
+    sqlx::query(
+        r#"
+        INSERT INTO goal_accounts (workspace_id, goal_id, account_id)
+        SELECT $1, $2, a FROM UNNEST($3::bigint[]) AS a
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(workspace_id)
+    .bind(id)
+    .bind(&req.account_ids)
+    .execute(&mut *tx)
+    .await?;
+    let goal = list_goals_db(&mut *tx, workspace_id, Some(id))
+        .await?
+        .pop()
+        .ok_or(sqlx::Error::RowNotFound)?;
+    tx.commit().await?;
+
+    Ok(goal)
+}
+pub async fn list_goals_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+) -> Result<Json<Vec<GoalDto>>, (StatusCode, String)> {
+    let rows = list_goals_db(&state.pool, user.workspace_id, None)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?;
+
+    Ok(Json(rows))
+}
+pub async fn list_goals_db(
+    db: impl sqlx::PgExecutor<'_>,
+    workspace_id: Uuid,
+    id: Option<i64>,
+) -> Result<Vec<GoalDto>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, GoalDto>(
+        r#"
+        SELECT
+            g.id, g.name, g.target_amount, g.target_date, g.tag, g.created_at,
+            COALESCE(
+                array_agg(ga.account_id ORDER BY ga.account_id)
+                    FILTER (WHERE ga.account_id IS NOT NULL),
+                '{}'
+            ) AS account_ids
+        FROM goals g
+        LEFT JOIN (
+            goal_accounts ga
+            JOIN accounts a ON a.id = ga.account_id AND a.deleted_at IS NULL
+        ) ON ga.goal_id = g.id
+        WHERE g.workspace_id = $1 AND g.deleted_at IS NULL AND ($2::bigint IS NULL OR g.id = $2)
+        GROUP BY g.id
+        ORDER BY g.target_date NULLS LAST, g.id
+        "#,
+    )
+    .bind(workspace_id)
+    .bind(id)
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows)
+}
+pub async fn delete_goal_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let deleted = delete_goal_db(&state.pool, user.workspace_id, user.user_id, id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?;
+    if !deleted {
+        return Err((StatusCode::NOT_FOUND, "goal not found".to_string()));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+pub async fn delete_goal_db(
+    pool: &PgPool,
+    workspace_id: Uuid,
+    user_id: Uuid,
+    id: i64,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = begin_as(pool, user_id).await?;
+    // soft delete, the linked accounts stay linked for a restore
+    let res = sqlx::query(
+        "UPDATE goals SET deleted_at = now() WHERE workspace_id = $1 AND id = $2 AND deleted_at IS NULL",
+    )
+    .bind(workspace_id)
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(res.rows_affected() > 0)
+}
+pub async fn restore_goal_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i64>,
+) -> Result<Json<GoalDto>, (StatusCode, String)> {
+    let goal = restore_goal_db(&state.pool, user.workspace_id, user.user_id, id)
+        .await
+        .map_err(|e| match &e {
+            sqlx::Error::Database(db_err)
+                if db_err.constraint() == Some("goals_workspace_id_name_key") =>
+            {
+                (StatusCode::CONFLICT, "a goal with this name exists".to_string())
+            }
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")),
+        })?
+        .ok_or((StatusCode::NOT_FOUND, "deleted goal not found".to_string()))?;
+
+    Ok(Json(goal))
+}
+pub async fn restore_goal_db(
+    pool: &PgPool,
+    workspace_id: Uuid,
+    user_id: Uuid,
+    id: i64,
+) -> Result<Option<GoalDto>, sqlx::Error> {
+    let mut tx = begin_as(pool, user_id).await?;
+    let res = sqlx::query(
+        "UPDATE goals SET deleted_at = NULL WHERE workspace_id = $1 AND id = $2 AND deleted_at IS NOT NULL",
+    )
+    .bind(workspace_id)
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
+    if res.rows_affected() == 0 {
+        return Ok(None);
+    }
+    let goal = list_goals_db(&mut *tx, workspace_id, Some(id)).await?.pop();
+    tx.commit().await?;
+
+    Ok(goal)
+}
+
+// investments: securities, imported prices and trades of investment accounts; holdings, lots
+// and gains are computed by the client
+/// cost basis method of an investment account, "fifo" or "average"
+pub async fn set_cost_basis_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(account_id): Path<i64>,
+    Json(req): Json<CostBasisReq>,
+) -> Result<Json<AccountDto>, (StatusCode, String)> {
+    let mut tx = begin_as(&state.pool, user.user_id).await.map_err(account_terms_error)?;
+    let row = sqlx::query_as!(
+        AccountRow,
+        r#"
+        UPDATE accounts
+        SET cost_basis = $3
+        WHERE workspace_id = $1 AND id = $2 AND deleted_at IS NULL
+        RETURNING id, workspace_id, name, account_type, currency, opening_balance, created_at,
+            credit_limit, statement_day, due_day, apr,
+            loan_principal, loan_rate, loan_term_months, loan_frequency, loan_first_payment,
+            cost_basis, contact_email
+        "#,
+        user.workspace_id,
+        account_id,
+        req.cost_basis
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(account_terms_error)?;
+    let dto: AccountDto = row.into();
+    webhooks::enqueue(&mut tx, user.workspace_id, "account.updated", &dto)
+        .await
+        .map_err(account_terms_error)?;
+    tx.commit().await.map_err(account_terms_error)?;
+
+    Ok(Json(dto))
+}
+/// adds a security or renames an existing one with the same symbol
+pub async fn create_security_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<CreateSecurityReq>,
+) -> Result<Json<SecurityDto>, (StatusCode, String)> {
+    let name = req.name.as_deref().map(str::trim).filter(|n| !n.is_empty());
+    let mut tx = begin_as(&state.pool, user.user_id).await.map_err(security_error)?;
+    let security = upsert_security_db(&mut *tx, user.workspace_id, &req.symbol, name)
+        .await
+        .map_err(security_error)?;
+    tx.commit().await.map_err(security_error)?;
+
+    Ok(Json(security))
+}
+pub async fn upsert_security_db(
+    db: impl sqlx::PgExecutor<'_>,
+    workspace_id: Uuid,
+    symbol: &str,
+    name: Option<&str>,
+) -> Result<SecurityDto, sqlx::Error> {
+    let row = sqlx::query_as::<_, SecurityDto>(
+        r#"
+        INSERT INTO securities (workspace_id, symbol, name)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (workspace_id, symbol) DO UPDATE SET name = COALESCE(EXCLUDED.name, securities.name)
+        RETURNING id, symbol, name, created_at
+        "#,
+    )
+    .bind(workspace_id)
+    .bind(symbol.trim().to_uppercase())
+    .bind(name)
+    .fetch_one(db)
+    .await?;
+
+    Ok(row)
+}
+pub async fn list_securities_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+) -> Result<Json<Vec<SecurityDto>>, (StatusCode, String)> {
+    let rows = list_securities_db(&state.pool, user.workspace_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?;
+
+    Ok(Json(rows))
+}
+pub async fn list_securities_db(
+    pool: &PgPool,
+    workspace_id: Uuid,
+) -> Result<Vec<SecurityDto>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, SecurityDto>(
+        r#"
+        SELECT id, symbol, name, created_at
+        FROM securities
+        WHERE workspace_id = $1
+        ORDER BY symbol
+        "#,
+    )
+    .bind(workspace_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+/// imports a price table, a price already known for that day is replaced
+///
+/// rows of symbols without a security are skipped and reported back, so a typo doesn't
+/// create a security nobody holds
+pub async fn import_prices_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Json(rows): Json<Vec<PriceReq>>,
+) -> Result<Json<PriceImportDto>, (StatusCode, String)> {
+    if let Some(bad) = rows.iter().find(|r| r.price < Decimal::ZERO) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("negative price for {} on {}", bad.symbol, bad.date),
+        ));
+    }
+    let result = import_prices_db(&state.pool, user.workspace_id, &rows)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?;
+
+    Ok(Json(result))
+}
+pub async fn import_prices_db(
+    pool: &PgPool,
+    workspace_id: Uuid,
+    rows: &[PriceReq],
+) -> Result<PriceImportDto, sqlx::Error> {
+    let ids: std::collections::HashMap<String, i64> = list_securities_db(pool, workspace_id)
+        .await?
+        .into_iter()
+        .map(|s| (s.symbol, s.id))
+        .collect();
+    let mut tx: Transaction<'_, Postgres> = pool.begin().await?;
+    let mut result = PriceImportDto { imported: 0, unknown: Vec::new() };
+    for row in rows {
+        let symbol = row.symbol.trim().to_uppercase();
+        let Some(id) = ids.get(&symbol) else {
+            if !result.unknown.contains(&symbol) {
+                result.unknown.push(symbol);
+            }
+            continue;
+        };
+        sqlx::query(
+            r#"
+            INSERT INTO security_prices (workspace_id, security_id, price_date, price)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (security_id, price_date) DO UPDATE SET price = EXCLUDED.price
+            "#,
+        )
+        .bind(workspace_id)
+        .bind(id)
+        .bind(row.date)
+        .bind(row.price)
+        .execute(&mut *tx)
+        .await?;
+        result.imported += 1;
+    }
+    tx.commit().await?;
+
+    Ok(result)
+}
+pub async fn list_prices_db(pool: &PgPool, workspace_id: Uuid) -> Result<Vec<PriceDto>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, PriceDto>(
+        r#"
+        SELECT security_id, price_date, price
+        FROM security_prices
+        WHERE workspace_id = $1
+        ORDER BY security_id, price_date
+        "#,
+    )
+    .bind(workspace_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+/// records a buy, sell, dividend or split of an investment account
+///
+/// the cash side is a transaction on the account: a buy pays quantity × price plus fees, a sell
+/// receives quantity × price minus fees, a dividend receives `amount`, a split moves no cash.
+/// The security is created on its first trade. A sell may not leave fewer than zero units at
+/// any date, counting splits
+pub async fn create_trade_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(account_id): Path<i64>,
+    Json(req): Json<CreateTradeReq>,
+) -> Result<Json<TradeDto>, (StatusCode, String)> {
+    let bad = |msg: &str| (StatusCode::BAD_REQUEST, msg.to_string());
+    let quantity = req.quantity.unwrap_or(Decimal::ZERO);
+    let price = req.price.unwrap_or(Decimal::ZERO);
+    let fees = req.fees.unwrap_or(Decimal::ZERO);
+    if fees < Decimal::ZERO || price < Decimal::ZERO {
+        return Err(bad("price and fees must not be negative"));
+    }
+    let symbol = req.symbol.trim().to_uppercase();
+    let (amount, memo, note) = match req.kind.as_str() {
+        "buy" | "sell" if quantity <= Decimal::ZERO => {
+            return Err(bad("quantity must be positive"));
+        }
+        "buy" => (
+            -(quantity * price + fees).round_dp(2),
+            format!("buy {quantity} {symbol} @ {price}"),
+            "buy",
+        ),
+        "sell" => (
+            (quantity * price - fees).round_dp(2),
+            format!("sell {quantity} {symbol} @ {price}"),
+            "sell",
+        ),
+        "dividend" => match req.amount {
+            Some(a) if a > Decimal::ZERO => (a, format!("dividend {symbol}"), "dividend"),
+            _ => return Err(bad("a dividend needs a positive amount")),
+        },
+        "split" if quantity <= Decimal::ZERO => {
+            return Err(bad("a split needs a positive quantity, the new units per old unit"));
+        }
+        "split" => (Decimal::ZERO, format!("split {symbol} {quantity}:1"), "split"),
+        _ => return Err(bad("kind must be buy, sell, dividend or split")),
+    };
+    let account_type = sqlx::query_scalar!(
+        "SELECT account_type FROM accounts WHERE workspace_id = $1 AND id = $2 AND deleted_at IS NULL",
+        user.workspace_id,
+        account_id
+    )
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?
+    .ok_or((StatusCode::NOT_FOUND, "account not found".to_string()))?;
+    if account_type != "investment" {
+        return Err(bad("trades need an investment account"));
+    }
+
+    // a split changes units only, it has no entry
+    let entries = match req.kind.as_str() {
+        "split" => Vec::new(),
+        _ => vec![CreateEntryReq {
+            account_id,
+            category_id: req.category_id.filter(|_| req.kind == "dividend"),
+            amount,
+            note: Some(note.to_string()),
+        }],
+    };
+    let tx = CreateTransactionsReq {
+        payee: Some(req.payee.clone().unwrap_or_else(|| symbol.clone())),
+        memo: Some(memo),
+        occurred_at: req.trade_date,
+        entries,
+    };
+    let trade = NewTrade {
+        account_id,
+        symbol: &symbol,
+        kind: &req.kind,
+        quantity,
+        price,
+        fees,
+        amount,
+    };
+    let dto = create_trade_db(&state.pool, user.workspace_id, user.user_id, &trade, &tx)
+        .await
+        .map_err(|e| match e {
+            TradeError::Oversold {
+                symbol,
+                date,
+                quantity,
+                held,
+            } => (
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "sells {} {symbol} on {date} but only {} are held",
+                    quantity.normalize(),
+                    held.normalize()
+                ),
+            ),
+            TradeError::Db(sqlx::Error::Database(db_err))
+                if db_err.constraint() == Some("entries_category_id_fkey") =>
+            {
+                (StatusCode::NOT_FOUND, "dividend category not found".to_string())
+            }
+            TradeError::Db(sqlx::Error::RowNotFound) => {
+                (StatusCode::NOT_FOUND, "dividend category not found".to_string())
+            }
+            TradeError::Db(e) => security_error(e),
+        })?;
+
+    Ok(Json(dto))
+}
+pub async fn create_trade_db(
+    pool: &PgPool,
+    workspace_id: Uuid,
+    user_id: Uuid,
+    trade: &NewTrade<'_>,
+    cash: &CreateTransactionsReq,
+) -> Result<TradeDto, TradeError> {
+    let mut tx = begin_as(pool, user_id).await?;
+    // one trade at a time per account, so two sells can't both pass the holdings check
+    sqlx::query("SELECT id FROM accounts WHERE workspace_id = $1 AND id = $2 AND deleted_at IS NULL FOR UPDATE")
+        .bind(workspace_id)
+        .bind(trade.account_id)
+        .execute(&mut *tx)
+        .await?;
+    let security = upsert_security_db(&mut *tx, workspace_id, trade.symbol, None).await?;
+    let cash = insert_transaction_with_entries(&mut tx, workspace_id, cash).await?;
+    let id: i64 = sqlx::query_scalar(
+        r#"
+        INSERT INTO trades (workspace_id, account_id, security_id, tx_id, kind, quantity, price, fees, amount)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        RETURNING id
+        "#,
+    )
+    .bind(workspace_id)
+    .bind(trade.account_id)
+    .bind(security.id)
+    .bind(cash.id)
+    .bind(trade.kind)
+    .bind(trade.quantity)
+    .bind(trade.price)
+    .bind(trade.fees)
+    .bind(trade.amount)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let trades = list_trades_db(&mut *tx, workspace_id, Some((trade.account_id, security.id))).await?;
+    if trade.kind == "sell" {
+        // units held after every trade in date order, a backdated sell must fit too
+        let mut held = Decimal::ZERO;
+        for t in &trades {
+            match t.kind.as_str() {
+                "buy" => held += t.quantity,
+                "sell" if t.quantity > held => {
+                    return Err(TradeError::Oversold {
+                        symbol: security.symbol,
+                        date: t.trade_date,
+                        quantity: t.quantity,
+                        held,
+                    });
+                }
+                "sell" => held -= t.quantity,
+                "split" => held *= t.quantity,
+                _ => {}
+            }
+        }
+    }
+    let dto = trades
+        .into_iter()
+        .find(|t| t.id == id)
+        .ok_or(sqlx::Error::RowNotFound)?;
+    tx.commit().await?;
+
+    Ok(dto)
+}
+/// live trades, i.e. whose transaction isn't deleted, oldest first; `of` narrows them to
+/// one security of one account
+pub async fn list_trades_db(
+    db: impl sqlx::PgExecutor<'_>,
+    workspace_id: Uuid,
+    of: Option<(i64, i64)>,
+) -> Result<Vec<TradeDto>, sqlx::Error> {
+    let (account_id, security_id) = of.unzip();
+    let rows = sqlx::query_as::<_, TradeDto>(
+        r#"
+        SELECT
+            tr.id, tr.account_id, tr.security_id, s.symbol, tr.tx_id, tr.kind,
+            t.occurred_at AS trade_date, tr.quantity, tr.price, tr.fees, tr.amount
+        FROM trades tr
+        JOIN transactions t ON t.id = tr.tx_id AND t.deleted_at IS NULL
+        JOIN securities s ON s.id = tr.security_id
+        WHERE tr.workspace_id = $1
+          AND ($2::bigint IS NULL OR tr.account_id = $2)
+          AND ($3::bigint IS NULL OR tr.security_id = $3)
+        ORDER BY t.occurred_at, tr.id
+        "#,
+    )
+    .bind(workspace_id)
+    .bind(account_id)
+    .bind(security_id)
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows)
+}
+/// a symbol the check constraint refuses is the caller's fault
+fn security_error(e: sqlx::Error) -> (StatusCode, String) {
+    match &e {
+        sqlx::Error::Database(db_err) if db_err.constraint() == Some("securities_symbol_check") => (
+            StatusCode::BAD_REQUEST,
+            "symbol may only contain letters, digits, '.', ':' and '-'".to_string(),
+        ),
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("db error: {e}"),
+        ),
+    }
+}
+
+// attachments: receipts and documents on transactions, the bytes go to the attachment storage
+// and the table keeps what they are, their checksum and where they are stored
+/// largest file accepted, larger uploads get 413
+pub const MAX_ATTACHMENT_BYTES: usize = 10 * 1024 * 1024;
+/// what one workspace may store across every attachment
+pub const MAX_WORKSPACE_ATTACHMENT_BYTES: i64 = 500 * 1024 * 1024;
+
+/// type of a file from its first bytes, none for anything that isn't an image or a PDF
+fn sniff_content_type(bytes: &[u8]) -> Option<&'static str> {
+    match bytes {
+        [0xFF, 0xD8, 0xFF, ..] => Some("image/jpeg"),
+        [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => Some("image/png"),
+        [b'G', b'I', b'F', b'8', ..] => Some("image/gif"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+        [b'%', b'P', b'D', b'F', b'-', ..] => Some("application/pdf"),
+        _ => None,
+    }
+}
+/// last path component of an uploaded name, without control characters
+fn clean_filename(name: &str) -> Option<String> {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let clean: String = base.chars().filter(|c| !c.is_control()).take(255).collect();
+    let clean = clean.trim();
+    (!clean.is_empty() && clean != "." && clean != "..").then(|| clean.to_string())
+}
+fn sha256_hex(bytes: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    hex::encode(Sha256::digest(bytes))
+}
+/// attaches the request body to a transaction
+///
+/// the type is detected from the bytes, only images and PDFs are accepted; `sha256`, when
+/// given, must match what arrived so a damaged upload is refused instead of stored
+pub async fn upload_attachment_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(tx_id): Path<Uuid>,
+    Query(q): Query<UploadAttachmentQuery>,
+    body: Bytes,
+) -> Result<(StatusCode, Json<AttachmentDto>), (StatusCode, String)> {
+    if body.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "empty attachment".to_string()));
+    }
+    if body.len() > MAX_ATTACHMENT_BYTES {
+        return Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("attachment is larger than {} MiB", MAX_ATTACHMENT_BYTES / 1024 / 1024),
+        ));
+    }
+    let filename = clean_filename(&q.filename)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "bad filename".to_string()))?;
+    let content_type = sniff_content_type(&body).ok_or_else(|| {
+        (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "only JPEG, PNG, GIF, WebP images and PDF documents can be attached".to_string(),
+        )
+    })?;
+    let sha256 = sha256_hex(&body);
+    if q.sha256.as_ref().is_some_and(|expected| !expected.eq_ignore_ascii_case(&sha256)) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("checksum mismatch, received {sha256}"),
+        ));
+    }
+
+    let live = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM transactions WHERE workspace_id = $1 AND id = $2 AND deleted_at IS NULL
+        )
+        "#,
+    )
+    .bind(user.workspace_id)
+    .bind(tx_id)
+    .fetch_one(&state.pool)
+    .await
+    .map_err(attachment_error)?;
+    if !live {
+        return Err((StatusCode::NOT_FOUND, "transaction not found".to_string()));
+    }
+    let used = sqlx::query_scalar::<_, i64>(
+        "SELECT COALESCE(SUM(size), 0)::bigint FROM attachments WHERE workspace_id = $1 AND deleted_at IS NULL",
+    )
+    .bind(user.workspace_id)
+    .fetch_one(&state.pool)
+    .await
+    .map_err(attachment_error)?;
+    if used + body.len() as i64 > MAX_WORKSPACE_ATTACHMENT_BYTES {
+        return Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!(
+                "attachment storage is full ({} MiB per workspace)",
+                MAX_WORKSPACE_ATTACHMENT_BYTES / 1024 / 1024
+            ),
+        ));
+    }
+
+    // bytes first: a row never points at nothing, a file without a row is removed again
+    let storage_key = format!("{}/{}", user.workspace_id, Uuid::new_v4());
+    state.storage.put(&storage_key, &body).await.map_err(storage_error)?;
+    let inserted = async {
+        let mut tx = begin_as(&state.pool, user.user_id).await?;
+        let dto = sqlx::query_as::<_, AttachmentDto>(
+            r#"
+            INSERT INTO attachments (workspace_id, tx_id, filename, content_type, size, sha256, storage_key)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id, tx_id, filename, content_type, size, sha256, created_at
+            "#,
+        )
+        .bind(user.workspace_id)
+        .bind(tx_id)
+        .bind(&filename)
+        .bind(content_type)
+        .bind(body.len() as i64)
+        .bind(&sha256)
+        .bind(&storage_key)
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok::<_, sqlx::Error>(dto)
+    }
+    .await;
+    match inserted {
+        Ok(dto) => Ok((StatusCode::CREATED, Json(dto))),
+        Err(e) => {
+            if let Err(cleanup) = state.storage.delete(&storage_key).await {
+                tracing::warn!("orphan attachment {storage_key}: {cleanup}");
+            }
+            Err(attachment_error(e))
+        }
+    }
+}
+pub async fn list_attachments_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(tx_id): Path<Uuid>,
+) -> Result<Json<Vec<AttachmentDto>>, (StatusCode, String)> {
+    let rows = list_attachments_db(&state.pool, user.workspace_id, Some(tx_id))
+        .await
+        .map_err(attachment_error)?;
+
+    Ok(Json(rows))
+}
+/// attachments of live transactions, oldest first; `tx_id` narrows them to one transaction
+pub async fn list_attachments_db(
+    pool: &PgPool,
+    workspace_id: Uuid,
+    tx_id: Option<Uuid>,
+) -> Result<Vec<AttachmentDto>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, AttachmentDto>(
+        r#"
+        SELECT a.id, a.tx_id, a.filename, a.content_type, a.size, a.sha256, a.created_at
+        FROM attachments a
+        JOIN transactions t ON t.id = a.tx_id AND t.deleted_at IS NULL
+        WHERE a.workspace_id = $1 AND a.deleted_at IS NULL
+          AND ($2::uuid IS NULL OR a.tx_id = $2)
+        ORDER BY a.created_at, a.id
+        "#,
+    )
+    .bind(workspace_id)
+    .bind(tx_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+/// the file itself, its checksum in `X-Content-SHA256`
+///
+/// bytes that no longer match the stored checksum are refused rather than served
+pub async fn download_attachment_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let row = sqlx::query_as::<_, AttachmentRow>(
+        r#"
+        SELECT filename, content_type, sha256, storage_key
+        FROM attachments
+        WHERE workspace_id = $1 AND id = $2 AND deleted_at IS NULL
+        "#,
+    )
+    .bind(user.workspace_id)
+    .bind(id)
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(attachment_error)?
+    .ok_or_else(|| (StatusCode::NOT_FOUND, "attachment not found".to_string()))?;
+
+    let bytes = state.storage.get(&row.storage_key).await.map_err(storage_error)?;
+    if sha256_hex(&bytes) != row.sha256 {
+        tracing::error!("attachment {id} at {} fails its checksum", row.storage_key);
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "stored attachment is damaged".to_string(),
+        ));
+    }
+    // header values are ASCII, the exact name is in the attachment list
+    let ascii: String = row
+        .filename
+        .chars()
+        .map(|c| if c.is_ascii_graphic() || c == ' ' { c } else { '_' })
+        .filter(|c| *c != '"' && *c != '\\')
+        .collect();
+    Ok((
+        [
+            (header::CONTENT_TYPE, row.content_type),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{ascii}\"")),
+            (HeaderName::from_static("x-content-sha256"), row.sha256),
+        ],
+        bytes,
+    ))
+}
+/// soft delete, the stored file is kept for a restore until the workspace is deleted
+pub async fn delete_attachment_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let mut tx = begin_as(&state.pool, user.user_id).await.map_err(attachment_error)?;
+    let res = sqlx::query(
+        "UPDATE attachments SET deleted_at = now() WHERE workspace_id = $1 AND id = $2 AND deleted_at IS NULL",
+    )
+    .bind(user.workspace_id)
+    .bind(id)
+    .execute(&mut *tx)
+    .await
+    .map_err(attachment_error)?;
+    if res.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "attachment not found".to_string()));
+    }
+    tx.commit().await.map_err(attachment_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+pub async fn restore_attachment_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i64>,
+) -> Result<Json<AttachmentDto>, (StatusCode, String)> {
+    let mut tx = begin_as(&state.pool, user.user_id).await.map_err(attachment_error)?;
+    let dto = sqlx::query_as::<_, AttachmentDto>(
+        r#"
+        UPDATE attachments
+        SET deleted_at = NULL
+        WHERE workspace_id = $1 AND id = $2 AND deleted_at IS NOT NULL
+        RETURNING id, tx_id, filename, content_type, size, sha256, created_at
+        "#,
+    )
+    .bind(user.workspace_id)
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(attachment_error)?
+    .ok_or_else(|| (StatusCode::NOT_FOUND, "deleted attachment not found".to_string()))?;
+    tx.commit().await.map_err(attachment_error)?;
+
+    Ok(Json(dto))
+}
+fn attachment_error(e: sqlx::Error) -> (StatusCode, String) {
+    match &e {
+        sqlx::Error::Database(db_err) if db_err.constraint() == Some("attachments_tx_id_sha256_key") => (
+            StatusCode::CONFLICT,
+            "this file is already attached to the transaction".to_string(),
+        ),
+        sqlx::Error::Database(db_err)
+            if db_err.constraint() == Some("attachments_workspace_id_tx_id_fkey") =>
+        {
+            (StatusCode::NOT_FOUND, "transaction not found".to_string())
+        }
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("db error: {e}"),
+        ),
+    }
+}
+fn storage_error(e: std::io::Error) -> (StatusCode, String) {
+    match e.kind() {
+        std::io::ErrorKind::NotFound => (
+            StatusCode::NOT_FOUND,
+            "attachment file is missing from storage".to_string(),
+        ),
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("storage error: {e}"),
+        ),
+    }
+}
+/// workspaces the caller belongs to, the personal one first
+pub async fn list_workspaces_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+) -> Result<Json<Vec<WorkspaceDto>>, (StatusCode, String)> {
+    let rows = sqlx::query_as::<_, WorkspaceDto>(
+        r#"
+        SELECT w.id, w.name, m.role, w.id = m.user_id AS personal, w.created_at
+        FROM workspace_members m
+        JOIN workspaces w ON w.id = m.workspace_id
+        WHERE m.user_id = $1
+        ORDER BY w.id = m.user_id DESC, w.created_at, w.name
+        "#,
+    )
+    .bind(user.user_id)
+    .fetch_all(&state.pool)
+    .await
+    .map_err(workspace_error)?;
+
+    Ok(Json(rows))
+}
+pub async fn get_workspace_db(
+    pool: &PgPool,
+    user_id: Uuid,
+    workspace_id: Uuid,
+) -> Result<WorkspaceDto, sqlx::Error> {
+    sqlx::query_as::<_, WorkspaceDto>(
+        r#"
+        SELECT w.id, w.name, m.role, w.id = m.user_id AS personal, w.created_at
+        FROM workspace_members m
+        JOIN workspaces w ON w.id = m.workspace_id
+        WHERE m.user_id = $1 AND w.id = $2
+        "#,
+    )
+    .bind(user_id)
+    .bind(workspace_id)
+    .fetch_one(pool)
+    .await
+}
+/// a shared workspace, the caller becomes its owner
+pub async fn create_workspace_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<CreateWorkspaceReq>,
+) -> Result<(StatusCode, Json<WorkspaceDto>), (StatusCode, String)> {
+    let name = req.name.trim();
+    if name.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "name is required".to_string()));
+    }
+    let id = Uuid::new_v4();
+    let mut tx: Transaction<'_, Postgres> = state.pool.begin().await.map_err(workspace_error)?;
+    sqlx::query("INSERT INTO workspaces (id, name) VALUES ($1, $2)")
+        .bind(id)
+        .bind(name)
+        .execute(&mut *tx)
+        .await
+        .map_err(workspace_error)?;
+    sqlx::query("INSERT INTO workspace_members (workspace_id, user_id, role) VALUES ($1, $2, 'owner')")
+        .bind(id)
+        .bind(user.user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(workspace_error)?;
+    tx.commit().await.map_err(workspace_error)?;
+
+    let dto = get_workspace_db(&state.pool, user.user_id, id)
+        .await
+        .map_err(workspace_error)?;
+    Ok((StatusCode::CREATED, Json(dto)))
+}
+/// owner only, the personal workspace can't go; ledger rows cascade, stored files are removed after
+pub async fn delete_workspace_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let mut tx: Transaction<'_, Postgres> = state.pool.begin().await.map_err(workspace_error)?;
+    lock_workspace(&mut tx, id).await?;
+    require_owner(&mut *tx, id, user.user_id).await?;
+    // whoever owns it, deleting a user's personal workspace would lock that user out
+    if personal_workspace(&mut *tx, id).await? {
+        return Err((StatusCode::CONFLICT, "a personal workspace cannot be deleted".to_string()));
+    }
+    let keys = sqlx::query_scalar::<_, String>(
+        "SELECT storage_key FROM attachments WHERE workspace_id = $1",
+    )
+    .bind(id)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(workspace_error)?;
+    // lets the cascade through the lock on reconciled entries, for this transaction only
+    sqlx::query("SELECT set_config('app.deleting_workspace', $1::text, true)")
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(workspace_error)?;
+    sqlx::query("DELETE FROM workspaces WHERE id = $1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(workspace_error)?;
+    tx.commit().await.map_err(workspace_error)?;
+
+    // the rows are gone either way, a file left behind is only wasted space
+    for key in keys {
+        if let Err(e) = state.storage.delete(&key).await {
+            tracing::warn!("could not remove attachment {key}: {e}");
+        }
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+/// members with their email, any member can see them
+pub async fn list_members_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<MemberDto>>, (StatusCode, String)> {
+    auth::member_role(&state.pool, id, user.user_id)
+        .await
+        .map_err(workspace_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "workspace not found".to_string()))?;
+    let rows = list_members_db(&state.pool, id, None)
+        .await
+        .map_err(workspace_error)?;
+
+    Ok(Json(rows))
+}
+pub async fn list_members_db<'e, E>(
+    executor: E,
+    workspace_id: Uuid,
+    user_id: Option<Uuid>,
+) -> Result<Vec<MemberDto>, sqlx::Error>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    sqlx::query_as::<_, MemberDto>(
+        r#"
+        SELECT m.user_id, u.email, m.role, m.created_at
+        FROM workspace_members m
+        JOIN users u ON u.id = m.user_id
+        WHERE m.workspace_id = $1 AND ($2::uuid IS NULL OR m.user_id = $2)
+        ORDER BY m.created_at, u.email
+        "#,
+    )
+    .bind(workspace_id)
+    .bind(user_id)
+    .fetch_all(executor)
+    .await
+}
+/// owner invites a registered user by email
+pub async fn add_member_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<AddMemberReq>,
+) -> Result<(StatusCode, Json<MemberDto>), (StatusCode, String)> {
+    let role = parse_role(&req.role)?;
+    require_owner(&state.pool, id, user.user_id).await?;
+    refuse_personal_owner(&state.pool, id, role).await?;
+    let member_id = sqlx::query_scalar::<_, Uuid>("SELECT id FROM users WHERE email = $1")
+        .bind(req.email.trim())
+        .fetch_optional(&state.pool)
+        .await
+        .map_err(workspace_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "no user with this email".to_string()))?;
+    sqlx::query("INSERT INTO workspace_members (workspace_id, user_id, role) VALUES ($1, $2, $3)")
+        .bind(id)
+        .bind(member_id)
+        .bind(role.as_str())
+        .execute(&state.pool)
+        .await
+        .map_err(workspace_error)?;
+
+    let member = list_members_db(&state.pool, id, Some(member_id))
+        .await
+        .map_err(workspace_error)?
+        .remove(0);
+    Ok((StatusCode::CREATED, Json(member)))
+}
+/// owner changes a role; a workspace always keeps an owner and the personal one keeps its user
+pub async fn set_member_role_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path((id, member_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<SetMemberRoleReq>,
+) -> Result<Json<MemberDto>, (StatusCode, String)> {
+    let role = parse_role(&req.role)?;
+    let mut tx: Transaction<'_, Postgres> = state.pool.begin().await.map_err(workspace_error)?;
+    lock_workspace(&mut tx, id).await?;
+    require_owner(&mut *tx, id, user.user_id).await?;
+    let current = auth::member_role(&mut *tx, id, member_id)
+        .await
+        .map_err(workspace_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "member not found".to_string()))?;
+    if current != auth::Role::Owner {
+        refuse_personal_owner(&mut *tx, id, role).await?;
+    }
+    if current == auth::Role::Owner && role != auth::Role::Owner {
+        keep_an_owner(&mut tx, id, member_id).await?;
+    }
+    sqlx::query("UPDATE workspace_members SET role = $3 WHERE workspace_id = $1 AND user_id = $2")
+        .bind(id)
+        .bind(member_id)
+        .bind(role.as_str())
+        .execute(&mut *tx)
+        .await
+        .map_err(workspace_error)?;
+    let member = list_members_db(&mut *tx, id, Some(member_id))
+        .await
+        .map_err(workspace_error)?
+        .remove(0);
+    tx.commit().await.map_err(workspace_error)?;
+
+    Ok(Json(member))
+}
+/// owner removes a member, or a member leaves
+pub async fn remove_member_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path((id, member_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let mut tx: Transaction<'_, Postgres> = state.pool.begin().await.map_err(workspace_error)?;
+    lock_workspace(&mut tx, id).await?;
+    if member_id != user.user_id {
+        require_owner(&mut *tx, id, user.user_id).await?;
+    }
+    let current = auth::member_role(&mut *tx, id, member_id)
+        .await
+        .map_err(workspace_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "member not found".to_string()))?;
+    if current == auth::Role::Owner {
+        keep_an_owner(&mut tx, id, member_id).await?;
+    }
+    sqlx::query("DELETE FROM workspace_members WHERE workspace_id = $1 AND user_id = $2")
+        .bind(id)
+        .bind(member_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(workspace_error)?;
+    tx.commit().await.map_err(workspace_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+/// serializes member changes of a workspace, 404 if it doesn't exist
+async fn lock_workspace(
+    tx: &mut Transaction<'_, Postgres>,
+    id: Uuid,
+) -> Result<(), (StatusCode, String)> {
+    sqlx::query("SELECT id FROM workspaces WHERE id = $1 FOR UPDATE")
+        .bind(id)
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(workspace_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "workspace not found".to_string()))?;
+    Ok(())
+}
+/// 404 for non members so workspace ids don't leak, 403 for members who aren't owners
+async fn require_owner<'e, E>(
+    executor: E,
+    workspace_id: Uuid,
+    user_id: Uuid,
+) -> Result<(), (StatusCode, String)>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    match auth::member_role(executor, workspace_id, user_id)
+        .await
+        .map_err(workspace_error)?
+    {
+        Some(auth::Role::Owner) => Ok(()),
+        Some(_) => Err((StatusCode::FORBIDDEN, "only owners can manage this workspace".to_string())),
+        None => Err((StatusCode::NOT_FOUND, "workspace not found".to_string())),
+    }
+}
+/// whether the workspace is a user's personal one (same id as the user)
+async fn personal_workspace<'e, E>(executor: E, id: Uuid) -> Result<bool, (StatusCode, String)>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM users WHERE id = $1)")
+        .bind(id)
+        .fetch_one(executor)
+        .await
+        .map_err(workspace_error)
+}
+/// a personal workspace has one owner, its user; others can only be editors or viewers
+async fn refuse_personal_owner<'e, E>(
+    executor: E,
+    workspace_id: Uuid,
+    role: auth::Role,
+) -> Result<(), (StatusCode, String)>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    if role == auth::Role::Owner && personal_workspace(executor, workspace_id).await? {
+        return Err((
+            StatusCode::CONFLICT,
+            "a personal workspace can only have its user as owner".to_string(),
+        ));
+    }
+    Ok(())
+}
+/// refuses to take away the last owner, or the owner of a personal workspace
+async fn keep_an_owner(
+    tx: &mut Transaction<'_, Postgres>,
+    workspace_id: Uuid,
+    owner_id: Uuid,
+) -> Result<(), (StatusCode, String)> {
+    if workspace_id == owner_id {
+        return Err((
+            StatusCode::CONFLICT,
+            "the owner of a personal workspace cannot leave it or change role".to_string(),
+        ));
+    }
+    let owners = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM workspace_members WHERE workspace_id = $1 AND role = 'owner'",
+    )
+    .bind(workspace_id)
+    .fetch_one(&mut **tx)
+    .await
+    .map_err(workspace_error)?;
+    if owners <= 1 {
+        return Err((
+            StatusCode::CONFLICT,
+            "a workspace needs at least one owner".to_string(),
+        ));
+    }
+    Ok(())
+}
+fn parse_role(s: &str) -> Result<auth::Role, (StatusCode, String)> {
+    auth::Role::parse(s.trim()).ok_or_else(|| {
+        (
+            StatusCode::BAD_REQUEST,
+            "role must be owner, editor or viewer".to_string(),
+        )
+    })
+}
+fn workspace_error(e: sqlx::Error) -> (StatusCode, String) {
+    match &e {
+        sqlx::Error::Database(db_err) if db_err.constraint() == Some("workspace_members_pkey") => (
+            StatusCode::CONFLICT,
+            "already a member of this workspace".to_string(),
+        ),
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("db error: {e}"),
+        ),
+    }
+}
+/// personal access tokens of the caller, newest first, revoked ones included
+pub async fn list_tokens_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+) -> Result<Json<Vec<TokenDto>>, (StatusCode, String)> {
+    let rows = sqlx::query_as::<_, TokenDto>(
+        r#"
+        SELECT id, name, prefix, scopes, expires_at, last_used_at, revoked_at, created_at
+        FROM api_tokens
+        WHERE user_id = $1
+        ORDER BY created_at DESC, id DESC
+        "#,
+    )
+    .bind(user.user_id)
+    .fetch_all(&state.pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?;
+
+    Ok(Json(rows))
+}
+/// a new token of the caller; the token itself is only in this response, the server keeps its hash
+pub async fn create_token_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<CreateTokenReq>,
+) -> Result<(StatusCode, Json<CreatedTokenDto>), (StatusCode, String)> {
+    let name = req.name.trim();
+    if name.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "name is required".to_string()));
+    }
+    let mut scopes: Vec<&str> = Vec::new();
+    for scope in &req.scopes {
+        let scope = auth::Scope::parse(scope.trim())
+            .ok_or((StatusCode::BAD_REQUEST, format!("unknown scope {scope:?}")))?;
+        if !scopes.contains(&scope.as_str()) {
+            scopes.push(scope.as_str());
+        }
+    }
+    if scopes.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "at least one scope is required".to_string()));
+    }
+    if req.expires_at.is_some_and(|at| at <= Utc::now()) {
+        return Err((StatusCode::BAD_REQUEST, "expires_at must be in the future".to_string()));
+    }
+    let token = auth::generate_token();
+    // enough of the token to recognize it, far too little to guess the rest
+    let prefix: String = token.chars().take(auth::TOKEN_PREFIX.len() + 8).collect();
+    let info = sqlx::query_as::<_, TokenDto>(
+        r#"
+        INSERT INTO api_tokens (user_id, name, token_hash, prefix, scopes, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, name, prefix, scopes, expires_at, last_used_at, revoked_at, created_at
+        "#,
+    )
+    .bind(user.user_id)
+    .bind(name)
+    .bind(auth::hash_token(&token))
+    .bind(&prefix)
+    .bind(&scopes)
+    .bind(req.expires_at)
+    .fetch_one(&state.pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?;
+
+    Ok((StatusCode::CREATED, Json(CreatedTokenDto { token, info })))
+}
+/// the token stops working at once; revoking it again keeps the first revocation time
+pub async fn revoke_token_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let res = sqlx::query(
+        "UPDATE api_tokens SET revoked_at = COALESCE(revoked_at, now()) WHERE id = $1 AND user_id = $2",
+    )
+    .bind(id)
+    .bind(user.user_id)
+    .execute(&state.pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?;
+    if res.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Token not found".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+// webhooks: a user's subscriptions to events of the current workspace, delivered by
+// webhooks::run_worker from the outbox
+/// webhooks of the caller in the current workspace, newest first, with their waiting and dead deliveries
+pub async fn list_webhooks_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+) -> Result<Json<Vec<WebhookDto>>, (StatusCode, String)> {
+    let rows = sqlx::query_as::<_, WebhookDto>(
+        r#"
+        SELECT w.id, w.url, w.events, w.created_at,
+            COUNT(d.id) FILTER (WHERE d.status = 'pending') AS pending,
+            COUNT(d.id) FILTER (WHERE d.status = 'dead') AS dead
+        FROM webhooks w
+        LEFT JOIN webhook_deliveries d ON d.webhook_id = w.id
+        WHERE w.workspace_id = $1 AND w.user_id = $2
+        GROUP BY w.id
+        ORDER BY w.created_at DESC, w.id DESC
+        "#,
+    )
+    .bind(user.workspace_id)
+    .bind(user.user_id)
+    .fetch_all(&state.pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?;
+
+    Ok(Json(rows))
+}
+/// subscribes a URL to events of the current workspace; the signing secret is only in this response
+pub async fn create_webhook_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<CreateWebhookReq>,
+) -> Result<(StatusCode, Json<CreatedWebhookDto>), (StatusCode, String)> {
+    let url = req.url.trim();
+    webhooks::check_url(url, webhooks::allow_local())
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let mut events: Vec<&str> = Vec::new();
+    for event in &req.events {
+        let event = webhooks::EVENTS
+            .into_iter()
+            .find(|e| *e == event.trim())
+            .ok_or((StatusCode::BAD_REQUEST, format!("unknown event {event:?}")))?;
+        if !events.contains(&event) {
+            events.push(event);
+        }
+    }
+    if events.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "at least one event is required".to_string()));
+    }
+    let secret = webhooks::generate_secret();
+    let info = sqlx::query_as::<_, WebhookDto>(
+        r#"
+        INSERT INTO webhooks (workspace_id, user_id, url, secret, events)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, url, events, created_at, 0::BIGINT AS pending, 0::BIGINT AS dead
+        "#,
+    )
+    .bind(user.workspace_id)
+    .bind(user.user_id)
+    .bind(url)
+    .bind(&secret)
+    .bind(&events)
+    .fetch_one(&state.pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?;
+
+    Ok((StatusCode::CREATED, Json(CreatedWebhookDto { secret, info })))
+}
+/// removes the webhook with its outbox and delivery log
+pub async fn delete_webhook_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let res = sqlx::query("DELETE FROM webhooks WHERE id = $1 AND workspace_id = $2 AND user_id = $3")
+        .bind(id)
+        .bind(user.workspace_id)
+        .bind(user.user_id)
+        .execute(&state.pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?;
+    if res.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Webhook not found".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+/// delivery log of one webhook, newest first, every attempt included; ?status= narrows it
+pub async fn list_webhook_deliveries_handler(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i64>,
+    Query(q): Query<DeliveryQuery>,
+) -> Result<Json<Vec<DeliveryDto>>, (StatusCode, String)> {
+    let db_error = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}"));
+    let exists = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM webhooks WHERE id = $1 AND workspace_id = $2 AND user_id = $3)",
+    )
+    .bind(id)
+    .bind(user.workspace_id)
+    .bind(user.user_id)
+    .fetch_one(&state.pool)
+    .await
+    .map_err(db_error)?;
+    if !exists {
+        return Err((StatusCode::NOT_FOUND, "Webhook not found".to_string()));
+    }
+    if let Some(status) = q.status.as_deref()
+        && !matches!(status, "pending" | "delivered" | "dead")
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "status must be pending, delivered or dead".to_string(),
+        ));
+    }
+    let rows = list_deliveries_db(&state.pool, &user, Some(id), q.status.as_deref(), q.limit)
+        .await
+        .map_err(db_error)?;
+    Ok(Json(rows))
+}
+/// the dead-letter list: deliveries of all the caller's webhooks in the workspace that ran out of attempts
+pub async fn list_dead_deliveries_handler(
     State(state): State<AppState>,
     Extension(user): Extension<AuthUser>,
-    Path(tx_id): Path<Uuid>,
-) -> Result<StatusCode, (StatusCode, String)> {
-    delete_transaction_db(&state.pool, user.user_id, tx_id)
+    Query(q): Query<DeliveryQuery>,
+) -> Result<Json<Vec<DeliveryDto>>, (StatusCode, String)> {
+    let rows = list_deliveries_db(&state.pool, &user, None, Some("dead"), q.limit)
         .await
-        .map_err(|e| match e {
-            sqlx::Error::RowNotFound => (StatusCode::NOT_FOUND, "transaction not found".to_string()),
-            _ => (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")),
-        })?;
-
-    Ok(StatusCode::NO_CONTENT)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}")))?;
+    Ok(Json(rows))
 }
-pub async fn delete_transaction_db(
+pub async fn list_deliveries_db(
     pool: &PgPool,
-    user_id: Uuid,
-    tx_id: Uuid,
-) -> Result<(), sqlx::Error> {
-    let result = sqlx::query!(
+    user: &AuthUser,
+    webhook_id: Option<i64>,
+    status: Option<&str>,
+    limit: Option<i64>,
+) -> Result<Vec<DeliveryDto>, sqlx::Error> {
+    let limit = limit.unwrap_or(50).clamp(1, 500);
+    let mut rows = sqlx::query_as::<_, DeliveryDto>(
         r#"
-        DELETE FROM transactions
-        WHERE user_id = $1 AND id = $2
+        SELECT d.id, d.webhook_id, d.event, d.payload, d.status, d.attempts,
+            CASE WHEN d.status = 'pending' THEN d.next_attempt_at END AS next_attempt_at,
+            d.delivered_at, d.created_at
+        FROM webhook_deliveries d
+        JOIN webhooks w ON w.id = d.webhook_id
+        WHERE w.workspace_id = $1 AND w.user_id = $2
+          AND ($3::BIGINT IS NULL OR d.webhook_id = $3)
+          AND ($4::TEXT IS NULL OR d.status = $4)
+        ORDER BY d.id DESC
+        LIMIT $5
         "#,
-        user_id,
-        tx_id,
     )
-    .execute(pool)
+    .bind(user.workspace_id)
+    .bind(user.user_id)
+    .bind(webhook_id)
+    .bind(status)
+    .bind(limit)
+    .fetch_all(pool)
     .await?;
 
-    if result.rows_affected() == 0 {
-        return Err(sqlx::Error::RowNotFound);
+    let ids: Vec<i64> = rows.iter().map(|d| d.id).collect();
+    let attempts = sqlx::query_as::<_, AttemptDto>(
+        r#"
+        SELECT delivery_id, status_code, error, duration_ms, attempted_at
+        FROM webhook_attempts
+        WHERE delivery_id = ANY($1)
+        ORDER BY id
+        "#,
+    )
+    .bind(&ids)
+    .fetch_all(pool)
+    .await?;
+    let index: std::collections::HashMap<i64, usize> =
+        ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+    for attempt in attempts {
+        if let Some(&i) = index.get(&attempt.delivery_id) {
+            rows[i].log.push(attempt);
+        }
     }
-
-    Ok(())
+    Ok(rows)
 }
-pub async fn delete_entry_handler(
+/// queues a dead (or waiting) delivery again at once with a fresh set of attempts
+pub async fn retry_delivery_handler(
     State(state): State<AppState>,
     Extension(user): Extension<AuthUser>,
-    Path(entry_id): Path<i64>,
+    Path(id): Path<i64>,
 ) -> Result<StatusCode, (StatusCode, String)> {
-    delete_entry_db(&state.pool, user.user_id, entry_id)
-        .await
-        .map_err(|e| match e {
-            sqlx::Error::RowNotFound => {
-                (StatusCode::NOT_FOUND, "entry not found".to_string())
-            }
-            _ => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("db error: {e}"),
-            ),
-        })?;
-
-    Ok(StatusCode::NO_CONTENT)
-}
-pub async fn delete_entry_db(
-    pool: &PgPool,
-    user_id: Uuid,
-    entry_id: i64,
-) -> Result<(), sqlx::Error> {
-    let res = sqlx::query!(
+    let db_error = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, format!("db error: {e}"));
+    let res = sqlx::query(
         r#"
-        DELETE FROM entries
-        WHERE id = $1 AND user_id = $2
+        UPDATE webhook_deliveries d
+        SET status = 'pending', attempts = 0, next_attempt_at = now()
+        FROM webhooks w
+        WHERE w.id = d.webhook_id AND d.id = $1 AND w.workspace_id = $2 AND w.user_id = $3
+          AND d.status <> 'delivered'
         "#,
-        entry_id,
-        user_id,
     )
-    .execute(pool)
-    .await?;
-
+    .bind(id)
+    .bind(user.workspace_id)
+    .bind(user.user_id)
+    .execute(&state.pool)
+    .await
+    .map_err(db_error)?;
     if res.rows_affected() == 0 {
-        return Err(sqlx::Error::RowNotFound);
+        let delivered = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM webhook_deliveries d
+                JOIN webhooks w ON w.id = d.webhook_id
+                WHERE d.id = $1 AND w.workspace_id = $2 AND w.user_id = $3
+            )
+            "#,
+        )
+        .bind(id)
+        .bind(user.workspace_id)
+        .bind(user.user_id)
+        .fetch_one(&state.pool)
+        .await
+        .map_err(db_error)?;
+        return Err(if delivered {
+            (StatusCode::CONFLICT, "Delivery already delivered".to_string())
+        } else {
+            (StatusCode::NOT_FOUND, "Delivery not found".to_string())
+        });
     }
-
-    Ok(())
+    Ok(StatusCode::ACCEPTED)
 }
 
 #[derive(Deserialize)]
@@ -617,12 +3760,13 @@ pub struct CreateEntryReq {
 #[derive(Debug, Serialize, sqlx::FromRow)]
 pub struct EntriesRow {
     pub id: i64,              // BIGSERIAL -> i64
-    pub user_id: Uuid,        // UUID
+    pub workspace_id: Uuid,        // UUID
     pub tx_id: Uuid, // UUID
     pub account_id: i64,      // BIGSERIAL -> i64
     pub category_id: Option<i64>,
     pub amount: Decimal,   // NUMERIC(14,2)
     pub note: Option<String>,
+    pub status: String,       // uncleared/cleared/reconciled
 }
 #[derive(Debug, Serialize, Clone)]
 pub struct EntriesDto {
@@ -632,6 +3776,7 @@ pub struct EntriesDto {
     pub category_id: Option<i64>,
     pub amount: Decimal,
     pub note: Option<String>,
+    pub status: String,
 }
 
 impl From<EntriesRow> for EntriesDto {
@@ -643,6 +3788,7 @@ impl From<EntriesRow> for EntriesDto {
             category_id: t.category_id,
             amount: t.amount,
             note: t.note,
+            status: t.status,
         }
     }
 }
@@ -650,7 +3796,7 @@ impl From<EntriesRow> for EntriesDto {
 #[derive(Debug, Serialize, sqlx::FromRow)]
 pub struct TransactionsRow {
     pub id: Uuid,              
-    pub user_id: Uuid,        // UUID
+    pub workspace_id: Uuid,        // UUID
     pub occurred_at: NaiveDate, // TIMESTAMPTZ
     pub payee: Option<String>,
     pub memo: Option<String>,
@@ -679,15 +3825,17 @@ pub struct TransactionsDto {
 #[derive(Debug, Serialize, sqlx::FromRow)]
 pub struct CategoriesRow {
     pub id: i64,              // BIGSERIAL -> i64
-    pub user_id: Uuid,        // UUID
+    pub workspace_id: Uuid,        // UUID
     pub parent_id: Option<i64>,
     pub name: String,         // TEXT
+    pub tax_line: Option<String>,
 
 }
 #[derive(serde::Deserialize)]
 pub struct CreateCategoriesReq {
     pub parent_id: Option<i64>,
     pub name: String,         // TEXT
+    pub tax_line: Option<String>,
 }
 
 #[derive(Debug, serde::Serialize, sqlx::FromRow,Clone)]
@@ -695,6 +3843,7 @@ pub struct CategoriesDto {
     pub id: i64,
     pub parent_id: Option<i64>,
     pub name: String,         // TEXT
+    pub tax_line: Option<String>, // medical, charitable, ... or none
 }
 
 impl From<CategoriesRow> for CategoriesDto {
@@ -703,9 +3852,14 @@ impl From<CategoriesRow> for CategoriesDto {
             id: r.id,
             name: r.name,
             parent_id: r.parent_id,
+            tax_line: r.tax_line,
         }
     }
 }
+#[derive(Debug, Deserialize)]
+pub struct TaxLineReq {
+    pub tax_line: Option<String>,
+}
 
 #[derive(Debug, Deserialize)]
 pub struct AccountQuery {
@@ -713,7 +3867,7 @@ pub struct AccountQuery {
     pub offset: Option<i64>,
 
     #[serde(rename = "type")]
-    pub account_type: Option<String>, // checking/cash/credit/other
+    pub account_type: Option<String>, // checking/cash/credit/loan/investment/other
     pub currency: Option<String>,      // CAD
 
     pub query: Option<String>,         // search string
@@ -726,20 +3880,86 @@ pub struct AccountQuery {
 #[derive(Debug, Serialize, sqlx::FromRow)]
 pub struct AccountRow {
     pub id: i64,              // BIGSERIAL -> i64
-    pub user_id: Uuid,        // UUID
+    pub workspace_id: Uuid,        // UUID
     pub name: String,         // TEXT
     pub account_type: String, // TEXT
     pub currency: String,     // CHAR(3) 
     pub opening_balance: Decimal, // NUMERIC(14,2)
     pub created_at: DateTime<Utc>, // TIMESTAMPTZ
+    pub credit_limit: Option<Decimal>, // credit accounts only
+    pub statement_day: Option<i16>,
+    pub due_day: Option<i16>,
+    pub apr: Option<Decimal>,
+    pub loan_principal: Option<Decimal>, // loan accounts only
+    pub loan_rate: Option<Decimal>,
+    pub loan_term_months: Option<i32>,
+    pub loan_frequency: Option<String>,
+    pub loan_first_payment: Option<NaiveDate>,
+    pub cost_basis: Option<String>, // investment accounts only
+    pub contact_email: Option<String>, // contacts only
 }
 
 #[derive(serde::Deserialize)]
 pub struct CreateAccountReq {
     pub name: String,
-    pub account_type: String,        // "checking" | "credit" | "cash"...
+    pub account_type: String,        // "checking" | "credit" | "cash" | "loan" | "investment"...
     pub currency: Option<String>,    
     pub opening_balance: Option<Decimal>, 
+    #[serde(flatten)]
+    pub credit: CreditTermsReq,      // credit accounts only
+    #[serde(flatten)]
+    pub loan: LoanTermsReq,          // loan accounts only
+    pub cost_basis: Option<String>,  // investment accounts, "fifo" if none
+    pub contact_email: Option<String>, // contacts only
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct CreditTermsReq {
+    pub credit_limit: Option<Decimal>,
+    pub statement_day: Option<i16>, // 1-31, the last day in shorter months
+    pub due_day: Option<i16>,       // 1-31, the first such day after the statement closed
+    pub apr: Option<Decimal>,       // percent per year
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LoanTermsReq {
+    pub loan_principal: Option<Decimal>,
+    pub loan_rate: Option<Decimal>,           // percent per year
+    pub loan_term_months: Option<i32>,        // 1-600
+    pub loan_frequency: Option<String>,       // "monthly" | "biweekly" | "weekly"
+    pub loan_first_payment: Option<NaiveDate>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoanPaymentReq {
+    pub from_account_id: i64,                 // account the payment leaves
+    pub amount: Decimal,                      // principal + interest
+    pub occurred_at: NaiveDate,
+    pub interest_category_id: Option<i64>,
+    pub payee: Option<String>,                // the loan account's name if none
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SettlementReq {
+    pub account_id: i64,                      // the real account the money moves through
+    pub amount: Option<Decimal>,              // > 0 received from the contact, the full balance if none
+    pub occurred_at: NaiveDate,
+    pub payee: Option<String>,                // the contact's name if none
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct ContactBalanceRow {
+    pub name: String,
+    pub account_type: String,
+    pub balance: Decimal,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct LoanBalanceRow {
+    pub name: String,
+    pub loan_rate: Option<Decimal>,
+    pub loan_frequency: Option<String>,
+    pub balance: Decimal,
 }
 
 #[derive(Debug, serde::Serialize, sqlx::FromRow,Clone)]
@@ -750,6 +3970,17 @@ pub struct AccountDto {
     pub currency: String,
     pub opening_balance: Decimal,
     pub created_at: DateTime<Utc>,
+    pub credit_limit: Option<Decimal>,
+    pub statement_day: Option<i16>,
+    pub due_day: Option<i16>,
+    pub apr: Option<Decimal>,
+    pub loan_principal: Option<Decimal>,
+    pub loan_rate: Option<Decimal>,
+    pub loan_term_months: Option<i32>,
+    pub loan_frequency: Option<String>,
+    pub loan_first_payment: Option<NaiveDate>,
+    pub cost_basis: Option<String>,
+    pub contact_email: Option<String>,
 }
 
 impl From<AccountRow> for AccountDto {
@@ -761,6 +3992,17 @@ impl From<AccountRow> for AccountDto {
             currency: r.currency,
             opening_balance: r.opening_balance,
             created_at: r.created_at,
+            credit_limit: r.credit_limit,
+            statement_day: r.statement_day,
+            due_day: r.due_day,
+            apr: r.apr,
+            loan_principal: r.loan_principal,
+            loan_rate: r.loan_rate,
+            loan_term_months: r.loan_term_months,
+            loan_frequency: r.loan_frequency,
+            loan_first_payment: r.loan_first_payment,
+            cost_basis: r.cost_basis,
+            contact_email: r.contact_email,
         }
     }
 }
@@ -776,8 +4018,437 @@ pub struct UserDto {
 #[derive(Debug, Clone, Serialize)]
 pub struct CloudLedger {
     pub user: UserDto,
+    pub workspace: WorkspaceDto, // the one the snapshot is of
     pub accounts: Vec<AccountDto>,
     pub categories: Vec<CategoriesDto>,
     pub transactions: Vec<TransactionsDto>,
     pub entries: Vec<EntriesDto>, 
-}
\ No newline at end of file
+    pub securities: Vec<SecurityDto>,
+    pub trades: Vec<TradeDto>,
+    pub prices: Vec<PriceDto>,
+    pub attachments: Vec<AttachmentDto>,
+}
+#[derive(Debug, Deserialize)]
+pub struct AuditQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+
+    pub table: Option<String>,   // accounts/categories/transactions/entries
+    pub row_id: Option<String>,
+    pub tx_id: Option<Uuid>,     // all changes of one transaction and its entries
+    pub action: Option<String>,  // insert/update/delete/soft_delete/restore
+    pub from: Option<NaiveDate>, // inclusive
+    pub to: Option<NaiveDate>,   // inclusive
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, Clone)]
+pub struct AuditDto {
+    pub id: i64,
+    pub table_name: String,
+    pub row_id: String,
+    pub tx_id: Option<Uuid>,
+    pub action: String,
+    pub before: Option<serde_json::Value>, // JSONB, NULL on insert
+    pub after: Option<serde_json::Value>,  // JSONB, NULL on delete
+    pub changed_at: DateTime<Utc>,
+    pub changed_by: Option<Uuid>, // NULL for changes made outside a request
+    pub changed_by_email: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EntryStatusReq {
+    pub status: String, // uncleared/cleared
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateReconciliationReq {
+    pub account_id: i64,
+    pub statement_date: NaiveDate,
+    pub statement_balance: Decimal,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReconciliationQuery {
+    pub account_id: Option<i64>,
+    pub status: Option<String>, // open/finished
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, Clone)]
+pub struct ReconciliationDto {
+    pub id: i64,
+    pub account_id: i64,
+    pub statement_date: NaiveDate,
+    pub statement_balance: Decimal,
+    pub cleared_balance: Option<Decimal>, // NULL while open
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub entry_count: i64, // entries locked by this session
+}
+
+pub enum FinishReconError {
+    NotFound,
+    Unbalanced {
+        statement_balance: Decimal,
+        cleared_balance: Decimal,
+    },
+    Db(sqlx::Error),
+}
+
+impl From<sqlx::Error> for FinishReconError {
+    fn from(e: sqlx::Error) -> Self {
+        match e {
+            sqlx::Error::RowNotFound => FinishReconError::NotFound,
+            e => FinishReconError::Db(e),
+        }
+    }
+}
+
+/// why a soft delete of an account or category was refused
+pub enum DeleteError {
+    NotFound,
+    InUse(&'static str), // what still uses the row, live rows only
+    Db(sqlx::Error),
+}
+
+impl From<sqlx::Error> for DeleteError {
+    fn from(e: sqlx::Error) -> Self {
+        match e {
+            sqlx::Error::RowNotFound => DeleteError::NotFound,
+            e => DeleteError::Db(e),
+        }
+    }
+}
+
+pub enum TradeError {
+    Oversold {
+        symbol: String,
+        date: NaiveDate,
+        quantity: Decimal,
+        held: Decimal,
+    },
+    Db(sqlx::Error),
+}
+
+impl From<sqlx::Error> for TradeError {
+    fn from(e: sqlx::Error) -> Self {
+        TradeError::Db(e)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReportQuery {
+    pub from: NaiveDate, // inclusive
+    pub to: NaiveDate,   // inclusive
+    pub account_id: Option<i64>,
+    pub category_id: Option<i64>,
+    pub limit: Option<i64>,      // top lists, default 10, max 100
+    pub rank: Option<String>,    // top lists: income/outcome/net (default net)
+    pub period: Option<String>,  // trends: week/month/quarter/year (default month)
+}
+
+impl ReportQuery {
+    // swapped if given the wrong way round, like the client's DateRange
+    pub fn range(&self) -> (NaiveDate, NaiveDate) {
+        if self.from <= self.to {
+            (self.from, self.to)
+        } else {
+            (self.to, self.from)
+        }
+    }
+    // largest first, spending ranks by size so the most negative outcome comes first
+    pub fn rank_order(&self) -> Option<&'static str> {
+        match self.rank.as_deref().unwrap_or("net") {
+            "income" => Some("income DESC"),
+            "outcome" => Some("outcome ASC"),
+            "net" => Some("net DESC"),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ReportGroup {
+    Category,
+    Account,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BalanceQuery {
+    pub date: Option<NaiveDate>, // inclusive, default today
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, Clone)]
+pub struct ReportTotalsDto {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub income: Decimal,
+    pub outcome: Decimal, // negative
+    pub net: Decimal,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, Clone)]
+pub struct ReportRankDto {
+    pub id: i64, // category or account id
+    pub name: String,
+    pub income: Decimal,
+    pub outcome: Decimal,
+    pub net: Decimal,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, Clone)]
+pub struct ReportBalanceDto {
+    pub account_id: i64,
+    pub name: String,
+    pub account_type: String,
+    pub currency: String,
+    pub balance: Decimal,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, Clone)]
+pub struct ReportContactDto {
+    pub account_id: i64,
+    pub name: String,
+    pub contact_email: Option<String>,
+    pub currency: String,
+    pub balance: Decimal,    // > 0 they owe you, < 0 you owe them
+    pub receivable: Decimal,
+    pub payable: Decimal,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateGoalReq {
+    pub name: String,
+    pub target_amount: Decimal,
+    pub target_date: Option<NaiveDate>,
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub account_ids: Vec<i64>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, Clone)]
+pub struct GoalDto {
+    pub id: i64,
+    pub name: String,
+    pub target_amount: Decimal,
+    pub target_date: Option<NaiveDate>,
+    pub tag: Option<String>,
+    pub account_ids: Vec<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CostBasisReq {
+    pub cost_basis: String, // "fifo" | "average"
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateSecurityReq {
+    pub symbol: String, // upper cased
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, Clone)]
+pub struct SecurityDto {
+    pub id: i64,
+    pub symbol: String,
+    pub name: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PriceReq {
+    pub symbol: String,
+    pub date: NaiveDate,
+    pub price: Decimal,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PriceImportDto {
+    pub imported: i64,
+    pub unknown: Vec<String>, // symbols without a security, their rows are skipped
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, Clone)]
+pub struct PriceDto {
+    pub security_id: i64,
+    pub price_date: NaiveDate,
+    pub price: Decimal,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTradeReq {
+    pub symbol: String,
+    pub kind: String,                 // "buy" | "sell" | "dividend" | "split"
+    pub trade_date: NaiveDate,
+    pub quantity: Option<Decimal>,    // units for buy and sell, new units per old unit for split
+    pub price: Option<Decimal>,       // per unit, buy and sell
+    pub fees: Option<Decimal>,        // buy and sell
+    pub amount: Option<Decimal>,      // dividend received
+    pub category_id: Option<i64>,     // dividend only
+    pub payee: Option<String>,        // the symbol if none
+}
+
+/// what goes into the trades table, the cash side is already worked out
+pub struct NewTrade<'a> {
+    pub account_id: i64,
+    pub symbol: &'a str,
+    pub kind: &'a str,
+    pub quantity: Decimal,
+    pub price: Decimal,
+    pub fees: Decimal,
+    pub amount: Decimal,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, Clone)]
+pub struct TradeDto {
+    pub id: i64,
+    pub account_id: i64,
+    pub security_id: i64,
+    pub symbol: String,
+    pub tx_id: Uuid,
+    pub kind: String,
+    pub trade_date: NaiveDate,
+    pub quantity: Decimal,
+    pub price: Decimal,
+    pub fees: Decimal,
+    pub amount: Decimal,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UploadAttachmentQuery {
+    pub filename: String,
+    pub sha256: Option<String>, // hex, checked against the received bytes
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, Clone)]
+pub struct AttachmentDto {
+    pub id: i64,
+    pub tx_id: Uuid,
+    pub filename: String,
+    pub content_type: String,
+    pub size: i64,
+    pub sha256: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct AttachmentRow {
+    pub filename: String,
+    pub content_type: String,
+    pub sha256: String,
+    pub storage_key: String,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, Clone)]
+pub struct WorkspaceDto {
+    pub id: Uuid,
+    pub name: String,
+    pub role: String,    // owner/editor/viewer, of the caller
+    pub personal: bool,  // the caller's own, id = user id
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateWorkspaceReq {
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, Clone)]
+pub struct MemberDto {
+    pub user_id: Uuid,
+    pub email: String,
+    pub role: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AddMemberReq {
+    pub email: String,
+    pub role: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetMemberRoleReq {
+    pub role: String,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, Clone)]
+pub struct TokenDto {
+    pub id: i64,
+    pub name: String,
+    pub prefix: String,      // first characters of the token
+    pub scopes: Vec<String>, // read:ledger, write:transactions, admin
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreatedTokenDto {
+    pub token: String, // shown this once
+    #[serde(flatten)]
+    pub info: TokenDto,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTokenReq {
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>, // never expires when absent
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, Clone)]
+pub struct WebhookDto {
+    pub id: i64,
+    pub url: String,
+    pub events: Vec<String>, // see webhooks::EVENTS
+    pub created_at: DateTime<Utc>,
+    pub pending: i64, // deliveries waiting for an attempt
+    pub dead: i64,    // deliveries out of attempts
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreatedWebhookDto {
+    pub secret: String, // shown this once, signs every delivery
+    #[serde(flatten)]
+    pub info: WebhookDto,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateWebhookReq {
+    pub url: String,
+    pub events: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeliveryQuery {
+    pub status: Option<String>, // pending | delivered | dead
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct DeliveryDto {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub event: String,
+    pub payload: serde_json::Value, // the body that is posted
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: Option<DateTime<Utc>>, // while pending
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    #[sqlx(skip)]
+    pub log: Vec<AttemptDto>, // every attempt, oldest first
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct AttemptDto {
+    #[serde(skip)]
+    pub delivery_id: i64,
+    pub status_code: Option<i32>, // none when no response came back
+    pub error: Option<String>,
+    pub duration_ms: i32,
+    pub attempted_at: DateTime<Utc>,
+}
@@ -0,0 +1,78 @@
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+
+// where attachment bytes live; the database only keeps their metadata and storage key, so
+// another backend (object storage...) only has to implement `Storage`
+
+pub type StorageFuture<'a, T> = Pin<Box<dyn Future<Output = io::Result<T>> + Send + 'a>>;
+
+/// a flat key -> bytes store, keys are made by the server and look like `<workspace_id>/<uuid>`
+pub trait Storage: Send + Sync {
+    /// writes `bytes` under `key`, replacing what was there
+    fn put<'a>(&'a self, key: &'a str, bytes: &'a [u8]) -> StorageFuture<'a, ()>;
+    /// bytes stored under `key`, `ErrorKind::NotFound` if there are none
+    fn get<'a>(&'a self, key: &'a str) -> StorageFuture<'a, Vec<u8>>;
+    /// removes `key`, a key that is already gone is not an error
+    fn delete<'a>(&'a self, key: &'a str) -> StorageFuture<'a, ()>;
+}
+
+/// files under a root directory, one per key
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// from ATTACHMENTS_DIR, `./attachments` if unset
+    pub fn from_env() -> Self {
+        Self::new(std::env::var("ATTACHMENTS_DIR").unwrap_or_else(|_| "attachments".to_string()))
+    }
+
+    /// path of a key, refusing anything that could leave the root
+    fn path_of(&self, key: &str) -> io::Result<PathBuf> {
+        let safe = !key.is_empty()
+            && key.split('/').all(|part| {
+                !part.is_empty() && part != "." && part != ".." && !part.contains('\\')
+            });
+        if !safe {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("bad storage key {key:?}"),
+            ));
+        }
+        Ok(self.root.join(Path::new(key)))
+    }
+}
+
+impl Storage for LocalStorage {
+    fn put<'a>(&'a self, key: &'a str, bytes: &'a [u8]) -> StorageFuture<'a, ()> {
+        Box::pin(async move {
+            let path = self.path_of(key)?;
+            if let Some(dir) = path.parent() {
+                tokio::fs::create_dir_all(dir).await?;
+            }
+            // written aside then renamed, a crash never leaves half a file under the key
+            let tmp = path.with_extension("part");
+            tokio::fs::write(&tmp, bytes).await?;
+            tokio::fs::rename(&tmp, &path).await
+        })
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> StorageFuture<'a, Vec<u8>> {
+        Box::pin(async move { tokio::fs::read(self.path_of(key)?).await })
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> StorageFuture<'a, ()> {
+        Box::pin(async move {
+            match tokio::fs::remove_file(self.path_of(key)?).await {
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+                other => other,
+            }
+        })
+    }
+}
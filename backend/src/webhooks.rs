@@ -0,0 +1,523 @@
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use rand_core::{OsRng, RngCore};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Url;
+use serde::Serialize;
+use sha2::Sha256;
+use sqlx::{FromRow, PgConnection, PgPool};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::task::JoinSet;
+use uuid::Uuid;
+
+// webhooks: an event is queued in the outbox (webhook_deliveries) by the database transaction
+// that made the change, so it is sent if and only if the change is committed; `run_worker` posts
+// what is due, logs every attempt and retries with backoff until the delivery is dead
+
+/// what a webhook can subscribe to, the same list as the check in 018_webhook_events.sql
+pub const EVENTS: [&str; 5] = [
+    "transaction.created",
+    "transaction.updated",
+    "transaction.deleted",
+    "account.updated",
+    "reconcile.completed",
+];
+/// failed attempts after which a delivery is dead
+pub const MAX_ATTEMPTS: i32 = 8;
+
+// headers of every delivery
+pub const EVENT_HEADER: &str = "x-webhook-event";
+pub const DELIVERY_HEADER: &str = "x-webhook-delivery";
+pub const TIMESTAMP_HEADER: &str = "x-webhook-timestamp";
+pub const SIGNATURE_HEADER: &str = "x-webhook-signature";
+
+/// deliveries claimed per round
+const BATCH: i64 = 20;
+/// sleep when nothing is due
+const POLL: std::time::Duration = std::time::Duration::from_secs(2);
+/// a receiver has this long to answer
+const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+/// a claimed delivery is due again after this many seconds, if the worker died while posting it
+const LEASE_SECS: f64 = 60.0;
+/// set to 1 to allow plain http and receivers on this machine or a private network, for local
+/// testing only
+pub const ALLOW_LOCAL_ENV: &str = "WEBHOOKS_ALLOW_LOCAL";
+
+pub fn allow_local() -> bool {
+    std::env::var(ALLOW_LOCAL_ENV).is_ok_and(|v| v == "1")
+}
+/// whether `ip` is a public unicast address; a subscription must not make the server post into
+/// its own network (loopback, private, link-local, shared and reserved ranges)
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, c, _] = v4.octets();
+            !(v4.is_unspecified()
+                || v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+                || v4.is_multicast()
+                || a == 0                              // this network
+                || (a == 100 && (64..128).contains(&b)) // carrier-grade NAT
+                || (a == 192 && b == 0 && c == 0)      // protocol assignments
+                || (a == 198 && (18..20).contains(&b)) // benchmarking
+                || a >= 240)                           // reserved
+        }
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => is_public(IpAddr::V4(v4)),
+            None => {
+                !(v6.is_unspecified()
+                    || v6.is_loopback()
+                    || v6.is_multicast()
+                    || v6.is_unique_local()
+                    || v6.is_unicast_link_local()
+                    || v6.segments()[..2] == [0x2001, 0x0db8]) // documentation
+            }
+        },
+    }
+}
+/// a receiver URL as it is accepted: https, with an IP host only if it is public; host names are
+/// checked when the worker resolves them
+pub fn check_url(url: &str, allow_local: bool) -> Result<Url, String> {
+    let url = Url::parse(url).map_err(|_| "url must be an https URL".to_string())?;
+    match url.scheme() {
+        "https" => {}
+        "http" if allow_local => {}
+        _ => return Err("url must be an https URL".to_string()),
+    }
+    let Some(host) = url.host_str() else {
+        return Err("url must be an https URL".to_string());
+    };
+    // IPv6 hosts come in brackets
+    match host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>().ok() {
+        Some(ip) if !allow_local && !is_public(ip) => {
+            Err(format!("{ip} is not a public address"))
+        }
+        _ => Ok(url),
+    }
+}
+/// DNS of the worker's client, drops every address that isn't public; the post connects to
+/// the addresses checked here, so a name can't be pointed elsewhere between check and connect
+struct PublicResolver;
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .filter(|a| is_public(a.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{host} has no public address").into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// a new signing secret, shown once when the webhook is created
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    format!("whsec_{}", hex::encode(bytes))
+}
+/// hex HMAC-SHA256 of `<timestamp>.<body>` keyed with the secret, what receivers recompute
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac takes any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+/// wait after the `attempts`-th failure: 30 s, doubling up to 32 min
+pub fn backoff(attempts: i32) -> Duration {
+    Duration::seconds(30 << (attempts - 1).clamp(0, 6))
+}
+/// queues `event` for every webhook of the workspace that subscribed to it, inside the caller's
+/// transaction; webhooks of users who left the workspace or are now viewers get nothing
+pub async fn enqueue(
+    conn: &mut PgConnection,
+    workspace_id: Uuid,
+    event: &str,
+    data: &impl Serialize,
+) -> Result<(), sqlx::Error> {
+    let data = serde_json::to_value(data).map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+    sqlx::query(
+        r#"
+        INSERT INTO webhook_deliveries (webhook_id, event, payload)
+        SELECT w.id, $2, jsonb_build_object(
+            'event', $2::text,
+            'workspace_id', $1::uuid,
+            'created_at', now(),
+            'data', $3::jsonb
+        )
+        FROM webhooks w
+        JOIN workspace_members m ON m.workspace_id = w.workspace_id AND m.user_id = w.user_id
+        WHERE w.workspace_id = $1 AND $2 = ANY(w.events) AND m.role <> 'viewer'
+        "#,
+    )
+    .bind(workspace_id)
+    .bind(event)
+    .bind(data)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+#[derive(FromRow)]
+struct DueDelivery {
+    id: i64,
+    event: String,
+    payload: serde_json::Value,
+    attempts: i32,
+    url: String,
+    secret: String,
+}
+
+/// posts due deliveries until the server stops, several workers can share the outbox
+pub async fn run_worker(pool: PgPool) {
+    let allow_local = allow_local();
+    let mut client = reqwest::Client::builder()
+        .timeout(TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none());
+    if allow_local {
+        tracing::warn!("{ALLOW_LOCAL_ENV} is set, webhooks may post to local and private addresses");
+    } else {
+        client = client.dns_resolver(Arc::new(PublicResolver));
+    }
+    let client = client.build().expect("webhook http client");
+    loop {
+        match deliver_due(&pool, &client, allow_local).await {
+            // a full batch, more may be waiting
+            Ok(n) if n as i64 == BATCH => continue,
+            Ok(_) => {}
+            Err(e) => tracing::warn!("webhook worker: {e}"),
+        }
+        tokio::time::sleep(POLL).await;
+    }
+}
+/// claims a batch of due deliveries and attempts them all, returns how many were claimed
+async fn deliver_due(
+    pool: &PgPool,
+    client: &reqwest::Client,
+    allow_local: bool,
+) -> Result<usize, sqlx::Error> {
+    // claiming pushes next_attempt_at past the lease, other workers skip them meanwhile
+    let due = sqlx::query_as::<_, DueDelivery>(
+        r#"
+        UPDATE webhook_deliveries d
+        SET next_attempt_at = now() + make_interval(secs => $2)
+        FROM webhooks w
+        WHERE w.id = d.webhook_id AND d.id IN (
+            SELECT id FROM webhook_deliveries
+            WHERE status = 'pending' AND next_attempt_at <= now()
+            ORDER BY next_attempt_at, id
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING d.id, d.event, d.payload, d.attempts, w.url, w.secret
+        "#,
+    )
+    .bind(BATCH)
+    .bind(LEASE_SECS)
+    .fetch_all(pool)
+    .await?;
+
+    let claimed = due.len();
+    let mut attempts = JoinSet::new();
+    for delivery in due {
+        let (pool, client) = (pool.clone(), client.clone());
+        attempts.spawn(async move { attempt(&pool, &client, allow_local, delivery).await });
+    }
+    while let Some(res) = attempts.join_next().await {
+        match res {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => tracing::warn!("webhook delivery not recorded: {e}"),
+            Err(e) => tracing::warn!("webhook delivery task failed: {e}"),
+        }
+    }
+    Ok(claimed)
+}
+/// what came of one post
+struct Outcome {
+    status_code: Option<i32>, // none when no response came back
+    error: Option<String>,    // none on a 2xx
+    duration_ms: i32,
+}
+/// one signed post of `body`, a 2xx is a success
+async fn post(
+    client: &reqwest::Client,
+    url: &str,
+    secret: &str,
+    event: &str,
+    delivery_id: i64,
+    body: String,
+) -> Outcome {
+    let timestamp = Utc::now().timestamp();
+    let signature = sign(secret, timestamp, body.as_bytes());
+    let started = Instant::now();
+    let res = client
+        .post(url)
+        .header(CONTENT_TYPE, "application/json")
+        .header(EVENT_HEADER, event)
+        .header(DELIVERY_HEADER, delivery_id.to_string())
+        .header(TIMESTAMP_HEADER, timestamp.to_string())
+        .header(SIGNATURE_HEADER, format!("sha256={signature}"))
+        .body(body)
+        .send()
+        .await;
+    let duration_ms = started.elapsed().as_millis().min(i32::MAX as u128) as i32;
+    let (status_code, error) = match res {
+        Ok(r) if r.status().is_success() => (Some(r.status().as_u16() as i32), None),
+        Ok(r) => (Some(r.status().as_u16() as i32), Some(format!("HTTP {}", r.status()))),
+        Err(e) => (None, Some(error_chain(&e))),
+    };
+    Outcome { status_code, error, duration_ms }
+}
+/// status of a delivery after its `attempts`-th attempt
+fn next_status(attempts: i32, failed: bool) -> &'static str {
+    match failed {
+        false => "delivered",
+        true if attempts >= MAX_ATTEMPTS => "dead",
+        true => "pending",
+    }
+}
+/// one post of a delivery, logged; a 2xx delivers it, anything else schedules a retry or kills it
+async fn attempt(
+    pool: &PgPool,
+    client: &reqwest::Client,
+    allow_local: bool,
+    d: DueDelivery,
+) -> Result<(), sqlx::Error> {
+    // webhooks created before the checks, or with the dev switch on, may point anywhere
+    let outcome = match check_url(&d.url, allow_local) {
+        Ok(_) => post(client, &d.url, &d.secret, &d.event, d.id, d.payload.to_string()).await,
+        Err(e) => Outcome { status_code: None, error: Some(format!("refused: {e}")), duration_ms: 0 },
+    };
+
+    let attempts = d.attempts + 1;
+    let status = next_status(attempts, outcome.error.is_some());
+    if status == "dead" {
+        tracing::warn!("webhook delivery {} is dead after {attempts} attempts", d.id);
+    }
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "INSERT INTO webhook_attempts (delivery_id, status_code, error, duration_ms) VALUES ($1, $2, $3, $4)",
+    )
+    .bind(d.id)
+    .bind(outcome.status_code)
+    .bind(&outcome.error)
+    .bind(outcome.duration_ms)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query(
+        r#"
+        UPDATE webhook_deliveries
+        SET status = $2, attempts = $3, next_attempt_at = $4,
+            delivered_at = CASE WHEN $2 = 'delivered' THEN now() END
+        WHERE id = $1
+        "#,
+    )
+    .bind(d.id)
+    .bind(status)
+    .bind(attempts)
+    .bind(Utc::now() + backoff(attempts))
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await
+}
+/// reqwest's message and its causes, "error sending request" alone says little
+fn error_chain(e: &dyn std::error::Error) -> String {
+    let mut msg = e.to_string();
+    let mut source = e.source();
+    while let Some(cause) = source {
+        msg.push_str(": ");
+        msg.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    msg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, extract::State, http::{HeaderMap, StatusCode}, routing::post as post_route};
+    use std::sync::{Arc, Mutex};
+
+    /// what the receiver saw of each request
+    #[derive(Clone, Debug)]
+    struct Received {
+        headers: HeaderMap,
+        body: String,
+    }
+    type Log = Arc<Mutex<Vec<Received>>>;
+
+    /// a local HTTP receiver on a free port answering `status`, with the log of what it got
+    async fn receiver(status: StatusCode) -> (String, Log) {
+        let log: Log = Arc::default();
+        let app = Router::new()
+            .route(
+                "/hook",
+                post_route(move |State(log): State<Log>, headers: HeaderMap, body: String| async move {
+                    log.lock().unwrap().push(Received { headers, body });
+                    status
+                }),
+            )
+            .with_state(log.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{addr}/hook"), log)
+    }
+
+    fn header<'a>(r: &'a Received, name: &str) -> &'a str {
+        r.headers.get(name).and_then(|v| v.to_str().ok()).unwrap_or_default()
+    }
+
+    #[test]
+    fn signs_timestamp_dot_body() {
+        // computed independently: HMAC-SHA256(key, "1700000000.<body>")
+        let body = br#"{"event":"transaction.created"}"#;
+        assert_eq!(
+            sign("whsec_test", 1_700_000_000, body),
+            "7c52508c485a0671af6c5cb3de2f9b3cf69c4e24d0616980f7ad617476dbb860"
+        );
+        assert_ne!(sign("whsec_other", 1_700_000_000, body), sign("whsec_test", 1_700_000_000, body));
+        assert_ne!(sign("whsec_test", 1_700_000_001, body), sign("whsec_test", 1_700_000_000, body));
+    }
+
+    #[test]
+    fn secrets_are_random_and_prefixed() {
+        let (a, b) = (generate_secret(), generate_secret());
+        assert!(a.starts_with("whsec_") && a.len() == 6 + 64);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn backoff_doubles_from_30s_and_stops_growing() {
+        for attempts in 1..=7 {
+            assert_eq!(backoff(attempts), Duration::seconds(30 << (attempts - 1)));
+        }
+        assert_eq!(backoff(1), Duration::seconds(30));
+        assert_eq!(backoff(7), Duration::minutes(32));
+        assert_eq!(backoff(MAX_ATTEMPTS), Duration::minutes(32));
+        assert_eq!(backoff(40), Duration::minutes(32));
+    }
+
+    #[test]
+    fn dead_after_max_attempts() {
+        assert_eq!(next_status(1, false), "delivered");
+        assert_eq!(next_status(MAX_ATTEMPTS, false), "delivered");
+        for attempts in 1..MAX_ATTEMPTS {
+            assert_eq!(next_status(attempts, true), "pending");
+        }
+        assert_eq!(next_status(MAX_ATTEMPTS, true), "dead");
+    }
+
+    #[test]
+    fn only_public_addresses_are_public() {
+        for ip in [
+            "127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "0.0.0.0",
+            "100.64.0.1", "255.255.255.255", "::1", "::", "fd00::1", "fe80::1", "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in ["93.184.216.34", "1.1.1.1", "2606:4700:4700::1111", "::ffff:8.8.8.8"] {
+            assert!(is_public(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[test]
+    fn receiver_urls_are_https_and_public() {
+        assert!(check_url("https://hooks.example.com/x", false).is_ok());
+        assert!(check_url("https://1.1.1.1/x", false).is_ok());
+        for url in [
+            "http://hooks.example.com/x",
+            "ftp://hooks.example.com/x",
+            "https://127.0.0.1/x",
+            "https://[::1]/x",
+            "https://169.254.169.254/latest/meta-data",
+            "https://10.0.0.8:8443/x",
+            "not a url",
+        ] {
+            assert!(check_url(url, false).is_err(), "{url}");
+        }
+        // the dev switch lets the local receiver of the fixtures through
+        assert!(check_url("http://127.0.0.1:9099/hook", true).is_ok());
+        assert!(check_url("ftp://127.0.0.1/hook", true).is_err());
+    }
+
+    #[tokio::test]
+    async fn resolver_drops_private_addresses() {
+        let err = PublicResolver.resolve("localhost".parse().unwrap()).await.err();
+        assert!(err.is_some_and(|e| e.to_string().contains("no public address")));
+    }
+
+    #[tokio::test]
+    async fn posts_signed_payload_to_receiver() {
+        let (url, log) = receiver(StatusCode::OK).await;
+        let client = reqwest::Client::new();
+        let body = r#"{"data":{"id":1},"event":"account.updated"}"#.to_string();
+        let outcome = post(&client, &url, "whsec_abc", "account.updated", 42, body.clone()).await;
+        assert_eq!(outcome.status_code, Some(200));
+        assert_eq!(outcome.error, None);
+
+        let got = log.lock().unwrap().clone();
+        assert_eq!(got.len(), 1);
+        let r = &got[0];
+        assert_eq!(r.body, body);
+        assert_eq!(header(r, "content-type"), "application/json");
+        assert_eq!(header(r, EVENT_HEADER), "account.updated");
+        assert_eq!(header(r, DELIVERY_HEADER), "42");
+        let timestamp: i64 = header(r, TIMESTAMP_HEADER).parse().unwrap();
+        assert!((Utc::now().timestamp() - timestamp).abs() < 60);
+        let signature = header(r, SIGNATURE_HEADER);
+        let hex = signature.strip_prefix("sha256=").expect("sha256= prefix");
+        assert!(hex.len() == 64 && hex.bytes().all(|b| b.is_ascii_hexdigit() && !b.is_ascii_uppercase()));
+        assert_eq!(hex, sign("whsec_abc", timestamp, r.body.as_bytes()));
+    }
+
+    #[tokio::test]
+    async fn failing_receiver_retries_until_dead() {
+        let (url, log) = receiver(StatusCode::SERVICE_UNAVAILABLE).await;
+        let client = reqwest::Client::new();
+        // the worker's loop without the database: post, count the attempt, decide
+        let mut attempts = 0;
+        let mut status = "pending";
+        let mut waits = Vec::new();
+        while status == "pending" {
+            let outcome = post(&client, &url, "whsec_abc", "transaction.deleted", 7, "{}".to_string()).await;
+            assert_eq!(outcome.status_code, Some(503));
+            assert!(outcome.error.as_deref().is_some_and(|e| e.contains("503")));
+            attempts += 1;
+            status = next_status(attempts, outcome.error.is_some());
+            if status == "pending" {
+                waits.push(backoff(attempts).num_seconds());
+            }
+        }
+        assert_eq!(status, "dead");
+        assert_eq!(attempts, MAX_ATTEMPTS);
+        assert_eq!(log.lock().unwrap().len(), MAX_ATTEMPTS as usize);
+        assert_eq!(waits, vec![30, 60, 120, 240, 480, 960, 1920]);
+        // every retry carries the same delivery id
+        assert!(log.lock().unwrap().iter().all(|r| header(r, DELIVERY_HEADER) == "7"));
+    }
+
+    #[tokio::test]
+    async fn unreachable_receiver_is_a_failure_without_status() {
+        // a port that was free a moment ago
+        let addr = {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            listener.local_addr().unwrap()
+        };
+        let outcome = post(&reqwest::Client::new(), &format!("http://{addr}/hook"), "s", "e", 1, "{}".into()).await;
+        assert_eq!(outcome.status_code, None);
+        assert!(outcome.error.is_some());
+        assert_eq!(next_status(1, outcome.error.is_some()), "pending");
+    }
+}
@@ -0,0 +1,268 @@
+//stats engine on a generated 100k entry ledger
+//
+// run with `cargo bench --bench stats`, every query should stay well under a millisecond
+use chrono::{Duration, NaiveDate, Utc};
+use client::stat::datatype::*;
+use client::stat::index::StatsIndex;
+use client::stat::*;
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+const ENTRIES: usize = 100_000;
+const ACCOUNTS: i64 = 8;
+const CATEGORIES: i64 = 30;
+const DAYS: i64 = 5 * 365;
+
+fn start() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2021, 1, 1).unwrap()
+}
+///five years of data, two entries per transaction (a split and its transfer side on another account)
+fn generate(userid: UserId) -> Ledger {
+    let mut rng = StdRng::seed_from_u64(7);
+    let now = Utc::now();
+    let user = vec![User {
+        id: userid,
+        email: "bench@example.com".to_string(),
+        create_date: now,
+    }];
+    let account = (1..=ACCOUNTS)
+        .map(|id| Account {
+            id,
+            userid,
+            name: format!("Account {id}"),
+            account_type: if id % 4 == 0 {
+                AccountType::Credit
+            } else {
+                AccountType::Checking
+            },
+            currency: Currency::new("cad"),
+            balance: 1000.0,
+            create_date: now,
+            credit: CreditTerms::default(),
+            loan: None,
+            cost_basis: None,
+            contact_email: None,
+        })
+        .collect();
+    let category = (1..=CATEGORIES)
+        .map(|id| Category {
+            id,
+            userid,
+            name: format!("Category {id}"),
+            parentid: None,
+            tax_line: None,
+        })
+        .collect();
+    let mut transaction = Vec::new();
+    let mut entry = Vec::new();
+    while entry.len() < ENTRIES {
+        let tx = Transaction {
+            id: Uuid::new_v4(),
+            userid,
+            occur_date: start() + Duration::days(rng.gen_range(0..DAYS)),
+            receiver: Some(format!("Payee {}", rng.gen_range(0..500))),
+            desc: None,
+            create_date: now,
+        };
+        let amount = (rng.gen_range(-50_000..20_000) as f64) / 100.0;
+        for (acc, value) in [
+            (rng.gen_range(1..=ACCOUNTS), amount),
+            (rng.gen_range(1..=ACCOUNTS), -amount),
+        ] {
+            entry.push(Entry {
+                id: entry.len() as i64 + 1,
+                userid,
+                tranid: tx.id,
+                accountid: acc,
+                categoryid: if rng.gen_bool(0.9) {
+                    Some(rng.gen_range(1..=CATEGORIES))
+                } else {
+                    None
+                },
+                amount: value,
+                desc: None,
+                status: EntryStatus::Uncleared,
+            });
+        }
+        transaction.push(tx);
+    }
+    Ledger::new(user, account, category, transaction, entry)
+}
+///the old way, one pass over every entry per call
+fn scan_summary(ledger: &Ledger, range: DateRange, category: Option<CategoryId>) -> f64 {
+    let dates: HashMap<TransactionId, NaiveDate> = ledger
+        .transaction
+        .iter()
+        .map(|i| (i.id, i.occur_date))
+        .collect();
+    ledger
+        .entry
+        .iter()
+        .filter(|e| category.is_none() || e.categoryid == category)
+        .filter(|e| dates.get(&e.tranid).is_some_and(|d| range.contains(*d)))
+        .map(|e| e.amount)
+        .sum()
+}
+///index answers must match a plain rescan before anything is timed
+fn check(ledger: &Ledger, userid: UserId) {
+    // summed in a different order, so equal to the cent
+    let close = |a: f64, b: f64| (a - b).abs() < 0.005;
+    let ranges = [
+        DateRange::months((2022, 1), (2022, 12)),
+        DateRange::new(start() + Duration::days(17), start() + Duration::days(400)),
+        DateRange::months((2021, 1), (2025, 12)),
+    ];
+    for range in ranges {
+        for category in [None, Some(3)] {
+            let indexed = ledger.month_summary(userid, range, None, category, None);
+            assert!(close(indexed, scan_summary(ledger, range, category)));
+        }
+    }
+    let total: f64 = ledger
+        .entry
+        .iter()
+        .filter(|e| e.accountid == 2)
+        .map(|e| e.amount)
+        .sum();
+    assert!(close(ledger.cal_balance(2), 1000.0 + total));
+}
+
+fn bench_stats(c: &mut Criterion) {
+    let userid = Uuid::new_v4();
+    let mut ledger = generate(userid);
+    check(&ledger, userid);
+    let year = DateRange::months((2024, 1), (2024, 12));
+    let odd = DateRange::new(
+        NaiveDate::from_ymd_opt(2023, 3, 15).unwrap(),
+        NaiveDate::from_ymd_opt(2024, 6, 14).unwrap(),
+    );
+
+    c.bench_function("build index 100k", |b| {
+        b.iter(|| {
+            Ledger::new(
+                Vec::new(),
+                Vec::new(),
+                Vec::new(),
+                ledger.transaction.clone(),
+                ledger.entry.clone(),
+            )
+        })
+    });
+    c.bench_function("scan month_summary 12 months (no index)", |b| {
+        b.iter(|| scan_summary(&ledger, black_box(year), None))
+    });
+    c.bench_function("month_summary 12 months", |b| {
+        b.iter(|| ledger.month_summary(userid, black_box(year), None, None, None))
+    });
+    c.bench_function(
+        "month_summary mid-month range, one account and category",
+        |b| b.iter(|| ledger.month_summary(userid, black_box(odd), Some(2), Some(5), Some(true))),
+    );
+    c.bench_function("data_linetrend 12 months", |b| {
+        b.iter(|| ledger.data_linetrend(userid, black_box(year), None, None))
+    });
+    c.bench_function("data_periodtrend weekly 12 months", |b| {
+        b.iter(|| ledger.data_periodtrend(userid, black_box(year), Period::Week, None, None))
+    });
+    c.bench_function("top_category 12 months", |b| {
+        b.iter(|| ledger.top_category(userid, black_box(year), None, 5, Some(true)))
+    });
+    c.bench_function("top_account 12 months", |b| {
+        b.iter(|| ledger.top_account(userid, black_box(year), None, 5, Some(true)))
+    });
+    c.bench_function("reconcile balance check 12 months", |b| {
+        b.iter(|| ledger.month_summary(userid, black_box(year), Some(1), None, None))
+    });
+    c.bench_function("compare_periods 12 months vs previous", |b| {
+        b.iter(|| ledger.compare_periods(userid, year.previous(), black_box(year)))
+    });
+    c.bench_function("all_account_summary", |b| {
+        b.iter(|| ledger.all_account_summary())
+    });
+    c.bench_function("networth_series monthly 5 years", |b| {
+        b.iter(|| {
+            ledger.networth_series(
+                userid,
+                start(),
+                start() + Duration::days(DAYS),
+                Granularity::Monthly,
+            )
+        })
+    });
+    c.bench_function("forecast 6 months", |b| {
+        b.iter(|| {
+            ledger.forecast(
+                userid,
+                black_box(start() + Duration::days(DAYS)),
+                ForecastConfig::default(),
+            )
+        })
+    });
+    c.bench_function("subscriptions 2 years", |b| {
+        b.iter(|| ledger.subscriptions(userid, black_box(DateRange::months((2024, 1), (2025, 12)))))
+    });
+    c.bench_function("anomalies one month", |b| {
+        b.iter(|| {
+            ledger.anomalies(
+                userid,
+                black_box(DateRange::month(2025, 6)),
+                AnomalyConfig::default(),
+            )
+        })
+    });
+    let goal = Goal {
+        id: 1,
+        userid,
+        name: "Trip".to_string(),
+        target: 5000.0,
+        deadline: Some(start() + Duration::days(DAYS + 365)),
+        accounts: Vec::new(),
+        tag: Some("trip".to_string()),
+        create_date: Utc::now(),
+    };
+    c.bench_function("goal_progress tagged", |b| {
+        b.iter(|| ledger.goal_progress(&goal, black_box(start() + Duration::days(DAYS))))
+    });
+
+    // incremental update: one new transaction with an entry, then take it out again
+    let tx = Transaction {
+        id: Uuid::new_v4(),
+        userid,
+        occur_date: NaiveDate::from_ymd_opt(2024, 5, 20).unwrap(),
+        receiver: None,
+        desc: None,
+        create_date: Utc::now(),
+    };
+    let e = Entry {
+        id: ENTRIES as i64 + 10,
+        userid,
+        tranid: tx.id,
+        accountid: 1,
+        categoryid: Some(1),
+        amount: -12.34,
+        desc: None,
+        status: EntryStatus::Uncleared,
+    };
+    // the index alone, the ledger also keeps its vectors in order which is a linear scan
+    let mut index = StatsIndex::build(&ledger.transaction, &ledger.entry);
+    c.bench_function("index insert + remove one transaction", |b| {
+        b.iter(|| {
+            index.insert_transaction(tx.id, tx.occur_date);
+            index.insert_entry(&e);
+            index.remove_transaction(tx.id);
+        })
+    });
+    c.bench_function("ledger insert + remove one transaction", |b| {
+        b.iter(|| {
+            ledger.insert_transaction(tx.clone());
+            ledger.insert_entry(e.clone());
+            ledger.remove_transaction(tx.id);
+        })
+    });
+}
+
+criterion_group!(benches, bench_stats);
+criterion_main!(benches);
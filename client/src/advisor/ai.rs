@@ -1,7 +1,8 @@
 //base on offical Candle repository https://github.com/huggingface/candle/blob/main/candle-examples/examples/quantized-qwen2-instruct/main.rs, with own build
 use crate::stat::datatype::*;
 use crate::stat::sync::*;
-use crate::stat::{Ledger, timephase_fromnow};
+use crate::stat::history::{Command, History};
+use crate::stat::{DateRange, GoalState, Ledger, Period, timephase_fromnow};
 use anyhow::{Result, anyhow};
 use candle_core::quantized::gguf_file;
 use candle_core::{Device, Tensor};
@@ -105,6 +106,18 @@ pub const TOOL: &str = r#"
     },
     "required":["months"]
   }}
+  {"name":"subscriptions",
+   "description":"List recurring charges (subscriptions, memberships, rent, bills) found in the last 2 years, with how often they are charged, the average amount, the yearly cost, the last charge date and any price increase. Use this when the user asks about subscriptions, recurring or forgotten charges.",
+   "parameters":{
+     "type":"object",
+     "properties":{
+       "include_ended":{
+         "type":"boolean",
+         "description":"Also list charges that stopped. If omitted, only active ones are listed."
+       }
+     },
+     "required":[]
+   }}
    {"name":"upload_transaction",
     "description":"Upload (record) a new transaction to the cloud server, e.g. salary received, paid rent, bought food.",
     "parameters":{
@@ -239,14 +252,14 @@ fn tool_month_summary(ledger: &Ledger, userid: UserId, args: &JsonValue) -> Stri
     } else {
         ((e_y, e_m), (s_y, s_m))
     };
-    let timephase = Some(((sy, sm), (ey, em)));
+    let range = DateRange::months((sy, sm), (ey, em));
     let kind = match args.get("kind") {
         Some(i) => i.as_str().unwrap_or("spend"),
         None => "spend",
     };
     match kind {
         "spend" => {
-            let sum = ledger.month_summary(userid, sy, sm, None, None, Some(true), timephase);
+            let sum = ledger.month_summary(userid, range, None, None, Some(true));
             let spend = -sum;
             format!(
                 "Total spending from {sy:04}-{sm:02} to {ey:04}-{em:02} is {spend:.2} CAD.",
@@ -258,7 +271,7 @@ fn tool_month_summary(ledger: &Ledger, userid: UserId, args: &JsonValue) -> Stri
             )
         }
         "net" => {
-            let sum = ledger.month_summary(userid, sy, sm, None, None, None, timephase);
+            let sum = ledger.month_summary(userid, range, None, None, None);
 
             format!(
                 "Total net income/outcome from {sy:04}-{sm:02} to {ey:04}-{em:02} is {spend:.2} CAD.",
@@ -270,7 +283,7 @@ fn tool_month_summary(ledger: &Ledger, userid: UserId, args: &JsonValue) -> Stri
             )
         }
         "income" => {
-            let sum = ledger.month_summary(userid, sy, sm, None, None, Some(false), timephase);
+            let sum = ledger.month_summary(userid, range, None, None, Some(false));
 
             format!(
                 "Total income from {sy:04}-{sm:02} to {ey:04}-{em:02} is {spend:.2} CAD.",
@@ -303,7 +316,7 @@ fn tool_recent_top_category(ledger: &Ledger, userid: UserId, args: &JsonValue) -
     let timephase = timephase_fromnow(m);
     let trend = ledger.top_category(userid, timephase, None, k, Some(true));
     let mut out = String::new();
-    for (cat, val) in trend.axis.iter().zip(trend.outcome.iter()) {
+    for ((cat, _), val) in trend.axis.iter().zip(trend.outcome.iter()) {
         let spend = val.abs();
         out.push_str(&format!(
             "- {cat}:{spend:.2}CAD\n",
@@ -332,7 +345,7 @@ fn tool_recent_top_account(ledger: &Ledger, userid: UserId, args: &JsonValue) ->
     let timephase = timephase_fromnow(m);
     let trend = ledger.top_account(userid, timephase, None, k, Some(true));
     let mut out = String::new();
-    for (cat, val) in trend.axis.iter().zip(trend.outcome.iter()) {
+    for ((cat, _), val) in trend.axis.iter().zip(trend.outcome.iter()) {
         let spend = val.abs();
         out.push_str(&format!(
             "- {cat}:{spend:.2}CAD\n",
@@ -345,8 +358,9 @@ fn tool_recent_top_account(ledger: &Ledger, userid: UserId, args: &JsonValue) ->
 //function for LLM upload transaction
 async fn tool_upload_transaction(
     base_url: &str,
-    token: &str,
+    session: &Session,
     ledger: &mut Ledger,
+    history: &mut History,
     args: &JsonValue,
 ) -> String {
     let res: anyhow::Result<String> = (async {
@@ -421,9 +435,13 @@ async fn tool_upload_transaction(
         }
         if !found {
             let created =
-                create_cloudaccount(base_url, token, acc_name, &acc_type, Some(currency), None)
+                create_cloudaccount(base_url, session, acc_name, &acc_type, Some(currency), None)
                     .await?;
             acc_id = created.id;
+            history.record(Command::CreateAccount {
+                id: created.id,
+                name: created.name,
+            });
         }
         let cat_name = match args.get("category") {
             Some(v) => v.as_str().unwrap_or("").trim(),
@@ -440,7 +458,11 @@ async fn tool_upload_transaction(
             {
                 Some(c) => Some(c.id),
                 None => {
-                    let created = create_cloudcate(base_url, token, cat_name, None).await?;
+                    let created = create_cloudcate(base_url, session, cat_name, None, None).await?;
+                    history.record(Command::CreateCategory {
+                        id: created.id,
+                        name: created.name,
+                    });
                     Some(created.id)
                 }
             }
@@ -464,8 +486,10 @@ async fn tool_upload_transaction(
                 None => None,
             },
         };
-        create_cloudtransaction(base_url, token, occ, payee, memo, vec![entry]).await?;
-        *ledger = download_ledger_from_server(base_url, token).await?;
+        let created =
+            create_cloudtransaction(base_url, session, occ, payee, memo, vec![entry]).await?;
+        history.record(Command::CreateTransaction { id: created.id });
+        *ledger = download_ledger_from_server(base_url, session).await?;
         Ok(format!(
             "uploaded transaction: {} {} {:.2} to account {}",
             occ,
@@ -511,12 +535,58 @@ fn tool_recent_trend(ledger: &Ledger, userid: UserId, args: &JsonValue) -> Strin
     }
     s
 }
+//function for LLM
+fn tool_subscriptions(ledger: &Ledger, userid: UserId, args: &JsonValue) -> String {
+    let include_ended = match args.get("include_ended") {
+        Some(v) => match v.as_bool() {
+            Some(b) => b,
+            None => return "given parameter wrong, function give error.".to_string(),
+        },
+        None => false,
+    };
+    let today = Local::now().date_naive();
+    let range = DateRange::new(timephase_fromnow(24).from, today);
+    let subs = ledger.subscriptions(userid, range);
+    let mut out = String::new();
+    let mut yearly = 0.0;
+    for i in subs.iter().filter(|i| i.active || include_ended) {
+        if i.active {
+            yearly += i.annual.abs();
+        }
+        out.push_str(&format!(
+            "- {payee}: {cadence} {avg:.2}CAD, {year:.2}CAD a year, last charged {last}",
+            payee = i.payee,
+            cadence = i.cadence.name().to_lowercase(),
+            avg = i.average.abs(),
+            year = i.annual.abs(),
+            last = i.last_date
+        ));
+        if let Some(c) = i.increase {
+            out.push_str(&format!(
+                ", price up from {:.2} to {:.2} on {}",
+                c.from.abs(),
+                c.to.abs(),
+                c.date
+            ));
+        }
+        if !i.active {
+            out.push_str(", ended");
+        }
+        out.push('\n');
+    }
+    if out.is_empty() {
+        return "no recurring charges found".to_string();
+    }
+    out.push_str(&format!("active total: {yearly:.2}CAD a year"));
+    out
+}
 //if add new function add here and update pub const TOOL, but don't add too much funtion to increase pressure for memory
 async fn run_toolcall(
     base_url: &str,
-    token: &str,
+    session: &Session,
     toolcall: &Toolcall,
     ledger: &mut Ledger,
+    history: &mut History,
     userid: UserId,
 ) -> String {
     let name = toolcall.name.as_str();
@@ -525,8 +595,9 @@ async fn run_toolcall(
         "recent_top_account" => tool_recent_top_account(ledger, userid, &toolcall.arguments),
         "recent_top_category" => tool_recent_top_category(ledger, userid, &toolcall.arguments),
         "recent_trend" => tool_recent_trend(ledger, userid, &toolcall.arguments),
+        "subscriptions" => tool_subscriptions(ledger, userid, &toolcall.arguments),
         "upload_transaction" => {
-            tool_upload_transaction(base_url, token, ledger, &toolcall.arguments).await
+            tool_upload_transaction(base_url, session, ledger, history, &toolcall.arguments).await
         }
         _ => "unknown tool name".to_string(),
     };
@@ -764,14 +835,14 @@ impl Model {
         &self,
         ledger: &Ledger,
         userid: UserId,
+        goals: &[Goal],
         top_k: usize,
         pastmonths: u32,
     ) -> String {
         let timephase = timephase_fromnow(pastmonths);
         let trend = ledger.data_linetrend(userid, timephase, None, None);
-        let lm = timephase.1.1;
-        let ly = timephase.1.0;
-        let top_cat = ledger.top_category(userid, ((ly, lm), (ly, lm)), None, top_k, Some(true));
+        let last_month = Period::Month.containing(timephase.to);
+        let top_cat = ledger.top_category(userid, last_month, None, top_k, Some(true));
         let mut prompt = String::new();
         prompt.push_str("You are a personal finance assistant.\n");
         prompt.push_str("Recent monthly totals:\n");
@@ -797,33 +868,59 @@ impl Model {
             }
         }
         prompt.push_str(&format!(
-            "Main spending categories in {ly:04}-{lm:02}:\n",
-            ly = ly,
-            lm = lm
+            "Main spending categories in {}:\n",
+            last_month.label()
         ));
         for i in 0..top_cat.axis.len() {
-            let name = &top_cat.axis[i];
+            let name = &top_cat.axis[i].0;
             let out = top_cat.outcome[i].abs();
             prompt.push_str(&format!("- {name}: {out:.2} CAD\n", name = name, out = out));
         }
+        let today = Local::now().date_naive();
+        let progress: Vec<_> = goals.iter().map(|g| ledger.goal_progress(g, today)).collect();
+        if !progress.is_empty() {
+            prompt.push_str("Savings goals:\n");
+            for p in &progress {
+                let mut line = format!(
+                    "- {}: {:.2} of {:.2} CAD saved ({:.0}%)",
+                    p.name, p.saved, p.target, p.percent
+                );
+                if let (Some(d), Some(req)) = (p.deadline, p.required_monthly) {
+                    line.push_str(&format!(", due {d}, needs {req:.2} CAD a month"));
+                }
+                line.push_str(&format!(
+                    ", saving {:.2} CAD a month lately: {}\n",
+                    p.recent_monthly,
+                    p.state.name().to_lowercase()
+                ));
+                prompt.push_str(&line);
+            }
+        }
         prompt.push_str("\n");
         prompt.push_str(
             "Now, in less than 200 English words:\n\
 1) Explain the main risks or problems in my spending.\n\
-2) Give 2–3 specific suggestions to improve my situation.\n\
-Focus on categories and behaviours, not on exact amounts.\n",
+2) Give 2–3 specific suggestions to improve my situation.\n",
         );
+        if progress
+            .iter()
+            .any(|p| matches!(p.state, GoalState::Behind | GoalState::Overdue))
+        {
+            prompt.push_str("3) Say how I could catch up on the goals that are behind.\n");
+        }
+        prompt.push_str("Focus on categories and behaviours, not on exact amounts.\n");
         prompt
     }
     pub fn generate_advicepair(
         &mut self,
         ledger: &Ledger,
         userid: UserId,
+        goals: &[Goal],
         top_k: usize,
         pastmonths: u32,
         cfg: &Generationcfg,
     ) -> Result<Vec<String>> {
-        let prompt = self.build_prompt(ledger, userid, top_k, pastmonths);
+        let prompt = self.build_prompt(ledger, userid, goals, top_k, pastmonths);
         let cad1 = self.generation(&prompt, cfg)?;
         let cad2 = self.generation(&prompt, cfg)?;
         let mut result = Vec::new();
@@ -833,12 +930,14 @@ Focus on categories and behaviours, not on exact amounts.\n",
         return Ok(result);
     }
     ///agent chain two round, user question-> ai choose function -> function output -> ai return, in CPU mod, it takes really long time, 0.5B 1.5B modle may make mistake, sugguest use 3,7B with cuda or metal
+    #[allow(clippy::too_many_arguments)]
     pub async fn answer_withtool(
         &mut self,
         content: &str,
         base_url: &str,
-        token: &str,
+        session: &Session,
         ledger: &mut Ledger,
+        history: &mut History,
         userid: UserId,
         cfg: &Generationcfg,
     ) -> Result<String> {
@@ -873,7 +972,7 @@ Focus on categories and behaviours, not on exact amounts.\n",
                 return Ok(backup);
             }
         };
-        let r = run_toolcall(base_url, token, &fc, ledger, userid).await;
+        let r = run_toolcall(base_url, session, &fc, ledger, history, userid).await;
         let second_prompt = self.name.apply_tool_out_chat_template(&prompt_first, &r);
         let final_a = match self.generation_core(&second_prompt, cfg) {
             Ok(v) => v,
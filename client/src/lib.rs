@@ -0,0 +1,2 @@
+//stats engine and server sync of the client, shared by the TUI binary and the benches
+pub mod stat;
@@ -1,6 +1,6 @@
 mod advisor;
-mod stat;
 mod tui;
+use client::stat;
 use anyhow::Result;
 use stat::*;
 
@@ -97,9 +97,10 @@ async fn main() -> Result<()> {
     };
     
     println!("running TUI...");
-    let ledger = download_ledger_from_server(&base_url, &token).await?;
+    let session = Session::new(token);
+    let ledger = download_ledger_from_server(&base_url, &session).await?;
     tokio::task::spawn_blocking(move || {
-        tui::run_tui(ledger, base_url.to_string(), token.to_string())
+        tui::run_tui(ledger, base_url.to_string(), session)
     })
     .await?
     .map_err(|e| anyhow::anyhow!("TUI error: {}", e))?;
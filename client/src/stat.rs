@@ -1,5 +1,34 @@
+pub mod anomaly;
+pub mod attachment;
+pub mod compare;
+pub mod contact;
+pub mod credit;
 pub mod datatype;
+pub mod discrepancy;
+pub mod forecast;
+pub mod goal;
+pub mod history;
+pub mod index;
+pub mod investment;
 pub mod ledger;
+pub mod loan;
+pub mod period;
+pub mod statement;
 pub mod sync;
+pub mod tax;
+pub use anomaly::*;
+pub use attachment::*;
+pub use compare::*;
+pub use contact::*;
+pub use credit::*;
+pub use discrepancy::*;
+pub use forecast::*;
+pub use goal::*;
+pub use history::*;
+pub use investment::*;
 pub use ledger::*;
+pub use loan::*;
+pub use period::*;
+pub use statement::*;
 pub use sync::*;
+pub use tax::*;
@@ -0,0 +1,468 @@
+use super::datatype::*;
+use super::ledger::*;
+use super::period::*;
+use super::statement::normalize_payee;
+use chrono::*;
+use std::collections::HashMap;
+//proactive anomaly detection: unusual amounts, category spikes and possible duplicate charges
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnomalyKind {
+    Payee,     // far above what this payee usually charges
+    Category,  // far above the usual amount of its category
+    Spike,     // a category month far above its trailing average
+    Duplicate, // same account, amount and payee a few days apart
+}
+impl AnomalyKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AnomalyKind::Payee => "Payee",
+            AnomalyKind::Category => "Category",
+            AnomalyKind::Spike => "Spike",
+            AnomalyKind::Duplicate => "Duplicate",
+        }
+    }
+}
+///one alert, amounts are spending so negative like the entries
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Anomaly {
+    pub kind: AnomalyKind,
+    pub date: NaiveDate, // of the entry, or the first day of the spiking month
+    pub accountid: Option<AccountId>,
+    pub categoryid: Option<CategoryId>,
+    pub entries: Vec<EntryId>, // the flagged entry, both copies for a duplicate
+    pub amount: f64,
+    pub expected: f64, // typical amount, trailing average, or the earlier copy
+    pub score: f64,    // how far past the threshold, 1 is right at it; the list is ranked by it
+    pub explanation: String,
+}
+///thresholds of the detector
+#[derive(Debug, Clone, Copy)]
+pub struct AnomalyConfig {
+    pub history_months: u32, // whole months before the checked range used for the distributions
+    pub min_samples: usize,  // smallest history a payee or category needs to be judged
+    pub max_z: f64,          // robust z-score above which an amount is unusual
+    pub spike_months: u32,   // trailing months a category month is compared with
+    pub spike_ratio: f64,    // month spend / trailing average above which it is a spike
+    pub spike_min: f64,      // smallest increase over the average worth an alert
+    pub duplicate_days: i64, // max days between a charge and its duplicate
+    pub top_k: usize,
+}
+impl Default for AnomalyConfig {
+    fn default() -> Self {
+        AnomalyConfig {
+            history_months: 12,
+            min_samples: 5,
+            max_z: 3.5,
+            spike_months: 6,
+            spike_ratio: 1.5,
+            spike_min: 50.0,
+            duplicate_days: 3,
+            top_k: 20,
+        }
+    }
+}
+///median and median absolute deviation of spending sizes
+///
+/// the deviation is kept at 5% of the median at least, otherwise a fixed price charge
+/// (deviation 0) would flag any cent of difference
+struct Spread {
+    median: f64,
+    mad: f64,
+}
+fn median_of(v: &mut [f64]) -> f64 {
+    v.sort_by(|a, b| a.total_cmp(b));
+    match v.len() {
+        0 => 0.0,
+        n if n % 2 == 1 => v[n / 2],
+        n => (v[n / 2 - 1] + v[n / 2]) / 2.0,
+    }
+}
+impl Spread {
+    fn of(mut sizes: Vec<f64>) -> Self {
+        let median = median_of(&mut sizes);
+        let mut dev: Vec<f64> = sizes.iter().map(|s| (s - median).abs()).collect();
+        let mad = median_of(&mut dev).max(0.05 * median);
+        Spread { median, mad }
+    }
+    ///robust z-score, 0.6745 scales the deviation to a standard deviation for normal data
+    fn z(&self, size: f64) -> f64 {
+        match self.mad > 0.0 {
+            true => 0.6745 * (size - self.median) / self.mad,
+            false => 0.0,
+        }
+    }
+}
+///one spending entry of the scan
+struct Charge<'a> {
+    entry: &'a Entry,
+    date: NaiveDate,
+    payee: String, // display name
+    key: String,   // normalized payee, empty when there is none
+}
+impl Ledger {
+    ///unusual spending inside `range`, highest score first
+    ///
+    /// - amounts: each spending entry against the robust z-score of its payee's earlier charges,
+    ///   or of its category's when the payee is new
+    /// - spikes: spending of a category per month of `range` against the average of the
+    ///   `spike_months` months before
+    /// - duplicates: the same payee, account and amount on another transaction a few days apart
+    ///
+    /// distributions come from the `history_months` before `range` only, so an outlier does not
+    /// hide itself
+    pub fn anomalies(
+        &self,
+        userid: UserId,
+        range: impl Into<DateRange>,
+        cfg: AnomalyConfig,
+    ) -> Vec<Anomaly> {
+        let range = range.into();
+        let hist_from = Period::Month
+            .containing(range.from)
+            .from
+            .checked_sub_months(Months::new(cfg.history_months.max(cfg.spike_months)))
+            .unwrap_or(range.from);
+        let scanned = DateRange::new(hist_from, range.to);
+        let txs: HashMap<TransactionId, &Transaction> = self
+            .transaction
+            .iter()
+            .filter(|t| t.userid == userid && scanned.contains(t.occur_date))
+            .map(|t| (t.id, t))
+            .collect();
+        let mut charges: Vec<Charge> = Vec::new();
+        for e in &self.entry {
+            if e.amount >= 0.0 {
+                continue;
+            }
+            let Some(tx) = txs.get(&e.tranid) else {
+                continue;
+            };
+            let payee = Self::entry_payee(e, tx);
+            charges.push(Charge {
+                entry: e,
+                date: tx.occur_date,
+                key: normalize_payee(&payee),
+                payee,
+            });
+        }
+        charges.sort_by_key(|c| (c.date, c.entry.id));
+
+        let mut result = self.amount_anomalies(&charges, range, cfg);
+        result.extend(self.spike_anomalies(&charges, range, cfg));
+        result.extend(duplicate_anomalies(&charges, range, cfg));
+        result.sort_by(|a, b| b.score.total_cmp(&a.score).then(b.date.cmp(&a.date)));
+        result.truncate(cfg.top_k);
+        result
+    }
+    fn amount_anomalies(
+        &self,
+        charges: &[Charge],
+        range: DateRange,
+        cfg: AnomalyConfig,
+    ) -> Vec<Anomaly> {
+        let hist_from = Period::Month
+            .containing(range.from)
+            .from
+            .checked_sub_months(Months::new(cfg.history_months))
+            .unwrap_or(range.from);
+        let mut by_payee: HashMap<&str, Vec<f64>> = HashMap::new();
+        let mut by_category: HashMap<CategoryId, Vec<f64>> = HashMap::new();
+        for c in charges
+            .iter()
+            .filter(|c| c.date >= hist_from && c.date < range.from)
+        {
+            if !c.key.is_empty() {
+                by_payee.entry(&c.key).or_default().push(-c.entry.amount);
+            }
+            if let Some(cat) = c.entry.categoryid {
+                by_category.entry(cat).or_default().push(-c.entry.amount);
+            }
+        }
+        let enough = |v: &&Vec<f64>| v.len() >= cfg.min_samples;
+        let payees: HashMap<&str, Spread> = by_payee
+            .iter()
+            .filter(|(_, v)| enough(v))
+            .map(|(k, v)| (*k, Spread::of(v.clone())))
+            .collect();
+        let categories: HashMap<CategoryId, Spread> = by_category
+            .iter()
+            .filter(|(_, v)| enough(v))
+            .map(|(k, v)| (*k, Spread::of(v.clone())))
+            .collect();
+
+        let mut result = Vec::new();
+        for c in charges.iter().filter(|c| range.contains(c.date)) {
+            let size = -c.entry.amount;
+            let (kind, spread, of) = match (payees.get(c.key.as_str()), c.entry.categoryid) {
+                (Some(s), _) => (AnomalyKind::Payee, s, c.payee.clone()),
+                // a payee seen a few times is not judged by a category that mixes many payees
+                (None, _) if by_payee.contains_key(c.key.as_str()) => continue,
+                (None, Some(cat)) => match categories.get(&cat) {
+                    Some(s) => (AnomalyKind::Category, s, self.trans_categoryid2name(cat)),
+                    None => continue,
+                },
+                (None, None) => continue,
+            };
+            let z = spread.z(size);
+            if z <= cfg.max_z {
+                continue;
+            }
+            result.push(Anomaly {
+                kind,
+                date: c.date,
+                accountid: Some(c.entry.accountid),
+                categoryid: c.entry.categoryid,
+                entries: vec![c.entry.id],
+                amount: c.entry.amount,
+                expected: -spread.median,
+                score: z / cfg.max_z,
+                explanation: format!(
+                    "{} {:.2} on {}, {} usually {:.2}",
+                    if c.payee.is_empty() {
+                        "-"
+                    } else {
+                        c.payee.as_str()
+                    },
+                    size,
+                    c.date,
+                    of,
+                    spread.median
+                ),
+            });
+        }
+        result
+    }
+    fn spike_anomalies(
+        &self,
+        charges: &[Charge],
+        range: DateRange,
+        cfg: AnomalyConfig,
+    ) -> Vec<Anomaly> {
+        let Some(first) = charges
+            .first()
+            .map(|c| Period::Month.containing(c.date).from)
+        else {
+            return Vec::new();
+        };
+        let mut spend: HashMap<(CategoryId, (i32, u32)), f64> = HashMap::new();
+        for c in charges {
+            if let Some(cat) = c.entry.categoryid {
+                *spend
+                    .entry((cat, (c.date.year(), c.date.month())))
+                    .or_default() -= c.entry.amount;
+            }
+        }
+        let mut cats: Vec<CategoryId> = spend.keys().map(|(cat, _)| *cat).collect();
+        cats.sort();
+        cats.dedup();
+
+        let mut result = Vec::new();
+        for month in range.split(Period::Month) {
+            // trailing months that have data at all, months without spending count as 0
+            let trailing: Vec<(i32, u32)> = (1..=cfg.spike_months)
+                .filter_map(|i| month.from.checked_sub_months(Months::new(i)))
+                .filter(|d| *d >= first)
+                .map(|d| (d.year(), d.month()))
+                .collect();
+            if trailing.len() < 3 {
+                continue;
+            }
+            let ym = (month.from.year(), month.from.month());
+            for &cat in &cats {
+                let now = spend.get(&(cat, ym)).copied().unwrap_or(0.0);
+                let past: Vec<f64> = trailing
+                    .iter()
+                    .map(|m| spend.get(&(cat, *m)).copied().unwrap_or(0.0))
+                    .collect();
+                let avg = past.iter().sum::<f64>() / past.len() as f64;
+                if avg <= 0.0 || now < avg * cfg.spike_ratio || now - avg < cfg.spike_min {
+                    continue;
+                }
+                result.push(Anomaly {
+                    kind: AnomalyKind::Spike,
+                    date: month.from,
+                    accountid: None,
+                    categoryid: Some(cat),
+                    entries: Vec::new(),
+                    amount: -now,
+                    expected: -avg,
+                    score: now / avg / cfg.spike_ratio,
+                    explanation: format!(
+                        "{} spending {:.2} in {}, {:.1}x the {}-month average {:.2}",
+                        self.trans_categoryid2name(cat),
+                        now,
+                        month.label(),
+                        now / avg,
+                        trailing.len(),
+                        avg
+                    ),
+                });
+            }
+        }
+        result
+    }
+}
+///later copies of a charge: same account, amount and payee on another transaction a few days after
+///
+/// scored 2 when on the same day, 1.5 otherwise
+fn duplicate_anomalies(charges: &[Charge], range: DateRange, cfg: AnomalyConfig) -> Vec<Anomaly> {
+    let mut seen: HashMap<(AccountId, i64, &str), Vec<&Charge>> = HashMap::new();
+    let mut result = Vec::new();
+    for c in charges {
+        if c.key.is_empty() || c.date < range.from - Duration::days(cfg.duplicate_days) {
+            continue;
+        }
+        let key = (
+            c.entry.accountid,
+            (c.entry.amount * 100.0).round() as i64,
+            c.key.as_str(),
+        );
+        let earlier = seen.entry(key).or_default();
+        let orig = earlier.iter().find(|o| {
+            o.entry.tranid != c.entry.tranid && (c.date - o.date).num_days() <= cfg.duplicate_days
+        });
+        if let Some(orig) = orig.filter(|_| range.contains(c.date)) {
+            result.push(Anomaly {
+                kind: AnomalyKind::Duplicate,
+                date: c.date,
+                accountid: Some(c.entry.accountid),
+                categoryid: c.entry.categoryid,
+                entries: vec![orig.entry.id, c.entry.id],
+                amount: c.entry.amount,
+                expected: orig.entry.amount,
+                score: if orig.date == c.date { 2.0 } else { 1.5 },
+                explanation: format!(
+                    "{} {:.2} on {}, same as #{} on {}",
+                    c.payee, -c.entry.amount, c.date, orig.entry.id, orig.date
+                ),
+            });
+        }
+        earlier.push(c);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn d(m: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, m, day).unwrap()
+    }
+    ///weekly groceries from January to August between 80 and 120, then `september` on top
+    fn ledger(september: &[(NaiveDate, f64, &str)]) -> Ledger {
+        let userid = Uuid::nil();
+        let category = vec![Category {
+            id: 1,
+            userid,
+            name: "Groceries".to_string(),
+            parentid: None,
+            tax_line: None,
+        }];
+        let history = (0..35).map(|i| {
+            let amount = -(80.0 + (i * 37 % 41) as f64);
+            (d(1, 3) + Duration::weeks(i), amount, "Grocer")
+        });
+        let (mut transaction, mut entry) = (Vec::new(), Vec::new());
+        for (i, (date, amount, payee)) in history.chain(september.iter().copied()).enumerate() {
+            let tranid = Uuid::from_u128(i as u128 + 1);
+            transaction.push(Transaction {
+                id: tranid,
+                userid,
+                occur_date: date,
+                receiver: Some(payee.to_string()),
+                desc: None,
+                create_date: Utc::now(),
+            });
+            entry.push(Entry {
+                id: i as i64 + 1,
+                userid,
+                tranid,
+                accountid: 1,
+                categoryid: Some(1),
+                amount,
+                desc: None,
+                status: EntryStatus::Cleared,
+            });
+        }
+        Ledger::new(Vec::new(), Vec::new(), category, transaction, entry)
+    }
+    fn september(ledger: &Ledger, cfg: AnomalyConfig) -> Vec<Anomaly> {
+        ledger.anomalies(Uuid::nil(), DateRange::month(2025, 9), cfg)
+    }
+    fn of_kind(found: &[Anomaly], kind: AnomalyKind) -> Vec<&Anomaly> {
+        found.iter().filter(|a| a.kind == kind).collect()
+    }
+
+    #[test]
+    fn clear_outlier_is_flagged_and_normal_variance_is_not() {
+        let ledger = ledger(&[
+            (d(9, 5), -110.0, "Grocer"),
+            (d(9, 12), -82.5, "GROCER"),
+            (d(9, 19), -450.0, "Grocer"),
+            (d(9, 26), -119.0, "Grocer"),
+        ]);
+        let found = september(&ledger, AnomalyConfig::default());
+        let payee = of_kind(&found, AnomalyKind::Payee);
+        assert_eq!(payee.len(), 1);
+        let outlier = payee[0];
+        assert_eq!(outlier.entries, vec![38]);
+        assert_eq!(outlier.date, d(9, 19));
+        assert_eq!(outlier.amount, -450.0);
+        assert!(outlier.expected < -95.0 && outlier.expected > -105.0);
+        assert!(outlier.score > 1.0);
+        // the normal weeks show up nowhere
+        assert!(
+            found
+                .iter()
+                .all(|a| !a.entries.iter().any(|id| [36, 37, 39].contains(id)))
+        );
+        assert!(of_kind(&found, AnomalyKind::Duplicate).is_empty());
+    }
+
+    #[test]
+    fn new_payee_is_judged_by_its_category() {
+        let ledger = ledger(&[
+            (d(9, 8), -400.0, "Fancy Market"),
+            (d(9, 9), -90.0, "Corner Shop"),
+        ]);
+        let found = september(&ledger, AnomalyConfig::default());
+        let category = of_kind(&found, AnomalyKind::Category);
+        assert_eq!(category.len(), 1);
+        assert_eq!(category[0].entries, vec![36]);
+        assert!(category[0].explanation.contains("Groceries"));
+        // too little history to judge anything
+        let strict = AnomalyConfig {
+            min_samples: 100,
+            ..AnomalyConfig::default()
+        };
+        assert!(of_kind(&september(&ledger, strict), AnomalyKind::Category).is_empty());
+    }
+
+    #[test]
+    fn spending_spike_and_duplicates() {
+        let ledger = ledger(&[
+            (d(9, 2), -100.0, "Grocer"),
+            (d(9, 3), -100.0, "Grocer"),
+            (d(9, 10), -300.0, "Bulk Barn"),
+            (d(9, 17), -300.0, "Bulk Barn"),
+        ]);
+        let found = september(&ledger, AnomalyConfig::default());
+        let spike = of_kind(&found, AnomalyKind::Spike);
+        assert_eq!(spike.len(), 1);
+        assert_eq!(spike[0].date, d(9, 1));
+        assert_eq!(spike[0].amount, -800.0);
+        assert!(spike[0].expected > -500.0);
+        // one day apart is a duplicate, a week apart is not
+        let dup = of_kind(&found, AnomalyKind::Duplicate);
+        assert_eq!(dup.len(), 1);
+        assert_eq!(dup[0].entries, vec![36, 37]);
+        assert_eq!(dup[0].score, 1.5);
+        // ranked by score, a duplicate beats a spike under 3x
+        let at = |kind| found.iter().position(|a| a.kind == kind).unwrap();
+        assert!(at(AnomalyKind::Duplicate) < at(AnomalyKind::Spike));
+    }
+}
@@ -0,0 +1,87 @@
+use super::datatype::*;
+use super::ledger::*;
+use anyhow::{Result, anyhow};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+//attachments: receipts and documents of transactions, the files themselves stay on the server
+//until saved to disk
+
+///largest file the server accepts
+pub const MAX_ATTACHMENT_BYTES: u64 = 10 * 1024 * 1024;
+
+///hex SHA-256 of `bytes`, the checksum the server keeps for every attachment
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+///size for people, e.g. `812 B`, `14.2 KiB`, `3.1 MiB`
+pub fn human_size(bytes: i64) -> String {
+    match bytes {
+        b if b < 1024 => format!("{b} B"),
+        b if b < 1024 * 1024 => format!("{:.1} KiB", b as f64 / 1024.0),
+        b => format!("{:.1} MiB", b as f64 / 1024.0 / 1024.0),
+    }
+}
+///name and bytes of a file to attach, refused before upload when the server would refuse it
+pub fn read_attachment_file(path: &str) -> Result<(String, Vec<u8>)> {
+    let path = Path::new(path);
+    let size = std::fs::metadata(path)
+        .map_err(|e| anyhow!("cannot read {}: {e}", path.display()))?
+        .len();
+    if size == 0 {
+        return Err(anyhow!("{} is empty", path.display()));
+    }
+    if size > MAX_ATTACHMENT_BYTES {
+        return Err(anyhow!(
+            "{} is larger than {} MiB",
+            path.display(),
+            MAX_ATTACHMENT_BYTES / 1024 / 1024
+        ));
+    }
+    let filename = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| anyhow!("{} is not a file", path.display()))?;
+    let bytes = std::fs::read(path).map_err(|e| anyhow!("cannot read {}: {e}", path.display()))?;
+    Ok((filename, bytes))
+}
+///write `bytes` into `dir` as `filename`, or `name (1).ext`... when that file exists already
+pub fn save_attachment(dir: &Path, filename: &str, bytes: &[u8]) -> Result<PathBuf> {
+    // the server keeps the uploaded name without directories, strip them anyway
+    let filename = Path::new(filename)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "attachment".to_string());
+    let (stem, ext) = match filename.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem.to_string(), format!(".{ext}")),
+        _ => (filename.clone(), String::new()),
+    };
+    for n in 0..1000 {
+        let path = match n {
+            0 => dir.join(&filename),
+            n => dir.join(format!("{stem} ({n}){ext}")),
+        };
+        // create_new: a file appearing meanwhile is never overwritten
+        let mut file = match std::fs::File::create_new(&path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(anyhow!("cannot write {}: {e}", path.display())),
+        };
+        file.write_all(bytes)
+            .map_err(|e| anyhow!("cannot write {}: {e}", path.display()))?;
+        return Ok(path);
+    }
+    Err(anyhow!(
+        "too many files named {filename} in {}",
+        dir.display()
+    ))
+}
+impl Ledger {
+    ///attachments of a transaction, oldest first
+    pub fn attachments_of(&self, tranid: TransactionId) -> Vec<&Attachment> {
+        self.attachment
+            .iter()
+            .filter(|a| a.tranid == tranid)
+            .collect()
+    }
+}
@@ -0,0 +1,303 @@
+use super::datatype::*;
+use super::ledger::*;
+use super::period::*;
+use std::collections::{HashMap, HashSet};
+//comparison of two periods: totals, per category and per account deltas, biggest movers
+
+///change of one value from the base period to the current one
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Delta {
+    pub base: f64,
+    pub current: f64,
+    pub change: f64,          // current - base
+    pub percent: Option<f64>, // change relative to the size of base, none when base is 0
+}
+impl Delta {
+    pub fn new(base: f64, current: f64) -> Self {
+        let change = current - base;
+        Delta {
+            base,
+            current,
+            change,
+            percent: match base.abs() < 0.005 {
+                true => None,
+                false => Some(change / base.abs() * 100.0),
+            },
+        }
+    }
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareStatus {
+    Both,
+    New,  // only in the current period
+    Gone, // only in the base period
+}
+impl CompareStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CompareStatus::Both => "",
+            CompareStatus::New => "new",
+            CompareStatus::Gone => "gone",
+        }
+    }
+}
+///net of one category or account in both periods
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct CompareRow<K> {
+    pub key: K,
+    pub name: String,
+    pub net: Delta,
+    pub status: CompareStatus,
+}
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct PeriodCompare {
+    pub base: DateRange,
+    pub current: DateRange,
+    pub income: Delta,
+    pub outcome: Delta,
+    pub net: Delta,
+    pub categories: Vec<CompareRow<Option<CategoryId>>>, // largest change first
+    pub accounts: Vec<CompareRow<AccountId>>,            // largest change first
+}
+impl PeriodCompare {
+    ///the `k` categories that changed most, new and gone ones included
+    pub fn movers(&self, k: usize) -> Vec<&CompareRow<Option<CategoryId>>> {
+        self.categories
+            .iter()
+            .filter(|r| r.net.change.abs() >= 0.005)
+            .take(k)
+            .collect()
+    }
+    pub fn new_categories(&self) -> impl Iterator<Item = &CompareRow<Option<CategoryId>>> {
+        self.categories
+            .iter()
+            .filter(|r| r.status == CompareStatus::New)
+    }
+    pub fn gone_categories(&self) -> impl Iterator<Item = &CompareRow<Option<CategoryId>>> {
+        self.categories
+            .iter()
+            .filter(|r| r.status == CompareStatus::Gone)
+    }
+}
+///one row per key used in either period, sorted by the size of the change
+fn compare_rows<K: Copy + Eq + std::hash::Hash>(
+    base: &HashMap<K, Detailstats>,
+    current: &HashMap<K, Detailstats>,
+    name: impl Fn(K) -> String,
+) -> Vec<CompareRow<K>> {
+    let keys: HashSet<K> = base.keys().chain(current.keys()).copied().collect();
+    let mut rows: Vec<CompareRow<K>> = keys
+        .into_iter()
+        .map(|k| {
+            let (b, c) = (base.get(&k), current.get(&k));
+            CompareRow {
+                key: k,
+                name: name(k),
+                net: Delta::new(
+                    b.map(|v| v.summary).unwrap_or(0.0),
+                    c.map(|v| v.summary).unwrap_or(0.0),
+                ),
+                status: match (b, c) {
+                    (None, Some(_)) => CompareStatus::New,
+                    (Some(_), None) => CompareStatus::Gone,
+                    _ => CompareStatus::Both,
+                },
+            }
+        })
+        .collect();
+    rows.sort_by(|a, b| {
+        b.net
+            .change
+            .abs()
+            .total_cmp(&a.net.change.abs())
+            .then(a.name.cmp(&b.name))
+    });
+    rows
+}
+impl Ledger {
+    ///compare `current` with `base`, e.g. this month with the same month last year or Q3 with Q2
+    ///
+    /// rows compare the net of a category / account, so more spending shows as a negative change;
+    /// percentages are relative to the size of the base value
+    pub fn compare_periods(
+        &self,
+        userid: UserId,
+        base: impl Into<DateRange>,
+        current: impl Into<DateRange>,
+    ) -> PeriodCompare {
+        let (base, current) = (base.into(), current.into());
+        let b = self.range_stats(userid, base);
+        let c = self.range_stats(userid, current);
+        PeriodCompare {
+            base,
+            current,
+            income: Delta::new(b.income, c.income),
+            outcome: Delta::new(b.outcome, c.outcome),
+            net: Delta::new(b.summary, c.summary),
+            categories: compare_rows(&b.category, &c.category, |k| match k {
+                Some(id) => self.trans_categoryid2name(id),
+                None => "Uncategorized".to_string(),
+            }),
+            accounts: compare_rows(&b.account, &c.account, |k| self.trans_accountid2name(k)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, Utc};
+    use uuid::Uuid;
+
+    fn d(y: i32, m: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, day).unwrap()
+    }
+    fn account(id: AccountId, name: &str) -> Account {
+        Account {
+            id,
+            userid: Uuid::nil(),
+            name: name.to_string(),
+            account_type: AccountType::Checking,
+            currency: Currency::new("CAD"),
+            balance: 0.0,
+            create_date: Utc::now(),
+            credit: CreditTerms::default(),
+            loan: None,
+            cost_basis: None,
+            contact_email: None,
+        }
+    }
+    ///one single entry transaction per (date, account, category, amount)
+    fn ledger(moves: &[(NaiveDate, AccountId, Option<CategoryId>, f64)]) -> Ledger {
+        let account = vec![account(1, "Chequing"), account(2, "Savings")];
+        let category = ["Groceries", "Rent", "Salary", "Travel", "Gym"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| Category {
+                id: i as CategoryId + 1,
+                userid: Uuid::nil(),
+                name: name.to_string(),
+                parentid: None,
+                tax_line: None,
+            })
+            .collect();
+        let (mut transaction, mut entry) = (Vec::new(), Vec::new());
+        for (i, (date, accountid, categoryid, amount)) in moves.iter().enumerate() {
+            let tranid = Uuid::from_u128(i as u128 + 1);
+            transaction.push(Transaction {
+                id: tranid,
+                userid: Uuid::nil(),
+                occur_date: *date,
+                receiver: None,
+                desc: None,
+                create_date: Utc::now(),
+            });
+            entry.push(Entry {
+                id: i as i64 + 1,
+                userid: Uuid::nil(),
+                tranid,
+                accountid: *accountid,
+                categoryid: *categoryid,
+                amount: *amount,
+                desc: None,
+                status: EntryStatus::Cleared,
+            });
+        }
+        Ledger::new(Vec::new(), account, category, transaction, entry)
+    }
+    ///October 2024 against October 2025: groceries up, rent the same, a raise, a trip that
+    /// is new and a gym that is gone; interest moved from chequing to savings
+    fn october() -> PeriodCompare {
+        let ledger = ledger(&[
+            (d(2024, 10, 1), 1, Some(2), -1000.0),
+            (d(2024, 10, 8), 1, Some(1), -300.0),
+            (d(2024, 10, 15), 1, Some(3), 3000.0),
+            (d(2024, 10, 20), 1, Some(5), -40.0),
+            (d(2024, 10, 31), 1, None, 10.0),
+            (d(2025, 10, 1), 1, Some(2), -1000.0),
+            (d(2025, 10, 8), 1, Some(1), -360.0),
+            (d(2025, 10, 15), 1, Some(3), 3200.0),
+            (d(2025, 10, 22), 1, Some(4), -500.0),
+            (d(2025, 10, 31), 2, None, 10.0),
+            // outside both periods
+            (d(2025, 9, 30), 1, Some(1), -999.0),
+        ]);
+        ledger.compare_periods(
+            Uuid::nil(),
+            (d(2024, 10, 1), d(2024, 10, 31)),
+            (d(2025, 10, 1), d(2025, 10, 31)),
+        )
+    }
+    fn row<'a>(cmp: &'a PeriodCompare, name: &str) -> &'a CompareRow<Option<CategoryId>> {
+        cmp.categories.iter().find(|r| r.name == name).expect(name)
+    }
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn totals_compare_income_spending_and_net() {
+        let cmp = october();
+        assert!(close(cmp.income.base, 3010.0) && close(cmp.income.current, 3210.0));
+        assert!(close(cmp.outcome.base, -1340.0) && close(cmp.outcome.current, -1860.0));
+        assert!(close(cmp.net.base, 1670.0));
+        assert!(close(cmp.net.current, 1350.0));
+        assert!(close(cmp.net.change, -320.0));
+        assert!(close(cmp.net.percent.unwrap(), -320.0 / 1670.0 * 100.0));
+    }
+
+    #[test]
+    fn categories_keep_sign_and_percent_of_the_base() {
+        let cmp = october();
+        // more spending is a negative change, relative to the size of the base
+        let groceries = row(&cmp, "Groceries");
+        assert_eq!(groceries.status, CompareStatus::Both);
+        assert!(close(groceries.net.change, -60.0));
+        assert!(close(groceries.net.percent.unwrap(), -20.0));
+        let rent = row(&cmp, "Rent");
+        assert!(close(rent.net.change, 0.0));
+        assert!(close(rent.net.percent.unwrap(), 0.0));
+        let uncategorized = row(&cmp, "Uncategorized");
+        assert_eq!(uncategorized.key, None);
+        assert!(close(uncategorized.net.change, 0.0));
+    }
+
+    #[test]
+    fn new_and_gone_categories() {
+        let cmp = october();
+        let new: Vec<&str> = cmp.new_categories().map(|r| r.name.as_str()).collect();
+        let gone: Vec<&str> = cmp.gone_categories().map(|r| r.name.as_str()).collect();
+        assert_eq!(new, ["Travel"]);
+        assert_eq!(gone, ["Gym"]);
+        // nothing to compare a new category against
+        assert_eq!(row(&cmp, "Travel").net.percent, None);
+        assert!(close(row(&cmp, "Gym").net.percent.unwrap(), 100.0));
+    }
+
+    #[test]
+    fn movers_are_the_largest_changes_without_unchanged_rows() {
+        let cmp = october();
+        let movers: Vec<&str> = cmp.movers(10).iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(movers, ["Travel", "Salary", "Groceries", "Gym"]);
+        let top: Vec<&str> = cmp.movers(2).iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(top, ["Travel", "Salary"]);
+    }
+
+    #[test]
+    fn accounts_compare_their_net() {
+        let cmp = october();
+        let names: Vec<(&str, f64)> = cmp
+            .accounts
+            .iter()
+            .map(|r| (r.name.as_str(), r.net.change))
+            .collect();
+        // chequing lost the interest to savings on top of the changes above
+        assert_eq!(names[0].0, "Chequing");
+        assert!(close(names[0].1, -330.0));
+        assert_eq!(names[1].0, "Savings");
+        assert!(close(names[1].1, 10.0));
+        assert_eq!(cmp.accounts[1].status, CompareStatus::New);
+    }
+}
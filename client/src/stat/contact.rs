@@ -0,0 +1,127 @@
+use super::datatype::*;
+use super::ledger::*;
+use chrono::*;
+//contacts: people expenses are split with, a contact account's balance is what the person owes
+//(positive) or is owed (negative); a share is an entry on it, settling up a transfer to a real account
+
+///where things stand with one person
+#[derive(Debug, Clone)]
+pub struct ContactBalance {
+    pub accountid: AccountId,
+    pub name: String,
+    pub email: Option<String>,
+    pub balance: f64, // what they owe, negative when they are owed
+}
+impl ContactBalance {
+    ///what the person owes, zero when they are owed
+    pub fn receivable(&self) -> f64 {
+        self.balance.max(0.0)
+    }
+    ///what the person is owed, zero when they owe
+    pub fn payable(&self) -> f64 {
+        (0.0 - self.balance).max(0.0)
+    }
+}
+impl Ledger {
+    ///balance of every contact on `date`, by name; the same as the server's contacts report
+    pub fn contact_balances(&self, date: NaiveDate) -> Vec<ContactBalance> {
+        let mut contacts: Vec<ContactBalance> = self
+            .account
+            .iter()
+            .filter(|a| a.account_type.is_contact())
+            .map(|a| ContactBalance {
+                accountid: a.id,
+                name: a.name.clone(),
+                email: a.contact_email.clone(),
+                balance: (self.balance_on(a.id, date) * 100.0).round() / 100.0,
+            })
+            .collect();
+        contacts.sort_by_key(|c| c.name.to_lowercase());
+        contacts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn account(id: AccountId, name: &str, account_type: AccountType, balance: f64) -> Account {
+        Account {
+            id,
+            userid: Uuid::nil(),
+            name: name.to_string(),
+            account_type,
+            currency: Currency::new("CAD"),
+            balance,
+            create_date: Utc::now(),
+            credit: CreditTerms::default(),
+            loan: None,
+            cost_basis: None,
+            contact_email: None,
+        }
+    }
+    fn d(y: i32, m: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, day).unwrap()
+    }
+
+    #[test]
+    fn contacts_stay_out_of_accounts_and_net_worth() {
+        let accounts = vec![
+            account(1, "Chequing", AccountType::Checking, 500.0),
+            account(2, "Alex", AccountType::Contact, 0.0),
+        ];
+        // a 90 dinner paid from chequing, Alex owes their 30 share
+        let tranid = Uuid::from_u128(1);
+        let transaction = vec![Transaction {
+            id: tranid,
+            userid: Uuid::nil(),
+            occur_date: d(2025, 10, 5),
+            receiver: Some("Bistro".to_string()),
+            desc: None,
+            create_date: Utc::now(),
+        }];
+        let entry = [(1, 1, -90.0), (2, 2, 30.0)]
+            .into_iter()
+            .map(|(id, accountid, amount)| Entry {
+                id,
+                userid: Uuid::nil(),
+                tranid,
+                accountid,
+                categoryid: None,
+                amount,
+                desc: None,
+                status: EntryStatus::Cleared,
+            })
+            .collect();
+        let ledger = Ledger::new(Vec::new(), accounts, Vec::new(), transaction, entry);
+
+        let summary = ledger.all_account_summary_on(d(2025, 10, 31));
+        assert_eq!(summary.len(), 1);
+        assert_eq!(summary[0].accountid, 1);
+        assert_eq!(summary[0].balance, 410.0);
+        assert_eq!(ledger.all_account_summary().len(), 1);
+
+        let networth = ledger.networth_series(
+            Uuid::nil(),
+            d(2025, 10, 31),
+            d(2025, 10, 31),
+            Granularity::Daily,
+        );
+        assert_eq!(networth.assets, vec![410.0]);
+        assert_eq!(networth.net_worth, vec![410.0]);
+
+        let top = ledger.top_account(
+            Uuid::nil(),
+            (d(2025, 10, 1), d(2025, 10, 31)),
+            None,
+            10,
+            None,
+        );
+        assert_eq!(top.axis, vec![("Chequing".to_string(), 1)]);
+
+        let contacts = ledger.contact_balances(d(2025, 10, 31));
+        assert_eq!(contacts.len(), 1);
+        assert_eq!(contacts[0].receivable(), 30.0);
+    }
+}
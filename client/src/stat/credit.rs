@@ -0,0 +1,313 @@
+use super::datatype::*;
+use super::ledger::*;
+use super::period::*;
+use chrono::*;
+use std::collections::HashMap;
+//credit cards: statement balance, due date, minimum payment, utilization and interest estimate
+
+///share of the statement balance a minimum payment covers
+pub const MIN_PAYMENT_RATE: f64 = 0.03;
+///smallest minimum payment, unless the statement balance is smaller
+pub const MIN_PAYMENT_FLOOR: f64 = 10.0;
+
+///the last closed statement of a card
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy)]
+pub struct CreditStatement {
+    pub cycle: DateRange, // days the statement covers, ends on its closing day
+    pub balance: f64,     // owed at closing, negative when in credit
+    pub paid: f64,        // payments and refunds since closing
+    pub due_date: Option<NaiveDate>, // none without a due day
+    pub minimum: f64,     // minimum payment of the statement
+    pub remaining: f64,   // statement balance still to pay, 0 once paid in full
+    pub minimum_remaining: f64, // part of the minimum payment still to pay
+    pub interest: Option<f64>, // charged on the next statement if `remaining` isn't paid by the due date, needs an apr
+}
+impl CreditStatement {
+    pub fn is_paid(&self) -> bool {
+        self.remaining < 0.005
+    }
+    ///days from `today` to the due date, negative once it passed
+    pub fn days_left(&self, today: NaiveDate) -> Option<i64> {
+        self.due_date.map(|d| (d - today).num_days())
+    }
+}
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct CreditStatus {
+    pub accountid: AccountId,
+    pub name: String,
+    pub terms: CreditTerms,
+    pub owed: f64,                // current balance owed, negative when in credit
+    pub available: Option<f64>,   // limit - owed
+    pub utilization: Option<f64>, // owed / limit in percent
+    pub statement: Option<CreditStatement>, // needs a statement day
+}
+///minimum payment of a statement balance: MIN_PAYMENT_RATE of it, MIN_PAYMENT_FLOOR at least,
+///never more than the balance
+pub fn minimum_payment(balance: f64) -> f64 {
+    match balance > 0.0 {
+        true => (balance * MIN_PAYMENT_RATE)
+            .max(MIN_PAYMENT_FLOOR)
+            .min(balance),
+        false => 0.0,
+    }
+}
+impl Ledger {
+    ///card status of every credit account as of `today`, soonest due date first
+    pub fn credit_status(&self, today: NaiveDate) -> Vec<CreditStatus> {
+        let dates: HashMap<TransactionId, NaiveDate> = self
+            .transaction
+            .iter()
+            .map(|t| (t.id, t.occur_date))
+            .collect();
+        let mut result: Vec<CreditStatus> = self
+            .account
+            .iter()
+            .filter(|a| matches!(a.account_type, AccountType::Credit))
+            .map(|a| self.card_status(a, today, &dates))
+            .collect();
+        let due = |s: &CreditStatus| s.statement.and_then(|st| st.due_date);
+        result.sort_by(|a, b| match (due(a), due(b)) {
+            (Some(x), Some(y)) => x.cmp(&y),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => a.name.cmp(&b.name),
+        });
+        result
+    }
+    fn card_status(
+        &self,
+        acc: &Account,
+        today: NaiveDate,
+        dates: &HashMap<TransactionId, NaiveDate>,
+    ) -> CreditStatus {
+        let terms = acc.credit;
+        let owed = 0.0 - self.balance_on(acc.id, today);
+        CreditStatus {
+            accountid: acc.id,
+            name: acc.name.clone(),
+            terms,
+            owed,
+            available: terms.limit.map(|l| l - owed),
+            utilization: terms
+                .limit
+                .filter(|l| *l > 0.0)
+                .map(|l| owed.max(0.0) / l * 100.0),
+            statement: terms
+                .statement_day
+                .map(|day| self.last_statement(acc, day, today, dates)),
+        }
+    }
+    ///the statement that closed last on or before `today`
+    ///
+    /// the interest estimate is the average daily balance owed over that cycle at the apr,
+    /// the usual charge when a statement isn't paid in full and its grace period is lost
+    fn last_statement(
+        &self,
+        acc: &Account,
+        closing_day: u32,
+        today: NaiveDate,
+        dates: &HashMap<TransactionId, NaiveDate>,
+    ) -> CreditStatement {
+        let cycles = Period::StatementCycle { closing_day };
+        let current = cycles.containing(today);
+        let cycle = cycles.containing(current.from - Duration::days(1));
+        let balance = 0.0 - self.balance_on(acc.id, cycle.to);
+        let paid: f64 = self
+            .entry
+            .iter()
+            .filter(|e| e.accountid == acc.id && e.amount > 0.0)
+            .filter(|e| {
+                dates
+                    .get(&e.tranid)
+                    .is_some_and(|d| *d > cycle.to && *d <= today)
+            })
+            .fold(0.0, |sum, e| sum + e.amount);
+        // the first due day after closing
+        let due_date = acc.credit.due_day.map(|day| {
+            Period::StatementCycle { closing_day: day }
+                .containing(cycle.to + Duration::days(1))
+                .to
+        });
+        let minimum = minimum_payment(balance);
+        let interest = acc.credit.apr.map(|apr| {
+            let mut owed = -self.balance_on(acc.id, cycle.from - Duration::days(1));
+            let moves: HashMap<NaiveDate, f64> =
+                self.account_moves(acc.id, cycle).into_iter().collect();
+            let mut total = 0.0;
+            for day in cycle.from.iter_days().take_while(|d| *d <= cycle.to) {
+                owed -= moves.get(&day).copied().unwrap_or(0.0);
+                total += owed.max(0.0);
+            }
+            total * apr / 100.0 / 365.0
+        });
+        CreditStatement {
+            cycle,
+            balance,
+            paid,
+            due_date,
+            minimum,
+            remaining: (balance - paid).max(0.0),
+            minimum_remaining: (minimum - paid).max(0.0),
+            interest,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn d(y: i32, m: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, day).unwrap()
+    }
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+    fn card(id: AccountId, name: &str, credit: CreditTerms) -> Account {
+        Account {
+            id,
+            userid: Uuid::nil(),
+            name: name.to_string(),
+            account_type: AccountType::Credit,
+            currency: Currency::new("CAD"),
+            balance: 0.0,
+            create_date: Utc::now(),
+            credit,
+            loan: None,
+            cost_basis: None,
+            contact_email: None,
+        }
+    }
+    ///one single entry transaction per (date, account, amount), charges are negative
+    fn ledger(account: Vec<Account>, moves: &[(NaiveDate, AccountId, f64)]) -> Ledger {
+        let (mut transaction, mut entry) = (Vec::new(), Vec::new());
+        for (i, (date, accountid, amount)) in moves.iter().enumerate() {
+            let tranid = Uuid::from_u128(i as u128 + 1);
+            transaction.push(Transaction {
+                id: tranid,
+                userid: Uuid::nil(),
+                occur_date: *date,
+                receiver: None,
+                desc: None,
+                create_date: Utc::now(),
+            });
+            entry.push(Entry {
+                id: i as i64 + 1,
+                userid: Uuid::nil(),
+                tranid,
+                accountid: *accountid,
+                categoryid: None,
+                amount: *amount,
+                desc: None,
+                status: EntryStatus::Cleared,
+            });
+        }
+        Ledger::new(Vec::new(), account, Vec::new(), transaction, entry)
+    }
+    ///limit 2000, statement closes on the 20th, due on the 15th, 20% apr
+    const VISA: CreditTerms = CreditTerms {
+        limit: Some(2000.0),
+        statement_day: Some(20),
+        due_day: Some(15),
+        apr: Some(20.0),
+    };
+    ///300 and 200 charged in the August 21 - September 20 cycle, 100 after it closed,
+    /// then a 150 payment
+    fn visa(extra: &[(NaiveDate, AccountId, f64)]) -> Ledger {
+        let mut moves = vec![
+            (d(2025, 8, 25), 1, -300.0),
+            (d(2025, 9, 10), 1, -200.0),
+            (d(2025, 9, 25), 1, -100.0),
+            (d(2025, 10, 1), 1, 150.0),
+        ];
+        moves.extend_from_slice(extra);
+        ledger(vec![card(1, "Visa", VISA)], &moves)
+    }
+    const TODAY: NaiveDate = NaiveDate::from_ymd_opt(2025, 10, 10).unwrap();
+
+    #[test]
+    fn minimum_payment_is_a_share_with_a_floor_capped_by_the_balance() {
+        assert!(close(minimum_payment(1000.0), 30.0));
+        assert!(close(minimum_payment(100.0), MIN_PAYMENT_FLOOR));
+        assert!(close(minimum_payment(4.5), 4.5));
+        assert!(close(minimum_payment(0.0), 0.0));
+        assert!(close(minimum_payment(-25.0), 0.0));
+    }
+
+    #[test]
+    fn owed_available_and_utilization_use_the_current_balance() {
+        let status = visa(&[]).credit_status(TODAY);
+        let visa = &status[0];
+        assert!(close(visa.owed, 450.0));
+        assert!(close(visa.available.unwrap(), 1550.0));
+        assert!(close(visa.utilization.unwrap(), 22.5));
+    }
+
+    #[test]
+    fn statement_of_the_last_closed_cycle() {
+        let status = visa(&[]).credit_status(TODAY);
+        let st = status[0].statement.expect("statement");
+        assert_eq!(st.cycle, DateRange::new(d(2025, 8, 21), d(2025, 9, 20)));
+        // the charge after closing waits for the next statement
+        assert!(close(st.balance, 500.0));
+        assert!(close(st.paid, 150.0));
+        assert_eq!(st.due_date, Some(d(2025, 10, 15)));
+        assert_eq!(st.days_left(TODAY), Some(5));
+        assert!(close(st.minimum, 15.0));
+        assert!(close(st.minimum_remaining, 0.0));
+        assert!(close(st.remaining, 350.0));
+        assert!(!st.is_paid());
+    }
+
+    #[test]
+    fn interest_is_the_average_daily_balance_of_the_cycle_at_the_apr() {
+        let status = visa(&[]).credit_status(TODAY);
+        let interest = status[0].statement.unwrap().interest.unwrap();
+        // 0 for 4 days, 300 for 16 days, 500 for 11 days
+        assert!(close(
+            interest,
+            (300.0 * 16.0 + 500.0 * 11.0) * 0.20 / 365.0
+        ));
+    }
+
+    #[test]
+    fn paid_in_full_once_payments_cover_the_statement() {
+        let status = visa(&[(d(2025, 10, 5), 1, 400.0)]).credit_status(TODAY);
+        let st = status[0].statement.unwrap();
+        assert!(close(st.paid, 550.0));
+        assert!(close(st.remaining, 0.0));
+        assert!(st.is_paid());
+        // the charge made after closing is still owed
+        assert!(close(status[0].owed, 50.0));
+        assert!(close(status[0].utilization.unwrap(), 2.5));
+    }
+
+    #[test]
+    fn cards_without_terms_sort_after_the_ones_due() {
+        let mut account = vec![
+            card(2, "Amex", CreditTerms::default()),
+            card(1, "Visa", VISA),
+        ];
+        account.push(card(
+            3,
+            "Store card",
+            CreditTerms {
+                statement_day: Some(5),
+                due_day: Some(28),
+                ..CreditTerms::default()
+            },
+        ));
+        let ledger = ledger(account, &[(d(2025, 9, 1), 3, -80.0)]);
+        let status = ledger.credit_status(TODAY);
+        let names: Vec<&str> = status.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["Visa", "Store card", "Amex"]);
+        let store = &status[1];
+        assert_eq!(store.available, None);
+        assert_eq!(store.utilization, None);
+        assert_eq!(store.statement.unwrap().interest, None);
+        assert!(status[2].statement.is_none());
+    }
+}
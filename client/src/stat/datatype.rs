@@ -6,12 +6,21 @@ pub type AccountId = i64;
 pub type CategoryId = i64;
 pub type TransactionId = Uuid;
 pub type EntryId = i64;
+pub type GoalId = i64;
+pub type SecurityId = i64;
+pub type TradeId = i64;
+pub type AttachmentId = i64;
+pub type WorkspaceId = Uuid;
+pub type TokenId = i64;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum AccountType {
     Checking,
     Cash,
     Credit,
+    Loan,
+    Investment,
+    Contact,
     Other(String),
 }
 impl From<String> for AccountType {
@@ -20,6 +29,9 @@ impl From<String> for AccountType {
             "checking" => AccountType::Checking,
             "credit" => AccountType::Credit,
             "cash" => AccountType::Cash,
+            "loan" => AccountType::Loan,
+            "investment" => AccountType::Investment,
+            "contact" => AccountType::Contact,
             _ => AccountType::Other(input),
         }
     }
@@ -31,9 +43,20 @@ impl AccountType {
             AccountType::Checking => "checking",
             AccountType::Credit => "credit",
             AccountType::Cash => "cash",
+            AccountType::Loan => "loan",
+            AccountType::Investment => "investment",
+            AccountType::Contact => "contact",
             AccountType::Other(_) => "other",
         }
     }
+    ///credit and loan balances are money owed, they count against net worth
+    pub fn is_liability(&self) -> bool {
+        matches!(self, AccountType::Credit | AccountType::Loan)
+    }
+    ///a contact balance is money a person owes or is owed, it lives on the People screen only
+    pub fn is_contact(&self) -> bool {
+        matches!(self, AccountType::Contact)
+    }
 }
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Currency(pub String);
@@ -58,6 +81,217 @@ pub struct Account {
     pub currency: Currency,
     pub balance: f64,
     pub create_date: DateTime<Utc>,
+    pub credit: CreditTerms, // credit accounts only, all unknown otherwise
+    pub loan: Option<LoanTerms>, // loan accounts only
+    pub cost_basis: Option<CostBasis>, // investment accounts only
+    pub contact_email: Option<String>, // contacts only
+}
+///credit card terms, any of them may be unknown
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct CreditTerms {
+    pub limit: Option<f64>,
+    pub statement_day: Option<u32>, // statement closes on this day, the last day in shorter months
+    pub due_day: Option<u32>,       // payment due on the first such day after the statement closed
+    pub apr: Option<f64>,           // percent per year
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LoanFrequency {
+    Monthly,
+    Biweekly,
+    Weekly,
+}
+impl LoanFrequency {
+    pub const ALL: [LoanFrequency; 3] = [
+        LoanFrequency::Monthly,
+        LoanFrequency::Biweekly,
+        LoanFrequency::Weekly,
+    ];
+    pub fn from_cloud(input: &str) -> Option<Self> {
+        match input {
+            "monthly" => Some(LoanFrequency::Monthly),
+            "biweekly" => Some(LoanFrequency::Biweekly),
+            "weekly" => Some(LoanFrequency::Weekly),
+            _ => None,
+        }
+    }
+    pub fn to_cloud(self) -> &'static str {
+        match self {
+            LoanFrequency::Monthly => "monthly",
+            LoanFrequency::Biweekly => "biweekly",
+            LoanFrequency::Weekly => "weekly",
+        }
+    }
+    pub fn periods_per_year(&self) -> u32 {
+        match self {
+            LoanFrequency::Monthly => 12,
+            LoanFrequency::Biweekly => 26,
+            LoanFrequency::Weekly => 52,
+        }
+    }
+}
+///loan or mortgage terms, the server keeps them all or none
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LoanTerms {
+    pub principal: f64,
+    pub rate: f64, // percent per year
+    pub term_months: u32,
+    pub frequency: LoanFrequency,
+    pub first_payment: NaiveDate,
+}
+///which units a sell takes its cost from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CostBasis {
+    #[default]
+    Fifo, // the oldest lots first
+    Average, // the average cost of every unit held
+}
+impl CostBasis {
+    pub fn from_cloud(input: &str) -> Option<Self> {
+        match input {
+            "fifo" => Some(CostBasis::Fifo),
+            "average" => Some(CostBasis::Average),
+            _ => None,
+        }
+    }
+    pub fn to_cloud(self) -> &'static str {
+        match self {
+            CostBasis::Fifo => "fifo",
+            CostBasis::Average => "average",
+        }
+    }
+}
+///a stock, fund or ETF held in investment accounts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Security {
+    pub id: SecurityId,
+    pub userid: UserId,
+    pub symbol: String,
+    pub name: Option<String>,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TradeKind {
+    Buy,
+    Sell,
+    Dividend,
+    Split,
+}
+impl TradeKind {
+    pub const ALL: [TradeKind; 4] = [
+        TradeKind::Buy,
+        TradeKind::Sell,
+        TradeKind::Dividend,
+        TradeKind::Split,
+    ];
+    pub fn from_cloud(input: &str) -> Option<Self> {
+        match input {
+            "buy" => Some(TradeKind::Buy),
+            "sell" => Some(TradeKind::Sell),
+            "dividend" => Some(TradeKind::Dividend),
+            "split" => Some(TradeKind::Split),
+            _ => None,
+        }
+    }
+    pub fn to_cloud(self) -> &'static str {
+        match self {
+            TradeKind::Buy => "buy",
+            TradeKind::Sell => "sell",
+            TradeKind::Dividend => "dividend",
+            TradeKind::Split => "split",
+        }
+    }
+}
+///a trade of an investment account, its cash side is the transaction `tranid`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Trade {
+    pub id: TradeId,
+    pub userid: UserId,
+    pub accountid: AccountId,
+    pub securityid: SecurityId,
+    pub tranid: TransactionId,
+    pub kind: TradeKind,
+    pub date: NaiveDate,
+    pub quantity: f64, // units bought or sold, new units per old unit for a split
+    pub price: f64,    // per unit
+    pub fees: f64,
+    pub amount: f64, // cash in (+) or out (-) of the account
+}
+///a receipt or document stored on the server for a transaction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub id: AttachmentId,
+    pub userid: UserId,
+    pub tranid: TransactionId,
+    pub filename: String,
+    pub content_type: String, // detected by the server: an image type or application/pdf
+    pub size: i64,            // bytes
+    pub sha256: String,       // hex
+    pub create_date: DateTime<Utc>,
+}
+///what a member may do in a workspace
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WorkspaceRole {
+    Owner,  // the ledger and the members
+    Editor, // the ledger
+    Viewer, // reads only
+}
+impl From<String> for WorkspaceRole {
+    fn from(input: String) -> Self {
+        match input.to_lowercase().as_str() {
+            "owner" => WorkspaceRole::Owner,
+            "editor" => WorkspaceRole::Editor,
+            // least access for anything unknown, the server decides anyway
+            _ => WorkspaceRole::Viewer,
+        }
+    }
+}
+impl WorkspaceRole {
+    pub fn to_cloud(self) -> &'static str {
+        match self {
+            WorkspaceRole::Owner => "owner",
+            WorkspaceRole::Editor => "editor",
+            WorkspaceRole::Viewer => "viewer",
+        }
+    }
+    ///viewer, editor, owner, viewer...
+    pub fn next(self) -> Self {
+        match self {
+            WorkspaceRole::Viewer => WorkspaceRole::Editor,
+            WorkspaceRole::Editor => WorkspaceRole::Owner,
+            WorkspaceRole::Owner => WorkspaceRole::Viewer,
+        }
+    }
+}
+///a ledger shared by its members, everyone has a personal one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Workspace {
+    pub id: WorkspaceId,
+    pub name: String,
+    pub role: WorkspaceRole, // of the logged in user
+    pub personal: bool,      // the user's own, same id as the user
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceMember {
+    pub userid: UserId,
+    pub email: String,
+    pub role: WorkspaceRole,
+}
+///a personal access token for scripts, the token itself is only known right after creating it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: TokenId,
+    pub name: String,
+    pub prefix: String,      // first characters of the token
+    pub scopes: Vec<String>, // read:ledger, write:transactions, admin
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked: bool,
+}
+///an imported closing price
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SecurityPrice {
+    pub securityid: SecurityId,
+    pub date: NaiveDate,
+    pub price: f64,
 }
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Category {
@@ -65,6 +299,69 @@ pub struct Category {
     pub userid: UserId,
     pub name: String,
     pub parentid: Option<CategoryId>,
+    pub tax_line: Option<TaxLine>, // none: the parent's, if any
+}
+///line of the tax return a category's entries are reported on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum TaxLine {
+    Medical,
+    Charitable,
+    Childcare,
+    Education,
+    BusinessIncome,
+    BusinessExpense,
+    RentalIncome,
+    RentalExpense,
+    InvestmentIncome,
+}
+impl TaxLine {
+    pub const ALL: [TaxLine; 9] = [
+        TaxLine::Medical,
+        TaxLine::Charitable,
+        TaxLine::Childcare,
+        TaxLine::Education,
+        TaxLine::BusinessIncome,
+        TaxLine::BusinessExpense,
+        TaxLine::RentalIncome,
+        TaxLine::RentalExpense,
+        TaxLine::InvestmentIncome,
+    ];
+    pub fn from_cloud(input: &str) -> Option<Self> {
+        TaxLine::ALL.into_iter().find(|l| l.to_cloud() == input)
+    }
+    pub fn to_cloud(self) -> &'static str {
+        match self {
+            TaxLine::Medical => "medical",
+            TaxLine::Charitable => "charitable",
+            TaxLine::Childcare => "childcare",
+            TaxLine::Education => "education",
+            TaxLine::BusinessIncome => "business_income",
+            TaxLine::BusinessExpense => "business_expense",
+            TaxLine::RentalIncome => "rental_income",
+            TaxLine::RentalExpense => "rental_expense",
+            TaxLine::InvestmentIncome => "investment_income",
+        }
+    }
+    pub fn name(self) -> &'static str {
+        match self {
+            TaxLine::Medical => "Medical expenses",
+            TaxLine::Charitable => "Charitable donations",
+            TaxLine::Childcare => "Childcare expenses",
+            TaxLine::Education => "Tuition and education",
+            TaxLine::BusinessIncome => "Business income",
+            TaxLine::BusinessExpense => "Business expenses",
+            TaxLine::RentalIncome => "Rental income",
+            TaxLine::RentalExpense => "Rental expenses",
+            TaxLine::InvestmentIncome => "Investment income",
+        }
+    }
+    ///income lines add up money received, the others money spent
+    pub fn is_income(self) -> bool {
+        matches!(
+            self,
+            TaxLine::BusinessIncome | TaxLine::RentalIncome | TaxLine::InvestmentIncome
+        )
+    }
 }
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Transaction {
@@ -76,6 +373,15 @@ pub struct Transaction {
     pub create_date: DateTime<Utc>,
 }
 
+///reconciliation state of an entry, reconciled entries are locked by the server
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryStatus {
+    #[default]
+    Uncleared,
+    Cleared,
+    Reconciled,
+}
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Entry {
     pub id: EntryId,
@@ -85,4 +391,17 @@ pub struct Entry {
     pub categoryid: Option<CategoryId>,
     pub amount: f64,
     pub desc: Option<String>,
+    pub status: EntryStatus,
+}
+///savings goal, progress counts the linked accounts' balances or the entries tagged #tag
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Goal {
+    pub id: GoalId,
+    pub userid: UserId,
+    pub name: String,
+    pub target: f64,
+    pub deadline: Option<NaiveDate>,
+    pub accounts: Vec<AccountId>,
+    pub tag: Option<String>, // without the #, lowercase
+    pub create_date: DateTime<Utc>,
 }
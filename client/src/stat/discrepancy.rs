@@ -0,0 +1,470 @@
+use super::datatype::*;
+use super::ledger::*;
+use super::period::*;
+use chrono::*;
+use std::collections::{HashMap, HashSet};
+//search for small groups of entries that explain a reconcile difference
+
+///how fixing one entry moves the balance being reconciled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixAction {
+    Remove,   // in the ledger but not on the statement
+    Tick,     // on the statement but not ticked yet
+    Untick,   // ticked but not on the statement
+    FlipSign, // entered with the wrong sign
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiscrepancyKind {
+    Single,
+    Combination,
+    Duplicate,
+    SignFlip,
+}
+#[derive(Debug, Clone)]
+pub struct DiscrepancyStep {
+    pub entry: Entry,
+    pub date: NaiveDate,
+    pub payee: String,
+    pub action: FixAction,
+    pub effect: f64, // change of the balance if this step is applied
+    pub duplicate_of: Option<EntryId>, // earlier entry with same amount, payee and nearly same date
+}
+///one way to close the gap, the effects of all steps sum to the difference
+#[derive(Debug, Clone)]
+pub struct DiscrepancyCandidate {
+    pub kind: DiscrepancyKind,
+    pub steps: Vec<DiscrepancyStep>,
+    pub score: f64, // plausibility in 0..=1
+    pub explanation: String,
+}
+///bounds of the subset-sum search
+#[derive(Debug, Clone, Copy)]
+pub struct DiscrepancySearch {
+    pub max_entries: usize, // largest combination tried, at most 3
+    pub max_pool: usize,    // entries considered, the ones closest to the statement end first
+    pub top_k: usize,
+    pub duplicate_days: i64, // max days between an entry and its duplicate
+}
+impl Default for DiscrepancySearch {
+    fn default() -> Self {
+        DiscrepancySearch {
+            max_entries: 3,
+            max_pool: 200,
+            top_k: 10,
+            duplicate_days: 3,
+        }
+    }
+}
+
+fn cents(v: f64) -> i64 {
+    (v * 100.0).round() as i64
+}
+fn describe_step(step: &DiscrepancyStep) -> String {
+    let head = format!(
+        "#{} {} {:.2} on {}",
+        step.entry.id,
+        if step.payee.is_empty() {
+            "-"
+        } else {
+            step.payee.as_str()
+        },
+        step.entry.amount,
+        step.date
+    );
+    if let Some(orig) = step.duplicate_of {
+        return format!("{head} looks like a duplicate of #{orig}");
+    }
+    match step.action {
+        FixAction::Remove => format!("{head} is in the ledger but not on the statement"),
+        FixAction::Tick => format!("{head} is on the statement but not ticked"),
+        FixAction::Untick => format!("{head} is ticked but not on the statement"),
+        FixAction::FlipSign => format!(
+            "{head} has the wrong sign, should be {:.2}",
+            -step.entry.amount
+        ),
+    }
+}
+///plausibility of one candidate
+///
+/// fewer entries is better, duplicates are the most common mistake, sign flips are rarer,
+/// entries close to the statement end are often just posted late
+fn score_steps(steps: &[DiscrepancyStep], period_end: NaiveDate) -> f64 {
+    let mut score: f64 = match steps.len() {
+        1 => 1.0,
+        2 => 0.55,
+        _ => 0.3,
+    };
+    for step in steps {
+        score *= if step.duplicate_of.is_some() {
+            1.0
+        } else {
+            match step.action {
+                FixAction::FlipSign => 0.7,
+                _ => 0.85,
+            }
+        };
+        if (period_end - step.date).num_days().abs() <= 5 {
+            score *= 1.1;
+        }
+    }
+    score.min(1.0)
+}
+fn build_candidate(steps: Vec<DiscrepancyStep>, period_end: NaiveDate) -> DiscrepancyCandidate {
+    let kind = match steps.as_slice() {
+        [s] if s.duplicate_of.is_some() => DiscrepancyKind::Duplicate,
+        [s] if s.action == FixAction::FlipSign => DiscrepancyKind::SignFlip,
+        [_] => DiscrepancyKind::Single,
+        _ => DiscrepancyKind::Combination,
+    };
+    let parts: Vec<String> = steps.iter().map(describe_step).collect();
+    let explanation = if steps.len() == 1 {
+        parts.join("")
+    } else {
+        let total: f64 = steps.iter().map(|s| s.effect).sum();
+        format!(
+            "{} entries together ({:+.2}): {}",
+            steps.len(),
+            total,
+            parts.join("; ")
+        )
+    };
+    DiscrepancyCandidate {
+        kind,
+        score: score_steps(&steps, period_end),
+        steps,
+        explanation,
+    }
+}
+///bounded subset-sum over the effects of `pool`
+///
+/// amounts are compared in cents, so only exact explanations are returned. Two steps on the same
+/// entry (e.g. remove and flip) never appear together
+pub fn search_discrepancies(
+    pool: Vec<DiscrepancyStep>,
+    difference: f64,
+    period_end: NaiveDate,
+    cfg: DiscrepancySearch,
+) -> Vec<DiscrepancyCandidate> {
+    let target = cents(difference);
+    if target == 0 || cfg.top_k == 0 {
+        return Vec::new();
+    }
+    let mut pool = pool;
+    pool.sort_by_key(|s| ((period_end - s.date).num_days().abs(), s.entry.id));
+    pool.truncate(cfg.max_pool);
+    // sorted by effect so the last member of a combination is a binary search
+    let mut sorted: Vec<(i64, usize)> = pool
+        .iter()
+        .enumerate()
+        .map(|(idx, s)| (cents(s.effect), idx))
+        .filter(|(c, _)| *c != 0)
+        .collect();
+    sorted.sort();
+    let n = sorted.len();
+    let after = |from: usize, want: i64| -> std::ops::Range<usize> {
+        let lo = from + sorted[from..].partition_point(|(c, _)| *c < want);
+        let hi = from + sorted[from..].partition_point(|(c, _)| *c <= want);
+        lo..hi
+    };
+    let distinct = |idx: &[usize]| -> bool {
+        let ids: HashSet<EntryId> = idx.iter().map(|&i| pool[i].entry.id).collect();
+        ids.len() == idx.len()
+    };
+    // cap on raw matches, small combinations are found first
+    const MAX_MATCHES: usize = 500;
+    let mut found: Vec<Vec<usize>> = Vec::new();
+    for p in after(0, target) {
+        found.push(vec![sorted[p].1]);
+    }
+    if cfg.max_entries >= 2 {
+        for i in 0..n {
+            for p in after(i + 1, target - sorted[i].0) {
+                let idx = vec![sorted[i].1, sorted[p].1];
+                if distinct(&idx) {
+                    found.push(idx);
+                }
+            }
+            if found.len() >= MAX_MATCHES {
+                break;
+            }
+        }
+    }
+    if cfg.max_entries >= 3 {
+        'outer: for i in 0..n {
+            for j in i + 1..n {
+                for p in after(j + 1, target - sorted[i].0 - sorted[j].0) {
+                    let idx = vec![sorted[i].1, sorted[j].1, sorted[p].1];
+                    if distinct(&idx) {
+                        found.push(idx);
+                    }
+                }
+                if found.len() >= MAX_MATCHES {
+                    break 'outer;
+                }
+            }
+        }
+    }
+    let mut result: Vec<DiscrepancyCandidate> = found
+        .into_iter()
+        .map(|idx| {
+            let mut steps: Vec<DiscrepancyStep> = idx.iter().map(|&i| pool[i].clone()).collect();
+            steps.sort_by_key(|s| (s.date, s.entry.id));
+            build_candidate(steps, period_end)
+        })
+        .collect();
+    result.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then(a.steps.len().cmp(&b.steps.len()))
+    });
+    result.truncate(cfg.top_k);
+    result
+}
+impl Ledger {
+    fn payee_of(&self, txid: TransactionId) -> String {
+        self.transaction
+            .iter()
+            .find(|t| t.id == txid)
+            .and_then(|t| t.receiver.clone())
+            .unwrap_or_default()
+    }
+    ///mark entries that repeat an earlier one: same account, amount and payee, dates close together
+    ///
+    /// only steps that take an entry out of the balance (remove / untick) can be duplicates
+    fn mark_duplicates(pool: &mut [DiscrepancyStep], duplicate_days: i64) {
+        let mut order: Vec<usize> = (0..pool.len())
+            .filter(|&i| matches!(pool[i].action, FixAction::Remove | FixAction::Untick))
+            .collect();
+        order.sort_by_key(|&i| (pool[i].date, pool[i].entry.id));
+        let mut seen: HashMap<(AccountId, i64, String), Vec<usize>> = HashMap::new();
+        for i in order {
+            let key = (
+                pool[i].entry.accountid,
+                cents(pool[i].entry.amount),
+                pool[i].payee.to_lowercase(),
+            );
+            let earlier = seen.entry(key).or_default();
+            let date = pool[i].date;
+            if let Some(&orig) = earlier
+                .iter()
+                .find(|&&j| (date - pool[j].date).num_days() <= duplicate_days)
+            {
+                pool[i].duplicate_of = Some(pool[orig].entry.id);
+            }
+            earlier.push(i);
+        }
+    }
+    fn discrepancy_step(
+        &self,
+        entry: &Entry,
+        date: NaiveDate,
+        action: FixAction,
+    ) -> DiscrepancyStep {
+        let effect = match action {
+            FixAction::Remove | FixAction::Untick => -entry.amount,
+            FixAction::Tick => entry.amount,
+            FixAction::FlipSign => -2.0 * entry.amount,
+        };
+        DiscrepancyStep {
+            entry: entry.clone(),
+            date,
+            payee: self.payee_of(entry.tranid),
+            action,
+            effect,
+            duplicate_of: None,
+        }
+    }
+    ///explain `difference` (external - internal) of the quick check over `range`
+    ///
+    /// every entry in range may be missing from the statement or have the wrong sign
+    pub fn explain_difference(
+        &self,
+        userid: UserId,
+        accountid: Option<AccountId>,
+        range: impl Into<DateRange>,
+        difference: f64,
+        cfg: DiscrepancySearch,
+    ) -> Vec<DiscrepancyCandidate> {
+        let range = range.into();
+        let dates: HashMap<TransactionId, NaiveDate> = self
+            .transaction
+            .iter()
+            .filter(|t| t.userid == userid)
+            .filter(|t| range.contains(t.occur_date))
+            .map(|t| (t.id, t.occur_date))
+            .collect();
+        let mut pool = Vec::new();
+        for e in &self.entry {
+            if e.userid != userid || accountid.is_some_and(|acc| acc != e.accountid) {
+                continue;
+            }
+            if let Some(&date) = dates.get(&e.tranid) {
+                pool.push(self.discrepancy_step(e, date, FixAction::Remove));
+                pool.push(self.discrepancy_step(e, date, FixAction::FlipSign));
+            }
+        }
+        Self::mark_duplicates(&mut pool, cfg.duplicate_days);
+        search_discrepancies(pool, difference, range.to, cfg)
+    }
+    ///explain the difference of an open statement reconciliation
+    ///
+    /// uncleared entries may need a tick, cleared ones may not be on the statement or have the wrong sign
+    pub fn explain_statement(
+        &self,
+        view: &StatementReconcile,
+        cfg: DiscrepancySearch,
+    ) -> Vec<DiscrepancyCandidate> {
+        let mut pool = Vec::new();
+        for (date, e) in &view.entries {
+            match e.status {
+                EntryStatus::Uncleared => {
+                    pool.push(self.discrepancy_step(e, *date, FixAction::Tick));
+                }
+                EntryStatus::Cleared => {
+                    pool.push(self.discrepancy_step(e, *date, FixAction::Untick));
+                    pool.push(self.discrepancy_step(e, *date, FixAction::FlipSign));
+                }
+                EntryStatus::Reconciled => {}
+            }
+        }
+        // a doubled entry only matters for the balance once both copies are ticked
+        Self::mark_duplicates(&mut pool, cfg.duplicate_days);
+        search_discrepancies(pool, view.difference, view.statement_date, cfg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, d).unwrap()
+    }
+    fn removal(id: EntryId, amount: f64, date: NaiveDate) -> DiscrepancyStep {
+        DiscrepancyStep {
+            entry: Entry {
+                id,
+                userid: UserId::nil(),
+                tranid: TransactionId::nil(),
+                accountid: 1,
+                categoryid: None,
+                amount,
+                desc: None,
+                status: EntryStatus::Cleared,
+            },
+            date,
+            payee: format!("payee {id}"),
+            action: FixAction::Remove,
+            effect: -amount,
+            duplicate_of: None,
+        }
+    }
+    fn ids(c: &DiscrepancyCandidate) -> Vec<EntryId> {
+        let mut ids: Vec<EntryId> = c.steps.iter().map(|s| s.entry.id).collect();
+        ids.sort();
+        ids
+    }
+    fn pool() -> Vec<DiscrepancyStep> {
+        vec![
+            removal(1, -10.00, day(2)),
+            removal(2, -25.50, day(9)),
+            removal(3, -4.49, day(17)),
+            removal(4, -61.20, day(24)),
+        ]
+    }
+
+    #[test]
+    fn exact_single_entry_is_found_first() {
+        let found = search_discrepancies(pool(), 25.50, day(31), DiscrepancySearch::default());
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].kind, DiscrepancyKind::Single);
+        assert_eq!(ids(&found[0]), vec![2]);
+        assert!(found[0].explanation.contains("#2"));
+    }
+
+    #[test]
+    fn combinations_of_two_and_three_entries() {
+        let found = search_discrepancies(pool(), 35.50, day(31), DiscrepancySearch::default());
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].kind, DiscrepancyKind::Combination);
+        assert_eq!(ids(&found[0]), vec![1, 2]);
+
+        let found = search_discrepancies(pool(), 39.99, day(31), DiscrepancySearch::default());
+        assert_eq!(found.len(), 1);
+        assert_eq!(ids(&found[0]), vec![1, 2, 3]);
+        let total: f64 = found[0].steps.iter().map(|s| s.effect).sum();
+        assert_eq!(cents(total), 3999);
+    }
+
+    #[test]
+    fn no_match_and_no_difference_give_nothing() {
+        let cfg = DiscrepancySearch::default();
+        assert!(search_discrepancies(pool(), 1.23, day(31), cfg).is_empty());
+        assert!(search_discrepancies(pool(), 0.0, day(31), cfg).is_empty());
+        // cents are compared exactly
+        assert!(search_discrepancies(pool(), 25.51, day(31), cfg).is_empty());
+    }
+
+    #[test]
+    fn same_entry_never_appears_twice() {
+        let mut flip = removal(1, -10.00, day(2));
+        flip.action = FixAction::FlipSign;
+        flip.effect = 20.00;
+        let found = search_discrepancies(
+            vec![removal(1, -10.00, day(2)), flip],
+            30.00,
+            day(31),
+            DiscrepancySearch::default(),
+        );
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn size_pool_and_top_k_limits() {
+        let two = DiscrepancySearch {
+            max_entries: 2,
+            ..DiscrepancySearch::default()
+        };
+        assert!(search_discrepancies(pool(), 39.99, day(31), two).is_empty());
+
+        // entry 1 is the farthest from the statement end, a pool of 3 leaves it out
+        let small_pool = DiscrepancySearch {
+            max_pool: 3,
+            ..DiscrepancySearch::default()
+        };
+        assert!(search_discrepancies(pool(), 10.00, day(31), small_pool).is_empty());
+        assert_eq!(
+            search_discrepancies(pool(), 25.50, day(31), small_pool).len(),
+            1
+        );
+
+        // five entries of 5.00 explain 5.00 alone, 10.00 in pairs and 15.00 in triples
+        let fives: Vec<DiscrepancyStep> = (1..=5).map(|id| removal(id, -5.00, day(10))).collect();
+        let cfg = DiscrepancySearch::default();
+        assert_eq!(
+            search_discrepancies(fives.clone(), 5.00, day(31), cfg).len(),
+            5
+        );
+        assert_eq!(
+            search_discrepancies(fives.clone(), 10.00, day(31), cfg).len(),
+            10
+        );
+        let top = DiscrepancySearch { top_k: 3, ..cfg };
+        let found = search_discrepancies(fives.clone(), 15.00, day(31), top);
+        assert_eq!(found.len(), 3);
+        assert!(found.iter().all(|c| c.steps.len() == 3));
+        let none = DiscrepancySearch { top_k: 0, ..cfg };
+        assert!(search_discrepancies(fives, 5.00, day(31), none).is_empty());
+    }
+
+    #[test]
+    fn single_entries_rank_above_combinations() {
+        let mut steps = pool();
+        steps.push(removal(5, -35.50, day(20)));
+        let found = search_discrepancies(steps, 35.50, day(31), DiscrepancySearch::default());
+        assert_eq!(found.len(), 2);
+        assert_eq!(ids(&found[0]), vec![5]);
+        assert_eq!(ids(&found[1]), vec![1, 2]);
+        assert!(found[0].score > found[1].score);
+    }
+}
@@ -0,0 +1,652 @@
+use super::datatype::*;
+use super::ledger::*;
+use super::period::*;
+use super::statement::normalize_payee;
+use chrono::*;
+use std::collections::{HashMap, HashSet};
+
+///account, category and month of a past recurring charge
+type MonthKey = (AccountId, Option<CategoryId>, (i32, u32));
+//cash-flow forecast: recurring patterns, seasonal category averages and scheduled entries
+
+///how often a recurring pattern repeats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cadence {
+    Weekly,
+    Monthly,
+    Yearly,
+}
+impl Cadence {
+    ///gap in days between two charges that still counts as this cadence
+    fn accepts(&self, days: i64) -> bool {
+        match self {
+            Cadence::Weekly => (6..=8).contains(&days),
+            Cadence::Monthly => (26..=35).contains(&days),
+            Cadence::Yearly => (350..=380).contains(&days),
+        }
+    }
+    ///`n`-th charge after `date`, months keep the day of month of `date`
+    pub fn nth_after(&self, date: NaiveDate, n: u32) -> NaiveDate {
+        match self {
+            Cadence::Weekly => date + Duration::weeks(n as i64),
+            Cadence::Monthly => date.checked_add_months(Months::new(n)).unwrap_or(date),
+            Cadence::Yearly => date.checked_add_months(Months::new(12 * n)).unwrap_or(date),
+        }
+    }
+    ///charges in a year
+    pub fn per_year(&self) -> f64 {
+        match self {
+            Cadence::Weekly => 52.0,
+            Cadence::Monthly => 12.0,
+            Cadence::Yearly => 1.0,
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            Cadence::Weekly => "Weekly",
+            Cadence::Monthly => "Monthly",
+            Cadence::Yearly => "Yearly",
+        }
+    }
+}
+///entries of one payee on one account that repeat at a fixed cadence with similar amounts
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct RecurringPattern {
+    pub accountid: AccountId,
+    pub categoryid: Option<CategoryId>, // most used category of the charges
+    pub payee: String,
+    pub cadence: Cadence,
+    pub amount: f64,                    // average of the last three charges
+    pub spread: f64,                    // standard deviation of all charges
+    pub charges: Vec<(NaiveDate, f64)>, // oldest first
+    pub entries: Vec<EntryId>,
+}
+impl RecurringPattern {
+    pub fn last_date(&self) -> NaiveDate {
+        self.charges.last().map(|c| c.0).unwrap_or(NaiveDate::MIN)
+    }
+    pub fn next_date(&self) -> NaiveDate {
+        self.cadence.nth_after(self.last_date(), 1)
+    }
+    ///still running on `today`: the next charge is not overdue by more than half a period
+    pub fn is_active(&self, today: NaiveDate) -> bool {
+        let grace = Duration::days(match self.cadence {
+            Cadence::Weekly => 4,
+            Cadence::Monthly => 15,
+            Cadence::Yearly => 45,
+        });
+        self.next_date() + grace >= today
+    }
+}
+///bounds of the forecast
+#[derive(Debug, Clone, Copy)]
+pub struct ForecastConfig {
+    pub months: u32,         // whole months projected after the current one
+    pub history_months: u32, // whole months looked back for patterns and averages
+    pub z: f64,              // half width of the band in standard deviations, 1.28 is about 80%
+}
+impl Default for ForecastConfig {
+    fn default() -> Self {
+        ForecastConfig {
+            months: 6,
+            history_months: 24,
+            z: 1.28,
+        }
+    }
+}
+///one projected bucket, the first one is the rest of the current month
+#[derive(Debug, Clone, Copy)]
+pub struct ForecastPoint {
+    pub range: DateRange,
+    pub net: f64,
+    pub net_low: f64,
+    pub net_high: f64,
+    pub balance: f64, // at `range.to`
+    pub balance_low: f64,
+    pub balance_high: f64,
+}
+#[derive(Debug, Clone)]
+pub struct AccountForecast {
+    pub accountid: AccountId,
+    pub name: String,
+    pub account_type: AccountType,
+    pub balance: f64, // today
+    pub points: Vec<ForecastPoint>,
+}
+///an asset account that goes (or may go) below zero
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct ForecastWarning {
+    pub accountid: AccountId,
+    pub name: String,
+    pub range: DateRange, // first bucket below zero
+    pub balance: f64,
+    pub likely: bool, // the projection itself is below zero, not only the low end of the band
+}
+#[derive(Debug, Clone)]
+pub struct Forecast {
+    pub today: NaiveDate,
+    pub accounts: Vec<AccountForecast>,
+    pub total: Vec<ForecastPoint>,       // every account together
+    pub patterns: Vec<RecurringPattern>, // active ones, used in the projection
+    pub warnings: Vec<ForecastWarning>,
+}
+fn mean(v: &[f64]) -> f64 {
+    match v.len() {
+        0 => 0.0,
+        n => v.iter().sum::<f64>() / n as f64,
+    }
+}
+fn std_dev(v: &[f64]) -> f64 {
+    if v.len() < 2 {
+        return 0.0;
+    }
+    let m = mean(v);
+    (v.iter().map(|x| (x - m) * (x - m)).sum::<f64>() / v.len() as f64).sqrt()
+}
+///one group of charges as a pattern, if the gaps fit a cadence and the amounts stay close
+fn build_pattern(
+    accountid: AccountId,
+    payee: String,
+    mut charges: Vec<(NaiveDate, f64, EntryId, Option<CategoryId>)>,
+) -> Option<RecurringPattern> {
+    charges.sort_by_key(|c| (c.0, c.2));
+    let gaps: Vec<i64> = charges
+        .windows(2)
+        .map(|w| (w[1].0 - w[0].0).num_days())
+        .collect();
+    // the cadence most gaps agree with, three out of four at least
+    let cadence = [Cadence::Weekly, Cadence::Monthly, Cadence::Yearly]
+        .into_iter()
+        .map(|c| (c, gaps.iter().filter(|g| c.accepts(**g)).count()))
+        .max_by_key(|(_, n)| *n)
+        .filter(|(_, n)| *n > 0 && *n * 4 >= gaps.len() * 3)
+        .map(|(c, _)| c)?;
+    let min_charges = match cadence {
+        Cadence::Yearly => 2,
+        _ => 3,
+    };
+    if charges.len() < min_charges {
+        return None;
+    }
+    let amounts: Vec<f64> = charges.iter().map(|c| c.1).collect();
+    let mut sorted = amounts.clone();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let median = sorted[sorted.len() / 2];
+    let close = amounts
+        .iter()
+        .filter(|a| (*a - median).abs() <= 0.25 * median.abs())
+        .count();
+    if close * 4 < amounts.len() * 3 {
+        return None;
+    }
+    let mut cats: HashMap<Option<CategoryId>, usize> = HashMap::new();
+    for c in &charges {
+        *cats.entry(c.3).or_default() += 1;
+    }
+    let categoryid = charges
+        .iter()
+        .max_by_key(|c| cats[&c.3])
+        .and_then(|c| c.3);
+    Some(RecurringPattern {
+        accountid,
+        categoryid,
+        payee,
+        cadence,
+        amount: mean(&amounts[amounts.len().saturating_sub(3)..]),
+        spread: std_dev(&amounts),
+        charges: charges.iter().map(|c| (c.0, c.1)).collect(),
+        entries: charges.iter().map(|c| c.2).collect(),
+    })
+}
+impl Ledger {
+    ///payee of an entry: the transaction's payee, else a memo
+    pub(crate) fn entry_payee(e: &Entry, tx: &Transaction) -> String {
+        tx.receiver
+            .clone()
+            .or_else(|| e.desc.clone())
+            .or_else(|| tx.desc.clone())
+            .unwrap_or_default()
+    }
+    ///charges inside `range` that repeat weekly, monthly or yearly
+    ///
+    /// grouped by account, payee and direction (income / spending); a group is a pattern when
+    /// three out of four gaps fit the cadence and three out of four amounts are within 25% of the median
+    pub fn recurring_patterns(&self, userid: UserId, range: DateRange) -> Vec<RecurringPattern> {
+        // payees are interned once per transaction, entries only carry the index
+        let mut names: Vec<String> = Vec::new();
+        let mut keys: HashMap<String, usize> = HashMap::new();
+        let mut intern = |payee: String| -> Option<usize> {
+            let key = normalize_payee(&payee);
+            if key.is_empty() {
+                return None;
+            }
+            Some(*keys.entry(key).or_insert_with(|| {
+                names.push(payee);
+                names.len() - 1
+            }))
+        };
+        let txs: HashMap<TransactionId, (&Transaction, Option<usize>)> = self
+            .transaction
+            .iter()
+            .filter(|t| t.userid == userid && range.contains(t.occur_date))
+            .map(|t| (t.id, (t, t.receiver.clone().and_then(&mut intern))))
+            .collect();
+        let mut groups: HashMap<(AccountId, usize, bool), Vec<_>> = HashMap::new();
+        for e in &self.entry {
+            let Some((tx, payee)) = txs.get(&e.tranid) else {
+                continue;
+            };
+            let payee = match payee {
+                Some(i) => *i,
+                None => match intern(Self::entry_payee(e, tx)) {
+                    Some(i) => i,
+                    None => continue,
+                },
+            };
+            groups
+                .entry((e.accountid, payee, e.amount >= 0.0))
+                .or_default()
+                .push((tx.occur_date, e.amount, e.id, e.categoryid));
+        }
+        let mut result: Vec<RecurringPattern> = groups
+            .into_iter()
+            .filter_map(|((acc, payee, _), charges)| {
+                build_pattern(acc, names[payee].clone(), charges)
+            })
+            .collect();
+        result.sort_by(|a, b| {
+            a.accountid
+                .cmp(&b.accountid)
+                .then(a.payee.cmp(&b.payee))
+                .then(a.amount.total_cmp(&b.amount))
+        });
+        result
+    }
+    ///project every account of `userid` over the rest of this month and `cfg.months` more
+    ///
+    /// a bucket adds up three parts:
+    /// - active recurring patterns (salary, rent, subscriptions) on their next dates
+    /// - the seasonal average of everything else per category, from `data_linetrend` minus the
+    ///   recurring charges; a calendar month seen in a full year of history uses its own average
+    /// - scheduled items, i.e. transactions already entered with a date after `today`
+    ///
+    /// the band adds up the spread of the non-recurring months and of the recurring amounts,
+    /// balances accumulate it so the band widens further out
+    pub fn forecast(&self, userid: UserId, today: NaiveDate, cfg: ForecastConfig) -> Forecast {
+        let this_month = Period::Month.containing(today);
+        let first_tx = self
+            .transaction
+            .iter()
+            .filter(|t| t.userid == userid)
+            .map(|t| t.occur_date)
+            .min()
+            .unwrap_or(today);
+        let lookback = this_month
+            .from
+            .checked_sub_months(Months::new(cfg.history_months))
+            .unwrap_or(this_month.from);
+        let hist_from = Period::Month.containing(first_tx.max(lookback)).from;
+        let history: Vec<DateRange> = match hist_from < this_month.from {
+            true => {
+                DateRange::new(hist_from, this_month.from - Duration::days(1)).split(Period::Month)
+            }
+            false => Vec::new(),
+        };
+        let seasonal = history.len() >= 12;
+        let horizon = DateRange::new(
+            today + Duration::days(1),
+            Period::Month
+                .containing(
+                    this_month
+                        .from
+                        .checked_add_months(Months::new(cfg.months))
+                        .unwrap_or(this_month.from),
+                )
+                .to,
+        );
+        let buckets = horizon.split(Period::Month);
+
+        let all = self.recurring_patterns(userid, DateRange::new(hist_from, today));
+        let recurring_ids: HashSet<EntryId> = all.iter().flat_map(|p| p.entries.clone()).collect();
+        let patterns: Vec<RecurringPattern> =
+            all.into_iter().filter(|p| p.is_active(today)).collect();
+
+        // past entries of a pattern, and scheduled entries after today
+        let dates: HashMap<TransactionId, &Transaction> = self
+            .transaction
+            .iter()
+            .filter(|t| t.userid == userid)
+            .map(|t| (t.id, t))
+            .collect();
+        let mut recurring_past: HashMap<MonthKey, f64> = HashMap::new();
+        let mut scheduled: Vec<(AccountId, NaiveDate, f64, String)> = Vec::new();
+        for e in &self.entry {
+            let Some(tx) = dates.get(&e.tranid) else {
+                continue;
+            };
+            let d = tx.occur_date;
+            if d > today {
+                scheduled.push((
+                    e.accountid,
+                    d,
+                    e.amount,
+                    normalize_payee(&Self::entry_payee(e, tx)),
+                ));
+            } else if recurring_ids.contains(&e.id) {
+                *recurring_past
+                    .entry((e.accountid, e.categoryid, (d.year(), d.month())))
+                    .or_default() += e.amount;
+            }
+        }
+
+        let mut accounts = Vec::new();
+        for acc in self.account.iter().filter(|a| a.userid == userid) {
+            // non-recurring flow per category and history month
+            let mut residual: Vec<(Option<CategoryId>, Vec<f64>)> = Vec::new();
+            let total = match history.is_empty() {
+                true => Vec::new(),
+                false => {
+                    let range = DateRange::new(history[0].from, history[history.len() - 1].to);
+                    self.data_linetrend(userid, range, Some(acc.id), None)
+                        .summary
+                }
+            };
+            let mut uncategorized = total.clone();
+            for cat in &self.category {
+                if history.is_empty() {
+                    break;
+                }
+                let range = DateRange::new(history[0].from, history[history.len() - 1].to);
+                let trend = self.data_linetrend(userid, range, Some(acc.id), Some(cat.id));
+                if trend.summary.iter().all(|v| *v == 0.0) {
+                    continue;
+                }
+                for (i, v) in trend.summary.iter().enumerate() {
+                    uncategorized[i] -= v;
+                }
+                residual.push((Some(cat.id), trend.summary));
+            }
+            residual.push((None, uncategorized));
+            for (cat, series) in residual.iter_mut() {
+                for (i, month) in history.iter().enumerate() {
+                    let key = (acc.id, *cat, (month.from.year(), month.from.month()));
+                    series[i] -= recurring_past.get(&key).copied().unwrap_or(0.0);
+                }
+            }
+            let monthly: Vec<f64> = (0..history.len())
+                .map(|i| residual.iter().map(|(_, s)| s[i]).sum())
+                .collect();
+            let sigma = std_dev(&monthly);
+
+            let balance = self.balance_on(acc.id, today);
+            let mut running = balance;
+            let mut running_var = 0.0;
+            let mut points = Vec::new();
+            for bucket in &buckets {
+                let month = bucket.from.month();
+                let full = Period::Month.containing(bucket.from);
+                let share = bucket.days() as f64 / full.days() as f64;
+                let mut net = 0.0;
+                let mut var = share * sigma * sigma;
+                for (_, series) in &residual {
+                    let same: Vec<f64> = history
+                        .iter()
+                        .zip(series)
+                        .filter(|(r, _)| r.from.month() == month)
+                        .map(|(_, v)| *v)
+                        .collect();
+                    let avg = match seasonal && !same.is_empty() {
+                        true => mean(&same),
+                        false => mean(series),
+                    };
+                    net += share * avg;
+                }
+                let mine: Vec<&(AccountId, NaiveDate, f64, String)> = scheduled
+                    .iter()
+                    .filter(|s| s.0 == acc.id && bucket.contains(s.1))
+                    .collect();
+                net += mine.iter().map(|s| s.2).sum::<f64>();
+                for p in patterns.iter().filter(|p| p.accountid == acc.id) {
+                    let key = normalize_payee(&p.payee);
+                    let mut n = 1;
+                    loop {
+                        let d = p.cadence.nth_after(p.last_date(), n);
+                        n += 1;
+                        if d > bucket.to {
+                            break;
+                        }
+                        if d <= today || !bucket.contains(d) {
+                            continue;
+                        }
+                        // already entered ahead of time
+                        let known = mine
+                            .iter()
+                            .any(|s| s.3 == key && (s.1 - d).num_days().abs() <= 5);
+                        if !known {
+                            net += p.amount;
+                            var += p.spread * p.spread;
+                        }
+                    }
+                }
+                running += net;
+                running_var += var;
+                let (band, balance_band) = (cfg.z * var.sqrt(), cfg.z * running_var.sqrt());
+                points.push(ForecastPoint {
+                    range: *bucket,
+                    net,
+                    net_low: net - band,
+                    net_high: net + band,
+                    balance: running,
+                    balance_low: running - balance_band,
+                    balance_high: running + balance_band,
+                });
+            }
+            accounts.push(AccountForecast {
+                accountid: acc.id,
+                name: acc.name.clone(),
+                account_type: acc.account_type.clone(),
+                balance,
+                points,
+            });
+        }
+
+        // accounts are taken as independent, so variances add up
+        let total = buckets
+            .iter()
+            .enumerate()
+            .map(|(i, bucket)| {
+                let (mut net, mut balance, mut net_var, mut balance_var) = (0.0, 0.0, 0.0, 0.0);
+                for p in accounts.iter().map(|a| &a.points[i]) {
+                    net += p.net;
+                    balance += p.balance;
+                    net_var += ((p.net - p.net_low) / cfg.z).powi(2);
+                    balance_var += ((p.balance - p.balance_low) / cfg.z).powi(2);
+                }
+                let (band, balance_band) = (cfg.z * net_var.sqrt(), cfg.z * balance_var.sqrt());
+                ForecastPoint {
+                    range: *bucket,
+                    net,
+                    net_low: net - band,
+                    net_high: net + band,
+                    balance,
+                    balance_low: balance - balance_band,
+                    balance_high: balance + balance_band,
+                }
+            })
+            .collect();
+
+        let warnings = accounts
+            .iter()
+            .filter(|a| !a.account_type.is_liability())
+            .filter_map(|a| {
+                let likely = a.points.iter().find(|p| p.balance < 0.0);
+                let possible = a.points.iter().find(|p| p.balance_low < 0.0);
+                let (p, likely) = match (likely, possible) {
+                    (Some(p), _) => (p, true),
+                    (None, Some(p)) => (p, false),
+                    (None, None) => return None,
+                };
+                Some(ForecastWarning {
+                    accountid: a.accountid,
+                    name: a.name.clone(),
+                    range: p.range,
+                    balance: match likely {
+                        true => p.balance,
+                        false => p.balance_low,
+                    },
+                    likely,
+                })
+            })
+            .collect();
+
+        Forecast {
+            today,
+            accounts,
+            total,
+            patterns,
+            warnings,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn d(y: i32, m: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, day).unwrap()
+    }
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+    ///a chequing account with a streaming charge on the 5th of every month from January
+    fn ledger(opening: f64, charges: &[(NaiveDate, f64, &str)]) -> Ledger {
+        let userid = Uuid::nil();
+        let account = vec![Account {
+            id: 1,
+            userid,
+            name: "Chequing".to_string(),
+            account_type: AccountType::Checking,
+            currency: Currency::new("cad"),
+            balance: opening,
+            create_date: Utc::now(),
+            credit: CreditTerms::default(),
+            loan: None,
+            cost_basis: None,
+            contact_email: None,
+        }];
+        let category = vec![Category {
+            id: 1,
+            userid,
+            name: "Streaming".to_string(),
+            parentid: None,
+            tax_line: None,
+        }];
+        let mut transaction = Vec::new();
+        let mut entry = Vec::new();
+        let monthly = (1..=9).map(|m| (d(2025, m, 5), -15.99, "Netflix"));
+        for (i, (date, amount, payee)) in monthly.chain(charges.iter().copied()).enumerate() {
+            let tranid = Uuid::from_u128(i as u128 + 1);
+            transaction.push(Transaction {
+                id: tranid,
+                userid,
+                occur_date: date,
+                receiver: Some(payee.to_string()),
+                desc: None,
+                create_date: Utc::now(),
+            });
+            entry.push(Entry {
+                id: i as i64 + 1,
+                userid,
+                tranid,
+                accountid: 1,
+                categoryid: Some(1),
+                amount,
+                desc: None,
+                status: EntryStatus::Cleared,
+            });
+        }
+        Ledger::new(Vec::new(), account, category, transaction, entry)
+    }
+    fn cfg() -> ForecastConfig {
+        ForecastConfig {
+            months: 3,
+            ..ForecastConfig::default()
+        }
+    }
+
+    #[test]
+    fn monthly_charge_is_found_and_projected_forward() {
+        let today = d(2025, 9, 20);
+        let ledger = ledger(1000.0, &[]);
+        let patterns = ledger.recurring_patterns(Uuid::nil(), DateRange::new(d(2025, 1, 1), today));
+        assert_eq!(patterns.len(), 1);
+        let p = &patterns[0];
+        assert_eq!(p.cadence, Cadence::Monthly);
+        assert_eq!(p.payee, "Netflix");
+        assert_eq!(p.categoryid, Some(1));
+        assert!(close(p.amount, -15.99));
+        assert_eq!(p.next_date(), d(2025, 10, 5));
+        assert!(p.is_active(today));
+        assert!(!p.is_active(d(2025, 10, 21)));
+
+        let f = ledger.forecast(Uuid::nil(), today, cfg());
+        assert_eq!(f.patterns.len(), 1);
+        let acc = &f.accounts[0];
+        assert!(close(acc.balance, 1000.0 - 9.0 * 15.99));
+        let ranges: Vec<DateRange> = acc.points.iter().map(|p| p.range).collect();
+        assert_eq!(
+            ranges,
+            vec![
+                DateRange::new(d(2025, 9, 21), d(2025, 9, 30)),
+                DateRange::month(2025, 10),
+                DateRange::month(2025, 11),
+                DateRange::month(2025, 12),
+            ]
+        );
+        let nets: Vec<f64> = acc.points.iter().map(|p| p.net).collect();
+        for (net, want) in nets.iter().zip([0.0, -15.99, -15.99, -15.99]) {
+            assert!(close(*net, want), "{nets:?}");
+        }
+        let last = acc.points.last().unwrap();
+        assert!(close(last.balance, 1000.0 - 12.0 * 15.99));
+        // the same amount every month leaves no uncertainty
+        assert!(close(last.balance_low, last.balance) && close(last.balance_high, last.balance));
+        assert!(f.warnings.is_empty());
+    }
+
+    #[test]
+    fn a_charge_entered_ahead_is_not_counted_twice() {
+        let today = d(2025, 9, 20);
+        let ahead = ledger(1000.0, &[(d(2025, 10, 6), -15.99, "Netflix")]);
+        let f = ahead.forecast(Uuid::nil(), today, cfg());
+        let nets: Vec<f64> = f.accounts[0].points.iter().map(|p| p.net).collect();
+        assert!(close(nets[1], -15.99), "{nets:?}");
+        assert!(close(f.total[1].net, -15.99));
+    }
+
+    #[test]
+    fn varying_amounts_widen_the_band_and_warn_before_overdraft() {
+        let today = d(2025, 9, 20);
+        // a one-off in the past makes the non-recurring months vary
+        let ledger = ledger(200.0, &[(d(2025, 3, 12), -40.0, "Hardware store")]);
+        let f = ledger.forecast(Uuid::nil(), today, cfg());
+        let points = &f.accounts[0].points;
+        assert!(
+            points[3].balance_high - points[3].balance_low
+                > points[1].balance_high - points[1].balance_low
+        );
+        assert_eq!(f.warnings.len(), 1);
+        let w = &f.warnings[0];
+        assert_eq!(w.accountid, 1);
+        assert!(points[0].balance > 0.0);
+        // the one-off averages -5.00 a month on top of the subscription
+        assert!(close(points[1].net, -20.99));
+        assert!(w.likely);
+        assert_eq!(w.range, DateRange::month(2025, 10));
+        assert!(close(w.balance, points[1].balance) && w.balance < 0.0);
+    }
+}
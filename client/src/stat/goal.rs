@@ -0,0 +1,352 @@
+use super::datatype::*;
+use super::ledger::*;
+use chrono::*;
+use std::collections::HashMap;
+//savings goals: progress from linked balances or #tag contributions, pace needed to make the deadline
+
+///months looked back for the current saving pace
+pub const GOAL_PACE_MONTHS: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GoalState {
+    Reached,
+    OnTrack,    // the recent pace makes the deadline
+    Behind,     // the recent pace is below the required contribution
+    Overdue,    // deadline passed, target not reached
+    NoDeadline, // open ended, nothing to be on track for
+}
+impl GoalState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GoalState::Reached => "Reached",
+            GoalState::OnTrack => "On track",
+            GoalState::Behind => "Behind",
+            GoalState::Overdue => "Overdue",
+            GoalState::NoDeadline => "No deadline",
+        }
+    }
+}
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct GoalProgress {
+    pub goalid: GoalId,
+    pub name: String,
+    pub target: f64,
+    pub saved: f64,
+    pub remaining: f64, // 0 once reached
+    pub percent: f64,   // saved / target, capped to 0..=100
+    pub deadline: Option<NaiveDate>,
+    pub months_left: Option<u32>, // whole months until the deadline, rounded up
+    pub required_monthly: Option<f64>, // to reach the target by the deadline, none without one
+    pub recent_monthly: f64,      // average growth of the last GOAL_PACE_MONTHS months
+    pub projected: Option<NaiveDate>, // reached at the recent pace, none when it is not growing
+    pub state: GoalState,
+}
+///whether `text` carries `#tag` as a whole word, case insensitive
+fn has_tag(text: &str, tag: &str) -> bool {
+    let text = text.to_lowercase();
+    let needle = format!("#{tag}");
+    text.match_indices(&needle).any(|(i, _)| {
+        text[i + needle.len()..]
+            .chars()
+            .next()
+            .is_none_or(|c| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+    })
+}
+impl Ledger {
+    ///contributions of a tagged goal as (date, amount)
+    ///
+    /// an entry is tagged when its note, or its transaction's memo, carries #tag; with linked
+    /// accounts only their tagged entries count, signed, so a tagged withdrawal lowers progress.
+    /// without accounts a transaction counts its positive tagged entries (money put aside), or the
+    /// size of its negative ones when it has no positive one (money moved out to the goal)
+    fn goal_contributions(&self, goal: &Goal, tag: &str) -> Vec<(NaiveDate, f64)> {
+        let txs: HashMap<TransactionId, &Transaction> = self
+            .transaction
+            .iter()
+            .filter(|t| t.userid == goal.userid)
+            .map(|t| (t.id, t))
+            .collect();
+        let mut per_tx: HashMap<TransactionId, (f64, f64)> = HashMap::new();
+        for e in &self.entry {
+            let Some(tx) = txs.get(&e.tranid) else {
+                continue;
+            };
+            let tagged = match e.desc.as_deref() {
+                Some(note) if has_tag(note, tag) => true,
+                _ => tx.desc.as_deref().is_some_and(|memo| has_tag(memo, tag)),
+            };
+            if !tagged {
+                continue;
+            }
+            let sums = per_tx.entry(e.tranid).or_default();
+            if !goal.accounts.is_empty() {
+                if goal.accounts.contains(&e.accountid) {
+                    sums.0 += e.amount;
+                }
+            } else if e.amount >= 0.0 {
+                sums.0 += e.amount;
+            } else {
+                sums.1 -= e.amount;
+            }
+        }
+        per_tx
+            .into_iter()
+            .map(|(txid, (plus, minus))| {
+                let amount = match plus != 0.0 || !goal.accounts.is_empty() {
+                    true => plus,
+                    false => minus,
+                };
+                (txs[&txid].occur_date, amount)
+            })
+            .collect()
+    }
+    ///amount saved towards `goal` at end of `date`
+    fn goal_saved_on(
+        &self,
+        goal: &Goal,
+        contributions: &[(NaiveDate, f64)],
+        date: NaiveDate,
+    ) -> f64 {
+        match goal.tag {
+            Some(_) => contributions
+                .iter()
+                .filter(|(d, _)| *d <= date)
+                .map(|(_, a)| a)
+                .sum(),
+            None => goal
+                .accounts
+                .iter()
+                .map(|a| self.value_on(*a, date))
+                .sum(),
+        }
+    }
+    ///progress of `goal` as of `today`
+    ///
+    /// the required contribution spreads what is left over the months to the deadline (1 at
+    /// least); the goal is on track when the last GOAL_PACE_MONTHS months grew it at least that fast
+    pub fn goal_progress(&self, goal: &Goal, today: NaiveDate) -> GoalProgress {
+        let contributions = match goal.tag.as_deref() {
+            Some(tag) => self.goal_contributions(goal, tag),
+            None => Vec::new(),
+        };
+        let saved = self.goal_saved_on(goal, &contributions, today);
+        let before = today
+            .checked_sub_months(Months::new(GOAL_PACE_MONTHS))
+            .unwrap_or(today);
+        let recent_monthly =
+            (saved - self.goal_saved_on(goal, &contributions, before)) / GOAL_PACE_MONTHS as f64;
+        let remaining = (goal.target - saved).max(0.0);
+        let months_left = goal.deadline.filter(|d| *d >= today).map(|d| {
+            let days = (d - today).num_days() as f64;
+            (days / 30.4375).ceil() as u32
+        });
+        let required_monthly = goal
+            .deadline
+            .map(|_| remaining / months_left.unwrap_or(0).max(1) as f64);
+        let projected = match (remaining > 0.0, recent_monthly > 0.0) {
+            (false, _) => Some(today),
+            (true, true) => {
+                let days = (remaining / recent_monthly * 30.4375).ceil() as i64;
+                today.checked_add_signed(Duration::days(days))
+            }
+            (true, false) => None,
+        };
+        let state = match (remaining <= 0.0, goal.deadline, required_monthly) {
+            (true, _, _) => GoalState::Reached,
+            (false, None, _) => GoalState::NoDeadline,
+            (false, Some(d), _) if d < today => GoalState::Overdue,
+            (false, Some(_), Some(req)) if recent_monthly + 0.005 >= req => GoalState::OnTrack,
+            _ => GoalState::Behind,
+        };
+        GoalProgress {
+            goalid: goal.id,
+            name: goal.name.clone(),
+            target: goal.target,
+            saved,
+            remaining,
+            percent: match goal.target > 0.0 {
+                true => (saved / goal.target * 100.0).clamp(0.0, 100.0),
+                false => 0.0,
+            },
+            deadline: goal.deadline,
+            months_left,
+            required_monthly,
+            recent_monthly,
+            projected,
+            state,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn d(y: i32, m: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, day).unwrap()
+    }
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+    ///account, amount and note of one entry
+    type Part = (AccountId, f64, Option<&'static str>);
+
+    ///chequing (1) and a savings account (2) opened with 1000, transactions of (date, memo, entries)
+    fn ledger(txs: &[(NaiveDate, Option<&str>, &[Part])]) -> Ledger {
+        let account = [(1, "Chequing", 0.0), (2, "Savings", 1000.0)]
+            .into_iter()
+            .map(|(id, name, balance)| Account {
+                id,
+                userid: Uuid::nil(),
+                name: name.to_string(),
+                account_type: AccountType::Checking,
+                currency: Currency::new("CAD"),
+                balance,
+                create_date: Utc::now(),
+                credit: CreditTerms::default(),
+                loan: None,
+                cost_basis: None,
+                contact_email: None,
+            })
+            .collect();
+        let (mut transaction, mut entry) = (Vec::new(), Vec::new());
+        for (i, (date, memo, parts)) in txs.iter().enumerate() {
+            let tranid = Uuid::from_u128(i as u128 + 1);
+            transaction.push(Transaction {
+                id: tranid,
+                userid: Uuid::nil(),
+                occur_date: *date,
+                receiver: None,
+                desc: memo.map(str::to_string),
+                create_date: Utc::now(),
+            });
+            for (accountid, amount, note) in parts.iter() {
+                entry.push(Entry {
+                    id: entry.len() as i64 + 1,
+                    userid: Uuid::nil(),
+                    tranid,
+                    accountid: *accountid,
+                    categoryid: None,
+                    amount: *amount,
+                    desc: note.map(str::to_string),
+                    status: EntryStatus::Cleared,
+                });
+            }
+        }
+        Ledger::new(Vec::new(), account, Vec::new(), transaction, entry)
+    }
+    fn goal(
+        target: f64,
+        deadline: Option<NaiveDate>,
+        accounts: &[AccountId],
+        tag: Option<&str>,
+    ) -> Goal {
+        Goal {
+            id: 1,
+            userid: Uuid::nil(),
+            name: "Emergency fund".to_string(),
+            target,
+            deadline,
+            accounts: accounts.to_vec(),
+            tag: tag.map(str::to_string),
+            create_date: Utc::now(),
+        }
+    }
+    ///500 moved from chequing to savings on the 15th of July, August and September 2025
+    fn monthly_savings() -> Ledger {
+        let transfer: &[Part] = &[(1, -500.0, None), (2, 500.0, None)];
+        ledger(&[
+            (d(2025, 7, 15), None, transfer),
+            (d(2025, 8, 15), None, transfer),
+            (d(2025, 9, 15), None, transfer),
+        ])
+    }
+    const TODAY: NaiveDate = NaiveDate::from_ymd_opt(2025, 10, 1).unwrap();
+
+    #[test]
+    fn linked_accounts_on_track_when_the_recent_pace_covers_the_required_contribution() {
+        let ledger = monthly_savings();
+        // 3500 left over the 7 months to May, exactly the 500 saved each month lately
+        let p = ledger.goal_progress(&goal(6000.0, Some(d(2026, 5, 1)), &[2], None), TODAY);
+        assert!(close(p.saved, 2500.0));
+        assert!(close(p.remaining, 3500.0));
+        assert!(close(p.percent, 2500.0 / 6000.0 * 100.0));
+        assert_eq!(p.months_left, Some(7));
+        assert!(close(p.required_monthly.unwrap(), 500.0));
+        assert!(close(p.recent_monthly, 500.0));
+        assert_eq!(p.state, GoalState::OnTrack);
+        // 3500 / 500 months, about 213 days
+        assert_eq!(p.projected, Some(d(2026, 5, 3)));
+    }
+
+    #[test]
+    fn linked_accounts_behind_when_the_deadline_is_sooner() {
+        let ledger = monthly_savings();
+        let p = ledger.goal_progress(&goal(6000.0, Some(d(2026, 4, 1)), &[2], None), TODAY);
+        assert_eq!(p.months_left, Some(6));
+        assert!(close(p.required_monthly.unwrap(), 3500.0 / 6.0));
+        assert_eq!(p.state, GoalState::Behind);
+    }
+
+    #[test]
+    fn reached_overdue_and_open_ended_goals() {
+        let ledger = monthly_savings();
+        let reached = ledger.goal_progress(&goal(2000.0, Some(d(2026, 1, 1)), &[2], None), TODAY);
+        assert_eq!(reached.state, GoalState::Reached);
+        assert!(close(reached.remaining, 0.0));
+        assert!(close(reached.percent, 100.0));
+        assert_eq!(reached.projected, Some(TODAY));
+
+        // what is left is due at once, spread over one month
+        let overdue = ledger.goal_progress(&goal(6000.0, Some(d(2025, 9, 1)), &[2], None), TODAY);
+        assert_eq!(overdue.state, GoalState::Overdue);
+        assert_eq!(overdue.months_left, None);
+        assert!(close(overdue.required_monthly.unwrap(), 3500.0));
+
+        let open = ledger.goal_progress(&goal(6000.0, None, &[2], None), TODAY);
+        assert_eq!(open.state, GoalState::NoDeadline);
+        assert_eq!(open.required_monthly, None);
+    }
+
+    #[test]
+    fn tagged_contributions_without_accounts() {
+        let ledger = ledger(&[
+            // a transfer put aside, counted once by its positive side
+            (
+                d(2025, 8, 1),
+                Some("for the #Trip"),
+                &[(1, -300.0, None), (2, 300.0, None)],
+            ),
+            // paid straight out of chequing, counted by its size
+            (d(2025, 9, 1), None, &[(1, -120.0, Some("deposit #trip"))]),
+            // another word that starts like the tag
+            (d(2025, 9, 2), Some("#tripod"), &[(1, -80.0, None)]),
+            (d(2025, 11, 1), Some("#trip"), &[(2, 50.0, None)]),
+        ]);
+        let p = ledger.goal_progress(&goal(1000.0, None, &[], Some("trip")), TODAY);
+        assert!(close(p.saved, 420.0));
+        assert!(close(p.recent_monthly, 420.0 / GOAL_PACE_MONTHS as f64));
+    }
+
+    #[test]
+    fn tagged_contributions_on_linked_accounts_count_withdrawals() {
+        let ledger = ledger(&[
+            (
+                d(2025, 8, 1),
+                Some("#trip"),
+                &[(1, -300.0, None), (2, 300.0, None)],
+            ),
+            (
+                d(2025, 9, 1),
+                Some("#trip"),
+                &[(2, -100.0, None), (1, 100.0, None)],
+            ),
+            // on the linked account but not tagged
+            (d(2025, 9, 5), None, &[(2, 1000.0, None)]),
+        ]);
+        let p = ledger.goal_progress(&goal(1000.0, None, &[2], Some("trip")), TODAY);
+        assert!(close(p.saved, 200.0));
+    }
+}
@@ -0,0 +1,291 @@
+use crate::stat::datatype::*;
+use crate::stat::sync::*;
+use anyhow::Result;
+//undo/redo for mutations made through `stat::sync`
+const MAX_HISTORY: usize = 100;
+
+///one mutation that already happened on the server
+///
+/// every variant knows how to replay its inverse, the inverse is again a `Command`,
+/// so undo pushes onto redo and redo pushes back onto undo
+#[derive(Debug, Clone)]
+pub enum Command {
+    CreateTransaction {
+        id: TransactionId,
+    },
+    DeleteTransaction {
+        id: TransactionId,
+    },
+    RestoreTransaction {
+        id: TransactionId,
+    },
+    DeleteEntry {
+        id: EntryId,
+    },
+    RestoreEntry {
+        id: EntryId,
+    },
+    CreateAccount {
+        id: AccountId,
+        name: String,
+    },
+    DeleteAccount {
+        id: AccountId,
+        name: String,
+    },
+    RestoreAccount {
+        id: AccountId,
+        name: String,
+    },
+    CreateCategory {
+        id: CategoryId,
+        name: String,
+    },
+    DeleteCategory {
+        id: CategoryId,
+        name: String,
+    },
+    RestoreCategory {
+        id: CategoryId,
+        name: String,
+    },
+    SetEntryStatus {
+        id: EntryId,
+        before: EntryStatus,
+        after: EntryStatus,
+    },
+    SetCreditTerms {
+        id: AccountId,
+        before: CreditTerms,
+        after: CreditTerms,
+    },
+    SetLoanTerms {
+        id: AccountId,
+        before: Option<LoanTerms>,
+        after: Option<LoanTerms>,
+    },
+    SetCostBasis {
+        id: AccountId,
+        before: CostBasis,
+        after: CostBasis,
+    },
+    SetTaxLine {
+        id: CategoryId,
+        before: Option<TaxLine>,
+        after: Option<TaxLine>,
+    },
+    CreateGoal {
+        goal: Goal,
+    },
+    DeleteGoal {
+        goal: Goal,
+    },
+    RestoreGoal {
+        goal: Goal,
+    },
+    UploadAttachment {
+        id: AttachmentId,
+        filename: String,
+    },
+    DeleteAttachment {
+        id: AttachmentId,
+        filename: String,
+    },
+    RestoreAttachment {
+        id: AttachmentId,
+        filename: String,
+    },
+}
+impl Command {
+    pub fn describe(&self) -> String {
+        match self {
+            Command::CreateTransaction { .. } => "create transaction".to_string(),
+            Command::DeleteTransaction { .. } => "delete transaction".to_string(),
+            Command::RestoreTransaction { .. } => "restore transaction".to_string(),
+            Command::DeleteEntry { .. } => "delete split".to_string(),
+            Command::RestoreEntry { .. } => "restore split".to_string(),
+            Command::CreateAccount { id, name } => format!("create account '{name}' (#{id})"),
+            Command::DeleteAccount { id, name } => format!("delete account '{name}' (#{id})"),
+            Command::RestoreAccount { id, name } => format!("restore account '{name}' (#{id})"),
+            Command::CreateCategory { id, name } => format!("create category '{name}' (#{id})"),
+            Command::DeleteCategory { id, name } => format!("delete category '{name}' (#{id})"),
+            Command::RestoreCategory { id, name } => {
+                format!("restore category '{name}' (#{id})")
+            }
+            Command::SetEntryStatus { after, .. } => match after {
+                EntryStatus::Uncleared => "unclear split".to_string(),
+                EntryStatus::Cleared => "clear split".to_string(),
+                EntryStatus::Reconciled => "reconcile split".to_string(),
+            },
+            Command::SetCreditTerms { id, .. } => format!("credit terms of account #{id}"),
+            Command::SetLoanTerms { id, .. } => format!("loan terms of account #{id}"),
+            Command::SetCostBasis { id, after, .. } => {
+                format!("cost basis of account #{id} to {}", after.to_cloud())
+            }
+            Command::SetTaxLine { id, .. } => format!("tax line of category #{id}"),
+            Command::CreateGoal { goal } => format!("create goal '{}'", goal.name),
+            Command::DeleteGoal { goal } => format!("delete goal '{}'", goal.name),
+            Command::RestoreGoal { goal } => format!("restore goal '{}'", goal.name),
+            Command::UploadAttachment { id, filename } => format!("attach '{filename}' (#{id})"),
+            Command::DeleteAttachment { id, filename } => {
+                format!("delete attachment '{filename}' (#{id})")
+            }
+            Command::RestoreAttachment { id, filename } => {
+                format!("restore attachment '{filename}' (#{id})")
+            }
+        }
+    }
+    ///run the inverse against server, return the command that was just done
+    ///
+    /// deletes are soft on the server, so an undone delete comes back under the same id and
+    /// later commands that name it still apply; updates set the value they replaced back
+    async fn inverse(self, base_url: &str, session: &Session) -> Result<Command> {
+        match self {
+            Command::CreateTransaction { id } | Command::RestoreTransaction { id } => {
+                delete_transaction_on_server(base_url, session, id).await?;
+                Ok(Command::DeleteTransaction { id })
+            }
+            Command::DeleteTransaction { id } => {
+                restore_transaction_on_server(base_url, session, id).await?;
+                Ok(Command::RestoreTransaction { id })
+            }
+            Command::DeleteEntry { id } => {
+                restore_entry_on_server(base_url, session, id).await?;
+                Ok(Command::RestoreEntry { id })
+            }
+            Command::RestoreEntry { id } => {
+                delete_entry_on_server(base_url, session, id).await?;
+                Ok(Command::DeleteEntry { id })
+            }
+            Command::CreateAccount { id, name } | Command::RestoreAccount { id, name } => {
+                delete_account_on_server(base_url, session, id).await?;
+                Ok(Command::DeleteAccount { id, name })
+            }
+            Command::DeleteAccount { id, name } => {
+                restore_account_on_server(base_url, session, id).await?;
+                Ok(Command::RestoreAccount { id, name })
+            }
+            Command::CreateCategory { id, name } | Command::RestoreCategory { id, name } => {
+                delete_category_on_server(base_url, session, id).await?;
+                Ok(Command::DeleteCategory { id, name })
+            }
+            Command::DeleteCategory { id, name } => {
+                restore_category_on_server(base_url, session, id).await?;
+                Ok(Command::RestoreCategory { id, name })
+            }
+            Command::SetEntryStatus { id, before, after } => {
+                set_entry_status_on_server(base_url, session, id, before).await?;
+                Ok(Command::SetEntryStatus {
+                    id,
+                    before: after,
+                    after: before,
+                })
+            }
+            Command::SetCreditTerms { id, before, after } => {
+                set_credit_terms_on_server(base_url, session, id, before).await?;
+                Ok(Command::SetCreditTerms {
+                    id,
+                    before: after,
+                    after: before,
+                })
+            }
+            Command::SetLoanTerms { id, before, after } => {
+                set_loan_terms_on_server(base_url, session, id, before).await?;
+                Ok(Command::SetLoanTerms {
+                    id,
+                    before: after,
+                    after: before,
+                })
+            }
+            Command::SetCostBasis { id, before, after } => {
+                set_cost_basis_on_server(base_url, session, id, before).await?;
+                Ok(Command::SetCostBasis {
+                    id,
+                    before: after,
+                    after: before,
+                })
+            }
+            Command::SetTaxLine { id, before, after } => {
+                set_tax_line_on_server(base_url, session, id, before).await?;
+                Ok(Command::SetTaxLine {
+                    id,
+                    before: after,
+                    after: before,
+                })
+            }
+            Command::CreateGoal { goal } | Command::RestoreGoal { goal } => {
+                delete_goal_on_server(base_url, session, goal.id).await?;
+                Ok(Command::DeleteGoal { goal })
+            }
+            Command::DeleteGoal { goal } => {
+                restore_goal_on_server(base_url, session, goal.id).await?;
+                Ok(Command::RestoreGoal { goal })
+            }
+            Command::UploadAttachment { id, filename } | Command::RestoreAttachment { id, filename } => {
+                delete_attachment_on_server(base_url, session, id).await?;
+                Ok(Command::DeleteAttachment { id, filename })
+            }
+            Command::DeleteAttachment { id, filename } => {
+                restore_attachment_on_server(base_url, session, id).await?;
+                Ok(Command::RestoreAttachment { id, filename })
+            }
+        }
+    }
+}
+#[derive(Debug, Default)]
+pub struct History {
+    undo: Vec<Command>,
+    redo: Vec<Command>,
+}
+impl History {
+    ///record a mutation that just succeeded, a new mutation drops the redo branch
+    pub fn record(&mut self, cmd: Command) {
+        self.undo.push(cmd);
+        if self.undo.len() > MAX_HISTORY {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+    ///forget both stacks, for mutations that can't be undone and would leave older commands
+    /// replaying against a ledger they no longer match
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+    ///replay the inverse of the last mutation, return what was undone
+    ///
+    /// if server refuses, the command stays on the undo stack
+    pub async fn undo(&mut self, base_url: &str, session: &Session) -> Result<Option<String>> {
+        let Some(cmd) = self.undo.pop() else {
+            return Ok(None);
+        };
+        let label = cmd.describe();
+        match cmd.clone().inverse(base_url, session).await {
+            Ok(done) => {
+                self.redo.push(done);
+                Ok(Some(label))
+            }
+            Err(e) => {
+                self.undo.push(cmd);
+                Err(e)
+            }
+        }
+    }
+    ///replay the last undone mutation, return what was redone
+    pub async fn redo(&mut self, base_url: &str, session: &Session) -> Result<Option<String>> {
+        let Some(cmd) = self.redo.pop() else {
+            return Ok(None);
+        };
+        match cmd.clone().inverse(base_url, session).await {
+            Ok(done) => {
+                let label = done.describe();
+                self.undo.push(done);
+                Ok(Some(label))
+            }
+            Err(e) => {
+                self.redo.push(cmd);
+                Err(e)
+            }
+        }
+    }
+}
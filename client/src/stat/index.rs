@@ -0,0 +1,347 @@
+use super::datatype::*;
+use super::ledger::{Detailstats, Monthstats};
+use super::period::*;
+use chrono::*;
+use std::collections::{BTreeMap, HashMap};
+//aggregate index over the ledger, updated on every mutation so stats queries never rescan entries
+
+///statistics of one user per day, rolled up per month
+#[derive(Debug, Clone, Default)]
+struct UserStats {
+    days: BTreeMap<NaiveDate, Monthstats>,
+    months: BTreeMap<(i32, u32), Monthstats>,
+}
+///net change of one account per day, rolled up per month, for balances
+#[derive(Debug, Clone, Default)]
+struct AccountStats {
+    days: BTreeMap<NaiveDate, Detailstats>,
+    months: BTreeMap<(i32, u32), Detailstats>,
+}
+impl UserStats {
+    fn apply(&mut self, e: &Entry, date: NaiveDate, sign: f64) {
+        let ym = (date.year(), date.month());
+        let day = self.days.entry(date).or_default();
+        let month = self.months.entry(ym).or_default();
+        if sign > 0.0 {
+            day.add(e);
+            month.add(e);
+        } else {
+            day.remove(e);
+            month.remove(e);
+        }
+        if day.count == 0 {
+            self.days.remove(&date);
+        }
+        if month.count == 0 {
+            self.months.remove(&ym);
+        }
+    }
+}
+impl AccountStats {
+    fn apply(&mut self, e: &Entry, date: NaiveDate, sign: f64) {
+        let ym = (date.year(), date.month());
+        let day = self.days.entry(date).or_default();
+        day.apply(e.amount, sign);
+        if day.count == 0 {
+            self.days.remove(&date);
+        }
+        let month = self.months.entry(ym).or_default();
+        month.apply(e.amount, sign);
+        if month.count == 0 {
+            self.months.remove(&ym);
+        }
+    }
+}
+///per day / account / category aggregates of a `Ledger`
+///
+/// an entry is counted on the date of its transaction, entries whose transaction is unknown are
+/// kept aside and counted as soon as the transaction shows up
+#[derive(Debug, Clone, Default)]
+pub struct StatsIndex {
+    users: HashMap<UserId, UserStats>,
+    accounts: HashMap<AccountId, AccountStats>,
+    tx_date: HashMap<TransactionId, NaiveDate>,
+    entries: HashMap<EntryId, Entry>,
+    by_tx: HashMap<TransactionId, Vec<EntryId>>,
+}
+impl StatsIndex {
+    pub fn build(transaction: &[Transaction], entry: &[Entry]) -> Self {
+        let mut index = StatsIndex::default();
+        for t in transaction {
+            index.insert_transaction(t.id, t.occur_date);
+        }
+        for e in entry {
+            index.insert_entry(e);
+        }
+        index
+    }
+    fn count(&mut self, e: &Entry, date: NaiveDate, sign: f64) {
+        self.users.entry(e.userid).or_default().apply(e, date, sign);
+        self.accounts
+            .entry(e.accountid)
+            .or_default()
+            .apply(e, date, sign);
+    }
+    fn count_tx(&mut self, txid: TransactionId, date: NaiveDate, sign: f64) {
+        let entries: Vec<Entry> = self
+            .by_tx
+            .get(&txid)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.entries.get(id).cloned())
+            .collect();
+        for e in &entries {
+            self.count(e, date, sign);
+        }
+    }
+    ///new transaction or a changed date
+    pub fn insert_transaction(&mut self, txid: TransactionId, date: NaiveDate) {
+        match self.tx_date.insert(txid, date) {
+            Some(old) if old == date => {}
+            Some(old) => {
+                self.count_tx(txid, old, -1.0);
+                self.count_tx(txid, date, 1.0);
+            }
+            None => self.count_tx(txid, date, 1.0),
+        }
+    }
+    ///forget a transaction together with its entries
+    pub fn remove_transaction(&mut self, txid: TransactionId) {
+        if let Some(date) = self.tx_date.remove(&txid) {
+            self.count_tx(txid, date, -1.0);
+        }
+        for id in self.by_tx.remove(&txid).unwrap_or_default() {
+            self.entries.remove(&id);
+        }
+    }
+    ///new entry, or replace the one with the same id
+    pub fn insert_entry(&mut self, e: &Entry) {
+        self.remove_entry(e.id);
+        if let Some(&date) = self.tx_date.get(&e.tranid) {
+            self.count(e, date, 1.0);
+        }
+        self.by_tx.entry(e.tranid).or_default().push(e.id);
+        self.entries.insert(e.id, e.clone());
+    }
+    pub fn remove_entry(&mut self, id: EntryId) {
+        let Some(e) = self.entries.remove(&id) else {
+            return;
+        };
+        if let Some(&date) = self.tx_date.get(&e.tranid) {
+            self.count(&e, date, -1.0);
+        }
+        if let Some(ids) = self.by_tx.get_mut(&e.tranid) {
+            ids.retain(|i| *i != id);
+        }
+    }
+    pub fn has_transaction(&self, txid: TransactionId) -> bool {
+        self.tx_date.contains_key(&txid)
+    }
+    pub fn has_entry(&self, id: EntryId) -> bool {
+        self.entries.contains_key(&id)
+    }
+    ///call `f` on the aggregates that together cover `range` exactly
+    ///
+    /// whole months come from the monthly roll-up, only the partial months at both ends walk days
+    pub fn visit(&self, userid: UserId, range: DateRange, mut f: impl FnMut(&Monthstats)) {
+        let Some(user) = self.users.get(&userid) else {
+            return;
+        };
+        for part in range.split(Period::Month) {
+            let ym = (part.from.year(), part.from.month());
+            if part == DateRange::month(ym.0, ym.1) {
+                if let Some(s) = user.months.get(&ym) {
+                    f(s);
+                }
+            } else {
+                for (_, s) in user.days.range(part.from..=part.to) {
+                    f(s);
+                }
+            }
+        }
+    }
+    ///sum of every entry of `accountid` dated on or before `date`
+    pub fn account_until(&self, accountid: AccountId, date: NaiveDate) -> f64 {
+        let Some(acc) = self.accounts.get(&accountid) else {
+            return 0.0;
+        };
+        let month = Period::Month.containing(date);
+        let before: f64 = acc
+            .months
+            .range(..(date.year(), date.month()))
+            .map(|(_, s)| s.summary)
+            .sum();
+        let this: f64 = acc
+            .days
+            .range(month.from..=date)
+            .map(|(_, s)| s.summary)
+            .sum();
+        before + this
+    }
+    ///net change of `accountid` on each day of `range` that has entries, oldest first
+    pub fn account_moves(&self, accountid: AccountId, range: DateRange) -> Vec<(NaiveDate, f64)> {
+        match self.accounts.get(&accountid) {
+            Some(acc) => acc
+                .days
+                .range(range.from..=range.to)
+                .map(|(d, s)| (*d, s.summary))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::HashSet;
+    use uuid::Uuid;
+
+    fn same_detail(a: &Detailstats, b: &Detailstats) -> bool {
+        a.count == b.count
+            && a.income == b.income
+            && a.outcome == b.outcome
+            && a.summary == b.summary
+    }
+    fn same_map<K: std::hash::Hash + Eq>(
+        a: &HashMap<K, Detailstats>,
+        b: &HashMap<K, Detailstats>,
+    ) -> bool {
+        a.len() == b.len()
+            && a.iter()
+                .all(|(k, v)| b.get(k).is_some_and(|w| same_detail(v, w)))
+    }
+    fn same_month(a: &Monthstats, b: &Monthstats) -> bool {
+        a.count == b.count
+            && a.income == b.income
+            && a.outcome == b.outcome
+            && a.summary == b.summary
+            && same_map(&a.category, &b.category)
+            && same_map(&a.account, &b.account)
+            && same_map(&a.account_category, &b.account_category)
+    }
+    ///same aggregates, a user or account whose entries are all gone counts as absent
+    fn same_index(a: &StatsIndex, b: &StatsIndex) -> bool {
+        let user_ids: HashSet<&UserId> = a.users.keys().chain(b.users.keys()).collect();
+        let users = user_ids.into_iter().all(|id| {
+            let (u, v) = (
+                a.users.get(id).cloned().unwrap_or_default(),
+                b.users.get(id).cloned().unwrap_or_default(),
+            );
+            u.days.len() == v.days.len()
+                && u.months.len() == v.months.len()
+                && u.days
+                    .iter()
+                    .all(|(d, s)| v.days.get(d).is_some_and(|t| same_month(s, t)))
+                && u.months
+                    .iter()
+                    .all(|(m, s)| v.months.get(m).is_some_and(|t| same_month(s, t)))
+        });
+        let account_ids: HashSet<&AccountId> = a.accounts.keys().chain(b.accounts.keys()).collect();
+        let accounts = account_ids.into_iter().all(|id| {
+            let (u, v) = (
+                a.accounts.get(id).cloned().unwrap_or_default(),
+                b.accounts.get(id).cloned().unwrap_or_default(),
+            );
+            u.days.len() == v.days.len()
+                && u.months.len() == v.months.len()
+                && u.days
+                    .iter()
+                    .all(|(d, s)| v.days.get(d).is_some_and(|t| same_detail(s, t)))
+                && u.months
+                    .iter()
+                    .all(|(m, s)| v.months.get(m).is_some_and(|t| same_detail(s, t)))
+        });
+        users && accounts
+    }
+
+    ///inserts, removes and date changes in random order, amounts in whole units so sums are exact
+    #[test]
+    fn incremental_updates_match_a_fresh_build() {
+        let mut rng = StdRng::seed_from_u64(35);
+        let users = [Uuid::from_u128(1), Uuid::from_u128(2)];
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let mut index = StatsIndex::default();
+        let mut transactions: Vec<Transaction> = Vec::new();
+        let mut entries: Vec<Entry> = Vec::new();
+        for step in 0..3000 {
+            let txid = Uuid::from_u128(100 + rng.gen_range(0..25));
+            match rng.gen_range(0..10) {
+                // new transaction, or a new date for a known one
+                0..=2 => {
+                    let date = start + Duration::days(rng.gen_range(0..800));
+                    index.insert_transaction(txid, date);
+                    transactions.retain(|t| t.id != txid);
+                    transactions.push(Transaction {
+                        id: txid,
+                        userid: users[0],
+                        occur_date: date,
+                        receiver: None,
+                        desc: None,
+                        create_date: Utc::now(),
+                    });
+                }
+                // new entry, or a replaced one, its transaction may not be known yet
+                3..=6 => {
+                    let e = Entry {
+                        id: rng.gen_range(0..80),
+                        userid: users[rng.gen_range(0..2)],
+                        tranid: txid,
+                        accountid: rng.gen_range(1..5),
+                        categoryid: rng.gen_bool(0.8).then(|| rng.gen_range(1..6)),
+                        amount: rng.gen_range(-500..=500) as f64,
+                        desc: None,
+                        status: EntryStatus::Uncleared,
+                    };
+                    index.insert_entry(&e);
+                    entries.retain(|x| x.id != e.id);
+                    entries.push(e);
+                }
+                7..=8 => {
+                    let id = rng.gen_range(0..80);
+                    index.remove_entry(id);
+                    entries.retain(|x| x.id != id);
+                }
+                _ => {
+                    index.remove_transaction(txid);
+                    transactions.retain(|t| t.id != txid);
+                    entries.retain(|x| x.tranid != txid);
+                }
+            }
+            if step % 50 == 0 {
+                let built = StatsIndex::build(&transactions, &entries);
+                assert!(
+                    same_index(&index, &built),
+                    "index drifted after step {step}"
+                );
+            }
+        }
+        let built = StatsIndex::build(&transactions, &entries);
+        assert!(same_index(&index, &built));
+        assert!(built.users.values().any(|u| u.days.len() > 10));
+
+        // and the queries agree on partial months, whole months and balances
+        for _ in 0..200 {
+            let from = start + Duration::days(rng.gen_range(0..800));
+            let range = DateRange::new(from, from + Duration::days(rng.gen_range(0..120)));
+            for user in users {
+                let (mut a, mut b) = (Monthstats::default(), Monthstats::default());
+                index.visit(user, range, |s| a.summary += s.summary);
+                built.visit(user, range, |s| b.summary += s.summary);
+                assert_eq!(a.summary, b.summary);
+            }
+            for acc in 1..5 {
+                assert_eq!(
+                    index.account_until(acc, range.to),
+                    built.account_until(acc, range.to)
+                );
+                assert_eq!(
+                    index.account_moves(acc, range),
+                    built.account_moves(acc, range)
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,469 @@
+use super::datatype::*;
+use super::ledger::*;
+use super::statement::{parse_statement_amount, parse_statement_date, split_csv_record};
+use anyhow::{Result, anyhow};
+use chrono::*;
+use std::collections::BTreeMap;
+//investments: lots per security, cost basis (FIFO or average), realized / unrealized gains and
+//market value from the imported price table
+
+///units below this are rounding left over by sells and splits
+const UNIT_EPSILON: f64 = 1e-9;
+
+///units bought together, what is left of them after sells
+#[derive(Debug, Clone, Copy)]
+pub struct Lot {
+    pub date: NaiveDate,
+    pub quantity: f64,
+    pub cost: f64, // of the units left, fees included
+}
+///a sell and the gain it realized
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy)]
+pub struct Sale {
+    pub date: NaiveDate,
+    pub quantity: f64,
+    pub proceeds: f64, // after fees
+    pub cost: f64,
+    pub gain: f64,
+}
+///one security of one investment account
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Holding {
+    pub accountid: AccountId,
+    pub securityid: SecurityId,
+    pub symbol: String,
+    pub name: Option<String>,
+    pub quantity: f64,
+    pub cost: f64,                     // cost basis of the units held
+    pub price: Option<f64>,            // latest known price, imported or traded
+    pub price_date: Option<NaiveDate>, // date of `price`
+    pub market_value: f64,
+    pub unrealized: f64, // market value - cost
+    pub realized: f64,   // gains of every sale
+    pub dividends: f64,
+    pub lots: Vec<Lot>, // oldest first
+    pub sales: Vec<Sale>,
+}
+///an investment account: cash, holdings and gains
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Portfolio {
+    pub accountid: AccountId,
+    pub name: String,
+    pub cost_basis: CostBasis,
+    pub cash: f64,
+    pub market_value: f64,
+    pub cost: f64,
+    pub unrealized: f64,
+    pub realized: f64,
+    pub dividends: f64,
+    pub holdings: Vec<Holding>, // by symbol
+}
+///one row of an imported price table
+#[derive(Debug, Clone)]
+pub struct PriceLine {
+    pub symbol: String,
+    pub date: NaiveDate,
+    pub price: f64,
+}
+///lots and sales of one security, built trade by trade
+#[derive(Debug, Clone, Default)]
+struct Position {
+    lots: Vec<Lot>,
+    sales: Vec<Sale>,
+    dividends: f64,
+    traded: Option<(NaiveDate, f64)>, // last buy or sell price, split adjusted
+    splits: Vec<(NaiveDate, f64)>,
+}
+impl Position {
+    // folded from 0.0, an empty f64 sum is -0.0
+    fn quantity(&self) -> f64 {
+        self.lots.iter().fold(0.0, |a, l| a + l.quantity)
+    }
+    fn cost(&self) -> f64 {
+        self.lots.iter().fold(0.0, |a, l| a + l.cost)
+    }
+    fn apply(&mut self, t: &Trade, method: CostBasis) {
+        match t.kind {
+            TradeKind::Buy => {
+                self.lots.push(Lot {
+                    date: t.date,
+                    quantity: t.quantity,
+                    cost: 0.0 - t.amount,
+                });
+                self.traded = Some((t.date, t.price));
+            }
+            TradeKind::Sell => {
+                // a sell beyond the units held lost its buy, only what is held is sold
+                let held = self.quantity();
+                let sold = t.quantity.min(held);
+                if sold <= UNIT_EPSILON {
+                    return;
+                }
+                let cost = match method {
+                    CostBasis::Fifo => self.take_oldest(sold),
+                    CostBasis::Average => self.take_share(sold / held),
+                };
+                let proceeds = t.amount * sold / t.quantity;
+                self.sales.push(Sale {
+                    date: t.date,
+                    quantity: sold,
+                    proceeds,
+                    cost,
+                    gain: proceeds - cost,
+                });
+                self.traded = Some((t.date, t.price));
+            }
+            TradeKind::Dividend => self.dividends += t.amount,
+            TradeKind::Split => {
+                for lot in &mut self.lots {
+                    lot.quantity *= t.quantity;
+                }
+                if let Some((_, price)) = &mut self.traded {
+                    *price /= t.quantity;
+                }
+                self.splits.push((t.date, t.quantity));
+            }
+        }
+    }
+    ///cost of `sold` units taken from the oldest lots first
+    fn take_oldest(&mut self, sold: f64) -> f64 {
+        let mut left = sold;
+        let mut cost = 0.0;
+        for lot in &mut self.lots {
+            if left <= UNIT_EPSILON {
+                break;
+            }
+            let take = lot.quantity.min(left);
+            let part = lot.cost * take / lot.quantity;
+            cost += part;
+            lot.cost -= part;
+            lot.quantity -= take;
+            left -= take;
+        }
+        self.lots.retain(|l| l.quantity > UNIT_EPSILON);
+        cost
+    }
+    ///cost of `share` of every lot, i.e. the units sold at the average cost
+    fn take_share(&mut self, share: f64) -> f64 {
+        let mut cost = 0.0;
+        for lot in &mut self.lots {
+            let part = lot.cost * share;
+            cost += part;
+            lot.cost -= part;
+            lot.quantity -= lot.quantity * share;
+        }
+        self.lots.retain(|l| l.quantity > UNIT_EPSILON);
+        cost
+    }
+}
+///read a price table, CSV with a header naming the symbol, date and price columns
+pub fn load_price_file(path: &str) -> Result<Vec<PriceLine>> {
+    let text = std::fs::read_to_string(path).map_err(|e| anyhow!("cannot read {path}: {e}"))?;
+    let lines = parse_price_csv(&text)?;
+    if lines.is_empty() {
+        return Err(anyhow!("no prices found in {path}"));
+    }
+    Ok(lines)
+}
+///price table CSV, e.g. `symbol,date,close`; other columns are ignored
+pub fn parse_price_csv(text: &str) -> Result<Vec<PriceLine>> {
+    let mut rows = text.lines().filter(|l| !l.trim().is_empty());
+    let header: Vec<String> = split_csv_record(rows.next().ok_or_else(|| anyhow!("empty CSV"))?)
+        .into_iter()
+        .map(|h| h.to_lowercase())
+        .collect();
+    let col = |names: &[&str]| header.iter().position(|h| names.contains(&h.as_str()));
+    let symbol_col = col(&["symbol", "ticker", "security"])
+        .ok_or_else(|| anyhow!("CSV has no symbol column"))?;
+    let date_col = col(&["date", "price date"]).ok_or_else(|| anyhow!("CSV has no date column"))?;
+    let price_col = col(&["price", "close", "adj close", "last"])
+        .ok_or_else(|| anyhow!("CSV has no price column"))?;
+
+    let mut result = Vec::new();
+    for (n, row) in rows.enumerate() {
+        let fields = split_csv_record(row);
+        let get = |c: usize| fields.get(c).map(|s| s.as_str()).unwrap_or_default();
+        let symbol = get(symbol_col).to_uppercase();
+        if symbol.is_empty() {
+            return Err(anyhow!("line {}: no symbol", n + 2));
+        }
+        let date = parse_statement_date(get(date_col))
+            .ok_or_else(|| anyhow!("line {}: bad date", n + 2))?;
+        let price = parse_statement_amount(get(price_col))
+            .filter(|p| *p >= 0.0)
+            .ok_or_else(|| anyhow!("line {}: bad price", n + 2))?;
+        result.push(PriceLine {
+            symbol,
+            date,
+            price,
+        });
+    }
+    Ok(result)
+}
+impl Ledger {
+    ///latest imported price of a security on or before `date`
+    pub fn price_on(&self, securityid: SecurityId, date: NaiveDate) -> Option<SecurityPrice> {
+        let start = self.price.partition_point(|p| p.securityid < securityid);
+        let end = self
+            .price
+            .partition_point(|p| (p.securityid, p.date) <= (securityid, date));
+        (end > start).then(|| self.price[end - 1])
+    }
+    ///holdings of an account at end of `date`, by symbol
+    ///
+    /// a sold out security stays listed for its realized gains and dividends
+    pub fn holdings_on(&self, accountid: AccountId, date: NaiveDate) -> Vec<Holding> {
+        let method = self
+            .account
+            .iter()
+            .find(|a| a.id == accountid)
+            .and_then(|a| a.cost_basis)
+            .unwrap_or_default();
+        let mut trades: Vec<&Trade> = self
+            .trade
+            .iter()
+            .filter(|t| t.accountid == accountid && t.date <= date)
+            .collect();
+        trades.sort_by_key(|t| (t.date, t.id));
+        let mut positions: BTreeMap<SecurityId, Position> = BTreeMap::new();
+        for t in trades {
+            positions.entry(t.securityid).or_default().apply(t, method);
+        }
+        let mut result: Vec<Holding> = positions
+            .into_iter()
+            .map(|(securityid, pos)| self.holding_of(accountid, securityid, pos, date))
+            .collect();
+        result.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        result
+    }
+    fn holding_of(
+        &self,
+        accountid: AccountId,
+        securityid: SecurityId,
+        pos: Position,
+        date: NaiveDate,
+    ) -> Holding {
+        let security = self.security.iter().find(|s| s.id == securityid);
+        // the newer of the imported and the traded price, an imported price from before a
+        // split is for the old units
+        let imported = self.price_on(securityid, date).map(|p| {
+            let ratio: f64 = pos
+                .splits
+                .iter()
+                .filter(|(d, _)| *d > p.date)
+                .map(|(_, r)| r)
+                .product();
+            (p.date, p.price / ratio)
+        });
+        let price = match (imported, pos.traded) {
+            (Some(i), Some(t)) if t.0 > i.0 => Some(t),
+            (Some(i), _) => Some(i),
+            (None, t) => t,
+        };
+        let quantity = pos.quantity();
+        let cost = pos.cost();
+        let market_value = price.map(|(_, p)| quantity * p).unwrap_or(cost);
+        Holding {
+            accountid,
+            securityid,
+            symbol: security
+                .map(|s| s.symbol.clone())
+                .unwrap_or_else(|| securityid.to_string()),
+            name: security.and_then(|s| s.name.clone()),
+            quantity,
+            cost,
+            price: price.map(|(_, p)| p),
+            price_date: price.map(|(d, _)| d),
+            market_value,
+            unrealized: market_value - cost,
+            realized: pos.sales.iter().fold(0.0, |a, s| a + s.gain),
+            dividends: pos.dividends,
+            lots: pos.lots,
+            sales: pos.sales,
+        }
+    }
+    ///market value of the holdings of an account at end of `date`, 0 without trades
+    pub fn market_value_on(&self, accountid: AccountId, date: NaiveDate) -> f64 {
+        if !self.trade.iter().any(|t| t.accountid == accountid) {
+            return 0.0;
+        }
+        self.holdings_on(accountid, date)
+            .iter()
+            .fold(0.0, |a, h| a + h.market_value)
+    }
+    ///cash balance plus market value of the holdings at end of `date`
+    pub fn value_on(&self, accountid: AccountId, date: NaiveDate) -> f64 {
+        self.balance_on(accountid, date) + self.market_value_on(accountid, date)
+    }
+    ///every investment account at end of `date`
+    pub fn portfolios(&self, date: NaiveDate) -> Vec<Portfolio> {
+        self.account
+            .iter()
+            .filter(|a| matches!(a.account_type, AccountType::Investment))
+            .map(|acc| {
+                let holdings = self.holdings_on(acc.id, date);
+                let sum = |f: fn(&Holding) -> f64| holdings.iter().map(f).fold(0.0, |a, b| a + b);
+                Portfolio {
+                    accountid: acc.id,
+                    name: acc.name.clone(),
+                    cost_basis: acc.cost_basis.unwrap_or_default(),
+                    cash: self.balance_on(acc.id, date),
+                    market_value: sum(|h| h.market_value),
+                    cost: sum(|h| h.cost),
+                    unrealized: sum(|h| h.unrealized),
+                    realized: sum(|h| h.realized),
+                    dividends: sum(|h| h.dividends),
+                    holdings,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn d(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 1, day).unwrap()
+    }
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+    fn trade(id: TradeId, kind: TradeKind, day: u32, quantity: f64, price: f64) -> Trade {
+        let fees = match kind {
+            TradeKind::Buy | TradeKind::Sell => 10.0,
+            _ => 0.0,
+        };
+        let amount = match kind {
+            TradeKind::Buy => -(quantity * price + fees),
+            TradeKind::Sell => quantity * price - fees,
+            TradeKind::Dividend => price,
+            TradeKind::Split => 0.0,
+        };
+        Trade {
+            id,
+            userid: Uuid::nil(),
+            accountid: 1,
+            securityid: 1,
+            tranid: Uuid::from_u128(id as u128),
+            kind,
+            date: d(day),
+            quantity,
+            price,
+            fees,
+            amount,
+        }
+    }
+    ///two buys of 10 units, a 2 for 1 split, then 10 of the new units sold at 70
+    fn ledger(cost_basis: CostBasis) -> Ledger {
+        let mut ledger = Ledger::default();
+        ledger.account.push(Account {
+            id: 1,
+            userid: Uuid::nil(),
+            name: "Brokerage".to_string(),
+            account_type: AccountType::Investment,
+            currency: Currency::new("cad"),
+            balance: 0.0,
+            create_date: Utc::now(),
+            credit: CreditTerms::default(),
+            loan: None,
+            cost_basis: Some(cost_basis),
+            contact_email: None,
+        });
+        ledger.security.push(Security {
+            id: 1,
+            userid: Uuid::nil(),
+            symbol: "ACME".to_string(),
+            name: None,
+        });
+        ledger.trade = vec![
+            trade(1, TradeKind::Buy, 1, 10.0, 100.0),
+            trade(2, TradeKind::Buy, 2, 10.0, 130.0),
+            trade(3, TradeKind::Split, 3, 2.0, 0.0),
+            trade(4, TradeKind::Sell, 4, 10.0, 70.0),
+            trade(5, TradeKind::Dividend, 5, 0.0, 12.5),
+        ];
+        ledger
+    }
+
+    #[test]
+    fn fifo_sells_the_oldest_split_units_first() {
+        let h = &ledger(CostBasis::Fifo).holdings_on(1, d(31))[0];
+        assert_eq!(h.symbol, "ACME");
+        assert!(close(h.quantity, 30.0));
+        let sale = h.sales[0];
+        assert!(close(sale.quantity, 10.0) && close(sale.proceeds, 690.0));
+        // half of the first lot: (10 x 100 + 10) / 2
+        assert!(close(sale.cost, 505.0));
+        assert!(close(h.realized, 185.0));
+        let lots: Vec<(f64, f64)> = h.lots.iter().map(|l| (l.quantity, l.cost)).collect();
+        assert_eq!(lots, vec![(10.0, 505.0), (20.0, 1310.0)]);
+        assert!(close(h.cost, 1815.0));
+        // the sell is the latest price, already in new units
+        assert_eq!(h.price, Some(70.0));
+        assert!(close(h.market_value, 2100.0));
+        assert!(close(h.unrealized, 285.0));
+        assert!(close(h.dividends, 12.5));
+    }
+
+    #[test]
+    fn average_cost_takes_a_share_of_every_lot() {
+        let h = &ledger(CostBasis::Average).holdings_on(1, d(31))[0];
+        assert!(close(h.quantity, 30.0));
+        // a quarter of the 2320 paid
+        assert!(close(h.sales[0].cost, 580.0));
+        assert!(close(h.realized, 110.0));
+        let lots: Vec<(f64, f64)> = h.lots.iter().map(|l| (l.quantity, l.cost)).collect();
+        assert_eq!(lots, vec![(15.0, 757.5), (15.0, 982.5)]);
+        assert!(close(h.cost, 1740.0));
+        // either way the cost sold and the cost held add up to what was paid
+        let fifo = &ledger(CostBasis::Fifo).holdings_on(1, d(31))[0];
+        assert!(close(h.sales[0].cost + h.cost, 2320.0));
+        assert!(close(fifo.sales[0].cost + fifo.cost, 2320.0));
+    }
+
+    #[test]
+    fn prices_from_before_a_split_are_for_the_old_units() {
+        let mut ledger = ledger(CostBasis::Fifo);
+        ledger.price = vec![
+            SecurityPrice {
+                securityid: 1,
+                date: d(2),
+                price: 150.0,
+            },
+            SecurityPrice {
+                securityid: 1,
+                date: d(10),
+                price: 80.0,
+            },
+        ];
+        // before the sell, the imported price is newer than the last buy
+        let h = &ledger.holdings_on(1, d(3))[0];
+        assert!(close(h.quantity, 40.0));
+        assert_eq!(h.price, Some(75.0));
+        assert!(close(h.market_value, 3000.0));
+        // a newer imported price wins over the sell
+        let h = &ledger.holdings_on(1, d(31))[0];
+        assert_eq!((h.price, h.price_date), (Some(80.0), Some(d(10))));
+        assert!(close(h.market_value, 2400.0));
+        let p = &ledger.portfolios(d(31))[0];
+        assert!(close(p.market_value, 2400.0) && close(p.realized, 185.0));
+    }
+
+    #[test]
+    fn selling_more_than_held_sells_what_is_held() {
+        let mut ledger = ledger(CostBasis::Fifo);
+        ledger.trade.push(trade(6, TradeKind::Sell, 6, 50.0, 80.0));
+        let h = &ledger.holdings_on(1, d(31))[0];
+        assert!(close(h.quantity, 0.0) && h.lots.is_empty());
+        let sale = h.sales[1];
+        assert!(close(sale.quantity, 30.0));
+        assert!(close(sale.proceeds, 3990.0 * 30.0 / 50.0));
+        assert!(close(sale.cost, 1815.0));
+    }
+}
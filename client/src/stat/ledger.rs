@@ -1,4 +1,8 @@
 use super::datatype::*;
+use super::discrepancy::*;
+use super::forecast::*;
+use super::index::*;
+use super::period::*;
 use crate::stat::datatype::{AccountId, CategoryId, UserId};
 use chrono::*;
 use std::collections::{HashMap, HashSet};
@@ -17,6 +21,44 @@ pub fn expand_month_range(mut sy: i32, mut sm: u32, ey: i32, em: u32) -> Vec<(i3
 
     result
 }
+///last day of a month
+pub fn month_end(y: i32, m: u32) -> NaiveDate {
+    let (ny, nm) = if m == 12 { (y + 1, 1) } else { (y, m + 1) };
+    NaiveDate::from_ymd_opt(ny, nm, 1)
+        .and_then(|d| d.pred_opt())
+        .unwrap_or(NaiveDate::MAX)
+}
+///sample dates between `from` and `to` (both inclusive)
+///
+/// `Monthly` gives every month end, the last point is `to` itself so a range ending mid-month still shows today
+pub fn expand_date_range(
+    from: NaiveDate,
+    to: NaiveDate,
+    granularity: Granularity,
+) -> Vec<NaiveDate> {
+    let mut result = Vec::new();
+    if from > to {
+        return result;
+    }
+    match granularity {
+        Granularity::Daily => {
+            let mut d = from;
+            while d <= to {
+                result.push(d);
+                match d.succ_opt() {
+                    Some(next) => d = next,
+                    None => break,
+                }
+            }
+        }
+        Granularity::Monthly => {
+            for (y, m) in expand_month_range(from.year(), from.month(), to.year(), to.month()) {
+                result.push(month_end(y, m).min(to));
+            }
+        }
+    }
+    result
+}
 #[derive(Debug, Clone)]
 pub struct ReconcileResult {
     pub good: bool,
@@ -24,6 +66,30 @@ pub struct ReconcileResult {
     pub external_balance: f64,
     pub difference: f64,
     pub suspicous_entry: Vec<Entry>,
+    pub candidates: Vec<DiscrepancyCandidate>, // combinations of entries that explain the difference
+}
+
+///state of a statement reconciliation for one account
+///
+/// `cleared_balance` = opening balance + entries reconciled by earlier sessions + entries ticked
+/// (cleared) up to `statement_date`, this is what the server checks when a session is finished
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct StatementReconcile {
+    pub accountid: AccountId,
+    pub statement_date: NaiveDate,
+    pub statement_balance: f64,
+    pub opening_balance: f64,
+    pub reconciled_total: f64,
+    pub cleared_total: f64,
+    pub cleared_balance: f64,
+    pub difference: f64,
+    pub entries: Vec<(NaiveDate, Entry)>,
+}
+impl StatementReconcile {
+    pub fn is_balanced(&self) -> bool {
+        self.difference.abs() < 0.005
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -41,6 +107,10 @@ impl Purpose {
         }
     }
 }
+///all data of a user
+///
+/// add or remove transactions and entries through `insert_*` / `remove_*` so `index` stays in step,
+/// only entry status may be changed in place, it is not indexed
 #[derive(Debug, Default)]
 pub struct Ledger {
     pub user: Vec<User>,
@@ -48,6 +118,12 @@ pub struct Ledger {
     pub category: Vec<Category>,
     pub transaction: Vec<Transaction>,
     pub entry: Vec<Entry>,
+    pub security: Vec<Security>,
+    pub trade: Vec<Trade>,       // oldest first, like the server sends them
+    pub price: Vec<SecurityPrice>, // sorted by security, then date
+    pub attachment: Vec<Attachment>, // oldest first
+    pub workspace: Option<Workspace>, // the one this ledger is of, none for local ledgers
+    index: StatsIndex,
 }
 #[derive(Debug, Clone)]
 pub struct AccountSummary {
@@ -57,13 +133,61 @@ pub struct AccountSummary {
     pub balance: f64,
     pub currency: Currency,
 }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Granularity {
+    Daily,
+    Monthly,
+}
+///running balance of one account, one value per axis date
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct BalanceSeries {
+    pub accountid: AccountId,
+    pub name: String,
+    pub account_type: AccountType,
+    pub axis: Vec<NaiveDate>,
+    pub balance: Vec<f64>,
+}
+///net worth over time, `liabilities` is what is owed on credit accounts (positive when in debt)
+#[derive(Debug, Clone)]
+pub struct NetWorthSeries {
+    pub axis: Vec<NaiveDate>,
+    pub assets: Vec<f64>,
+    pub liabilities: Vec<f64>,
+    pub net_worth: Vec<f64>,
+}
 #[derive(Debug, Clone, Default)]
 pub struct Detailstats {
     pub income: f64,
     pub outcome: f64,
     pub summary: f64,
+    pub count: usize, // entries counted
 }
 impl Detailstats {
+    ///count `amount` in (`sign` = 1.0) or take it out again (`sign` = -1.0)
+    pub fn apply(&mut self, amount: f64, sign: f64) {
+        if amount >= 0.0 {
+            self.income += sign * amount;
+        } else {
+            self.outcome += sign * amount;
+        }
+        if sign > 0.0 {
+            self.count += 1;
+        } else {
+            self.count = self.count.saturating_sub(1);
+        }
+        self.summary = self.income + self.outcome;
+        // nothing left, drop the rounding noise of add then subtract
+        if self.count == 0 {
+            *self = Detailstats::default();
+        }
+    }
+    pub fn merge(&mut self, other: &Detailstats) {
+        self.income += other.income;
+        self.outcome += other.outcome;
+        self.summary = self.income + self.outcome;
+        self.count += other.count;
+    }
     fn get(&self, purpose: Purpose) -> f64 {
         match purpose {
             Purpose::All => self.summary,
@@ -77,11 +201,49 @@ pub struct Monthstats {
     pub income: f64,
     pub outcome: f64,
     pub summary: f64,
+    pub count: usize,
     pub category: HashMap<Option<CategoryId>, Detailstats>,
     pub account: HashMap<AccountId, Detailstats>,
     pub account_category: HashMap<(AccountId, Option<CategoryId>), Detailstats>,
 }
 impl Monthstats {
+    pub fn add(&mut self, i: &Entry) {
+        self.apply(i, 1.0);
+    }
+    pub fn remove(&mut self, i: &Entry) {
+        self.apply(i, -1.0);
+    }
+    fn apply(&mut self, i: &Entry, sign: f64) {
+        let mut total = Detailstats {
+            income: self.income,
+            outcome: self.outcome,
+            summary: self.summary,
+            count: self.count,
+        };
+        total.apply(i.amount, sign);
+        self.income = total.income;
+        self.outcome = total.outcome;
+        self.summary = total.summary;
+        self.count = total.count;
+        let cat = i.categoryid;
+        let acc = i.accountid;
+        // keys without entries are removed, so the pie charts never list an emptied category
+        let catstat = self.category.entry(cat).or_default();
+        catstat.apply(i.amount, sign);
+        if catstat.count == 0 {
+            self.category.remove(&cat);
+        }
+        let acctat = self.account.entry(acc).or_default();
+        acctat.apply(i.amount, sign);
+        if acctat.count == 0 {
+            self.account.remove(&acc);
+        }
+        let acccattat = self.account_category.entry((acc, cat)).or_default();
+        acccattat.apply(i.amount, sign);
+        if acccattat.count == 0 {
+            self.account_category.remove(&(acc, cat));
+        }
+    }
     fn get(&self, purpose: Purpose) -> f64 {
         match purpose {
             Purpose::All => self.summary,
@@ -159,24 +321,121 @@ impl<K: Clone> Trend<K> {
         };
     }
 }
-///last n month time phase range
-pub fn timephase_fromnow(n: u32) -> ((i32, u32), (i32, u32)) {
-    let now = Utc::now().date_naive();
-    let now_y = now.year();
-    let now_m = now.month();
-    let mut from_y = now_y;
-    let mut from_m = now_m;
-    for _ in 1..n {
-        if from_m == 1 {
-            from_m = 12;
-            from_y -= 1;
-        } else {
-            from_m -= 1;
-        }
+///a price change of a subscription, amounts are negative like the charges
+#[derive(Debug, Clone, Copy)]
+pub struct PriceChange {
+    pub from: f64,
+    pub to: f64,
+    pub date: NaiveDate, // first charge at the new price
+}
+///a recurring spending charge of one payee on one account, e.g. a streaming service or a gym
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Subscription {
+    pub accountid: AccountId,
+    pub categoryid: Option<CategoryId>,
+    pub payee: String,
+    pub cadence: Cadence,
+    pub count: usize,
+    pub average: f64, // mean of every charge
+    pub latest: f64,  // the last charge, i.e. the current price
+    pub annual: f64,  // latest charge times charges per year
+    pub first_date: NaiveDate,
+    pub last_date: NaiveDate,
+    pub next_date: NaiveDate,
+    pub active: bool, // the next charge is not overdue
+    pub increase: Option<PriceChange>,
+}
+///the latest price when it is higher than the one before
+///
+/// both prices must have held for a while (two charges at the old price, the new one up to now),
+/// so charges whose amount moves every time, like groceries, are not reported
+fn price_increase(charges: &[(NaiveDate, f64)]) -> Option<PriceChange> {
+    let same = |a: f64, b: f64| (a - b).abs() < 0.005;
+    let (_, latest) = *charges.last()?;
+    let start = charges
+        .iter()
+        .rposition(|c| !same(c.1, latest))
+        .map(|i| i + 1)?;
+    if start < 2 || !same(charges[start - 1].1, charges[start - 2].1) {
+        return None;
+    }
+    let old = charges[start - 1].1;
+    match latest.abs() > old.abs() {
+        true => Some(PriceChange {
+            from: old,
+            to: latest,
+            date: charges[start].0,
+        }),
+        false => None,
     }
-    ((from_y, from_m), (now_y, now_m))
+}
+///last n month time phase range
+pub fn timephase_fromnow(n: u32) -> DateRange {
+    DateRange::last_months(Utc::now().date_naive(), n)
 }
 impl Ledger {
+    pub fn new(
+        user: Vec<User>,
+        account: Vec<Account>,
+        category: Vec<Category>,
+        transaction: Vec<Transaction>,
+        entry: Vec<Entry>,
+    ) -> Self {
+        let index = StatsIndex::build(&transaction, &entry);
+        Ledger {
+            user,
+            account,
+            category,
+            transaction,
+            entry,
+            security: Vec::new(),
+            trade: Vec::new(),
+            price: Vec::new(),
+            attachment: Vec::new(),
+            workspace: None,
+            index,
+        }
+    }
+    ///add a transaction, or replace the one with the same id
+    pub fn insert_transaction(&mut self, t: Transaction) {
+        // the index knows every id, new ones skip the linear search
+        let known = self.index.has_transaction(t.id);
+        self.index.insert_transaction(t.id, t.occur_date);
+        let slot = match known {
+            true => self.transaction.iter_mut().find(|i| i.id == t.id),
+            false => None,
+        };
+        match slot {
+            Some(old) => *old = t,
+            None => self.transaction.push(t),
+        }
+    }
+    ///remove a transaction, all of its entries, its trade and its attachments
+    pub fn remove_transaction(&mut self, txid: TransactionId) {
+        self.index.remove_transaction(txid);
+        self.transaction.retain(|i| i.id != txid);
+        self.entry.retain(|i| i.tranid != txid);
+        self.trade.retain(|i| i.tranid != txid);
+        self.attachment.retain(|i| i.tranid != txid);
+    }
+    ///add an entry, or replace the one with the same id
+    pub fn insert_entry(&mut self, e: Entry) {
+        let known = self.index.has_entry(e.id);
+        self.index.insert_entry(&e);
+        let slot = match known {
+            true => self.entry.iter_mut().find(|i| i.id == e.id),
+            false => None,
+        };
+        match slot {
+            Some(old) => *old = e,
+            None => self.entry.push(e),
+        }
+    }
+    pub fn remove_entry(&mut self, id: EntryId) {
+        self.index.remove_entry(id);
+        self.entry.retain(|i| i.id != id);
+    }
     fn filter_value(
         s: &Monthstats,
         accountid: Option<AccountId>,
@@ -220,7 +479,7 @@ impl Ledger {
         txt.push_str(&accid.to_string());
         return txt;
     }
-    ///return account current balance
+    ///return account current balance, holdings of investment accounts at market value
     pub fn cal_balance(&self, accountid: AccountId) -> f64 {
         let current = self
             .account
@@ -228,18 +487,14 @@ impl Ledger {
             .find(|i| i.id == accountid)
             .map(|i| i.balance)
             .unwrap_or(0.0);
-        let temp: f64 = self
-            .entry
-            .iter()
-            .filter(|i| i.accountid == accountid)
-            .map(|i| i.amount)
-            .sum();
-        return current + temp;
+        let temp = self.index.account_until(accountid, NaiveDate::MAX);
+        current + temp + self.market_value_on(accountid, NaiveDate::MAX)
     }
-    /// build  summary for all accounts in ledger
+    /// build  summary for all accounts in ledger, contacts are left to `contact_balances`
     pub fn all_account_summary(&self) -> Vec<AccountSummary> {
         self.account
             .iter()
+            .filter(|i| !i.account_type.is_contact())
             .map(|i| AccountSummary {
                 accountid: i.id,
                 name: i.name.clone(),
@@ -249,71 +504,158 @@ impl Ledger {
             })
             .collect()
     }
-    ///  month statistics for user within a time range
-    pub fn monthstats(
+    ///account balance at end of `date`: opening balance plus every entry dated on or before it
+    pub fn balance_on(&self, accountid: AccountId, date: NaiveDate) -> f64 {
+        let opening = self
+            .account
+            .iter()
+            .find(|i| i.id == accountid)
+            .map(|i| i.balance)
+            .unwrap_or(0.0);
+        opening + self.index.account_until(accountid, date)
+    }
+    ///net change of an account on each day of `range` that has entries, oldest first
+    pub fn account_moves(&self, accountid: AccountId, range: DateRange) -> Vec<(NaiveDate, f64)> {
+        self.index.account_moves(accountid, range)
+    }
+    ///summary for all accounts as they stood at end of `date`, contacts left out
+    pub fn all_account_summary_on(&self, date: NaiveDate) -> Vec<AccountSummary> {
+        self.account
+            .iter()
+            .filter(|i| !i.account_type.is_contact())
+            .map(|i| AccountSummary {
+                accountid: i.id,
+                name: i.name.clone(),
+                account_type: i.account_type.clone(),
+                balance: self.value_on(i.id, date),
+                currency: i.currency.clone(),
+            })
+            .collect()
+    }
+    ///running balance for every account of user, sampled daily or at month ends
+    ///
+    /// balance before `from` is carried in, so the first point is the real balance on that day;
+    /// investment accounts add the market value of their holdings on each date; contacts are
+    /// not the user's money and are skipped
+    pub fn balance_series(
         &self,
         userid: UserId,
-        timephase: ((i32, u32), (i32, u32)),
-    ) -> HashMap<(i32, u32), Monthstats> {
-        let start = timephase.0;
-        let end = timephase.1;
-        let sy = start.0;
-        let sm = start.1;
-        let ey = end.0;
-        let em = end.1;
-        let phase = expand_month_range(sy, sm, ey, em);
-        let mut trans: HashMap<TransactionId, (i32, u32)> = HashMap::new();
-        for i in &self.transaction {
-            trans.insert(i.id, (i.occur_date.year(), i.occur_date.month()));
-        }
-        let mut stats: HashMap<(i32, u32), Monthstats> = HashMap::new();
-        for i in &self.entry {
-            if i.userid != userid {
+        from: NaiveDate,
+        to: NaiveDate,
+        granularity: Granularity,
+    ) -> Vec<BalanceSeries> {
+        let axis = expand_date_range(from, to, granularity);
+        let mut result = Vec::new();
+        for acc in &self.account {
+            if acc.userid != userid || acc.account_type.is_contact() {
                 continue;
             }
-            let (y, m) = match trans.get(&i.tranid) {
-                Some(&(y1, m1)) => (y1, m1),
-                None => continue,
+            let moves = self.index.account_moves(acc.id, DateRange::new(from, to));
+            // walk daily moves once, axis is sorted too
+            let mut running = match from.pred_opt() {
+                Some(before) => self.balance_on(acc.id, before),
+                None => acc.balance,
             };
-            if !phase.contains(&(y, m)) {
-                continue;
+            let mut next = 0;
+            let mut balance = Vec::new();
+            let invested = self.trade.iter().any(|t| t.accountid == acc.id);
+            for d in &axis {
+                while next < moves.len() && moves[next].0 <= *d {
+                    running += moves[next].1;
+                    next += 1;
+                }
+                balance.push(match invested {
+                    true => running + self.market_value_on(acc.id, *d),
+                    false => running,
+                });
             }
-            let temp = stats.entry((y, m)).or_insert(Monthstats::default());
-            let cat = i.categoryid;
-            let catstat = temp.category.entry(cat).or_insert(Detailstats::default());
-            let acc = i.accountid;
-            let acctat = temp.account.entry(acc).or_insert(Detailstats::default());
-            let acc_cat = (acc, cat);
-            let acccattat = temp
-                .account_category
-                .entry(acc_cat)
-                .or_insert(Detailstats::default());
-            if i.amount >= 0.0 {
-                temp.income += i.amount;
-                catstat.income += i.amount;
-                acctat.income += i.amount;
-                acccattat.income += i.amount;
-            } else {
-                temp.outcome += i.amount;
-                catstat.outcome += i.amount;
-                acctat.outcome += i.amount;
-                acccattat.outcome += i.amount;
+            result.push(BalanceSeries {
+                accountid: acc.id,
+                name: acc.name.clone(),
+                account_type: acc.account_type.clone(),
+                axis: axis.clone(),
+                balance,
+            });
+        }
+        result
+    }
+    ///assets, liabilities and net worth over time
+    ///
+    /// credit and loan accounts are liabilities, a negative balance (money owed) adds to
+    /// `liabilities`; every other account type is an asset, investments at market value;
+    /// contacts count in neither
+    pub fn networth_series(
+        &self,
+        userid: UserId,
+        from: NaiveDate,
+        to: NaiveDate,
+        granularity: Granularity,
+    ) -> NetWorthSeries {
+        let axis = expand_date_range(from, to, granularity);
+        let mut assets = vec![0.0; axis.len()];
+        let mut liabilities = vec![0.0; axis.len()];
+        for s in self.balance_series(userid, from, to, granularity) {
+            let liability = s.account_type.is_liability();
+            for (idx, b) in s.balance.iter().enumerate() {
+                if liability {
+                    liabilities[idx] -= *b;
+                } else {
+                    assets[idx] += *b;
+                }
             }
-            temp.summary = temp.income + temp.outcome;
-            catstat.summary = catstat.income + catstat.outcome;
-            acctat.summary = acctat.income + acctat.outcome;
-            acccattat.summary = acccattat.income + acccattat.outcome;
         }
-        for &(y, m) in &phase {
-            stats.entry((y, m)).or_insert(Monthstats::default());
+        let net_worth = assets
+            .iter()
+            .zip(&liabilities)
+            .map(|(a, l)| a - l)
+            .collect();
+        NetWorthSeries {
+            axis,
+            assets,
+            liabilities,
+            net_worth,
         }
-        return stats;
+    }
+    ///income, outcome and net over `range`, read from the index
+    fn range_value(
+        &self,
+        userid: UserId,
+        range: DateRange,
+        accountid: Option<AccountId>,
+        category: Option<CategoryId>,
+    ) -> Detailstats {
+        let mut v = Detailstats::default();
+        self.index.visit(userid, range, |s| {
+            v.income += Self::filter_value(s, accountid, category, Purpose::Income);
+            v.outcome += Self::filter_value(s, accountid, category, Purpose::Outcome);
+        });
+        v.summary = v.income + v.outcome;
+        v
+    }
+    ///every total of `range` at once: overall, per category, per account and per both
+    pub fn range_stats(&self, userid: UserId, range: impl Into<DateRange>) -> Monthstats {
+        let mut total = Monthstats::default();
+        self.index.visit(userid, range.into(), |s| {
+            total.income += s.income;
+            total.outcome += s.outcome;
+            total.count += s.count;
+            for (k, v) in &s.category {
+                total.category.entry(*k).or_default().merge(v);
+            }
+            for (k, v) in &s.account {
+                total.account.entry(*k).or_default().merge(v);
+            }
+            for (k, v) in &s.account_category {
+                total.account_category.entry(*k).or_default().merge(v);
+            }
+        });
+        total.summary = total.income + total.outcome;
+        total
     }
     ///return statistics value
     ///
-    /// `timephase`
-    /// - if `timephase` is not provided，only search year/month data
-    /// - if provide `timephase`, year/month will be overwritten, will search months between start (year,month) to end (year, month)
+    /// `range`
+    /// - days to count, month tuples `((y, m), (y, m))` convert into whole months
     ///
     /// `category`
     /// - if `category` is not provided, search all category instead
@@ -326,27 +668,19 @@ impl Ledger {
     pub fn month_summary(
         &self,
         userid: UserId,
-        year: i32,
-        month: u32,
+        range: impl Into<DateRange>,
         accountid: Option<AccountId>,
         category: Option<CategoryId>,
         onlyspend: Option<bool>,
-        timephase: Option<((i32, u32), (i32, u32))>,
     ) -> f64 {
-        let phase = timephase.unwrap_or(((year, month), (year, month)));
         let purpose = Purpose::trans(onlyspend);
-        let stat = self.monthstats(userid, phase);
-        let mut total = 0.0;
-        for (_, s) in stat.iter() {
-            let val = Self::filter_value(s, accountid, category, purpose);
-            total += val;
-        }
-        total
+        self.range_value(userid, range.into(), accountid, category)
+            .get(purpose)
     }
     ///use to draw line graph
     ///
-    /// `timephase`
-    /// - return data between `timephase`
+    /// `range`
+    /// - return data between `range`, one point per month
     ///
     /// `accountid`
     /// - if not given, defult use all account under user id
@@ -358,75 +692,82 @@ impl Ledger {
     pub fn data_linetrend(
         &self,
         userid: UserId,
-        timephase: ((i32, u32), (i32, u32)),
+        range: impl Into<DateRange>,
         accountid: Option<AccountId>,
         category: Option<CategoryId>,
     ) -> Trend<(i32, u32)> {
-        let stat = self.monthstats(userid, timephase);
-        let mut k: Vec<(i32, u32)> = stat.keys().cloned().collect();
-        k.sort();
-
-        let mut date = Vec::new();
-        let mut inc = Vec::new();
-        let mut out = Vec::new();
-        let mut sum = Vec::new();
-        for element in k {
-            let v = match stat.get(&element) {
-                Some(v) => v,
-                None => {
-                    continue;
-                }
-            };
-            let v_inc = Self::filter_value(v, accountid, category, Purpose::Income);
-            let v_out = Self::filter_value(v, accountid, category, Purpose::Outcome);
-            let v_sum = Self::filter_value(v, accountid, category, Purpose::All);
-            date.push(element);
-            inc.push(v_inc);
-            out.push(v_out);
-            sum.push(v_sum);
-        }
+        let trend = self.data_periodtrend(userid, range, Period::Month, accountid, category);
         Trend {
-            axis: date,
-            income: inc,
-            outcome: out,
-            summary: sum,
+            axis: trend
+                .axis
+                .iter()
+                .map(|r| (r.from.year(), r.from.month()))
+                .collect(),
+            income: trend.income,
+            outcome: trend.outcome,
+            summary: trend.summary,
         }
     }
-    ///use to draw pie graph-mutiple category,cross months
+    ///like `data_linetrend`, one point per `period` (week, quarter, statement cycle ...)
+    ///
+    /// the first and last bucket only count the days inside `range`
+    pub fn data_periodtrend(
+        &self,
+        userid: UserId,
+        range: impl Into<DateRange>,
+        period: Period,
+        accountid: Option<AccountId>,
+        category: Option<CategoryId>,
+    ) -> Trend<DateRange> {
+        let mut trend = Trend {
+            axis: Vec::new(),
+            income: Vec::new(),
+            outcome: Vec::new(),
+            summary: Vec::new(),
+        };
+        for bucket in range.into().split(period) {
+            let v = self.range_value(userid, bucket, accountid, category);
+            trend.axis.push(bucket);
+            trend.income.push(v.income);
+            trend.outcome.push(v.outcome);
+            trend.summary.push(v.summary);
+        }
+        trend
+    }
+    ///use to draw pie graph-mutiple category,cross months, axis is (name, id)
     pub fn category_pietrend(
         &self,
         userid: UserId,
-        timephase: ((i32, u32), (i32, u32)),
+        range: impl Into<DateRange>,
         accountid: Option<AccountId>,
-    ) -> Trend<String> {
-        let stat = self.monthstats(userid, timephase);
-        let mut set = HashSet::<CategoryId>::new();
-        for i in stat.values() {
-            for key in i.category.keys() {
-                if let Some(cat) = key {
-                    set.insert(*cat);
+    ) -> Trend<(String, CategoryId)> {
+        // every category used in range, a category without entries on `accountid` shows as 0
+        let mut totals: HashMap<CategoryId, Detailstats> = HashMap::new();
+        self.index.visit(userid, range.into(), |s| {
+            for (key, v) in &s.category {
+                let Some(cat) = key else {
+                    continue;
+                };
+                let total = totals.entry(*cat).or_default();
+                match accountid {
+                    None => total.merge(v),
+                    Some(acc) => {
+                        if let Some(v) = s.account_category.get(&(acc, *key)) {
+                            total.merge(v);
+                        }
+                    }
                 }
             }
-        }
+        });
         let mut axis = Vec::new();
         let mut inc = Vec::new();
         let mut out = Vec::new();
         let mut sum = Vec::new();
-        for i in set {
-            let mut v_inc = 0.0;
-            let mut v_out = 0.0;
-            let mut v_sum = 0.0;
-
-            for j in stat.values() {
-                v_inc += Self::filter_value(j, accountid, Some(i), Purpose::Income);
-                v_out += Self::filter_value(j, accountid, Some(i), Purpose::Outcome);
-                v_sum += Self::filter_value(j, accountid, Some(i), Purpose::All);
-            }
-
-            axis.push(self.trans_categoryid2name(i));
-            inc.push(v_inc);
-            out.push(v_out);
-            sum.push(v_sum);
+        for (i, v) in totals {
+            axis.push((self.trans_categoryid2name(i), i));
+            inc.push(v.income);
+            out.push(v.outcome);
+            sum.push(v.summary);
         }
         return Trend {
             axis: axis,
@@ -435,40 +776,46 @@ impl Ledger {
             summary: sum,
         };
     }
-    ///use to draw pie graph-mutiple account,cross months
+    ///use to draw pie graph-mutiple account,cross months, axis is (name, id), contacts left out
     pub fn account_pietrend(
         &self,
         userid: UserId,
-        timephase: ((i32, u32), (i32, u32)),
+        range: impl Into<DateRange>,
         category: Option<CategoryId>,
-    ) -> Trend<String> {
-        let stat = self.monthstats(userid, timephase);
-        let mut set = HashSet::<AccountId>::new();
-        for i in stat.values() {
-            for key in i.account.keys() {
-                let acc = key;
-                set.insert(*acc);
+    ) -> Trend<(String, AccountId)> {
+        let contacts: HashSet<AccountId> = self
+            .account
+            .iter()
+            .filter(|a| a.account_type.is_contact())
+            .map(|a| a.id)
+            .collect();
+        // every account used in range, an account without entries in `category` shows as 0
+        let mut totals: HashMap<AccountId, Detailstats> = HashMap::new();
+        self.index.visit(userid, range.into(), |s| {
+            for (acc, v) in &s.account {
+                if contacts.contains(acc) {
+                    continue;
+                }
+                let total = totals.entry(*acc).or_default();
+                match category {
+                    None => total.merge(v),
+                    Some(cat) => {
+                        if let Some(v) = s.account_category.get(&(*acc, Some(cat))) {
+                            total.merge(v);
+                        }
+                    }
+                }
             }
-        }
+        });
         let mut axis = Vec::new();
         let mut inc = Vec::new();
         let mut out = Vec::new();
         let mut sum = Vec::new();
-        for i in set {
-            let mut v_inc = 0.0;
-            let mut v_out = 0.0;
-            let mut v_sum = 0.0;
-
-            for j in stat.values() {
-                v_inc += Self::filter_value(j, Some(i), category, Purpose::Income);
-                v_out += Self::filter_value(j, Some(i), category, Purpose::Outcome);
-                v_sum += Self::filter_value(j, Some(i), category, Purpose::All);
-            }
-
-            axis.push(self.trans_accountid2name(i));
-            inc.push(v_inc);
-            out.push(v_out);
-            sum.push(v_sum);
+        for (i, v) in totals {
+            axis.push((self.trans_accountid2name(i), i));
+            inc.push(v.income);
+            out.push(v.outcome);
+            sum.push(v.summary);
         }
         return Trend {
             axis: axis,
@@ -477,8 +824,8 @@ impl Ledger {
             summary: sum,
         };
     }
-    ///rank topk
-    fn rank_trend<K: Clone>(trend: Trend<K>, purpose: Purpose, top_k: usize) -> Trend<K> {
+    ///rank topk, ties by name (then id) like the server's reports
+    fn rank_trend<K: Clone + Ord>(trend: Trend<K>, purpose: Purpose, top_k: usize) -> Trend<K> {
         let len = trend.axis.len();
         if len == 0 || top_k == 0 {
             return trend;
@@ -501,6 +848,7 @@ impl Ledger {
                 Purpose::Outcome => trend.outcome[j].abs(),
             };
             v_j.total_cmp(&v_i)
+                .then_with(|| trend.axis[i].cmp(&trend.axis[j]))
         });
         temp.truncate(k);
         let mut axis = Vec::new();
@@ -527,12 +875,12 @@ impl Ledger {
     pub fn top_category(
         &self,
         userid: UserId,
-        timephase: ((i32, u32), (i32, u32)),
+        range: impl Into<DateRange>,
         accountid: Option<AccountId>,
         top_k: usize,
         onlyspend: Option<bool>,
-    ) -> Trend<String> {
-        let temp = self.category_pietrend(userid, timephase, accountid);
+    ) -> Trend<(String, CategoryId)> {
+        let temp = self.category_pietrend(userid, range, accountid);
         let purpose = Purpose::trans(onlyspend);
         Self::rank_trend(temp, purpose, top_k)
     }
@@ -543,43 +891,69 @@ impl Ledger {
     pub fn top_account(
         &self,
         userid: UserId,
-        timephase: ((i32, u32), (i32, u32)),
+        range: impl Into<DateRange>,
         category: Option<CategoryId>,
         top_k: usize,
         onlyspend: Option<bool>,
-    ) -> Trend<String> {
-        let temp = self.account_pietrend(userid, timephase, category);
+    ) -> Trend<(String, AccountId)> {
+        let temp = self.account_pietrend(userid, range, category);
         let purpose = Purpose::trans(onlyspend);
         Self::rank_trend(temp, purpose, top_k)
     }
+    ///recurring spending charges inside `range`, the ones still active first, then by yearly cost
+    ///
+    /// built on `recurring_patterns`, a charge counts as active while its next date is not overdue
+    /// at the end of `range`
+    pub fn subscriptions(&self, userid: UserId, range: impl Into<DateRange>) -> Vec<Subscription> {
+        let range = range.into();
+        let mut result: Vec<Subscription> = self
+            .recurring_patterns(userid, range)
+            .into_iter()
+            .filter(|p| p.amount < 0.0)
+            .map(|p| {
+                let amounts: Vec<f64> = p.charges.iter().map(|c| c.1).collect();
+                let latest = amounts.last().copied().unwrap_or(0.0);
+                Subscription {
+                    accountid: p.accountid,
+                    categoryid: p.categoryid,
+                    payee: p.payee.clone(),
+                    cadence: p.cadence,
+                    count: p.charges.len(),
+                    average: amounts.iter().sum::<f64>() / amounts.len().max(1) as f64,
+                    latest,
+                    annual: latest * p.cadence.per_year(),
+                    first_date: p.charges.first().map(|c| c.0).unwrap_or(range.from),
+                    last_date: p.last_date(),
+                    next_date: p.next_date(),
+                    active: p.is_active(range.to),
+                    increase: price_increase(&p.charges),
+                }
+            })
+            .collect();
+        result.sort_by(|a, b| {
+            b.active
+                .cmp(&a.active)
+                .then(a.annual.total_cmp(&b.annual))
+                .then(a.payee.cmp(&b.payee))
+        });
+        result
+    }
     ///find entry closest to diff
     fn reconcile_supicous_entry(
         &self,
         userid: UserId,
         accountid: Option<AccountId>,
-        timephase: ((i32, u32), (i32, u32)),
+        range: DateRange,
         difference: f64,
         top_k: usize,
     ) -> Vec<Entry> {
         if top_k == 0 {
             return Vec::new();
         }
-        let start = timephase.0;
-        let end = timephase.1;
-        let sy = start.0;
-        let sm = start.1;
-        let ey = end.0;
-        let em = end.1;
-        let phase = expand_month_range(sy, sm, ey, em);
-        let mut hash_phase = HashSet::new();
-        for i in phase {
-            hash_phase.insert(i);
-        }
-
         let mut trans = HashSet::new();
         for i in &self.transaction {
             if i.userid == userid {
-                if hash_phase.contains(&(i.occur_date.year(), i.occur_date.month())) {
+                if range.contains(i.occur_date) {
                     trans.insert(i.id);
                 }
             }
@@ -606,23 +980,28 @@ impl Ledger {
         return result;
     }
     ///compare internal vs external
+    ///
+    /// the external balance is a closing balance, so the internal one is the balance the day
+    /// before `range` plus the net of `range`; `accountid` None adds up every account of user
     pub fn reconcile(
         &self,
         userid: UserId,
         accountid: Option<AccountId>,
         external_balance: f64,
-        timephase: ((i32, u32), (i32, u32)),
+        range: impl Into<DateRange>,
         top_k: usize,
     ) -> ReconcileResult {
-        let internal_ban = self.month_summary(
-            userid,
-            timephase.0.0,
-            timephase.0.1,
-            accountid,
-            None,
-            None,
-            Some(timephase),
-        );
+        let range = range.into();
+        let opening: f64 = self
+            .account
+            .iter()
+            .filter(|a| a.userid == userid && accountid.is_none_or(|id| id == a.id))
+            .map(|a| match range.from.pred_opt() {
+                Some(before) => self.balance_on(a.id, before),
+                None => a.balance,
+            })
+            .sum();
+        let internal_ban = opening + self.month_summary(userid, range, accountid, None, None);
         let diff = external_balance - internal_ban;
         if diff.abs() <= 0.01 {
             return ReconcileResult {
@@ -631,16 +1010,357 @@ impl Ledger {
                 external_balance: external_balance,
                 difference: diff,
                 suspicous_entry: Vec::new(),
+                candidates: Vec::new(),
             };
         } else {
-            let cad = self.reconcile_supicous_entry(userid, accountid, timephase, diff, top_k);
+            let cad = self.reconcile_supicous_entry(userid, accountid, range, diff, top_k);
+            let cfg = DiscrepancySearch {
+                top_k,
+                ..DiscrepancySearch::default()
+            };
+            let candidates = self.explain_difference(userid, accountid, range, diff, cfg);
             return ReconcileResult {
                 good: false,
                 internal_balance: internal_ban,
                 external_balance: external_balance,
                 difference: diff,
                 suspicous_entry: cad,
+                candidates,
+            };
+        }
+    }
+    ///reconcile one account against a statement (end date + closing balance)
+    ///
+    /// `entries` are the not yet reconciled entries dated on or before `statement_date`, oldest first,
+    /// these are the ones to tick off
+    pub fn statement_reconcile(
+        &self,
+        accountid: AccountId,
+        statement_date: NaiveDate,
+        statement_balance: f64,
+    ) -> StatementReconcile {
+        let opening_balance = self
+            .account
+            .iter()
+            .find(|i| i.id == accountid)
+            .map(|i| i.balance)
+            .unwrap_or(0.0);
+        let dates: HashMap<TransactionId, NaiveDate> = self
+            .transaction
+            .iter()
+            .map(|i| (i.id, i.occur_date))
+            .collect();
+        let mut reconciled_total = 0.0;
+        let mut cleared_total = 0.0;
+        let mut entries = Vec::new();
+        for i in &self.entry {
+            if i.accountid != accountid {
+                continue;
+            }
+            let Some(&date) = dates.get(&i.tranid) else {
+                continue;
             };
+            match i.status {
+                // locked by an earlier statement, counts whatever its date
+                EntryStatus::Reconciled => reconciled_total += i.amount,
+                _ if date > statement_date => {}
+                EntryStatus::Cleared => {
+                    cleared_total += i.amount;
+                    entries.push((date, i.clone()));
+                }
+                EntryStatus::Uncleared => entries.push((date, i.clone())),
+            }
+        }
+        entries.sort_by_key(|(d, e)| (*d, e.id));
+        let cleared_balance = opening_balance + reconciled_total + cleared_total;
+        StatementReconcile {
+            accountid,
+            statement_date,
+            statement_balance,
+            opening_balance,
+            reconciled_total,
+            cleared_total,
+            cleared_balance,
+            difference: statement_balance - cleared_balance,
+            entries,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn d(y: i32, m: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, day).unwrap()
+    }
+    ///a chequing account opened with 1000, one entry per (date, amount), each its own payee
+    fn ledger(moves: &[(NaiveDate, f64)]) -> Ledger {
+        let payees: Vec<String> = (0..moves.len()).map(|i| format!("payee {i}")).collect();
+        let moves: Vec<_> = moves
+            .iter()
+            .zip(&payees)
+            .map(|(&(date, amount), payee)| (date, amount, payee.as_str()))
+            .collect();
+        ledger_with_payees(&moves)
+    }
+    ///same account, one entry per (date, amount, payee)
+    fn ledger_with_payees(moves: &[(NaiveDate, f64, &str)]) -> Ledger {
+        let chequing = account(1, "Chequing", AccountType::Checking, 1000.0);
+        let moves: Vec<_> = moves
+            .iter()
+            .map(|&(date, amount, payee)| (date, 1, amount, payee))
+            .collect();
+        accounts_ledger(vec![chequing], &moves)
+    }
+    fn account(id: AccountId, name: &str, account_type: AccountType, opening: f64) -> Account {
+        Account {
+            id,
+            userid: Uuid::nil(),
+            name: name.to_string(),
+            account_type,
+            currency: Currency::new("CAD"),
+            balance: opening,
+            create_date: Utc::now(),
+            credit: CreditTerms::default(),
+            loan: None,
+            cost_basis: None,
+            contact_email: None,
+        }
+    }
+    ///one single entry transaction per (date, account, amount, payee)
+    fn accounts_ledger(
+        account: Vec<Account>,
+        moves: &[(NaiveDate, AccountId, f64, &str)],
+    ) -> Ledger {
+        let (mut transaction, mut entry) = (Vec::new(), Vec::new());
+        for (i, (date, accountid, amount, payee)) in moves.iter().enumerate() {
+            let tranid = Uuid::from_u128(i as u128 + 1);
+            transaction.push(Transaction {
+                id: tranid,
+                userid: Uuid::nil(),
+                occur_date: *date,
+                receiver: Some(payee.to_string()),
+                desc: None,
+                create_date: Utc::now(),
+            });
+            entry.push(Entry {
+                id: i as i64 + 1,
+                userid: Uuid::nil(),
+                tranid,
+                accountid: *accountid,
+                categoryid: None,
+                amount: *amount,
+                desc: None,
+                status: EntryStatus::Cleared,
+            });
+        }
+        Ledger::new(Vec::new(), account, Vec::new(), transaction, entry)
+    }
+
+    #[test]
+    fn reconcile_carries_the_balance_before_the_range() {
+        let ledger = ledger(&[
+            (d(2025, 9, 20), -200.0),
+            (d(2025, 10, 3), -45.5),
+            (d(2025, 10, 15), 1500.0),
+        ]);
+        let october = (d(2025, 10, 1), d(2025, 10, 31));
+        // 1000 opening, -200 in September, +1454.50 in October
+        let res = ledger.reconcile(Uuid::nil(), Some(1), 2254.5, october, 5);
+        assert!(res.good);
+        assert!((res.internal_balance - 2254.5).abs() < 1e-9);
+
+        // the October statement missed the 45.50 charge
+        let res = ledger.reconcile(Uuid::nil(), Some(1), 2300.0, october, 5);
+        assert!(!res.good);
+        assert!((res.difference - 45.5).abs() < 1e-9);
+        assert_eq!(res.candidates[0].steps[0].entry.id, 2);
+    }
+
+    ///two years and nine months of charges on the chequing account
+    /// - Spotify: 10.99 on the 12th of every month of 2025
+    /// - Netflix: 15.99 on the 5th from January to June 2025, 17.99 from July
+    /// - a domain renewed for 20 every March 1st since 2023
+    /// - a gym at 40 a month from January to April 2025, cancelled since
+    fn subscription_history() -> Ledger {
+        let mut moves = Vec::new();
+        for m in 1..=9 {
+            moves.push((d(2025, m, 12), -10.99, "Spotify"));
+            let netflix = if m < 7 { -15.99 } else { -17.99 };
+            moves.push((d(2025, m, 5), netflix, "Netflix"));
+        }
+        for y in 2023..=2025 {
+            moves.push((d(y, 3, 1), -20.0, "Domain"));
+        }
+        for m in 1..=4 {
+            moves.push((d(2025, m, 20), -40.0, "Gym"));
         }
+        // groceries every week, the amounts too far apart for a subscription
+        for w in 0..30 {
+            let amount = -(20.0 + 40.0 * (w % 5) as f64);
+            moves.push((d(2025, 1, 4) + Duration::weeks(w), amount, "Grocer"));
+        }
+        ledger_with_payees(&moves)
+    }
+    fn subscription<'a>(subs: &'a [Subscription], payee: &str) -> &'a Subscription {
+        subs.iter().find(|s| s.payee == payee).expect(payee)
+    }
+    const HISTORY: (NaiveDate, NaiveDate) = (
+        NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+        NaiveDate::from_ymd_opt(2025, 9, 30).unwrap(),
+    );
+
+    #[test]
+    fn subscriptions_report_a_monthly_charge() {
+        let subs = subscription_history().subscriptions(Uuid::nil(), HISTORY);
+        let spotify = subscription(&subs, "Spotify");
+        assert_eq!(spotify.cadence, Cadence::Monthly);
+        assert_eq!(spotify.count, 9);
+        assert!((spotify.average + 10.99).abs() < 1e-9);
+        assert!((spotify.annual + 131.88).abs() < 1e-9);
+        assert_eq!(spotify.first_date, d(2025, 1, 12));
+        assert_eq!(spotify.last_date, d(2025, 9, 12));
+        assert_eq!(spotify.next_date, d(2025, 10, 12));
+        assert!(spotify.active);
+        assert!(spotify.increase.is_none());
+        assert!(subs.iter().all(|s| s.payee != "Grocer"));
+    }
+
+    #[test]
+    fn subscriptions_report_a_yearly_charge() {
+        let subs = subscription_history().subscriptions(Uuid::nil(), HISTORY);
+        let domain = subscription(&subs, "Domain");
+        assert_eq!(domain.cadence, Cadence::Yearly);
+        assert_eq!(domain.count, 3);
+        assert!((domain.annual + 20.0).abs() < 1e-9);
+        assert_eq!(domain.next_date, d(2026, 3, 1));
+        assert!(domain.active);
+    }
+
+    #[test]
+    fn subscriptions_report_a_price_increase() {
+        let subs = subscription_history().subscriptions(Uuid::nil(), HISTORY);
+        let netflix = subscription(&subs, "Netflix");
+        assert_eq!(netflix.cadence, Cadence::Monthly);
+        assert!((netflix.latest + 17.99).abs() < 1e-9);
+        // the yearly cost is at the new price, the average still has the old one in it
+        assert!((netflix.annual + 215.88).abs() < 1e-9);
+        assert!((netflix.average + (6.0 * 15.99 + 3.0 * 17.99) / 9.0).abs() < 1e-9);
+        let increase = netflix.increase.expect("price increase");
+        assert!((increase.from + 15.99).abs() < 1e-9);
+        assert!((increase.to + 17.99).abs() < 1e-9);
+        assert_eq!(increase.date, d(2025, 7, 5));
+    }
+
+    #[test]
+    fn subscriptions_list_a_cancelled_charge_as_inactive_last() {
+        let subs = subscription_history().subscriptions(Uuid::nil(), HISTORY);
+        let gym = subscription(&subs, "Gym");
+        assert_eq!(gym.cadence, Cadence::Monthly);
+        assert_eq!(gym.last_date, d(2025, 4, 20));
+        assert!(!gym.active);
+        // still active while the range ends before the next charge is overdue
+        let april = (d(2025, 1, 1), d(2025, 5, 31));
+        let subs_then = subscription_history().subscriptions(Uuid::nil(), april);
+        assert!(subscription(&subs_then, "Gym").active);
+
+        // active ones first, the most expensive of them first
+        let order: Vec<&str> = subs.iter().map(|s| s.payee.as_str()).collect();
+        assert_eq!(order, ["Netflix", "Spotify", "Domain", "Gym"]);
+    }
+
+    ///chequing opened with 1000, a card, a loan of 10000 and a contact who owes 50
+    fn net_worth_ledger() -> Ledger {
+        let account = vec![
+            account(1, "Chequing", AccountType::Checking, 1000.0),
+            account(2, "Visa", AccountType::Credit, 0.0),
+            account(3, "Car loan", AccountType::Loan, -10000.0),
+            account(4, "Sam", AccountType::Contact, 0.0),
+        ];
+        accounts_ledger(
+            account,
+            &[
+                (d(2025, 6, 20), 1, -200.0, "Rent"),
+                (d(2025, 7, 2), 4, 50.0, "Dinner"),
+                (d(2025, 7, 3), 1, -45.5, "Grocer"),
+                (d(2025, 7, 10), 2, -300.0, "Airline"),
+                (d(2025, 7, 15), 3, 500.0, "Loan payment"),
+                (d(2025, 7, 31), 1, 1500.0, "Salary"),
+                (d(2025, 8, 5), 2, 300.0, "Card payment"),
+                (d(2025, 8, 10), 1, -100.0, "Grocer"),
+                (d(2025, 8, 15), 3, 500.0, "Loan payment"),
+                (d(2025, 8, 20), 2, -120.0, "Hotel"),
+                (d(2025, 9, 5), 1, -50.0, "Grocer"),
+            ],
+        )
+    }
+    fn close_all(a: &[f64], b: &[f64]) -> bool {
+        a.len() == b.len() && a.iter().zip(b).all(|(x, y)| (x - y).abs() < 1e-9)
+    }
+
+    #[test]
+    fn balance_series_at_month_ends_carries_the_balance_before_the_range() {
+        let series = net_worth_ledger().balance_series(
+            Uuid::nil(),
+            d(2025, 7, 1),
+            d(2025, 9, 15),
+            Granularity::Monthly,
+        );
+        // contacts are not the user's money
+        let names: Vec<&str> = series.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["Chequing", "Visa", "Car loan"]);
+        let chequing = &series[0];
+        // the last point is the end of the range, not of September
+        assert_eq!(
+            chequing.axis,
+            [d(2025, 7, 31), d(2025, 8, 31), d(2025, 9, 15)]
+        );
+        assert!(close_all(&chequing.balance, &[2254.5, 2154.5, 2104.5]));
+        assert!(close_all(&series[1].balance, &[-300.0, -120.0, -120.0]));
+        assert!(close_all(&series[2].balance, &[-9500.0, -9000.0, -9000.0]));
+    }
+
+    #[test]
+    fn balance_series_daily() {
+        let series = net_worth_ledger().balance_series(
+            Uuid::nil(),
+            d(2025, 7, 1),
+            d(2025, 7, 4),
+            Granularity::Daily,
+        );
+        assert_eq!(series[0].axis.len(), 4);
+        assert!(close_all(&series[0].balance, &[800.0, 800.0, 754.5, 754.5]));
+    }
+
+    #[test]
+    fn net_worth_counts_credit_and_loans_as_liabilities() {
+        let nw = net_worth_ledger().networth_series(
+            Uuid::nil(),
+            d(2025, 7, 1),
+            d(2025, 8, 31),
+            Granularity::Monthly,
+        );
+        assert_eq!(nw.axis, [d(2025, 7, 31), d(2025, 8, 31)]);
+        assert!(close_all(&nw.assets, &[2254.5, 2154.5]));
+        // what is owed on the card and the loan, positive
+        assert!(close_all(&nw.liabilities, &[9800.0, 9120.0]));
+        assert!(close_all(&nw.net_worth, &[-7545.5, -6965.5]));
+    }
+
+    #[test]
+    fn net_worth_on_a_day_matches_the_account_breakdown() {
+        let ledger = net_worth_ledger();
+        let day = d(2025, 8, 12);
+        let nw = ledger.networth_series(Uuid::nil(), day, day, Granularity::Daily);
+        let breakdown: f64 = ledger
+            .all_account_summary_on(day)
+            .iter()
+            .map(|a| a.balance)
+            .sum();
+        // chequing 2154.5, card paid off, loan 9500
+        assert!(close_all(&nw.net_worth, &[2154.5 - 9500.0]));
+        assert!((breakdown - nw.net_worth[0]).abs() < 1e-9);
     }
 }
@@ -0,0 +1,347 @@
+use super::datatype::*;
+use super::ledger::*;
+use chrono::*;
+use std::collections::HashMap;
+//loans: amortization schedule, principal / interest split, payoff projection with extra payments
+
+///payments a projection runs at most, a payment below the interest never pays the loan off
+const MAX_PAYMENTS: usize = 3000;
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy)]
+pub struct LoanPayment {
+    pub number: u32, // 1 for the first payment of the loan
+    pub date: NaiveDate,
+    pub payment: f64,
+    pub principal: f64,
+    pub interest: f64,
+    pub balance: f64, // owed after the payment
+}
+///where a loan stands and where it is heading
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct LoanStatus {
+    pub accountid: AccountId,
+    pub name: String,
+    pub terms: LoanTerms,
+    pub owed: f64,
+    pub paid_off: f64,                // principal - owed
+    pub scheduled_owed: f64, // owed today by the original schedule, above `owed` when ahead of it
+    pub payment: f64,        // regular payment of the terms
+    pub payments_made: usize, // payments recorded on the loan account
+    pub next_date: Option<NaiveDate>, // first scheduled date after the last payment, none once paid off
+    pub next_principal: f64,          // split of the regular payment on what is owed now
+    pub next_interest: f64,
+    pub payoff: Option<NaiveDate>, // last payment at the regular payment, none if it never pays off
+    pub interest_left: f64,
+    pub extra: f64, // added to every payment in the projection below
+    pub extra_payoff: Option<NaiveDate>, // payoff with `extra`
+    pub extra_interest_left: f64,
+    pub schedule: Vec<LoanPayment>, // payments left with `extra`
+}
+fn cents(v: f64) -> f64 {
+    (v * 100.0).round() / 100.0
+}
+///number of payments over the term
+pub fn payment_count(terms: &LoanTerms) -> u32 {
+    let n = terms.term_months as f64 * terms.frequency.periods_per_year() as f64 / 12.0;
+    (n.round() as u32).max(1)
+}
+///regular payment that pays `principal` off in `count` payments, interest compounding every payment
+pub fn regular_payment(principal: f64, rate: f64, frequency: LoanFrequency, count: u32) -> f64 {
+    let r = rate / 100.0 / frequency.periods_per_year() as f64;
+    match r > 0.0 {
+        true => cents(principal * r / (1.0 - (1.0 + r).powi(-(count as i32)))),
+        false => cents(principal / count as f64),
+    }
+}
+///date of payment `index`, 0 being the first payment
+pub fn payment_date(terms: &LoanTerms, index: u32) -> Option<NaiveDate> {
+    match terms.frequency {
+        LoanFrequency::Monthly => terms.first_payment.checked_add_months(Months::new(index)),
+        LoanFrequency::Biweekly => terms
+            .first_payment
+            .checked_add_signed(Duration::days(14 * index as i64)),
+        LoanFrequency::Weekly => terms
+            .first_payment
+            .checked_add_signed(Duration::days(7 * index as i64)),
+    }
+}
+///(principal, interest) of `amount` paid on `owed`, the interest being one period of the rate;
+///the server splits recorded payments the same way
+pub fn split_payment(owed: f64, rate: f64, frequency: LoanFrequency, amount: f64) -> (f64, f64) {
+    let interest =
+        cents(owed.max(0.0) * rate / 100.0 / frequency.periods_per_year() as f64).min(amount);
+    (cents(amount - interest), interest)
+}
+///payments that pay `owed` off from payment `index` on, `payment` each
+///
+/// the last payment of the term clears whatever is left, so rounding doesn't leave a cent-sized
+/// payment behind; empty when nothing is owed, stops after MAX_PAYMENTS past the term when the
+/// payment can't outgrow the interest
+pub fn amortize(terms: &LoanTerms, owed: f64, index: u32, payment: f64) -> Vec<LoanPayment> {
+    let last = payment_count(terms);
+    let mut result = Vec::new();
+    let mut owed = cents(owed);
+    let mut index = index;
+    while owed > 0.0 && result.len() < MAX_PAYMENTS {
+        let Some(date) = payment_date(terms, index) else {
+            break;
+        };
+        let (mut principal, interest) = split_payment(owed, terms.rate, terms.frequency, payment);
+        principal = match index + 1 == last {
+            true => owed,
+            false => principal.min(owed),
+        };
+        owed = cents(owed - principal);
+        result.push(LoanPayment {
+            number: index + 1,
+            date,
+            payment: cents(principal + interest),
+            principal,
+            interest,
+            balance: owed,
+        });
+        index += 1;
+    }
+    result
+}
+///the schedule the loan started with
+pub fn amortization_schedule(terms: &LoanTerms) -> Vec<LoanPayment> {
+    let count = payment_count(terms);
+    let payment = regular_payment(terms.principal, terms.rate, terms.frequency, count);
+    amortize(terms, terms.principal, 0, payment)
+}
+impl Ledger {
+    ///status of every loan account with terms as of `today`, `extra` added to each future payment
+    pub fn loan_status(&self, today: NaiveDate, extra: f64) -> Vec<LoanStatus> {
+        let dates: HashMap<TransactionId, NaiveDate> = self
+            .transaction
+            .iter()
+            .map(|t| (t.id, t.occur_date))
+            .collect();
+        self.account
+            .iter()
+            .filter(|a| matches!(a.account_type, AccountType::Loan))
+            .filter_map(|a| Some((a, a.loan?)))
+            .map(|(acc, terms)| {
+                let payments: Vec<NaiveDate> = self
+                    .entry
+                    .iter()
+                    .filter(|e| e.accountid == acc.id && e.amount > 0.0)
+                    .filter_map(|e| dates.get(&e.tranid).copied())
+                    .filter(|d| *d <= today)
+                    .collect();
+                self.loan_status_of(acc, terms, today, extra, &payments)
+            })
+            .collect()
+    }
+    fn loan_status_of(
+        &self,
+        acc: &Account,
+        terms: LoanTerms,
+        today: NaiveDate,
+        extra: f64,
+        payments: &[NaiveDate],
+    ) -> LoanStatus {
+        let owed = cents(0.0 - self.balance_on(acc.id, today));
+        let payment = regular_payment(
+            terms.principal,
+            terms.rate,
+            terms.frequency,
+            payment_count(&terms),
+        );
+        // next scheduled payment after the last one recorded, a missed one stays next
+        let last = payments.iter().max().copied();
+        let next = (0..)
+            .map_while(|i| payment_date(&terms, i).map(|d| (i, d)))
+            .take(MAX_PAYMENTS)
+            .find(|(_, d)| last.is_none_or(|l| *d > l));
+        let plain = next
+            .map(|(i, _)| amortize(&terms, owed, i, payment))
+            .unwrap_or_default();
+        let schedule = match extra > 0.0 {
+            true => next
+                .map(|(i, _)| amortize(&terms, owed, i, payment + extra))
+                .unwrap_or_default(),
+            false => plain.clone(),
+        };
+        // the last entry is the payoff, unless the projection ran out
+        let payoff = |s: &[LoanPayment]| s.last().filter(|p| p.balance <= 0.0).map(|p| p.date);
+        let interest = |s: &[LoanPayment]| cents(s.iter().map(|p| p.interest).sum());
+        let (next_principal, next_interest) =
+            split_payment(owed, terms.rate, terms.frequency, payment);
+        LoanStatus {
+            accountid: acc.id,
+            name: acc.name.clone(),
+            terms,
+            owed,
+            paid_off: cents(terms.principal - owed),
+            scheduled_owed: amortization_schedule(&terms)
+                .iter()
+                .take_while(|p| p.date <= today)
+                .last()
+                .map(|p| p.balance)
+                .unwrap_or(terms.principal),
+            payment,
+            payments_made: payments.len(),
+            next_date: next.filter(|_| owed > 0.0).map(|(_, d)| d),
+            next_principal: next_principal.min(owed.max(0.0)),
+            next_interest,
+            payoff: payoff(&plain),
+            interest_left: interest(&plain),
+            extra,
+            extra_payoff: payoff(&schedule),
+            extra_interest_left: interest(&schedule),
+            schedule,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn d(y: i32, m: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, day).unwrap()
+    }
+    ///10 000 at 6% over a year, paid monthly from February 1st
+    fn terms() -> LoanTerms {
+        LoanTerms {
+            principal: 10000.0,
+            rate: 6.0,
+            term_months: 12,
+            frequency: LoanFrequency::Monthly,
+            first_payment: d(2025, 2, 1),
+        }
+    }
+    fn interest(s: &[LoanPayment]) -> f64 {
+        cents(s.iter().map(|p| p.interest).sum())
+    }
+
+    #[test]
+    fn schedule_ends_on_a_final_payment_that_clears_the_loan() {
+        let schedule = amortization_schedule(&terms());
+        assert_eq!(payment_count(&terms()), 12);
+        assert_eq!(schedule.len(), 12);
+        let first = schedule[0];
+        assert_eq!(
+            (first.payment, first.principal, first.interest),
+            (860.66, 810.66, 50.0)
+        );
+        assert_eq!(first.balance, 9189.34);
+        // the rounding left over goes into the last payment
+        let last = schedule[11];
+        assert_eq!(last.date, d(2026, 1, 1));
+        assert_eq!(
+            (last.payment, last.principal, last.interest),
+            (860.70, 856.42, 4.28)
+        );
+        assert_eq!(last.balance, 0.0);
+        assert!(schedule[..11].iter().all(|p| p.payment == 860.66));
+        assert_eq!(interest(&schedule), 327.96);
+        let principal = cents(schedule.iter().map(|p| p.principal).sum());
+        assert_eq!(principal, 10000.0);
+    }
+
+    #[test]
+    fn mortgage_and_zero_rate_schedules() {
+        let mortgage = LoanTerms {
+            principal: 250000.0,
+            rate: 4.5,
+            term_months: 300,
+            ..terms()
+        };
+        let schedule = amortization_schedule(&mortgage);
+        assert_eq!(schedule.len(), 300);
+        assert_eq!(schedule[0].payment, 1389.58);
+        assert_eq!(schedule[299].payment, 1390.27);
+        assert_eq!(interest(&schedule), 166874.69);
+
+        let free = LoanTerms {
+            principal: 1200.0,
+            rate: 0.0,
+            ..terms()
+        };
+        let schedule = amortization_schedule(&free);
+        assert!(
+            schedule
+                .iter()
+                .all(|p| p.payment == 100.0 && p.interest == 0.0)
+        );
+        assert_eq!(schedule.last().unwrap().balance, 0.0);
+
+        let biweekly = LoanTerms {
+            frequency: LoanFrequency::Biweekly,
+            ..terms()
+        };
+        assert_eq!(payment_count(&biweekly), 26);
+        assert_eq!(payment_date(&biweekly, 2), Some(d(2025, 3, 1)));
+    }
+
+    #[test]
+    fn status_after_three_payments_and_with_an_extra_payment() {
+        let schedule = amortization_schedule(&terms());
+        let userid = Uuid::nil();
+        let account = vec![Account {
+            id: 1,
+            userid,
+            name: "Car loan".to_string(),
+            account_type: AccountType::Loan,
+            currency: Currency::new("cad"),
+            balance: -10000.0,
+            create_date: Utc::now(),
+            credit: CreditTerms::default(),
+            loan: Some(terms()),
+            cost_basis: None,
+            contact_email: None,
+        }];
+        // the loan account gets the principal part of each payment
+        let (mut transaction, mut entry) = (Vec::new(), Vec::new());
+        for (i, p) in schedule[..3].iter().enumerate() {
+            let tranid = Uuid::from_u128(i as u128 + 1);
+            transaction.push(Transaction {
+                id: tranid,
+                userid,
+                occur_date: p.date,
+                receiver: None,
+                desc: None,
+                create_date: Utc::now(),
+            });
+            entry.push(Entry {
+                id: i as i64 + 1,
+                userid,
+                tranid,
+                accountid: 1,
+                categoryid: None,
+                amount: p.principal,
+                desc: None,
+                status: EntryStatus::Cleared,
+            });
+        }
+        let ledger = Ledger::new(Vec::new(), account, Vec::new(), transaction, entry);
+
+        let status = &ledger.loan_status(d(2025, 4, 15), 0.0)[0];
+        assert_eq!(status.payments_made, 3);
+        assert_eq!(status.owed, schedule[2].balance);
+        assert_eq!(status.scheduled_owed, status.owed);
+        assert_eq!(status.next_date, Some(d(2025, 5, 1)));
+        assert_eq!(status.next_interest, schedule[3].interest);
+        assert_eq!(status.payoff, Some(d(2026, 1, 1)));
+        assert_eq!(status.interest_left, interest(&schedule[3..]));
+
+        let faster = &ledger.loan_status(d(2025, 4, 15), 200.0)[0];
+        assert!(faster.extra_payoff.unwrap() < faster.payoff.unwrap());
+        assert!(faster.extra_interest_left < faster.interest_left);
+        assert!(
+            faster
+                .schedule
+                .iter()
+                .rev()
+                .skip(1)
+                .all(|p| p.payment == 1060.66)
+        );
+        assert_eq!(faster.schedule.last().unwrap().balance, 0.0);
+    }
+}
@@ -0,0 +1,285 @@
+use super::ledger::month_end;
+use chrono::*;
+//day precision date ranges and the period presets that produce them
+
+///inclusive range of days, the unit every stats query works on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DateRange {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+///how to cut time into buckets or where to move next
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Week, // ISO week, Monday to Sunday
+    Month,
+    Quarter,
+    Year,
+    StatementCycle { closing_day: u32 }, // e.g. closing_day 14 gives the 15th to the 14th
+    Custom(DateRange),
+}
+fn add_months(date: NaiveDate, n: i32) -> NaiveDate {
+    if n >= 0 {
+        date.checked_add_months(Months::new(n as u32))
+    } else {
+        date.checked_sub_months(Months::new(n.unsigned_abs()))
+    }
+    .unwrap_or(date)
+}
+///`day` of month (y, m), clamped to the month length
+fn day_in_month(y: i32, m: u32, day: u32) -> NaiveDate {
+    let last = month_end(y, m);
+    NaiveDate::from_ymd_opt(y, m, day.clamp(1, last.day())).unwrap_or(last)
+}
+impl DateRange {
+    ///`from` and `to` are swapped if given the wrong way round
+    pub fn new(from: NaiveDate, to: NaiveDate) -> Self {
+        if from <= to {
+            DateRange { from, to }
+        } else {
+            DateRange { from: to, to: from }
+        }
+    }
+    pub fn month(y: i32, m: u32) -> Self {
+        let first = NaiveDate::from_ymd_opt(y, m, 1).unwrap_or(NaiveDate::MIN);
+        DateRange::new(first, month_end(y, m))
+    }
+    ///whole months from `start` to `end`, the old `((y, m), (y, m))` time phase
+    pub fn months(start: (i32, u32), end: (i32, u32)) -> Self {
+        DateRange::new(
+            DateRange::month(start.0, start.1).from,
+            DateRange::month(end.0, end.1).to,
+        )
+    }
+    ///the last `n` whole months, current month included
+    pub fn last_months(today: NaiveDate, n: u32) -> Self {
+        let this = Period::Month.containing(today);
+        DateRange::new(add_months(this.from, 1 - n.max(1) as i32), this.to)
+    }
+    ///starts on the 1st and ends on a month end
+    pub fn is_whole_months(&self) -> bool {
+        self.from.day() == 1 && self.to == month_end(self.to.year(), self.to.month())
+    }
+    ///moved by `n` months, whole months stay whole (Feb 1 ~ Feb 28 + 1 is Mar 1 ~ Mar 31)
+    pub fn shift_months(&self, n: i32) -> Self {
+        if self.is_whole_months() {
+            let to = add_months(Period::Month.containing(self.to).from, n);
+            DateRange::new(add_months(self.from, n), month_end(to.year(), to.month()))
+        } else {
+            DateRange::new(add_months(self.from, n), add_months(self.to, n))
+        }
+    }
+    ///the range of the same length right before, counted in months when whole months
+    pub fn previous(&self) -> Self {
+        if self.is_whole_months() {
+            let months = (self.to.year() - self.from.year()) * 12 + self.to.month() as i32
+                - self.from.month() as i32
+                + 1;
+            self.shift_months(-months)
+        } else {
+            let days = Duration::days(self.days());
+            DateRange::new(self.from - days, self.to - days)
+        }
+    }
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.from <= date && date <= self.to
+    }
+    pub fn days(&self) -> i64 {
+        (self.to - self.from).num_days() + 1
+    }
+    ///cut into consecutive buckets of `period`, the first and last are clipped to the range
+    pub fn split(&self, period: Period) -> Vec<DateRange> {
+        let mut result = Vec::new();
+        let mut cur = period.containing(self.from);
+        loop {
+            result.push(DateRange::new(cur.from.max(self.from), cur.to.min(self.to)));
+            if cur.to >= self.to {
+                break;
+            }
+            let next = period.next(cur);
+            if next.from <= cur.to {
+                break;
+            }
+            cur = next;
+        }
+        result
+    }
+    ///short label for axes and tables: "2026-03", "2026-Q1", "2026", a single day, or from~to
+    pub fn label(&self) -> String {
+        let (y, m) = (self.from.year(), self.from.month());
+        if *self == DateRange::month(y, m) {
+            format!("{y:04}-{m:02}")
+        } else if *self == Period::Quarter.containing(self.from) {
+            format!("{y:04}-Q{}", (m - 1) / 3 + 1)
+        } else if *self == Period::Year.containing(self.from) {
+            format!("{y:04}")
+        } else if self.from == self.to {
+            self.from.to_string()
+        } else if self.from.year() == self.to.year() {
+            format!("{}~{}", self.from, self.to.format("%m-%d"))
+        } else {
+            format!("{}~{}", self.from, self.to)
+        }
+    }
+}
+impl From<((i32, u32), (i32, u32))> for DateRange {
+    fn from(timephase: ((i32, u32), (i32, u32))) -> Self {
+        DateRange::months(timephase.0, timephase.1)
+    }
+}
+impl From<(NaiveDate, NaiveDate)> for DateRange {
+    fn from((from, to): (NaiveDate, NaiveDate)) -> Self {
+        DateRange::new(from, to)
+    }
+}
+impl std::fmt::Display for DateRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ~ {}", self.from, self.to)
+    }
+}
+impl Period {
+    ///the period of this kind that contains `date`, `Custom` is always its own range
+    pub fn containing(&self, date: NaiveDate) -> DateRange {
+        let (y, m) = (date.year(), date.month());
+        match *self {
+            Period::Week => {
+                let monday = date - Duration::days(date.weekday().num_days_from_monday() as i64);
+                DateRange::new(monday, monday + Duration::days(6))
+            }
+            Period::Month => DateRange::month(y, m),
+            Period::Quarter => {
+                let first = (m - 1) / 3 * 3 + 1;
+                DateRange::months((y, first), (y, first + 2))
+            }
+            Period::Year => DateRange::months((y, 1), (y, 12)),
+            Period::StatementCycle { closing_day } => {
+                let close_this = day_in_month(y, m, closing_day);
+                let end = if date <= close_this {
+                    close_this
+                } else {
+                    let next = add_months(NaiveDate::from_ymd_opt(y, m, 1).unwrap_or(date), 1);
+                    day_in_month(next.year(), next.month(), closing_day)
+                };
+                let prev = add_months(
+                    NaiveDate::from_ymd_opt(end.year(), end.month(), 1).unwrap_or(end),
+                    -1,
+                );
+                let prev_close = day_in_month(prev.year(), prev.month(), closing_day);
+                DateRange::new(prev_close + Duration::days(1), end)
+            }
+            Period::Custom(range) => range,
+        }
+    }
+    ///period right after `range`, a custom range moves by its own length
+    pub fn next(&self, range: DateRange) -> DateRange {
+        match *self {
+            Period::Custom(_) => DateRange::new(
+                range.to + Duration::days(1),
+                range.to + Duration::days(range.days()),
+            ),
+            _ => self.containing(range.to + Duration::days(1)),
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            Period::Week => "Weekly",
+            Period::Month => "Monthly",
+            Period::Quarter => "Quarterly",
+            Period::Year => "Yearly",
+            Period::StatementCycle { .. } => "Statement cycle",
+            Period::Custom(_) => "Custom",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(y: i32, m: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, day).unwrap()
+    }
+
+    #[test]
+    fn statement_cycle_closing_on_31_clamps_in_short_months() {
+        let cycle = Period::StatementCycle { closing_day: 31 };
+        assert_eq!(
+            cycle.containing(d(2025, 2, 10)),
+            DateRange::new(d(2025, 2, 1), d(2025, 2, 28))
+        );
+        assert_eq!(
+            cycle.containing(d(2025, 2, 28)),
+            DateRange::new(d(2025, 2, 1), d(2025, 2, 28))
+        );
+        assert_eq!(
+            cycle.containing(d(2025, 3, 1)),
+            DateRange::new(d(2025, 3, 1), d(2025, 3, 31))
+        );
+        assert_eq!(
+            cycle.containing(d(2024, 2, 29)),
+            DateRange::new(d(2024, 2, 1), d(2024, 2, 29))
+        );
+        assert_eq!(
+            cycle.containing(d(2025, 4, 30)),
+            DateRange::new(d(2025, 4, 1), d(2025, 4, 30))
+        );
+    }
+
+    #[test]
+    fn statement_cycle_after_february_starts_the_day_after_it_closed() {
+        let cycle = Period::StatementCycle { closing_day: 30 };
+        assert_eq!(
+            cycle.containing(d(2025, 2, 14)),
+            DateRange::new(d(2025, 1, 31), d(2025, 2, 28))
+        );
+        assert_eq!(
+            cycle.containing(d(2025, 3, 1)),
+            DateRange::new(d(2025, 3, 1), d(2025, 3, 30))
+        );
+        assert_eq!(
+            cycle.containing(d(2025, 3, 31)),
+            DateRange::new(d(2025, 3, 31), d(2025, 4, 30))
+        );
+        // a year of cycles leaves no gaps and no overlaps
+        for closing_day in [1, 14, 28, 29, 30, 31] {
+            let cycle = Period::StatementCycle { closing_day };
+            let year = DateRange::new(d(2024, 1, 1), d(2024, 12, 31));
+            let buckets = year.split(cycle);
+            assert_eq!(buckets.first().unwrap().from, year.from);
+            assert_eq!(buckets.last().unwrap().to, year.to);
+            for w in buckets.windows(2) {
+                assert_eq!(
+                    w[0].to + Duration::days(1),
+                    w[1].from,
+                    "closing day {closing_day}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn iso_weeks_cross_the_year_boundary() {
+        // Wednesday 2025-12-31 is in ISO week 1 of 2026
+        let week = Period::Week.containing(d(2025, 12, 31));
+        assert_eq!(week, DateRange::new(d(2025, 12, 29), d(2026, 1, 4)));
+        assert_eq!(Period::Week.containing(d(2026, 1, 4)), week);
+        // Friday 2021-01-01 is in ISO week 53 of 2020
+        let week = Period::Week.containing(d(2021, 1, 1));
+        assert_eq!(week, DateRange::new(d(2020, 12, 28), d(2021, 1, 3)));
+        assert_eq!(week.from.iso_week().week(), 53);
+        assert_eq!(week.from.weekday(), Weekday::Mon);
+        assert_eq!(Period::Week.next(week).from, d(2021, 1, 4));
+        // split clips the first and last week to the year
+        let weeks = DateRange::new(d(2021, 1, 1), d(2021, 12, 31)).split(Period::Week);
+        assert_eq!(
+            weeks.first(),
+            Some(&DateRange::new(d(2021, 1, 1), d(2021, 1, 3)))
+        );
+        assert_eq!(
+            weeks.last(),
+            Some(&DateRange::new(d(2021, 12, 27), d(2021, 12, 31)))
+        );
+        assert_eq!(weeks.len(), 53);
+    }
+}
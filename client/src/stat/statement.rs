@@ -0,0 +1,457 @@
+use super::datatype::*;
+use super::ledger::*;
+use anyhow::{Result, anyhow};
+use chrono::*;
+use std::collections::{HashMap, HashSet};
+//bank statement import (CSV / OFX) and line-by-line matching against the ledger
+
+///one line of a bank or card statement
+#[derive(Debug, Clone)]
+pub struct StatementLine {
+    pub date: NaiveDate,
+    pub amount: f64,
+    pub payee: String,
+    pub memo: Option<String>,
+}
+///result of matching statement lines to entries of one account
+#[derive(Debug, Clone, Default)]
+pub struct StatementMatch {
+    pub matched: Vec<LineMatch>,
+    pub ledger_only: Vec<(NaiveDate, Entry)>,
+    pub statement_only: Vec<StatementLine>,
+}
+#[derive(Debug, Clone)]
+pub struct LineMatch {
+    pub line: StatementLine,
+    pub entry: Entry,
+    pub date: NaiveDate,
+    pub score: f64, // 0..=1, date distance and payee similarity
+}
+#[derive(Debug, Clone, Copy)]
+pub struct MatchConfig {
+    pub date_window: i64, // max days between statement line and entry date
+}
+impl Default for MatchConfig {
+    fn default() -> Self {
+        MatchConfig { date_window: 3 }
+    }
+}
+
+///read a statement file, OFX if it looks like one, else CSV
+pub fn load_statement_file(path: &str) -> Result<Vec<StatementLine>> {
+    let text = std::fs::read_to_string(path).map_err(|e| anyhow!("cannot read {path}: {e}"))?;
+    let lines = if path.to_lowercase().ends_with(".ofx")
+        || path.to_lowercase().ends_with(".qfx")
+        || text.contains("<OFX>")
+    {
+        parse_statement_ofx(&text)?
+    } else {
+        parse_statement_csv(&text)?
+    };
+    if lines.is_empty() {
+        return Err(anyhow!("no statement lines found in {path}"));
+    }
+    Ok(lines)
+}
+pub(super) fn parse_statement_date(s: &str) -> Option<NaiveDate> {
+    let s = s.trim();
+    for fmt in ["%Y-%m-%d", "%Y/%m/%d", "%m/%d/%Y", "%Y%m%d"] {
+        if let Ok(d) = NaiveDate::parse_from_str(s, fmt) {
+            return Some(d);
+        }
+    }
+    None
+}
+///"1,234.50", "$-12.00", "(12.00)" -> f64
+pub(super) fn parse_statement_amount(s: &str) -> Option<f64> {
+    let s = s.trim();
+    if s.is_empty() {
+        return None;
+    }
+    let (negative, s) = match s.strip_prefix('(').and_then(|x| x.strip_suffix(')')) {
+        Some(inner) => (true, inner),
+        None => (false, s),
+    };
+    let cleaned: String = s
+        .chars()
+        .filter(|c| !matches!(c, '$' | ',' | ' '))
+        .collect();
+    let v: f64 = cleaned.parse().ok()?;
+    Some(if negative { -v } else { v })
+}
+///split one CSV record, double quotes may wrap fields and "" is an escaped quote
+pub(super) fn split_csv_record(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut cur = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                cur.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut cur)),
+            _ => cur.push(c),
+        }
+    }
+    fields.push(cur);
+    fields.into_iter().map(|f| f.trim().to_string()).collect()
+}
+///CSV with a header row
+///
+/// needs a date column and either an amount column or debit/credit columns,
+/// payee comes from payee/description/name, memo from memo/note
+pub fn parse_statement_csv(text: &str) -> Result<Vec<StatementLine>> {
+    let mut rows = text.lines().filter(|l| !l.trim().is_empty());
+    let header: Vec<String> = split_csv_record(rows.next().ok_or_else(|| anyhow!("empty CSV"))?)
+        .into_iter()
+        .map(|h| h.to_lowercase())
+        .collect();
+    let col = |names: &[&str]| header.iter().position(|h| names.contains(&h.as_str()));
+    let date_col = col(&["date", "posted", "posting date", "transaction date"])
+        .ok_or_else(|| anyhow!("CSV has no date column"))?;
+    let amount_col = col(&["amount", "value"]);
+    let debit_col = col(&["debit", "withdrawal", "out"]);
+    let credit_col = col(&["credit", "deposit", "in"]);
+    if amount_col.is_none() && debit_col.is_none() && credit_col.is_none() {
+        return Err(anyhow!("CSV has no amount or debit/credit column"));
+    }
+    let payee_col = col(&["payee", "description", "name", "merchant"]);
+    let memo_col = col(&["memo", "note", "notes"]);
+
+    let mut result = Vec::new();
+    for (n, row) in rows.enumerate() {
+        let fields = split_csv_record(row);
+        let get = |c: Option<usize>| c.and_then(|i| fields.get(i)).map(|s| s.as_str());
+        let date = get(Some(date_col))
+            .and_then(parse_statement_date)
+            .ok_or_else(|| anyhow!("line {}: bad date", n + 2))?;
+        let amount = match get(amount_col).and_then(parse_statement_amount) {
+            Some(v) => v,
+            None => {
+                let debit = get(debit_col)
+                    .and_then(parse_statement_amount)
+                    .unwrap_or(0.0);
+                let credit = get(credit_col)
+                    .and_then(parse_statement_amount)
+                    .unwrap_or(0.0);
+                credit - debit.abs()
+            }
+        };
+        result.push(StatementLine {
+            date,
+            amount,
+            payee: get(payee_col).unwrap_or_default().to_string(),
+            memo: get(memo_col)
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string()),
+        });
+    }
+    Ok(result)
+}
+///value of an OFX tag inside one <STMTTRN> block, closing tags are optional in OFX 1.x
+fn ofx_tag(block: &str, tag: &str) -> Option<String> {
+    let open = format!("<{tag}>");
+    let start = block.find(&open)? + open.len();
+    let rest = &block[start..];
+    let end = rest.find(['<', '\n', '\r']).unwrap_or(rest.len());
+    let value = rest[..end].trim();
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+///OFX / QFX bank statement, one line per <STMTTRN>
+pub fn parse_statement_ofx(text: &str) -> Result<Vec<StatementLine>> {
+    let mut result = Vec::new();
+    for block in text.split("<STMTTRN>").skip(1) {
+        let block = block.split("</STMTTRN>").next().unwrap_or(block);
+        // DTPOSTED is YYYYMMDD followed by optional time and zone
+        let date = ofx_tag(block, "DTPOSTED")
+            .and_then(|d| d.get(..8).and_then(parse_statement_date))
+            .ok_or_else(|| anyhow!("OFX transaction without DTPOSTED"))?;
+        let amount = ofx_tag(block, "TRNAMT")
+            .and_then(|a| parse_statement_amount(&a))
+            .ok_or_else(|| anyhow!("OFX transaction without TRNAMT"))?;
+        result.push(StatementLine {
+            date,
+            amount,
+            payee: ofx_tag(block, "NAME").unwrap_or_default(),
+            memo: ofx_tag(block, "MEMO"),
+        });
+    }
+    Ok(result)
+}
+pub(crate) fn normalize_payee(s: &str) -> String {
+    s.to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect()
+}
+///payee similarity in 0..=1, Dice coefficient over character bigrams
+///
+/// bank payees are often longer ("NETFLIX.COM 866-579") so containment counts as a full match
+pub fn payee_similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (normalize_payee(a), normalize_payee(b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    if a.contains(&b) || b.contains(&a) {
+        return 1.0;
+    }
+    let bigrams = |s: &str| -> Vec<(char, char)> {
+        let chars: Vec<char> = s.chars().collect();
+        chars.windows(2).map(|w| (w[0], w[1])).collect()
+    };
+    let (ba, bb) = (bigrams(&a), bigrams(&b));
+    if ba.is_empty() || bb.is_empty() {
+        return 0.0;
+    }
+    let mut pool: HashMap<(char, char), usize> = HashMap::new();
+    for g in &bb {
+        *pool.entry(*g).or_default() += 1;
+    }
+    let mut common = 0;
+    for g in &ba {
+        if let Some(n) = pool.get_mut(g).filter(|n| **n > 0) {
+            *n -= 1;
+            common += 1;
+        }
+    }
+    2.0 * common as f64 / (ba.len() + bb.len()) as f64
+}
+impl Ledger {
+    ///match statement lines of one account to its entries
+    ///
+    /// a pair needs the same amount (to the cent) and dates at most `date_window` days apart,
+    /// pairs are taken best score first so every line and entry is used once.
+    /// `ledger_only` holds unmatched entries dated inside the statement period
+    pub fn match_statement(
+        &self,
+        accountid: AccountId,
+        lines: &[StatementLine],
+        cfg: MatchConfig,
+    ) -> StatementMatch {
+        let (Some(first), Some(last)) = (
+            lines.iter().map(|l| l.date).min(),
+            lines.iter().map(|l| l.date).max(),
+        ) else {
+            return StatementMatch::default();
+        };
+        let txs: HashMap<TransactionId, &Transaction> =
+            self.transaction.iter().map(|t| (t.id, t)).collect();
+        let window = Duration::days(cfg.date_window);
+        let mut entries: Vec<(NaiveDate, &Entry, String)> = Vec::new();
+        for e in &self.entry {
+            if e.accountid != accountid {
+                continue;
+            }
+            let Some(tx) = txs.get(&e.tranid) else {
+                continue;
+            };
+            if tx.occur_date < first - window || tx.occur_date > last + window {
+                continue;
+            }
+            let payee = tx
+                .receiver
+                .clone()
+                .or_else(|| e.desc.clone())
+                .or_else(|| tx.desc.clone())
+                .unwrap_or_default();
+            entries.push((tx.occur_date, e, payee));
+        }
+
+        let mut pairs: Vec<(f64, usize, usize)> = Vec::new();
+        for (li, line) in lines.iter().enumerate() {
+            let cents = (line.amount * 100.0).round() as i64;
+            for (ei, (date, e, payee)) in entries.iter().enumerate() {
+                let days = (line.date - *date).num_days().abs();
+                if (e.amount * 100.0).round() as i64 != cents || days > cfg.date_window {
+                    continue;
+                }
+                let date_score = 1.0 - days as f64 / (cfg.date_window + 1) as f64;
+                let score = 0.5 * date_score + 0.5 * payee_similarity(&line.payee, payee);
+                pairs.push((score, li, ei));
+            }
+        }
+        pairs.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)).then(a.2.cmp(&b.2)));
+
+        let mut used_lines = HashSet::new();
+        let mut used_entries = HashSet::new();
+        let mut result = StatementMatch::default();
+        for (score, li, ei) in pairs {
+            if used_lines.contains(&li) || used_entries.contains(&ei) {
+                continue;
+            }
+            used_lines.insert(li);
+            used_entries.insert(ei);
+            result.matched.push(LineMatch {
+                line: lines[li].clone(),
+                entry: entries[ei].1.clone(),
+                date: entries[ei].0,
+                score,
+            });
+        }
+        result.matched.sort_by_key(|m| (m.line.date, m.entry.id));
+        result.statement_only = lines
+            .iter()
+            .enumerate()
+            .filter(|(li, _)| !used_lines.contains(li))
+            .map(|(_, l)| l.clone())
+            .collect();
+        result.ledger_only = entries
+            .iter()
+            .enumerate()
+            .filter(|(ei, (date, _, _))| {
+                !used_entries.contains(ei) && *date >= first && *date <= last
+            })
+            .map(|(_, (date, e, _))| (*date, (*e).clone()))
+            .collect();
+        result.ledger_only.sort_by_key(|(d, e)| (*d, e.id));
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 4, d).unwrap()
+    }
+    fn line(d: u32, amount: f64, payee: &str) -> StatementLine {
+        StatementLine {
+            date: day(d),
+            amount,
+            payee: payee.to_string(),
+            memo: None,
+        }
+    }
+    ///one transaction with a single entry on account 1
+    fn add_entry(ledger: &mut Ledger, id: EntryId, d: u32, amount: f64, payee: &str) {
+        let tranid = Uuid::from_u128(id as u128);
+        ledger.transaction.push(Transaction {
+            id: tranid,
+            userid: Uuid::nil(),
+            occur_date: day(d),
+            receiver: Some(payee.to_string()),
+            desc: None,
+            create_date: Utc::now(),
+        });
+        ledger.entry.push(Entry {
+            id,
+            userid: Uuid::nil(),
+            tranid,
+            accountid: 1,
+            categoryid: None,
+            amount,
+            desc: None,
+            status: EntryStatus::Uncleared,
+        });
+    }
+
+    #[test]
+    fn csv_quotes_and_amount_formats() {
+        let text = "Date,Description,Amount,Memo\n\
+            2025-04-01,\"Smith, \"\"Bob\"\" & Co\",(12.00),\"rent, April\"\n\
+            04/02/2025,Payroll,\"$1,234.50\",\n\
+            2025/04/03,Refund,\"$-1,234.50\",\n";
+        let lines = parse_statement_csv(text).unwrap();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].date, day(1));
+        assert_eq!(lines[0].payee, "Smith, \"Bob\" & Co");
+        assert_eq!(lines[0].amount, -12.00);
+        assert_eq!(lines[0].memo.as_deref(), Some("rent, April"));
+        assert_eq!(lines[1].date, day(2));
+        assert_eq!(lines[1].amount, 1234.50);
+        assert_eq!(lines[1].memo, None);
+        assert_eq!(lines[2].date, day(3));
+        assert_eq!(lines[2].amount, -1234.50);
+        assert_eq!(parse_statement_amount("$-1,234.50"), Some(-1234.50));
+        assert_eq!(parse_statement_amount("(1,000)"), Some(-1000.0));
+        assert_eq!(parse_statement_amount(" "), None);
+    }
+
+    #[test]
+    fn csv_debit_and_credit_columns() {
+        let text = "Posting Date,Name,Debit,Credit\n\
+            20250405,Grocer,45.10,\n\
+            20250406,Salary,,2000.00\n\
+            20250407,Fee,-3.00,\n";
+        let lines = parse_statement_csv(text).unwrap();
+        let amounts: Vec<f64> = lines.iter().map(|l| l.amount).collect();
+        assert_eq!(amounts, vec![-45.10, 2000.00, -3.00]);
+        assert_eq!(lines[1].payee, "Salary");
+        assert!(parse_statement_csv("Date,Payee\n2025-04-01,x\n").is_err());
+        assert!(parse_statement_csv("Payee,Amount\nx,1\n").is_err());
+        assert!(parse_statement_csv("Date,Amount\nyesterday,1\n").is_err());
+    }
+
+    #[test]
+    fn ofx_without_closing_tags_and_with_time_zone() {
+        let text = "OFXHEADER:100\n<OFX><BANKMSGSRSV1><STMTTRNRS><STMTRS><BANKTRANLIST>\n\
+            <STMTTRN>\n<TRNTYPE>DEBIT\n<DTPOSTED>20250408120000.000[-5:EST]\n\
+            <TRNAMT>-9.99\n<NAME>NETFLIX.COM\n<MEMO>monthly\n\
+            <STMTTRN><TRNTYPE>CREDIT<DTPOSTED>20250409<TRNAMT>150.00<NAME>Transfer</STMTTRN>\n\
+            </BANKTRANLIST></STMTRS></STMTTRNRS></BANKMSGSRSV1></OFX>\n";
+        let lines = parse_statement_ofx(text).unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].date, day(8));
+        assert_eq!(lines[0].amount, -9.99);
+        assert_eq!(lines[0].payee, "NETFLIX.COM");
+        assert_eq!(lines[0].memo.as_deref(), Some("monthly"));
+        assert_eq!(lines[1].date, day(9));
+        assert_eq!(lines[1].amount, 150.00);
+        assert_eq!(lines[1].memo, None);
+        assert!(parse_statement_ofx("<STMTTRN><TRNAMT>1.00</STMTTRN>").is_err());
+    }
+
+    #[test]
+    fn matching_pairs_best_first_and_once() {
+        let mut ledger = Ledger::default();
+        add_entry(&mut ledger, 1, 11, -20.00, "Coffee Shop");
+        add_entry(&mut ledger, 2, 12, -20.00, "Coffee Shop");
+        add_entry(&mut ledger, 3, 14, -55.00, "Gas");
+        // same amount and date, only the payee tells them apart
+        add_entry(&mut ledger, 4, 15, -9.99, "Spotify");
+        add_entry(&mut ledger, 5, 15, -9.99, "Netflix");
+        let lines = vec![
+            line(10, -20.00, "COFFEE SHOP #12"),
+            line(12, -20.00, "COFFEE SHOP #12"),
+            line(15, -9.99, "NETFLIX.COM 866-579"),
+            line(16, -70.00, "Hardware"),
+        ];
+        let m = ledger.match_statement(1, &lines, MatchConfig::default());
+        let pairs: Vec<(NaiveDate, EntryId)> = m
+            .matched
+            .iter()
+            .map(|m| (m.line.date, m.entry.id))
+            .collect();
+        // the exact date pair goes first, the earlier line takes what is left
+        assert_eq!(pairs, vec![(day(10), 1), (day(12), 2), (day(15), 5)]);
+        assert_eq!(m.matched[1].score, 1.0);
+        assert!(m.matched[0].score < 1.0);
+        assert_eq!(m.statement_only.len(), 1);
+        assert_eq!(m.statement_only[0].payee, "Hardware");
+        let ledger_only: Vec<EntryId> = m.ledger_only.iter().map(|(_, e)| e.id).collect();
+        assert_eq!(ledger_only, vec![3, 4]);
+    }
+
+    #[test]
+    fn matching_respects_the_date_window() {
+        let mut ledger = Ledger::default();
+        add_entry(&mut ledger, 1, 5, -20.00, "Coffee Shop");
+        let lines = vec![line(10, -20.00, "Coffee Shop")];
+        let m = ledger.match_statement(1, &lines, MatchConfig::default());
+        assert!(m.matched.is_empty());
+        // outside the statement period, so not ledger only either
+        assert!(m.ledger_only.is_empty());
+        let wide = MatchConfig { date_window: 5 };
+        assert_eq!(ledger.match_statement(1, &lines, wide).matched.len(), 1);
+        assert!(ledger.match_statement(2, &lines, wide).matched.is_empty());
+    }
+}
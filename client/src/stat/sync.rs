@@ -1,7 +1,7 @@
-use crate::stat::{Ledger, datatype::*};
+use crate::stat::{AccountSummary, DateRange, Ledger, Period, Trend, datatype::*};
 use anyhow::Result;
 use chrono::{DateTime, NaiveDate, Utc};
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
 use rust_decimal::Decimal;
 use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
 use serde::{Deserialize, Serialize};
@@ -43,6 +43,14 @@ pub struct Cloudaccount {
     pub opening_balance: Decimal,
     #[serde(rename = "created_at")]
     pub create_date: DateTime<Utc>,
+    #[serde(flatten)]
+    pub credit: Creditreq,
+    #[serde(flatten)]
+    pub loan: Loanreq,
+    #[serde(default)]
+    pub cost_basis: Option<String>,
+    #[serde(default)]
+    pub contact_email: Option<String>,
 }
 impl From<(Cloudaccount, Uuid)> for Account {
     fn from((v, user_id): (Cloudaccount, Uuid)) -> Self {
@@ -54,6 +62,10 @@ impl From<(Cloudaccount, Uuid)> for Account {
             currency: Currency::new(&v.currency),
             balance: v.opening_balance.to_f64().unwrap_or(0.0),
             create_date: v.create_date,
+            credit: CreditTerms::from(v.credit),
+            loan: v.loan.into(),
+            cost_basis: v.cost_basis.as_deref().and_then(CostBasis::from_cloud),
+            contact_email: v.contact_email,
         }
     }
 }
@@ -66,6 +78,10 @@ impl From<Account> for Cloudaccount {
             currency: v.currency.0.clone(),
             opening_balance: Decimal::from_f64(v.balance).unwrap_or(Decimal::ZERO),
             create_date: v.create_date,
+            credit: Creditreq::from(v.credit),
+            loan: Loanreq::from(v.loan),
+            cost_basis: v.cost_basis.map(|c| c.to_cloud().to_string()),
+            contact_email: v.contact_email,
         }
     }
 }
@@ -75,6 +91,8 @@ pub struct Cloudcategory {
     pub name: String,
     #[serde(rename = "parent_id")]
     pub parentid: Option<i64>,
+    #[serde(default)]
+    pub tax_line: Option<String>,
 }
 impl From<(Cloudcategory, Uuid)> for Category {
     fn from((v, user_id): (Cloudcategory, Uuid)) -> Self {
@@ -83,6 +101,7 @@ impl From<(Cloudcategory, Uuid)> for Category {
             userid: user_id,
             name: v.name,
             parentid: v.parentid,
+            tax_line: v.tax_line.as_deref().and_then(TaxLine::from_cloud),
         }
     }
 }
@@ -92,6 +111,7 @@ impl From<Category> for Cloudcategory {
             id: v.id,
             name: v.name,
             parentid: v.parentid,
+            tax_line: v.tax_line.map(|l| l.to_cloud().to_string()),
         }
     }
 }
@@ -144,6 +164,8 @@ pub struct Cloudentry {
     pub amount: Decimal,
     #[serde(rename = "note")]
     pub desc: Option<String>,
+    #[serde(default)]
+    pub status: EntryStatus,
 }
 impl From<(Cloudentry, Uuid)> for Entry {
     fn from((v, user_id): (Cloudentry, Uuid)) -> Self {
@@ -155,6 +177,7 @@ impl From<(Cloudentry, Uuid)> for Entry {
             categoryid: v.categoryid,
             amount: v.amount.to_f64().unwrap_or(0.0),
             desc: v.desc,
+            status: v.status,
         }
     }
 }
@@ -167,6 +190,135 @@ impl From<Entry> for Cloudentry {
             categoryid: v.categoryid,
             amount: Decimal::from_f64(v.amount).unwrap_or(Decimal::ZERO),
             desc: v.desc,
+            status: v.status,
+        }
+    }
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cloudsecurity {
+    pub id: i64,
+    pub symbol: String,
+    pub name: Option<String>,
+}
+impl From<(Cloudsecurity, Uuid)> for Security {
+    fn from((v, user_id): (Cloudsecurity, Uuid)) -> Self {
+        Self {
+            id: v.id,
+            userid: user_id,
+            symbol: v.symbol,
+            name: v.name,
+        }
+    }
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cloudtrade {
+    pub id: i64,
+    #[serde(rename = "account_id")]
+    pub accountid: i64,
+    #[serde(rename = "security_id")]
+    pub securityid: i64,
+    #[serde(rename = "tx_id")]
+    pub tranid: Uuid,
+    pub kind: String,
+    #[serde(rename = "trade_date")]
+    pub date: NaiveDate,
+    pub quantity: Decimal,
+    pub price: Decimal,
+    pub fees: Decimal,
+    pub amount: Decimal,
+}
+impl Cloudtrade {
+    ///none for a kind this client doesn't know
+    pub fn into_trade(self, user_id: Uuid) -> Option<Trade> {
+        Some(Trade {
+            id: self.id,
+            userid: user_id,
+            accountid: self.accountid,
+            securityid: self.securityid,
+            tranid: self.tranid,
+            kind: TradeKind::from_cloud(&self.kind)?,
+            date: self.date,
+            quantity: self.quantity.to_f64().unwrap_or(0.0),
+            price: self.price.to_f64().unwrap_or(0.0),
+            fees: self.fees.to_f64().unwrap_or(0.0),
+            amount: self.amount.to_f64().unwrap_or(0.0),
+        })
+    }
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cloudprice {
+    #[serde(rename = "security_id")]
+    pub securityid: i64,
+    #[serde(rename = "price_date")]
+    pub date: NaiveDate,
+    pub price: Decimal,
+}
+impl From<Cloudprice> for SecurityPrice {
+    fn from(v: Cloudprice) -> Self {
+        Self {
+            securityid: v.securityid,
+            date: v.date,
+            price: v.price.to_f64().unwrap_or(0.0),
+        }
+    }
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cloudattachment {
+    pub id: i64,
+    #[serde(rename = "tx_id")]
+    pub tranid: Uuid,
+    pub filename: String,
+    pub content_type: String,
+    pub size: i64,
+    pub sha256: String,
+    #[serde(rename = "created_at")]
+    pub create_date: DateTime<Utc>,
+}
+impl From<(Cloudattachment, Uuid)> for Attachment {
+    fn from((v, user_id): (Cloudattachment, Uuid)) -> Self {
+        Self {
+            id: v.id,
+            userid: user_id,
+            tranid: v.tranid,
+            filename: v.filename,
+            content_type: v.content_type,
+            size: v.size,
+            sha256: v.sha256,
+            create_date: v.create_date,
+        }
+    }
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cloudworkspace {
+    pub id: Uuid,
+    pub name: String,
+    pub role: String,
+    #[serde(default)]
+    pub personal: bool,
+}
+impl From<Cloudworkspace> for Workspace {
+    fn from(v: Cloudworkspace) -> Self {
+        Self {
+            id: v.id,
+            name: v.name,
+            role: WorkspaceRole::from(v.role),
+            personal: v.personal,
+        }
+    }
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cloudmember {
+    #[serde(rename = "user_id")]
+    pub userid: Uuid,
+    pub email: String,
+    pub role: String,
+}
+impl From<Cloudmember> for WorkspaceMember {
+    fn from(v: Cloudmember) -> Self {
+        Self {
+            userid: v.userid,
+            email: v.email,
+            role: WorkspaceRole::from(v.role),
         }
     }
 }
@@ -174,6 +326,8 @@ impl From<Entry> for Cloudentry {
 pub struct Cloudledger {
     pub user: Clouduser,
     #[serde(default)]
+    pub workspace: Option<Cloudworkspace>,
+    #[serde(default)]
     pub accounts: Vec<Cloudaccount>,
     #[serde(default)]
     pub categories: Vec<Cloudcategory>,
@@ -181,6 +335,14 @@ pub struct Cloudledger {
     pub transactions: Vec<Cloudtransaction>,
     #[serde(default)]
     pub entries: Vec<Cloudentry>,
+    #[serde(default)]
+    pub securities: Vec<Cloudsecurity>,
+    #[serde(default)]
+    pub trades: Vec<Cloudtrade>,
+    #[serde(default)]
+    pub prices: Vec<Cloudprice>,
+    #[serde(default)]
+    pub attachments: Vec<Cloudattachment>,
 }
 impl From<Cloudledger> for Ledger {
     fn from(v: Cloudledger) -> Self {
@@ -202,22 +364,73 @@ impl From<Cloudledger> for Ledger {
         for i in v.entries {
             entry.push(Entry::from((i, user_id)));
         }
-        Ledger {
-            user,
-            account: acc,
-            category: cat,
-            transaction: tran,
-            entry,
+        let mut ledger = Ledger::new(user, acc, cat, tran, entry);
+        ledger.security = v
+            .securities
+            .into_iter()
+            .map(|i| Security::from((i, user_id)))
+            .collect();
+        ledger.trade = v
+            .trades
+            .into_iter()
+            .filter_map(|i| i.into_trade(user_id))
+            .collect();
+        // the server sends prices by security then date, lookups rely on it
+        ledger.price = v.prices.into_iter().map(SecurityPrice::from).collect();
+        ledger.price.sort_by_key(|p| (p.securityid, p.date));
+        ledger.attachment = v
+            .attachments
+            .into_iter()
+            .map(|i| Attachment::from((i, user_id)))
+            .collect();
+        ledger.workspace = v.workspace.map(Workspace::from);
+        ledger
+    }
+}
+impl Ledger {
+    ///put a transaction the server just created into the ledger, stats see it before the next download
+    pub fn apply_cloudtransaction(&mut self, mut v: Cloudtransaction, user_id: Uuid) {
+        let entries = std::mem::take(&mut v.entries);
+        self.insert_transaction(Transaction::from((v, user_id)));
+        for e in entries {
+            self.insert_entry(Entry::from((e, user_id)));
+        }
+    }
+}
+///who ledger requests are made as: the bearer token and the workspace they work in
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    pub token: String,
+    pub workspace: Option<WorkspaceId>, // none = the personal one
+}
+impl Session {
+    pub fn new(token: String) -> Self {
+        Self {
+            token,
+            workspace: None,
+        }
+    }
+}
+trait Authed {
+    ///bearer token plus the X-Workspace-Id of the session's workspace
+    fn auth(self, session: &Session) -> Self;
+}
+impl Authed for RequestBuilder {
+    fn auth(self, session: &Session) -> Self {
+        let req = self.bearer_auth(&session.token);
+        match session.workspace {
+            Some(id) => req.header("X-Workspace-Id", id.to_string()),
+            None => req,
         }
     }
 }
 ///GET full ledger
-pub async fn download_ledger_from_server(base_url: &str, token: &str) -> Result<Ledger> {
+pub async fn download_ledger_from_server(base_url: &str, session: &Session) -> Result<Ledger> {
     let client = Client::new();
     let url = format!("{}/ledger", base_url.trim_end_matches('/'));
     let resp = client
         .get(&url)
-        .bearer_auth(token)
+        .auth(session)
         .send()
         .await?
         .error_for_status()?;
@@ -231,12 +444,89 @@ pub struct ACCreq {
     pub account_type: String,
     pub currency: Option<String>,
     pub opening_balance: Option<Decimal>,
+    #[serde(flatten)]
+    pub credit: Creditreq,
+    #[serde(flatten)]
+    pub loan: Loanreq,
+    pub cost_basis: Option<String>, // investment accounts, the server picks fifo if none
+    pub contact_email: Option<String>, // contacts only
+}
+///credit card terms as the server keeps them, all null for other account types
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Creditreq {
+    pub credit_limit: Option<Decimal>,
+    pub statement_day: Option<u32>,
+    pub due_day: Option<u32>,
+    pub apr: Option<Decimal>,
+}
+impl From<Creditreq> for CreditTerms {
+    fn from(v: Creditreq) -> Self {
+        Self {
+            limit: v.credit_limit.and_then(|d| d.to_f64()),
+            statement_day: v.statement_day,
+            due_day: v.due_day,
+            apr: v.apr.and_then(|d| d.to_f64()),
+        }
+    }
+}
+impl From<CreditTerms> for Creditreq {
+    fn from(v: CreditTerms) -> Self {
+        Self {
+            credit_limit: v.limit.and_then(Decimal::from_f64).map(|d| d.round_dp(2)),
+            statement_day: v.statement_day,
+            due_day: v.due_day,
+            apr: v.apr.and_then(Decimal::from_f64),
+        }
+    }
+}
+///loan terms as the server keeps them, all null unless a loan account has every one
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Loanreq {
+    pub loan_principal: Option<Decimal>,
+    pub loan_rate: Option<Decimal>,
+    pub loan_term_months: Option<u32>,
+    pub loan_frequency: Option<String>,
+    pub loan_first_payment: Option<NaiveDate>,
+}
+impl From<Loanreq> for Option<LoanTerms> {
+    fn from(v: Loanreq) -> Self {
+        Some(LoanTerms {
+            principal: v.loan_principal?.to_f64()?,
+            rate: v.loan_rate?.to_f64()?,
+            term_months: v.loan_term_months?,
+            frequency: LoanFrequency::from_cloud(v.loan_frequency.as_deref()?)?,
+            first_payment: v.loan_first_payment?,
+        })
+    }
+}
+impl From<Option<LoanTerms>> for Loanreq {
+    fn from(v: Option<LoanTerms>) -> Self {
+        match v {
+            Some(t) => Self {
+                loan_principal: Decimal::from_f64(t.principal).map(|d| d.round_dp(2)),
+                loan_rate: Decimal::from_f64(t.rate),
+                loan_term_months: Some(t.term_months),
+                loan_frequency: Some(t.frequency.to_cloud().to_string()),
+                loan_first_payment: Some(t.first_payment),
+            },
+            None => Self::default(),
+        }
+    }
+}
+#[derive(Debug, Clone, Serialize)]
+pub struct Loanpaymentreq {
+    pub from_account_id: i64,
+    pub amount: Decimal,
+    pub occurred_at: NaiveDate,
+    pub interest_category_id: Option<i64>,
+    pub payee: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct Catreq {
     pub parent_id: Option<i64>,
     pub name: String,
+    pub tax_line: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -254,6 +544,27 @@ pub struct Transreq {
     pub occurred_at: NaiveDate,
     pub entries: Vec<Entryreq>,
 }
+///non-2xx response from server, keep status so caller can react (e.g. 409 conflict)
+#[derive(Debug, Clone)]
+pub struct ServerError {
+    pub status: StatusCode,
+    pub message: String,
+}
+impl std::fmt::Display for ServerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "server error: {} {}", self.status, self.message)
+    }
+}
+impl std::error::Error for ServerError {}
+///like error_for_status, but keep server message body
+async fn check_status(resp: Response) -> Result<Response> {
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(resp);
+    }
+    let message = resp.text().await.unwrap_or_default();
+    Err(ServerError { status, message }.into())
+}
 ///base url+path
 fn api_url(base_url: &str, path: &str) -> String {
     format!(
@@ -264,7 +575,7 @@ fn api_url(base_url: &str, path: &str) -> String {
 }
 pub async fn create_cloudaccount(
     base_url: &str,
-    token: &str,
+    session: &Session,
     name: &str,
     account_type: &AccountType,
     currency: Option<&str>,
@@ -285,32 +596,263 @@ pub async fn create_cloudaccount(
         account_type: account_type.to_cloud().to_string(),
         currency: currency,
         opening_balance: ob,
+        credit: Creditreq::default(),
+        loan: Loanreq::default(),
+        cost_basis: None,
+        contact_email: None,
     };
     let resp = client
         .post(&url)
-        .bearer_auth(token)
+        .auth(session)
         .json(&body)
         .send()
         .await?
         .error_for_status()?;
     Ok(resp.json::<Cloudaccount>().await?)
 }
+///POST /accounts with a full request, credit terms included
+pub async fn create_cloudaccount_from(
+    base_url: &str,
+    session: &Session,
+    req: &ACCreq,
+) -> Result<Cloudaccount> {
+    let client = Client::new();
+    let url = api_url(base_url, "/accounts");
+    let resp = client.post(&url).auth(session).json(req).send().await?;
+    let resp = check_status(resp).await?;
+    Ok(resp.json::<Cloudaccount>().await?)
+}
+///PATCH /accounts/{id}/credit, replaces all credit terms, 400 for non credit accounts
+pub async fn set_credit_terms_on_server(
+    base_url: &str,
+    session: &Session,
+    account_id: i64,
+    terms: CreditTerms,
+) -> Result<()> {
+    let client = Client::new();
+    let url = api_url(base_url, &format!("/accounts/{account_id}/credit"));
+    let resp = client
+        .patch(&url)
+        .auth(session)
+        .json(&Creditreq::from(terms))
+        .send()
+        .await?;
+    check_status(resp).await?;
+    Ok(())
+}
+///PATCH /accounts/{id}/loan, none clears the terms, 400 for non loan accounts
+pub async fn set_loan_terms_on_server(
+    base_url: &str,
+    session: &Session,
+    account_id: i64,
+    terms: Option<LoanTerms>,
+) -> Result<()> {
+    let client = Client::new();
+    let url = api_url(base_url, &format!("/accounts/{account_id}/loan"));
+    let resp = client
+        .patch(&url)
+        .auth(session)
+        .json(&Loanreq::from(terms))
+        .send()
+        .await?;
+    check_status(resp).await?;
+    Ok(())
+}
+///POST /accounts/{id}/loan/payments, the server splits the payment into principal and interest
+pub async fn record_loan_payment_on_server(
+    base_url: &str,
+    session: &Session,
+    account_id: i64,
+    req: &Loanpaymentreq,
+) -> Result<Cloudtransaction> {
+    let client = Client::new();
+    let url = api_url(base_url, &format!("/accounts/{account_id}/loan/payments"));
+    let resp = client.post(&url).auth(session).json(req).send().await?;
+    let resp = check_status(resp).await?;
+    Ok(resp.json::<Cloudtransaction>().await?)
+}
+///settling up with a contact, the amount defaults to everything owed on `occurred_at`
+#[derive(Debug, Clone, Serialize)]
+pub struct Settlementreq {
+    pub account_id: i64, // real account the money goes into or comes out of
+    pub amount: Option<Decimal>,
+    pub occurred_at: NaiveDate,
+    pub payee: Option<String>,
+}
+///POST /accounts/{id}/settlements, a transfer between the contact and a real account
+pub async fn settle_contact_on_server(
+    base_url: &str,
+    session: &Session,
+    contact_id: i64,
+    req: &Settlementreq,
+) -> Result<Cloudtransaction> {
+    let client = Client::new();
+    let url = api_url(base_url, &format!("/accounts/{contact_id}/settlements"));
+    let resp = client.post(&url).auth(session).json(req).send().await?;
+    let resp = check_status(resp).await?;
+    Ok(resp.json::<Cloudtransaction>().await?)
+}
+#[derive(Debug, Clone, Serialize)]
+pub struct Tradereq {
+    pub symbol: String,
+    pub kind: String,
+    pub trade_date: NaiveDate,
+    pub quantity: Option<Decimal>,
+    pub price: Option<Decimal>,
+    pub fees: Option<Decimal>,
+    pub amount: Option<Decimal>,
+    pub category_id: Option<i64>,
+    pub payee: Option<String>,
+}
+#[derive(Debug, Clone, Serialize)]
+pub struct Pricereq {
+    pub symbol: String,
+    pub date: NaiveDate,
+    pub price: Decimal,
+}
+///what a price import did, rows of unknown symbols are skipped
+#[derive(Debug, Clone, Deserialize)]
+pub struct Cloudpriceimport {
+    pub imported: i64,
+    pub unknown: Vec<String>,
+}
+///POST /accounts/{id}/trades, the server books the cash side as a transaction on the account
+pub async fn create_trade_on_server(
+    base_url: &str,
+    session: &Session,
+    account_id: i64,
+    req: &Tradereq,
+) -> Result<Cloudtrade> {
+    let client = Client::new();
+    let url = api_url(base_url, &format!("/accounts/{account_id}/trades"));
+    let resp = client.post(&url).auth(session).json(req).send().await?;
+    let resp = check_status(resp).await?;
+    Ok(resp.json::<Cloudtrade>().await?)
+}
+///PATCH /accounts/{id}/cost-basis, 400 for non investment accounts
+pub async fn set_cost_basis_on_server(
+    base_url: &str,
+    session: &Session,
+    account_id: i64,
+    cost_basis: CostBasis,
+) -> Result<()> {
+    let client = Client::new();
+    let url = api_url(base_url, &format!("/accounts/{account_id}/cost-basis"));
+    let resp = client
+        .patch(&url)
+        .auth(session)
+        .json(&serde_json::json!({ "cost_basis": cost_basis.to_cloud() }))
+        .send()
+        .await?;
+    check_status(resp).await?;
+    Ok(())
+}
+///PATCH /categories/{id}/tax-line, none clears it
+pub async fn set_tax_line_on_server(
+    base_url: &str,
+    session: &Session,
+    category_id: i64,
+    tax_line: Option<TaxLine>,
+) -> Result<()> {
+    let client = Client::new();
+    let url = api_url(base_url, &format!("/categories/{category_id}/tax-line"));
+    let resp = client
+        .patch(&url)
+        .auth(session)
+        .json(&serde_json::json!({ "tax_line": tax_line.map(|l| l.to_cloud()) }))
+        .send()
+        .await?;
+    check_status(resp).await?;
+    Ok(())
+}
+///POST /securities/prices, a price already known for that day is replaced
+pub async fn import_prices_on_server(
+    base_url: &str,
+    session: &Session,
+    rows: &[Pricereq],
+) -> Result<Cloudpriceimport> {
+    let client = Client::new();
+    let url = api_url(base_url, "/securities/prices");
+    let resp = client.post(&url).auth(session).json(rows).send().await?;
+    let resp = check_status(resp).await?;
+    Ok(resp.json::<Cloudpriceimport>().await?)
+}
+///POST /transactions/{id}/attachments, the server checks the bytes against `sha256` of them
+pub async fn upload_attachment_on_server(
+    base_url: &str,
+    session: &Session,
+    tx_id: Uuid,
+    filename: &str,
+    bytes: Vec<u8>,
+) -> Result<Cloudattachment> {
+    let client = Client::new();
+    let url = api_url(base_url, &format!("/transactions/{tx_id}/attachments"));
+    let sha256 = crate::stat::sha256_hex(&bytes);
+    let resp = client
+        .post(&url)
+        .auth(session)
+        .query(&[("filename", filename), ("sha256", sha256.as_str())])
+        .body(bytes)
+        .send()
+        .await?;
+    let resp = check_status(resp).await?;
+    Ok(resp.json::<Cloudattachment>().await?)
+}
+///GET /attachments/{id}, bytes that don't match the server's checksum are an error
+pub async fn download_attachment_from_server(
+    base_url: &str,
+    session: &Session,
+    id: i64,
+) -> Result<Vec<u8>> {
+    let client = Client::new();
+    let url = api_url(base_url, &format!("/attachments/{id}"));
+    let resp = client.get(&url).auth(session).send().await?;
+    let resp = check_status(resp).await?;
+    let expected = resp
+        .headers()
+        .get("x-content-sha256")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let bytes = resp.bytes().await?.to_vec();
+    if let Some(expected) = expected
+        && crate::stat::sha256_hex(&bytes) != expected
+    {
+        anyhow::bail!("attachment {id} arrived damaged, checksum mismatch");
+    }
+    Ok(bytes)
+}
+pub async fn delete_attachment_on_server(base_url: &str, session: &Session, id: i64) -> Result<()> {
+    let client = Client::new();
+    let url = api_url(base_url, &format!("/attachments/{id}"));
+    let resp = client.delete(&url).auth(session).send().await?;
+    check_status(resp).await?;
+    Ok(())
+}
+pub async fn restore_attachment_on_server(base_url: &str, session: &Session, id: i64) -> Result<()> {
+    let client = Client::new();
+    let url = api_url(base_url, &format!("/attachments/{id}/restore"));
+    let resp = client.post(&url).auth(session).send().await?;
+    check_status(resp).await?;
+    Ok(())
+}
 
 pub async fn create_cloudcate(
     base_url: &str,
-    token: &str,
+    session: &Session,
     name: &str,
     parent_id: Option<i64>,
+    tax_line: Option<&str>,
 ) -> Result<Cloudcategory> {
     let client = Client::new();
     let url = api_url(base_url, "/categories");
     let body = Catreq {
         parent_id,
         name: name.to_string(),
+        tax_line: tax_line.map(str::to_string),
     };
     let resp = client
         .post(&url)
-        .bearer_auth(token)
+        .auth(session)
         .json(&body)
         .send()
         .await?
@@ -319,7 +861,7 @@ pub async fn create_cloudcate(
 }
 pub async fn create_cloudtransaction(
     base_url: &str,
-    token: &str,
+    session: &Session,
     occurred_date: NaiveDate,
     reciver: Option<&str>,
     desc: Option<&str>,
@@ -343,64 +885,505 @@ pub async fn create_cloudtransaction(
     };
     let resp = client
         .post(&url)
-        .bearer_auth(token)
+        .auth(session)
         .json(&body)
         .send()
         .await?
         .error_for_status()?;
     Ok(resp.json::<Cloudtransaction>().await?)
 }
-pub async fn delete_transaction_on_server(base_url: &str, token: &str, tx_id: Uuid) -> Result<()> {
+pub async fn delete_transaction_on_server(base_url: &str, session: &Session, tx_id: Uuid) -> Result<()> {
     let client = Client::new();
     let url = api_url(base_url, &format!("/transactions/{tx_id}"));
-    client
-        .delete(&url)
-        .bearer_auth(token)
-        .send()
-        .await?
-        .error_for_status()?;
+    let resp = client.delete(&url).auth(session).send().await?;
+    check_status(resp).await?;
     Ok(())
 }
-#[allow(dead_code)]
-pub async fn delete_entry_on_server(base_url: &str, token: &str, entry_id: i64) -> Result<()> {
+pub async fn restore_transaction_on_server(base_url: &str, session: &Session, tx_id: Uuid) -> Result<()> {
+    let client = Client::new();
+    let url = api_url(base_url, &format!("/transactions/{tx_id}/restore"));
+    let resp = client.post(&url).auth(session).send().await?;
+    check_status(resp).await?;
+    Ok(())
+}
+pub async fn delete_entry_on_server(base_url: &str, session: &Session, entry_id: i64) -> Result<()> {
     let client = Client::new();
     let url = api_url(base_url, &format!("/entries/{entry_id}"));
-    client
-        .delete(&url)
-        .bearer_auth(token)
-        .send()
-        .await?
-        .error_for_status()?;
+    let resp = client.delete(&url).auth(session).send().await?;
+    check_status(resp).await?;
+    Ok(())
+}
+pub async fn restore_entry_on_server(base_url: &str, session: &Session, entry_id: i64) -> Result<()> {
+    let client = Client::new();
+    let url = api_url(base_url, &format!("/entries/{entry_id}/restore"));
+    let resp = client.post(&url).auth(session).send().await?;
+    check_status(resp).await?;
     Ok(())
 }
-#[allow(dead_code)]
 pub async fn delete_category_on_server(
     base_url: &str,
-    token: &str,
+    session: &Session,
     category_id: i64,
 ) -> Result<()> {
     let client = Client::new();
     let url = api_url(base_url, &format!("/categories/{category_id}"));
-    client
-        .delete(&url)
-        .bearer_auth(token)
+    let resp = client.delete(&url).auth(session).send().await?;
+    check_status(resp).await?;
+    Ok(())
+}
+pub async fn restore_category_on_server(
+    base_url: &str,
+    session: &Session,
+    category_id: i64,
+) -> Result<()> {
+    let client = Client::new();
+    let url = api_url(base_url, &format!("/categories/{category_id}/restore"));
+    let resp = client.post(&url).auth(session).send().await?;
+    check_status(resp).await?;
+    Ok(())
+}
+pub async fn delete_account_on_server(base_url: &str, session: &Session, account_id: i64) -> Result<()> {
+    let client = Client::new();
+    let url = api_url(base_url, &format!("/accounts/{account_id}"));
+    let resp = client.delete(&url).auth(session).send().await?;
+    check_status(resp).await?;
+    Ok(())
+}
+pub async fn restore_account_on_server(base_url: &str, session: &Session, account_id: i64) -> Result<()> {
+    let client = Client::new();
+    let url = api_url(base_url, &format!("/accounts/{account_id}/restore"));
+    let resp = client.post(&url).auth(session).send().await?;
+    check_status(resp).await?;
+    Ok(())
+}
+///tick an entry off (cleared) or back (uncleared), reconciled is only set by finishing
+pub async fn set_entry_status_on_server(
+    base_url: &str,
+    session: &Session,
+    entry_id: i64,
+    status: EntryStatus,
+) -> Result<()> {
+    let client = Client::new();
+    let url = api_url(base_url, &format!("/entries/{entry_id}/status"));
+    let resp = client
+        .patch(&url)
+        .auth(session)
+        .json(&serde_json::json!({ "status": status }))
         .send()
-        .await?
-        .error_for_status()?;
+        .await?;
+    check_status(resp).await?;
     Ok(())
 }
+///reconciliation session of one account against one statement
 #[allow(dead_code)]
-pub async fn delete_account_on_server(base_url: &str, token: &str, account_id: i64) -> Result<()> {
+#[derive(Debug, Clone, Deserialize)]
+pub struct Cloudreconciliation {
+    pub id: i64,
+    pub account_id: i64,
+    pub statement_date: NaiveDate,
+    pub statement_balance: Decimal,
+    pub cleared_balance: Option<Decimal>,
+    pub status: String, // open/finished
+    pub created_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub entry_count: i64,
+}
+impl Cloudreconciliation {
+    pub fn is_open(&self) -> bool {
+        self.status == "open"
+    }
+}
+///POST /reconciliations, start a session, server allows one open session per account
+pub async fn create_reconciliation_on_server(
+    base_url: &str,
+    session: &Session,
+    account_id: i64,
+    statement_date: NaiveDate,
+    statement_balance: f64,
+) -> Result<Cloudreconciliation> {
     let client = Client::new();
-    let url = api_url(base_url, &format!("/accounts/{account_id}"));
-    client
-        .delete(&url)
-        .bearer_auth(token)
+    let url = api_url(base_url, "/reconciliations");
+    let body = serde_json::json!({
+        "account_id": account_id,
+        "statement_date": statement_date,
+        "statement_balance": Decimal::from_f64(statement_balance).unwrap_or(Decimal::ZERO).round_dp(2),
+    });
+    let resp = client
+        .post(&url)
+        .auth(session)
+        .json(&body)
         .send()
-        .await?
-        .error_for_status()?;
+        .await?;
+    let resp = check_status(resp).await?;
+    Ok(resp.json::<Cloudreconciliation>().await?)
+}
+///GET /reconciliations for one account, newest statement first
+pub async fn list_reconciliations_from_server(
+    base_url: &str,
+    session: &Session,
+    account_id: i64,
+) -> Result<Vec<Cloudreconciliation>> {
+    let client = Client::new();
+    let url = api_url(base_url, "/reconciliations");
+    let resp = client
+        .get(&url)
+        .auth(session)
+        .query(&[("account_id", account_id)])
+        .send()
+        .await?;
+    let resp = check_status(resp).await?;
+    Ok(resp.json::<Vec<Cloudreconciliation>>().await?)
+}
+///POST /reconciliations/{id}/finish, locks the cleared entries, 409 if the balance is off
+pub async fn finish_reconciliation_on_server(
+    base_url: &str,
+    session: &Session,
+    id: i64,
+) -> Result<Cloudreconciliation> {
+    let client = Client::new();
+    let url = api_url(base_url, &format!("/reconciliations/{id}/finish"));
+    let resp = client.post(&url).auth(session).send().await?;
+    let resp = check_status(resp).await?;
+    Ok(resp.json::<Cloudreconciliation>().await?)
+}
+///DELETE /reconciliations/{id}, drop an open session, ticks stay
+pub async fn cancel_reconciliation_on_server(base_url: &str, session: &Session, id: i64) -> Result<()> {
+    let client = Client::new();
+    let url = api_url(base_url, &format!("/reconciliations/{id}"));
+    let resp = client.delete(&url).auth(session).send().await?;
+    check_status(resp).await?;
+    Ok(())
+}
+///savings goal as stored on the server
+#[derive(Debug, Clone, Deserialize)]
+pub struct Cloudgoal {
+    pub id: i64,
+    pub name: String,
+    pub target_amount: Decimal,
+    pub target_date: Option<NaiveDate>,
+    pub tag: Option<String>,
+    pub account_ids: Vec<i64>,
+    #[serde(rename = "created_at")]
+    pub create_date: DateTime<Utc>,
+}
+impl From<(Cloudgoal, Uuid)> for Goal {
+    fn from((v, user_id): (Cloudgoal, Uuid)) -> Self {
+        Self {
+            id: v.id,
+            userid: user_id,
+            name: v.name,
+            target: v.target_amount.to_f64().unwrap_or(0.0),
+            deadline: v.target_date,
+            accounts: v.account_ids,
+            tag: v.tag,
+            create_date: v.create_date,
+        }
+    }
+}
+///GET /goals, earliest deadline first
+pub async fn list_goals_from_server(base_url: &str, session: &Session, userid: Uuid) -> Result<Vec<Goal>> {
+    let client = Client::new();
+    let url = api_url(base_url, "/goals");
+    let resp = client.get(&url).auth(session).send().await?;
+    let resp = check_status(resp).await?;
+    let goals = resp.json::<Vec<Cloudgoal>>().await?;
+    Ok(goals.into_iter().map(|g| Goal::from((g, userid))).collect())
+}
+///POST /goals, needs linked accounts or a tag, 409 when the name is taken
+pub async fn create_goal_on_server(
+    base_url: &str,
+    session: &Session,
+    goal: &Goal,
+) -> Result<Goal> {
+    let client = Client::new();
+    let url = api_url(base_url, "/goals");
+    let body = serde_json::json!({
+        "name": goal.name,
+        "target_amount": Decimal::from_f64(goal.target).unwrap_or(Decimal::ZERO).round_dp(2),
+        "target_date": goal.deadline,
+        "tag": goal.tag,
+        "account_ids": goal.accounts,
+    });
+    let resp = client
+        .post(&url)
+        .auth(session)
+        .json(&body)
+        .send()
+        .await?;
+    let resp = check_status(resp).await?;
+    let created = resp.json::<Cloudgoal>().await?;
+    Ok(Goal::from((created, goal.userid)))
+}
+///DELETE /goals/{id}
+pub async fn delete_goal_on_server(base_url: &str, session: &Session, id: i64) -> Result<()> {
+    let client = Client::new();
+    let url = api_url(base_url, &format!("/goals/{id}"));
+    let resp = client.delete(&url).auth(session).send().await?;
+    check_status(resp).await?;
     Ok(())
 }
+///POST /goals/{id}/restore
+pub async fn restore_goal_on_server(base_url: &str, session: &Session, id: i64) -> Result<()> {
+    let client = Client::new();
+    let url = api_url(base_url, &format!("/goals/{id}/restore"));
+    let resp = client.post(&url).auth(session).send().await?;
+    check_status(resp).await?;
+    Ok(())
+}
+///one row of the server side audit log
+#[allow(dead_code)]
+#[derive(Debug, Clone, Deserialize)]
+pub struct Cloudaudit {
+    pub id: i64,
+    pub table_name: String,
+    pub row_id: String,
+    pub tx_id: Option<Uuid>,
+    pub action: String,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub changed_at: DateTime<Utc>,
+    pub changed_by: Option<Uuid>,
+    pub changed_by_email: Option<String>,
+}
+impl Cloudaudit {
+    ///(field, before, after) for every column that differs, bookkeeping columns skipped
+    pub fn changed_fields(&self) -> Vec<(String, String, String)> {
+        const SKIP: [&str; 3] = ["user_id", "created_at", "deleted_at"];
+        let empty = serde_json::Map::new();
+        let before = self
+            .before
+            .as_ref()
+            .and_then(|v| v.as_object())
+            .unwrap_or(&empty);
+        let after = self
+            .after
+            .as_ref()
+            .and_then(|v| v.as_object())
+            .unwrap_or(&empty);
+        let show = |v: Option<&serde_json::Value>| match v {
+            None | Some(serde_json::Value::Null) => "-".to_string(),
+            Some(serde_json::Value::String(s)) => s.clone(),
+            Some(other) => other.to_string(),
+        };
+        let mut keys: Vec<&String> = before.keys().chain(after.keys()).collect();
+        keys.sort();
+        keys.dedup();
+        keys.into_iter()
+            .filter(|k| !SKIP.contains(&k.as_str()))
+            .filter(|k| before.get(*k) != after.get(*k))
+            .map(|k| (k.clone(), show(before.get(k)), show(after.get(k))))
+            .collect()
+    }
+}
+///GET /audit for one transaction, covers the transaction row and its entries, newest first
+pub async fn fetch_transaction_audit(
+    base_url: &str,
+    session: &Session,
+    tx_id: Uuid,
+) -> Result<Vec<Cloudaudit>> {
+    let client = Client::new();
+    let url = api_url(base_url, "/audit");
+    let resp = client
+        .get(&url)
+        .auth(session)
+        .query(&[("tx_id", tx_id.to_string()), ("limit", "500".to_string())])
+        .send()
+        .await?;
+    let resp = check_status(resp).await?;
+    Ok(resp.json::<Vec<Cloudaudit>>().await?)
+}
+///totals of one range, GET /reports/summary and one bucket of GET /reports/trends
+#[derive(Debug, Clone, Deserialize)]
+pub struct Cloudreporttotals {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub income: Decimal,
+    pub outcome: Decimal,
+    pub net: Decimal,
+}
+///one category or account of GET /reports/top-*
+#[allow(dead_code)]
+#[derive(Debug, Clone, Deserialize)]
+pub struct Cloudreportrank {
+    pub id: i64,
+    pub name: String,
+    pub income: Decimal,
+    pub outcome: Decimal,
+    pub net: Decimal,
+}
+///one account of GET /reports/balances
+#[derive(Debug, Clone, Deserialize)]
+pub struct Cloudreportbalance {
+    pub account_id: i64,
+    pub name: String,
+    pub account_type: String,
+    pub currency: String,
+    pub balance: Decimal,
+}
+///query of a report over `range`, optionally narrowed to one account / category
+fn report_params(
+    range: DateRange,
+    accountid: Option<AccountId>,
+    category: Option<CategoryId>,
+) -> Vec<(&'static str, String)> {
+    let mut params = vec![
+        ("from", range.from.to_string()),
+        ("to", range.to.to_string()),
+    ];
+    if let Some(acc) = accountid {
+        params.push(("account_id", acc.to_string()));
+    }
+    if let Some(cat) = category {
+        params.push(("category_id", cat.to_string()));
+    }
+    params
+}
+///`onlyspend` like `Ledger::top_category`: true ranks by outcome, false by income, None by net
+fn report_rank(onlyspend: Option<bool>) -> &'static str {
+    match onlyspend {
+        Some(true) => "outcome",
+        Some(false) => "income",
+        None => "net",
+    }
+}
+///server name of `period`, None for periods only the client can split (statement cycle, custom)
+pub fn report_period(period: Period) -> Option<&'static str> {
+    match period {
+        Period::Week => Some("week"),
+        Period::Month => Some("month"),
+        Period::Quarter => Some("quarter"),
+        Period::Year => Some("year"),
+        Period::StatementCycle { .. } | Period::Custom(_) => None,
+    }
+}
+async fn get_report<T: serde::de::DeserializeOwned>(
+    base_url: &str,
+    session: &Session,
+    path: &str,
+    params: &[(&'static str, String)],
+) -> Result<T> {
+    let client = Client::new();
+    let url = api_url(base_url, path);
+    let resp = client
+        .get(&url)
+        .auth(session)
+        .query(params)
+        .send()
+        .await?;
+    let resp = check_status(resp).await?;
+    Ok(resp.json::<T>().await?)
+}
+fn rank_trend(rows: Vec<Cloudreportrank>) -> Trend<(String, i64)> {
+    let mut trend = Trend {
+        axis: Vec::new(),
+        income: Vec::new(),
+        outcome: Vec::new(),
+        summary: Vec::new(),
+    };
+    for r in rows {
+        trend.axis.push((r.name, r.id));
+        trend.income.push(r.income.to_f64().unwrap_or(0.0));
+        trend.outcome.push(r.outcome.to_f64().unwrap_or(0.0));
+        trend.summary.push(r.net.to_f64().unwrap_or(0.0));
+    }
+    trend
+}
+///GET /reports/summary, (income, outcome, net) like `Ledger::month_summary`
+pub async fn fetch_report_summary(
+    base_url: &str,
+    session: &Session,
+    range: DateRange,
+    accountid: Option<AccountId>,
+    category: Option<CategoryId>,
+) -> Result<(f64, f64, f64)> {
+    let params = report_params(range, accountid, category);
+    let v: Cloudreporttotals = get_report(base_url, session, "/reports/summary", &params).await?;
+    Ok((
+        v.income.to_f64().unwrap_or(0.0),
+        v.outcome.to_f64().unwrap_or(0.0),
+        v.net.to_f64().unwrap_or(0.0),
+    ))
+}
+///GET /reports/top-categories, same as `Ledger::top_category`
+pub async fn fetch_report_top_categories(
+    base_url: &str,
+    session: &Session,
+    range: DateRange,
+    accountid: Option<AccountId>,
+    top_k: usize,
+    onlyspend: Option<bool>,
+) -> Result<Trend<(String, CategoryId)>> {
+    let mut params = report_params(range, accountid, None);
+    params.push(("limit", top_k.to_string()));
+    params.push(("rank", report_rank(onlyspend).to_string()));
+    let rows = get_report(base_url, session, "/reports/top-categories", &params).await?;
+    Ok(rank_trend(rows))
+}
+///GET /reports/top-accounts, same as `Ledger::top_account`
+pub async fn fetch_report_top_accounts(
+    base_url: &str,
+    session: &Session,
+    range: DateRange,
+    category: Option<CategoryId>,
+    top_k: usize,
+    onlyspend: Option<bool>,
+) -> Result<Trend<(String, AccountId)>> {
+    let mut params = report_params(range, None, category);
+    params.push(("limit", top_k.to_string()));
+    params.push(("rank", report_rank(onlyspend).to_string()));
+    let rows = get_report(base_url, session, "/reports/top-accounts", &params).await?;
+    Ok(rank_trend(rows))
+}
+///GET /reports/trends, same as `Ledger::data_periodtrend`, None when the server has no such period
+pub async fn fetch_report_trends(
+    base_url: &str,
+    session: &Session,
+    range: DateRange,
+    period: Period,
+    accountid: Option<AccountId>,
+    category: Option<CategoryId>,
+) -> Result<Option<Trend<DateRange>>> {
+    let Some(name) = report_period(period) else {
+        return Ok(None);
+    };
+    let mut params = report_params(range, accountid, category);
+    params.push(("period", name.to_string()));
+    let rows: Vec<Cloudreporttotals> =
+        get_report(base_url, session, "/reports/trends", &params).await?;
+    let mut trend = Trend {
+        axis: Vec::new(),
+        income: Vec::new(),
+        outcome: Vec::new(),
+        summary: Vec::new(),
+    };
+    for r in rows {
+        trend.axis.push(DateRange::new(r.from, r.to));
+        trend.income.push(r.income.to_f64().unwrap_or(0.0));
+        trend.outcome.push(r.outcome.to_f64().unwrap_or(0.0));
+        trend.summary.push(r.net.to_f64().unwrap_or(0.0));
+    }
+    Ok(Some(trend))
+}
+///GET /reports/balances, same as `Ledger::all_account_summary_on`
+pub async fn fetch_report_balances(
+    base_url: &str,
+    session: &Session,
+    date: NaiveDate,
+) -> Result<Vec<AccountSummary>> {
+    let params = [("date", date.to_string())];
+    let rows: Vec<Cloudreportbalance> =
+        get_report(base_url, session, "/reports/balances", &params).await?;
+    Ok(rows
+        .into_iter()
+        .map(|r| AccountSummary {
+            accountid: r.account_id,
+            name: r.name,
+            account_type: AccountType::from(r.account_type),
+            balance: r.balance.to_f64().unwrap_or(0.0),
+            currency: Currency::new(&r.currency),
+        })
+        .collect())
+}
 #[derive(Debug, Clone, Serialize)]
 struct Loginreq {
     email: String,
@@ -456,3 +1439,278 @@ pub async fn register(base_url: &str, email: &str, password: &str) -> Result<Reg
         Err(anyhow::anyhow!("register error: {status} {msg}"))
     }
 }
+
+///GET /workspaces, the user's workspaces, personal one first
+pub async fn list_workspaces_from_server(base_url: &str, token: &str) -> Result<Vec<Workspace>> {
+    let client = Client::new();
+    let url = api_url(base_url, "/workspaces");
+    let resp = client.get(&url).bearer_auth(token).send().await?;
+    let resp = check_status(resp).await?;
+    let rows = resp.json::<Vec<Cloudworkspace>>().await?;
+    Ok(rows.into_iter().map(Workspace::from).collect())
+}
+#[derive(Debug, Clone, Serialize)]
+pub struct Workspacereq {
+    pub name: String,
+}
+///POST /workspaces, the user becomes owner
+pub async fn create_workspace_on_server(base_url: &str, token: &str, name: &str) -> Result<Workspace> {
+    let client = Client::new();
+    let url = api_url(base_url, "/workspaces");
+    let body = Workspacereq {
+        name: name.to_string(),
+    };
+    let resp = client.post(&url).bearer_auth(token).json(&body).send().await?;
+    let resp = check_status(resp).await?;
+    Ok(Workspace::from(resp.json::<Cloudworkspace>().await?))
+}
+///GET /workspaces/{id}/members
+pub async fn list_members_from_server(
+    base_url: &str,
+    token: &str,
+    workspace_id: WorkspaceId,
+) -> Result<Vec<WorkspaceMember>> {
+    let client = Client::new();
+    let url = api_url(base_url, &format!("/workspaces/{workspace_id}/members"));
+    let resp = client.get(&url).bearer_auth(token).send().await?;
+    let resp = check_status(resp).await?;
+    let rows = resp.json::<Vec<Cloudmember>>().await?;
+    Ok(rows.into_iter().map(WorkspaceMember::from).collect())
+}
+#[derive(Debug, Clone, Serialize)]
+pub struct Memberreq {
+    pub email: String,
+    pub role: String,
+}
+///POST /workspaces/{id}/members, owners only, 404 for an unknown email, 409 if already a member
+pub async fn add_member_on_server(
+    base_url: &str,
+    token: &str,
+    workspace_id: WorkspaceId,
+    email: &str,
+    role: WorkspaceRole,
+) -> Result<WorkspaceMember> {
+    let client = Client::new();
+    let url = api_url(base_url, &format!("/workspaces/{workspace_id}/members"));
+    let body = Memberreq {
+        email: email.to_string(),
+        role: role.to_cloud().to_string(),
+    };
+    let resp = client.post(&url).bearer_auth(token).json(&body).send().await?;
+    let resp = check_status(resp).await?;
+    Ok(WorkspaceMember::from(resp.json::<Cloudmember>().await?))
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cloudtoken {
+    pub id: i64,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+impl From<Cloudtoken> for ApiToken {
+    fn from(v: Cloudtoken) -> Self {
+        Self {
+            id: v.id,
+            name: v.name,
+            prefix: v.prefix,
+            scopes: v.scopes,
+            expires_at: v.expires_at,
+            last_used_at: v.last_used_at,
+            revoked: v.revoked_at.is_some(),
+        }
+    }
+}
+#[derive(Debug, Clone, Deserialize)]
+struct Cloudcreatedtoken {
+    token: String,
+    #[serde(flatten)]
+    info: Cloudtoken,
+}
+#[derive(Debug, Clone, Serialize)]
+pub struct Tokenreq {
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>, // never expires when none
+}
+///GET /tokens, the user's personal access tokens, newest first
+pub async fn list_tokens_from_server(base_url: &str, token: &str) -> Result<Vec<ApiToken>> {
+    let client = Client::new();
+    let url = api_url(base_url, "/tokens");
+    let resp = client.get(&url).bearer_auth(token).send().await?;
+    let resp = check_status(resp).await?;
+    let rows = resp.json::<Vec<Cloudtoken>>().await?;
+    Ok(rows.into_iter().map(ApiToken::from).collect())
+}
+///POST /tokens, the new token comes with its description and is never sent again
+pub async fn create_token_on_server(
+    base_url: &str,
+    token: &str,
+    req: &Tokenreq,
+) -> Result<(String, ApiToken)> {
+    let client = Client::new();
+    let url = api_url(base_url, "/tokens");
+    let resp = client.post(&url).bearer_auth(token).json(req).send().await?;
+    let resp = check_status(resp).await?;
+    let created = resp.json::<Cloudcreatedtoken>().await?;
+    Ok((created.token, ApiToken::from(created.info)))
+}
+///DELETE /tokens/{id}, the token stops working at once
+pub async fn revoke_token_on_server(base_url: &str, token: &str, id: TokenId) -> Result<()> {
+    let client = Client::new();
+    let url = api_url(base_url, &format!("/tokens/{id}"));
+    let resp = client.delete(&url).bearer_auth(token).send().await?;
+    check_status(resp).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stat::{Granularity, Period};
+    use std::collections::HashMap;
+    use std::process::Command;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 0.005
+    }
+    fn assert_trend_eq<K: PartialEq + std::fmt::Debug>(what: &str, server: &Trend<K>, local: &Trend<K>) {
+        assert_eq!(server.axis, local.axis, "{what}: axis");
+        for (name, s, l) in [
+            ("income", &server.income, &local.income),
+            ("outcome", &server.outcome, &local.outcome),
+            ("net", &server.summary, &local.summary),
+        ] {
+            assert_eq!(s.len(), l.len(), "{what}: {name} length");
+            for (i, (s, l)) in s.iter().zip(l).enumerate() {
+                assert!(close(*s, *l), "{what}: {name}[{i}] server {s} local {l}");
+            }
+        }
+    }
+
+    /// loads backend/fixtures/reports.sh into a running server, then asks the server for every
+    /// report and checks it against what the client computes from the downloaded ledger
+    #[tokio::test]
+    #[ignore = "needs a running backend at $BASE (default http://localhost:8080), run with --ignored"]
+    async fn server_reports_match_ledger_on_shared_fixture() {
+        let base = std::env::var("BASE").unwrap_or_else(|_| "http://localhost:8080".to_string());
+        let script = concat!(env!("CARGO_MANIFEST_DIR"), "/../backend/fixtures/reports.sh");
+        let out = Command::new("bash").arg(script).env("BASE", &base).output().expect("run reports.sh");
+        assert!(out.status.success(), "reports.sh: {}", String::from_utf8_lossy(&out.stderr));
+        let out = String::from_utf8(out.stdout).unwrap();
+        let field = |name: &str| {
+            out.lines()
+                .find_map(|l| l.strip_prefix(name))
+                .map(|v| v.trim().to_string())
+                .unwrap_or_else(|| panic!("{name} missing in reports.sh output"))
+        };
+        let (email, password) = (field("email:"), field("password:"));
+        let accounts: HashMap<String, AccountId> = field("accounts:")
+            .split_whitespace()
+            .filter_map(|kv| kv.split_once('='))
+            .map(|(k, v)| (k.to_string(), v.parse().unwrap()))
+            .collect();
+
+        let auth = login(&base, &email, &password).await.unwrap();
+        let user = auth.user_id;
+        let session = &Session::new(auth.token);
+        let ledger = download_ledger_from_server(&base, session).await.unwrap();
+        let food = ledger.category.iter().find(|c| c.name == "Food").unwrap().id;
+        let visa = accounts["Visa"];
+
+        let whole = DateRange::new(
+            NaiveDate::from_ymd_opt(2025, 9, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2025, 12, 31).unwrap(),
+        );
+        let ranges = [
+            whole,
+            DateRange::month(2025, 9),
+            DateRange::month(2025, 10),
+            DateRange::month(2025, 11),
+            DateRange::month(2025, 12),
+            DateRange::new(
+                NaiveDate::from_ymd_opt(2025, 10, 5).unwrap(),
+                NaiveDate::from_ymd_opt(2025, 11, 16).unwrap(),
+            ),
+        ];
+
+        // summary, unfiltered and narrowed to an account or a category
+        for range in ranges {
+            for (acc, cat) in [(None, None), (Some(visa), None), (None, Some(food)), (Some(visa), Some(food))] {
+                let server = fetch_report_summary(&base, session, range, acc, cat).await.unwrap();
+                let local = (
+                    ledger.month_summary(user, range, acc, cat, Some(false)),
+                    ledger.month_summary(user, range, acc, cat, Some(true)),
+                    ledger.month_summary(user, range, acc, cat, None),
+                );
+                assert!(
+                    close(server.0, local.0) && close(server.1, local.1) && close(server.2, local.2),
+                    "summary {range:?} {acc:?} {cat:?}: server {server:?} local {local:?}"
+                );
+            }
+            let stats = ledger.range_stats(user, range);
+            let server = fetch_report_summary(&base, session, range, None, None).await.unwrap();
+            assert!(close(stats.income, server.0) && close(stats.outcome, server.1), "range_stats {range:?}");
+        }
+
+        // trend buckets
+        for period in [Period::Week, Period::Month, Period::Quarter, Period::Year] {
+            for (acc, cat) in [(None, None), (Some(visa), None), (None, Some(food))] {
+                let server = fetch_report_trends(&base, session, whole, period, acc, cat)
+                    .await
+                    .unwrap()
+                    .expect("server period");
+                let local = ledger.data_periodtrend(user, whole, period, acc, cat);
+                assert_trend_eq(&format!("trends {period:?} {acc:?} {cat:?}"), &server, &local);
+            }
+        }
+
+        // top-k of both kinds, every ranking
+        for range in ranges {
+            for onlyspend in [Some(true), Some(false), None] {
+                for k in [1, 2, 10] {
+                    let server = fetch_report_top_categories(&base, session, range, None, k, onlyspend).await.unwrap();
+                    let local = ledger.top_category(user, range, None, k, onlyspend);
+                    assert_trend_eq(&format!("top categories {range:?} {onlyspend:?} {k}"), &server, &local);
+                    let server = fetch_report_top_accounts(&base, session, range, None, k, onlyspend).await.unwrap();
+                    let local = ledger.top_account(user, range, None, k, onlyspend);
+                    assert_trend_eq(&format!("top accounts {range:?} {onlyspend:?} {k}"), &server, &local);
+                }
+            }
+        }
+
+        // balances, and the net worth they add up to
+        for (y, m, d) in [(2025, 8, 31), (2025, 9, 14), (2025, 10, 20), (2025, 12, 31)] {
+            let date = NaiveDate::from_ymd_opt(y, m, d).unwrap();
+            let server = fetch_report_balances(&base, session, date).await.unwrap();
+            let local = ledger.all_account_summary_on(date);
+            assert_eq!(server.len(), local.len(), "balances {date}");
+            for s in &server {
+                let l = local.iter().find(|l| l.accountid == s.accountid).expect("same accounts");
+                assert!(close(s.balance, l.balance), "balance of {} on {date}: server {} local {}", s.name, s.balance, l.balance);
+            }
+            let series = ledger.networth_series(user, date, date, Granularity::Daily);
+            let server_net: f64 = server.iter().map(|s| s.balance).sum();
+            assert!(close(*series.net_worth.last().unwrap(), server_net), "net worth on {date}");
+        }
+    }
+
+    #[test]
+    fn rates_are_sent_as_entered() {
+        let loan = Loanreq::from(Some(LoanTerms {
+            principal: 300_000.0,
+            rate: 4.875,
+            term_months: 300,
+            frequency: LoanFrequency::Monthly,
+            first_payment: NaiveDate::from_ymd_opt(2026, 1, 1).unwrap(),
+        }));
+        assert_eq!(loan.loan_rate, Some(Decimal::new(4875, 3)));
+        let credit = Creditreq::from(CreditTerms {
+            apr: Some(22.9949),
+            ..CreditTerms::default()
+        });
+        assert_eq!(credit.apr, Some(Decimal::new(229949, 4)));
+    }
+}
@@ -0,0 +1,421 @@
+use super::datatype::*;
+use super::ledger::*;
+use anyhow::{Result, anyhow};
+use chrono::*;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+//year-end tax summary: entries of categories mapped to a tax line, a split transaction counts
+//only its parts on mapped categories
+
+///width of the text report, fits a portrait page in a monospace font
+const TEXT_WIDTH: usize = 80;
+
+///one entry reported on a tax line
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct TaxItem {
+    pub date: NaiveDate,
+    pub tranid: TransactionId,
+    pub entryid: EntryId,
+    pub payee: Option<String>,
+    pub memo: Option<String>, // note of the entry, else of the transaction
+    pub categoryid: CategoryId,
+    pub category: String,
+    pub account: String,
+    pub amount: f64,           // signed like the entry
+    pub split_of: Option<f64>, // whole transaction when the entry is one part of a split
+}
+///one tax line over the year
+#[derive(Debug, Clone)]
+pub struct TaxLineTotal {
+    pub line: TaxLine,
+    pub total: f64, // received for income lines, spent for the others; refunds lower it
+    pub categories: Vec<(String, f64)>, // share of each category in `total`, largest first
+    pub items: Vec<TaxItem>, // by date
+}
+///tax-relevant totals of one calendar year
+#[derive(Debug, Clone)]
+pub struct TaxSummary {
+    pub year: i32,
+    pub lines: Vec<TaxLineTotal>, // in TaxLine order, lines without entries left out
+    pub income: f64,
+    pub deductions: f64, // every spending line
+}
+///quote a CSV field when it needs it
+fn csv_field(v: &str) -> String {
+    match v.contains([',', '"', '\n']) {
+        true => format!("\"{}\"", v.replace('"', "\"\"")),
+        false => v.to_string(),
+    }
+}
+///cut `v` to `width` characters, padded
+fn column(v: &str, width: usize) -> String {
+    let cut: String = v.chars().take(width).collect();
+    format!("{cut:<width$}")
+}
+impl TaxSummary {
+    ///one row per entry, the totals are left to the spreadsheet
+    pub fn to_csv(&self) -> String {
+        let mut out =
+            String::from("tax_line,date,payee,category,account,amount,memo,split_of,transaction\n");
+        for line in &self.lines {
+            for i in &line.items {
+                let row = [
+                    line.line.to_cloud().to_string(),
+                    i.date.to_string(),
+                    i.payee.clone().unwrap_or_default(),
+                    i.category.clone(),
+                    i.account.clone(),
+                    format!("{:.2}", i.amount),
+                    i.memo.clone().unwrap_or_default(),
+                    i.split_of.map(|v| format!("{:.2}", v)).unwrap_or_default(),
+                    i.tranid.to_string(),
+                ];
+                let row: Vec<String> = row.iter().map(|f| csv_field(f)).collect();
+                out.push_str(&row.join(","));
+                out.push('\n');
+            }
+        }
+        out
+    }
+    ///plain text report, ready to print or convert to PDF: the totals on the first page,
+    ///then one page per tax line with its supporting entries; pages are split by form feeds
+    pub fn to_text(&self) -> String {
+        let rule = "=".repeat(TEXT_WIDTH);
+        let thin = "-".repeat(TEXT_WIDTH);
+        let mut out = format!("Tax summary {}\n{rule}\n\n", self.year);
+        if self.lines.is_empty() {
+            out.push_str("No entries on categories with a tax line.\n");
+            return out;
+        }
+        for line in &self.lines {
+            let count = match line.items.len() {
+                1 => "1 entry".to_string(),
+                n => format!("{n} entries"),
+            };
+            out.push_str(&format!(
+                "{}{:>12}  ({count})\n",
+                column(line.line.name(), 52),
+                format!("{:.2}", line.total)
+            ));
+        }
+        out.push_str(&format!(
+            "{thin}\n{}{:>12}\n{}{:>12}\n",
+            column("Total income", 52),
+            format!("{:.2}", self.income),
+            column("Total deductions and expenses", 52),
+            format!("{:.2}", self.deductions)
+        ));
+        for line in &self.lines {
+            out.push_str(&format!("\x0c{} {}\n{rule}\n", line.line.name(), self.year));
+            for (category, total) in &line.categories {
+                out.push_str(&format!(
+                    "{}{:>12}\n",
+                    column(category, 52),
+                    format!("{:.2}", total)
+                ));
+            }
+            out.push_str(&format!(
+                "{thin}\n{}{:>12}\n\n",
+                column("Total", 52),
+                format!("{:.2}", line.total)
+            ));
+            out.push_str(&format!(
+                "{} {} {} {}{:>12}\n{thin}\n",
+                column("Date", 10),
+                column("Payee", 22),
+                column("Category", 16),
+                column("Account", 14),
+                "Amount"
+            ));
+            for i in &line.items {
+                out.push_str(&format!(
+                    "{} {} {} {}{:>12}\n",
+                    i.date,
+                    column(i.payee.as_deref().unwrap_or("-"), 22),
+                    column(&i.category, 16),
+                    column(&i.account, 14),
+                    format!("{:.2}", i.amount)
+                ));
+                let mut notes = Vec::new();
+                if let Some(memo) = &i.memo {
+                    notes.push(memo.clone());
+                }
+                if let Some(whole) = i.split_of {
+                    notes.push(format!("part of a split transaction of {:.2}", whole));
+                }
+                if !notes.is_empty() {
+                    let notes: String = notes.join("; ").chars().take(69).collect();
+                    out.push_str(&format!("{:11}{notes}\n", ""));
+                }
+            }
+        }
+        out
+    }
+    ///write `tax-summary-<year>.csv` and `.txt` into `dir`
+    pub fn export(&self, dir: &Path) -> Result<(PathBuf, PathBuf)> {
+        let csv = dir.join(format!("tax-summary-{}.csv", self.year));
+        let text = dir.join(format!("tax-summary-{}.txt", self.year));
+        std::fs::write(&csv, self.to_csv())
+            .map_err(|e| anyhow!("cannot write {}: {e}", csv.display()))?;
+        std::fs::write(&text, self.to_text())
+            .map_err(|e| anyhow!("cannot write {}: {e}", text.display()))?;
+        Ok((csv, text))
+    }
+}
+impl Ledger {
+    ///tax line of a category, inherited from the closest parent that has one
+    pub fn tax_line_of(&self, categoryid: CategoryId) -> Option<TaxLine> {
+        let mut id = Some(categoryid);
+        // bounded walk, the server doesn't stop a parent loop
+        for _ in 0..self.category.len() {
+            let cat = self.category.iter().find(|c| Some(c.id) == id)?;
+            if cat.tax_line.is_some() {
+                return cat.tax_line;
+            }
+            id = cat.parentid;
+        }
+        None
+    }
+    ///entries of `year` on categories with a tax line, per line
+    pub fn tax_summary(&self, year: i32) -> TaxSummary {
+        let transactions: HashMap<TransactionId, &Transaction> = self
+            .transaction
+            .iter()
+            .filter(|t| t.occur_date.year() == year)
+            .map(|t| (t.id, t))
+            .collect();
+        // a transaction with more than one categorized entry is a split
+        let mut parts: HashMap<TransactionId, (usize, f64)> = HashMap::new();
+        for e in &self.entry {
+            if e.categoryid.is_some() && transactions.contains_key(&e.tranid) {
+                let part = parts.entry(e.tranid).or_default();
+                part.0 += 1;
+                part.1 += e.amount;
+            }
+        }
+        let mut by_line: BTreeMap<TaxLine, Vec<TaxItem>> = BTreeMap::new();
+        for e in &self.entry {
+            let Some(categoryid) = e.categoryid else {
+                continue;
+            };
+            let Some(tx) = transactions.get(&e.tranid) else {
+                continue;
+            };
+            let Some(line) = self.tax_line_of(categoryid) else {
+                continue;
+            };
+            by_line.entry(line).or_default().push(TaxItem {
+                date: tx.occur_date,
+                tranid: tx.id,
+                entryid: e.id,
+                payee: tx.receiver.clone(),
+                memo: e.desc.clone().or_else(|| tx.desc.clone()),
+                categoryid,
+                category: self.trans_categoryid2name(categoryid),
+                account: self.trans_accountid2name(e.accountid),
+                amount: e.amount,
+                split_of: parts
+                    .get(&e.tranid)
+                    .filter(|(count, _)| *count > 1)
+                    .map(|(_, total)| *total),
+            });
+        }
+        let lines: Vec<TaxLineTotal> = by_line
+            .into_iter()
+            .map(|(line, mut items)| {
+                items.sort_by_key(|i| (i.date, i.entryid));
+                let sign = if line.is_income() { 1.0 } else { -1.0 };
+                let mut categories: BTreeMap<CategoryId, (String, f64)> = BTreeMap::new();
+                for i in &items {
+                    let slot = categories
+                        .entry(i.categoryid)
+                        .or_insert_with(|| (i.category.clone(), 0.0));
+                    slot.1 += sign * i.amount;
+                }
+                let mut categories: Vec<(String, f64)> = categories.into_values().collect();
+                categories.sort_by(|a, b| b.1.total_cmp(&a.1));
+                TaxLineTotal {
+                    line,
+                    total: items.iter().fold(0.0, |a, i| a + sign * i.amount),
+                    categories,
+                    items,
+                }
+            })
+            .collect();
+        let sum = |income: bool| {
+            lines
+                .iter()
+                .filter(|l| l.line.is_income() == income)
+                .fold(0.0, |a, l| a + l.total)
+        };
+        TaxSummary {
+            year,
+            income: sum(true),
+            deductions: sum(false),
+            lines,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn category(
+        id: CategoryId,
+        name: &str,
+        parentid: Option<CategoryId>,
+        tax_line: Option<TaxLine>,
+    ) -> Category {
+        Category {
+            id,
+            userid: Uuid::nil(),
+            name: name.to_string(),
+            parentid,
+            tax_line,
+        }
+    }
+    ///account, category and amount of one entry
+    type Part = (AccountId, Option<CategoryId>, f64);
+
+    ///transactions of (date, payee, entries)
+    fn ledger(txs: &[(NaiveDate, &str, &[Part])]) -> Ledger {
+        let category = vec![
+            category(1, "Pharmacy", None, Some(TaxLine::Medical)),
+            category(2, "Groceries", None, None),
+            category(3, "Dental", Some(4), None),
+            category(4, "Health", None, Some(TaxLine::Medical)),
+            category(5, "Consulting", None, Some(TaxLine::BusinessIncome)),
+            category(6, "Donations", None, Some(TaxLine::Charitable)),
+        ];
+        let (mut transaction, mut entry) = (Vec::new(), Vec::new());
+        for (i, (date, payee, parts)) in txs.iter().enumerate() {
+            let tranid = Uuid::from_u128(i as u128 + 1);
+            transaction.push(Transaction {
+                id: tranid,
+                userid: Uuid::nil(),
+                occur_date: *date,
+                receiver: Some(payee.to_string()),
+                desc: None,
+                create_date: Utc::now(),
+            });
+            for (accountid, categoryid, amount) in parts.iter() {
+                entry.push(Entry {
+                    id: entry.len() as i64 + 1,
+                    userid: Uuid::nil(),
+                    tranid,
+                    accountid: *accountid,
+                    categoryid: *categoryid,
+                    amount: *amount,
+                    desc: None,
+                    status: EntryStatus::Cleared,
+                });
+            }
+        }
+        Ledger::new(Vec::new(), Vec::new(), category, transaction, entry)
+    }
+    fn d(y: i32, m: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, day).unwrap()
+    }
+
+    #[test]
+    fn split_transactions_count_only_their_mapped_parts() {
+        let ledger = ledger(&[
+            (
+                d(2025, 3, 3),
+                "Costco",
+                &[(1, Some(2), -80.0), (1, Some(1), -25.5)],
+            ),
+            (d(2025, 4, 10), "Dr Smile", &[(1, Some(3), -200.0)]),
+            (d(2025, 5, 1), "Pharmacy", &[(1, Some(1), 10.0)]),
+            (d(2025, 6, 1), "Client", &[(1, Some(5), 1500.0)]),
+            // a transfer side without a category does not make a split
+            (
+                d(2025, 7, 4),
+                "Red Cross",
+                &[(1, Some(6), -75.0), (2, None, 75.0)],
+            ),
+            (d(2024, 12, 31), "Red Cross", &[(1, Some(6), -50.0)]),
+        ]);
+        assert_eq!(ledger.tax_line_of(3), Some(TaxLine::Medical));
+        assert_eq!(ledger.tax_line_of(2), None);
+
+        let summary = ledger.tax_summary(2025);
+        let lines: Vec<TaxLine> = summary.lines.iter().map(|l| l.line).collect();
+        assert_eq!(
+            lines,
+            vec![
+                TaxLine::Medical,
+                TaxLine::Charitable,
+                TaxLine::BusinessIncome
+            ]
+        );
+
+        let medical = &summary.lines[0];
+        // the refund lowers the total, groceries of the split are left out
+        assert_eq!(medical.total, 215.5);
+        assert_eq!(
+            medical.categories,
+            vec![
+                ("Dental".to_string(), 200.0),
+                ("Pharmacy".to_string(), 15.5)
+            ]
+        );
+        let items: Vec<(f64, Option<f64>)> = medical
+            .items
+            .iter()
+            .map(|i| (i.amount, i.split_of))
+            .collect();
+        assert_eq!(
+            items,
+            vec![(-25.5, Some(-105.5)), (-200.0, None), (10.0, None)]
+        );
+
+        let charitable = &summary.lines[1];
+        assert_eq!(charitable.total, 75.0);
+        assert_eq!(charitable.items.len(), 1);
+        assert_eq!(charitable.items[0].split_of, None);
+
+        assert_eq!(summary.income, 1500.0);
+        assert_eq!(summary.deductions, 290.5);
+    }
+
+    #[test]
+    fn csv_and_text_reports() {
+        let ledger = ledger(&[
+            (
+                d(2025, 3, 3),
+                "Costco, Inc.",
+                &[(1, Some(2), -80.0), (1, Some(1), -25.5)],
+            ),
+            (d(2025, 6, 1), "Client", &[(1, Some(5), 1500.0)]),
+        ]);
+        let summary = ledger.tax_summary(2025);
+        let csv = summary.to_csv();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows.len(), 3);
+        assert!(
+            rows[1].starts_with("medical,2025-03-03,\"Costco, Inc.\",Pharmacy,1,-25.50,,-105.50,")
+        );
+        assert!(rows[2].starts_with("business_income,2025-06-01,Client,Consulting,1,1500.00,,,"));
+
+        let text = summary.to_text();
+        assert!(text.starts_with("Tax summary 2025\n"));
+        assert_eq!(text.matches('\x0c').count(), 2);
+        assert!(text.contains("part of a split transaction of -105.50"));
+        assert!(
+            text.lines()
+                .all(|l| l.trim_start_matches('\x0c').chars().count() <= TEXT_WIDTH)
+        );
+
+        let empty = ledger.tax_summary(2023);
+        assert!(empty.lines.is_empty());
+        assert!(
+            empty
+                .to_text()
+                .contains("No entries on categories with a tax line.")
+        );
+    }
+}
@@ -1,21 +1,59 @@
 use crate::advisor::Modeltype;
-use crate::stat::Ledger;
-use crate::stat::datatype::{Entry, UserId};
-use chrono::{Datelike, Local};
+use crate::stat::sync::{Cloudaudit, Cloudreconciliation, Session};
+use crate::stat::{
+    AccountSummary, Anomaly, AnomalyConfig, ContactBalance, CreditStatus, DateRange, DiscrepancyCandidate, Forecast, ForecastConfig, GoalProgress,
+    Granularity, History, Ledger, LoanStatus, MatchConfig, Period, PeriodCompare, Portfolio, StatementLine, StatementMatch,
+    StatementReconcile, Subscription, TaxSummary, Trend,
+};
+use crate::stat::datatype::{
+    Account, AccountId, ApiToken, Attachment, AttachmentId, CategoryId, Entry, EntryId, Goal, GoalId, LoanFrequency, TokenId, TradeKind, TransactionId,
+    UserId, Workspace, WorkspaceMember, WorkspaceRole,
+};
+use chrono::{Datelike, Local, Months, NaiveDate};
+use rust_decimal::prelude::ToPrimitive;
+
+// statement cycle shown on Trends runs from the 15th to the 14th
+const TREND_CLOSING_DAY: u32 = 14;
+// months looked back for subscriptions, enough for two charges of a yearly one
+pub const SUBSCRIPTION_MONTHS: u32 = 24;
+// step of the extra payment on the Loans screen
+pub const LOAN_EXTRA_STEP: f64 = 50.0;
+// scopes a new personal access token can get, picked with j/k
+pub const TOKEN_SCOPES: [&[&str]; 3] = [
+    &["read:ledger"],
+    &["read:ledger", "write:transactions"],
+    &["admin"],
+];
 
 #[derive(Copy, Clone, Debug)]
 pub enum Screen {
     Dashboard,
     Accounts,
+    NetWorth,
     Transactions,
     CategoryStats,
     AccountStats,
     Trends,
+    Compare,
+    Forecast,
+    Subscriptions,
+    Goals,
+    Loans,
+    People,
+    Investments,
+    Taxes,
     Reconcile,
     Advisor,
     Help,
 }
 
+/// what the global range is compared with on the Compare screen
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CompareMode {
+    Previous, // the same number of months right before
+    LastYear, // the same months a year earlier
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum InputMode {
     Normal,
@@ -24,6 +62,37 @@ pub enum InputMode {
     CreatingCategory,
     CreatingAccount,
     AdvisorChat,
+    ConfirmDelete,
+    ViewingHistory,
+    StartingReconcile,
+    ImportingStatement,
+    CreatingGoal,
+    EditingCreditTerms,
+    EditingLoanTerms,
+    RecordingLoanPayment,
+    CreatingContact,
+    SettlingContact,
+    RecordingTrade,
+    ImportingPrices,
+    EditingTaxLines,
+    ViewingAttachments,
+    AddingAttachment,
+    SwitchingWorkspace,
+    CreatingWorkspace,
+    InvitingMember,
+    ViewingTokens,
+    CreatingToken,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DeleteTarget {
+    Transaction(TransactionId),
+    Entry(EntryId),
+    Account(AccountId),
+    Category(CategoryId),
+    Goal(GoalId),
+    Attachment(AttachmentId),
+    Token(TokenId),
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -65,6 +134,21 @@ pub struct ReconcileView {
     pub external_balance: f64,
     pub difference: f64,
     pub entries: Vec<ReconcileEntryView>,
+    pub candidates: Vec<DiscrepancyCandidate>,
+}
+
+/// stats computed by the server's /reports endpoints, valid for the keys they were fetched with
+#[derive(Clone, Debug)]
+pub struct ServerReport {
+    pub range: DateRange,
+    pub period: Period,
+    pub month: (i32, u32),
+    pub networth_date: NaiveDate,
+    pub month_totals: (f64, f64, f64), // income, outcome, net of `month`
+    pub top_categories: Trend<(String, CategoryId)>,
+    pub top_accounts: Trend<(String, AccountId)>,
+    pub trends: Option<Trend<DateRange>>, // None: period only the client can split
+    pub balances: Vec<AccountSummary>,    // at `networth_date`
 }
 
 pub struct App {
@@ -78,14 +162,110 @@ pub struct App {
     pub selected_transaction_idx: usize,
     pub selected_category_stats_idx: usize,
     pub selected_account_stats_idx: usize,
+    pub networth_date: NaiveDate, // date shown in the Net Worth breakdown
+    pub networth_granularity: Granularity,
+    pub chart_view: bool, // Trends / stats screens: chart instead of table
+    pub trend_period: Period, // bucket size of the Trends screen
+    pub compare_mode: CompareMode,
+    pub forecast_months: u32, // whole months projected on the Forecast screen
+    pub forecast: Option<Forecast>, // computed in the run loop, cleared when the ledger changes
+    pub subscriptions: Option<Vec<Subscription>>, // same as `forecast`
+    pub anomalies: Option<Vec<Anomaly>>, // of `selected_month`, same as `forecast`
+    pub server_reports: bool, // stats screens show server reports instead of the local ledger
+    pub server_report: Option<ServerReport>,
     pub input_mode: InputMode,
+    pub pending_delete: Option<DeleteTarget>,
+    pub delete_entry_idx: usize, // selected split in the delete popup
     pub reconcile_external_balance: String,
     pub reconcile_result: Option<ReconcileView>,
+    pub recon_account_idx: usize,
+    pub recon_sessions: Vec<Cloudreconciliation>, // sessions of the selected account, newest first
+    pub recon_needs_reload: bool,
+    pub recon_entry_idx: usize,
+    pub recon_statement_date: String,
+    pub recon_statement_balance: String,
+    pub recon_field_idx: usize, // 0=statement date, 1=closing balance
+    pub recon_import_path: String,
+    pub recon_statement_lines: Vec<StatementLine>, // imported statement, empty = none loaded
+    pub recon_line_idx: usize,                     // selected statement-only line
+    pub goals: Vec<Goal>,
+    pub goals_needs_reload: bool,
+    pub selected_goal_idx: usize,
+    pub new_goal_name: String,
+    pub new_goal_target: String,
+    pub new_goal_deadline: String,
+    pub new_goal_tag: String,
+    pub new_goal_accounts: Vec<AccountId>, // linked accounts, toggled with Space
+    pub new_goal_account_idx: usize,       // account under the cursor
+    pub new_goal_field_idx: usize,         // 0=name, 1=target, 2=deadline, 3=tag, 4=accounts
+    pub credit_accountid: Option<AccountId>, // card whose terms are being edited
+    pub credit_limit: String,
+    pub credit_statement_day: String,
+    pub credit_due_day: String,
+    pub credit_apr: String,
+    pub credit_field_idx: usize, // 0=limit, 1=statement day, 2=due day, 3=apr
+    pub selected_loan_idx: usize,
+    pub loan_extra: f64, // added to every payment in the Loans payoff projection
+    pub loan_accountid: Option<AccountId>, // loan whose terms are being edited or paid
+    pub loan_principal: String,
+    pub loan_rate: String,
+    pub loan_term_months: String,
+    pub loan_frequency: LoanFrequency,
+    pub loan_first_payment: String,
+    pub loan_field_idx: usize, // 0=principal, 1=rate, 2=term, 3=frequency, 4=first payment
+    pub loan_pay_from_idx: usize, // index into the ledger accounts
+    pub loan_pay_amount: String,
+    pub loan_pay_date: String,
+    pub loan_pay_category_idx: usize, // 0=none, otherwise index into the ledger categories + 1
+    pub loan_pay_field_idx: usize,    // 0=from account, 1=amount, 2=date, 3=interest category
+    pub selected_contact_idx: usize,
+    pub contact_accountid: Option<AccountId>, // contact being settled with
+    pub contact_name: String,
+    pub contact_email: String,
+    pub contact_field_idx: usize, // 0=name, 1=email
+    pub settle_account_idx: usize, // index into the ledger accounts
+    pub settle_amount: String,     // positive when they pay you, negative when you pay them
+    pub settle_date: String,
+    pub settle_field_idx: usize, // 0=account, 1=amount, 2=date
+    pub selected_portfolio_idx: usize,
+    pub trade_accountid: Option<AccountId>, // investment account the trade is recorded on
+    pub trade_kind: TradeKind,
+    pub trade_symbol: String,
+    pub trade_date: String,
+    pub trade_quantity: String, // units, new units per old unit for a split
+    pub trade_price: String,
+    pub trade_fees: String,
+    pub trade_amount: String,       // dividend received
+    pub trade_category_idx: usize,  // dividend category, 0=none, otherwise ledger categories + 1
+    pub trade_field_idx: usize, // 0=kind, 1=symbol, 2=date, 3=quantity, 4=price, 5=fees, 6=amount, 7=category
+    pub price_import_path: String,
+    pub tax_year: i32,
+    pub selected_tax_line_idx: usize,
+    pub tax_category_idx: usize, // category under the cursor of the tax lines popup
     pub should_quit: bool,
     pub base_url: String,
-    pub token: String,
+    pub session: Session, // token and workspace every ledger request is made with
     pub needs_refresh: bool,
     pub error_message: Option<String>,
+    pub status_message: Option<String>,
+    pub history: History,
+    pub tx_history: Vec<Cloudaudit>, // audit rows of the transaction in the history popup
+    pub tx_history_scroll: u16,
+    pub attachment_tranid: Option<TransactionId>, // transaction of the attachments popup
+    pub selected_attachment_idx: usize,
+    pub attachment_path: String, // file to attach
+    pub workspaces: Vec<Workspace>, // fetched when the workspaces popup opens
+    pub selected_workspace_idx: usize,
+    pub workspace_members: Vec<WorkspaceMember>, // of the workspace under the cursor
+    pub workspace_input: String, // name of a new workspace or email to invite
+    pub invite_role: WorkspaceRole,
+    pub tokens: Vec<ApiToken>, // fetched when the tokens popup opens
+    pub selected_token_idx: usize,
+    pub created_token: Option<String>, // shown once, until the popup closes
+    pub token_name: String,
+    pub token_scope_idx: usize, // index into TOKEN_SCOPES
+    pub token_expiry_days: String, // empty for a token that never expires
+    pub token_field_idx: usize, // 0=name, 1=scopes, 2=expiry
     pub new_tx_date: String,
     pub new_tx_payee: String,
     pub new_tx_memo: String,
@@ -99,7 +279,7 @@ pub struct App {
     pub new_tx_selected_entry_idx: usize,
     pub new_account_name: String,
     pub new_account_type_idx: usize, // field index (0=name, 1=type, 2=currency, 3=balance)
-    pub new_account_type_selection: usize, // account type selection (0=Checking, 1=Credit, 2=Cash, 3=Other)
+    pub new_account_type_selection: usize, // account type selection (0=Checking, 1=Credit, 2=Cash, 3=Loan, 4=Investment, 5=Other)
     pub new_account_currency: String,
     pub new_account_balance: String,
     pub advisor_model_type: Modeltype,
@@ -124,7 +304,7 @@ fn add_months((y, m): (i32, u32), delta: i32) -> (i32, u32) {
 }
 
 impl App {
-    pub fn new(ledger: Ledger, base_url: String, token: String) -> Self {
+    pub fn new(ledger: Ledger, base_url: String, session: Session) -> Self {
         let user_id = ledger
             .user
             .first()
@@ -160,7 +340,7 @@ impl App {
             ledger,
             user_id,
             base_url,
-            token,
+            session,
             current_screen: Screen::Dashboard,
             start_month: min_ym,
             end_month: max_ym,
@@ -169,12 +349,112 @@ impl App {
             selected_transaction_idx: 0,
             selected_category_stats_idx: 0,
             selected_account_stats_idx: 0,
+            networth_date: today,
+            networth_granularity: Granularity::Monthly,
+            chart_view: false,
+            trend_period: Period::Month,
+            compare_mode: CompareMode::Previous,
+            forecast_months: ForecastConfig::default().months,
+            forecast: None,
+            subscriptions: None,
+            anomalies: None,
+            server_reports: false,
+            server_report: None,
             input_mode: InputMode::Normal,
+            pending_delete: None,
+            delete_entry_idx: 0,
             reconcile_external_balance: String::new(),
             reconcile_result: None,
+            recon_account_idx: 0,
+            recon_sessions: Vec::new(),
+            recon_needs_reload: true,
+            recon_entry_idx: 0,
+            recon_statement_date: String::new(),
+            recon_statement_balance: String::new(),
+            recon_field_idx: 0,
+            recon_import_path: String::new(),
+            recon_statement_lines: Vec::new(),
+            recon_line_idx: 0,
+            goals: Vec::new(),
+            goals_needs_reload: true,
+            selected_goal_idx: 0,
+            new_goal_name: String::new(),
+            new_goal_target: String::new(),
+            new_goal_deadline: String::new(),
+            new_goal_tag: String::new(),
+            new_goal_accounts: Vec::new(),
+            new_goal_account_idx: 0,
+            new_goal_field_idx: 0,
+            credit_accountid: None,
+            credit_limit: String::new(),
+            credit_statement_day: String::new(),
+            credit_due_day: String::new(),
+            credit_apr: String::new(),
+            credit_field_idx: 0,
+            selected_loan_idx: 0,
+            loan_extra: 0.0,
+            loan_accountid: None,
+            loan_principal: String::new(),
+            loan_rate: String::new(),
+            loan_term_months: String::new(),
+            loan_frequency: LoanFrequency::Monthly,
+            loan_first_payment: String::new(),
+            loan_field_idx: 0,
+            loan_pay_from_idx: 0,
+            loan_pay_amount: String::new(),
+            loan_pay_date: String::new(),
+            loan_pay_category_idx: 0,
+            loan_pay_field_idx: 0,
+            selected_contact_idx: 0,
+            contact_accountid: None,
+            contact_name: String::new(),
+            contact_email: String::new(),
+            contact_field_idx: 0,
+            settle_account_idx: 0,
+            settle_amount: String::new(),
+            settle_date: String::new(),
+            settle_field_idx: 0,
+            selected_portfolio_idx: 0,
+            trade_accountid: None,
+            trade_kind: TradeKind::Buy,
+            trade_symbol: String::new(),
+            trade_date: String::new(),
+            trade_quantity: String::new(),
+            trade_price: String::new(),
+            trade_fees: String::new(),
+            trade_amount: String::new(),
+            trade_category_idx: 0,
+            trade_field_idx: 0,
+            price_import_path: String::new(),
+            // the year being filed until spring, then the running one
+            tax_year: match today.month() {
+                1..=4 => today.year() - 1,
+                _ => today.year(),
+            },
+            selected_tax_line_idx: 0,
+            tax_category_idx: 0,
             should_quit: false,
             needs_refresh: false,
             error_message: None,
+            status_message: None,
+            history: History::default(),
+            tx_history: Vec::new(),
+            tx_history_scroll: 0,
+            attachment_tranid: None,
+            selected_attachment_idx: 0,
+            attachment_path: String::new(),
+            workspaces: Vec::new(),
+            selected_workspace_idx: 0,
+            workspace_members: Vec::new(),
+            workspace_input: String::new(),
+            invite_role: WorkspaceRole::Editor,
+            tokens: Vec::new(),
+            selected_token_idx: 0,
+            created_token: None,
+            token_name: String::new(),
+            token_scope_idx: 0,
+            token_expiry_days: String::new(),
+            token_field_idx: 0,
             new_tx_date: String::new(),
             new_tx_payee: String::new(),
             new_tx_memo: String::new(),
@@ -207,11 +487,20 @@ impl App {
     pub fn next_screen(&mut self) {
         self.current_screen = match self.current_screen {
             Screen::Dashboard => Screen::Accounts,
-            Screen::Accounts => Screen::Transactions,
+            Screen::Accounts => Screen::NetWorth,
+            Screen::NetWorth => Screen::Transactions,
             Screen::Transactions => Screen::CategoryStats,
             Screen::CategoryStats => Screen::AccountStats,
             Screen::AccountStats => Screen::Trends,
-            Screen::Trends => Screen::Reconcile,
+            Screen::Trends => Screen::Compare,
+            Screen::Compare => Screen::Forecast,
+            Screen::Forecast => Screen::Subscriptions,
+            Screen::Subscriptions => Screen::Goals,
+            Screen::Goals => Screen::Loans,
+            Screen::Loans => Screen::People,
+            Screen::People => Screen::Investments,
+            Screen::Investments => Screen::Taxes,
+            Screen::Taxes => Screen::Reconcile,
             Screen::Reconcile => Screen::Advisor,
             Screen::Advisor => Screen::Help,
             Screen::Help => Screen::Dashboard,
@@ -222,11 +511,20 @@ impl App {
         self.current_screen = match self.current_screen {
             Screen::Dashboard => Screen::Help,
             Screen::Accounts => Screen::Dashboard,
-            Screen::Transactions => Screen::Accounts,
+            Screen::NetWorth => Screen::Accounts,
+            Screen::Transactions => Screen::NetWorth,
             Screen::CategoryStats => Screen::Transactions,
             Screen::AccountStats => Screen::CategoryStats,
             Screen::Trends => Screen::AccountStats,
-            Screen::Reconcile => Screen::Trends,
+            Screen::Compare => Screen::Trends,
+            Screen::Forecast => Screen::Compare,
+            Screen::Subscriptions => Screen::Forecast,
+            Screen::Goals => Screen::Subscriptions,
+            Screen::Loans => Screen::Goals,
+            Screen::People => Screen::Loans,
+            Screen::Investments => Screen::People,
+            Screen::Taxes => Screen::Investments,
+            Screen::Reconcile => Screen::Taxes,
             Screen::Advisor => Screen::Reconcile,
             Screen::Help => Screen::Advisor,
         };
@@ -234,10 +532,12 @@ impl App {
 
     pub fn next_month(&mut self) {
         self.selected_month = add_months(self.selected_month, 1);
+        self.anomalies = None;
     }
 
     pub fn prev_month(&mut self) {
         self.selected_month = add_months(self.selected_month, -1);
+        self.anomalies = None;
     }
 
     pub fn startmonth_range(&mut self, delta: i32) {
@@ -246,6 +546,354 @@ impl App {
     pub fn endmonth_range(&mut self, delta: i32) {
         self.end_month = add_months(self.end_month, delta);
     }
+    /// days covered by the global month range
+    pub fn date_range(&self) -> DateRange {
+        DateRange::months(self.start_month, self.end_month)
+    }
+
+    /// Trends buckets: month → week → quarter → year → statement cycle
+    pub fn cycle_trend_period(&mut self) {
+        self.trend_period = match self.trend_period {
+            Period::Month => Period::Week,
+            Period::Week => Period::Quarter,
+            Period::Quarter => Period::Year,
+            Period::Year => Period::StatementCycle {
+                closing_day: TREND_CLOSING_DAY,
+            },
+            _ => Period::Month,
+        };
+    }
+
+    pub fn cycle_compare_mode(&mut self) {
+        self.compare_mode = match self.compare_mode {
+            CompareMode::Previous => CompareMode::LastYear,
+            CompareMode::LastYear => CompareMode::Previous,
+        };
+    }
+
+    /// the global range against the one `compare_mode` picks
+    pub fn compare(&self) -> PeriodCompare {
+        let current = self.date_range();
+        let base = match self.compare_mode {
+            CompareMode::Previous => current.previous(),
+            CompareMode::LastYear => current.shift_months(-12),
+        };
+        self.ledger.compare_periods(self.user_id, base, current)
+    }
+
+    /// cash-flow projection from today over the rest of this month and `forecast_months` more
+    pub fn compute_forecast(&self) -> Forecast {
+        let cfg = ForecastConfig {
+            months: self.forecast_months,
+            ..ForecastConfig::default()
+        };
+        self.ledger
+            .forecast(self.user_id, Local::now().date_naive(), cfg)
+    }
+
+    /// recurring charges of the last `SUBSCRIPTION_MONTHS` months up to today
+    pub fn compute_subscriptions(&self) -> Vec<Subscription> {
+        let today = Local::now().date_naive();
+        let range = DateRange::new(
+            DateRange::last_months(today, SUBSCRIPTION_MONTHS).from,
+            today,
+        );
+        self.ledger.subscriptions(self.user_id, range)
+    }
+
+    /// unusual spending of the focused month, ranked
+    pub fn compute_anomalies(&self) -> Vec<Anomaly> {
+        let (y, m) = self.selected_month;
+        self.ledger
+            .anomalies(self.user_id, DateRange::month(y, m), AnomalyConfig::default())
+    }
+
+    /// progress of every goal as of today, in the server's order
+    pub fn goal_progress(&self) -> Vec<GoalProgress> {
+        let today = Local::now().date_naive();
+        self.goals
+            .iter()
+            .map(|g| self.ledger.goal_progress(g, today))
+            .collect()
+    }
+
+    /// statement, due date and utilization of every credit card as of today
+    pub fn credit_status(&self) -> Vec<CreditStatus> {
+        self.ledger.credit_status(Local::now().date_naive())
+    }
+
+    /// balance, next payment split and payoff projection of every loan as of today
+    pub fn loan_status(&self) -> Vec<LoanStatus> {
+        self.ledger
+            .loan_status(Local::now().date_naive(), self.loan_extra)
+    }
+
+    /// loan under the cursor on the Loans screen
+    pub fn selected_loan(&self) -> Option<LoanStatus> {
+        let mut loans = self.loan_status();
+        if loans.is_empty() {
+            return None;
+        }
+        Some(loans.swap_remove(self.selected_loan_idx.min(loans.len() - 1)))
+    }
+
+    /// change the Loans extra payment, never below zero
+    pub fn shift_loan_extra(&mut self, delta: f64) {
+        self.loan_extra = (self.loan_extra + delta).max(0.0);
+    }
+
+    /// what every contact owes or is owed as of today
+    pub fn contact_balances(&self) -> Vec<ContactBalance> {
+        self.ledger.contact_balances(Local::now().date_naive())
+    }
+
+    /// contact under the cursor on the People screen
+    pub fn selected_contact(&self) -> Option<ContactBalance> {
+        let mut contacts = self.contact_balances();
+        if contacts.is_empty() {
+            return None;
+        }
+        Some(contacts.swap_remove(self.selected_contact_idx.min(contacts.len() - 1)))
+    }
+
+    /// holdings, gains and market value of every investment account as of today
+    pub fn portfolios(&self) -> Vec<Portfolio> {
+        self.ledger.portfolios(Local::now().date_naive())
+    }
+
+    /// investment account under the cursor on the Investments screen
+    pub fn selected_portfolio(&self) -> Option<Portfolio> {
+        let mut portfolios = self.portfolios();
+        if portfolios.is_empty() {
+            return None;
+        }
+        Some(portfolios.swap_remove(self.selected_portfolio_idx.min(portfolios.len() - 1)))
+    }
+
+    /// tax lines of the Taxes screen year
+    pub fn tax_summary(&self) -> TaxSummary {
+        self.ledger.tax_summary(self.tax_year)
+    }
+
+    /// account under the cursor on the Accounts screen
+    pub fn selected_account(&self) -> Option<&Account> {
+        // same rows as the Accounts screen, contacts are on the People screen
+        let accounts: Vec<&Account> = self
+            .ledger
+            .account
+            .iter()
+            .filter(|a| !a.account_type.is_contact())
+            .collect();
+        let len = accounts.len();
+        if len == 0 {
+            return None;
+        }
+        accounts.get(self.selected_account_idx.min(len - 1)).copied()
+    }
+
+    /// attachment under the cursor of the attachments popup
+    pub fn selected_attachment(&self) -> Option<&Attachment> {
+        let attachments = self.ledger.attachments_of(self.attachment_tranid?);
+        let idx = self.selected_attachment_idx.min(attachments.len().checked_sub(1)?);
+        attachments.get(idx).copied()
+    }
+
+    /// workspace under the cursor of the workspaces popup
+    pub fn selected_workspace(&self) -> Option<&Workspace> {
+        let idx = self.selected_workspace_idx.min(self.workspaces.len().checked_sub(1)?);
+        self.workspaces.get(idx)
+    }
+
+    /// token under the cursor of the tokens popup
+    pub fn selected_token(&self) -> Option<&ApiToken> {
+        let idx = self.selected_token_idx.min(self.tokens.len().checked_sub(1)?);
+        self.tokens.get(idx)
+    }
+
+    /// goal under the cursor on the Goals screen
+    pub fn selected_goal(&self) -> Option<&Goal> {
+        let len = self.goals.len();
+        if len == 0 {
+            return None;
+        }
+        self.goals.get(self.selected_goal_idx.min(len - 1))
+    }
+
+    pub fn shift_forecast_months(&mut self, delta: i32) {
+        self.forecast_months = (self.forecast_months as i32 + delta).clamp(1, 24) as u32;
+        self.forecast = None;
+    }
+
+    /// drop everything computed from the old ledger
+    pub fn ledger_changed(&mut self) {
+        self.server_report = None;
+        self.forecast = None;
+        self.subscriptions = None;
+        self.anomalies = None;
+    }
+
+    /// move the Net Worth date by one day or one month, kept inside the global range
+    pub fn shift_networth_date(&mut self, delta: i32) {
+        let d = self.networth_date;
+        let moved = match (self.networth_granularity, delta >= 0) {
+            (Granularity::Daily, true) => d.succ_opt(),
+            (Granularity::Daily, false) => d.pred_opt(),
+            (Granularity::Monthly, true) => d.checked_add_months(Months::new(1)),
+            (Granularity::Monthly, false) => d.checked_sub_months(Months::new(1)),
+        };
+        let DateRange { from, to } = self.date_range();
+        self.networth_date = moved.unwrap_or(d).clamp(from, to);
+    }
+
+    pub fn toggle_networth_granularity(&mut self) {
+        self.networth_granularity = match self.networth_granularity {
+            Granularity::Daily => Granularity::Monthly,
+            Granularity::Monthly => Granularity::Daily,
+        };
+    }
+
+    /// fetched server report, if enabled and still matching the range, month and date on screen
+    pub fn server_report(&self) -> Option<&ServerReport> {
+        let report = self.server_report.as_ref().filter(|_| self.server_reports)?;
+        let fresh = report.range == self.date_range()
+            && report.period == self.trend_period
+            && report.month == self.selected_month
+            && report.networth_date == self.networth_date;
+        fresh.then_some(report)
+    }
+
+    /// (income, outcome, net) of the focused month
+    pub fn report_month_totals(&self) -> (f64, f64, f64) {
+        if let Some(report) = self.server_report() {
+            return report.month_totals;
+        }
+        let (y, m) = self.selected_month;
+        let month = DateRange::month(y, m);
+        let value = |onlyspend| {
+            self.ledger
+                .month_summary(self.user_id, month, None, None, onlyspend)
+        };
+        (value(Some(false)), value(Some(true)), value(None))
+    }
+
+    /// top 10 categories by outcome over the global range
+    pub fn report_top_categories(&self) -> Trend<(String, CategoryId)> {
+        match self.server_report() {
+            Some(report) => report.top_categories.clone(),
+            None => self
+                .ledger
+                .top_category(self.user_id, self.date_range(), None, 10, Some(true)),
+        }
+    }
+
+    /// top 10 accounts by outcome over the global range
+    pub fn report_top_accounts(&self) -> Trend<(String, AccountId)> {
+        match self.server_report() {
+            Some(report) => report.top_accounts.clone(),
+            None => self
+                .ledger
+                .top_account(self.user_id, self.date_range(), None, 10, Some(true)),
+        }
+    }
+
+    /// Trends buckets over the global range
+    pub fn report_trends(&self) -> Trend<DateRange> {
+        match self.server_report().and_then(|r| r.trends.clone()) {
+            Some(trend) => trend,
+            None => self.ledger.data_periodtrend(
+                self.user_id,
+                self.date_range(),
+                self.trend_period,
+                None,
+                None,
+            ),
+        }
+    }
+
+    /// account balances at the Net Worth date
+    pub fn report_balances(&self) -> Vec<AccountSummary> {
+        match self.server_report() {
+            Some(report) => report.balances.clone(),
+            None => self.ledger.all_account_summary_on(self.networth_date),
+        }
+    }
+
+    /// transaction under the cursor on the Transactions screen
+    pub fn selected_transaction(&self) -> Option<TransactionId> {
+        let len = self.ledger.transaction.len();
+        if len == 0 {
+            return None;
+        }
+        let idx = self.selected_transaction_idx.min(len - 1);
+        Some(self.ledger.transaction[idx].id)
+    }
+
+    /// category under the cursor on the CategoryStats screen (same ranking as the table)
+    pub fn selected_stats_category(&self) -> Option<CategoryId> {
+        let trend = self.report_top_categories();
+        if trend.axis.is_empty() {
+            return None;
+        }
+        let idx = self.selected_category_stats_idx.min(trend.axis.len() - 1);
+        Some(trend.axis[idx].1)
+    }
+
+    /// account under the cursor on the AccountStats screen (same ranking as the table)
+    pub fn selected_stats_account(&self) -> Option<AccountId> {
+        let trend = self.report_top_accounts();
+        if trend.axis.is_empty() {
+            return None;
+        }
+        let idx = self.selected_account_stats_idx.min(trend.axis.len() - 1);
+        Some(trend.axis[idx].1)
+    }
+
+    /// entries (splits) of a transaction, in server order
+    pub fn transaction_entries(&self, tx_id: TransactionId) -> Vec<&Entry> {
+        self.ledger
+            .entry
+            .iter()
+            .filter(|e| e.tranid == tx_id)
+            .collect()
+    }
+
+    /// account being reconciled on the Reconcile screen
+    pub fn recon_account(&self) -> Option<&Account> {
+        let len = self.ledger.account.len();
+        if len == 0 {
+            return None;
+        }
+        self.ledger.account.get(self.recon_account_idx.min(len - 1))
+    }
+
+    /// open reconciliation session of the selected account, if any
+    pub fn recon_open_session(&self) -> Option<&Cloudreconciliation> {
+        self.recon_sessions.iter().find(|r| r.is_open())
+    }
+
+    /// live state of the open session, computed from the ledger
+    pub fn recon_statement(&self) -> Option<StatementReconcile> {
+        let session = self.recon_open_session()?;
+        let balance = session.statement_balance.to_f64().unwrap_or(0.0);
+        Some(
+            self.ledger
+                .statement_reconcile(session.account_id, session.statement_date, balance),
+        )
+    }
+
+    /// imported statement lines matched against the selected account
+    pub fn recon_statement_match(&self) -> Option<StatementMatch> {
+        if self.recon_statement_lines.is_empty() {
+            return None;
+        }
+        let account = self.recon_account()?;
+        Some(self.ledger.match_statement(
+            account.id,
+            &self.recon_statement_lines,
+            MatchConfig::default(),
+        ))
+    }
+
     pub fn perform_reconcile(&mut self) {
         let trimmed = self.reconcile_external_balance.trim();
         if trimmed.is_empty() {
@@ -259,10 +907,11 @@ impl App {
             return;
         };
 
-        let timephase = (self.start_month, self.end_month);
+        let timephase = self.date_range();
+        let accountid = self.recon_account().map(|a| a.id);
         let res = self
             .ledger
-            .reconcile(self.user_id, None, external, timephase, 10);
+            .reconcile(self.user_id, accountid, external, timephase, 10);
 
         let mut entries_view = Vec::new();
         for e in &res.suspicous_entry {
@@ -275,6 +924,7 @@ impl App {
             external_balance: res.external_balance,
             difference: res.difference,
             entries: entries_view,
+            candidates: res.candidates,
         });
     }
 
@@ -1,11 +1,12 @@
 use rust_decimal::prelude::FromPrimitive;
 
+use std::collections::HashMap;
 use std::error::Error;
 use std::io;
 use std::time::Duration;
 
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
     event::{EnableMouseCapture, DisableMouseCapture},
@@ -14,18 +15,31 @@ use ratatui::{
     Frame, Terminal,
     backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Modifier, Style},
-    text::Span,
-    widgets::{Block, Borders, Paragraph, Row, Table},
+    style::{Color, Modifier, Style},
+    symbols,
+    text::{Line, Span},
+    widgets::{
+        Axis, Bar, BarChart, BarGroup, Block, Borders, Chart, Clear, Dataset, GraphType,
+        Cell, Paragraph, Row, Sparkline, Table, Wrap,
+    },
 };
 
-use super::app::{App, InputMode, Screen, LoginApp, LoginMode, LoginStep};
+use super::app::{App, CompareMode, DeleteTarget, InputMode, Screen, LoginApp, LoginMode, LoginStep, ServerReport, LOAN_EXTRA_STEP, SUBSCRIPTION_MONTHS, TOKEN_SCOPES};
 use crate::advisor::{Generationcfg, Model, Modeltype};
-use crate::stat::Ledger;
+use crate::stat::datatype::{
+    Account, AccountType, CostBasis, Entry, LoanFrequency, TaxLine, TradeKind, TransactionId,
+    WorkspaceRole,
+};
+use crate::stat::ledger::StatementReconcile;
+use crate::stat::sync::{Cloudreconciliation, Session};
+use crate::stat::{
+    DateRange, DiscrepancyCandidate, DiscrepancyKind, DiscrepancySearch, Forecast, Ledger,
+    CompareRow, ContactBalance, CreditStatus, Delta, GoalState, LoanStatus, StatementMatch, Subscription, Trend,
+};
 use anyhow;
 
-pub fn run_tui(ledger: Ledger, base_url: String, token: String) -> anyhow::Result<()> {
-    let mut app = App::new(ledger, base_url, token);
+pub fn run_tui(ledger: Ledger, base_url: String, session: Session) -> anyhow::Result<()> {
+    let mut app = App::new(ledger, base_url, session);
     let rt = tokio::runtime::Runtime::new()?;
 
     enable_raw_mode()?;
@@ -35,6 +49,18 @@ pub fn run_tui(ledger: Ledger, base_url: String, token: String) -> anyhow::Resul
     let mut terminal = Terminal::new(backend)?;
 
     loop {
+        // the forecast scans every entry, so it is kept until the ledger changes
+        if app.forecast.is_none() && matches!(app.current_screen, Screen::Dashboard | Screen::Forecast)
+        {
+            app.forecast = Some(app.compute_forecast());
+        }
+        if app.anomalies.is_none() && matches!(app.current_screen, Screen::Dashboard) {
+            app.anomalies = Some(app.compute_anomalies());
+        }
+        if app.subscriptions.is_none() && matches!(app.current_screen, Screen::Subscriptions) {
+            app.subscriptions = Some(app.compute_subscriptions());
+        }
+
         terminal.draw(|f| ui(f, &app))?;
 
         if app.should_quit {
@@ -45,7 +71,7 @@ pub fn run_tui(ledger: Ledger, base_url: String, token: String) -> anyhow::Resul
             app.needs_refresh = false;
             match rt.block_on(crate::stat::sync::download_ledger_from_server(
                 &app.base_url,
-                &app.token,
+                &app.session,
             )) {
                 Ok(new_ledger) => {
                     if app.is_creating_new_category && !app.new_category_name.trim().is_empty() {
@@ -64,6 +90,9 @@ pub fn run_tui(ledger: Ledger, base_url: String, token: String) -> anyhow::Resul
                         app.new_category_name = String::new();
                     }
                     app.ledger = new_ledger;
+                    app.recon_needs_reload = true;
+                    app.goals_needs_reload = true;
+                    app.ledger_changed();
                 }
                 Err(e) => {
                     app.error_message = Some(format!("Refresh failed: {}", e));
@@ -71,6 +100,22 @@ pub fn run_tui(ledger: Ledger, base_url: String, token: String) -> anyhow::Resul
             }
         }
 
+        // reconciliation sessions are only fetched while the Reconcile screen is shown
+        if app.recon_needs_reload && matches!(app.current_screen, Screen::Reconcile) {
+            app.recon_needs_reload = false;
+            load_reconciliations(&mut app, &rt);
+        }
+        // goals also feed the advisor prompt
+        if app.goals_needs_reload && matches!(app.current_screen, Screen::Goals | Screen::Advisor) {
+            app.goals_needs_reload = false;
+            load_goals(&mut app, &rt);
+        }
+
+        // server reports are fetched again whenever range, month or date moved away from them
+        if app.server_reports && app.server_report().is_none() {
+            load_server_report(&mut app, &rt);
+        }
+
         if event::poll(Duration::from_millis(200))? {
             if let Event::Key(key) = event::read()? {
                 handle_key_event(&mut app, key, &rt);
@@ -97,12 +142,33 @@ fn handle_key_event(app: &mut App, key: KeyEvent, rt: &tokio::runtime::Runtime)
         InputMode::CreatingCategory => handle_key_create_category(app, key, rt),
         InputMode::CreatingAccount => handle_key_create_account(app, key, rt),
         InputMode::AdvisorChat => handle_key_advisor_chat(app, key, rt),
+        InputMode::ConfirmDelete => handle_key_confirm_delete(app, key, rt),
+        InputMode::ViewingHistory => handle_key_view_history(app, key),
+        InputMode::StartingReconcile => handle_key_start_reconcile(app, key, rt),
+        InputMode::ImportingStatement => handle_key_import_statement(app, key),
+        InputMode::CreatingGoal => handle_key_create_goal(app, key, rt),
+        InputMode::EditingCreditTerms => handle_key_credit_terms(app, key, rt),
+        InputMode::EditingLoanTerms => handle_key_loan_terms(app, key, rt),
+        InputMode::RecordingLoanPayment => handle_key_loan_payment(app, key, rt),
+        InputMode::CreatingContact => handle_key_create_contact(app, key, rt),
+        InputMode::SettlingContact => handle_key_settle_contact(app, key, rt),
+        InputMode::RecordingTrade => handle_key_trade(app, key, rt),
+        InputMode::ImportingPrices => handle_key_import_prices(app, key, rt),
+        InputMode::EditingTaxLines => handle_key_tax_lines(app, key, rt),
+        InputMode::ViewingAttachments => handle_key_attachments(app, key, rt),
+        InputMode::AddingAttachment => handle_key_add_attachment(app, key, rt),
+        InputMode::SwitchingWorkspace => handle_key_workspaces(app, key, rt),
+        InputMode::CreatingWorkspace => handle_key_create_workspace(app, key, rt),
+        InputMode::InvitingMember => handle_key_invite_member(app, key, rt),
+        InputMode::ViewingTokens => handle_key_tokens(app, key),
+        InputMode::CreatingToken => handle_key_create_token(app, key, rt),
     }
 }
 
 fn handle_key_normal(app: &mut App, key: KeyEvent, rt: &tokio::runtime::Runtime) {
     use KeyCode::*;
 
+    app.status_message = None;
     match key.code {
         // Quit
         Char('q') => app.should_quit = true,
@@ -111,9 +177,17 @@ fn handle_key_normal(app: &mut App, key: KeyEvent, rt: &tokio::runtime::Runtime)
         Tab => app.next_screen(),
         BackTab => app.prev_screen(),
 
-        // Change month on Dashboard
-        Left => app.prev_month(),
-        Right => app.next_month(),
+        // Change month on Dashboard, date on Net Worth
+        Left => match app.current_screen {
+            Screen::NetWorth => app.shift_networth_date(-1),
+            Screen::Taxes => app.tax_year -= 1,
+            _ => app.prev_month(),
+        },
+        Right => match app.current_screen {
+            Screen::NetWorth => app.shift_networth_date(1),
+            Screen::Taxes => app.tax_year += 1,
+            _ => app.next_month(),
+        },
 
         // Shift global time range
         Char('[') => app.startmonth_range(-1),
@@ -142,6 +216,27 @@ fn handle_key_normal(app: &mut App, key: KeyEvent, rt: &tokio::runtime::Runtime)
                     app.selected_account_stats_idx -= 1;
                 }
             }
+            Screen::Goals => {
+                app.selected_goal_idx = app.selected_goal_idx.saturating_sub(1);
+            }
+            Screen::Loans => {
+                app.selected_loan_idx = app.selected_loan_idx.saturating_sub(1);
+            }
+            Screen::People => {
+                app.selected_contact_idx = app.selected_contact_idx.saturating_sub(1);
+            }
+            Screen::Investments => {
+                app.selected_portfolio_idx = app.selected_portfolio_idx.saturating_sub(1);
+            }
+            Screen::Taxes => {
+                app.selected_tax_line_idx = app.selected_tax_line_idx.saturating_sub(1);
+            }
+            Screen::Reconcile if !app.recon_statement_lines.is_empty() => {
+                app.recon_line_idx = app.recon_line_idx.saturating_sub(1);
+            }
+            Screen::Reconcile => {
+                app.recon_entry_idx = app.recon_entry_idx.saturating_sub(1);
+            }
             Screen::Advisor => {
                 if app.advisor_selecting_model {
                     if app.advisor_model_choice_idx > 0 {
@@ -167,6 +262,36 @@ fn handle_key_normal(app: &mut App, key: KeyEvent, rt: &tokio::runtime::Runtime)
             Screen::AccountStats => {
                 app.selected_account_stats_idx += 1;
             }
+            Screen::Goals if app.selected_goal_idx + 1 < app.goals.len() => {
+                app.selected_goal_idx += 1;
+            }
+            Screen::Loans if app.selected_loan_idx + 1 < app.loan_status().len() => {
+                app.selected_loan_idx += 1;
+            }
+            Screen::People if app.selected_contact_idx + 1 < app.contact_balances().len() => {
+                app.selected_contact_idx += 1;
+            }
+            Screen::Investments if app.selected_portfolio_idx + 1 < app.portfolios().len() => {
+                app.selected_portfolio_idx += 1;
+            }
+            Screen::Taxes if app.selected_tax_line_idx + 1 < app.tax_summary().lines.len() => {
+                app.selected_tax_line_idx += 1;
+            }
+            Screen::Reconcile if !app.recon_statement_lines.is_empty() => {
+                let count = app
+                    .recon_statement_match()
+                    .map(|m| m.statement_only.len())
+                    .unwrap_or(0);
+                if app.recon_line_idx + 1 < count {
+                    app.recon_line_idx += 1;
+                }
+            }
+            Screen::Reconcile => {
+                let count = app.recon_statement().map(|v| v.entries.len()).unwrap_or(0);
+                if app.recon_entry_idx + 1 < count {
+                    app.recon_entry_idx += 1;
+                }
+            }
             Screen::Advisor => {
                 if app.advisor_selecting_model {
                     let max_idx = 3; // 0.5B, 1.5B, 3B, 7B
@@ -180,6 +305,14 @@ fn handle_key_normal(app: &mut App, key: KeyEvent, rt: &tokio::runtime::Runtime)
             _ => {}
         },
 
+        // taxes: tax lines of the categories, export of the year
+        Char('l') if matches!(app.current_screen, Screen::Taxes) => {
+            app.tax_category_idx = 0;
+            app.error_message = None;
+            app.input_mode = InputMode::EditingTaxLines;
+        }
+        Char('e') if matches!(app.current_screen, Screen::Taxes) => export_tax_summary(app),
+
         Char('e') => {
             if let Screen::Reconcile = app.current_screen {
                 app.input_mode = InputMode::EditingReconcile;
@@ -189,11 +322,198 @@ fn handle_key_normal(app: &mut App, key: KeyEvent, rt: &tokio::runtime::Runtime)
         Char('?') => {
             app.current_screen = Screen::Help;
         }
+        Char('w') => open_workspaces(app, rt),
+        Char('k') => open_tokens(app, rt),
+
+        // reconcile: next account / start / tick / finish / cancel
+        Char('a')
+            if matches!(app.current_screen, Screen::Reconcile) && !app.ledger.account.is_empty() =>
+        {
+            app.recon_account_idx = (app.recon_account_idx + 1) % app.ledger.account.len();
+            app.recon_entry_idx = 0;
+            app.recon_sessions.clear();
+            app.recon_needs_reload = true;
+        }
+        // reconcile: imported statement lines
+        Char('i') if matches!(app.current_screen, Screen::Reconcile) => {
+            app.input_mode = InputMode::ImportingStatement;
+            app.error_message = None;
+        }
+        Char('n')
+            if matches!(app.current_screen, Screen::Reconcile)
+                && !app.recon_statement_lines.is_empty() =>
+        {
+            create_from_statement_line(app, rt);
+        }
+        Char('m')
+            if matches!(app.current_screen, Screen::Reconcile)
+                && !app.recon_statement_lines.is_empty() =>
+        {
+            tick_matched_entries(app, rt);
+        }
+        Char('c')
+            if matches!(app.current_screen, Screen::Reconcile)
+                && !app.recon_statement_lines.is_empty()
+                && app.error_message.is_none() =>
+        {
+            app.recon_statement_lines.clear();
+            app.recon_line_idx = 0;
+        }
+        Char('s') if matches!(app.current_screen, Screen::Reconcile) => {
+            if app.recon_account().is_none() {
+                app.error_message = Some("No account to reconcile".to_string());
+            } else if app.recon_open_session().is_some() {
+                app.status_message =
+                    Some("A reconciliation is already open, f finish or x cancel".to_string());
+            } else {
+                app.recon_statement_date = chrono::Local::now().format("%Y-%m-%d").to_string();
+                app.recon_statement_balance = String::new();
+                app.recon_field_idx = 0;
+                app.input_mode = InputMode::StartingReconcile;
+            }
+        }
+        Char(' ') if matches!(app.current_screen, Screen::Reconcile) => {
+            toggle_recon_entry(app, rt);
+        }
+        Char('f') if matches!(app.current_screen, Screen::Reconcile) => {
+            finish_reconciliation(app, rt);
+        }
+        Char('x') if matches!(app.current_screen, Screen::Reconcile) => {
+            if let Some(id) = app.recon_open_session().map(|r| r.id) {
+                match rt.block_on(crate::stat::sync::cancel_reconciliation_on_server(
+                    &app.base_url,
+                    &app.session,
+                    id,
+                )) {
+                    Ok(_) => {
+                        app.status_message = Some("Reconciliation cancelled".to_string());
+                        app.recon_needs_reload = true;
+                    }
+                    Err(e) => app.error_message = Some(format!("Cancel failed: {}", e)),
+                }
+            }
+        }
+
+        // redo (Ctrl+R), must come before plain refresh
+        Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            let base_url = app.base_url.clone();
+            let session = app.session.clone();
+            match rt.block_on(app.history.redo(&base_url, &session)) {
+                Ok(Some(label)) => {
+                    app.status_message = Some(format!("Redone: {}", label));
+                    app.needs_refresh = true;
+                }
+                Ok(None) => app.status_message = Some("Nothing to redo".to_string()),
+                Err(e) => app.error_message = Some(format!("Redo failed: {}", e)),
+            }
+        }
 
         Char('r') => {
             app.needs_refresh = true;
         }
 
+        Char('u') => {
+            let base_url = app.base_url.clone();
+            let session = app.session.clone();
+            match rt.block_on(app.history.undo(&base_url, &session)) {
+                Ok(Some(label)) => {
+                    app.status_message = Some(format!("Undone: {}", label));
+                    app.needs_refresh = true;
+                }
+                Ok(None) => app.status_message = Some("Nothing to undo".to_string()),
+                Err(e) => app.error_message = Some(format!("Undo failed: {}", e)),
+            }
+        }
+
+        // loans: terms, payment and extra payment of the selected loan
+        Char('t') if matches!(app.current_screen, Screen::Loans) => {
+            if let Some(loan) = app.selected_loan() {
+                open_loan_terms(app, loan.accountid);
+            }
+        }
+        Char('p') if matches!(app.current_screen, Screen::Loans) => {
+            if let Some(loan) = app.selected_loan() {
+                open_loan_payment(app, &loan);
+            }
+        }
+        Char('+') | Char('=') if matches!(app.current_screen, Screen::Loans) => {
+            app.shift_loan_extra(LOAN_EXTRA_STEP);
+        }
+        Char('-') if matches!(app.current_screen, Screen::Loans) => {
+            app.shift_loan_extra(-LOAN_EXTRA_STEP);
+        }
+
+        // people: new contact and settling up with the selected one
+        Char('n') if matches!(app.current_screen, Screen::People) => {
+            app.contact_name = String::new();
+            app.contact_email = String::new();
+            app.contact_field_idx = 0;
+            app.error_message = None;
+            app.input_mode = InputMode::CreatingContact;
+        }
+        Char('s') if matches!(app.current_screen, Screen::People) => {
+            if let Some(contact) = app.selected_contact() {
+                open_settlement(app, &contact);
+            }
+        }
+
+        // investments: trades, prices and cost basis of the selected portfolio
+        Char('t') if matches!(app.current_screen, Screen::Investments) => {
+            if let Some(p) = app.selected_portfolio() {
+                open_trade(app, p.accountid);
+            }
+        }
+        Char('i') if matches!(app.current_screen, Screen::Investments) => {
+            app.error_message = None;
+            app.input_mode = InputMode::ImportingPrices;
+        }
+        Char('m') if matches!(app.current_screen, Screen::Investments) => {
+            if let Some(p) = app.selected_portfolio() {
+                toggle_cost_basis(app, &p, rt);
+            }
+        }
+
+        // accounts: card or loan terms of the selected account
+        Char('t') if matches!(app.current_screen, Screen::Accounts) => {
+            match app.selected_account() {
+                Some(acc) if matches!(acc.account_type, AccountType::Loan) => {
+                    let id = acc.id;
+                    open_loan_terms(app, id);
+                }
+                Some(acc) if matches!(acc.account_type, AccountType::Credit) => {
+                    let terms = acc.credit;
+                    let text = |v: Option<f64>| v.map(|v| format!("{:.2}", v)).unwrap_or_default();
+                    let day = |v: Option<u32>| v.map(|v| v.to_string()).unwrap_or_default();
+                    app.credit_accountid = Some(acc.id);
+                    app.credit_limit = text(terms.limit);
+                    app.credit_statement_day = day(terms.statement_day);
+                    app.credit_due_day = day(terms.due_day);
+                    app.credit_apr = text(terms.apr);
+                    app.credit_field_idx = 0;
+                    app.error_message = None;
+                    app.input_mode = InputMode::EditingCreditTerms;
+                }
+                Some(_) => {
+                    app.error_message =
+                        Some("Only credit and loan accounts have terms".to_string());
+                }
+                None => {}
+            }
+        }
+
+        // goals: new goal form
+        Char('n') if matches!(app.current_screen, Screen::Goals) => {
+            app.new_goal_name = String::new();
+            app.new_goal_target = String::new();
+            app.new_goal_deadline = String::new();
+            app.new_goal_tag = String::new();
+            app.new_goal_accounts = Vec::new();
+            app.new_goal_account_idx = 0;
+            app.new_goal_field_idx = 0;
+            app.error_message = None;
+            app.input_mode = InputMode::CreatingGoal;
+        }
+
         Char('n') => {
             if matches!(
                 app.current_screen,
@@ -211,27 +531,43 @@ fn handle_key_normal(app: &mut App, key: KeyEvent, rt: &tokio::runtime::Runtime)
         }
 
         Char('d') => {
-            if let Screen::Accounts = app.current_screen {
-                if let Some(account) = app.ledger.account.get(app.selected_account_idx) {
-                    if let Some(entry) = app.ledger.entry.iter().find(|e| e.accountid == account.id)
-                    {
-                        if let Some(tx) =
-                            app.ledger.transaction.iter().find(|t| t.id == entry.tranid)
-                        {
-                            match rt.block_on(crate::stat::sync::delete_transaction_on_server(
-                                &app.base_url,
-                                &app.token,
-                                tx.id,
-                            )) {
-                                Ok(_) => {
-                                    app.needs_refresh = true;
-                                }
-                                Err(e) => {
-                                    app.error_message = Some(format!("Delete failed: {}", e));
-                                }
-                            }
-                        }
+            let target = match app.current_screen {
+                Screen::Transactions => app.selected_transaction().map(DeleteTarget::Transaction),
+                Screen::Accounts => app.selected_account().map(|a| DeleteTarget::Account(a.id)),
+                Screen::CategoryStats => app.selected_stats_category().map(DeleteTarget::Category),
+                Screen::Goals => app.selected_goal().map(|g| DeleteTarget::Goal(g.id)),
+                _ => None,
+            };
+            if let Some(target) = target {
+                app.pending_delete = Some(target);
+                app.delete_entry_idx = 0;
+                app.input_mode = InputMode::ConfirmDelete;
+                app.error_message = None;
+            }
+        }
+
+        // receipts and documents of the selected transaction
+        Char('f') if matches!(app.current_screen, Screen::Transactions) => {
+            if let Some(tx_id) = app.selected_transaction() {
+                open_attachments(app, tx_id);
+            }
+        }
+
+        // change history of the selected transaction
+        Char('h') if matches!(app.current_screen, Screen::Transactions) => {
+            if let Some(tx_id) = app.selected_transaction() {
+                let fetched = rt.block_on(crate::stat::sync::fetch_transaction_audit(
+                    &app.base_url,
+                    &app.session,
+                    tx_id,
+                ));
+                match fetched {
+                    Ok(rows) => {
+                        app.tx_history = rows;
+                        app.tx_history_scroll = 0;
+                        app.input_mode = InputMode::ViewingHistory;
                     }
+                    Err(e) => app.error_message = Some(format!("Load history failed: {}", e)),
                 }
             }
         }
@@ -265,6 +601,48 @@ fn handle_key_normal(app: &mut App, key: KeyEvent, rt: &tokio::runtime::Runtime)
             }
         }
 
+        // trends / stats: switch between table and chart
+        Char('v') => {
+            if matches!(
+                app.current_screen,
+                Screen::Trends | Screen::CategoryStats | Screen::AccountStats | Screen::Forecast
+            ) {
+                app.chart_view = !app.chart_view;
+            }
+        }
+
+        // trends: switch week / month / quarter / year / statement cycle buckets
+        Char('p') if matches!(app.current_screen, Screen::Trends) => {
+            app.cycle_trend_period();
+        }
+        Char('p') if matches!(app.current_screen, Screen::Compare) => {
+            app.cycle_compare_mode();
+        }
+
+        // forecast: project more / fewer months
+        Char('+') | Char('=') if matches!(app.current_screen, Screen::Forecast) => {
+            app.shift_forecast_months(1);
+        }
+        Char('-') if matches!(app.current_screen, Screen::Forecast) => {
+            app.shift_forecast_months(-1);
+        }
+
+        // stats screens: server reports / local ledger
+        Char('o') => {
+            app.server_reports = !app.server_reports;
+            app.server_report = None;
+            app.status_message = Some(if app.server_reports {
+                "Stats: server reports".to_string()
+            } else {
+                "Stats: local ledger".to_string()
+            });
+        }
+
+        // net worth: switch daily / monthly points
+        Char('g') if matches!(app.current_screen, Screen::NetWorth) => {
+            app.toggle_networth_granularity();
+        }
+
         Char('g') => {
             if let Screen::Advisor = app.current_screen {
                 if let Err(e) = advisor_generate_report(app) {
@@ -525,9 +903,9 @@ fn submit_new_transaction(app: &mut App, rt: &tokio::runtime::Runtime) -> anyhow
         return Err(anyhow::anyhow!("At least one entry is required").into());
     }
 
-    rt.block_on(create_cloudtransaction(
+    let created = rt.block_on(create_cloudtransaction(
         &app.base_url,
-        &app.token,
+        &app.session,
         date,
         if app.new_tx_payee.is_empty() {
             None
@@ -541,6 +919,10 @@ fn submit_new_transaction(app: &mut App, rt: &tokio::runtime::Runtime) -> anyhow
         },
         entries,
     ))?;
+    app.history
+        .record(crate::stat::history::Command::CreateTransaction { id: created.id });
+    app.ledger.apply_cloudtransaction(created, app.user_id);
+    app.ledger_changed();
     Ok(())
 }
 
@@ -609,505 +991,5113 @@ fn handle_key_advisor_chat(app: &mut App, key: KeyEvent, rt: &tokio::runtime::Ru
     }
 }
 
-fn ui(f: &mut Frame<'_>, app: &App) {
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .margin(1)
-        .constraints([
-            Constraint::Length(3), // header
-            Constraint::Min(0),    // main
-            Constraint::Length(3), // footer (more space for per-screen shortcuts)
-        ])
-        .split(f.area());
+fn handle_key_confirm_delete(app: &mut App, key: KeyEvent, rt: &tokio::runtime::Runtime) {
+    use KeyCode::*;
 
-    // Header
-    let (sy, sm) = app.start_month;
-    let (ey, em) = app.end_month;
-    let screen_name = match app.current_screen {
-        Screen::Dashboard => "Dashboard",
-        Screen::Accounts => "Accounts",
-        Screen::Transactions => "Transactions",
-        Screen::CategoryStats => "Category Stats",
-        Screen::AccountStats => "Account Stats",
-        Screen::Trends => "Trends",
-        Screen::Reconcile => "Reconcile",
-        Screen::Help => "Help",
-        Screen::Advisor => "Advisor",
+    let Some(target) = app.pending_delete else {
+        app.input_mode = InputMode::Normal;
+        return;
     };
-    let header_text = format!(
-        "Rust Finance Tracker - {screen_name}   |   Range: {sy:04}-{sm:02} ~ {ey:04}-{em:02}"
-    );
-    let header = Paragraph::new(header_text).block(Block::default().borders(Borders::ALL));
-    f.render_widget(header, chunks[0]);
 
-    // Main content
-    match app.current_screen {
-        Screen::Dashboard => {
-            if app.input_mode == InputMode::CreatingCategory {
-                draw_create_category(f, chunks[1], app);
-            } else if app.input_mode == InputMode::CreatingTransaction {
-                draw_create_transaction(f, chunks[1], app);
-            } else {
-                draw_dashboard(f, chunks[1], app);
+    match key.code {
+        Esc | Char('n') => {
+            app.pending_delete = None;
+            app.input_mode = confirm_delete_return(target);
+        }
+        Up => {
+            app.delete_entry_idx = app.delete_entry_idx.saturating_sub(1);
+        }
+        Down => {
+            if let DeleteTarget::Transaction(tx_id) = target {
+                let count = app.transaction_entries(tx_id).len();
+                if app.delete_entry_idx + 1 < count {
+                    app.delete_entry_idx += 1;
+                }
             }
         }
-        Screen::Accounts => {
-            if app.input_mode == InputMode::CreatingAccount {
-                draw_create_account(f, chunks[1], app);
-            } else if app.input_mode == InputMode::CreatingCategory {
-                draw_create_category(f, chunks[1], app);
-            } else if app.input_mode == InputMode::CreatingTransaction {
-                draw_create_transaction(f, chunks[1], app);
-            } else {
-                draw_accounts(f, chunks[1], app);
+        // narrow the delete down to the selected split only
+        Char('x') => {
+            if let DeleteTarget::Transaction(tx_id) = target
+                && let Some(entry) = app.transaction_entries(tx_id).get(app.delete_entry_idx)
+            {
+                app.pending_delete = Some(DeleteTarget::Entry(entry.id));
             }
         }
-        Screen::Transactions => draw_transactions(f, chunks[1], app),
-        Screen::CategoryStats => draw_category_stats(f, chunks[1], app),
-        Screen::AccountStats => draw_account_stats(f, chunks[1], app),
-        Screen::Trends => draw_trends(f, chunks[1], app),
-        Screen::Reconcile => draw_reconcile(f, chunks[1], app),
-        Screen::Advisor => draw_advisor(f, chunks[1], app),
-        Screen::Help => draw_help(f, chunks[1], app),
+        Char('y') | Enter => {
+            match perform_delete(app, target, rt) {
+                Ok(_) => {
+                    app.needs_refresh = true;
+                }
+                Err(msg) => {
+                    app.error_message = Some(msg);
+                }
+            }
+            app.pending_delete = None;
+            app.input_mode = confirm_delete_return(target);
+        }
+        _ => {}
     }
+}
 
-    // Footer
-    let footer_text = if let Some(ref msg) = app.error_message {
-        format!("ERROR: {} | Press c to clear", msg)
-    } else {
-        match app.input_mode {
-            InputMode::Normal => match app.current_screen {
-                Screen::Dashboard => "Dashboard: Tab/Shift+Tab switch | ←/→ month | [ ] min month range |shift [] max month range| n new tx | r refresh | ? help | q quit".to_string(),
-                Screen::Accounts => "Accounts: Tab/Shift+Tab switch | ↑/↓ select account | n new tx | c new account | d delete first tx | r refresh | q quit".to_string(),
-                Screen::Transactions => "Transactions: Tab/Shift+Tab switch | ↑/↓ select tx | n new tx | r refresh | q quit".to_string(),
-                Screen::CategoryStats => "Category Stats: Tab/Shift+Tab switch | ↑/↓ move | r refresh | q quit".to_string(),
-                Screen::AccountStats => "Account Stats: Tab/Shift+Tab switch | ↑/↓ move | r refresh | q quit".to_string(),
-                Screen::Trends => "Trends: Tab/Shift+Tab switch | [ ] min month range |shift [] max month range| r refresh | q quit".to_string(),
-                Screen::Reconcile => "Reconcile: Tab/Shift+Tab switch | e edit external balance | r refresh | q quit".to_string(),
-                Screen::Advisor => "Advisor: Tab/Shift+Tab switch | g generate | m select model (↑/↓/Enter/Esc) | i chat | PageUp/PageDown chat scroll | ↑/↓ output scroll | r refresh | q quit".to_string(),
-                Screen::Help => "Help: Tab/Shift+Tab switch | q quit".to_string(),
-            },
-            InputMode::EditingReconcile => {
-                "Reconcile edit: 0-9 . - to type | Enter submit | Esc cancel".to_string()
-            }
-            InputMode::CreatingTransaction => {
-                "Create tx: Tab/Shift+Tab fields | j/k account/category | n new category | a add entry | x del entry | Enter submit | Esc cancel".to_string()
-            }
-            InputMode::CreatingCategory => {
-                "Create category: Type name | Enter submit | Esc cancel".to_string()
+/// an attachment is deleted from its popup, which stays open
+fn confirm_delete_return(target: DeleteTarget) -> InputMode {
+    match target {
+        DeleteTarget::Attachment(_) => InputMode::ViewingAttachments,
+        DeleteTarget::Token(_) => InputMode::ViewingTokens,
+        _ => InputMode::Normal,
+    }
+}
+
+fn handle_key_start_reconcile(app: &mut App, key: KeyEvent, rt: &tokio::runtime::Runtime) {
+    use KeyCode::*;
+
+    match key.code {
+        Esc => {
+            app.input_mode = InputMode::Normal;
+        }
+        Tab | BackTab | Up | Down => {
+            app.recon_field_idx = 1 - app.recon_field_idx.min(1);
+        }
+        Backspace => {
+            if app.recon_field_idx == 0 {
+                app.recon_statement_date.pop();
+            } else {
+                app.recon_statement_balance.pop();
             }
-            InputMode::CreatingAccount => {
-                "Create account: Tab/Shift+Tab fields | j/k account type | Enter submit | Esc cancel".to_string()
+        }
+        Char(c) => {
+            if app.recon_field_idx == 0 {
+                if c.is_ascii_digit() || c == '-' {
+                    app.recon_statement_date.push(c);
+                }
+            } else if c.is_ascii_digit() || c == '.' || c == '-' {
+                app.recon_statement_balance.push(c);
             }
-            InputMode::AdvisorChat => {
-                "Advisor chat: Type message | Enter send | Esc cancel".to_string()
+        }
+        Enter => {
+            let Some(account_id) = app.recon_account().map(|a| a.id) else {
+                app.input_mode = InputMode::Normal;
+                return;
+            };
+            let Ok(date) =
+                chrono::NaiveDate::parse_from_str(app.recon_statement_date.trim(), "%Y-%m-%d")
+            else {
+                app.error_message = Some("Statement date must be YYYY-MM-DD".to_string());
+                return;
+            };
+            let Ok(balance) = app.recon_statement_balance.trim().parse::<f64>() else {
+                app.error_message = Some("Closing balance must be a number".to_string());
+                return;
+            };
+            match rt.block_on(crate::stat::sync::create_reconciliation_on_server(
+                &app.base_url,
+                &app.session,
+                account_id,
+                date,
+                balance,
+            )) {
+                Ok(session) => {
+                    app.recon_sessions.insert(0, session);
+                    app.recon_entry_idx = 0;
+                    app.error_message = None;
+                    app.input_mode = InputMode::Normal;
+                }
+                Err(e) => {
+                    app.error_message = Some(format!("Start reconciliation failed: {}", e));
+                    app.input_mode = InputMode::Normal;
+                }
             }
         }
-    };
-    let footer = Paragraph::new(footer_text).block(Block::default().borders(Borders::ALL));
-    f.render_widget(footer, chunks[2]);
+        _ => {}
+    }
 }
 
-fn draw_dashboard(f: &mut Frame<'_>, area: Rect, app: &App) {
-    let (year, month) = app.selected_month;
-    let user_id = app.user_id;
-
-    let income = app
-        .ledger
-        .month_summary(user_id, year, month, None, None, Some(false), None);
-    let outcome = app
-        .ledger
-        .month_summary(user_id, year, month, None, None, Some(true), None);
-    let net = app
-        .ledger
-        .month_summary(user_id, year, month, None, None, None, None);
-
-    let text = format!(
-        "Focused Month: {year:04}-{month:02}\n\
-         Income:  {income:.2}\n\
-         Outcome: {outcome:.2}\n\
-         Net:     {net:.2}\n"
-    );
-
-    let block = Block::default()
-        .title(Span::raw("Monthly Summary"))
-        .borders(Borders::ALL);
-
-    let p = Paragraph::new(text).block(block);
-    f.render_widget(p, area);
-}
+fn handle_key_create_goal(app: &mut App, key: KeyEvent, rt: &tokio::runtime::Runtime) {
+    use KeyCode::*;
+
+    let accounts = app.ledger.account.len();
+    match key.code {
+        Esc => {
+            app.input_mode = InputMode::Normal;
+            app.error_message = None;
+        }
+        Tab | Down => {
+            app.new_goal_field_idx = (app.new_goal_field_idx + 1) % 5;
+        }
+        BackTab | Up => {
+            app.new_goal_field_idx = (app.new_goal_field_idx + 4) % 5;
+        }
+        Backspace => match app.new_goal_field_idx {
+            0 => {
+                app.new_goal_name.pop();
+            }
+            1 => {
+                app.new_goal_target.pop();
+            }
+            2 => {
+                app.new_goal_deadline.pop();
+            }
+            3 => {
+                app.new_goal_tag.pop();
+            }
+            _ => {}
+        },
+        Char(c) => match app.new_goal_field_idx {
+            0 => app.new_goal_name.push(c),
+            1 => {
+                if c.is_ascii_digit() || c == '.' {
+                    app.new_goal_target.push(c);
+                }
+            }
+            2 => {
+                if c.is_ascii_digit() || c == '-' {
+                    app.new_goal_deadline.push(c);
+                }
+            }
+            3 => {
+                if c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '#' {
+                    app.new_goal_tag.push(c);
+                }
+            }
+            _ if accounts == 0 => {}
+            _ => match c {
+                'j' => app.new_goal_account_idx = (app.new_goal_account_idx + 1) % accounts,
+                'k' => app.new_goal_account_idx = (app.new_goal_account_idx + accounts - 1) % accounts,
+                ' ' => {
+                    let id = app.ledger.account[app.new_goal_account_idx.min(accounts - 1)].id;
+                    match app.new_goal_accounts.iter().position(|a| *a == id) {
+                        Some(pos) => {
+                            app.new_goal_accounts.remove(pos);
+                        }
+                        None => app.new_goal_accounts.push(id),
+                    }
+                }
+                _ => {}
+            },
+        },
+        Enter => match submit_new_goal(app, rt) {
+            Ok(_) => {
+                app.error_message = None;
+                app.input_mode = InputMode::Normal;
+            }
+            Err(e) => app.error_message = Some(format!("Failed: {}", e)),
+        },
+        _ => {}
+    }
+}
+
+fn handle_key_credit_terms(app: &mut App, key: KeyEvent, rt: &tokio::runtime::Runtime) {
+    use KeyCode::*;
+
+    let field = match app.credit_field_idx {
+        0 => &mut app.credit_limit,
+        1 => &mut app.credit_statement_day,
+        2 => &mut app.credit_due_day,
+        _ => &mut app.credit_apr,
+    };
+    match key.code {
+        Esc => {
+            app.input_mode = InputMode::Normal;
+            app.error_message = None;
+        }
+        Tab | Down => app.credit_field_idx = (app.credit_field_idx + 1) % 4,
+        BackTab | Up => app.credit_field_idx = (app.credit_field_idx + 3) % 4,
+        Backspace => {
+            field.pop();
+        }
+        Char(c) if c.is_ascii_digit() || (c == '.' && matches!(app.credit_field_idx, 0 | 3)) => {
+            field.push(c);
+        }
+        Enter => match submit_credit_terms(app, rt) {
+            Ok(_) => {
+                app.error_message = None;
+                app.input_mode = InputMode::Normal;
+                app.needs_refresh = true;
+            }
+            Err(e) => app.error_message = Some(format!("Failed: {}", e)),
+        },
+        _ => {}
+    }
+}
+
+fn submit_credit_terms(app: &mut App, rt: &tokio::runtime::Runtime) -> anyhow::Result<()> {
+    use crate::stat::datatype::CreditTerms;
+
+    let Some(account_id) = app.credit_accountid else {
+        return Ok(());
+    };
+    fn parse<T: std::str::FromStr>(v: &str, what: &str) -> anyhow::Result<Option<T>> {
+        match v.trim() {
+            "" => Ok(None),
+            v => v
+                .parse::<T>()
+                .map(Some)
+                .map_err(|_| anyhow::anyhow!("{} is not a number", what)),
+        }
+    }
+    let terms = CreditTerms {
+        limit: parse(&app.credit_limit, "Credit limit")?,
+        statement_day: parse(&app.credit_statement_day, "Statement day")?,
+        due_day: parse(&app.credit_due_day, "Due day")?,
+        apr: parse(&app.credit_apr, "APR")?,
+    };
+    if terms.limit.is_some_and(|l| l <= 0.0) {
+        anyhow::bail!("Credit limit must be positive");
+    }
+    if [terms.statement_day, terms.due_day]
+        .iter()
+        .flatten()
+        .any(|d| !(1..=31).contains(d))
+    {
+        anyhow::bail!("Statement and due day must be 1-31");
+    }
+    if terms.apr.is_some_and(|a| !(0.0..100.0).contains(&a)) {
+        anyhow::bail!("APR must be 0-100");
+    }
+    rt.block_on(crate::stat::sync::set_credit_terms_on_server(
+        &app.base_url,
+        &app.session,
+        account_id,
+        terms,
+    ))?;
+    // show the new terms right away, the refresh brings the rest
+    if let Some(acc) = app.ledger.account.iter_mut().find(|a| a.id == account_id) {
+        app.history
+            .record(crate::stat::history::Command::SetCreditTerms {
+                id: account_id,
+                before: acc.credit,
+                after: terms,
+            });
+        acc.credit = terms;
+    }
+    Ok(())
+}
+
+/// loan terms form, prefilled with the account's terms
+fn open_loan_terms(app: &mut App, account_id: i64) {
+    let terms = app
+        .ledger
+        .account
+        .iter()
+        .find(|a| a.id == account_id)
+        .and_then(|a| a.loan);
+    app.loan_accountid = Some(account_id);
+    app.loan_principal = terms.map(|t| format!("{:.2}", t.principal)).unwrap_or_default();
+    app.loan_rate = terms.map(|t| format!("{:.2}", t.rate)).unwrap_or_default();
+    app.loan_term_months = terms.map(|t| t.term_months.to_string()).unwrap_or_default();
+    app.loan_frequency = terms.map(|t| t.frequency).unwrap_or(LoanFrequency::Monthly);
+    app.loan_first_payment = terms.map(|t| t.first_payment.to_string()).unwrap_or_default();
+    app.loan_field_idx = 0;
+    app.error_message = None;
+    app.input_mode = InputMode::EditingLoanTerms;
+}
+
+/// payment form for `loan`: the regular payment plus the extra one, from the first asset account,
+/// interest booked under a category named like "interest" when there is one
+fn open_loan_payment(app: &mut App, loan: &LoanStatus) {
+    if loan.owed <= 0.0 {
+        app.error_message = Some(format!("{} is paid off", loan.name));
+        return;
+    }
+    let amount = (loan.payment + loan.extra).min(loan.owed + loan.next_interest);
+    app.loan_accountid = Some(loan.accountid);
+    app.loan_pay_from_idx = app
+        .ledger
+        .account
+        .iter()
+        .position(|a| !a.account_type.is_liability())
+        .unwrap_or(0);
+    app.loan_pay_amount = format!("{:.2}", amount);
+    app.loan_pay_date = chrono::Local::now().date_naive().to_string();
+    app.loan_pay_category_idx = app
+        .ledger
+        .category
+        .iter()
+        .position(|c| c.name.to_lowercase().contains("interest"))
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    app.loan_pay_field_idx = 0;
+    app.error_message = None;
+    app.input_mode = InputMode::RecordingLoanPayment;
+}
+
+fn handle_key_loan_terms(app: &mut App, key: KeyEvent, rt: &tokio::runtime::Runtime) {
+    use KeyCode::*;
+
+    match key.code {
+        Esc => {
+            app.input_mode = InputMode::Normal;
+            app.error_message = None;
+        }
+        Tab | Down => app.loan_field_idx = (app.loan_field_idx + 1) % 5,
+        BackTab | Up => app.loan_field_idx = (app.loan_field_idx + 4) % 5,
+        Char(c) if app.loan_field_idx == 3 && (c == 'j' || c == 'k') => {
+            let all = LoanFrequency::ALL;
+            let idx = all.iter().position(|f| *f == app.loan_frequency).unwrap_or(0);
+            let idx = match c {
+                'k' => (idx + 1) % all.len(),
+                _ => (idx + all.len() - 1) % all.len(),
+            };
+            app.loan_frequency = all[idx];
+        }
+        Backspace => {
+            let field = match app.loan_field_idx {
+                0 => &mut app.loan_principal,
+                1 => &mut app.loan_rate,
+                2 => &mut app.loan_term_months,
+                4 => &mut app.loan_first_payment,
+                _ => return,
+            };
+            field.pop();
+        }
+        Char(c) => {
+            let field = match app.loan_field_idx {
+                0 | 1 if c.is_ascii_digit() || c == '.' => match app.loan_field_idx {
+                    0 => &mut app.loan_principal,
+                    _ => &mut app.loan_rate,
+                },
+                2 if c.is_ascii_digit() => &mut app.loan_term_months,
+                4 if c.is_ascii_digit() || c == '-' => &mut app.loan_first_payment,
+                _ => return,
+            };
+            field.push(c);
+        }
+        Enter => match submit_loan_terms(app, rt) {
+            Ok(_) => {
+                app.error_message = None;
+                app.input_mode = InputMode::Normal;
+                app.needs_refresh = true;
+            }
+            Err(e) => app.error_message = Some(format!("Failed: {}", e)),
+        },
+        _ => {}
+    }
+}
+
+fn submit_loan_terms(app: &mut App, rt: &tokio::runtime::Runtime) -> anyhow::Result<()> {
+    use crate::stat::datatype::LoanTerms;
+
+    let Some(account_id) = app.loan_accountid else {
+        return Ok(());
+    };
+    let texts = [
+        &app.loan_principal,
+        &app.loan_rate,
+        &app.loan_term_months,
+        &app.loan_first_payment,
+    ];
+    // nothing typed clears the terms
+    let terms = if texts.iter().all(|t| t.trim().is_empty()) {
+        None
+    } else {
+        let principal: f64 = app
+            .loan_principal
+            .trim()
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Principal is not a number"))?;
+        let rate: f64 = app
+            .loan_rate
+            .trim()
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Rate is not a number"))?;
+        let term_months: u32 = app
+            .loan_term_months
+            .trim()
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Term is not a whole number of months"))?;
+        let first_payment = chrono::NaiveDate::parse_from_str(app.loan_first_payment.trim(), "%Y-%m-%d")
+            .map_err(|_| anyhow::anyhow!("First payment must be YYYY-MM-DD"))?;
+        if principal <= 0.0 {
+            anyhow::bail!("Principal must be positive");
+        }
+        if !(0.0..100.0).contains(&rate) {
+            anyhow::bail!("Rate must be 0-100");
+        }
+        if !(1..=600).contains(&term_months) {
+            anyhow::bail!("Term must be 1-600 months");
+        }
+        Some(LoanTerms {
+            principal,
+            rate,
+            term_months,
+            frequency: app.loan_frequency,
+            first_payment,
+        })
+    };
+    rt.block_on(crate::stat::sync::set_loan_terms_on_server(
+        &app.base_url,
+        &app.session,
+        account_id,
+        terms,
+    ))?;
+    if let Some(acc) = app.ledger.account.iter_mut().find(|a| a.id == account_id) {
+        app.history
+            .record(crate::stat::history::Command::SetLoanTerms {
+                id: account_id,
+                before: acc.loan,
+                after: terms,
+            });
+        acc.loan = terms;
+    }
+    Ok(())
+}
+
+fn handle_key_loan_payment(app: &mut App, key: KeyEvent, rt: &tokio::runtime::Runtime) {
+    use KeyCode::*;
+
+    let accounts = app.ledger.account.len().max(1);
+    let categories = app.ledger.category.len() + 1;
+    match key.code {
+        Esc => {
+            app.input_mode = InputMode::Normal;
+            app.error_message = None;
+        }
+        Tab | Down => app.loan_pay_field_idx = (app.loan_pay_field_idx + 1) % 4,
+        BackTab | Up => app.loan_pay_field_idx = (app.loan_pay_field_idx + 3) % 4,
+        Char('k') if app.loan_pay_field_idx == 0 => {
+            app.loan_pay_from_idx = (app.loan_pay_from_idx + 1) % accounts;
+        }
+        Char('j') if app.loan_pay_field_idx == 0 => {
+            app.loan_pay_from_idx = (app.loan_pay_from_idx + accounts - 1) % accounts;
+        }
+        Char('k') if app.loan_pay_field_idx == 3 => {
+            app.loan_pay_category_idx = (app.loan_pay_category_idx + 1) % categories;
+        }
+        Char('j') if app.loan_pay_field_idx == 3 => {
+            app.loan_pay_category_idx = (app.loan_pay_category_idx + categories - 1) % categories;
+        }
+        Backspace => match app.loan_pay_field_idx {
+            1 => {
+                app.loan_pay_amount.pop();
+            }
+            2 => {
+                app.loan_pay_date.pop();
+            }
+            _ => {}
+        },
+        Char(c) if app.loan_pay_field_idx == 1 && (c.is_ascii_digit() || c == '.') => {
+            app.loan_pay_amount.push(c);
+        }
+        Char(c) if app.loan_pay_field_idx == 2 && (c.is_ascii_digit() || c == '-') => {
+            app.loan_pay_date.push(c);
+        }
+        Enter => match submit_loan_payment(app, rt) {
+            Ok(_) => {
+                app.error_message = None;
+                app.input_mode = InputMode::Normal;
+            }
+            Err(e) => app.error_message = Some(format!("Failed: {}", e)),
+        },
+        _ => {}
+    }
+}
+
+fn submit_loan_payment(app: &mut App, rt: &tokio::runtime::Runtime) -> anyhow::Result<()> {
+    use crate::stat::sync::{Loanpaymentreq, record_loan_payment_on_server};
+    use rust_decimal::Decimal;
+    use rust_decimal::prelude::ToPrimitive;
+
+    let Some(loan_id) = app.loan_accountid else {
+        return Ok(());
+    };
+    let from = app
+        .ledger
+        .account
+        .get(app.loan_pay_from_idx)
+        .ok_or_else(|| anyhow::anyhow!("No account to pay from"))?;
+    let amount: f64 = app
+        .loan_pay_amount
+        .trim()
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Amount is not a number"))?;
+    if amount <= 0.0 {
+        anyhow::bail!("Amount must be positive");
+    }
+    let date = chrono::NaiveDate::parse_from_str(app.loan_pay_date.trim(), "%Y-%m-%d")
+        .map_err(|_| anyhow::anyhow!("Date must be YYYY-MM-DD"))?;
+    let req = Loanpaymentreq {
+        from_account_id: from.id,
+        amount: Decimal::from_f64(amount)
+            .ok_or_else(|| anyhow::anyhow!("Invalid amount"))?
+            .round_dp(2),
+        occurred_at: date,
+        interest_category_id: app
+            .loan_pay_category_idx
+            .checked_sub(1)
+            .and_then(|i| app.ledger.category.get(i))
+            .map(|c| c.id),
+        payee: None,
+    };
+    let created = rt.block_on(record_loan_payment_on_server(
+        &app.base_url,
+        &app.session,
+        loan_id,
+        &req,
+    ))?;
+    // the loan's own entry is the principal, the rest of the payment was interest
+    let principal: f64 = created
+        .entries
+        .iter()
+        .filter(|e| e.accountid == loan_id)
+        .filter_map(|e| e.amount.to_f64())
+        .sum();
+    app.status_message = Some(format!(
+        "Payment recorded: {:.2} principal, {:.2} interest",
+        principal,
+        amount - principal
+    ));
+    app.history
+        .record(crate::stat::history::Command::CreateTransaction { id: created.id });
+    app.ledger.apply_cloudtransaction(created, app.user_id);
+    app.ledger_changed();
+    Ok(())
+}
+
+/// accounts money can be settled through, every account but the contacts
+fn settle_accounts(app: &App) -> Vec<&Account> {
+    app.ledger
+        .account
+        .iter()
+        .filter(|a| !matches!(a.account_type, AccountType::Contact))
+        .collect()
+}
+
+/// settle-up form for `contact`: everything owed today, through the first asset account
+fn open_settlement(app: &mut App, contact: &ContactBalance) {
+    if contact.balance.abs() < 0.005 {
+        app.error_message = Some(format!("Nothing to settle with {}", contact.name));
+        return;
+    }
+    app.contact_accountid = Some(contact.accountid);
+    app.settle_account_idx = settle_accounts(app)
+        .iter()
+        .position(|a| !a.account_type.is_liability())
+        .unwrap_or(0);
+    app.settle_amount = format!("{:.2}", contact.balance);
+    app.settle_date = chrono::Local::now().date_naive().to_string();
+    app.settle_field_idx = 0;
+    app.error_message = None;
+    app.input_mode = InputMode::SettlingContact;
+}
+
+fn handle_key_create_contact(app: &mut App, key: KeyEvent, rt: &tokio::runtime::Runtime) {
+    use KeyCode::*;
+
+    match key.code {
+        Esc => {
+            app.input_mode = InputMode::Normal;
+            app.error_message = None;
+        }
+        Tab | Down | BackTab | Up => app.contact_field_idx = (app.contact_field_idx + 1) % 2,
+        Backspace => match app.contact_field_idx {
+            0 => {
+                app.contact_name.pop();
+            }
+            _ => {
+                app.contact_email.pop();
+            }
+        },
+        Char(c) if app.contact_field_idx == 0 => app.contact_name.push(c),
+        Char(c) if !c.is_whitespace() => app.contact_email.push(c),
+        Enter => match submit_contact(app, rt) {
+            Ok(_) => {
+                app.error_message = None;
+                app.input_mode = InputMode::Normal;
+                app.needs_refresh = true;
+            }
+            Err(e) => app.error_message = Some(format!("Failed: {}", e)),
+        },
+        _ => {}
+    }
+}
+
+fn submit_contact(app: &mut App, rt: &tokio::runtime::Runtime) -> anyhow::Result<()> {
+    use crate::stat::sync::{ACCreq, create_cloudaccount_from};
+
+    let name = app.contact_name.trim();
+    if name.is_empty() {
+        anyhow::bail!("Name is required");
+    }
+    let email = app.contact_email.trim();
+    if !email.is_empty() && !email.contains('@') {
+        anyhow::bail!("Email must look like name@example.com");
+    }
+    let req = ACCreq {
+        name: name.to_string(),
+        account_type: AccountType::Contact.to_cloud().to_string(),
+        currency: None,
+        opening_balance: None,
+        credit: Default::default(),
+        loan: Default::default(),
+        cost_basis: None,
+        contact_email: (!email.is_empty()).then(|| email.to_string()),
+    };
+    let created = rt.block_on(create_cloudaccount_from(&app.base_url, &app.session, &req))?;
+    app.status_message = Some(format!("Contact {} created", created.name));
+    app.history.record(crate::stat::history::Command::CreateAccount {
+        id: created.id,
+        name: created.name,
+    });
+    Ok(())
+}
+
+fn handle_key_settle_contact(app: &mut App, key: KeyEvent, rt: &tokio::runtime::Runtime) {
+    use KeyCode::*;
+
+    let accounts = settle_accounts(app).len().max(1);
+    match key.code {
+        Esc => {
+            app.input_mode = InputMode::Normal;
+            app.error_message = None;
+        }
+        Tab | Down => app.settle_field_idx = (app.settle_field_idx + 1) % 3,
+        BackTab | Up => app.settle_field_idx = (app.settle_field_idx + 2) % 3,
+        Char('k') if app.settle_field_idx == 0 => {
+            app.settle_account_idx = (app.settle_account_idx + 1) % accounts;
+        }
+        Char('j') if app.settle_field_idx == 0 => {
+            app.settle_account_idx = (app.settle_account_idx + accounts - 1) % accounts;
+        }
+        Backspace => match app.settle_field_idx {
+            1 => {
+                app.settle_amount.pop();
+            }
+            2 => {
+                app.settle_date.pop();
+            }
+            _ => {}
+        },
+        Char(c) if app.settle_field_idx == 1 && (c.is_ascii_digit() || c == '.' || c == '-') => {
+            app.settle_amount.push(c);
+        }
+        Char(c) if app.settle_field_idx == 2 && (c.is_ascii_digit() || c == '-') => {
+            app.settle_date.push(c);
+        }
+        Enter => match submit_settlement(app, rt) {
+            Ok(_) => {
+                app.error_message = None;
+                app.input_mode = InputMode::Normal;
+            }
+            Err(e) => app.error_message = Some(format!("Failed: {}", e)),
+        },
+        _ => {}
+    }
+}
+
+fn submit_settlement(app: &mut App, rt: &tokio::runtime::Runtime) -> anyhow::Result<()> {
+    use crate::stat::sync::{Settlementreq, settle_contact_on_server};
+    use rust_decimal::Decimal;
+
+    let Some(contact_id) = app.contact_accountid else {
+        return Ok(());
+    };
+    let account = *settle_accounts(app)
+        .get(app.settle_account_idx)
+        .ok_or_else(|| anyhow::anyhow!("No account to settle through"))?;
+    let amount: f64 = app
+        .settle_amount
+        .trim()
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Amount is not a number"))?;
+    if amount.abs() < 0.005 {
+        anyhow::bail!("Amount must not be zero");
+    }
+    let date = chrono::NaiveDate::parse_from_str(app.settle_date.trim(), "%Y-%m-%d")
+        .map_err(|_| anyhow::anyhow!("Date must be YYYY-MM-DD"))?;
+    let req = Settlementreq {
+        account_id: account.id,
+        amount: Some(
+            Decimal::from_f64(amount)
+                .ok_or_else(|| anyhow::anyhow!("Invalid amount"))?
+                .round_dp(2),
+        ),
+        occurred_at: date,
+        payee: None,
+    };
+    let created = rt.block_on(settle_contact_on_server(
+        &app.base_url,
+        &app.session,
+        contact_id,
+        &req,
+    ))?;
+    app.status_message = Some(match amount > 0.0 {
+        true => format!("Settled: {:.2} received into {}", amount, account.name),
+        false => format!("Settled: {:.2} paid from {}", -amount, account.name),
+    });
+    app.history
+        .record(crate::stat::history::Command::CreateTransaction { id: created.id });
+    app.ledger.apply_cloudtransaction(created, app.user_id);
+    app.ledger_changed();
+    Ok(())
+}
+
+/// trade form for an investment account, dividends booked under a category named like "dividend"
+/// when there is one
+fn open_trade(app: &mut App, account_id: i64) {
+    app.trade_accountid = Some(account_id);
+    app.trade_kind = TradeKind::Buy;
+    app.trade_symbol = String::new();
+    app.trade_date = chrono::Local::now().date_naive().to_string();
+    app.trade_quantity = String::new();
+    app.trade_price = String::new();
+    app.trade_fees = String::new();
+    app.trade_amount = String::new();
+    app.trade_category_idx = app
+        .ledger
+        .category
+        .iter()
+        .position(|c| c.name.to_lowercase().contains("dividend"))
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    app.trade_field_idx = 0;
+    app.error_message = None;
+    app.input_mode = InputMode::RecordingTrade;
+}
+
+/// fields of the trade form that `kind` uses, see App::trade_field_idx
+fn trade_fields(kind: TradeKind) -> &'static [usize] {
+    match kind {
+        TradeKind::Buy | TradeKind::Sell => &[0, 1, 2, 3, 4, 5],
+        TradeKind::Dividend => &[0, 1, 2, 6, 7],
+        TradeKind::Split => &[0, 1, 2, 3],
+    }
+}
+
+fn handle_key_trade(app: &mut App, key: KeyEvent, rt: &tokio::runtime::Runtime) {
+    use KeyCode::*;
+
+    let fields = trade_fields(app.trade_kind);
+    let pos = fields.iter().position(|f| *f == app.trade_field_idx).unwrap_or(0);
+    let kinds = TradeKind::ALL.len();
+    let kind = TradeKind::ALL.iter().position(|k| *k == app.trade_kind).unwrap_or(0);
+    let categories = app.ledger.category.len() + 1;
+    match key.code {
+        Esc => {
+            app.input_mode = InputMode::Normal;
+            app.error_message = None;
+        }
+        Tab | Down => app.trade_field_idx = fields[(pos + 1) % fields.len()],
+        BackTab | Up => app.trade_field_idx = fields[(pos + fields.len() - 1) % fields.len()],
+        Char('k') if app.trade_field_idx == 0 => {
+            app.trade_kind = TradeKind::ALL[(kind + 1) % kinds];
+        }
+        Char('j') if app.trade_field_idx == 0 => {
+            app.trade_kind = TradeKind::ALL[(kind + kinds - 1) % kinds];
+        }
+        Char('k') if app.trade_field_idx == 7 => {
+            app.trade_category_idx = (app.trade_category_idx + 1) % categories;
+        }
+        Char('j') if app.trade_field_idx == 7 => {
+            app.trade_category_idx = (app.trade_category_idx + categories - 1) % categories;
+        }
+        Backspace => {
+            let field = match app.trade_field_idx {
+                1 => &mut app.trade_symbol,
+                2 => &mut app.trade_date,
+                3 => &mut app.trade_quantity,
+                4 => &mut app.trade_price,
+                5 => &mut app.trade_fees,
+                6 => &mut app.trade_amount,
+                _ => return,
+            };
+            field.pop();
+        }
+        Char(c) if app.trade_field_idx == 1 && (c.is_ascii_alphanumeric() || ".:-".contains(c)) => {
+            app.trade_symbol.push(c.to_ascii_uppercase());
+        }
+        Char(c) if app.trade_field_idx == 2 && (c.is_ascii_digit() || c == '-') => {
+            app.trade_date.push(c);
+        }
+        Char(c) if (3..=6).contains(&app.trade_field_idx) && (c.is_ascii_digit() || c == '.') => {
+            match app.trade_field_idx {
+                3 => app.trade_quantity.push(c),
+                4 => app.trade_price.push(c),
+                5 => app.trade_fees.push(c),
+                _ => app.trade_amount.push(c),
+            }
+        }
+        Enter => match submit_trade(app, rt) {
+            Ok(_) => {
+                app.error_message = None;
+                app.input_mode = InputMode::Normal;
+            }
+            Err(e) => app.error_message = Some(format!("Failed: {}", e)),
+        },
+        _ => {}
+    }
+}
+
+fn submit_trade(app: &mut App, rt: &tokio::runtime::Runtime) -> anyhow::Result<()> {
+    use crate::stat::sync::{Tradereq, create_trade_on_server};
+    use rust_decimal::Decimal;
+
+    let Some(account_id) = app.trade_accountid else {
+        return Ok(());
+    };
+    fn parse(v: &str, what: &str) -> anyhow::Result<Option<Decimal>> {
+        match v.trim() {
+            "" => Ok(None),
+            v => v
+                .parse::<Decimal>()
+                .map(Some)
+                .map_err(|_| anyhow::anyhow!("{} is not a number", what)),
+        }
+    }
+    let symbol = app.trade_symbol.trim().to_string();
+    if symbol.is_empty() {
+        anyhow::bail!("Symbol is required");
+    }
+    let date = chrono::NaiveDate::parse_from_str(app.trade_date.trim(), "%Y-%m-%d")
+        .map_err(|_| anyhow::anyhow!("Date must be YYYY-MM-DD"))?;
+    let kind = app.trade_kind;
+    let uses = |field: usize| trade_fields(kind).contains(&field);
+    let req = Tradereq {
+        symbol: symbol.clone(),
+        kind: kind.to_cloud().to_string(),
+        trade_date: date,
+        quantity: if uses(3) { parse(&app.trade_quantity, "Quantity")? } else { None },
+        price: if uses(4) { parse(&app.trade_price, "Price")? } else { None },
+        fees: if uses(5) { parse(&app.trade_fees, "Fees")? } else { None },
+        amount: if uses(6) { parse(&app.trade_amount, "Amount")? } else { None },
+        category_id: app
+            .trade_category_idx
+            .checked_sub(1)
+            .filter(|_| uses(7))
+            .and_then(|i| app.ledger.category.get(i))
+            .map(|c| c.id),
+        payee: None,
+    };
+    let created = rt.block_on(create_trade_on_server(
+        &app.base_url,
+        &app.session,
+        account_id,
+        &req,
+    ))?;
+    app.status_message = Some(match kind {
+        TradeKind::Buy => format!(
+            "Bought {} {} for {:.2}",
+            created.quantity.normalize(),
+            symbol,
+            -created.amount
+        ),
+        TradeKind::Sell => format!(
+            "Sold {} {} for {:.2}",
+            created.quantity.normalize(),
+            symbol,
+            created.amount
+        ),
+        TradeKind::Dividend => format!("Dividend of {:.2} from {} recorded", created.amount, symbol),
+        TradeKind::Split => format!("{} split {} for 1", symbol, created.quantity.normalize()),
+    });
+    app.history
+        .record(crate::stat::history::Command::CreateTransaction { id: created.tranid });
+    app.needs_refresh = true;
+    Ok(())
+}
+
+/// switch the selected portfolio between FIFO and average cost
+fn toggle_cost_basis(
+    app: &mut App,
+    portfolio: &crate::stat::Portfolio,
+    rt: &tokio::runtime::Runtime,
+) {
+    let next = match portfolio.cost_basis {
+        CostBasis::Fifo => CostBasis::Average,
+        CostBasis::Average => CostBasis::Fifo,
+    };
+    match rt.block_on(crate::stat::sync::set_cost_basis_on_server(
+        &app.base_url,
+        &app.session,
+        portfolio.accountid,
+        next,
+    )) {
+        Ok(_) => {
+            app.history
+                .record(crate::stat::history::Command::SetCostBasis {
+                    id: portfolio.accountid,
+                    before: portfolio.cost_basis,
+                    after: next,
+                });
+            if let Some(acc) = app
+                .ledger
+                .account
+                .iter_mut()
+                .find(|a| a.id == portfolio.accountid)
+            {
+                acc.cost_basis = Some(next);
+            }
+            app.ledger_changed();
+            app.status_message = Some(format!(
+                "{} now uses {} cost",
+                portfolio.name,
+                cost_basis_name(next)
+            ));
+        }
+        Err(e) => app.error_message = Some(format!("Cost basis change failed: {}", e)),
+    }
+}
+
+fn cost_basis_name(method: CostBasis) -> &'static str {
+    match method {
+        CostBasis::Fifo => "FIFO",
+        CostBasis::Average => "average",
+    }
+}
+
+fn handle_key_import_prices(app: &mut App, key: KeyEvent, rt: &tokio::runtime::Runtime) {
+    use KeyCode::*;
+
+    match key.code {
+        Esc => {
+            app.input_mode = InputMode::Normal;
+            app.error_message = None;
+        }
+        Backspace => {
+            app.price_import_path.pop();
+        }
+        Char(c) => app.price_import_path.push(c),
+        Enter => match import_prices(app, rt) {
+            Ok(_) => {
+                app.error_message = None;
+                app.input_mode = InputMode::Normal;
+            }
+            Err(e) => app.error_message = Some(format!("Import failed: {}", e)),
+        },
+        _ => {}
+    }
+}
+
+/// send the price table at `price_import_path`, rows of symbols never traded are skipped
+fn import_prices(app: &mut App, rt: &tokio::runtime::Runtime) -> anyhow::Result<()> {
+    use crate::stat::sync::{Pricereq, import_prices_on_server};
+    use rust_decimal::Decimal;
+
+    let lines = crate::stat::load_price_file(app.price_import_path.trim())?;
+    let rows = lines
+        .into_iter()
+        .map(|l| {
+            Ok(Pricereq {
+                symbol: l.symbol,
+                date: l.date,
+                price: Decimal::from_f64(l.price)
+                    .ok_or_else(|| anyhow::anyhow!("Invalid price {}", l.price))?
+                    .round_dp(6),
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let result = rt.block_on(import_prices_on_server(&app.base_url, &app.session, &rows))?;
+    app.status_message = Some(match result.unknown.is_empty() {
+        true => format!("Imported {} prices", result.imported),
+        false => format!(
+            "Imported {} prices, skipped unknown symbols: {}",
+            result.imported,
+            result.unknown.join(", ")
+        ),
+    });
+    // the server has no delete for prices, so there is nothing to undo the import with
+    app.history.clear();
+    app.status_message = app
+        .status_message
+        .take()
+        .map(|m| format!("{m}, cannot be undone"));
+    app.needs_refresh = true;
+    Ok(())
+}
+
+fn handle_key_tax_lines(app: &mut App, key: KeyEvent, rt: &tokio::runtime::Runtime) {
+    use KeyCode::*;
+
+    let count = app.ledger.category.len();
+    match key.code {
+        Esc | Enter => {
+            app.input_mode = InputMode::Normal;
+            app.error_message = None;
+        }
+        Up => app.tax_category_idx = app.tax_category_idx.saturating_sub(1),
+        Down if app.tax_category_idx + 1 < count => app.tax_category_idx += 1,
+        Char(c @ ('j' | 'k')) => {
+            let Some(cat) = app.ledger.category.get(app.tax_category_idx) else {
+                return;
+            };
+            // none, then every line, round and round
+            let lines: Vec<Option<TaxLine>> = std::iter::once(None)
+                .chain(TaxLine::ALL.into_iter().map(Some))
+                .collect();
+            let at = lines.iter().position(|l| *l == cat.tax_line).unwrap_or(0);
+            let next = match c {
+                'k' => lines[(at + 1) % lines.len()],
+                _ => lines[(at + lines.len() - 1) % lines.len()],
+            };
+            let (id, before) = (cat.id, cat.tax_line);
+            match rt.block_on(crate::stat::sync::set_tax_line_on_server(
+                &app.base_url,
+                &app.session,
+                id,
+                next,
+            )) {
+                Ok(_) => {
+                    app.history
+                        .record(crate::stat::history::Command::SetTaxLine {
+                            id,
+                            before,
+                            after: next,
+                        });
+                    if let Some(cat) = app.ledger.category.iter_mut().find(|c| c.id == id) {
+                        cat.tax_line = next;
+                    }
+                    app.error_message = None;
+                }
+                Err(e) => app.error_message = Some(format!("Failed: {}", e)),
+            }
+        }
+        _ => {}
+    }
+}
+
+/// write the Taxes screen year as CSV and text into the working directory
+fn export_tax_summary(app: &mut App) {
+    let summary = app.tax_summary();
+    match summary.export(std::path::Path::new(".")) {
+        Ok((csv, text)) => {
+            app.status_message = Some(format!(
+                "Tax summary {} written to {} and {}",
+                summary.year,
+                csv.display(),
+                text.display()
+            ))
+        }
+        Err(e) => app.error_message = Some(format!("Export failed: {}", e)),
+    }
+}
+
+fn open_attachments(app: &mut App, tx_id: TransactionId) {
+    app.attachment_tranid = Some(tx_id);
+    app.selected_attachment_idx = 0;
+    app.error_message = None;
+    app.input_mode = InputMode::ViewingAttachments;
+}
+
+fn handle_key_attachments(app: &mut App, key: KeyEvent, rt: &tokio::runtime::Runtime) {
+    use KeyCode::*;
+
+    let count = app
+        .attachment_tranid
+        .map(|id| app.ledger.attachments_of(id).len())
+        .unwrap_or_default();
+    match key.code {
+        Esc | Char('q') | Char('f') => {
+            app.attachment_tranid = None;
+            app.error_message = None;
+            app.input_mode = InputMode::Normal;
+        }
+        Up => app.selected_attachment_idx = app.selected_attachment_idx.saturating_sub(1),
+        Down if app.selected_attachment_idx + 1 < count => app.selected_attachment_idx += 1,
+        Char('s') | Enter => save_selected_attachment(app, rt),
+        Char('a') => {
+            app.attachment_path = String::new();
+            app.error_message = None;
+            app.input_mode = InputMode::AddingAttachment;
+        }
+        Char('d') => {
+            if let Some(id) = app.selected_attachment().map(|a| a.id) {
+                app.pending_delete = Some(DeleteTarget::Attachment(id));
+                app.error_message = None;
+                app.input_mode = InputMode::ConfirmDelete;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// download the attachment under the cursor into the current directory, never over a file
+fn save_selected_attachment(app: &mut App, rt: &tokio::runtime::Runtime) {
+    let Some(a) = app.selected_attachment().cloned() else {
+        return;
+    };
+    let saved = rt
+        .block_on(crate::stat::sync::download_attachment_from_server(
+            &app.base_url,
+            &app.session,
+            a.id,
+        ))
+        .and_then(|bytes| crate::stat::save_attachment(std::path::Path::new("."), &a.filename, &bytes));
+    match saved {
+        Ok(path) => {
+            app.error_message = None;
+            app.status_message = Some(format!("Saved {} to {}", a.filename, path.display()));
+        }
+        Err(e) => app.error_message = Some(format!("Save failed: {}", e)),
+    }
+}
+
+fn handle_key_add_attachment(app: &mut App, key: KeyEvent, rt: &tokio::runtime::Runtime) {
+    use KeyCode::*;
+
+    match key.code {
+        Esc => {
+            app.error_message = None;
+            app.input_mode = InputMode::ViewingAttachments;
+        }
+        Backspace => {
+            app.attachment_path.pop();
+        }
+        Char(c) => app.attachment_path.push(c),
+        Enter => match upload_attachment(app, rt) {
+            Ok(_) => {
+                app.error_message = None;
+                app.input_mode = InputMode::ViewingAttachments;
+            }
+            Err(e) => app.error_message = Some(format!("Attach failed: {}", e)),
+        },
+        _ => {}
+    }
+}
+
+/// send the file at `attachment_path` and select it in the popup
+fn upload_attachment(app: &mut App, rt: &tokio::runtime::Runtime) -> anyhow::Result<()> {
+    use crate::stat::datatype::Attachment;
+
+    let tx_id = app
+        .attachment_tranid
+        .ok_or_else(|| anyhow::anyhow!("No transaction selected"))?;
+    let (filename, bytes) = crate::stat::read_attachment_file(app.attachment_path.trim())?;
+    let created = rt.block_on(crate::stat::sync::upload_attachment_on_server(
+        &app.base_url,
+        &app.session,
+        tx_id,
+        &filename,
+        bytes,
+    ))?;
+    app.status_message = Some(format!("Attached {}", created.filename));
+    app.history
+        .record(crate::stat::history::Command::UploadAttachment {
+            id: created.id,
+            filename: created.filename.clone(),
+        });
+    app.ledger
+        .attachment
+        .push(Attachment::from((created, app.user_id)));
+    app.selected_attachment_idx = app.ledger.attachments_of(tx_id).len().saturating_sub(1);
+    Ok(())
+}
+
+/// fetch the user's workspaces and open the switcher on the current one
+fn open_workspaces(app: &mut App, rt: &tokio::runtime::Runtime) {
+    match rt.block_on(crate::stat::sync::list_workspaces_from_server(
+        &app.base_url,
+        &app.session.token,
+    )) {
+        Ok(workspaces) => {
+            let current = app.ledger.workspace.as_ref().map(|w| w.id);
+            app.selected_workspace_idx = workspaces
+                .iter()
+                .position(|w| Some(w.id) == current)
+                .unwrap_or(0);
+            app.workspaces = workspaces;
+            app.error_message = None;
+            app.input_mode = InputMode::SwitchingWorkspace;
+            load_workspace_members(app, rt);
+        }
+        Err(e) => app.error_message = Some(format!("Loading workspaces failed: {}", e)),
+    }
+}
+
+fn load_workspace_members(app: &mut App, rt: &tokio::runtime::Runtime) {
+    app.workspace_members.clear();
+    let Some(id) = app.selected_workspace().map(|w| w.id) else {
+        return;
+    };
+    match rt.block_on(crate::stat::sync::list_members_from_server(
+        &app.base_url,
+        &app.session.token,
+        id,
+    )) {
+        Ok(members) => app.workspace_members = members,
+        Err(e) => app.error_message = Some(format!("Loading members failed: {}", e)),
+    }
+}
+
+fn handle_key_workspaces(app: &mut App, key: KeyEvent, rt: &tokio::runtime::Runtime) {
+    use KeyCode::*;
+
+    match key.code {
+        Esc | Char('q') | Char('w') => {
+            app.error_message = None;
+            app.input_mode = InputMode::Normal;
+        }
+        Up if app.selected_workspace_idx > 0 => {
+            app.selected_workspace_idx -= 1;
+            load_workspace_members(app, rt);
+        }
+        Down if app.selected_workspace_idx + 1 < app.workspaces.len() => {
+            app.selected_workspace_idx += 1;
+            load_workspace_members(app, rt);
+        }
+        Enter => switch_workspace(app),
+        Char('n') => {
+            app.workspace_input = String::new();
+            app.error_message = None;
+            app.input_mode = InputMode::CreatingWorkspace;
+        }
+        Char('i') => match app.selected_workspace() {
+            Some(w) if w.role == WorkspaceRole::Owner => {
+                app.workspace_input = String::new();
+                app.invite_role = WorkspaceRole::Editor;
+                app.error_message = None;
+                app.input_mode = InputMode::InvitingMember;
+            }
+            Some(_) => app.error_message = Some("Only owners can invite members".to_string()),
+            None => {}
+        },
+        _ => {}
+    }
+}
+
+/// reload everything from the workspace under the cursor
+fn switch_workspace(app: &mut App) {
+    let Some(w) = app.selected_workspace().cloned() else {
+        return;
+    };
+    // the personal workspace is the server default, no header needed
+    app.session.workspace = (!w.personal).then_some(w.id);
+    // undo entries and selections point into the old ledger
+    app.history = crate::stat::History::default();
+    app.selected_account_idx = 0;
+    app.selected_transaction_idx = 0;
+    app.selected_category_stats_idx = 0;
+    app.selected_account_stats_idx = 0;
+    app.selected_goal_idx = 0;
+    app.selected_loan_idx = 0;
+    app.selected_contact_idx = 0;
+    app.selected_portfolio_idx = 0;
+    app.selected_tax_line_idx = 0;
+    app.recon_account_idx = 0;
+    app.recon_entry_idx = 0;
+    app.recon_sessions.clear();
+    app.recon_statement_lines.clear();
+    app.reconcile_result = None;
+    app.error_message = None;
+    app.status_message = Some(format!("Switched to {} ({})", w.name, w.role.to_cloud()));
+    app.needs_refresh = true;
+    app.input_mode = InputMode::Normal;
+}
+
+fn handle_key_create_workspace(app: &mut App, key: KeyEvent, rt: &tokio::runtime::Runtime) {
+    use KeyCode::*;
+
+    match key.code {
+        Esc => {
+            app.error_message = None;
+            app.input_mode = InputMode::SwitchingWorkspace;
+        }
+        Backspace => {
+            app.workspace_input.pop();
+        }
+        Char(c) => app.workspace_input.push(c),
+        Enter => {
+            let name = app.workspace_input.trim().to_string();
+            if name.is_empty() {
+                app.error_message = Some("Workspace name is required".to_string());
+                return;
+            }
+            match rt.block_on(crate::stat::sync::create_workspace_on_server(
+                &app.base_url,
+                &app.session.token,
+                &name,
+            )) {
+                Ok(created) => {
+                    app.workspaces.push(created);
+                    app.selected_workspace_idx = app.workspaces.len() - 1;
+                    app.error_message = None;
+                    app.input_mode = InputMode::SwitchingWorkspace;
+                    load_workspace_members(app, rt);
+                }
+                Err(e) => app.error_message = Some(format!("Create failed: {}", e)),
+            }
+        }
+        _ => {}
+    }
+}
+
+fn handle_key_invite_member(app: &mut App, key: KeyEvent, rt: &tokio::runtime::Runtime) {
+    use KeyCode::*;
+
+    match key.code {
+        Esc => {
+            app.error_message = None;
+            app.input_mode = InputMode::SwitchingWorkspace;
+        }
+        Tab => app.invite_role = app.invite_role.next(),
+        Backspace => {
+            app.workspace_input.pop();
+        }
+        Char(c) => app.workspace_input.push(c),
+        Enter => {
+            let Some(id) = app.selected_workspace().map(|w| w.id) else {
+                return;
+            };
+            let email = app.workspace_input.trim().to_string();
+            if email.is_empty() {
+                app.error_message = Some("Email is required".to_string());
+                return;
+            }
+            match rt.block_on(crate::stat::sync::add_member_on_server(
+                &app.base_url,
+                &app.session.token,
+                id,
+                &email,
+                app.invite_role,
+            )) {
+                Ok(member) => {
+                    app.status_message = Some(format!(
+                        "Invited {} as {}",
+                        member.email,
+                        member.role.to_cloud()
+                    ));
+                    app.workspace_members.push(member);
+                    app.error_message = None;
+                    app.input_mode = InputMode::SwitchingWorkspace;
+                }
+                Err(e) => app.error_message = Some(format!("Invite failed: {}", e)),
+            }
+        }
+        _ => {}
+    }
+}
+
+fn submit_new_goal(app: &mut App, rt: &tokio::runtime::Runtime) -> anyhow::Result<()> {
+    use crate::stat::datatype::Goal;
+
+    let name = app.new_goal_name.trim();
+    if name.is_empty() {
+        anyhow::bail!("Goal name is required");
+    }
+    let target = app
+        .new_goal_target
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|t| *t > 0.0)
+        .ok_or_else(|| anyhow::anyhow!("Target must be a positive number"))?;
+    let deadline = match app.new_goal_deadline.trim() {
+        "" => None,
+        d => Some(
+            chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d")
+                .map_err(|_| anyhow::anyhow!("Deadline must be YYYY-MM-DD"))?,
+        ),
+    };
+    let tag = app.new_goal_tag.trim().trim_start_matches('#').to_lowercase();
+    if tag.is_empty() && app.new_goal_accounts.is_empty() {
+        anyhow::bail!("Link at least one account or set a tag");
+    }
+    let goal = Goal {
+        id: 0,
+        userid: app.user_id,
+        name: name.to_string(),
+        target,
+        deadline,
+        accounts: app.new_goal_accounts.clone(),
+        tag: (!tag.is_empty()).then_some(tag),
+        create_date: chrono::Utc::now(),
+    };
+    let created = rt.block_on(crate::stat::sync::create_goal_on_server(
+        &app.base_url,
+        &app.session,
+        &goal,
+    ))?;
+    app.history
+        .record(crate::stat::history::Command::CreateGoal {
+            goal: created.clone(),
+        });
+    app.selected_goal_idx = app.goals.len();
+    app.goals.push(created);
+    app.goals_needs_reload = true;
+    Ok(())
+}
+
+fn handle_key_import_statement(app: &mut App, key: KeyEvent) {
+    use KeyCode::*;
+
+    match key.code {
+        Esc => {
+            app.input_mode = InputMode::Normal;
+            app.error_message = None;
+        }
+        Backspace => {
+            app.recon_import_path.pop();
+        }
+        Char(c) => {
+            app.recon_import_path.push(c);
+        }
+        Enter => match crate::stat::load_statement_file(app.recon_import_path.trim()) {
+            Ok(lines) => {
+                app.status_message = Some(format!("Loaded {} statement lines", lines.len()));
+                app.recon_statement_lines = lines;
+                app.recon_line_idx = 0;
+                app.error_message = None;
+                app.input_mode = InputMode::Normal;
+            }
+            Err(e) => app.error_message = Some(format!("Import failed: {}", e)),
+        },
+        _ => {}
+    }
+}
+
+/// create the missing transaction for the selected statement-only line
+fn create_from_statement_line(app: &mut App, rt: &tokio::runtime::Runtime) {
+    use crate::stat::sync::Entryreq;
+    use rust_decimal::Decimal;
+
+    let Some(account_id) = app.recon_account().map(|a| a.id) else {
+        return;
+    };
+    let Some(line) = app
+        .recon_statement_match()
+        .and_then(|m| m.statement_only.get(app.recon_line_idx).cloned())
+    else {
+        app.status_message = Some("No statement-only line selected".to_string());
+        return;
+    };
+    let Some(amount) = Decimal::from_f64(line.amount) else {
+        return;
+    };
+    let created = rt.block_on(crate::stat::sync::create_cloudtransaction(
+        &app.base_url,
+        &app.session,
+        line.date,
+        if line.payee.is_empty() {
+            None
+        } else {
+            Some(&line.payee)
+        },
+        line.memo.as_deref(),
+        vec![Entryreq {
+            account_id,
+            category_id: None,
+            amount: amount.round_dp(2),
+            note: None,
+        }],
+    ));
+    match created {
+        Ok(tx) => {
+            app.history
+                .record(crate::stat::history::Command::CreateTransaction { id: tx.id });
+            app.ledger.apply_cloudtransaction(tx, app.user_id);
+            app.ledger_changed();
+            app.status_message = Some(format!(
+                "Created {} {:.2} on {}",
+                line.payee, line.amount, line.date
+            ));
+            app.needs_refresh = true;
+        }
+        Err(e) => app.error_message = Some(format!("Create transaction failed: {}", e)),
+    }
+}
+
+/// mark every matched entry as cleared
+fn tick_matched_entries(app: &mut App, rt: &tokio::runtime::Runtime) {
+    use crate::stat::datatype::EntryStatus;
+
+    let Some(matches) = app.recon_statement_match() else {
+        return;
+    };
+    let mut ticked = 0;
+    for m in matches
+        .matched
+        .iter()
+        .filter(|m| m.entry.status == EntryStatus::Uncleared)
+    {
+        if let Err(e) = rt.block_on(crate::stat::sync::set_entry_status_on_server(
+            &app.base_url,
+            &app.session,
+            m.entry.id,
+            EntryStatus::Cleared,
+        )) {
+            app.error_message = Some(format!("Update status failed: {}", e));
+            break;
+        }
+        app.history
+            .record(crate::stat::history::Command::SetEntryStatus {
+                id: m.entry.id,
+                before: EntryStatus::Uncleared,
+                after: EntryStatus::Cleared,
+            });
+        if let Some(e) = app.ledger.entry.iter_mut().find(|e| e.id == m.entry.id) {
+            e.status = EntryStatus::Cleared;
+        }
+        ticked += 1;
+    }
+    app.status_message = Some(format!("Ticked {} matched entries", ticked));
+}
+
+/// fetch every report the stats screens show, on failure fall back to the local ledger
+fn load_server_report(app: &mut App, rt: &tokio::runtime::Runtime) {
+    use crate::stat::sync::*;
+
+    let (base_url, session) = (app.base_url.as_str(), &app.session);
+    let range = app.date_range();
+    let (period, month, date) = (app.trend_period, app.selected_month, app.networth_date);
+    let res = rt.block_on(async {
+        let month_totals = fetch_report_summary(
+            base_url,
+            session,
+            DateRange::month(month.0, month.1),
+            None,
+            None,
+        )
+        .await?;
+        let top_categories =
+            fetch_report_top_categories(base_url, session, range, None, 10, Some(true)).await?;
+        let top_accounts =
+            fetch_report_top_accounts(base_url, session, range, None, 10, Some(true)).await?;
+        let trends = fetch_report_trends(base_url, session, range, period, None, None).await?;
+        let balances = fetch_report_balances(base_url, session, date).await?;
+        anyhow::Ok(ServerReport {
+            range,
+            period,
+            month,
+            networth_date: date,
+            month_totals,
+            top_categories,
+            top_accounts,
+            trends,
+            balances,
+        })
+    });
+    match res {
+        Ok(report) => app.server_report = Some(report),
+        Err(e) => {
+            app.server_reports = false;
+            app.error_message = Some(format!("Server reports failed: {}", e));
+        }
+    }
+}
+
+fn load_reconciliations(app: &mut App, rt: &tokio::runtime::Runtime) {
+    let Some(account_id) = app.recon_account().map(|a| a.id) else {
+        app.recon_sessions.clear();
+        return;
+    };
+    match rt.block_on(crate::stat::sync::list_reconciliations_from_server(
+        &app.base_url,
+        &app.session,
+        account_id,
+    )) {
+        Ok(sessions) => app.recon_sessions = sessions,
+        Err(e) => app.error_message = Some(format!("Load reconciliations failed: {}", e)),
+    }
+}
+
+fn load_goals(app: &mut App, rt: &tokio::runtime::Runtime) {
+    match rt.block_on(crate::stat::sync::list_goals_from_server(
+        &app.base_url,
+        &app.session,
+        app.user_id,
+    )) {
+        Ok(goals) => app.goals = goals,
+        Err(e) => app.error_message = Some(format!("Load goals failed: {}", e)),
+    }
+}
+
+/// tick the selected entry off against the statement, or untick it
+fn toggle_recon_entry(app: &mut App, rt: &tokio::runtime::Runtime) {
+    use crate::stat::datatype::EntryStatus;
+
+    let Some(view) = app.recon_statement() else {
+        app.status_message = Some("Press s to start a reconciliation first".to_string());
+        return;
+    };
+    let Some((_, entry)) = view.entries.get(app.recon_entry_idx) else {
+        return;
+    };
+    let status = match entry.status {
+        EntryStatus::Cleared => EntryStatus::Uncleared,
+        _ => EntryStatus::Cleared,
+    };
+    let (entry_id, before) = (entry.id, entry.status);
+    match rt.block_on(crate::stat::sync::set_entry_status_on_server(
+        &app.base_url,
+        &app.session,
+        entry_id,
+        status,
+    )) {
+        // update the local copy, no need to download the whole ledger for a tick
+        Ok(_) => {
+            app.history
+                .record(crate::stat::history::Command::SetEntryStatus {
+                    id: entry_id,
+                    before,
+                    after: status,
+                });
+            if let Some(e) = app.ledger.entry.iter_mut().find(|e| e.id == entry_id) {
+                e.status = status;
+            }
+        }
+        Err(e) => app.error_message = Some(format!("Update status failed: {}", e)),
+    }
+}
+
+fn finish_reconciliation(app: &mut App, rt: &tokio::runtime::Runtime) {
+    let Some(view) = app.recon_statement() else {
+        return;
+    };
+    if !view.is_balanced() {
+        app.error_message = Some(format!(
+            "Cannot finish: cleared balance {:.2} is {:+.2} away from the statement",
+            view.cleared_balance, view.difference
+        ));
+        return;
+    }
+    let Some(id) = app.recon_open_session().map(|r| r.id) else {
+        return;
+    };
+    match rt.block_on(crate::stat::sync::finish_reconciliation_on_server(
+        &app.base_url,
+        &app.session,
+        id,
+    )) {
+        Ok(done) => {
+            // the entries are locked now, undoing older status changes would unreconcile them
+            app.history.clear();
+            app.status_message = Some(format!(
+                "Reconciled {} entries up to {}, cannot be undone",
+                done.entry_count, done.statement_date
+            ));
+            app.recon_entry_idx = 0;
+            app.needs_refresh = true;
+        }
+        Err(e) => app.error_message = Some(format!("Finish failed: {}", e)),
+    }
+}
+
+fn handle_key_view_history(app: &mut App, key: KeyEvent) {
+    use KeyCode::*;
+
+    match key.code {
+        Esc | Char('q') | Char('h') => {
+            app.tx_history.clear();
+            app.input_mode = InputMode::Normal;
+        }
+        Up => app.tx_history_scroll = app.tx_history_scroll.saturating_sub(1),
+        Down => app.tx_history_scroll = app.tx_history_scroll.saturating_add(1),
+        PageUp => app.tx_history_scroll = app.tx_history_scroll.saturating_sub(10),
+        PageDown => app.tx_history_scroll = app.tx_history_scroll.saturating_add(10),
+        _ => {}
+    }
+}
+
+fn perform_delete(
+    app: &mut App,
+    target: DeleteTarget,
+    rt: &tokio::runtime::Runtime,
+) -> Result<(), String> {
+    use crate::stat::history::*;
+    use crate::stat::sync::*;
+
+    // build the undo command before the row disappears from the ledger, deletes are soft so
+    // undo restores by id; revoked tokens stay revoked, they have none
+    let cmd = match target {
+        DeleteTarget::Transaction(id) => Some(Command::DeleteTransaction { id }),
+        DeleteTarget::Entry(id) => Some(Command::DeleteEntry { id }),
+        DeleteTarget::Account(id) => {
+            let acc = app.ledger.account.iter().find(|a| a.id == id);
+            let Some(acc) = acc else {
+                return Err("Account not found".to_string());
+            };
+            Some(Command::DeleteAccount {
+                id,
+                name: acc.name.clone(),
+            })
+        }
+        DeleteTarget::Category(id) => {
+            let cat = app.ledger.category.iter().find(|c| c.id == id);
+            let Some(cat) = cat else {
+                return Err("Category not found".to_string());
+            };
+            Some(Command::DeleteCategory {
+                id,
+                name: cat.name.clone(),
+            })
+        }
+        DeleteTarget::Goal(id) => {
+            let Some(goal) = app.goals.iter().find(|g| g.id == id) else {
+                return Err("Goal not found".to_string());
+            };
+            Some(Command::DeleteGoal { goal: goal.clone() })
+        }
+        DeleteTarget::Attachment(id) => {
+            let Some(att) = app.ledger.attachment.iter().find(|a| a.id == id) else {
+                return Err("Attachment not found".to_string());
+            };
+            Some(Command::DeleteAttachment {
+                id,
+                filename: att.filename.clone(),
+            })
+        }
+        DeleteTarget::Token(_) => None,
+    };
+    let res = match target {
+        DeleteTarget::Transaction(id) => {
+            rt.block_on(delete_transaction_on_server(&app.base_url, &app.session, id))
+        }
+        DeleteTarget::Entry(id) => rt.block_on(delete_entry_on_server(&app.base_url, &app.session, id)),
+        DeleteTarget::Account(id) => {
+            rt.block_on(delete_account_on_server(&app.base_url, &app.session, id))
+        }
+        DeleteTarget::Category(id) => {
+            rt.block_on(delete_category_on_server(&app.base_url, &app.session, id))
+        }
+        DeleteTarget::Goal(id) => rt.block_on(delete_goal_on_server(&app.base_url, &app.session, id)),
+        DeleteTarget::Attachment(id) => {
+            rt.block_on(delete_attachment_on_server(&app.base_url, &app.session, id))
+        }
+        DeleteTarget::Token(id) => rt.block_on(revoke_token_on_server(&app.base_url, &app.session.token, id)),
+    };
+    match res {
+        Ok(_) => {
+            if let Some(cmd) = cmd {
+                app.history.record(cmd);
+            }
+            // stats follow right away, the refresh brings the rest
+            match target {
+                DeleteTarget::Transaction(id) => app.ledger.remove_transaction(id),
+                DeleteTarget::Entry(id) => app.ledger.remove_entry(id),
+                DeleteTarget::Goal(id) => app.goals.retain(|g| g.id != id),
+                DeleteTarget::Attachment(id) => app.ledger.attachment.retain(|a| a.id != id),
+                DeleteTarget::Token(id) => {
+                    if let Some(t) = app.tokens.iter_mut().find(|t| t.id == id) {
+                        t.revoked = true;
+                    }
+                }
+                DeleteTarget::Account(_) | DeleteTarget::Category(_) => {}
+            }
+            app.ledger_changed();
+            Ok(())
+        }
+        Err(e) => Err(delete_error_message(app, target, &e)),
+    }
+}
+
+/// turn the server's 409 into something the user can act on
+fn delete_error_message(app: &App, target: DeleteTarget, e: &anyhow::Error) -> String {
+    use crate::stat::sync::ServerError;
+
+    let conflict = e
+        .downcast_ref::<ServerError>()
+        .is_some_and(|s| s.status == reqwest::StatusCode::CONFLICT);
+    if !conflict {
+        return format!("Delete failed: {}", e);
+    }
+    match target {
+        DeleteTarget::Account(id) => format!(
+            "Cannot delete account '{}': it still has entries, delete its transactions first",
+            app.ledger.trans_accountid2name(id)
+        ),
+        DeleteTarget::Category(id) => format!(
+            "Cannot delete category '{}': it still has sub-categories, delete them first",
+            app.ledger.trans_categoryid2name(id)
+        ),
+        _ => format!("Delete failed: {}", e),
+    }
+}
+
+fn ui(f: &mut Frame<'_>, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([
+            Constraint::Length(3), // header
+            Constraint::Min(0),    // main
+            Constraint::Length(3), // footer (more space for per-screen shortcuts)
+        ])
+        .split(f.area());
+
+    // Header
+    let (sy, sm) = app.start_month;
+    let (ey, em) = app.end_month;
+    let screen_name = match app.current_screen {
+        Screen::Dashboard => "Dashboard",
+        Screen::Accounts => "Accounts",
+        Screen::NetWorth => "Net Worth",
+        Screen::Transactions => "Transactions",
+        Screen::CategoryStats => "Category Stats",
+        Screen::AccountStats => "Account Stats",
+        Screen::Trends => "Trends",
+        Screen::Compare => "Compare",
+        Screen::Forecast => "Forecast",
+        Screen::Subscriptions => "Subscriptions",
+        Screen::Goals => "Goals",
+        Screen::Loans => "Loans",
+        Screen::People => "People",
+        Screen::Investments => "Investments",
+        Screen::Taxes => "Taxes",
+        Screen::Reconcile => "Reconcile",
+        Screen::Help => "Help",
+        Screen::Advisor => "Advisor",
+    };
+    let workspace = match &app.ledger.workspace {
+        Some(w) => format!("   |   Workspace: {} ({})", w.name, w.role.to_cloud()),
+        None => String::new(),
+    };
+    let header_text = format!(
+        "Rust Finance Tracker - {screen_name}   |   Range: {sy:04}-{sm:02} ~ {ey:04}-{em:02}{workspace}"
+    );
+    let header = Paragraph::new(header_text).block(Block::default().borders(Borders::ALL));
+    f.render_widget(header, chunks[0]);
+
+    // Main content
+    match app.current_screen {
+        Screen::Dashboard => {
+            if app.input_mode == InputMode::CreatingCategory {
+                draw_create_category(f, chunks[1], app);
+            } else if app.input_mode == InputMode::CreatingTransaction {
+                draw_create_transaction(f, chunks[1], app);
+            } else {
+                draw_dashboard(f, chunks[1], app);
+            }
+        }
+        Screen::Accounts => {
+            if app.input_mode == InputMode::CreatingAccount {
+                draw_create_account(f, chunks[1], app);
+            } else if app.input_mode == InputMode::CreatingCategory {
+                draw_create_category(f, chunks[1], app);
+            } else if app.input_mode == InputMode::CreatingTransaction {
+                draw_create_transaction(f, chunks[1], app);
+            } else {
+                draw_accounts(f, chunks[1], app);
+            }
+        }
+        Screen::NetWorth => draw_networth(f, chunks[1], app),
+        Screen::Transactions => draw_transactions(f, chunks[1], app),
+        Screen::CategoryStats => draw_category_stats(f, chunks[1], app),
+        Screen::AccountStats => draw_account_stats(f, chunks[1], app),
+        Screen::Trends => draw_trends(f, chunks[1], app),
+        Screen::Compare => draw_compare(f, chunks[1], app),
+        Screen::Forecast => draw_forecast(f, chunks[1], app),
+        Screen::Subscriptions => draw_subscriptions(f, chunks[1], app),
+        Screen::Goals => draw_goals(f, chunks[1], app),
+        Screen::Loans => draw_loans(f, chunks[1], app),
+        Screen::People => draw_people(f, chunks[1], app),
+        Screen::Investments => draw_investments(f, chunks[1], app),
+        Screen::Taxes => draw_taxes(f, chunks[1], app),
+        Screen::Reconcile => draw_reconcile(f, chunks[1], app),
+        Screen::Advisor => draw_advisor(f, chunks[1], app),
+        Screen::Help => draw_help(f, chunks[1], app),
+    }
+    if app.input_mode == InputMode::ConfirmDelete {
+        draw_confirm_delete(f, chunks[1], app);
+    }
+    if app.input_mode == InputMode::ViewingHistory {
+        draw_tx_history(f, chunks[1], app);
+    }
+    if app.input_mode == InputMode::StartingReconcile {
+        draw_start_reconcile(f, chunks[1], app);
+    }
+    if app.input_mode == InputMode::ImportingStatement {
+        draw_import_statement(f, chunks[1], app);
+    }
+    if app.input_mode == InputMode::CreatingGoal {
+        draw_create_goal(f, chunks[1], app);
+    }
+    if app.input_mode == InputMode::EditingCreditTerms {
+        draw_credit_terms(f, chunks[1], app);
+    }
+    if app.input_mode == InputMode::EditingLoanTerms {
+        draw_loan_terms(f, chunks[1], app);
+    }
+    if app.input_mode == InputMode::RecordingLoanPayment {
+        draw_loan_payment(f, chunks[1], app);
+    }
+    if app.input_mode == InputMode::CreatingContact {
+        draw_create_contact(f, chunks[1], app);
+    }
+    if app.input_mode == InputMode::SettlingContact {
+        draw_settlement(f, chunks[1], app);
+    }
+    if app.input_mode == InputMode::RecordingTrade {
+        draw_trade(f, chunks[1], app);
+    }
+    if app.input_mode == InputMode::ImportingPrices {
+        draw_import_prices(f, chunks[1], app);
+    }
+    if app.input_mode == InputMode::EditingTaxLines {
+        draw_tax_lines(f, chunks[1], app);
+    }
+    if matches!(
+        app.input_mode,
+        InputMode::ViewingAttachments | InputMode::AddingAttachment
+    ) {
+        draw_attachments(f, chunks[1], app);
+    }
+    if app.input_mode == InputMode::AddingAttachment {
+        draw_add_attachment(f, chunks[1], app);
+    }
+    if matches!(
+        app.input_mode,
+        InputMode::SwitchingWorkspace | InputMode::CreatingWorkspace | InputMode::InvitingMember
+    ) {
+        draw_workspaces(f, chunks[1], app);
+    }
+    if matches!(
+        app.input_mode,
+        InputMode::CreatingWorkspace | InputMode::InvitingMember
+    ) {
+        draw_workspace_input(f, chunks[1], app);
+    }
+    if matches!(app.input_mode, InputMode::ViewingTokens | InputMode::CreatingToken) {
+        draw_tokens(f, chunks[1], app);
+    }
+    if app.input_mode == InputMode::CreatingToken {
+        draw_create_token(f, chunks[1], app);
+    }
+
+    // Footer
+    let footer_text = if let Some(ref msg) = app.error_message {
+        format!("ERROR: {} | Press c to clear", msg)
+    } else if let Some(ref msg) = app.status_message {
+        format!("{} | u undo | Ctrl+R redo", msg)
+    } else {
+        match app.input_mode {
+            InputMode::Normal => match app.current_screen {
+                Screen::Dashboard => "Dashboard: Tab/Shift+Tab switch | ←/→ month | [ ] min month range |shift [] max month range| n new tx | o server/local | u undo | r refresh | ? help | q quit".to_string(),
+                Screen::Accounts => "Accounts: Tab/Shift+Tab switch | ↑/↓ select account | n new tx | c new account | t card/loan terms | d delete account | u undo | r refresh | q quit".to_string(),
+                Screen::NetWorth => "Net Worth: Tab/Shift+Tab switch | ←/→ move date | g daily/monthly | o server/local | [ ] min month range |shift [] max month range| r refresh | q quit".to_string(),
+                Screen::Transactions => "Transactions: Tab/Shift+Tab switch | ↑/↓ select tx | n new tx | d delete tx/split | f attachments | h history | u undo | r refresh | q quit".to_string(),
+                Screen::CategoryStats => "Category Stats: Tab/Shift+Tab switch | ↑/↓ move | v table/chart | o server/local | d delete category | r refresh | q quit".to_string(),
+                Screen::AccountStats => "Account Stats: Tab/Shift+Tab switch | ↑/↓ move | v table/chart | o server/local | r refresh | q quit".to_string(),
+                Screen::Trends => "Trends: Tab/Shift+Tab switch | v table/chart | p period | o server/local | [ ] min month range |shift [] max month range| r refresh | q quit".to_string(),
+                Screen::Compare => "Compare: Tab/Shift+Tab switch | p previous period / last year | [ ] min month range |shift [] max month range| r refresh | q quit".to_string(),
+                Screen::Forecast => "Forecast: Tab/Shift+Tab switch | + / - months ahead | v table/chart | r refresh | q quit".to_string(),
+                Screen::Subscriptions => "Subscriptions: Tab/Shift+Tab switch | r refresh | q quit".to_string(),
+                Screen::Goals => "Goals: Tab/Shift+Tab switch | ↑/↓ select goal | n new goal | d delete goal | r refresh | q quit".to_string(),
+                Screen::Loans => "Loans: Tab/Shift+Tab switch | ↑/↓ select loan | p record payment | + / - extra payment | t loan terms | u undo | r refresh | q quit".to_string(),
+                Screen::People => "People: Tab/Shift+Tab switch | ↑/↓ select contact | n new contact | s settle up | u undo | r refresh | q quit".to_string(),
+                Screen::Investments => "Investments: Tab/Shift+Tab switch | ↑/↓ select account | t record trade | i import prices | m FIFO/average cost | u undo | r refresh | q quit".to_string(),
+                Screen::Taxes => "Taxes: Tab/Shift+Tab switch | ←/→ year | ↑/↓ select tax line | l category tax lines | e export CSV + text | r refresh | q quit".to_string(),
+                Screen::Reconcile if !app.recon_statement_lines.is_empty() => "Statement: ↑/↓ select line | n create missing tx | m tick matched | c close | i import | a account | q quit".to_string(),
+                Screen::Reconcile => "Reconcile: a account | s start | ↑/↓ select | Space tick | f finish | x cancel | i import | e quick check | q quit".to_string(),
+                Screen::Advisor => "Advisor: Tab/Shift+Tab switch | g generate | m select model (↑/↓/Enter/Esc) | i chat | PageUp/PageDown chat scroll | ↑/↓ output scroll | r refresh | q quit".to_string(),
+                Screen::Help => "Help: Tab/Shift+Tab switch | q quit".to_string(),
+            },
+            InputMode::EditingReconcile => {
+                "Reconcile edit: 0-9 . - to type | Enter submit | Esc cancel".to_string()
+            }
+            InputMode::CreatingTransaction => {
+                "Create tx: Tab/Shift+Tab fields | j/k account/category | n new category | a add entry | x del entry | Enter submit | Esc cancel".to_string()
+            }
+            InputMode::CreatingCategory => {
+                "Create category: Type name | Enter submit | Esc cancel".to_string()
+            }
+            InputMode::CreatingAccount => {
+                "Create account: Tab/Shift+Tab fields | j/k account type | Enter submit | Esc cancel".to_string()
+            }
+            InputMode::AdvisorChat => {
+                "Advisor chat: Type message | Enter send | Esc cancel".to_string()
+            }
+            InputMode::ConfirmDelete => {
+                "Confirm delete: y/Enter delete | ↑/↓ select split | x split only | n/Esc cancel".to_string()
+            }
+            InputMode::ViewingHistory => {
+                "History: ↑/↓ PageUp/PageDown scroll | h/Esc close".to_string()
+            }
+            InputMode::ImportingStatement => {
+                "Import statement: type path of a .csv or .ofx file | Enter load | Esc cancel".to_string()
+            }
+            InputMode::StartingReconcile => {
+                "Start reconciliation: Tab switch field | type date / closing balance | Enter start | Esc cancel".to_string()
+            }
+            InputMode::EditingCreditTerms => {
+                "Card terms: Tab/Shift+Tab fields | type numbers, empty = unknown | Enter save | Esc cancel".to_string()
+            }
+            InputMode::EditingLoanTerms => {
+                "Loan terms: Tab/Shift+Tab fields | j/k frequency | all empty = clear | Enter save | Esc cancel".to_string()
+            }
+            InputMode::RecordingLoanPayment => {
+                "Loan payment: Tab/Shift+Tab fields | j/k account/category | Enter record | Esc cancel".to_string()
+            }
+            InputMode::CreatingContact => {
+                "New contact: Tab/Shift+Tab fields | Enter create | Esc cancel".to_string()
+            }
+            InputMode::SettlingContact => {
+                "Settle up: Tab/Shift+Tab fields | j/k account | Enter record | Esc cancel".to_string()
+            }
+            InputMode::RecordingTrade => {
+                "Trade: Tab/Shift+Tab fields | j/k kind/category | Enter record | Esc cancel".to_string()
+            }
+            InputMode::ImportingPrices => {
+                "Import prices: type path of a .csv file | Enter import | Esc cancel".to_string()
+            }
+            InputMode::EditingTaxLines => {
+                "Tax lines: ↑/↓ select category | j/k change its tax line | Esc close".to_string()
+            }
+            InputMode::ViewingAttachments => {
+                "Attachments: ↑/↓ select | s/Enter save to current dir | a attach file | d delete | f/Esc close".to_string()
+            }
+            InputMode::AddingAttachment => {
+                "Attach file: type path of an image or PDF | Enter upload | Esc cancel".to_string()
+            }
+            InputMode::SwitchingWorkspace => {
+                "Workspaces: ↑/↓ select | Enter switch | n new | i invite member | w/Esc close".to_string()
+            }
+            InputMode::CreatingWorkspace => {
+                "New workspace: type name | Enter create | Esc cancel".to_string()
+            }
+            InputMode::InvitingMember => {
+                "Invite: type email | Tab role | Enter invite | Esc cancel".to_string()
+            }
+            InputMode::ViewingTokens => {
+                "API tokens: ↑/↓ select | n new | d revoke | k/Esc close".to_string()
+            }
+            InputMode::CreatingToken => {
+                "New token: Tab/Shift+Tab fields | j/k scopes | Enter create | Esc cancel".to_string()
+            }
+            InputMode::CreatingGoal => {
+                "New goal: Tab/Shift+Tab fields | j/k account, Space link/unlink | Enter create | Esc cancel".to_string()
+            }
+        }
+    };
+    let footer = Paragraph::new(footer_text).block(Block::default().borders(Borders::ALL));
+    f.render_widget(footer, chunks[2]);
+}
+
+fn draw_dashboard(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let (year, month) = app.selected_month;
+    let (income, outcome, net) = app.report_month_totals();
+
+    let text = format!(
+        "Focused Month: {year:04}-{month:02}\n\
+         Income:  {income:.2}\n\
+         Outcome: {outcome:.2}\n\
+         Net:     {net:.2}\n"
+    );
+    let mut lines: Vec<Line> = text.lines().map(Line::from).collect();
+    let warnings = app
+        .forecast
+        .as_ref()
+        .map(forecast_warning_lines)
+        .unwrap_or_default();
+    if !warnings.is_empty() {
+        lines.push(Line::from(""));
+        lines.extend(warnings);
+    }
+
+    let block = Block::default()
+        .title(Span::raw(format!("Monthly Summary{}", report_source(app))))
+        .borders(Borders::ALL);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(lines.len() as u16 + 2), Constraint::Min(4)])
+        .split(area);
+    let p = Paragraph::new(lines).block(block);
+    f.render_widget(p, chunks[0]);
+    draw_anomalies(f, chunks[1], app);
+}
+
+/// ranked alerts of the focused month, strongest first
+fn draw_anomalies(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let anomalies = app.anomalies.as_deref().unwrap_or_default();
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let rows = anomalies.iter().map(|a| {
+        // well past the threshold in red, the rest in yellow
+        let color = if a.score >= 2.0 {
+            Color::Red
+        } else {
+            Color::Yellow
+        };
+        Row::new(vec![
+            a.date.to_string(),
+            a.kind.name().to_string(),
+            format!("{:.2}", a.amount),
+            format!("{:.2}", a.expected),
+            a.explanation.clone(),
+        ])
+        .style(Style::default().fg(color))
+    });
+    let widths = [
+        Constraint::Length(11),
+        Constraint::Length(10),
+        Constraint::Length(11),
+        Constraint::Length(11),
+        Constraint::Min(20),
+    ];
+    let (y, m) = app.selected_month;
+    let title = match anomalies.len() {
+        0 => format!("Alerts {y:04}-{m:02}: nothing unusual"),
+        n => format!("Alerts {y:04}-{m:02} ({n})"),
+    };
+    let table = Table::new(rows, widths)
+        .header(Row::new(vec!["Date", "Kind", "Amount", "Usual", "Details"]).style(bold))
+        .block(Block::default().title(title).borders(Borders::ALL));
+    f.render_widget(table, area);
+}
 
 fn draw_accounts(f: &mut Frame<'_>, area: Rect, app: &App) {
     let accounts = app.ledger.all_account_summary();
 
-    let mut selected_idx = app.selected_account_idx;
-    if !accounts.is_empty() && selected_idx >= accounts.len() {
-        selected_idx = accounts.len() - 1;
-    }
+    let mut selected_idx = app.selected_account_idx;
+    if !accounts.is_empty() && selected_idx >= accounts.len() {
+        selected_idx = accounts.len() - 1;
+    }
+
+    let rows = accounts.iter().enumerate().map(|(idx, acc)| {
+        let acc_type_str = format!("{:?}", acc.account_type);
+        let currency_str = format!("{:?}", acc.currency);
+
+        let cells = vec![
+            acc.accountid.to_string(),
+            acc.name.clone(),
+            acc_type_str,
+            format!("{:.2}", acc.balance),
+            currency_str,
+        ];
+
+        let mut row = Row::new(cells);
+        if idx == selected_idx {
+            row = row.style(Style::default().add_modifier(Modifier::REVERSED));
+        }
+        row
+    });
+
+    let widths = [
+        Constraint::Length(4),
+        Constraint::Length(16),
+        Constraint::Length(12),
+        Constraint::Length(12),
+        Constraint::Length(10),
+    ];
+
+    let table = Table::new(rows, widths)
+        .header(
+            Row::new(vec!["ID", "Name", "Type", "Balance", "Currency"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::default().title("Accounts").borders(Borders::ALL));
+
+    let cards = app.credit_status();
+    if cards.is_empty() {
+        f.render_widget(table, area);
+        return;
+    }
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(5), Constraint::Length(cards.len() as u16 + 3)])
+        .split(area);
+    f.render_widget(table, chunks[0]);
+    draw_upcoming_due(f, chunks[1], &cards);
+}
+
+/// statement, payment due and utilization of each credit card
+fn draw_upcoming_due(f: &mut Frame<'_>, area: Rect, cards: &[CreditStatus]) {
+    let today = chrono::Local::now().date_naive();
+    let money = |v: Option<f64>| v.map(|v| format!("{:.2}", v)).unwrap_or_else(|| "-".to_string());
+    let rows = cards.iter().map(|c| {
+        let st = c.statement;
+        let due = match st.and_then(|s| s.due_date.map(|d| (d, s.days_left(today)))) {
+            Some((d, Some(n))) if n >= 0 => format!("{} ({}d)", d, n),
+            Some((d, _)) => format!("{} (past)", d),
+            None => "-".to_string(),
+        };
+        // red once the due date passed unpaid, yellow when it is a week away
+        let color = match st {
+            Some(s) if s.is_paid() => Color::Green,
+            Some(s) => match s.days_left(today) {
+                Some(n) if n < 0 => Color::Red,
+                Some(n) if n <= 7 => Color::Yellow,
+                _ => Color::Reset,
+            },
+            None => Color::Reset,
+        };
+        let interest = match st {
+            Some(s) if !s.is_paid() => money(s.interest),
+            Some(_) => "0.00".to_string(),
+            None => "-".to_string(),
+        };
+        Row::new(vec![
+            Cell::from(c.name.clone()),
+            Cell::from(format!("{:.2}", c.owed)),
+            Cell::from(money(c.terms.limit)),
+            Cell::from(
+                c.utilization
+                    .map(|u| format!("{:.0}%", u))
+                    .unwrap_or_else(|| "-".to_string()),
+            ),
+            Cell::from(st.map(|s| s.cycle.to.to_string()).unwrap_or_else(|| "-".to_string())),
+            Cell::from(money(st.map(|s| s.balance))),
+            Cell::from(money(st.map(|s| s.paid))),
+            Cell::from(money(st.map(|s| s.minimum_remaining))),
+            Cell::from(money(st.map(|s| s.remaining))),
+            Cell::from(due).style(Style::default().fg(color)),
+            Cell::from(interest),
+        ])
+    });
+    let widths = [
+        Constraint::Min(12),
+        Constraint::Length(10),
+        Constraint::Length(10),
+        Constraint::Length(6),
+        Constraint::Length(11),
+        Constraint::Length(10),
+        Constraint::Length(9),
+        Constraint::Length(9),
+        Constraint::Length(10),
+        Constraint::Length(19),
+        Constraint::Length(9),
+    ];
+    let header = Row::new(vec![
+        "Card", "Owed", "Limit", "Used", "Statement", "Stmt bal", "Paid", "Min due", "To pay", "Due", "Interest",
+    ])
+    .style(Style::default().add_modifier(Modifier::BOLD));
+    let table = Table::new(rows, widths).header(header).block(
+        Block::default()
+            .title("Upcoming payments (interest: estimate if the statement isn't paid in full)")
+            .borders(Borders::ALL),
+    );
+    f.render_widget(table, area);
+}
+
+fn draw_networth(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let DateRange { from, to } = app.date_range();
+    let series = app
+        .ledger
+        .networth_series(app.user_id, from, to, app.networth_granularity);
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(62), Constraint::Percentage(38)])
+        .split(area);
+
+    // chart: x is the point index, labels show the dates
+    let points = |v: &[f64]| -> Vec<(f64, f64)> {
+        v.iter().enumerate().map(|(i, y)| (i as f64, *y)).collect()
+    };
+    let net = points(&series.net_worth);
+    let assets = points(&series.assets);
+    let liabilities = points(&series.liabilities);
+    let marker: Vec<(f64, f64)> = series
+        .axis
+        .iter()
+        .position(|d| *d >= app.networth_date)
+        .map(|i| vec![(i as f64, series.net_worth[i])])
+        .unwrap_or_default();
+
+    let mut y_min = 0.0_f64;
+    let mut y_max = 0.0_f64;
+    for v in series
+        .net_worth
+        .iter()
+        .chain(&series.assets)
+        .chain(&series.liabilities)
+    {
+        y_min = y_min.min(*v);
+        y_max = y_max.max(*v);
+    }
+    let pad = ((y_max - y_min) * 0.05).max(1.0);
+    let (y_min, y_max) = (y_min - pad, y_max + pad);
+    let x_max = series.axis.len().saturating_sub(1).max(1) as f64;
+    let x_labels: Vec<Span> = match (series.axis.first(), series.axis.last()) {
+        (Some(first), Some(last)) => vec![
+            Span::raw(first.to_string()),
+            Span::raw(series.axis[series.axis.len() / 2].to_string()),
+            Span::raw(last.to_string()),
+        ],
+        _ => Vec::new(),
+    };
+
+    let datasets = vec![
+        Dataset::default()
+            .name("Net worth")
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Cyan))
+            .data(&net),
+        Dataset::default()
+            .name("Assets")
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Green))
+            .data(&assets),
+        Dataset::default()
+            .name("Liabilities")
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Red))
+            .data(&liabilities),
+        Dataset::default()
+            .marker(symbols::Marker::Block)
+            .graph_type(GraphType::Scatter)
+            .style(Style::default().fg(Color::Yellow))
+            .data(&marker),
+    ];
+    let granularity = match app.networth_granularity {
+        crate::stat::Granularity::Daily => "daily",
+        crate::stat::Granularity::Monthly => "monthly",
+    };
+    let chart = Chart::new(datasets)
+        .block(
+            Block::default()
+                .title(format!("Net Worth ({granularity})"))
+                .borders(Borders::ALL),
+        )
+        .x_axis(Axis::default().bounds([0.0, x_max]).labels(x_labels))
+        .y_axis(Axis::default().bounds([y_min, y_max]).labels(vec![
+            Span::raw(format!("{y_min:.0}")),
+            Span::raw(format!("{:.0}", (y_min + y_max) / 2.0)),
+            Span::raw(format!("{y_max:.0}")),
+        ]));
+    f.render_widget(chart, chunks[0]);
+
+    // breakdown per account at the selected date
+    let date = app.networth_date;
+    let accounts = app.report_balances();
+    let mut total_assets = 0.0;
+    let mut total_liabilities = 0.0;
+    for acc in &accounts {
+        if acc.account_type.is_liability() {
+            total_liabilities -= acc.balance;
+        } else {
+            total_assets += acc.balance;
+        }
+    }
+    let mut rows: Vec<Row> = accounts
+        .iter()
+        .map(|acc| {
+            let kind = if acc.account_type.is_liability() {
+                "Liability"
+            } else {
+                "Asset"
+            };
+            Row::new(vec![
+                acc.name.clone(),
+                kind.to_string(),
+                format!("{:.2}", acc.balance),
+            ])
+        })
+        .collect();
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    rows.push(Row::new(vec![String::new(), String::new(), String::new()]));
+    rows.push(
+        Row::new(vec![
+            "Assets".to_string(),
+            String::new(),
+            format!("{total_assets:.2}"),
+        ])
+        .style(bold),
+    );
+    rows.push(
+        Row::new(vec![
+            "Liabilities".to_string(),
+            String::new(),
+            format!("{total_liabilities:.2}"),
+        ])
+        .style(bold),
+    );
+    rows.push(
+        Row::new(vec![
+            "Net worth".to_string(),
+            String::new(),
+            format!("{:.2}", total_assets - total_liabilities),
+        ])
+        .style(bold),
+    );
+    let widths = [
+        Constraint::Min(12),
+        Constraint::Length(10),
+        Constraint::Length(12),
+    ];
+    let table = Table::new(rows, widths)
+        .header(Row::new(vec!["Account", "Kind", "Balance"]).style(bold))
+        .block(
+            Block::default()
+                .title(format!("Breakdown on {date}{}", report_source(app)))
+                .borders(Borders::ALL),
+        );
+    f.render_widget(table, chunks[1]);
+}
+
+fn draw_category_stats(f: &mut Frame<'_>, area: Rect, app: &App) {
+    if app.chart_view {
+        draw_category_stats_chart(f, area, app);
+        return;
+    }
+    let trend = app.report_top_categories();
+
+    let norm = trend.normalize();
+    // (name, income, outcome, net, percentage_of_spend)
+    let mut data: Vec<(String, f64, f64, f64, f64)> = Vec::new();
+    for i in 0..trend.axis.len() {
+        let name = trend.axis[i].0.clone();
+        let income = trend.income[i];
+        let outcome = trend.outcome[i];
+        let net = trend.summary[i];
+        let perc = norm.outcome[i].abs() * 100.0;
+
+        data.push((name, income, outcome, net, perc));
+    }
+
+    let mut selected_idx = app.selected_category_stats_idx;
+    if !data.is_empty() && selected_idx >= data.len() {
+        selected_idx = data.len() - 1;
+    }
+
+    let rows = data
+        .into_iter()
+        .enumerate()
+        .map(|(idx, (name, inc, out, net, pct))| {
+            let cells = vec![
+                format!("{}", idx + 1),
+                name,
+                format!("{inc:.2}"),
+                format!("{out:.2}"),
+                format!("{net:.2}"),
+                format!("{pct:.1}%"),
+            ];
+            let mut row = Row::new(cells);
+            if idx == selected_idx {
+                row = row.style(Style::default().add_modifier(Modifier::REVERSED));
+            }
+            row
+        });
+
+    let widths = [
+        Constraint::Length(4),
+        Constraint::Length(18),
+        Constraint::Length(12),
+        Constraint::Length(12),
+        Constraint::Length(12),
+        Constraint::Length(8),
+    ];
+
+    let table = Table::new(rows, widths)
+        .header(
+            Row::new(vec!["#", "Category", "Income", "Outcome", "Net", "% Spend"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(
+            Block::default()
+                .title(format!("Top Categories by Outcome{}", report_source(app)))
+                .borders(Borders::ALL),
+        );
+
+    f.render_widget(table, area);
+}
+
+fn draw_transactions(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let transactions = &app.ledger.transaction;
+
+    let mut selected_idx = app.selected_transaction_idx;
+    if !transactions.is_empty() && selected_idx >= transactions.len() {
+        selected_idx = transactions.len() - 1;
+    }
+
+    let mut files: HashMap<TransactionId, usize> = HashMap::new();
+    for a in &app.ledger.attachment {
+        *files.entry(a.tranid).or_default() += 1;
+    }
+
+    let rows = transactions.iter().enumerate().map(|(idx, tx)| {
+        let total_amount: f64 = app
+            .ledger
+            .entry
+            .iter()
+            .filter(|e| e.tranid == tx.id)
+            .map(|e| e.amount)
+            .sum();
+
+        let entry_count = app
+            .ledger
+            .entry
+            .iter()
+            .filter(|e| e.tranid == tx.id)
+            .count();
+
+        let payee_str = tx.receiver.as_deref().unwrap_or("-");
+        let memo_str = tx.desc.as_deref().unwrap_or("-");
+
+        let cells = vec![
+            format!("{}", tx.occur_date),
+            payee_str.to_string(),
+            memo_str.to_string(),
+            format!("{:.2}", total_amount),
+            format!("{}", entry_count),
+            files
+                .get(&tx.id)
+                .map(|n| format!("📎 {n}"))
+                .unwrap_or_default(),
+        ];
+
+        let mut row = Row::new(cells);
+        if idx == selected_idx {
+            row = row.style(Style::default().add_modifier(Modifier::REVERSED));
+        }
+        row
+    });
+
+    let widths = [
+        Constraint::Length(12),
+        Constraint::Length(20),
+        Constraint::Length(20),
+        Constraint::Length(12),
+        Constraint::Length(8),
+        Constraint::Length(6),
+    ];
+
+    let table = Table::new(rows, widths)
+        .header(
+            Row::new(vec!["Date", "Payee", "Memo", "Amount", "Entries", "Files"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::default().title("Transactions").borders(Borders::ALL));
+
+    f.render_widget(table, area);
+}
+
+fn draw_account_stats(f: &mut Frame<'_>, area: Rect, app: &App) {
+    if app.chart_view {
+        draw_account_stats_chart(f, area, app);
+        return;
+    }
+    let trend = app.report_top_accounts();
+
+    let norm = trend.normalize();
+
+    let mut data: Vec<(String, f64, f64, f64, f64)> = Vec::new();
+    for i in 0..trend.axis.len() {
+        let name = trend.axis[i].0.clone();
+        let inc = trend.income[i];
+        let out = trend.outcome[i];
+        let net = trend.summary[i];
+        let pct = norm.outcome[i].abs() * 100.0;
+
+        data.push((name, inc, out, net, pct));
+    }
+
+    let mut selected_idx = app.selected_account_stats_idx;
+    if !data.is_empty() && selected_idx >= data.len() {
+        selected_idx = data.len() - 1;
+    }
+
+    let rows = data
+        .into_iter()
+        .enumerate()
+        .map(|(idx, (name, inc, out, net, pct))| {
+            let cells = vec![
+                format!("{}", idx + 1),
+                name,
+                format!("{inc:.2}"),
+                format!("{out:.2}"),
+                format!("{net:.2}"),
+                format!("{pct:.1}%"),
+            ];
+            let mut row = Row::new(cells);
+            if idx == selected_idx {
+                row = row.style(Style::default().add_modifier(Modifier::REVERSED));
+            }
+            row
+        });
+
+    let widths = [
+        Constraint::Length(4),
+        Constraint::Length(18),
+        Constraint::Length(12),
+        Constraint::Length(12),
+        Constraint::Length(12),
+        Constraint::Length(8),
+    ];
+
+    let table = Table::new(rows, widths)
+        .header(
+            Row::new(vec!["#", "Account", "Income", "Outcome", "Net", "% Spend"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(
+            Block::default()
+                .title(format!("Top Accounts by Outcome{}", report_source(app)))
+                .borders(Borders::ALL),
+        );
+
+    f.render_widget(table, area);
+}
+
+fn draw_trends(f: &mut Frame<'_>, area: Rect, app: &App) {
+    if app.chart_view {
+        draw_trends_chart(f, area, app);
+        return;
+    }
+    let trend = app.report_trends();
+    // statement cycles are only split on the client
+    let source = match app.server_report().is_some_and(|r| r.trends.is_some()) {
+        true => report_source(app),
+        false => "",
+    };
+
+    let mut data: Vec<(String, f64, f64, f64)> = Vec::new();
+    for i in 0..trend.axis.len() {
+        let ym = trend.axis[i].label();
+        let inc = trend.income[i];
+        let out = trend.outcome[i];
+        let net = trend.summary[i];
+        data.push((ym, inc, out, net));
+    }
+
+    let rows = data.into_iter().map(|(ym, inc, out, net)| {
+        let cells = vec![
+            ym,
+            format!("{inc:.2}"),
+            format!("{out:.2}"),
+            format!("{net:.2}"),
+        ];
+        Row::new(cells)
+    });
+
+    let widths = [
+        Constraint::Length(18),
+        Constraint::Length(14),
+        Constraint::Length(14),
+        Constraint::Length(14),
+    ];
+
+    let table = Table::new(rows, widths)
+        .header(
+            Row::new(vec!["Period", "Income", "Outcome", "Net"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(
+            Block::default()
+                .title(format!("{} Trends{source}", app.trend_period.name()))
+                .borders(Borders::ALL),
+        );
+
+    f.render_widget(table, area);
+}
+
+/// one red (or yellow, if only the low end of the band) line per account projected below zero
+fn forecast_warning_lines(forecast: &Forecast) -> Vec<Line<'static>> {
+    forecast
+        .warnings
+        .iter()
+        .map(|w| {
+            let (text, color) = match w.likely {
+                true => ("projected below zero", Color::Red),
+                false => ("may go below zero", Color::Yellow),
+            };
+            Line::from(Span::styled(
+                format!(
+                    "! {} {text} in {} ({:.2})",
+                    w.name,
+                    w.range.label(),
+                    w.balance
+                ),
+                Style::default().fg(color).add_modifier(Modifier::BOLD),
+            ))
+        })
+        .collect()
+}
+
+fn draw_forecast(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let Some(forecast) = app.forecast.as_ref() else {
+        return;
+    };
+    let warnings = forecast_warning_lines(forecast);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(warnings.len().max(1) as u16 + 2),
+            Constraint::Min(8),
+            Constraint::Length(forecast.patterns.len().clamp(1, 8) as u16 + 3),
+        ])
+        .split(area);
+
+    let warning_text = match warnings.is_empty() {
+        true => vec![Line::from(format!(
+            "No account projected below zero in the next {} months",
+            app.forecast_months
+        ))],
+        false => warnings,
+    };
+    let p = Paragraph::new(warning_text).block(
+        Block::default()
+            .title(format!("Forecast from {}", forecast.today))
+            .borders(Borders::ALL),
+    );
+    f.render_widget(p, chunks[0]);
+
+    if app.chart_view {
+        draw_forecast_chart(f, chunks[1], forecast);
+    } else {
+        draw_forecast_tables(f, chunks[1], forecast);
+    }
+
+    // recurring patterns the projection repeats
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let rows = forecast.patterns.iter().map(|p| {
+        Row::new(vec![
+            p.payee.clone(),
+            app.ledger.trans_accountid2name(p.accountid),
+            p.cadence.name().to_string(),
+            format!("{:.2}", p.amount),
+            p.next_date().to_string(),
+        ])
+    });
+    let widths = [
+        Constraint::Min(16),
+        Constraint::Length(16),
+        Constraint::Length(9),
+        Constraint::Length(12),
+        Constraint::Length(12),
+    ];
+    let table = Table::new(rows, widths)
+        .header(Row::new(vec!["Recurring", "Account", "Cadence", "Amount", "Next"]).style(bold))
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(table, chunks[2]);
+}
+
+/// change in percent, `-` when the base is 0
+fn percent_text(delta: &Delta) -> String {
+    match delta.percent {
+        Some(p) => format!("{p:+.1}%"),
+        None => "-".to_string(),
+    }
+}
+
+/// base, current, change and percent cells, the change green when the net got better
+fn delta_cells(delta: &Delta) -> Vec<Cell<'static>> {
+    let color = if delta.change > 0.005 {
+        Color::Green
+    } else if delta.change < -0.005 {
+        Color::Red
+    } else {
+        Color::Reset
+    };
+    vec![
+        Cell::from(format!("{:.2}", delta.base)),
+        Cell::from(format!("{:.2}", delta.current)),
+        Cell::from(format!("{:+.2}", delta.change)).style(Style::default().fg(color)),
+        Cell::from(percent_text(delta)).style(Style::default().fg(color)),
+    ]
+}
+
+fn compare_table<K>(title: &str, rows: &[CompareRow<K>]) -> Table<'static> {
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let rows = rows.iter().map(|r| {
+        let mut cells = vec![Cell::from(r.name.clone())];
+        cells.extend(delta_cells(&r.net));
+        cells.push(Cell::from(r.status.name()));
+        Row::new(cells)
+    });
+    let widths = [
+        Constraint::Min(12),
+        Constraint::Length(11),
+        Constraint::Length(11),
+        Constraint::Length(11),
+        Constraint::Length(9),
+        Constraint::Length(5),
+    ];
+    Table::new(rows, widths)
+        .header(Row::new(vec!["Name", "Base", "Current", "Change", "%", ""]).style(bold))
+        .block(Block::default().title(title.to_string()).borders(Borders::ALL))
+}
+
+fn draw_compare(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let cmp = app.compare();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(6),
+            Constraint::Length(4),
+            Constraint::Min(5),
+        ])
+        .split(area);
+
+    // totals, the biggest movers and the categories that appeared or disappeared
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let totals = [("Income", &cmp.income), ("Outcome", &cmp.outcome), ("Net", &cmp.net)]
+        .into_iter()
+        .map(|(name, delta)| {
+            let mut cells = vec![Cell::from(name)];
+            cells.extend(delta_cells(delta));
+            Row::new(cells)
+        });
+    let names = |rows: Vec<&CompareRow<_>>| match rows.is_empty() {
+        true => "-".to_string(),
+        false => rows
+            .iter()
+            .map(|r| r.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", "),
+    };
+    let movers: Vec<String> = cmp
+        .movers(3)
+        .iter()
+        .map(|r| format!("{} {:+.2}", r.name, r.net.change))
+        .collect();
+    let lines = vec![
+        Line::from(format!("Biggest movers: {}", movers.join(", "))),
+        Line::from(format!(
+            "New: {} | Gone: {}",
+            names(cmp.new_categories().collect()),
+            names(cmp.gone_categories().collect())
+        )),
+    ];
+    let mode = match app.compare_mode {
+        CompareMode::Previous => "previous period",
+        CompareMode::LastYear => "same period last year",
+    };
+    let widths = [
+        Constraint::Length(10),
+        Constraint::Length(12),
+        Constraint::Length(12),
+        Constraint::Length(12),
+        Constraint::Length(9),
+    ];
+    let table = Table::new(totals, widths)
+        .header(Row::new(vec!["", "Base", "Current", "Change", "%"]).style(bold))
+        .block(
+            Block::default()
+                .title(format!(
+                    "{} vs {} ({mode})",
+                    cmp.current.label(),
+                    cmp.base.label()
+                ))
+                .borders(Borders::ALL),
+        );
+    f.render_widget(table, chunks[0]);
+    let p = Paragraph::new(lines)
+        .wrap(Wrap { trim: true })
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(p, chunks[1]);
+
+    let halves = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(chunks[2]);
+    f.render_widget(compare_table("Categories", &cmp.categories), halves[0]);
+    f.render_widget(compare_table("Accounts", &cmp.accounts), halves[1]);
+}
+
+fn draw_subscriptions(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let Some(subs) = app.subscriptions.as_ref() else {
+        return;
+    };
+    let active: Vec<&Subscription> = subs.iter().filter(|s| s.active).collect();
+    let yearly: f64 = active.iter().map(|s| s.annual).sum();
+    let mut lines = vec![Line::from(format!(
+        "{} active, {:.2} a year ({:.2} a month)",
+        active.len(),
+        yearly.abs(),
+        yearly.abs() / 12.0
+    ))];
+    for s in subs.iter().filter(|s| s.active) {
+        if let Some(c) = s.increase {
+            lines.push(Line::from(Span::styled(
+                format!(
+                    "! {} went up from {:.2} to {:.2} on {}",
+                    s.payee,
+                    c.from.abs(),
+                    c.to.abs(),
+                    c.date
+                ),
+                Style::default().fg(Color::Yellow),
+            )));
+        }
+    }
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(lines.len() as u16 + 2), Constraint::Min(5)])
+        .split(area);
+    let p = Paragraph::new(lines).block(
+        Block::default()
+            .title(format!("Recurring charges of the last {SUBSCRIPTION_MONTHS} months"))
+            .borders(Borders::ALL),
+    );
+    f.render_widget(p, chunks[0]);
+
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let rows = subs.iter().map(|s| {
+        let category = match s.categoryid {
+            Some(id) => app.ledger.trans_categoryid2name(id),
+            None => "-".to_string(),
+        };
+        let row = Row::new(vec![
+            s.payee.clone(),
+            app.ledger.trans_accountid2name(s.accountid),
+            category,
+            s.cadence.name().to_string(),
+            format!("{:.2}", s.average.abs()),
+            format!("{:.2}", s.annual.abs()),
+            s.last_date.to_string(),
+            match s.active {
+                true => s.next_date.to_string(),
+                false => "ended".to_string(),
+            },
+            match s.increase {
+                Some(c) => format!("+{:.2}", c.to.abs() - c.from.abs()),
+                None => String::new(),
+            },
+        ]);
+        match s.active {
+            true => row,
+            false => row.style(Style::default().fg(Color::DarkGray)),
+        }
+    });
+    let widths = [
+        Constraint::Min(14),
+        Constraint::Length(12),
+        Constraint::Length(12),
+        Constraint::Length(8),
+        Constraint::Length(10),
+        Constraint::Length(10),
+        Constraint::Length(11),
+        Constraint::Length(11),
+        Constraint::Length(8),
+    ];
+    let header = Row::new(vec![
+        "Payee", "Account", "Category", "Cadence", "Average", "Per year", "Last", "Next", "Change",
+    ])
+    .style(bold);
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(table, chunks[1]);
+}
+
+/// `width` cells of a bar filled to `percent`
+fn progress_bar(percent: f64, width: usize) -> String {
+    let filled = ((percent / 100.0 * width as f64).round() as usize).min(width);
+    format!("{}{}", "█".repeat(filled), "░".repeat(width - filled))
+}
+
+fn draw_goals(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let progress = app.goal_progress();
+    let saved: f64 = progress.iter().map(|p| p.saved.clamp(0.0, p.target)).sum();
+    let target: f64 = progress.iter().map(|p| p.target).sum();
+    let behind = progress
+        .iter()
+        .filter(|p| matches!(p.state, GoalState::Behind | GoalState::Overdue))
+        .count();
+    let mut lines = vec![Line::from(format!(
+        "{} goals, {:.2} of {:.2} saved, {} behind",
+        progress.len(),
+        saved,
+        target,
+        behind
+    ))];
+    if progress.is_empty() {
+        lines.push(Line::from("No goals yet, n to create one"));
+    }
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(lines.len() as u16 + 2), Constraint::Min(5)])
+        .split(area);
+    let p = Paragraph::new(lines).block(Block::default().title("Savings goals").borders(Borders::ALL));
+    f.render_widget(p, chunks[0]);
+
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let rows = progress.iter().enumerate().map(|(idx, p)| {
+        let color = match p.state {
+            GoalState::Reached | GoalState::OnTrack => Color::Green,
+            GoalState::Behind | GoalState::Overdue => Color::Red,
+            GoalState::NoDeadline => Color::Reset,
+        };
+        let row = Row::new(vec![
+            Cell::from(p.name.clone()),
+            Cell::from(format!("{} {:>3.0}%", progress_bar(p.percent, 12), p.percent)),
+            Cell::from(format!("{:.2}", p.saved)),
+            Cell::from(format!("{:.2}", p.target)),
+            Cell::from(p.deadline.map(|d| d.to_string()).unwrap_or_else(|| "-".to_string())),
+            Cell::from(
+                p.required_monthly
+                    .filter(|_| p.state != GoalState::Reached)
+                    .map(|v| format!("{:.2}", v))
+                    .unwrap_or_else(|| "-".to_string()),
+            ),
+            Cell::from(format!("{:.2}", p.recent_monthly)),
+            Cell::from(
+                p.projected
+                    .map(|d| d.to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+            ),
+            Cell::from(p.state.name()).style(Style::default().fg(color)),
+        ]);
+        match idx == app.selected_goal_idx.min(progress.len().saturating_sub(1)) {
+            true => row.style(Style::default().add_modifier(Modifier::REVERSED)),
+            false => row,
+        }
+    });
+    let widths = [
+        Constraint::Min(14),
+        Constraint::Length(17),
+        Constraint::Length(10),
+        Constraint::Length(10),
+        Constraint::Length(11),
+        Constraint::Length(11),
+        Constraint::Length(10),
+        Constraint::Length(11),
+        Constraint::Length(11),
+    ];
+    let header = Row::new(vec![
+        "Goal", "Progress", "Saved", "Target", "Deadline", "Needed/mo", "Pace/mo", "Reached by", "Status",
+    ])
+    .style(bold);
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(table, chunks[1]);
+}
+
+fn draw_loans(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let loans = app.loan_status();
+    if loans.is_empty() {
+        let p = Paragraph::new(
+            "No loans with terms yet. Create a Loan account (c on Accounts), then set its principal, rate and term with t.",
+        )
+        .wrap(Wrap { trim: false })
+        .block(Block::default().title("Loans").borders(Borders::ALL));
+        f.render_widget(p, area);
+        return;
+    }
+    let selected = app.selected_loan_idx.min(loans.len() - 1);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(loans.len() as u16 + 3),
+            Constraint::Length(4),
+            Constraint::Min(5),
+        ])
+        .split(area);
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let today = chrono::Local::now().date_naive();
+    let date = |d: Option<chrono::NaiveDate>| d.map(|d| d.to_string()).unwrap_or_else(|| "-".to_string());
+
+    let rows = loans.iter().enumerate().map(|(idx, l)| {
+        let next = match l.next_date {
+            Some(d) if d < today => Cell::from(format!("{} overdue", d)).style(Style::default().fg(Color::Red)),
+            Some(d) => Cell::from(d.to_string()),
+            None => Cell::from("paid off").style(Style::default().fg(Color::Green)),
+        };
+        let row = Row::new(vec![
+            Cell::from(l.name.clone()),
+            Cell::from(format!("{:.2}", l.owed)),
+            Cell::from(format!("{:.2}", l.terms.principal)),
+            Cell::from(format!("{:.2}%", l.terms.rate)),
+            Cell::from(format!("{:.2} {}", l.payment, l.terms.frequency.to_cloud())),
+            next,
+            Cell::from(format!("{:.2} / {:.2}", l.next_principal, l.next_interest)),
+            Cell::from(date(l.payoff)),
+            Cell::from(format!("{:.2}", l.interest_left)),
+        ]);
+        match idx == selected {
+            true => row.style(Style::default().add_modifier(Modifier::REVERSED)),
+            false => row,
+        }
+    });
+    let widths = [
+        Constraint::Min(14),
+        Constraint::Length(10),
+        Constraint::Length(10),
+        Constraint::Length(7),
+        Constraint::Length(17),
+        Constraint::Length(18),
+        Constraint::Length(17),
+        Constraint::Length(11),
+        Constraint::Length(13),
+    ];
+    let header = Row::new(vec![
+        "Loan", "Owed", "Principal", "Rate", "Payment", "Next payment", "Principal/int.", "Payoff", "Interest left",
+    ])
+    .style(bold);
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(Block::default().title("Loans").borders(Borders::ALL));
+    f.render_widget(table, chunks[0]);
+
+    // payoff with the extra payment against the regular schedule
+    let loan = &loans[selected];
+    let summary = match (loan.extra > 0.0, loan.payoff, loan.extra_payoff) {
+        (_, _, None) if loan.owed > 0.0 => "The payment doesn't cover the interest, the loan never pays off".to_string(),
+        (false, _, _) => format!(
+            "{} payments left, paid off {}, {:.2} interest to go. + / - adds an extra payment",
+            loan.schedule.len(),
+            date(loan.payoff),
+            loan.interest_left
+        ),
+        (true, _, _) => {
+            let sooner = loan
+                .payoff
+                .zip(loan.extra_payoff)
+                .map(|(a, b)| (a - b).num_days())
+                .unwrap_or(0);
+            format!(
+                "With {:.2} extra per payment: paid off {} ({} days sooner), {:.2} interest saved",
+                loan.extra,
+                date(loan.extra_payoff),
+                sooner,
+                loan.interest_left - loan.extra_interest_left
+            )
+        }
+    };
+    let pace = match loan.scheduled_owed - loan.owed {
+        d if d >= 0.005 => format!("{:.2} ahead of schedule", d),
+        d if d <= -0.005 => format!("{:.2} behind schedule", -d),
+        _ => "on schedule".to_string(),
+    };
+    let p = Paragraph::new(vec![
+        Line::from(summary),
+        Line::from(format!(
+            "{} payments recorded, {:.2} of the principal paid, {}",
+            loan.payments_made, loan.paid_off, pace
+        )),
+    ])
+    .block(Block::default().borders(Borders::ALL));
+    f.render_widget(p, chunks[1]);
+
+    let rows = loan.schedule.iter().map(|p| {
+        Row::new(vec![
+            p.number.to_string(),
+            p.date.to_string(),
+            format!("{:.2}", p.payment),
+            format!("{:.2}", p.principal),
+            format!("{:.2}", p.interest),
+            format!("{:.2}", p.balance),
+        ])
+    });
+    let widths = [
+        Constraint::Length(6),
+        Constraint::Length(11),
+        Constraint::Length(11),
+        Constraint::Length(11),
+        Constraint::Length(11),
+        Constraint::Length(12),
+    ];
+    let table = Table::new(rows, widths)
+        .header(Row::new(vec!["#", "Date", "Payment", "Principal", "Interest", "Balance"]).style(bold))
+        .block(
+            Block::default()
+                .title(format!("Amortization schedule of {}", loan.name))
+                .borders(Borders::ALL),
+        );
+    f.render_widget(table, chunks[2]);
+}
+
+fn draw_people(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let contacts = app.contact_balances();
+    if contacts.is_empty() {
+        let p = Paragraph::new(
+            "No contacts yet. Add one with n, then split an expense by giving the contact's share its own entry: the contact as account, the expense category, their share as a positive amount.",
+        )
+        .wrap(Wrap { trim: false })
+        .block(Block::default().title("People").borders(Borders::ALL));
+        f.render_widget(p, area);
+        return;
+    }
+    let selected = app.selected_contact_idx.min(contacts.len() - 1);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(5), Constraint::Length(3)])
+        .split(area);
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let amount = |v: f64, color: Color| match v >= 0.005 {
+        true => Cell::from(format!("{:.2}", v)).style(Style::default().fg(color)),
+        false => Cell::from("-"),
+    };
+
+    let rows = contacts.iter().enumerate().map(|(idx, c)| {
+        let row = Row::new(vec![
+            Cell::from(c.name.clone()),
+            Cell::from(c.email.clone().unwrap_or_default()),
+            amount(c.receivable(), Color::Green),
+            amount(c.payable(), Color::Red),
+        ]);
+        match idx == selected {
+            true => row.style(Style::default().add_modifier(Modifier::REVERSED)),
+            false => row,
+        }
+    });
+    let widths = [
+        Constraint::Min(16),
+        Constraint::Min(24),
+        Constraint::Length(12),
+        Constraint::Length(12),
+    ];
+    let table = Table::new(rows, widths)
+        .header(Row::new(vec!["Name", "Email", "Owes you", "You owe"]).style(bold))
+        .block(Block::default().title("People").borders(Borders::ALL));
+    f.render_widget(table, chunks[0]);
+
+    let receivable: f64 = contacts.iter().map(|c| c.receivable()).sum();
+    let payable: f64 = contacts.iter().map(|c| c.payable()).sum();
+    let p = Paragraph::new(format!(
+        "Owed to you {:.2}, you owe {:.2}, net {:.2}",
+        receivable,
+        payable,
+        receivable - payable
+    ))
+    .block(Block::default().borders(Borders::ALL));
+    f.render_widget(p, chunks[1]);
+}
+
+fn gain_cell(v: f64) -> Cell<'static> {
+    let style = match v {
+        v if v >= 0.005 => Style::default().fg(Color::Green),
+        v if v <= -0.005 => Style::default().fg(Color::Red),
+        _ => Style::default(),
+    };
+    Cell::from(format!("{:.2}", v)).style(style)
+}
+
+fn draw_investments(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let portfolios = app.portfolios();
+    if portfolios.is_empty() {
+        let p = Paragraph::new(
+            "No investment accounts yet. Create an Investment account (c on Accounts), then record buys, sells, dividends and splits with t.",
+        )
+        .wrap(Wrap { trim: false })
+        .block(Block::default().title("Investments").borders(Borders::ALL));
+        f.render_widget(p, area);
+        return;
+    }
+    let selected = app.selected_portfolio_idx.min(portfolios.len() - 1);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(portfolios.len() as u16 + 3),
+            Constraint::Min(5),
+            Constraint::Length(10),
+        ])
+        .split(area);
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+
+    let rows = portfolios.iter().enumerate().map(|(idx, p)| {
+        let row = Row::new(vec![
+            Cell::from(p.name.clone()),
+            Cell::from(cost_basis_name(p.cost_basis)),
+            Cell::from(format!("{:.2}", p.cash)),
+            Cell::from(format!("{:.2}", p.market_value)),
+            Cell::from(format!("{:.2}", p.cost)),
+            gain_cell(p.unrealized),
+            gain_cell(p.realized),
+            Cell::from(format!("{:.2}", p.dividends)),
+            Cell::from(format!("{:.2}", p.cash + p.market_value)),
+        ]);
+        match idx == selected {
+            true => row.style(Style::default().add_modifier(Modifier::REVERSED)),
+            false => row,
+        }
+    });
+    let widths = [
+        Constraint::Min(14),
+        Constraint::Length(8),
+        Constraint::Length(11),
+        Constraint::Length(13),
+        Constraint::Length(11),
+        Constraint::Length(11),
+        Constraint::Length(10),
+        Constraint::Length(10),
+        Constraint::Length(12),
+    ];
+    let header = Row::new(vec![
+        "Account", "Method", "Cash", "Market value", "Book cost", "Unrealized", "Realized", "Dividends", "Total",
+    ])
+    .style(bold);
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(Block::default().title("Investment accounts").borders(Borders::ALL));
+    f.render_widget(table, chunks[0]);
+
+    let portfolio = &portfolios[selected];
+    let rows = portfolio.holdings.iter().map(|h| {
+        let price = match (h.price, h.price_date) {
+            (Some(p), Some(d)) => format!("{} ({})", (p * 1e6).round() / 1e6, d),
+            _ => "-".to_string(),
+        };
+        let percent = match h.cost > 0.0 {
+            true => format!("{:+.1}%", h.unrealized / h.cost * 100.0),
+            false => "-".to_string(),
+        };
+        Row::new(vec![
+            Cell::from(h.symbol.clone()),
+            Cell::from(h.name.clone().unwrap_or_default()),
+            Cell::from(format!("{}", (h.quantity * 1e6).round() / 1e6)),
+            Cell::from(price),
+            Cell::from(format!("{:.2}", h.market_value)),
+            Cell::from(format!("{:.2}", h.cost)),
+            gain_cell(h.unrealized),
+            Cell::from(percent),
+            gain_cell(h.realized),
+            Cell::from(format!("{:.2}", h.dividends)),
+        ])
+    });
+    let widths = [
+        Constraint::Length(9),
+        Constraint::Min(10),
+        Constraint::Length(10),
+        Constraint::Length(22),
+        Constraint::Length(13),
+        Constraint::Length(11),
+        Constraint::Length(11),
+        Constraint::Length(8),
+        Constraint::Length(10),
+        Constraint::Length(10),
+    ];
+    let header = Row::new(vec![
+        "Symbol", "Name", "Units", "Price (date)", "Market value", "Book cost", "Unrealized", "%", "Realized", "Dividends",
+    ])
+    .style(bold);
+    let table = Table::new(rows, widths).header(header).block(
+        Block::default()
+            .title(format!(
+                "Holdings of {} ({} cost)",
+                portfolio.name,
+                cost_basis_name(portfolio.cost_basis)
+            ))
+            .borders(Borders::ALL),
+    );
+    f.render_widget(table, chunks[1]);
+
+    let bottom = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(45), Constraint::Percentage(55)])
+        .split(chunks[2]);
+    let rows = portfolio.holdings.iter().flat_map(|h| {
+        h.lots.iter().map(|l| {
+            Row::new(vec![
+                h.symbol.clone(),
+                l.date.to_string(),
+                format!("{}", (l.quantity * 1e6).round() / 1e6),
+                format!("{:.2}", l.cost),
+                format!("{:.4}", l.cost / l.quantity),
+            ])
+        })
+    });
+    let widths = [
+        Constraint::Length(9),
+        Constraint::Length(11),
+        Constraint::Length(10),
+        Constraint::Length(11),
+        Constraint::Length(10),
+    ];
+    let table = Table::new(rows, widths)
+        .header(Row::new(vec!["Symbol", "Bought", "Units", "Cost", "Per unit"]).style(bold))
+        .block(Block::default().title("Open lots").borders(Borders::ALL));
+    f.render_widget(table, bottom[0]);
+
+    let mut sales: Vec<(&str, &crate::stat::Sale)> = portfolio
+        .holdings
+        .iter()
+        .flat_map(|h| h.sales.iter().map(move |s| (h.symbol.as_str(), s)))
+        .collect();
+    sales.sort_by_key(|(_, s)| std::cmp::Reverse(s.date));
+    let rows = sales.into_iter().map(|(symbol, s)| {
+        Row::new(vec![
+            Cell::from(s.date.to_string()),
+            Cell::from(symbol.to_string()),
+            Cell::from(format!("{}", (s.quantity * 1e6).round() / 1e6)),
+            Cell::from(format!("{:.2}", s.proceeds)),
+            Cell::from(format!("{:.2}", s.cost)),
+            gain_cell(s.gain),
+        ])
+    });
+    let widths = [
+        Constraint::Length(11),
+        Constraint::Length(9),
+        Constraint::Length(10),
+        Constraint::Length(11),
+        Constraint::Length(11),
+        Constraint::Length(10),
+    ];
+    let table = Table::new(rows, widths)
+        .header(Row::new(vec!["Sold", "Symbol", "Units", "Proceeds", "Cost", "Gain"]).style(bold))
+        .block(Block::default().title("Realized gains").borders(Borders::ALL));
+    f.render_widget(table, bottom[1]);
+}
+
+fn draw_taxes(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let summary = app.tax_summary();
+    if summary.lines.is_empty() {
+        let p = Paragraph::new(format!(
+            "No entries in {} on categories with a tax line. Press l to map categories to tax lines (medical, charitable, business expenses, rental income...), ←/→ to change the year.",
+            summary.year
+        ))
+        .wrap(Wrap { trim: false })
+        .block(Block::default().title(format!("Tax summary {}", summary.year)).borders(Borders::ALL));
+        f.render_widget(p, area);
+        return;
+    }
+    let selected = app.selected_tax_line_idx.min(summary.lines.len() - 1);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(summary.lines.len() as u16 + 5),
+            Constraint::Min(5),
+        ])
+        .split(area);
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+
+    let mut rows: Vec<Row> = summary
+        .lines
+        .iter()
+        .enumerate()
+        .map(|(idx, l)| {
+            let splits = l.items.iter().filter(|i| i.split_of.is_some()).count();
+            let row = Row::new(vec![
+                l.line.name().to_string(),
+                if l.line.is_income() { "income" } else { "deduction" }.to_string(),
+                l.items.len().to_string(),
+                splits.to_string(),
+                format!("{:.2}", l.total),
+            ]);
+            match idx == selected {
+                true => row.style(Style::default().add_modifier(Modifier::REVERSED)),
+                false => row,
+            }
+        })
+        .collect();
+    rows.push(Row::new(vec![
+        "Total income".to_string(),
+        String::new(),
+        String::new(),
+        String::new(),
+        format!("{:.2}", summary.income),
+    ]).style(bold));
+    rows.push(Row::new(vec![
+        "Total deductions and expenses".to_string(),
+        String::new(),
+        String::new(),
+        String::new(),
+        format!("{:.2}", summary.deductions),
+    ]).style(bold));
+    let widths = [
+        Constraint::Min(30),
+        Constraint::Length(10),
+        Constraint::Length(8),
+        Constraint::Length(7),
+        Constraint::Length(12),
+    ];
+    let table = Table::new(rows, widths)
+        .header(Row::new(vec!["Tax line", "Kind", "Entries", "Splits", "Total"]).style(bold))
+        .block(
+            Block::default()
+                .title(format!("Tax summary {}", summary.year))
+                .borders(Borders::ALL),
+        );
+    f.render_widget(table, chunks[0]);
+
+    let line = &summary.lines[selected];
+    let bottom = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
+        .split(chunks[1]);
+    let rows = line
+        .categories
+        .iter()
+        .map(|(name, total)| Row::new(vec![name.clone(), format!("{:.2}", total)]));
+    let table = Table::new(rows, [Constraint::Min(12), Constraint::Length(12)])
+        .header(Row::new(vec!["Category", "Total"]).style(bold))
+        .block(Block::default().title(line.line.name()).borders(Borders::ALL));
+    f.render_widget(table, bottom[0]);
+
+    // a split entry shows the whole transaction it is part of
+    let rows = line.items.iter().map(|i| {
+        Row::new(vec![
+            i.date.to_string(),
+            i.payee.clone().unwrap_or_default(),
+            i.category.clone(),
+            i.account.clone(),
+            format!("{:.2}", i.amount),
+            i.split_of.map(|v| format!("of {:.2}", v)).unwrap_or_default(),
+            i.memo.clone().unwrap_or_default(),
+        ])
+    });
+    let widths = [
+        Constraint::Length(11),
+        Constraint::Length(18),
+        Constraint::Length(14),
+        Constraint::Length(12),
+        Constraint::Length(10),
+        Constraint::Length(12),
+        Constraint::Min(10),
+    ];
+    let table = Table::new(rows, widths)
+        .header(Row::new(vec!["Date", "Payee", "Category", "Account", "Amount", "Split", "Memo"]).style(bold))
+        .block(Block::default().title("Supporting entries").borders(Borders::ALL));
+    f.render_widget(table, bottom[1]);
+}
+
+fn draw_forecast_tables(f: &mut Frame<'_>, area: Rect, forecast: &Forecast) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(area);
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let band = |lo: f64, hi: f64| format!("{lo:.0}~{hi:.0}");
+
+    // every account together, per month
+    let rows = forecast.total.iter().map(|p| {
+        Row::new(vec![
+            p.range.label(),
+            format!("{:.2}", p.net),
+            band(p.net_low, p.net_high),
+            format!("{:.2}", p.balance),
+            band(p.balance_low, p.balance_high),
+        ])
+    });
+    let widths = [
+        Constraint::Length(16),
+        Constraint::Length(10),
+        Constraint::Length(14),
+        Constraint::Length(10),
+        Constraint::Length(14),
+    ];
+    let table = Table::new(rows, widths)
+        .header(Row::new(vec!["Period", "Net", "Net range", "Total", "Total range"]).style(bold))
+        .block(
+            Block::default()
+                .title("Projected cash flow (80% range)")
+                .borders(Borders::ALL),
+        );
+    f.render_widget(table, chunks[0]);
+
+    // each account at the end of the horizon
+    let rows = forecast.accounts.iter().map(|a| {
+        let (end, low, high) = a
+            .points
+            .last()
+            .map(|p| (p.balance, p.balance_low, p.balance_high))
+            .unwrap_or((a.balance, a.balance, a.balance));
+        let mut row = Row::new(vec![
+            a.name.clone(),
+            format!("{:.2}", a.balance),
+            format!("{end:.2}"),
+            band(low, high),
+        ]);
+        if !a.account_type.is_liability() && a.points.iter().any(|p| p.balance < 0.0) {
+            row = row.style(Style::default().fg(Color::Red));
+        } else if !a.account_type.is_liability() && low < 0.0 {
+            row = row.style(Style::default().fg(Color::Yellow));
+        }
+        row
+    });
+    let widths = [
+        Constraint::Min(8),
+        Constraint::Length(10),
+        Constraint::Length(10),
+        Constraint::Length(13),
+    ];
+    let end = forecast
+        .total
+        .last()
+        .map(|p| p.range.to.to_string())
+        .unwrap_or_default();
+    let table = Table::new(rows, widths)
+        .header(Row::new(vec!["Account", "Today", "Projected", "Range"]).style(bold))
+        .block(
+            Block::default()
+                .title(format!("Balances on {end}"))
+                .borders(Borders::ALL),
+        );
+    f.render_widget(table, chunks[1]);
+}
+
+fn draw_forecast_chart(f: &mut Frame<'_>, area: Rect, forecast: &Forecast) {
+    // total balance with its band, x is the bucket index (0 = today)
+    let mut mid = vec![(0.0, forecast.accounts.iter().map(|a| a.balance).sum::<f64>())];
+    let (mut low, mut high) = (mid.clone(), mid.clone());
+    for (i, p) in forecast.total.iter().enumerate() {
+        let x = (i + 1) as f64;
+        mid.push((x, p.balance));
+        low.push((x, p.balance_low));
+        high.push((x, p.balance_high));
+    }
+    let x_max = mid.len().saturating_sub(1).max(1) as f64;
+    let zero = vec![(0.0, 0.0), (x_max, 0.0)];
+    let mut y_min = 0.0_f64;
+    let mut y_max = 0.0_f64;
+    for (_, v) in low.iter().chain(&high) {
+        y_min = y_min.min(*v);
+        y_max = y_max.max(*v);
+    }
+    let pad = ((y_max - y_min) * 0.05).max(1.0);
+    let (y_min, y_max) = (y_min - pad, y_max + pad);
+    let x_labels: Vec<Span> = match forecast.total.last() {
+        Some(last) => vec![
+            Span::raw(forecast.today.to_string()),
+            Span::raw(last.range.to.to_string()),
+        ],
+        None => Vec::new(),
+    };
+    let datasets = vec![
+        Dataset::default()
+            .name("Projected")
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Cyan))
+            .data(&mid),
+        Dataset::default()
+            .name("Low")
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Red))
+            .data(&low),
+        Dataset::default()
+            .name("High")
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Green))
+            .data(&high),
+        Dataset::default()
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::DarkGray))
+            .data(&zero),
+    ];
+    let chart = Chart::new(datasets)
+        .block(
+            Block::default()
+                .title("Projected total balance (80% range)")
+                .borders(Borders::ALL),
+        )
+        .x_axis(Axis::default().bounds([0.0, x_max]).labels(x_labels))
+        .y_axis(Axis::default().bounds([y_min, y_max]).labels(vec![
+            Span::raw(format!("{y_min:.0}")),
+            Span::raw(format!("{:.0}", (y_min + y_max) / 2.0)),
+            Span::raw(format!("{y_max:.0}")),
+        ]));
+    f.render_widget(chart, area);
+}
+
+// colors for series / stacked segments, "Other" is drawn gray
+const CHART_COLORS: [Color; 6] = [
+    Color::Cyan,
+    Color::Yellow,
+    Color::Magenta,
+    Color::Green,
+    Color::Blue,
+    Color::LightRed,
+];
+
+/// title suffix of tables filled from server reports
+fn report_source(app: &App) -> &'static str {
+    match app.server_report() {
+        Some(_) => " (server)",
+        None => "",
+    }
+}
+
+fn month_label((y, m): (i32, u32)) -> String {
+    format!("{y:04}-{m:02}")
+}
+
+fn draw_trends_chart(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let timephase = app.date_range();
+    let trend = app
+        .ledger
+        .data_periodtrend(app.user_id, timephase, app.trend_period, None, None);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+        .split(area);
+
+    // income / outcome / net lines, x is the bucket index
+    let points = |v: &[f64]| -> Vec<(f64, f64)> {
+        v.iter().enumerate().map(|(i, y)| (i as f64, *y)).collect()
+    };
+    let inc = points(&trend.income);
+    let out = points(&trend.outcome);
+    let net = points(&trend.summary);
+    let mut y_min = 0.0_f64;
+    let mut y_max = 0.0_f64;
+    for v in trend
+        .income
+        .iter()
+        .chain(&trend.outcome)
+        .chain(&trend.summary)
+    {
+        y_min = y_min.min(*v);
+        y_max = y_max.max(*v);
+    }
+    let pad = ((y_max - y_min) * 0.05).max(1.0);
+    let (y_min, y_max) = (y_min - pad, y_max + pad);
+    let x_max = trend.axis.len().saturating_sub(1).max(1) as f64;
+    let x_labels: Vec<Span> = match (trend.axis.first(), trend.axis.last()) {
+        (Some(first), Some(last)) => vec![
+            Span::raw(first.label()),
+            Span::raw(trend.axis[trend.axis.len() / 2].label()),
+            Span::raw(last.label()),
+        ],
+        _ => Vec::new(),
+    };
+    let datasets = vec![
+        Dataset::default()
+            .name("Income")
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Green))
+            .data(&inc),
+        Dataset::default()
+            .name("Outcome")
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Red))
+            .data(&out),
+        Dataset::default()
+            .name("Net")
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Cyan))
+            .data(&net),
+    ];
+    let chart = Chart::new(datasets)
+        .block(
+            Block::default()
+                .title(format!("{} Trends", app.trend_period.name()))
+                .borders(Borders::ALL),
+        )
+        .x_axis(Axis::default().bounds([0.0, x_max]).labels(x_labels))
+        .y_axis(Axis::default().bounds([y_min, y_max]).labels(vec![
+            Span::raw(format!("{y_min:.0}")),
+            Span::raw(format!("{:.0}", (y_min + y_max) / 2.0)),
+            Span::raw(format!("{y_max:.0}")),
+        ]));
+    f.render_widget(chart, chunks[0]);
+
+    // spend per bucket stacked by top categories, the rest goes to "Other"
+    let top = app
+        .ledger
+        .top_category(app.user_id, timephase, None, CHART_COLORS.len(), Some(true));
+    let mut stacks: Vec<(String, Color, Vec<f64>)> = Vec::new();
+    let mut covered = vec![0.0; trend.axis.len()];
+    for (idx, (name, cat)) in top.axis.iter().enumerate() {
+        let cat_trend = app.ledger.data_periodtrend(
+            app.user_id,
+            timephase,
+            app.trend_period,
+            None,
+            Some(*cat),
+        );
+        let spend: Vec<f64> = cat_trend.outcome.iter().map(|v| v.abs()).collect();
+        if spend.iter().all(|v| *v == 0.0) {
+            continue;
+        }
+        for (c, v) in covered.iter_mut().zip(&spend) {
+            *c += *v;
+        }
+        stacks.push((name.clone(), CHART_COLORS[idx], spend));
+    }
+    let other: Vec<f64> = trend
+        .outcome
+        .iter()
+        .zip(&covered)
+        .map(|(total, c)| (total.abs() - c).max(0.0))
+        .collect();
+    if other.iter().any(|v| *v > 0.005) {
+        stacks.push(("Other".to_string(), Color::Gray, other));
+    }
+    let labels: Vec<String> = trend.axis.iter().map(|r| r.label()).collect();
+    draw_stacked_bars(f, chunks[1], "Spend by Category", &labels, &stacks);
+}
+
+///vertical bars, one per label, each split into colored segments from bottom to top
+fn draw_stacked_bars(
+    f: &mut Frame<'_>,
+    area: Rect,
+    title: &str,
+    labels: &[String],
+    stacks: &[(String, Color, Vec<f64>)],
+) {
+    let block = Block::default()
+        .title(title.to_string())
+        .borders(Borders::ALL);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    if inner.height < 4 || inner.width == 0 || labels.is_empty() {
+        return;
+    }
+
+    // legend on the first line, month labels on the last
+    let legend: Vec<Span> = stacks
+        .iter()
+        .flat_map(|(name, color, _)| {
+            vec![
+                Span::styled("■ ", Style::default().fg(*color)),
+                Span::raw(format!("{name}  ")),
+            ]
+        })
+        .collect();
+    f.render_widget(
+        Paragraph::new(Line::from(legend)),
+        Rect::new(inner.x, inner.y, inner.width, 1),
+    );
+    let bar_top = inner.y + 1;
+    let bar_height = inner.height - 2;
+    let label_y = inner.y + inner.height - 1;
+
+    let totals: Vec<f64> = (0..labels.len())
+        .map(|i| {
+            stacks
+                .iter()
+                .map(|s| s.2.get(i).copied().unwrap_or(0.0))
+                .sum()
+        })
+        .collect();
+    let max_total = totals.iter().cloned().fold(0.0_f64, f64::max);
+    let col_width = (inner.width / labels.len() as u16).max(1);
+    let bar_width = if col_width > 2 {
+        col_width - 1
+    } else {
+        col_width
+    };
+
+    let buf = f.buffer_mut();
+    for (i, label) in labels.iter().enumerate() {
+        let x0 = inner.x + i as u16 * col_width;
+        if x0 + bar_width > inner.x + inner.width {
+            break;
+        }
+        if max_total > 0.0 {
+            // cumulative rounding so segments always add up to the bar height
+            let mut cum = 0.0;
+            let mut drawn = 0u16;
+            for (_, color, values) in stacks {
+                cum += values.get(i).copied().unwrap_or(0.0);
+                let top = ((cum / max_total) * bar_height as f64).round() as u16;
+                for h in drawn..top.min(bar_height) {
+                    let y = bar_top + bar_height - 1 - h;
+                    for x in x0..x0 + bar_width {
+                        buf[(x, y)].set_symbol("█").set_fg(*color);
+                    }
+                }
+                drawn = drawn.max(top);
+            }
+        }
+        // label shows the month (MM), or as much of it as fits
+        let short = label.rsplit('-').next().unwrap_or(label);
+        buf.set_stringn(x0, label_y, short, col_width as usize, Style::default());
+    }
+}
+
+///share of spend as horizontal bars (from `Trend::normalize`) and a monthly sparkline for the selected row
+fn draw_share_chart(
+    f: &mut Frame<'_>,
+    area: Rect,
+    title: &str,
+    trend: &Trend<(String, i64)>,
+    selected_idx: usize,
+    monthly: &Trend<(i32, u32)>,
+) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+        .split(area);
+
+    let norm = trend.normalize();
+    let bars: Vec<Bar> = trend
+        .axis
+        .iter()
+        .enumerate()
+        .map(|(idx, (name, _))| {
+            let pct = norm.outcome[idx] * 100.0;
+            let mut style = Style::default().fg(CHART_COLORS[idx % CHART_COLORS.len()]);
+            if idx == selected_idx {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            // percentage goes in the label, small bars are too short to hold it
+            Bar::default()
+                .label(Line::from(format!("{name} {pct:.1}%")))
+                .value((pct * 10.0).round() as u64)
+                .text_value(String::new())
+                .style(style)
+        })
+        .collect();
+    let barchart = BarChart::default()
+        .block(
+            Block::default()
+                .title(format!("{title} (% of spend)"))
+                .borders(Borders::ALL),
+        )
+        .direction(Direction::Horizontal)
+        .bar_width(1)
+        .bar_gap(0)
+        .max(1000)
+        .data(BarGroup::default().bars(&bars));
+    f.render_widget(barchart, chunks[0]);
+
+    let right = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(6), Constraint::Min(3)])
+        .split(chunks[1]);
+    let name = trend.axis.get(selected_idx).map(|(n, _)| n.clone()).unwrap_or_default();
+    let spend: Vec<u64> = monthly
+        .outcome
+        .iter()
+        .map(|v| v.abs().round() as u64)
+        .collect();
+    let total: f64 = monthly.outcome.iter().sum();
+    let peak = monthly
+        .outcome
+        .iter()
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(b.1))
+        .map(|(i, v)| format!("{} ({:.2})", month_label(monthly.axis[i]), v))
+        .unwrap_or_else(|| "-".to_string());
+    let text = format!(
+        "Selected: {name}\nOutcome in range: {total:.2}\nBiggest month: {peak}\nMonths: {}",
+        monthly.axis.len()
+    );
+    f.render_widget(
+        Paragraph::new(text).block(Block::default().title("Selected").borders(Borders::ALL)),
+        right[0],
+    );
+    let sparkline = Sparkline::default()
+        .block(
+            Block::default()
+                .title("Monthly spend")
+                .borders(Borders::ALL),
+        )
+        .data(&spend)
+        .style(Style::default().fg(Color::Yellow));
+    f.render_widget(sparkline, right[1]);
+}
+
+fn draw_category_stats_chart(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let timephase = app.date_range();
+    let trend = app
+        .ledger
+        .top_category(app.user_id, timephase, None, 10, Some(true));
+    let selected_idx = app
+        .selected_category_stats_idx
+        .min(trend.axis.len().saturating_sub(1));
+    let monthly = match app.selected_stats_category() {
+        Some(cat) => app
+            .ledger
+            .data_linetrend(app.user_id, timephase, None, Some(cat)),
+        None => app
+            .ledger
+            .data_linetrend(app.user_id, timephase, None, None),
+    };
+    draw_share_chart(f, area, "Top Categories", &trend, selected_idx, &monthly);
+}
+
+fn draw_account_stats_chart(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let timephase = app.date_range();
+    let trend = app
+        .ledger
+        .top_account(app.user_id, timephase, None, 10, Some(true));
+    let selected_idx = app
+        .selected_account_stats_idx
+        .min(trend.axis.len().saturating_sub(1));
+    let monthly = match app.selected_stats_account() {
+        Some(acc) => app
+            .ledger
+            .data_linetrend(app.user_id, timephase, Some(acc), None),
+        None => app
+            .ledger
+            .data_linetrend(app.user_id, timephase, None, None),
+    };
+    draw_share_chart(f, area, "Top Accounts", &trend, selected_idx, &monthly);
+}
+
+fn draw_reconcile(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let Some(account) = app.recon_account() else {
+        let p = Paragraph::new("No accounts yet. Create one on the Accounts screen.")
+            .block(Block::default().title("Reconcile").borders(Borders::ALL));
+        f.render_widget(p, area);
+        return;
+    };
 
-    let rows = accounts.iter().enumerate().map(|(idx, acc)| {
-        let acc_type_str = format!("{:?}", acc.account_type);
-        let currency_str = format!("{:?}", acc.currency);
+    let history: Vec<&Cloudreconciliation> =
+        app.recon_sessions.iter().filter(|r| !r.is_open()).collect();
+    let history_height = if history.is_empty() {
+        0
+    } else {
+        (history.len() as u16 + 3).min(8)
+    };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(history_height)])
+        .split(area);
 
-        let cells = vec![
-            acc.accountid.to_string(),
-            acc.name.clone(),
-            acc_type_str,
-            format!("{:.2}", acc.balance),
-            currency_str,
+    if let Some(matches) = app.recon_statement_match() {
+        draw_statement_match(f, chunks[0], app, account, &matches);
+    } else if let Some(view) = app.recon_statement() {
+        draw_reconcile_session(f, chunks[0], app, account, &view);
+    } else {
+        draw_reconcile_quick(f, chunks[0], app, account);
+    }
+
+    if !history.is_empty() {
+        let rows = history.iter().map(|r| {
+            Row::new(vec![
+                r.statement_date.to_string(),
+                format!("{:.2}", r.statement_balance),
+                r.cleared_balance
+                    .map(|b| format!("{:.2}", b))
+                    .unwrap_or_else(|| "-".to_string()),
+                r.entry_count.to_string(),
+                r.finished_at
+                    .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_default(),
+            ])
+        });
+        let widths = [
+            Constraint::Length(12),
+            Constraint::Length(12),
+            Constraint::Length(12),
+            Constraint::Length(8),
+            Constraint::Min(16),
         ];
+        let table = Table::new(rows, widths)
+            .header(
+                Row::new(vec![
+                    "Statement",
+                    "Balance",
+                    "Cleared",
+                    "Entries",
+                    "Finished",
+                ])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+            )
+            .block(
+                Block::default()
+                    .title("Past Reconciliations")
+                    .borders(Borders::ALL),
+            );
+        f.render_widget(table, chunks[1]);
+    }
+}
 
-        let mut row = Row::new(cells);
-        if idx == selected_idx {
+fn draw_reconcile_session(
+    f: &mut Frame<'_>,
+    area: Rect,
+    app: &App,
+    account: &Account,
+    view: &StatementReconcile,
+) {
+    use crate::stat::datatype::EntryStatus;
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(6), Constraint::Min(0)])
+        .split(area);
+
+    let status = if view.is_balanced() {
+        Span::styled(
+            "BALANCED, press f to finish",
+            Style::default().fg(Color::Green),
+        )
+    } else {
+        Span::styled(
+            format!("OFF BY {:+.2}", view.difference),
+            Style::default().fg(Color::Red),
+        )
+    };
+    let text = vec![
+        Line::from(format!(
+            "Account: {} ({:?})   Statement: {}   Closing balance: {:.2}",
+            account.name, account.account_type, view.statement_date, view.statement_balance
+        )),
+        Line::from(format!(
+            "Opening: {:.2}   Reconciled earlier: {:+.2}   Cleared now: {:+.2}",
+            view.opening_balance, view.reconciled_total, view.cleared_total
+        )),
+        Line::from(format!(
+            "Cleared balance: {:.2}   Difference: {:+.2}",
+            view.cleared_balance, view.difference
+        )),
+        Line::from(status),
+    ];
+    let block = Block::default()
+        .title("Reconcile (session open)")
+        .borders(Borders::ALL);
+    f.render_widget(Paragraph::new(text).block(block), chunks[0]);
+
+    let entries_area = if view.is_balanced() {
+        chunks[1]
+    } else {
+        let candidates = app
+            .ledger
+            .explain_statement(view, DiscrepancySearch::default());
+        let parts = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(5), Constraint::Length(10)])
+            .split(chunks[1]);
+        draw_discrepancy_candidates(f, parts[1], &candidates);
+        parts[0]
+    };
+
+    let category_label = |cat: Option<i64>| match cat {
+        Some(id) => app.ledger.trans_categoryid2name(id),
+        None => "Uncategorized".to_string(),
+    };
+    let rows = view.entries.iter().enumerate().map(|(idx, (date, e))| {
+        let payee = app
+            .ledger
+            .transaction
+            .iter()
+            .find(|t| t.id == e.tranid)
+            .and_then(|t| t.receiver.clone())
+            .unwrap_or_default();
+        let mut row = Row::new(vec![
+            if e.status == EntryStatus::Cleared {
+                "[x]"
+            } else {
+                "[ ]"
+            }
+            .to_string(),
+            date.to_string(),
+            payee,
+            category_label(e.categoryid),
+            format!("{:.2}", e.amount),
+            e.desc.clone().unwrap_or_default(),
+        ]);
+        if idx == app.recon_entry_idx {
             row = row.style(Style::default().add_modifier(Modifier::REVERSED));
         }
         row
     });
-
     let widths = [
         Constraint::Length(4),
+        Constraint::Length(11),
         Constraint::Length(16),
-        Constraint::Length(12),
-        Constraint::Length(12),
+        Constraint::Length(14),
         Constraint::Length(10),
+        Constraint::Min(10),
     ];
-
     let table = Table::new(rows, widths)
         .header(
-            Row::new(vec!["ID", "Name", "Type", "Balance", "Currency"])
+            Row::new(vec!["", "Date", "Payee", "Category", "Amount", "Note"])
                 .style(Style::default().add_modifier(Modifier::BOLD)),
         )
-        .block(Block::default().title("Accounts").borders(Borders::ALL));
+        .block(
+            Block::default()
+                .title(format!(
+                    "Entries up to {} (Space to tick)",
+                    view.statement_date
+                ))
+                .borders(Borders::ALL),
+        );
+    f.render_widget(table, entries_area);
+}
 
-    f.render_widget(table, area);
+/// imported statement lines next to the ledger: matched, ledger only, statement only
+fn draw_statement_match(
+    f: &mut Frame<'_>,
+    area: Rect,
+    app: &App,
+    account: &Account,
+    matches: &StatementMatch,
+) {
+    use crate::stat::datatype::EntryStatus;
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(4),
+            Constraint::Percentage(50),
+            Constraint::Min(5),
+        ])
+        .split(area);
+    let lists = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(chunks[2]);
+
+    let statement_total: f64 = app.recon_statement_lines.iter().map(|l| l.amount).sum();
+    let text = vec![
+        Line::from(format!(
+            "Account: {}   Statement: {} lines, total {:+.2}",
+            account.name,
+            app.recon_statement_lines.len(),
+            statement_total
+        )),
+        Line::from(format!(
+            "Matched: {}   Ledger only: {}   Statement only: {}",
+            matches.matched.len(),
+            matches.ledger_only.len(),
+            matches.statement_only.len()
+        )),
+    ];
+    let block = Block::default()
+        .title("Statement Import")
+        .borders(Borders::ALL);
+    f.render_widget(Paragraph::new(text).block(block), chunks[0]);
+
+    let payee_of = |e: &Entry| {
+        app.ledger
+            .transaction
+            .iter()
+            .find(|t| t.id == e.tranid)
+            .and_then(|t| t.receiver.clone())
+            .unwrap_or_default()
+    };
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+
+    let rows = matches.matched.iter().map(|m| {
+        Row::new(vec![
+            if m.entry.status == EntryStatus::Uncleared {
+                "[ ]"
+            } else {
+                "[x]"
+            }
+            .to_string(),
+            m.line.date.to_string(),
+            m.line.payee.clone(),
+            format!("{:.2}", m.line.amount),
+            m.date.to_string(),
+            payee_of(&m.entry),
+            format!("{:.0}%", m.score * 100.0),
+        ])
+    });
+    let widths = [
+        Constraint::Length(4),
+        Constraint::Length(11),
+        Constraint::Min(12),
+        Constraint::Length(10),
+        Constraint::Length(11),
+        Constraint::Min(12),
+        Constraint::Length(5),
+    ];
+    let table = Table::new(rows, widths)
+        .header(
+            Row::new(vec![
+                "",
+                "Stmt date",
+                "Statement payee",
+                "Amount",
+                "Ledger",
+                "Ledger payee",
+                "Match",
+            ])
+            .style(bold),
+        )
+        .block(Block::default().title("Matched").borders(Borders::ALL));
+    f.render_widget(table, chunks[1]);
+
+    let rows = matches.ledger_only.iter().map(|(date, e)| {
+        Row::new(vec![
+            date.to_string(),
+            payee_of(e),
+            format!("{:.2}", e.amount),
+        ])
+    });
+    let widths = [
+        Constraint::Length(11),
+        Constraint::Min(10),
+        Constraint::Length(10),
+    ];
+    let table = Table::new(rows, widths)
+        .header(Row::new(vec!["Date", "Payee", "Amount"]).style(bold))
+        .block(Block::default().title("Ledger Only").borders(Borders::ALL));
+    f.render_widget(table, lists[0]);
+
+    let rows = matches.statement_only.iter().enumerate().map(|(idx, l)| {
+        let mut row = Row::new(vec![
+            l.date.to_string(),
+            l.payee.clone(),
+            format!("{:.2}", l.amount),
+        ]);
+        if idx == app.recon_line_idx {
+            row = row.style(Style::default().add_modifier(Modifier::REVERSED));
+        }
+        row
+    });
+    let table = Table::new(rows, widths)
+        .header(Row::new(vec!["Date", "Payee", "Amount"]).style(bold))
+        .block(
+            Block::default()
+                .title("Statement Only (n to create)")
+                .borders(Borders::ALL),
+        );
+    f.render_widget(table, lists[1]);
 }
 
-fn draw_category_stats(f: &mut Frame<'_>, area: Rect, app: &App) {
-    let timephase = (app.start_month, app.end_month);
-    let trend = app
-        .ledger
-        .top_category(app.user_id, timephase, None, 10, Some(true));
+/// ranked explanations of a reconcile difference, best first
+fn draw_discrepancy_candidates(f: &mut Frame<'_>, area: Rect, candidates: &[DiscrepancyCandidate]) {
+    let mut lines = Vec::new();
+    if candidates.is_empty() {
+        lines.push(Line::from(
+            "No combination of up to 3 entries explains the difference.",
+        ));
+    }
+    for c in candidates {
+        let kind = match c.kind {
+            DiscrepancyKind::Single => "single",
+            DiscrepancyKind::Combination => "combination",
+            DiscrepancyKind::Duplicate => "duplicate",
+            DiscrepancyKind::SignFlip => "sign flip",
+        };
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("{:>3.0}% {:<12}", c.score * 100.0, kind),
+                Style::default().fg(Color::Yellow),
+            ),
+            Span::raw(c.explanation.clone()),
+        ]));
+    }
+    let block = Block::default()
+        .title("Possible Explanations")
+        .borders(Borders::ALL);
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(block)
+            .wrap(Wrap { trim: false }),
+        area,
+    );
+}
 
-    let norm = trend.normalize();
-    // (name, income, outcome, net, percentage_of_spend)
-    let mut data: Vec<(String, f64, f64, f64, f64)> = Vec::new();
-    for i in 0..trend.axis.len() {
-        let name = trend.axis[i].clone();
-        let income = trend.income[i];
-        let outcome = trend.outcome[i];
-        let net = trend.summary[i];
-        let perc = norm.outcome[i].abs() * 100.0;
+/// no open session: hint plus the old external balance quick check
+fn draw_reconcile_quick(f: &mut Frame<'_>, area: Rect, app: &App, account: &Account) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(7), Constraint::Min(0)])
+        .split(area);
 
-        data.push((name, income, outcome, net, perc));
+    let mut text = String::new();
+    let (sy, sm) = app.start_month;
+    let (ey, em) = app.end_month;
+
+    text.push_str(&format!(
+        "Account: {} (a next)   no open reconciliation, press s to start one\n",
+        account.name
+    ));
+    text.push_str(&format!("Time range: {sy:04}-{sm:02} ~ {ey:04}-{em:02}\n"));
+    text.push_str(&format!(
+        "External balance: {}\n",
+        app.reconcile_external_balance
+    ));
+
+    if let Some(ref view) = app.reconcile_result {
+        text.push_str(&format!(
+            "Internal: {:+.2}   External: {:+.2}   Diff: {:+.2}\nStatus: {}\n",
+            view.internal_balance,
+            view.external_balance,
+            view.difference,
+            if view.good { "OK ✅" } else { "MISMATCH ❌" },
+        ));
+    } else {
+        text.push_str("No reconcile result yet. Press 'e' to edit external balance.\n");
+    }
+
+    let block = Block::default()
+        .title(Span::raw("Reconcile"))
+        .borders(Borders::ALL);
+    let p = Paragraph::new(text).block(block);
+    f.render_widget(p, chunks[0]);
+
+    if let Some(ref view) = app.reconcile_result {
+        let table_area = if view.candidates.is_empty() {
+            chunks[1]
+        } else {
+            let parts = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(chunks[1]);
+            draw_discrepancy_candidates(f, parts[0], &view.candidates);
+            parts[1]
+        };
+        let rows = view.entries.iter().map(|e| {
+            let cells = vec![
+                e.entry_id.clone(),
+                e.date.clone(),
+                e.account_name.clone(),
+                e.category_name.clone(),
+                format!("{:.2}", e.amount),
+                e.desc.clone(),
+            ];
+            Row::new(cells)
+        });
+
+        let widths = [
+            Constraint::Length(6),
+            Constraint::Length(10),
+            Constraint::Length(12),
+            Constraint::Length(14),
+            Constraint::Length(10),
+            Constraint::Min(10),
+        ];
+
+        let table = Table::new(rows, widths)
+            .header(
+                Row::new(vec!["ID", "Date", "Account", "Category", "Amount", "Desc"])
+                    .style(Style::default().add_modifier(Modifier::BOLD)),
+            )
+            .block(
+                Block::default()
+                    .title("Suspicious Entries")
+                    .borders(Borders::ALL),
+            );
+
+        f.render_widget(table, table_area);
+    }
+}
+
+/// rect of `percent_x` x `percent_y` centered in `area`
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
+}
+
+fn draw_confirm_delete(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let Some(target) = app.pending_delete else {
+        return;
+    };
+    let popup = centered_rect(80, 70, area);
+    f.render_widget(Clear, popup);
+
+    let category_label = |cat: Option<i64>| match cat {
+        Some(id) => app.ledger.trans_categoryid2name(id),
+        None => "Uncategorized".to_string(),
+    };
+
+    match target {
+        DeleteTarget::Transaction(tx_id) => {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(7), Constraint::Min(3)])
+                .split(popup);
+
+            let entries = app.transaction_entries(tx_id);
+            let total: f64 = entries.iter().map(|e| e.amount).sum();
+            let mut text = String::new();
+            if let Some(tx) = app.ledger.transaction.iter().find(|t| t.id == tx_id) {
+                text.push_str(&format!("Date:  {}\n", tx.occur_date));
+                text.push_str(&format!("Payee: {}\n", tx.receiver.as_deref().unwrap_or("-")));
+                text.push_str(&format!("Memo:  {}\n", tx.desc.as_deref().unwrap_or("-")));
+            }
+            text.push_str(&format!("Total: {:.2} in {} split(s)\n", total, entries.len()));
+            text.push_str("y/Enter delete transaction | ↑/↓ select split | x delete split only | n/Esc cancel");
+
+            let block = Block::default()
+                .title("Delete Transaction?")
+                .borders(Borders::ALL);
+            f.render_widget(Paragraph::new(text).block(block), chunks[0]);
+
+            let rows = entries.iter().enumerate().map(|(idx, e)| {
+                let mut row = Row::new(vec![
+                    format!("{}", idx + 1),
+                    app.ledger.trans_accountid2name(e.accountid),
+                    category_label(e.categoryid),
+                    format!("{:.2}", e.amount),
+                    e.desc.clone().unwrap_or_default(),
+                ]);
+                if idx == app.delete_entry_idx {
+                    row = row.style(Style::default().add_modifier(Modifier::REVERSED));
+                }
+                row
+            });
+            let widths = [
+                Constraint::Length(4),
+                Constraint::Length(16),
+                Constraint::Length(16),
+                Constraint::Length(12),
+                Constraint::Min(10),
+            ];
+            let table = Table::new(rows, widths)
+                .header(
+                    Row::new(vec!["#", "Account", "Category", "Amount", "Note"])
+                        .style(Style::default().add_modifier(Modifier::BOLD)),
+                )
+                .block(Block::default().title("Splits").borders(Borders::ALL));
+            f.render_widget(table, chunks[1]);
+        }
+        DeleteTarget::Entry(entry_id) => {
+            let mut text = String::new();
+            if let Some(e) = app.ledger.entry.iter().find(|e| e.id == entry_id) {
+                text.push_str(&format!(
+                    "Account:  {}\n",
+                    app.ledger.trans_accountid2name(e.accountid)
+                ));
+                text.push_str(&format!("Category: {}\n", category_label(e.categoryid)));
+                text.push_str(&format!("Amount:   {:.2}\n", e.amount));
+                text.push_str(&format!("Note:     {}\n", e.desc.as_deref().unwrap_or("-")));
+            }
+            text.push_str("\nThe rest of the transaction is kept.\n");
+            text.push_str("y/Enter delete split | n/Esc cancel");
+            let block = Block::default().title("Delete Split?").borders(Borders::ALL);
+            f.render_widget(Paragraph::new(text).block(block), popup);
+        }
+        DeleteTarget::Account(acc_id) => {
+            let used = app.ledger.entry.iter().filter(|e| e.accountid == acc_id).count();
+            let mut text = format!(
+                "Account: {}\nBalance: {:.2}\nEntries: {}\n\n",
+                app.ledger.trans_accountid2name(acc_id),
+                app.ledger.cal_balance(acc_id),
+                used
+            );
+            if used > 0 {
+                text.push_str("The server refuses to delete accounts that still have entries.\n");
+            }
+            text.push_str("y/Enter delete account | n/Esc cancel");
+            let block = Block::default().title("Delete Account?").borders(Borders::ALL);
+            f.render_widget(Paragraph::new(text).block(block), popup);
+        }
+        DeleteTarget::Category(cat_id) => {
+            let used = app
+                .ledger
+                .entry
+                .iter()
+                .filter(|e| e.categoryid == Some(cat_id))
+                .count();
+            let text = format!(
+                "Category: {}\nEntries using it: {} (they become Uncategorized)\n\n\
+                 y/Enter delete category | n/Esc cancel",
+                app.ledger.trans_categoryid2name(cat_id),
+                used
+            );
+            let block = Block::default().title("Delete Category?").borders(Borders::ALL);
+            f.render_widget(Paragraph::new(text).block(block), popup);
+        }
+        DeleteTarget::Goal(goal_id) => {
+            let mut text = String::new();
+            if let Some(g) = app.goals.iter().find(|g| g.id == goal_id) {
+                text.push_str(&format!("Goal:   {}\n", g.name));
+                text.push_str(&format!("Target: {:.2}\n", g.target));
+            }
+            text.push_str("\nAccounts and entries are kept, deleting a goal can't be undone.\n");
+            text.push_str("y/Enter delete goal | n/Esc cancel");
+            let block = Block::default().title("Delete Goal?").borders(Borders::ALL);
+            f.render_widget(Paragraph::new(text).block(block), popup);
+        }
+        DeleteTarget::Attachment(id) => {
+            let mut text = String::new();
+            if let Some(a) = app.ledger.attachment.iter().find(|a| a.id == id) {
+                text.push_str(&format!("File: {}
+", a.filename));
+                text.push_str(&format!("Size: {}
+", crate::stat::human_size(a.size)));
+            }
+            text.push_str("
+The file is removed from the server, this can't be undone.
+");
+            text.push_str("y/Enter delete attachment | n/Esc cancel");
+            let block = Block::default().title("Delete Attachment?").borders(Borders::ALL);
+            f.render_widget(Paragraph::new(text).block(block), popup);
+        }
+        DeleteTarget::Token(id) => {
+            let mut text = String::new();
+            if let Some(t) = app.tokens.iter().find(|t| t.id == id) {
+                text.push_str(&format!("Token:  {} ({}...)\n", t.name, t.prefix));
+                text.push_str(&format!("Scopes: {}\n", t.scopes.join(", ")));
+            }
+            text.push_str("\nScripts using it stop working at once, this can't be undone.\n");
+            text.push_str("y/Enter revoke token | n/Esc cancel");
+            let block = Block::default().title("Revoke Token?").borders(Borders::ALL);
+            f.render_widget(Paragraph::new(text).block(block), popup);
+        }
     }
+}
 
-    let mut selected_idx = app.selected_category_stats_idx;
-    if !data.is_empty() && selected_idx >= data.len() {
-        selected_idx = data.len() - 1;
-    }
+fn draw_tx_history(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let popup = centered_rect(85, 80, area);
+    f.render_widget(Clear, popup);
 
-    let rows = data
-        .into_iter()
-        .enumerate()
-        .map(|(idx, (name, inc, out, net, pct))| {
-            let cells = vec![
-                format!("{}", idx + 1),
-                name,
-                format!("{inc:.2}"),
-                format!("{out:.2}"),
-                format!("{net:.2}"),
-                format!("{pct:.1}%"),
-            ];
-            let mut row = Row::new(cells);
-            if idx == selected_idx {
-                row = row.style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut text = String::new();
+    if app.tx_history.is_empty() {
+        text.push_str("No recorded changes for this transaction.\n");
+    }
+    // rows come newest first from the server
+    for row in &app.tx_history {
+        let what = match row.table_name.as_str() {
+            "transactions" => "transaction".to_string(),
+            "entries" => format!("split #{}", row.row_id),
+            other => format!("{} {}", other, row.row_id),
+        };
+        let who = match (&row.changed_by_email, row.changed_by) {
+            (Some(email), _) => email.clone(),
+            (None, Some(id)) => id.to_string(),
+            (None, None) => "-".to_string(),
+        };
+        text.push_str(&format!(
+            "{}  {:<11} {}  by {}\n",
+            row.changed_at
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S"),
+            row.action,
+            what,
+            who
+        ));
+        // soft delete and restore only flip deleted_at, nothing else to show
+        if matches!(row.action.as_str(), "soft_delete" | "restore") {
+            continue;
+        }
+        for (field, before, after) in row.changed_fields() {
+            match row.action.as_str() {
+                "insert" => text.push_str(&format!("    {}: {}\n", field, after)),
+                "delete" => text.push_str(&format!("    {}: {}\n", field, before)),
+                _ => text.push_str(&format!("    {}: {} -> {}\n", field, before, after)),
             }
-            row
-        });
+        }
+    }
 
-    let widths = [
-        Constraint::Length(4),
-        Constraint::Length(18),
-        Constraint::Length(12),
-        Constraint::Length(12),
-        Constraint::Length(12),
-        Constraint::Length(8),
-    ];
+    let block = Block::default()
+        .title("Transaction History")
+        .borders(Borders::ALL);
+    let p = Paragraph::new(text)
+        .block(block)
+        .scroll((app.tx_history_scroll, 0));
+    f.render_widget(p, popup);
+}
 
-    let table = Table::new(rows, widths)
-        .header(
-            Row::new(vec!["#", "Category", "Income", "Outcome", "Net", "% Spend"])
-                .style(Style::default().add_modifier(Modifier::BOLD)),
-        )
-        .block(
-            Block::default()
-                .title("Top Categories by Outcome")
-                .borders(Borders::ALL),
-        );
+fn draw_attachments(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let popup = centered_rect(75, 60, area);
+    f.render_widget(Clear, popup);
 
-    f.render_widget(table, area);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(3)])
+        .split(popup);
+    let tx = app
+        .attachment_tranid
+        .and_then(|id| app.ledger.transaction.iter().find(|t| t.id == id));
+    let attachments = tx.map(|t| app.ledger.attachments_of(t.id)).unwrap_or_default();
+    let selected = app.selected_attachment_idx.min(attachments.len().saturating_sub(1));
+    let rows = attachments.iter().enumerate().map(|(idx, a)| {
+        let row = Row::new(vec![
+            a.filename.clone(),
+            a.content_type.clone(),
+            crate::stat::human_size(a.size),
+            a.create_date
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string(),
+        ]);
+        match idx == selected {
+            true => row.style(Style::default().add_modifier(Modifier::REVERSED)),
+            false => row,
+        }
+    });
+    let title = match tx {
+        Some(t) => format!(
+            "Attachments of {} {}",
+            t.occur_date,
+            t.receiver.as_deref().unwrap_or("-")
+        ),
+        None => "Attachments".to_string(),
+    };
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Min(20),
+            Constraint::Length(16),
+            Constraint::Length(10),
+            Constraint::Length(16),
+        ],
+    )
+    .header(
+        Row::new(vec!["File", "Type", "Size", "Added"])
+            .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(Block::default().title(title).borders(Borders::ALL));
+    f.render_widget(table, chunks[0]);
+
+    let text = match (&app.error_message, attachments.is_empty()) {
+        (Some(msg), _) => msg.clone(),
+        (None, true) => "No attachments yet, a attaches a receipt or document".to_string(),
+        (None, false) => "s/Enter save to the current directory | a attach | d delete".to_string(),
+    };
+    f.render_widget(
+        Paragraph::new(text).block(Block::default().borders(Borders::ALL)),
+        chunks[1],
+    );
 }
 
-fn draw_transactions(f: &mut Frame<'_>, area: Rect, app: &App) {
-    let transactions = &app.ledger.transaction;
+fn draw_add_attachment(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let popup = centered_rect(70, 30, area);
+    f.render_widget(Clear, popup);
 
-    let mut selected_idx = app.selected_transaction_idx;
-    if !transactions.is_empty() && selected_idx >= transactions.len() {
-        selected_idx = transactions.len() - 1;
+    let mut text = format!(
+        "File: {}\n\nJPEG, PNG, GIF or WebP images and PDF documents, up to {} MiB.\n",
+        app.attachment_path,
+        crate::stat::MAX_ATTACHMENT_BYTES / 1024 / 1024
+    );
+    if let Some(ref msg) = app.error_message {
+        text.push_str(&format!("\n{}", msg));
     }
+    let block = Block::default()
+        .title("Attach File (Enter to upload, Esc to cancel)")
+        .borders(Borders::ALL);
+    f.render_widget(
+        Paragraph::new(text).block(block).wrap(Wrap { trim: false }),
+        popup,
+    );
+}
 
-    let rows = transactions.iter().enumerate().map(|(idx, tx)| {
-        let total_amount: f64 = app
-            .ledger
-            .entry
-            .iter()
-            .filter(|e| e.tranid == tx.id)
-            .map(|e| e.amount)
-            .sum();
-
-        let entry_count = app
-            .ledger
-            .entry
-            .iter()
-            .filter(|e| e.tranid == tx.id)
-            .count();
-
-        let payee_str = tx.receiver.as_deref().unwrap_or("-");
-        let memo_str = tx.desc.as_deref().unwrap_or("-");
-
-        let cells = vec![
-            format!("{}", tx.occur_date),
-            payee_str.to_string(),
-            memo_str.to_string(),
-            format!("{:.2}", total_amount),
-            format!("{}", entry_count),
-        ];
+fn draw_workspaces(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let popup = centered_rect(80, 60, area);
+    f.render_widget(Clear, popup);
 
-        let mut row = Row::new(cells);
-        if idx == selected_idx {
-            row = row.style(Style::default().add_modifier(Modifier::REVERSED));
+    let rows_area = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(3)])
+        .split(popup);
+    let panes = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(rows_area[0]);
+    let current = app.ledger.workspace.as_ref().map(|w| w.id);
+    let selected = app
+        .selected_workspace_idx
+        .min(app.workspaces.len().saturating_sub(1));
+    let rows = app.workspaces.iter().enumerate().map(|(idx, w)| {
+        let marker = if Some(w.id) == current { "●" } else { " " };
+        let name = match w.personal {
+            true => format!("{} (personal)", w.name),
+            false => w.name.clone(),
+        };
+        let row = Row::new(vec![marker.to_string(), name, w.role.to_cloud().to_string()]);
+        match idx == selected {
+            true => row.style(Style::default().add_modifier(Modifier::REVERSED)),
+            false => row,
         }
-        row
     });
-
-    let widths = [
-        Constraint::Length(12),
-        Constraint::Length(20),
-        Constraint::Length(20),
-        Constraint::Length(12),
-        Constraint::Length(8),
-    ];
-
-    let table = Table::new(rows, widths)
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(2),
+            Constraint::Min(16),
+            Constraint::Length(8),
+        ],
+    )
+    .header(
+        Row::new(vec!["", "Workspace", "Role"]).style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(Block::default().title("Workspaces").borders(Borders::ALL));
+    f.render_widget(table, panes[0]);
+
+    let members = app.workspace_members.iter().map(|m| {
+        let email = match m.userid == app.user_id {
+            true => format!("{} (you)", m.email),
+            false => m.email.clone(),
+        };
+        Row::new(vec![email, m.role.to_cloud().to_string()])
+    });
+    let table = Table::new(members, [Constraint::Min(20), Constraint::Length(8)])
         .header(
-            Row::new(vec!["Date", "Payee", "Memo", "Amount", "Entries"])
-                .style(Style::default().add_modifier(Modifier::BOLD)),
+            Row::new(vec!["Member", "Role"]).style(Style::default().add_modifier(Modifier::BOLD)),
         )
-        .block(Block::default().title("Transactions").borders(Borders::ALL));
+        .block(Block::default().title("Members").borders(Borders::ALL));
+    f.render_widget(table, panes[1]);
 
-    f.render_widget(table, area);
+    let text = match &app.error_message {
+        Some(msg) => msg.clone(),
+        None => "Enter switch | n new workspace | i invite member (owners)".to_string(),
+    };
+    f.render_widget(
+        Paragraph::new(text).block(Block::default().borders(Borders::ALL)),
+        rows_area[1],
+    );
 }
 
-fn draw_account_stats(f: &mut Frame<'_>, area: Rect, app: &App) {
-    let timephase = (app.start_month, app.end_month);
-    let trend = app
-        .ledger
-        .top_account(app.user_id, timephase, None, 10, Some(true));
-
-    let norm = trend.normalize();
-
-    let mut data: Vec<(String, f64, f64, f64, f64)> = Vec::new();
-    for i in 0..trend.axis.len() {
-        let name = trend.axis[i].clone();
-        let inc = trend.income[i];
-        let out = trend.outcome[i];
-        let net = trend.summary[i];
-        let pct = norm.outcome[i].abs() * 100.0;
-
-        data.push((name, inc, out, net, pct));
+fn draw_workspace_input(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let popup = centered_rect(60, 40, area);
+    f.render_widget(Clear, popup);
+
+    let (title, mut text) = match app.input_mode {
+        InputMode::InvitingMember => (
+            "Invite Member (Tab role, Enter to invite, Esc to cancel)",
+            format!(
+                "Email: {}\nRole:  {}\n\nThe user must be registered already. Editors change the ledger, viewers only read it, owners also manage members.\n",
+                app.workspace_input,
+                app.invite_role.to_cloud()
+            ),
+        ),
+        _ => (
+            "New Workspace (Enter to create, Esc to cancel)",
+            format!(
+                "Name: {}\n\nYou become its owner and can invite others.\n",
+                app.workspace_input
+            ),
+        ),
+    };
+    if let Some(ref msg) = app.error_message {
+        text.push_str(&format!("\n{}", msg));
     }
+    let block = Block::default().title(title).borders(Borders::ALL);
+    f.render_widget(
+        Paragraph::new(text).block(block).wrap(Wrap { trim: false }),
+        popup,
+    );
+}
 
-    let mut selected_idx = app.selected_account_stats_idx;
-    if !data.is_empty() && selected_idx >= data.len() {
-        selected_idx = data.len() - 1;
+fn open_tokens(app: &mut App, rt: &tokio::runtime::Runtime) {
+    match rt.block_on(crate::stat::sync::list_tokens_from_server(&app.base_url, &app.session.token)) {
+        Ok(tokens) => {
+            app.tokens = tokens;
+            app.selected_token_idx = 0;
+            app.created_token = None;
+            app.error_message = None;
+            app.input_mode = InputMode::ViewingTokens;
+        }
+        Err(e) => app.error_message = Some(format!("Loading tokens failed: {}", e)),
     }
+}
 
-    let rows = data
-        .into_iter()
-        .enumerate()
-        .map(|(idx, (name, inc, out, net, pct))| {
-            let cells = vec![
-                format!("{}", idx + 1),
-                name,
-                format!("{inc:.2}"),
-                format!("{out:.2}"),
-                format!("{net:.2}"),
-                format!("{pct:.1}%"),
-            ];
-            let mut row = Row::new(cells);
-            if idx == selected_idx {
-                row = row.style(Style::default().add_modifier(Modifier::REVERSED));
-            }
-            row
-        });
+fn handle_key_tokens(app: &mut App, key: KeyEvent) {
+    use KeyCode::*;
 
-    let widths = [
-        Constraint::Length(4),
-        Constraint::Length(18),
-        Constraint::Length(12),
-        Constraint::Length(12),
-        Constraint::Length(12),
-        Constraint::Length(8),
-    ];
+    match key.code {
+        Esc | Char('q') | Char('k') => {
+            // the new token is gone for good once the popup closes
+            app.created_token = None;
+            app.error_message = None;
+            app.input_mode = InputMode::Normal;
+        }
+        Up => app.selected_token_idx = app.selected_token_idx.saturating_sub(1),
+        Down if app.selected_token_idx + 1 < app.tokens.len() => app.selected_token_idx += 1,
+        Char('n') => {
+            app.token_name = String::new();
+            app.token_scope_idx = 0;
+            app.token_expiry_days = String::new();
+            app.token_field_idx = 0;
+            app.error_message = None;
+            app.input_mode = InputMode::CreatingToken;
+        }
+        Char('d') => match app.selected_token() {
+            Some(t) if t.revoked => app.error_message = Some(format!("{} is revoked already", t.name)),
+            Some(t) => {
+                app.pending_delete = Some(DeleteTarget::Token(t.id));
+                app.error_message = None;
+                app.input_mode = InputMode::ConfirmDelete;
+            }
+            None => {}
+        },
+        _ => {}
+    }
+}
 
-    let table = Table::new(rows, widths)
-        .header(
-            Row::new(vec!["#", "Account", "Income", "Outcome", "Net", "% Spend"])
-                .style(Style::default().add_modifier(Modifier::BOLD)),
-        )
-        .block(
-            Block::default()
-                .title("Top Accounts by Outcome")
-                .borders(Borders::ALL),
-        );
+fn handle_key_create_token(app: &mut App, key: KeyEvent, rt: &tokio::runtime::Runtime) {
+    use KeyCode::*;
 
-    f.render_widget(table, area);
+    match key.code {
+        Esc => {
+            app.error_message = None;
+            app.input_mode = InputMode::ViewingTokens;
+        }
+        Tab | Down => app.token_field_idx = (app.token_field_idx + 1) % 3,
+        BackTab | Up => app.token_field_idx = (app.token_field_idx + 2) % 3,
+        Char('k') if app.token_field_idx == 1 => {
+            app.token_scope_idx = (app.token_scope_idx + 1) % TOKEN_SCOPES.len();
+        }
+        Char('j') if app.token_field_idx == 1 => {
+            app.token_scope_idx = (app.token_scope_idx + TOKEN_SCOPES.len() - 1) % TOKEN_SCOPES.len();
+        }
+        Backspace => match app.token_field_idx {
+            0 => {
+                app.token_name.pop();
+            }
+            2 => {
+                app.token_expiry_days.pop();
+            }
+            _ => {}
+        },
+        Char(c) if app.token_field_idx == 0 => app.token_name.push(c),
+        Char(c) if app.token_field_idx == 2 && c.is_ascii_digit() => app.token_expiry_days.push(c),
+        Enter => match submit_token(app, rt) {
+            Ok(_) => {
+                app.error_message = None;
+                app.input_mode = InputMode::ViewingTokens;
+            }
+            Err(e) => app.error_message = Some(format!("Failed: {}", e)),
+        },
+        _ => {}
+    }
 }
 
-fn draw_trends(f: &mut Frame<'_>, area: Rect, app: &App) {
-    let timephase = (app.start_month, app.end_month);
-    let trend = app
-        .ledger
-        .data_linetrend(app.user_id, timephase, None, None);
+fn submit_token(app: &mut App, rt: &tokio::runtime::Runtime) -> anyhow::Result<()> {
+    use crate::stat::sync::{Tokenreq, create_token_on_server};
 
-    let mut data: Vec<(String, f64, f64, f64)> = Vec::new();
-    for i in 0..trend.axis.len() {
-        let (y, m) = trend.axis[i];
-        let ym = format!("{y:04}-{m:02}");
-        let inc = trend.income[i];
-        let out = trend.outcome[i];
-        let net = trend.summary[i];
-        data.push((ym, inc, out, net));
+    let name = app.token_name.trim();
+    if name.is_empty() {
+        anyhow::bail!("Name is required");
     }
+    let expires_at = match app.token_expiry_days.trim() {
+        "" => None,
+        days => {
+            let days: i64 = days
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Expiry is not a whole number of days"))?;
+            if !(1..=3650).contains(&days) {
+                anyhow::bail!("Expiry must be 1-3650 days");
+            }
+            Some(chrono::Utc::now() + chrono::Duration::days(days))
+        }
+    };
+    let req = Tokenreq {
+        name: name.to_string(),
+        scopes: TOKEN_SCOPES[app.token_scope_idx % TOKEN_SCOPES.len()]
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+        expires_at,
+    };
+    let (token, info) = rt.block_on(create_token_on_server(&app.base_url, &app.session.token, &req))?;
+    app.tokens.insert(0, info);
+    app.selected_token_idx = 0;
+    app.created_token = Some(token);
+    Ok(())
+}
 
-    let rows = data.into_iter().map(|(ym, inc, out, net)| {
-        let cells = vec![
-            ym,
-            format!("{inc:.2}"),
-            format!("{out:.2}"),
-            format!("{net:.2}"),
-        ];
-        Row::new(cells)
-    });
+fn draw_tokens(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let popup = centered_rect(90, 70, area);
+    f.render_widget(Clear, popup);
 
-    let widths = [
-        Constraint::Length(10),
-        Constraint::Length(14),
-        Constraint::Length(14),
-        Constraint::Length(14),
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(4)])
+        .split(popup);
+    let date = |d: Option<chrono::DateTime<chrono::Utc>>, none: &str| {
+        d.map(|d| d.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| none.to_string())
+    };
+    let now = chrono::Utc::now();
+    let selected = app.selected_token_idx.min(app.tokens.len().saturating_sub(1));
+    let rows = app.tokens.iter().enumerate().map(|(idx, t)| {
+        let status = match t.expires_at {
+            _ if t.revoked => Cell::from("revoked").style(Style::default().fg(Color::Red)),
+            Some(at) if at <= now => Cell::from("expired").style(Style::default().fg(Color::Red)),
+            _ => Cell::from("active").style(Style::default().fg(Color::Green)),
+        };
+        let row = Row::new(vec![
+            Cell::from(t.name.clone()),
+            Cell::from(format!("{}...", t.prefix)),
+            Cell::from(t.scopes.join(", ")),
+            Cell::from(date(t.expires_at, "never")),
+            Cell::from(date(t.last_used_at, "never")),
+            status,
+        ]);
+        match idx == selected {
+            true => row.style(Style::default().add_modifier(Modifier::REVERSED)),
+            false => row,
+        }
+    });
+    let widths = [
+        Constraint::Min(10),
+        Constraint::Length(16),
+        Constraint::Length(31),
+        Constraint::Length(17),
+        Constraint::Length(17),
+        Constraint::Length(8),
     ];
-
     let table = Table::new(rows, widths)
         .header(
-            Row::new(vec!["Month", "Income", "Outcome", "Net"])
+            Row::new(vec!["Name", "Token", "Scopes", "Expires", "Last used", "Status"])
                 .style(Style::default().add_modifier(Modifier::BOLD)),
         )
-        .block(
-            Block::default()
-                .title("Monthly Trends")
-                .borders(Borders::ALL),
-        );
-
-    f.render_widget(table, area);
+        .block(Block::default().title("API Tokens").borders(Borders::ALL));
+    f.render_widget(table, chunks[0]);
+
+    let text = match (&app.error_message, &app.created_token) {
+        (Some(msg), _) => msg.clone(),
+        (None, Some(token)) => format!("Copy the new token now, it is not shown again:\n{}", token),
+        (None, None) => {
+            "Send a token as \"Authorization: Bearer <token>\". n new token | d revoke".to_string()
+        }
+    };
+    f.render_widget(
+        Paragraph::new(text)
+            .block(Block::default().borders(Borders::ALL))
+            .wrap(Wrap { trim: false }),
+        chunks[1],
+    );
 }
 
-fn draw_reconcile(f: &mut Frame<'_>, area: Rect, app: &App) {
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([Constraint::Length(5), Constraint::Min(0)])
-        .split(area);
+fn draw_create_token(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let popup = centered_rect(60, 45, area);
+    f.render_widget(Clear, popup);
 
+    let scopes = format!(
+        "{} (j/k to change)",
+        TOKEN_SCOPES[app.token_scope_idx % TOKEN_SCOPES.len()].join(" + ")
+    );
+    let fields = [
+        ("Name", &app.token_name),
+        ("Scopes", &scopes),
+        ("Expires in days (empty = never)", &app.token_expiry_days),
+    ];
     let mut text = String::new();
-    let (sy, sm) = app.start_month;
-    let (ey, em) = app.end_month;
-
-    text.push_str(&format!("Time range: {sy:04}-{sm:02} ~ {ey:04}-{em:02}\n"));
-    text.push_str(&format!(
-        "External balance: {}\n",
-        app.reconcile_external_balance
-    ));
-
-    if let Some(ref view) = app.reconcile_result {
-        text.push_str(&format!(
-            "Internal: {:+.2}   External: {:+.2}   Diff: {:+.2}\nStatus: {}\n",
-            view.internal_balance,
-            view.external_balance,
-            view.difference,
-            if view.good { "OK ✅" } else { "MISMATCH ❌" },
-        ));
-    } else {
-        text.push_str("No reconcile result yet. Press 'e' to edit external balance.\n");
+    for (idx, (label, value)) in fields.iter().enumerate() {
+        let marker = if idx == app.token_field_idx { "> " } else { "  " };
+        text.push_str(&format!("{}{}: {}\n", marker, label, value));
+    }
+    text.push_str(
+        "\nread:ledger reads everything, write:transactions adds and deletes transactions, entries and attachments, admin does all a login can.\n",
+    );
+    if let Some(ref msg) = app.error_message {
+        text.push_str(&format!("\n{}", msg));
     }
 
     let block = Block::default()
-        .title(Span::raw("Reconcile"))
+        .title("New API Token (Enter to create, Esc to cancel)")
         .borders(Borders::ALL);
-    let p = Paragraph::new(text).block(block);
-    f.render_widget(p, chunks[0]);
-
-    if let Some(ref view) = app.reconcile_result {
-        let rows = view.entries.iter().map(|e| {
-            let cells = vec![
-                e.entry_id.clone(),
-                e.date.clone(),
-                e.account_name.clone(),
-                e.category_name.clone(),
-                format!("{:.2}", e.amount),
-                e.desc.clone(),
-            ];
-            Row::new(cells)
-        });
-
-        let widths = [
-            Constraint::Length(6),
-            Constraint::Length(10),
-            Constraint::Length(12),
-            Constraint::Length(14),
-            Constraint::Length(10),
-            Constraint::Min(10),
-        ];
-
-        let table = Table::new(rows, widths)
-            .header(
-                Row::new(vec!["ID", "Date", "Account", "Category", "Amount", "Desc"])
-                    .style(Style::default().add_modifier(Modifier::BOLD)),
-            )
-            .block(
-                Block::default()
-                    .title("Suspicious Entries")
-                    .borders(Borders::ALL),
-            );
-
-        f.render_widget(table, chunks[1]);
-    }
+    f.render_widget(
+        Paragraph::new(text).block(block).wrap(Wrap { trim: false }),
+        popup,
+    );
 }
 
 fn draw_help(f: &mut Frame<'_>, area: Rect, _app: &App) {
     let text = "\
 Screens:
-  Dashboard     – overview of monthly income/expense/net
-  Accounts      – list of accounts with balances
+  Dashboard     – overview of monthly income/expense/net, forecast warnings and alerts on unusual spending
+  Accounts      – list of accounts with balances, upcoming credit card payments with minimum due, utilization and interest
+  NetWorth      – net worth over time (credit accounts count as liabilities) and per-account breakdown
   CategoryStats – top spending categories over selected period
   AccountStats  – top spending accounts over selected period
-  Trends        – monthly trends of income, outcome, and net
-  Reconcile     – compare internal balance with external statement
+  Trends        – weekly / monthly / quarterly / yearly / statement cycle trends of income, outcome, and net
+  Compare       – global range against the previous period or the same period last year, per category and account
+  Forecast      – projected monthly net and balances with an 80% range, warns before an account goes below zero
+  Subscriptions – weekly / monthly / yearly charges with their yearly cost and price increases
+  Goals         – savings goals with progress, the monthly contribution they need and whether they are on track
+  Loans         – loans with the next payment split, payoff date, interest left and the remaining amortization schedule
+  People        – what each contact owes you or is owed from split expenses, with totals
+  Investments   – investment accounts with holdings, market value, open lots, realized / unrealized gains and dividends
+  Taxes         – yearly totals of categories mapped to tax lines with their supporting entries, export to CSV and text
+  Reconcile     – tick entries off against a bank statement, quick balance check
 
 Key bindings:
   Tab / Shift+Tab : switch screen
-  ← / →           : change focused month (Dashboard)
+  ← / →           : change focused month (Dashboard) / date (NetWorth)
+  g               : daily / monthly points (NetWorth)
+  v               : table / chart view (Trends, CategoryStats, AccountStats, Forecast)
+  p               : week / month / quarter / year / statement cycle buckets (Trends)
+                    previous period / same period last year (Compare)
+  + / -           : project more / fewer months (Forecast)
+  o               : stats from server reports / local ledger (Dashboard, NetWorth, stats, Trends)
   [ / ]           : shift global time range
   ↑ / ↓           : move selection in lists
   e               : edit external balance (Reconcile)
+  a / s           : next account / start reconciliation (Reconcile)
+  Space           : tick / untick selected entry (Reconcile)
+  f / x           : finish / cancel reconciliation (Reconcile)
+  i               : import a CSV/OFX statement and match its lines (Reconcile)
+  n / m / c       : create missing tx / tick matched / close statement (Reconcile)
+  t               : credit limit, statement / due day and APR of the selected card, or the loan terms (Accounts, Loans)
+  p               : record a loan payment, split into principal and interest (Loans)
+  + / -           : extra payment per loan payment in the payoff projection (Loans)
+  n / s           : new contact / settle up with the selected one (People)
+  t / i           : record a buy, sell, dividend or split / import a price CSV (Investments)
+  m               : FIFO / average cost basis of the selected account (Investments)
+  ← / →           : change year (Taxes)
+  l / e           : map categories to tax lines / export the year as CSV and printable text (Taxes)
+  n               : new goal (Goals)
+  d               : delete selected transaction/account/category/goal (asks first)
+  h               : change history of selected transaction (Transactions)
+  f               : attachments of selected transaction, save / attach / delete files (Transactions)
+  u / Ctrl+R      : undo / redo the last create or delete
+  w               : switch workspace, create one or invite members (owners)
+  k               : personal access tokens for scripts: create with scopes and expiry, revoke
   ?               : open this help
   q               : quit
 ";
@@ -1155,7 +6145,7 @@ fn advisor_generate_report(app: &mut App) -> Result<(), Box<dyn Error>> {
     let cfg = Generationcfg::default();
 
     // use last 3 months and top 3 categories
-    let samples = model.generate_advicepair(&app.ledger, app.user_id, 3, 3, &cfg)?;
+    let samples = model.generate_advicepair(&app.ledger, app.user_id, &app.goals, 3, 3, &cfg)?;
 
     app.advisor_prompt = samples.get(0).cloned().unwrap_or_default();
     app.advisor_advice1 = samples.get(1).cloned().unwrap_or_default();
@@ -1174,14 +6164,15 @@ fn advisor_answer_chat(
     let cfg = Generationcfg::default();
 
     let base_url = app.base_url.clone();
-    let token = app.token.clone();
+    let session = app.session.clone();
     let userid = app.user_id;
 
     let answer = rt.block_on(model.answer_withtool(
         question,
         &base_url,
-        &token,
+        &session,
         &mut app.ledger,
+        &mut app.history,
         userid,
         &cfg,
     ))?;
@@ -1493,7 +6484,7 @@ fn handle_key_create_account(app: &mut App, key: KeyEvent, rt: &tokio::runtime::
                 if c == 'j' && app.new_account_type_selection > 0 {
                     app.new_account_type_selection -= 1;
                 } else if c == 'k' {
-                    app.new_account_type_selection = (app.new_account_type_selection + 1) % 4;
+                    app.new_account_type_selection = (app.new_account_type_selection + 1) % 6;
                 }
             }
             2 => {
@@ -1511,14 +6502,15 @@ fn handle_key_create_account(app: &mut App, key: KeyEvent, rt: &tokio::runtime::
 }
 
 fn submit_new_account(app: &mut App, rt: &tokio::runtime::Runtime) -> anyhow::Result<()> {
-    use crate::stat::datatype::AccountType;
     use crate::stat::sync::*;
 
     let account_type = match app.new_account_type_selection {
         0 => AccountType::Checking,
         1 => AccountType::Credit,
         2 => AccountType::Cash,
-        3 => AccountType::Other("other".to_string()),
+        3 => AccountType::Loan,
+        4 => AccountType::Investment,
+        5 => AccountType::Other("other".to_string()),
         _ => AccountType::Checking,
     };
 
@@ -1539,24 +6531,460 @@ fn submit_new_account(app: &mut App, rt: &tokio::runtime::Runtime) -> anyhow::Re
         )
     };
 
-    rt.block_on(create_cloudaccount(
+    let created = rt.block_on(create_cloudaccount(
         &app.base_url,
-        &app.token,
+        &app.session,
         &app.new_account_name.trim(),
         &account_type,
         currency,
         balance,
     ))?;
+    app.history.record(crate::stat::history::Command::CreateAccount {
+        id: created.id,
+        name: created.name,
+    });
     Ok(())
 }
 
+fn draw_import_statement(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let popup = centered_rect(70, 30, area);
+    f.render_widget(Clear, popup);
+
+    let mut text = format!(
+        "Statement file: {}\n\nCSV needs a header with date and amount (or debit/credit) columns,\npayee/description and memo are optional. OFX/QFX files work as exported.\n",
+        app.recon_import_path
+    );
+    if let Some(ref msg) = app.error_message {
+        text.push_str(&format!("\n{}", msg));
+    }
+    let block = Block::default()
+        .title("Import Statement (Enter to load, Esc to cancel)")
+        .borders(Borders::ALL);
+    f.render_widget(
+        Paragraph::new(text).block(block).wrap(Wrap { trim: false }),
+        popup,
+    );
+}
+
+fn draw_start_reconcile(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let popup = centered_rect(60, 40, area);
+    f.render_widget(Clear, popup);
+
+    let account = app
+        .recon_account()
+        .map(|a| a.name.clone())
+        .unwrap_or_default();
+    let fields = [
+        ("Statement date (YYYY-MM-DD)", &app.recon_statement_date),
+        ("Closing balance", &app.recon_statement_balance),
+    ];
+    let mut text = format!("Account: {}\n\n", account);
+    for (idx, (label, value)) in fields.iter().enumerate() {
+        let marker = if idx == app.recon_field_idx {
+            "> "
+        } else {
+            "  "
+        };
+        text.push_str(&format!("{}{}: {}\n", marker, label, value));
+    }
+    if let Some(ref msg) = app.error_message {
+        text.push_str(&format!("\n{}", msg));
+    }
+
+    let block = Block::default()
+        .title("Start Reconciliation (Enter to start, Esc to cancel)")
+        .borders(Borders::ALL);
+    f.render_widget(Paragraph::new(text).block(block), popup);
+}
+
+fn draw_create_goal(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let popup = centered_rect(70, 70, area);
+    f.render_widget(Clear, popup);
+
+    let fields = [
+        ("Name", &app.new_goal_name),
+        ("Target amount", &app.new_goal_target),
+        ("Deadline (YYYY-MM-DD, empty for none)", &app.new_goal_deadline),
+        ("Tag (#tag in notes or memos counts)", &app.new_goal_tag),
+    ];
+    let mut text = String::new();
+    for (idx, (label, value)) in fields.iter().enumerate() {
+        let marker = if idx == app.new_goal_field_idx {
+            "> "
+        } else {
+            "  "
+        };
+        text.push_str(&format!("{}{}: {}\n", marker, label, value));
+    }
+    let marker = if app.new_goal_field_idx == 4 {
+        "> "
+    } else {
+        "  "
+    };
+    text.push_str(&format!(
+        "{}Linked accounts, balance counts (j/k move, Space toggle):\n",
+        marker
+    ));
+    for (idx, a) in app.ledger.account.iter().enumerate() {
+        let cursor = if app.new_goal_field_idx == 4 && idx == app.new_goal_account_idx {
+            ">"
+        } else {
+            " "
+        };
+        let linked = if app.new_goal_accounts.contains(&a.id) {
+            "[x]"
+        } else {
+            "[ ]"
+        };
+        text.push_str(&format!("   {} {} {}\n", cursor, linked, a.name));
+    }
+    if let Some(ref msg) = app.error_message {
+        text.push_str(&format!("\n{}", msg));
+    }
+
+    let block = Block::default()
+        .title("New Goal (Enter to create, Esc to cancel)")
+        .borders(Borders::ALL);
+    f.render_widget(
+        Paragraph::new(text).block(block).wrap(Wrap { trim: false }),
+        popup,
+    );
+}
+
+fn draw_credit_terms(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let popup = centered_rect(60, 45, area);
+    f.render_widget(Clear, popup);
+
+    let account = app
+        .credit_accountid
+        .map(|id| app.ledger.trans_accountid2name(id))
+        .unwrap_or_default();
+    let fields = [
+        ("Credit limit", &app.credit_limit),
+        ("Statement closing day (1-31)", &app.credit_statement_day),
+        ("Payment due day (1-31)", &app.credit_due_day),
+        ("APR %", &app.credit_apr),
+    ];
+    let mut text = format!("Card: {}\n\n", account);
+    for (idx, (label, value)) in fields.iter().enumerate() {
+        let marker = if idx == app.credit_field_idx {
+            "> "
+        } else {
+            "  "
+        };
+        text.push_str(&format!("{}{}: {}\n", marker, label, value));
+    }
+    text.push_str("\nThe payment is due on the first due day after the statement closed.\n");
+    if let Some(ref msg) = app.error_message {
+        text.push_str(&format!("\n{}", msg));
+    }
+
+    let block = Block::default()
+        .title("Card Terms (Enter to save, Esc to cancel)")
+        .borders(Borders::ALL);
+    f.render_widget(
+        Paragraph::new(text).block(block).wrap(Wrap { trim: false }),
+        popup,
+    );
+}
+
+fn draw_loan_terms(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let popup = centered_rect(60, 65, area);
+    f.render_widget(Clear, popup);
+
+    let account = app
+        .loan_accountid
+        .map(|id| app.ledger.trans_accountid2name(id))
+        .unwrap_or_default();
+    let frequency = format!("{} (j/k to change)", app.loan_frequency.to_cloud());
+    let fields = [
+        ("Principal", &app.loan_principal),
+        ("Rate % per year", &app.loan_rate),
+        ("Term in months", &app.loan_term_months),
+        ("Payment frequency", &frequency),
+        ("First payment (YYYY-MM-DD)", &app.loan_first_payment),
+    ];
+    let mut text = format!("Loan: {}\n\n", account);
+    for (idx, (label, value)) in fields.iter().enumerate() {
+        let marker = if idx == app.loan_field_idx { "> " } else { "  " };
+        text.push_str(&format!("{}{}: {}\n", marker, label, value));
+    }
+    text.push_str("\nLeave every field empty to clear the terms.\n");
+    if let Some(ref msg) = app.error_message {
+        text.push_str(&format!("\n{}", msg));
+    }
+
+    let block = Block::default()
+        .title("Loan Terms (Enter to save, Esc to cancel)")
+        .borders(Borders::ALL);
+    f.render_widget(
+        Paragraph::new(text).block(block).wrap(Wrap { trim: false }),
+        popup,
+    );
+}
+
+fn draw_loan_payment(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let popup = centered_rect(60, 65, area);
+    f.render_widget(Clear, popup);
+
+    let loan = app
+        .loan_status()
+        .into_iter()
+        .find(|l| Some(l.accountid) == app.loan_accountid);
+    let from = app
+        .ledger
+        .account
+        .get(app.loan_pay_from_idx)
+        .map(|a| format!("{} (j/k to change)", a.name))
+        .unwrap_or_default();
+    let category = match app.loan_pay_category_idx.checked_sub(1) {
+        Some(i) => app.ledger.category.get(i).map(|c| c.name.clone()).unwrap_or_default(),
+        None => "none".to_string(),
+    } + " (j/k to change)";
+    let fields = [
+        ("Pay from", &from),
+        ("Amount", &app.loan_pay_amount),
+        ("Date (YYYY-MM-DD)", &app.loan_pay_date),
+        ("Interest category", &category),
+    ];
+    let mut text = format!(
+        "Loan: {}\n\n",
+        loan.as_ref().map(|l| l.name.clone()).unwrap_or_default()
+    );
+    for (idx, (label, value)) in fields.iter().enumerate() {
+        let marker = if idx == app.loan_pay_field_idx { "> " } else { "  " };
+        text.push_str(&format!("{}{}: {}\n", marker, label, value));
+    }
+    // the server splits on the balance at the payment date, today's balance is the usual case
+    if let (Some(l), Ok(amount)) = (&loan, app.loan_pay_amount.trim().parse::<f64>()) {
+        let (principal, interest) =
+            crate::stat::split_payment(l.owed, l.terms.rate, l.terms.frequency, amount);
+        text.push_str(&format!(
+            "\nOwed {:.2}: about {:.2} principal and {:.2} interest\n",
+            l.owed, principal, interest
+        ));
+    }
+    if let Some(ref msg) = app.error_message {
+        text.push_str(&format!("\n{}", msg));
+    }
+
+    let block = Block::default()
+        .title("Record Loan Payment (Enter to record, Esc to cancel)")
+        .borders(Borders::ALL);
+    f.render_widget(
+        Paragraph::new(text).block(block).wrap(Wrap { trim: false }),
+        popup,
+    );
+}
+
+fn draw_create_contact(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let popup = centered_rect(60, 40, area);
+    f.render_widget(Clear, popup);
+
+    let fields = [("Name", &app.contact_name), ("Email (optional)", &app.contact_email)];
+    let mut text = String::new();
+    for (idx, (label, value)) in fields.iter().enumerate() {
+        let marker = if idx == app.contact_field_idx { "> " } else { "  " };
+        text.push_str(&format!("{}{}: {}\n", marker, label, value));
+    }
+    if let Some(ref msg) = app.error_message {
+        text.push_str(&format!("\n{}", msg));
+    }
+
+    let block = Block::default()
+        .title("New Contact (Enter to create, Esc to cancel)")
+        .borders(Borders::ALL);
+    f.render_widget(
+        Paragraph::new(text).block(block).wrap(Wrap { trim: false }),
+        popup,
+    );
+}
+
+fn draw_settlement(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let popup = centered_rect(60, 50, area);
+    f.render_widget(Clear, popup);
+
+    let contact = app
+        .contact_balances()
+        .into_iter()
+        .find(|c| Some(c.accountid) == app.contact_accountid);
+    let account = settle_accounts(app)
+        .get(app.settle_account_idx)
+        .map(|a| format!("{} (j/k to change)", a.name))
+        .unwrap_or_default();
+    let fields = [
+        ("Account", &account),
+        ("Amount", &app.settle_amount),
+        ("Date (YYYY-MM-DD)", &app.settle_date),
+    ];
+    let mut text = match &contact {
+        Some(c) if c.balance > 0.0 => format!("{} owes you {:.2}\n\n", c.name, c.balance),
+        Some(c) => format!("You owe {} {:.2}\n\n", c.name, -c.balance),
+        None => String::new(),
+    };
+    for (idx, (label, value)) in fields.iter().enumerate() {
+        let marker = if idx == app.settle_field_idx { "> " } else { "  " };
+        text.push_str(&format!("{}{}: {}\n", marker, label, value));
+    }
+    text.push_str("\nPositive: they pay you into the account, negative: you pay them from it\n");
+    if let Some(ref msg) = app.error_message {
+        text.push_str(&format!("\n{}", msg));
+    }
+
+    let block = Block::default()
+        .title("Settle Up (Enter to record, Esc to cancel)")
+        .borders(Borders::ALL);
+    f.render_widget(
+        Paragraph::new(text).block(block).wrap(Wrap { trim: false }),
+        popup,
+    );
+}
+
+fn draw_trade(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let popup = centered_rect(60, 70, area);
+    f.render_widget(Clear, popup);
+
+    let account = app
+        .ledger
+        .account
+        .iter()
+        .find(|a| Some(a.id) == app.trade_accountid)
+        .map(|a| a.name.clone())
+        .unwrap_or_default();
+    let kind = format!("{} (j/k to change)", app.trade_kind.to_cloud());
+    let category = match app.trade_category_idx.checked_sub(1) {
+        Some(i) => app.ledger.category.get(i).map(|c| c.name.clone()).unwrap_or_default(),
+        None => "none".to_string(),
+    } + " (j/k to change)";
+    let quantity_label = match app.trade_kind {
+        TradeKind::Split => "New units per old unit",
+        _ => "Units",
+    };
+    let fields = [
+        ("Kind", &kind),
+        ("Symbol", &app.trade_symbol),
+        ("Date (YYYY-MM-DD)", &app.trade_date),
+        (quantity_label, &app.trade_quantity),
+        ("Price per unit", &app.trade_price),
+        ("Fees", &app.trade_fees),
+        ("Amount received", &app.trade_amount),
+        ("Dividend category", &category),
+    ];
+    let mut text = format!("Account: {}\n\n", account);
+    for idx in trade_fields(app.trade_kind) {
+        let (label, value) = fields[*idx];
+        let marker = if *idx == app.trade_field_idx { "> " } else { "  " };
+        text.push_str(&format!("{}{}: {}\n", marker, label, value));
+    }
+    // the cash the trade moves, as the server books it
+    let number = |v: &str| v.trim().parse::<f64>().ok();
+    let cash = match app.trade_kind {
+        TradeKind::Buy | TradeKind::Sell => number(&app.trade_quantity)
+            .zip(number(&app.trade_price))
+            .map(|(q, p)| (q * p, number(&app.trade_fees).unwrap_or(0.0))),
+        _ => None,
+    };
+    match (app.trade_kind, cash) {
+        (TradeKind::Buy, Some((value, fees))) => {
+            text.push_str(&format!("\nPays {:.2} out of the account\n", value + fees))
+        }
+        (TradeKind::Sell, Some((value, fees))) => {
+            text.push_str(&format!("\nPuts {:.2} into the account\n", value - fees))
+        }
+        (TradeKind::Split, _) => {
+            text.push_str("\nNo cash moves, units and cost per unit of every lot are adjusted\n")
+        }
+        _ => {}
+    }
+    if let Some(ref msg) = app.error_message {
+        text.push_str(&format!("\n{}", msg));
+    }
+
+    let block = Block::default()
+        .title("Record Trade (Enter to record, Esc to cancel)")
+        .borders(Borders::ALL);
+    f.render_widget(
+        Paragraph::new(text).block(block).wrap(Wrap { trim: false }),
+        popup,
+    );
+}
+
+fn draw_import_prices(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let popup = centered_rect(70, 30, area);
+    f.render_widget(Clear, popup);
+
+    let mut text = format!(
+        "Price file: {}\n\nCSV needs a header with symbol, date and price (or close) columns,\nother columns are ignored. Symbols never traded are skipped.\n",
+        app.price_import_path
+    );
+    if let Some(ref msg) = app.error_message {
+        text.push_str(&format!("\n{}", msg));
+    }
+    let block = Block::default()
+        .title("Import Prices (Enter to import, Esc to cancel)")
+        .borders(Borders::ALL);
+    f.render_widget(
+        Paragraph::new(text).block(block).wrap(Wrap { trim: false }),
+        popup,
+    );
+}
+
+fn draw_tax_lines(f: &mut Frame<'_>, area: Rect, app: &App) {
+    let popup = centered_rect(60, 80, area);
+    f.render_widget(Clear, popup);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(3)])
+        .split(popup);
+    let selected = app.tax_category_idx.min(app.ledger.category.len().saturating_sub(1));
+    // keep the cursor in view
+    let visible = chunks[0].height.saturating_sub(3) as usize;
+    let skip = (selected + 1).saturating_sub(visible.max(1));
+    let rows = app
+        .ledger
+        .category
+        .iter()
+        .enumerate()
+        .skip(skip)
+        .map(|(idx, c)| {
+            let line = match (c.tax_line, app.ledger.tax_line_of(c.id)) {
+                (Some(l), _) => l.name().to_string(),
+                (None, Some(l)) => format!("{} (from parent)", l.name()),
+                (None, None) => "-".to_string(),
+            };
+            let row = Row::new(vec![c.name.clone(), line]);
+            match idx == selected {
+                true => row.style(Style::default().add_modifier(Modifier::REVERSED)),
+                false => row,
+            }
+        });
+    let table = Table::new(rows, [Constraint::Percentage(45), Constraint::Percentage(55)])
+        .header(Row::new(vec!["Category", "Tax line"]).style(Style::default().add_modifier(Modifier::BOLD)))
+        .block(
+            Block::default()
+                .title("Tax lines (j/k change, Esc to close)")
+                .borders(Borders::ALL),
+        );
+    f.render_widget(table, chunks[0]);
+
+    let text = app
+        .error_message
+        .clone()
+        .unwrap_or_else(|| "Sub-categories without a tax line report under their parent's".to_string());
+    f.render_widget(
+        Paragraph::new(text).block(Block::default().borders(Borders::ALL)),
+        chunks[1],
+    );
+}
+
 fn draw_create_account(f: &mut Frame<'_>, area: Rect, app: &App) {
     let _chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([Constraint::Length(6), Constraint::Min(0)])
         .split(area);
 
-    let account_types = vec!["Chequing", "Credit", "Cash", "Other"];
+    let account_types = ["Chequing", "Credit", "Cash", "Loan", "Investment", "Other"];
     let account_type_name = account_types
         .get(app.new_account_type_selection)
         .unwrap_or(&"Checking")
@@ -1880,12 +7308,17 @@ fn handle_key_create_category(app: &mut App, key: KeyEvent, rt: &tokio::runtime:
 fn submit_new_category(app: &mut App, rt: &tokio::runtime::Runtime) -> anyhow::Result<()> {
     use crate::stat::sync::*;
 
-    rt.block_on(create_cloudcate(
+    let created = rt.block_on(create_cloudcate(
         &app.base_url,
-        &app.token,
+        &app.session,
         &app.new_category_name.trim(),
         None,
+        None,
     ))?;
+    app.history.record(crate::stat::history::Command::CreateCategory {
+        id: created.id,
+        name: created.name,
+    });
     Ok(())
 }
 